@@ -67,15 +67,24 @@ pub struct Args {
     #[arg(long = "merge-threshold")]
     pub merge_threshold: Option<usize>,
 
+    /// Start blocks nested in another block, like methods, with the signature line of that block
+    #[arg(long = "parent-context")]
+    pub parent_context: bool,
+
     /// Output only file names and line numbers without full content
     #[arg(long = "dry-run")]
     pub dry_run: bool,
 
     /// Output format (default: color)
-    /// Use 'json' or 'xml' for machine-readable output
-    #[arg(short = 'o', long = "format", default_value = "color", value_parser = ["terminal", "markdown", "plain", "json", "xml", "color"])]
+    /// Use 'json', 'jsonl' or 'xml' for machine-readable output
+    #[arg(short = 'o', long = "format", default_value = "color", value_parser = ["terminal", "markdown", "plain", "json", "jsonl", "xml", "color"])]
     pub format: String,
 
+    /// Render results with a template file instead of --format
+    /// ('markdown' and 'xml' name built-in templates)
+    #[arg(long = "template", value_name = "FILE")]
+    pub template: Option<String>,
+
     /// Session ID for caching search results
     #[arg(long = "session")]
     pub session: Option<String>,
@@ -84,6 +93,59 @@ pub struct Args {
     #[arg(long = "timeout", default_value = "30")]
     pub timeout: u64,
 
+    /// Return page N of the blocks not yet seen in the session (requires --session)
+    #[arg(long = "page", value_name = "N")]
+    pub page: Option<usize>,
+
+    /// Explain the ranking of every result: parsed query, matched terms, IDF and BM25
+    /// contribution per term, file name boost and merged blocks
+    #[arg(long = "explain")]
+    pub explain: bool,
+
+    /// Compute the size and complexity of every result: cyclomatic complexity, nesting
+    /// depth, parameter count and line counts
+    #[arg(long = "metrics")]
+    pub metrics: bool,
+
+    /// Attach the imports of the file and the definitions of the types, constants and
+    /// functions every result references, within TOKENS tokens per result (default: 2000)
+    #[arg(
+        long = "expand-context",
+        value_name = "TOKENS",
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "2000"
+    )]
+    pub expand_context: Option<usize>,
+
+    /// Tolerate typos: match terms found nowhere against similar identifiers in the files
+    #[arg(long = "fuzzy")]
+    pub fuzzy: bool,
+
+    /// Use the pattern as a regular expression instead of parsing it into search terms
+    #[arg(long = "regex", conflicts_with_all = ["literal", "exact", "fuzzy"])]
+    pub regex: bool,
+
+    /// Match the pattern as literal text instead of parsing it into search terms
+    #[arg(long = "literal", conflicts_with_all = ["exact", "fuzzy"])]
+    pub literal: bool,
+
+    /// Skip files larger than this (e.g. 500K, 2M); 0 searches files of any size
+    #[arg(long = "max-file-size", value_name = "SIZE", default_value = "1M", value_parser = crate::search::source_files::parse_file_size)]
+    pub max_file_size: u64,
+
+    /// Search minified and generated files too
+    #[arg(long = "include-generated")]
+    pub include_generated: bool,
+
+    /// Workspace file (TOML) naming several roots to search, extract and query at once
+    #[arg(long = "workspace", value_name = "FILE", global = true)]
+    pub workspace: Option<PathBuf>,
+
+    /// Profile of the project's .probe.toml whose options to use
+    #[arg(long = "profile", value_name = "NAME", global = true)]
+    pub profile: Option<String>,
+
     #[command(subcommand)]
     pub command: Option<Commands>,
 }
@@ -98,8 +160,8 @@ pub enum Commands {
     /// Results are presented as code blocks with relevant context.
     Search {
         /// Search pattern (regex supported)
-        #[arg(value_name = "PATTERN")]
-        pattern: String,
+        #[arg(value_name = "PATTERN", required_unless_present = "batch")]
+        pattern: Option<String>,
 
         /// Files or directories to search (defaults to current directory)
         #[arg(value_name = "PATH", default_value = ".")]
@@ -170,15 +232,24 @@ pub enum Commands {
         #[arg(long = "merge-threshold")]
         merge_threshold: Option<usize>,
 
+        /// Start blocks nested in another block, like methods, with the signature line of that block
+        #[arg(long = "parent-context")]
+        parent_context: bool,
+
         /// Output only file names and line numbers without full content
         #[arg(long = "dry-run")]
         dry_run: bool,
 
         /// Output format (default: color)
-        /// Use 'json' or 'xml' for machine-readable output with structured data
-        #[arg(short = 'o', long = "format", default_value = "color", value_parser = ["terminal", "markdown", "plain", "json", "xml", "color"])]
+        /// Use 'json', 'jsonl' (one result per line) or 'xml' for machine-readable output with structured data
+        #[arg(short = 'o', long = "format", default_value = "color", value_parser = ["terminal", "markdown", "plain", "json", "jsonl", "xml", "color"])]
         format: String,
 
+        /// Render results with a template file instead of --format
+        /// ('markdown' and 'xml' name built-in templates)
+        #[arg(long = "template", value_name = "FILE")]
+        template: Option<String>,
+
         /// Session ID for caching search results
         #[arg(long = "session")]
         session: Option<String>,
@@ -186,6 +257,107 @@ pub enum Commands {
         /// Timeout in seconds for search operation (default: 30)
         #[arg(long = "timeout", default_value = "30")]
         timeout: u64,
+
+        /// Return page N of the blocks not yet seen in the session (requires --session)
+        ///
+        /// Pages are recorded in the session: asking for a page again returns the same
+        /// blocks, and the page after the last one served returns the next unseen blocks.
+        /// The page size is --max-results (default: 10).
+        #[arg(long = "page", value_name = "N")]
+        page: Option<usize>,
+
+        /// Explain the ranking of every result
+        ///
+        /// Reports the parsed query, the matched terms (stemmed and as written in the
+        /// code), the IDF and BM25 contribution of every term, the part of the score that
+        /// comes from the file name and the blocks merged into the result. Shown below
+        /// each result in color and terminal output and as an "explanation" field in JSON.
+        #[arg(long = "explain")]
+        explain: bool,
+
+        /// Compute the size and complexity of every result
+        ///
+        /// Adds the cyclomatic complexity, deepest nesting of control flow, parameter
+        /// count (for functions) and line counts of every block, computed from its syntax
+        /// tree. Shown below each result in text output and as a "metrics" field in JSON.
+        #[arg(long = "metrics")]
+        metrics: bool,
+
+        /// Attach the imports and referenced definitions of every result
+        ///
+        /// Adds the import section of the result's file and the definitions of the local
+        /// types, constants and helper functions the result references: from the same
+        /// file first, then from the other files of its directory, then from the files of
+        /// the repository it imports them from. Context blocks that don't fit in TOKENS
+        /// tokens per result (default: 2000) are left out. Shown after each result in
+        /// text output and as a "context" field in JSON.
+        #[arg(
+            long = "expand-context",
+            value_name = "TOKENS",
+            num_args = 0..=1,
+            require_equals = true,
+            default_missing_value = "2000"
+        )]
+        expand_context: Option<usize>,
+
+        /// Tolerate typos in search terms
+        ///
+        /// Terms that occur in none of the searched files also match the most similar
+        /// identifier parts in those files: one edit away for terms of 3 to 5 characters,
+        /// two for longer terms. Such matches count for half when ranking.
+        #[arg(long = "fuzzy")]
+        fuzzy: bool,
+
+        /// Use the pattern as a regular expression
+        ///
+        /// The expression is matched line by line, case-sensitively unless it starts with
+        /// (?i), and the blocks around the matching lines are extracted as usual. Results
+        /// are ranked by how many matches they contain.
+        #[arg(long = "regex", conflicts_with_all = ["literal", "exact", "fuzzy"])]
+        regex: bool,
+
+        /// Match the pattern as literal text
+        ///
+        /// Like --regex, but every character of the pattern is matched as written.
+        #[arg(long = "literal", conflicts_with_all = ["exact", "fuzzy"])]
+        literal: bool,
+
+        /// Skip files larger than SIZE bytes; K, M and G suffixes are accepted
+        ///
+        /// Skipped files are reported after the results. Use 0 to search files of any size.
+        #[arg(long = "max-file-size", value_name = "SIZE", default_value = "1M", value_parser = crate::search::source_files::parse_file_size)]
+        max_file_size: u64,
+
+        /// Search minified and generated files too
+        ///
+        /// By default, files named *.min.*, files made of very long lines, files with a
+        /// generated-code header (such as @generated or "Code generated ... DO NOT EDIT.")
+        /// and files marked linguist-generated in .gitattributes are skipped. Binary files
+        /// are always skipped.
+        #[arg(long = "include-generated")]
+        include_generated: bool,
+
+        /// Run the queries of a JSON file instead of PATTERN, in a single pass over the files
+        ///
+        /// The file holds an array of queries, each a string or an object with "query" and
+        /// an optional "max_results". Files are walked and read once for all queries, and
+        /// the results are reported per query. All arguments after the options are paths
+        /// to search. Use - to read the queries from stdin.
+        #[arg(long = "batch", value_name = "FILE", conflicts_with = "template")]
+        batch: Option<PathBuf>,
+
+        /// Leave out blocks that an earlier query of the batch already returned
+        #[arg(long = "dedupe", requires = "batch")]
+        dedupe: bool,
+
+        /// Keep running, and rerun the search whenever files under PATH change
+        ///
+        /// After the first results, only the results that were added, changed or removed
+        /// by a rerun are printed. Text formats list them under a heading; JSON Lines output
+        /// has a result record with a "change" field per result, followed by the summary of
+        /// the new result set. Stop with Ctrl+C.
+        #[arg(long = "watch", conflicts_with_all = ["batch", "template", "session"])]
+        watch: bool,
     },
 
     /// Extract code blocks from files
@@ -208,10 +380,15 @@ pub enum Commands {
         #[arg(short = 'c', long = "context", default_value = "0")]
         context_lines: usize,
 
-        /// Output format (default: color)
-        /// Use 'json' or 'xml' for machine-readable output with structured data
-        #[arg(short = 'o', long = "format", default_value = "color", value_parser = ["markdown", "plain", "json", "xml", "color"])]
-        format: String,
+        /// Output format (default: the format of the prompt template, or color)
+        /// Use 'json', 'jsonl' (one result per line) or 'xml' for machine-readable output with structured data
+        #[arg(short = 'o', long = "format", value_parser = ["markdown", "plain", "json", "jsonl", "xml", "color"])]
+        format: Option<String>,
+
+        /// Render results with a template file instead of --format
+        /// ('markdown' and 'xml' name built-in templates)
+        #[arg(long = "template", value_name = "FILE")]
+        template: Option<String>,
 
         /// Read input from clipboard instead of files
         #[arg(short = 'f', long = "from-clipboard")]
@@ -240,7 +417,24 @@ pub enum Commands {
         #[arg(short = 'k', long = "keep-input")]
         keep_input: bool,
 
-        /// System prompt template for LLM models (engineer, architect, or path to file)
+        /// Compute the size and complexity of every result: cyclomatic complexity, nesting
+        /// depth, parameter count and line counts
+        #[arg(long = "metrics")]
+        metrics: bool,
+
+        /// Attach the imports of the file and the definitions of the types, constants and
+        /// functions every result references, within TOKENS tokens per result (default: 2000)
+        #[arg(
+            long = "expand-context",
+            value_name = "TOKENS",
+            num_args = 0..=1,
+            require_equals = true,
+            default_missing_value = "2000"
+        )]
+        expand_context: Option<usize>,
+
+        /// System prompt template for LLM models: engineer, architect, the name of a
+        /// template (see 'probe prompts list'), or a path to a file
         #[arg(long = "prompt")]
         prompt: Option<String>,
 
@@ -295,8 +489,300 @@ pub enum Commands {
         max_results: Option<usize>,
 
         /// Output format (default: color)
-        /// Use 'json' or 'xml' for machine-readable output with structured data
-        #[arg(short = 'o', long = "format", default_value = "color", value_parser = ["markdown", "plain", "json", "xml", "color"])]
+        /// Use 'json', 'jsonl' (one result per line) or 'xml' for machine-readable output,
+        /// or 'sarif' (SARIF 2.1.0) for code-scanning viewers
+        #[arg(short = 'o', long = "format", default_value = "color", value_parser = ["markdown", "plain", "json", "jsonl", "xml", "sarif", "color"])]
         format: String,
+
+        /// Render results with a template file instead of --format
+        /// ('markdown' and 'xml' name built-in templates)
+        #[arg(long = "template", value_name = "FILE")]
+        template: Option<String>,
+
+        /// Keep running, and rerun the query whenever files under PATH change, printing
+        /// only the matches that were added, changed or removed (Ctrl+C to stop)
+        #[arg(long = "watch", conflicts_with = "template")]
+        watch: bool,
+    },
+
+    /// Search the commit history of a git repository
+    ///
+    /// Ranks commit messages and diff hunks with the same query syntax and ranking as
+    /// search, and reports the commit, author, date, file and enclosing symbol of each
+    /// matching hunk. Requires a `git` executable in PATH, which reads the local
+    /// repository; nothing is fetched.
+    History {
+        /// Search pattern, with the same syntax as search
+        #[arg(value_name = "PATTERN")]
+        pattern: String,
+
+        /// Repository, directory or file whose history is searched (defaults to current directory)
+        #[arg(value_name = "PATH", default_value = ".")]
+        path: PathBuf,
+
+        /// Maximum number of results to return
+        #[arg(long = "max-results", default_value = "10")]
+        max_results: usize,
+
+        /// Number of most recent commits to search (0 searches all commits)
+        #[arg(long = "max-commits", default_value = "1000")]
+        max_commits: usize,
+
+        /// Only search commits more recent than a date, e.g. "2024-01-01" or "3 months ago"
+        #[arg(long = "since", value_name = "DATE")]
+        since: Option<String>,
+
+        /// Output format (default: color)
+        /// Use 'json' or 'jsonl' (one result per line) for machine-readable output
+        #[arg(short = 'o', long = "format", default_value = "color", value_parser = ["terminal", "markdown", "plain", "json", "jsonl", "color"])]
+        format: String,
+    },
+
+    /// Find code similar to a function or other code block
+    ///
+    /// Blocks are compared by their token structure, with identifiers, numbers and strings
+    /// normalized, so that copies with renamed variables are found as well.
+    Similar {
+        /// Block to compare, as file#symbol, file:line or file:start-end
+        #[arg(value_name = "TARGET")]
+        target: String,
+
+        /// Directory or file to search for similar code (defaults to current directory)
+        #[arg(value_name = "PATH", default_value = ".")]
+        path: PathBuf,
+
+        /// Lowest similarity, between 0 and 1, of the blocks to return
+        #[arg(long = "threshold", default_value = "0.5")]
+        threshold: f64,
+
+        /// Ignore blocks with fewer lines
+        #[arg(long = "min-lines", default_value = "5")]
+        min_lines: usize,
+
+        /// Maximum number of results to return
+        #[arg(long = "max-results", default_value = "10")]
+        max_results: usize,
+
+        /// Custom patterns to ignore (in addition to .gitignore and common patterns)
+        #[arg(short, long)]
+        ignore: Vec<String>,
+
+        /// Allow test files and test code blocks in results
+        #[arg(long = "allow-tests")]
+        allow_tests: bool,
+
+        /// Output format (default: color)
+        /// Use 'json' or 'jsonl' (one result per line) for machine-readable output
+        #[arg(short = 'o', long = "format", default_value = "color", value_parser = ["terminal", "markdown", "plain", "json", "jsonl", "color"])]
+        format: String,
+    },
+
+    /// Find clusters of duplicated and near-duplicate code
+    Dupes {
+        /// Directory or file to search for duplicated code (defaults to current directory)
+        #[arg(value_name = "PATH", default_value = ".")]
+        path: PathBuf,
+
+        /// Lowest similarity, between 0 and 1, of blocks in a cluster
+        #[arg(long = "threshold", default_value = "0.8")]
+        threshold: f64,
+
+        /// Ignore blocks with fewer lines
+        #[arg(long = "min-lines", default_value = "5")]
+        min_lines: usize,
+
+        /// Maximum number of clusters to return
+        #[arg(long = "max-results", default_value = "20")]
+        max_results: usize,
+
+        /// Custom patterns to ignore (in addition to .gitignore and common patterns)
+        #[arg(short, long)]
+        ignore: Vec<String>,
+
+        /// Allow test files and test code blocks in results
+        #[arg(long = "allow-tests")]
+        allow_tests: bool,
+
+        /// Output format (default: color)
+        /// Use 'json' or 'jsonl' (one cluster per line) for machine-readable output
+        #[arg(short = 'o', long = "format", default_value = "color", value_parser = ["terminal", "markdown", "plain", "json", "jsonl", "color"])]
+        format: String,
+    },
+
+    /// Report the size and complexity of the functions of a project
+    ///
+    /// Lists the functions over any of the thresholds, the most complex first, with
+    /// their cyclomatic complexity, deepest nesting, parameter count and lines of code.
+    Metrics {
+        /// Directory or file to measure (defaults to current directory)
+        #[arg(value_name = "PATH", default_value = ".")]
+        path: PathBuf,
+
+        /// Report functions with a higher cyclomatic complexity
+        #[arg(long = "max-complexity", default_value = "10")]
+        max_complexity: usize,
+
+        /// Report functions with control flow nested deeper
+        #[arg(long = "max-nesting", default_value = "4")]
+        max_nesting: usize,
+
+        /// Report functions with more parameters
+        #[arg(long = "max-params", default_value = "5")]
+        max_params: usize,
+
+        /// Report functions with more lines of code
+        #[arg(long = "max-lines", default_value = "60")]
+        max_lines: usize,
+
+        /// Report every function, not only those over a threshold
+        #[arg(long = "all")]
+        all: bool,
+
+        /// Maximum number of functions to report
+        #[arg(long = "max-results", default_value = "50")]
+        max_results: usize,
+
+        /// Custom patterns to ignore (in addition to .gitignore and common patterns)
+        #[arg(short, long)]
+        ignore: Vec<String>,
+
+        /// Measure test files and test functions too
+        #[arg(long = "allow-tests")]
+        allow_tests: bool,
+
+        /// Output format (default: color)
+        /// Use 'json' or 'jsonl' (one function per line) for machine-readable output
+        #[arg(short = 'o', long = "format", default_value = "color", value_parser = ["terminal", "markdown", "plain", "json", "jsonl", "color"])]
+        format: String,
+    },
+
+    /// List the TODO, FIXME, HACK and XXX markers of code comments
+    ///
+    /// Only comments are searched, using the syntax tree of every file. Markers are listed
+    /// with the symbol enclosing them, the issues they reference and, in git repositories,
+    /// the author and date of their line.
+    Todos {
+        /// Directory or file to search (defaults to current directory)
+        #[arg(value_name = "PATH", default_value = ".")]
+        path: PathBuf,
+
+        /// Custom tag to harvest in addition to TODO, FIXME, HACK and XXX (can be repeated)
+        #[arg(short = 't', long = "tag", value_name = "TAG")]
+        tags: Vec<String>,
+
+        /// Don't look up the author and date of the markers with git blame
+        #[arg(long = "no-blame")]
+        no_blame: bool,
+
+        /// Custom patterns to ignore (in addition to .gitignore and common patterns)
+        #[arg(short, long)]
+        ignore: Vec<String>,
+
+        /// Include test files and test code
+        #[arg(long = "allow-tests")]
+        allow_tests: bool,
+
+        /// Output format (default: color)
+        /// Use 'json' or 'jsonl' (one marker per line) for machine-readable output
+        #[arg(short = 'o', long = "format", default_value = "color", value_parser = ["terminal", "markdown", "plain", "json", "jsonl", "color"])]
+        format: String,
+    },
+
+    /// List and show the prompt templates available to extract --prompt
+    ///
+    /// Templates are discovered in the project's .probe/prompts directory and in
+    /// ~/.config/probe/prompts, besides the built-in engineer and architect templates.
+    Prompts {
+        #[command(subcommand)]
+        command: PromptsCommands,
+    },
+
+    /// Manage the session caches used by --session
+    ///
+    /// Sessions remember which code blocks were already returned for a query so that
+    /// follow-up searches only show new blocks. Sessions expire after 24 hours without
+    /// use (configurable with PROBE_SESSION_TTL, in seconds).
+    Session {
+        #[command(subcommand)]
+        command: SessionCommands,
+    },
+
+    /// Inspect the project configuration
+    ///
+    /// Default options are read from the first .probe.toml found in the directory a
+    /// command works on or the directories above it. Top-level options apply to every
+    /// command that accepts them, [search], [extract] and other sections to one command,
+    /// and [profiles.NAME] sections to runs with --profile NAME. Flags given on the
+    /// command line take precedence.
+    Config {
+        #[command(subcommand)]
+        command: ConfigCommands,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+pub enum ConfigCommands {
+    /// Show the effective options of every command and the sections they came from
+    Show {
+        /// Directory to look for .probe.toml from
+        #[arg(value_name = "PATH", default_value = ".")]
+        path: PathBuf,
+
+        /// Output format (default: color)
+        #[arg(short = 'o', long = "format", default_value = "color", value_parser = ["terminal", "plain", "json", "color"])]
+        format: String,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+pub enum PromptsCommands {
+    /// List the available prompt templates
+    List {
+        /// Output format (default: color)
+        #[arg(short = 'o', long = "format", default_value = "color", value_parser = ["terminal", "plain", "json", "color"])]
+        format: String,
+    },
+
+    /// Show a prompt template and its metadata
+    Show {
+        /// Template name
+        #[arg(value_name = "NAME")]
+        name: String,
+
+        /// Output format (default: color)
+        #[arg(short = 'o', long = "format", default_value = "color", value_parser = ["terminal", "plain", "json", "color"])]
+        format: String,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+pub enum SessionCommands {
+    /// List active sessions, most recently used first
+    List {
+        /// Output format (default: color)
+        #[arg(short = 'o', long = "format", default_value = "color", value_parser = ["terminal", "plain", "json", "color"])]
+        format: String,
+    },
+
+    /// Show the queries, pages and blocks cached in a session
+    Show {
+        /// Session ID
+        #[arg(value_name = "SESSION")]
+        session_id: String,
+
+        /// Output format (default: color)
+        #[arg(short = 'o', long = "format", default_value = "color", value_parser = ["terminal", "plain", "json", "color"])]
+        format: String,
+    },
+
+    /// Remove session caches
+    Clear {
+        /// Session ID to clear (clears all sessions if not specified)
+        #[arg(value_name = "SESSION")]
+        session_id: Option<String>,
+
+        /// Only remove expired caches
+        #[arg(long = "expired")]
+        expired: bool,
     },
 }
@@ -0,0 +1,394 @@
+//! Project configuration.
+//!
+//! A `.probe.toml` file sets default options so that they don't have to be repeated on
+//! every invocation. It is found by walking up from the path a command works on:
+//!
+//! ```toml
+//! # Options of every command that accepts them
+//! ignore = ["dist/*"]
+//! allow-tests = true
+//!
+//! [search]
+//! max-tokens = 8000
+//! merge-threshold = 10
+//! language = "rust"
+//!
+//! [extract]
+//! context = 2
+//!
+//! # Selected with --profile agent
+//! [profiles.agent]
+//! format = "json"
+//!
+//! [profiles.agent.search]
+//! max-tokens = 20000
+//! ```
+//!
+//! Options are named like the long command-line flags, without the dashes. The options
+//! of a command's section override the top-level ones, and those of the selected profile
+//! override both. Lists, such as `ignore`, accumulate instead. Flags given on the command
+//! line take precedence over the file, and a flag the file turns on is turned off with
+//! `--no-<flag>`, e.g. `--no-allow-tests`.
+
+use anyhow::{Context, Result};
+use colored::*;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+/// Name of the project configuration file
+pub const CONFIG_FILE_NAME: &str = ".probe.toml";
+
+/// Options by name
+type Options = BTreeMap<String, toml::Value>;
+
+/// The options of the top level of the file or of a profile, and of its command sections
+#[derive(Debug, Clone, Default)]
+struct Layer {
+    options: Options,
+    commands: BTreeMap<String, Options>,
+}
+
+/// A loaded `.probe.toml`
+#[derive(Debug, Clone)]
+pub struct ProbeConfig {
+    /// Path of the configuration file
+    pub file: PathBuf,
+    base: Layer,
+    profiles: BTreeMap<String, Layer>,
+}
+
+/// An option of the effective configuration of a command
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ConfigOption {
+    pub name: String,
+    pub value: toml::Value,
+    /// The sections the value came from, e.g. "[search]" or "[profiles.agent]"; lists
+    /// can come from several
+    pub sources: Vec<String>,
+}
+
+/// A section of the configuration file, for checking its options
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConfigSection {
+    /// Section as written in the file, e.g. "[profiles.agent.search]"
+    pub label: String,
+    /// The command the section is for; None for options of every command
+    pub command: Option<String>,
+    pub options: Vec<String>,
+}
+
+/// Option names are the long flags; `max_tokens` is accepted for `max-tokens`
+fn option_name(key: &str) -> String {
+    key.replace('_', "-")
+}
+
+fn section_label(profile: Option<&str>, command: Option<&str>) -> String {
+    match (profile, command) {
+        (None, None) => "(top level)".to_string(),
+        (None, Some(command)) => format!("[{}]", command),
+        (Some(profile), None) => format!("[profiles.{}]", profile),
+        (Some(profile), Some(command)) => format!("[profiles.{}.{}]", profile, command),
+    }
+}
+
+impl Layer {
+    /// Parse the options and command sections of a table; `profile` names the profile
+    /// the table is for, if any
+    fn parse(table: toml::Table, profile: Option<&str>) -> Result<Self> {
+        let mut layer = Layer::default();
+        for (key, value) in table {
+            match value {
+                toml::Value::Table(section) => {
+                    let mut options = Options::new();
+                    for (name, value) in section {
+                        if value.is_table() {
+                            anyhow::bail!(
+                                "Unexpected table '{}' in {}",
+                                name,
+                                section_label(profile, Some(&key))
+                            );
+                        }
+                        options.insert(option_name(&name), value);
+                    }
+                    layer.commands.insert(key, options);
+                }
+                value => {
+                    layer.options.insert(option_name(&key), value);
+                }
+            }
+        }
+        Ok(layer)
+    }
+}
+
+impl ProbeConfig {
+    /// Find the configuration file of a path: the first `.probe.toml` in the path's
+    /// directory or the directories above it
+    pub fn discover(start: &Path) -> Result<Option<Self>> {
+        let start = std::path::absolute(start).unwrap_or_else(|_| start.to_path_buf());
+        for dir in start.ancestors() {
+            let file = dir.join(CONFIG_FILE_NAME);
+            if file.is_file() {
+                return Self::load(&file).map(Some);
+            }
+        }
+        Ok(None)
+    }
+
+    /// Load a configuration file
+    pub fn load(file: &Path) -> Result<Self> {
+        let content = std::fs::read_to_string(file)
+            .with_context(|| format!("Failed to read configuration file: {}", file.display()))?;
+        Self::from_toml_str(&content, file)
+            .with_context(|| format!("Invalid configuration file: {}", file.display()))
+    }
+
+    /// Parse a configuration read from `file`
+    pub fn from_toml_str(content: &str, file: &Path) -> Result<Self> {
+        let mut table: toml::Table = toml::from_str(content)?;
+
+        let mut profiles = BTreeMap::new();
+        if let Some(value) = table.remove("profiles") {
+            let toml::Value::Table(entries) = value else {
+                anyhow::bail!("'profiles' must be a table of profiles");
+            };
+            for (name, profile) in entries {
+                let toml::Value::Table(profile) = profile else {
+                    anyhow::bail!("Profile '{}' must be a table", name);
+                };
+                let layer = Layer::parse(profile, Some(&name))?;
+                profiles.insert(name, layer);
+            }
+        }
+
+        Ok(ProbeConfig {
+            file: file.to_path_buf(),
+            base: Layer::parse(table, None)?,
+            profiles,
+        })
+    }
+
+    /// Names of the profiles of the file
+    pub fn profile_names(&self) -> Vec<&str> {
+        self.profiles.keys().map(String::as_str).collect()
+    }
+
+    fn profile(&self, name: &str) -> Result<&Layer> {
+        self.profiles.get(name).ok_or_else(|| {
+            let names = self.profile_names();
+            anyhow::anyhow!(
+                "Unknown profile '{}' in {} (profiles: {})",
+                name,
+                self.file.display(),
+                if names.is_empty() {
+                    "none".to_string()
+                } else {
+                    names.join(", ")
+                }
+            )
+        })
+    }
+
+    /// The sections of the file and the options they set
+    pub fn sections(&self) -> Vec<ConfigSection> {
+        let mut sections = Vec::new();
+        let mut add_layer = |layer: &Layer, profile: Option<&str>| {
+            sections.push(ConfigSection {
+                label: section_label(profile, None),
+                command: None,
+                options: layer.options.keys().cloned().collect(),
+            });
+            for (command, options) in &layer.commands {
+                sections.push(ConfigSection {
+                    label: section_label(profile, Some(command)),
+                    command: Some(command.clone()),
+                    options: options.keys().cloned().collect(),
+                });
+            }
+        };
+        add_layer(&self.base, None);
+        for (name, profile) in &self.profiles {
+            add_layer(profile, Some(name));
+        }
+        sections
+    }
+
+    /// The options that apply to a command, with the sections they came from, in option
+    /// name order
+    ///
+    /// Options that apply to every command are included whether the command accepts
+    /// them or not.
+    pub fn options_for(&self, command: &str, profile: Option<&str>) -> Result<Vec<ConfigOption>> {
+        let mut merged: BTreeMap<String, ConfigOption> = BTreeMap::new();
+        let mut apply = |options: &Options, label: String| {
+            for (name, value) in options {
+                match (merged.get_mut(name), value) {
+                    (Some(existing), toml::Value::Array(items)) if existing.value.is_array() => {
+                        if let toml::Value::Array(values) = &mut existing.value {
+                            values.extend(items.iter().cloned());
+                        }
+                        existing.sources.push(label.clone());
+                    }
+                    _ => {
+                        merged.insert(
+                            name.clone(),
+                            ConfigOption {
+                                name: name.clone(),
+                                value: value.clone(),
+                                sources: vec![label.clone()],
+                            },
+                        );
+                    }
+                }
+            }
+        };
+
+        let mut layers = vec![(&self.base, None)];
+        if let Some(name) = profile {
+            layers.push((self.profile(name)?, Some(name)));
+        }
+        for (layer, profile) in layers {
+            apply(&layer.options, section_label(profile, None));
+            if let Some(options) = layer.commands.get(command) {
+                apply(options, section_label(profile, Some(command)));
+            }
+        }
+        Ok(merged.into_values().collect())
+    }
+}
+
+/// Print the effective configuration of the given commands, see `handle_config_show`
+pub fn format_and_print_config(
+    config: &ProbeConfig,
+    profile: Option<&str>,
+    commands: &[(String, Vec<ConfigOption>)],
+    format: &str,
+) -> Result<()> {
+    if format == "json" {
+        let commands: BTreeMap<&str, &Vec<ConfigOption>> = commands
+            .iter()
+            .map(|(command, options)| (command.as_str(), options))
+            .collect();
+        let wrapper = serde_json::json!({
+            "file": config.file,
+            "profile": profile,
+            "profiles": config.profile_names(),
+            "commands": commands,
+        });
+        println!("{}", serde_json::to_string_pretty(&wrapper)?);
+        return Ok(());
+    }
+
+    let use_color = format == "color" || format == "terminal";
+    let heading = |text: &str| {
+        if use_color {
+            text.bold().green().to_string()
+        } else {
+            text.to_string()
+        }
+    };
+    println!("{} {}", heading("Configuration:"), config.file.display());
+    if let Some(profile) = profile {
+        println!("{} {}", heading("Profile:"), profile);
+    }
+    let names = config.profile_names();
+    if !names.is_empty() {
+        println!("{} {}", heading("Profiles:"), names.join(", "));
+    }
+
+    for (command, options) in commands {
+        println!();
+        println!("{}", heading(&format!("[{}]", command)));
+        let width = options.iter().map(|o| o.name.len()).max().unwrap_or(0);
+        for option in options {
+            let source = format!("# {}", option.sources.join(", "));
+            println!(
+                "  {:width$} = {}  {}",
+                option.name,
+                option.value,
+                if use_color {
+                    source.dimmed().to_string()
+                } else {
+                    source
+                },
+                width = width
+            );
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CONFIG: &str = r#"
+ignore = ["dist/*"]
+allow_tests = true
+
+[search]
+max-tokens = 8000
+ignore = ["*.snap"]
+
+[profiles.agent]
+format = "json"
+allow-tests = false
+
+[profiles.agent.search]
+max-tokens = 20000
+ignore = ["fixtures/*"]
+"#;
+
+    #[test]
+    fn test_options_merge_sections_and_profiles() {
+        let config = ProbeConfig::from_toml_str(CONFIG, Path::new(".probe.toml")).unwrap();
+        let search = config.options_for("search", None).unwrap();
+        let values: Vec<(&str, String)> = search
+            .iter()
+            .map(|o| (o.name.as_str(), o.value.to_string()))
+            .collect();
+        assert_eq!(
+            values,
+            [
+                ("allow-tests", "true".to_string()),
+                ("ignore", r#"["dist/*", "*.snap"]"#.to_string()),
+                ("max-tokens", "8000".to_string()),
+            ]
+        );
+        assert_eq!(search[1].sources, ["(top level)", "[search]"]);
+
+        let agent = config.options_for("search", Some("agent")).unwrap();
+        let max_tokens = agent.iter().find(|o| o.name == "max-tokens").unwrap();
+        assert_eq!(max_tokens.value.as_integer(), Some(20000));
+        assert_eq!(max_tokens.sources, ["[profiles.agent.search]"]);
+        let allow_tests = agent.iter().find(|o| o.name == "allow-tests").unwrap();
+        assert_eq!(allow_tests.value.as_bool(), Some(false));
+        let ignore = agent.iter().find(|o| o.name == "ignore").unwrap();
+        assert_eq!(ignore.value.as_array().unwrap().len(), 3);
+
+        // Other commands only get the options of every command
+        let extract = config.options_for("extract", Some("agent")).unwrap();
+        let names: Vec<&str> = extract.iter().map(|o| o.name.as_str()).collect();
+        assert_eq!(names, ["allow-tests", "format", "ignore"]);
+
+        assert!(config.options_for("search", Some("missing")).is_err());
+    }
+
+    #[test]
+    fn test_discover_walks_up() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let nested = temp_dir.path().join("src/api");
+        std::fs::create_dir_all(&nested).unwrap();
+        std::fs::write(temp_dir.path().join(CONFIG_FILE_NAME), CONFIG).unwrap();
+
+        let config = ProbeConfig::discover(&nested).unwrap().unwrap();
+        assert_eq!(config.file, temp_dir.path().join(CONFIG_FILE_NAME));
+        // A file that doesn't exist, like src/api/main.rs:10, is looked up from its directory
+        let config = ProbeConfig::discover(&nested.join("main.rs:10")).unwrap();
+        assert!(config.is_some());
+
+        assert!(ProbeConfig::from_toml_str("[search.nested]\na = 1\n", Path::new("x")).is_err());
+        assert!(ProbeConfig::from_toml_str("profiles = 1\n", Path::new("x")).is_err());
+    }
+}
@@ -46,8 +46,13 @@ pub fn is_git_diff_format(content: &str) -> bool {
 /// We don't add arbitrary context lines - instead we rely on the AST parser to find
 /// the full function or code block that contains the changed lines.
 ///
-/// If allow_tests is false, test files will be filtered out.
-pub fn extract_file_paths_from_git_diff(text: &str, allow_tests: bool) -> Vec<FilePathInfo> {
+/// If allow_tests is false, test files will be filtered out. Files whose path contains
+/// one of `custom_ignores` are left out as well.
+pub fn extract_file_paths_from_git_diff(
+    text: &str,
+    allow_tests: bool,
+    custom_ignores: &[String],
+) -> Vec<FilePathInfo> {
     let mut results = Vec::new();
     let mut processed_files = HashSet::new();
     let mut current_file: Option<PathBuf> = None;
@@ -81,7 +86,7 @@ pub fn extract_file_paths_from_git_diff(text: &str, allow_tests: bool) -> Vec<Fi
         {
             // Skip test files if allow_tests is false
             let is_test = is_test_file(file_path);
-            if !is_ignored_by_gitignore(file_path) && (allow_tests || !is_test) {
+            if !is_ignored_by_gitignore(file_path, custom_ignores) && (allow_tests || !is_test) {
                 if debug_mode {
                     println!(
                         "[DEBUG] Adding file with {} changed lines: {:?}",
@@ -103,7 +108,7 @@ pub fn extract_file_paths_from_git_diff(text: &str, allow_tests: bool) -> Vec<Fi
                 ));
                 processed_files.insert(file_path.to_string_lossy().to_string());
             } else if debug_mode {
-                if is_ignored_by_gitignore(file_path) {
+                if is_ignored_by_gitignore(file_path, custom_ignores) {
                     println!("[DEBUG] Skipping ignored file: {:?}", file_path);
                 } else if !allow_tests && is_test {
                     println!("[DEBUG] Skipping test file: {:?}", file_path);
@@ -232,8 +237,13 @@ pub fn extract_file_paths_from_git_diff(text: &str, allow_tests: bool) -> Vec<Fi
 /// - File paths with symbol references (e.g., file.rs#function_name)
 /// - Paths can be wrapped in backticks, single quotes, or double quotes
 ///
-/// If allow_tests is false, test files will be filtered out.
-pub fn extract_file_paths_from_text(text: &str, allow_tests: bool) -> Vec<FilePathInfo> {
+/// If allow_tests is false, test files will be filtered out. Files whose path contains
+/// one of `custom_ignores` are left out as well.
+pub fn extract_file_paths_from_text(
+    text: &str,
+    allow_tests: bool,
+    custom_ignores: &[String],
+) -> Vec<FilePathInfo> {
     let mut results = Vec::new();
     let mut processed_paths = HashSet::new();
 
@@ -296,15 +306,15 @@ pub fn extract_file_paths_from_text(text: &str, allow_tests: bool) -> Vec<FilePa
                 for entry in paths.flatten() {
                     // Check if the file should be ignored or is a test file
                     let is_test = is_test_file(&entry);
-                    let should_include =
-                        !is_ignored_by_gitignore(&entry) && (allow_tests || !is_test);
+                    let should_include = !is_ignored_by_gitignore(&entry, custom_ignores)
+                        && (allow_tests || !is_test);
                     if should_include {
                         let path_str = entry.to_string_lossy().to_string();
                         processed_paths.insert(path_str.clone());
                         // Pass the symbol name directly instead of using environment variables
                         results.push((entry, None, None, Some(symbol.to_string()), None));
                     } else if debug_mode {
-                        if is_ignored_by_gitignore(&entry) {
+                        if is_ignored_by_gitignore(&entry, custom_ignores) {
                             println!("DEBUG: Skipping ignored file: {:?}", entry);
                         } else if !allow_tests && is_test {
                             println!("DEBUG: Skipping test file: {:?}", entry);
@@ -317,12 +327,14 @@ pub fn extract_file_paths_from_text(text: &str, allow_tests: bool) -> Vec<FilePa
             match resolve_path(file_path) {
                 Ok(resolved_path) => {
                     let is_test = is_test_file(&resolved_path);
-                    if !is_ignored_by_gitignore(&resolved_path) && (allow_tests || !is_test) {
+                    if !is_ignored_by_gitignore(&resolved_path, custom_ignores)
+                        && (allow_tests || !is_test)
+                    {
                         processed_paths.insert(file_path.to_string());
                         // Pass the symbol name directly instead of using environment variables
                         results.push((resolved_path, None, None, Some(symbol.to_string()), None));
                     } else if debug_mode {
-                        if is_ignored_by_gitignore(&resolved_path) {
+                        if is_ignored_by_gitignore(&resolved_path, custom_ignores) {
                             println!("DEBUG: Skipping ignored file: {:?}", file_path);
                         } else if !allow_tests && is_test {
                             println!("DEBUG: Skipping test file: {:?}", file_path);
@@ -337,12 +349,13 @@ pub fn extract_file_paths_from_text(text: &str, allow_tests: bool) -> Vec<FilePa
                     // Fall back to the original path
                     let path = PathBuf::from(file_path);
                     let is_test = is_test_file(&path);
-                    if !is_ignored_by_gitignore(&path) && (allow_tests || !is_test) {
+                    if !is_ignored_by_gitignore(&path, custom_ignores) && (allow_tests || !is_test)
+                    {
                         processed_paths.insert(file_path.to_string());
                         // Pass the symbol name directly instead of using environment variables
                         results.push((path, None, None, Some(symbol.to_string()), None));
                     } else if debug_mode {
-                        if is_ignored_by_gitignore(&path) {
+                        if is_ignored_by_gitignore(&path, custom_ignores) {
                             println!("DEBUG: Skipping ignored file: {:?}", file_path);
                         } else if !allow_tests && is_test {
                             println!("DEBUG: Skipping test file: {:?}", file_path);
@@ -375,13 +388,13 @@ pub fn extract_file_paths_from_text(text: &str, allow_tests: bool) -> Vec<FilePa
                     for entry in paths.flatten() {
                         // Check if the file should be ignored or is a test file
                         let is_test = is_test_file(&entry);
-                        let should_include =
-                            !is_ignored_by_gitignore(&entry) && (allow_tests || !is_test);
+                        let should_include = !is_ignored_by_gitignore(&entry, custom_ignores)
+                            && (allow_tests || !is_test);
                         if should_include {
                             processed_paths.insert(entry.to_string_lossy().to_string());
                             results.push((entry, Some(start), Some(end), None, None));
                         } else if debug_mode {
-                            if is_ignored_by_gitignore(&entry) {
+                            if is_ignored_by_gitignore(&entry, custom_ignores) {
                                 println!("DEBUG: Skipping ignored file: {:?}", entry);
                             } else if !allow_tests && is_test {
                                 println!("DEBUG: Skipping test file: {:?}", entry);
@@ -394,11 +407,13 @@ pub fn extract_file_paths_from_text(text: &str, allow_tests: bool) -> Vec<FilePa
                 match resolve_path(file_path) {
                     Ok(resolved_path) => {
                         let is_test = is_test_file(&resolved_path);
-                        if !is_ignored_by_gitignore(&resolved_path) && (allow_tests || !is_test) {
+                        if !is_ignored_by_gitignore(&resolved_path, custom_ignores)
+                            && (allow_tests || !is_test)
+                        {
                             processed_paths.insert(file_path.to_string());
                             results.push((resolved_path, Some(start), Some(end), None, None));
                         } else if debug_mode {
-                            if is_ignored_by_gitignore(&resolved_path) {
+                            if is_ignored_by_gitignore(&resolved_path, custom_ignores) {
                                 println!("DEBUG: Skipping ignored file: {:?}", file_path);
                             } else if !allow_tests && is_test {
                                 println!("DEBUG: Skipping test file: {:?}", file_path);
@@ -413,11 +428,13 @@ pub fn extract_file_paths_from_text(text: &str, allow_tests: bool) -> Vec<FilePa
                         // Fall back to the original path
                         let path = PathBuf::from(file_path);
                         let is_test = is_test_file(&path);
-                        if !is_ignored_by_gitignore(&path) && (allow_tests || !is_test) {
+                        if !is_ignored_by_gitignore(&path, custom_ignores)
+                            && (allow_tests || !is_test)
+                        {
                             processed_paths.insert(file_path.to_string());
                             results.push((path, Some(start), Some(end), None, None));
                         } else if debug_mode {
-                            if is_ignored_by_gitignore(&path) {
+                            if is_ignored_by_gitignore(&path, custom_ignores) {
                                 println!("DEBUG: Skipping ignored file: {:?}", file_path);
                             } else if !allow_tests && is_test {
                                 println!("DEBUG: Skipping test file: {:?}", file_path);
@@ -452,13 +469,13 @@ pub fn extract_file_paths_from_text(text: &str, allow_tests: bool) -> Vec<FilePa
                     if !processed_paths.contains(&path_str) {
                         // Check if the file should be ignored or is a test file
                         let is_test = is_test_file(&entry);
-                        let should_include =
-                            !is_ignored_by_gitignore(&entry) && (allow_tests || !is_test);
+                        let should_include = !is_ignored_by_gitignore(&entry, custom_ignores)
+                            && (allow_tests || !is_test);
                         if should_include {
                             processed_paths.insert(path_str);
                             results.push((entry, line_num, None, None, None));
                         } else if debug_mode {
-                            if is_ignored_by_gitignore(&entry) {
+                            if is_ignored_by_gitignore(&entry, custom_ignores) {
                                 println!("DEBUG: Skipping ignored file: {:?}", entry);
                             } else if !allow_tests && is_test {
                                 println!("DEBUG: Skipping test file: {:?}", entry);
@@ -472,11 +489,12 @@ pub fn extract_file_paths_from_text(text: &str, allow_tests: bool) -> Vec<FilePa
             match resolve_path(file_path) {
                 Ok(path) => {
                     let is_test = is_test_file(&path);
-                    if !is_ignored_by_gitignore(&path) && (allow_tests || !is_test) {
+                    if !is_ignored_by_gitignore(&path, custom_ignores) && (allow_tests || !is_test)
+                    {
                         processed_paths.insert(file_path.to_string());
                         results.push((path, line_num, None, None, None));
                     } else if debug_mode {
-                        if is_ignored_by_gitignore(&path) {
+                        if is_ignored_by_gitignore(&path, custom_ignores) {
                             println!("DEBUG: Skipping ignored file: {:?}", file_path);
                         } else if !allow_tests && is_test {
                             println!("DEBUG: Skipping test file: {:?}", file_path);
@@ -491,11 +509,12 @@ pub fn extract_file_paths_from_text(text: &str, allow_tests: bool) -> Vec<FilePa
                     // Fall back to the original path
                     let path = PathBuf::from(file_path);
                     let is_test = is_test_file(&path);
-                    if !is_ignored_by_gitignore(&path) && (allow_tests || !is_test) {
+                    if !is_ignored_by_gitignore(&path, custom_ignores) && (allow_tests || !is_test)
+                    {
                         processed_paths.insert(file_path.to_string());
                         results.push((path, line_num, None, None, None));
                     } else if debug_mode {
-                        if is_ignored_by_gitignore(&path) {
+                        if is_ignored_by_gitignore(&path, custom_ignores) {
                             println!("DEBUG: Skipping ignored file: {:?}", file_path);
                         } else if !allow_tests && is_test {
                             println!("DEBUG: Skipping test file: {:?}", file_path);
@@ -524,13 +543,13 @@ pub fn extract_file_paths_from_text(text: &str, allow_tests: bool) -> Vec<FilePa
                         if !processed_paths.contains(&path_str) {
                             // Check if the file should be ignored or is a test file
                             let is_test = is_test_file(&entry);
-                            let should_include =
-                                !is_ignored_by_gitignore(&entry) && (allow_tests || !is_test);
+                            let should_include = !is_ignored_by_gitignore(&entry, custom_ignores)
+                                && (allow_tests || !is_test);
                             if should_include {
                                 processed_paths.insert(path_str);
                                 results.push((entry, None, None, None, None));
                             } else if debug_mode {
-                                if is_ignored_by_gitignore(&entry) {
+                                if is_ignored_by_gitignore(&entry, custom_ignores) {
                                     println!("DEBUG: Skipping ignored file: {:?}", entry);
                                 } else if !allow_tests && is_test {
                                     println!("DEBUG: Skipping test file: {:?}", entry);
@@ -544,11 +563,13 @@ pub fn extract_file_paths_from_text(text: &str, allow_tests: bool) -> Vec<FilePa
                 match resolve_path(file_path) {
                     Ok(path) => {
                         let is_test = is_test_file(&path);
-                        if !is_ignored_by_gitignore(&path) && (allow_tests || !is_test) {
+                        if !is_ignored_by_gitignore(&path, custom_ignores)
+                            && (allow_tests || !is_test)
+                        {
                             results.push((path, None, None, None, None));
                             processed_paths.insert(file_path.to_string());
                         } else if debug_mode {
-                            if is_ignored_by_gitignore(&path) {
+                            if is_ignored_by_gitignore(&path, custom_ignores) {
                                 println!("DEBUG: Skipping ignored file: {:?}", file_path);
                             } else if !allow_tests && is_test {
                                 println!("DEBUG: Skipping test file: {:?}", file_path);
@@ -563,11 +584,13 @@ pub fn extract_file_paths_from_text(text: &str, allow_tests: bool) -> Vec<FilePa
                         // Fall back to the original path
                         let path = PathBuf::from(file_path);
                         let is_test = is_test_file(&path);
-                        if !is_ignored_by_gitignore(&path) && (allow_tests || !is_test) {
+                        if !is_ignored_by_gitignore(&path, custom_ignores)
+                            && (allow_tests || !is_test)
+                        {
                             results.push((path, None, None, None, None));
                             processed_paths.insert(file_path.to_string());
                         } else if debug_mode {
-                            if is_ignored_by_gitignore(&path) {
+                            if is_ignored_by_gitignore(&path, custom_ignores) {
                                 println!("DEBUG: Skipping ignored file: {:?}", file_path);
                             } else if !allow_tests && is_test {
                                 println!("DEBUG: Skipping test file: {:?}", file_path);
@@ -584,8 +607,13 @@ pub fn extract_file_paths_from_text(text: &str, allow_tests: bool) -> Vec<FilePa
 
 /// Parse a file path with optional line number or range (e.g., "file.rs:10" or "file.rs:1-60")
 ///
-/// If allow_tests is false, test files will be filtered out.
-pub fn parse_file_with_line(input: &str, allow_tests: bool) -> Vec<FilePathInfo> {
+/// If allow_tests is false, test files will be filtered out. Files whose path contains
+/// one of `custom_ignores` are left out as well.
+pub fn parse_file_with_line(
+    input: &str,
+    allow_tests: bool,
+    custom_ignores: &[String],
+) -> Vec<FilePathInfo> {
     let mut results = Vec::new();
 
     // Remove any surrounding backticks or quotes, but not apostrophes within words
@@ -654,8 +682,8 @@ pub fn parse_file_with_line(input: &str, allow_tests: bool) -> Vec<FilePathInfo>
                         for entry in paths.flatten() {
                             // Check if the file should be ignored or is a test file
                             let is_test = is_test_file(&entry);
-                            let should_include =
-                                !is_ignored_by_gitignore(&entry) && (allow_tests || !is_test);
+                            let should_include = !is_ignored_by_gitignore(&entry, custom_ignores)
+                                && (allow_tests || !is_test);
                             if should_include {
                                 results.push((entry, Some(start), Some(end), None, None));
                             }
@@ -666,7 +694,9 @@ pub fn parse_file_with_line(input: &str, allow_tests: bool) -> Vec<FilePathInfo>
                     match resolve_path(file_part) {
                         Ok(path) => {
                             let is_test = is_test_file(&path);
-                            if !is_ignored_by_gitignore(&path) && (allow_tests || !is_test) {
+                            if !is_ignored_by_gitignore(&path, custom_ignores)
+                                && (allow_tests || !is_test)
+                            {
                                 results.push((path, Some(start), Some(end), None, None));
                             }
                         }
@@ -678,7 +708,9 @@ pub fn parse_file_with_line(input: &str, allow_tests: bool) -> Vec<FilePathInfo>
                             // Fall back to the original path
                             let path = PathBuf::from(file_part);
                             let is_test = is_test_file(&path);
-                            if !is_ignored_by_gitignore(&path) && (allow_tests || !is_test) {
+                            if !is_ignored_by_gitignore(&path, custom_ignores)
+                                && (allow_tests || !is_test)
+                            {
                                 results.push((path, Some(start), Some(end), None, None));
                             }
                         }
@@ -697,8 +729,8 @@ pub fn parse_file_with_line(input: &str, allow_tests: bool) -> Vec<FilePathInfo>
                         for entry in paths.flatten() {
                             // Check if the file should be ignored or is a test file
                             let is_test = is_test_file(&entry);
-                            let should_include =
-                                !is_ignored_by_gitignore(&entry) && (allow_tests || !is_test);
+                            let should_include = !is_ignored_by_gitignore(&entry, custom_ignores)
+                                && (allow_tests || !is_test);
                             if should_include {
                                 // Create a HashSet with just this line number
                                 let mut lines_set = HashSet::new();
@@ -712,7 +744,9 @@ pub fn parse_file_with_line(input: &str, allow_tests: bool) -> Vec<FilePathInfo>
                     match resolve_path(file_part) {
                         Ok(path) => {
                             let is_test = is_test_file(&path);
-                            if !is_ignored_by_gitignore(&path) && (allow_tests || !is_test) {
+                            if !is_ignored_by_gitignore(&path, custom_ignores)
+                                && (allow_tests || !is_test)
+                            {
                                 // Create a HashSet with just this line number
                                 let mut lines_set = HashSet::new();
                                 lines_set.insert(num);
@@ -727,7 +761,9 @@ pub fn parse_file_with_line(input: &str, allow_tests: bool) -> Vec<FilePathInfo>
                             // Fall back to the original path
                             let path = PathBuf::from(file_part);
                             let is_test = is_test_file(&path);
-                            if !is_ignored_by_gitignore(&path) && (allow_tests || !is_test) {
+                            if !is_ignored_by_gitignore(&path, custom_ignores)
+                                && (allow_tests || !is_test)
+                            {
                                 // Create a HashSet with just this line number
                                 let mut lines_set = HashSet::new();
                                 lines_set.insert(num);
@@ -746,8 +782,8 @@ pub fn parse_file_with_line(input: &str, allow_tests: bool) -> Vec<FilePathInfo>
                 for entry in paths.flatten() {
                     // Check if the file should be ignored or is a test file
                     let is_test = is_test_file(&entry);
-                    let should_include =
-                        !is_ignored_by_gitignore(&entry) && (allow_tests || !is_test);
+                    let should_include = !is_ignored_by_gitignore(&entry, custom_ignores)
+                        && (allow_tests || !is_test);
                     if should_include {
                         results.push((entry, None, None, None, None));
                     }
@@ -758,7 +794,8 @@ pub fn parse_file_with_line(input: &str, allow_tests: bool) -> Vec<FilePathInfo>
             match resolve_path(cleaned_input) {
                 Ok(path) => {
                     let is_test = is_test_file(&path);
-                    if !is_ignored_by_gitignore(&path) && (allow_tests || !is_test) {
+                    if !is_ignored_by_gitignore(&path, custom_ignores) && (allow_tests || !is_test)
+                    {
                         results.push((path, None, None, None, None));
                     }
                 }
@@ -771,7 +808,8 @@ pub fn parse_file_with_line(input: &str, allow_tests: bool) -> Vec<FilePathInfo>
                     // Fall back to the original path
                     let path = PathBuf::from(cleaned_input);
                     let is_test = is_test_file(&path);
-                    if !is_ignored_by_gitignore(&path) && (allow_tests || !is_test) {
+                    if !is_ignored_by_gitignore(&path, custom_ignores) && (allow_tests || !is_test)
+                    {
                         results.push((path, None, None, None, None));
                     }
                 }
@@ -782,22 +820,8 @@ pub fn parse_file_with_line(input: &str, allow_tests: bool) -> Vec<FilePathInfo>
     results
 }
 
-// Thread-local storage for the custom ignore patterns
-thread_local! {
-    static CUSTOM_IGNORES: std::cell::RefCell<Vec<String>> = const { std::cell::RefCell::new(Vec::new()) };
-}
-
-/// Set custom ignore patterns for the current thread
-pub fn set_custom_ignores(patterns: &[String]) {
-    CUSTOM_IGNORES.with(|cell| {
-        let mut ignores = cell.borrow_mut();
-        ignores.clear();
-        ignores.extend(patterns.iter().cloned());
-    });
-}
-
 /// Check if a file should be ignored according to .gitignore rules
-fn is_ignored_by_gitignore(path: &PathBuf) -> bool {
+fn is_ignored_by_gitignore(path: &PathBuf, custom_ignores: &[String]) -> bool {
     // Check if debug mode is enabled
     let debug_mode = std::env::var("DEBUG").unwrap_or_default() == "1";
 
@@ -819,13 +843,6 @@ fn is_ignored_by_gitignore(path: &PathBuf) -> bool {
         "__pycache__",
     ];
 
-    // Get custom ignore patterns
-    let mut custom_patterns = Vec::new();
-    CUSTOM_IGNORES.with(|cell| {
-        let ignores = cell.borrow();
-        custom_patterns.extend(ignores.iter().cloned());
-    });
-
     // Check if the path contains any of the common ignore patterns
     for pattern in &common_ignore_patterns {
         if path_str.contains(pattern) {
@@ -840,7 +857,7 @@ fn is_ignored_by_gitignore(path: &PathBuf) -> bool {
     }
 
     // Check if the path contains any of the custom ignore patterns
-    for pattern in &custom_patterns {
+    for pattern in custom_ignores {
         if path_str.contains(pattern) {
             if debug_mode {
                 println!(
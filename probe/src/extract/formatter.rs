@@ -1,11 +1,17 @@
 //! Functions for formatting and printing extraction results.
 //!
 //! This module provides functions for formatting and printing extraction results
-//! in various formats (terminal, markdown, plain, json, xml, color).
+//! in various formats (terminal, markdown, plain, json, jsonl, xml, color).
 
-use crate::models::SearchResult;
+use crate::models::{CodeMetrics, ContextBlock, SearchResult};
 use crate::search::search_tokens::count_tokens;
+use crate::search::{
+    context_lines, context_xml, is_structured_format, json_line, metrics_xml,
+    search_result_template_value,
+};
 use anyhow::Result;
+use probe::output_template::{OutputTemplate, TemplateContext, TemplateTotals};
+use probe::workspace::display_path;
 use serde::Serialize;
 use std::fmt::Write as FmtWrite;
 use std::path::Path;
@@ -40,6 +46,8 @@ fn format_extraction_internal(
                 #[derive(Serialize)]
                 struct JsonDryRunResult<'a> {
                     file: &'a str,
+                    #[serde(skip_serializing_if = "Option::is_none")]
+                    root: Option<&'a str>,
                     #[serde(serialize_with = "serialize_lines_as_array")]
                     lines: (usize, usize),
                     node_type: &'a str,
@@ -64,6 +72,7 @@ fn format_extraction_internal(
                     .iter()
                     .map(|r| JsonDryRunResult {
                         file: &r.file,
+                        root: r.workspace_root.as_deref(),
                         lines: r.lines,
                         node_type: &r.node_type,
                     })
@@ -97,12 +106,18 @@ fn format_extraction_internal(
                 #[derive(Serialize)]
                 struct JsonResult<'a> {
                     file: &'a str,
+                    #[serde(skip_serializing_if = "Option::is_none")]
+                    root: Option<&'a str>,
                     #[serde(serialize_with = "serialize_lines_as_array")]
                     lines: (usize, usize),
                     node_type: &'a str,
                     code: &'a str,
                     #[serde(skip_serializing_if = "Option::is_none")]
                     original_input: Option<&'a str>,
+                    #[serde(skip_serializing_if = "Option::is_none")]
+                    metrics: Option<&'a CodeMetrics>,
+                    #[serde(skip_serializing_if = "Option::is_none")]
+                    context: Option<&'a Vec<ContextBlock>>,
                 }
 
                 // Helper function to serialize lines as an array
@@ -124,6 +139,7 @@ fn format_extraction_internal(
                     .iter()
                     .map(|r| JsonResult {
                         file: &r.file,
+                        root: r.workspace_root.as_deref(),
                         lines: r.lines,
                         node_type: &r.node_type,
                         code: &r.code,
@@ -131,6 +147,8 @@ fn format_extraction_internal(
                         // you can uncomment the line below, but it's typically at the root.
                         // original_input: r.original_input.as_deref(),
                         original_input: None,
+                        metrics: r.metrics.as_ref(),
+                        context: r.context_blocks.as_ref(),
                     })
                     .collect();
 
@@ -162,6 +180,53 @@ fn format_extraction_internal(
             }
         }
 
+        // ---------------------------------------
+        // JSON Lines output
+        // ---------------------------------------
+        "jsonl" => {
+            // One record per result, followed by a summary record
+            for result in results {
+                let mut record = serde_json::json!({
+                    "file": result.file,
+                    "lines": [result.lines.0, result.lines.1],
+                    "node_type": result.node_type,
+                });
+                if let Some(root) = &result.workspace_root {
+                    record["root"] = serde_json::Value::String(root.clone());
+                }
+                if !is_dry_run {
+                    record["code"] = serde_json::Value::String(result.code.clone());
+                }
+                if let Some(metrics) = &result.metrics {
+                    record["metrics"] = serde_json::to_value(metrics)?;
+                }
+                if let Some(blocks) = &result.context_blocks {
+                    record["context"] = serde_json::to_value(blocks)?;
+                }
+                writeln!(output, "{}", json_line("result", &record)?)?;
+            }
+
+            let mut summary = serde_json::json!({ "count": results.len() });
+            if !is_dry_run {
+                summary["total_bytes"] = results.iter().map(|r| r.code.len()).sum::<usize>().into();
+                summary["total_tokens"] = results
+                    .iter()
+                    .map(|r| count_tokens(&r.code))
+                    .sum::<usize>()
+                    .into();
+            }
+            if let Some(input) = original_input {
+                summary["original_input"] = serde_json::Value::String(input.to_string());
+            }
+            if let Some(prompt) = system_prompt {
+                summary["system_prompt"] = serde_json::Value::String(prompt.to_string());
+            }
+            if let Some(instructions) = user_instructions {
+                summary["user_instructions"] = serde_json::Value::String(instructions.to_string());
+            }
+            write!(output, "{}", json_line("summary", &summary)?)?;
+        }
+
         // ---------------------------------------
         // XML output
         // ---------------------------------------
@@ -176,6 +241,9 @@ fn format_extraction_internal(
                 for result in results {
                     writeln!(output, "  <result>")?;
                     writeln!(output, "    <file>{}</file>", escape_xml(&result.file))?;
+                    if let Some(root) = &result.workspace_root {
+                        writeln!(output, "    <root>{}</root>", escape_xml(root))?;
+                    }
 
                     if result.node_type != "file" {
                         writeln!(output, "    <lines>")?;
@@ -203,6 +271,9 @@ fn format_extraction_internal(
                 for result in results {
                     writeln!(output, "  <result>")?;
                     writeln!(output, "    <file>{}</file>", escape_xml(&result.file))?;
+                    if let Some(root) = &result.workspace_root {
+                        writeln!(output, "    <root>{}</root>", escape_xml(root))?;
+                    }
 
                     if result.node_type != "file" {
                         writeln!(output, "    <lines>")?;
@@ -215,9 +286,17 @@ fn format_extraction_internal(
                         writeln!(output, "    <node_type>{}</node_type>", &result.node_type)?;
                     }
 
+                    if let Some(metrics) = &result.metrics {
+                        writeln!(output, "{}", metrics_xml(metrics, "    "))?;
+                    }
+
                     // Use CDATA to preserve formatting and special characters
                     writeln!(output, "    <code><![CDATA[{}]]></code>", &result.code)?;
 
+                    if let Some(blocks) = &result.context_blocks {
+                        writeln!(output, "{}", context_xml(blocks, "    "))?;
+                    }
+
                     writeln!(output, "  </result>")?;
                 }
 
@@ -279,10 +358,11 @@ fn format_extraction_internal(
                 // For each result, we either skip the code if is_dry_run, or include it otherwise.
                 for result in results {
                     // Common: show file (with format-specific prefix)
+                    let display_file = display_path(result.workspace_root.as_deref(), &result.file);
                     if format == "markdown" {
-                        writeln!(output, "## File: {}", result.file.yellow())?;
+                        writeln!(output, "## File: {}", display_file.yellow())?;
                     } else {
-                        writeln!(output, "File: {}", result.file.yellow())?;
+                        writeln!(output, "File: {}", display_file.yellow())?;
                     }
 
                     // Show lines if not a full file
@@ -303,6 +383,14 @@ fn format_extraction_internal(
                         }
                     }
 
+                    if let Some(metrics) = &result.metrics {
+                        if format == "markdown" {
+                            writeln!(output, "### Metrics: {}", metrics)?;
+                        } else {
+                            writeln!(output, "Metrics: {}", metrics)?;
+                        }
+                    }
+
                     // In dry-run, we do NOT print the code
                     if !is_dry_run {
                         // Attempt a basic "highlight" approach by checking file extension
@@ -326,8 +414,6 @@ fn format_extraction_internal(
                                 writeln!(output)?;
                                 writeln!(output, "{}", result.code)?;
                                 writeln!(output)?;
-                                writeln!(output, "----------------------------------------")?;
-                                writeln!(output)?;
                             }
                             "color" => {
                                 if !language.is_empty() {
@@ -351,6 +437,17 @@ fn format_extraction_internal(
                         }
                     }
 
+                    if let Some(blocks) = &result.context_blocks {
+                        for line in context_lines(blocks, is_dry_run) {
+                            writeln!(output, "{}", line)?;
+                        }
+                    }
+
+                    if format == "plain" && !is_dry_run {
+                        writeln!(output, "----------------------------------------")?;
+                        writeln!(output)?;
+                    }
+
                     writeln!(output)?;
                 }
             }
@@ -372,7 +469,7 @@ fn format_extraction_internal(
             }
 
             // Summaries for non-JSON/XML:
-            if !["json", "xml"].contains(&format) && !results.is_empty() {
+            if !is_structured_format(format) && !results.is_empty() {
                 writeln!(output)?;
                 if is_dry_run {
                     writeln!(
@@ -461,6 +558,41 @@ pub fn format_extraction_results(
     )
 }
 
+/// Render extraction results with an output template.
+///
+/// # Arguments
+///
+/// * `results` - The search results to render
+/// * `template` - The output template
+/// * `is_dry_run` - Whether this is a dry-run request (only file names/line numbers)
+/// * `original_input` - Optional original user input
+/// * `system_prompt` - Optional system prompt for LLM models
+/// * `user_instructions` - Optional user instructions for LLM models
+pub fn format_extraction_with_template(
+    results: &[SearchResult],
+    template: &OutputTemplate,
+    is_dry_run: bool,
+    original_input: Option<&str>,
+    system_prompt: Option<&str>,
+    user_instructions: Option<&str>,
+) -> Result<String> {
+    let context = TemplateContext {
+        command: "extract",
+        query: None,
+        dry_run: is_dry_run,
+        results: results.iter().map(search_result_template_value).collect(),
+        totals: TemplateTotals {
+            count: results.len(),
+            total_bytes: results.iter().map(|r| r.code.len()).sum(),
+            total_tokens: results.iter().map(|r| count_tokens(&r.code)).sum(),
+        },
+        original_input: original_input.map(str::to_string),
+        system_prompt: system_prompt.map(str::to_string),
+        user_instructions: user_instructions.map(str::to_string),
+    };
+    template.render(&context)
+}
+
 /// Format and print the extraction results in the specified format
 ///
 /// # Arguments
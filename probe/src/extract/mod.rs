@@ -7,7 +7,8 @@
 mod file_paths;
 mod formatter;
 mod processor;
-mod prompts;
+pub mod prompt_commands;
+pub mod prompts;
 pub mod symbol_finder;
 
 // Re-export public functions
@@ -19,15 +20,21 @@ pub use file_paths::{
 #[allow(unused_imports)]
 pub use formatter::{
     format_and_print_extraction_results, format_extraction_dry_run, format_extraction_results,
+    get_language_from_extension,
 };
 #[allow(unused_imports)]
 pub use processor::process_file_for_extraction;
 #[allow(unused_imports)]
-pub use prompts::PromptTemplate;
+pub use prompts::{PromptTemplate, PromptVariables};
 
-use crate::extract::file_paths::{set_custom_ignores, FilePathInfo};
+use crate::extract::file_paths::FilePathInfo;
+use crate::language::block_context::add_block_context;
+use crate::language::block_metrics::add_block_metrics;
 use crate::models::SearchResult;
+use crate::search::is_structured_format;
+use crate::search::search_tokens::count_tokens;
 use anyhow::Result;
+use probe::workspace::Workspace;
 use std::collections::HashSet;
 use std::io::Read;
 #[allow(unused_imports)]
@@ -61,507 +68,127 @@ pub struct ExtractOptions {
     pub prompt: Option<prompts::PromptTemplate>,
     /// Optional user instructions for LLM models
     pub instructions: Option<String>,
+    /// Optional workspace; `<root>/<path>` references resolve against its roots
+    pub workspace: Option<Workspace>,
+    /// Optional output template, used instead of `format`
+    pub template: Option<probe::output_template::OutputTemplate>,
+    /// Whether to compute the size and complexity metrics of every result
+    pub metrics: bool,
+    /// Token budget per result of the imports and referenced definitions to attach
+    pub expand_context: Option<usize>,
 }
 
-/// Handle the extract command
-pub fn handle_extract(options: ExtractOptions) -> Result<()> {
-    use arboard::Clipboard;
-    use colored::*;
-
-    // Check if debug mode is enabled
-    let debug_mode = std::env::var("DEBUG").unwrap_or_default() == "1";
-
-    if debug_mode {
-        println!("\n[DEBUG] ===== Extract Command Started =====");
-        println!("[DEBUG] Files to process: {:?}", options.files);
-        println!("[DEBUG] Custom ignores: {:?}", options.custom_ignores);
-        println!("[DEBUG] Context lines: {}", options.context_lines);
-        println!("[DEBUG] Output format: {}", options.format);
-        println!("[DEBUG] Read from clipboard: {}", options.from_clipboard);
-        println!("[DEBUG] Write to clipboard: {}", options.to_clipboard);
-        println!("[DEBUG] Dry run: {}", options.dry_run);
-        println!("[DEBUG] Parse as git diff: {}", options.diff);
-        println!("[DEBUG] Allow tests: {}", options.allow_tests);
-        println!("[DEBUG] Prompt template: {:?}", options.prompt);
-        println!("[DEBUG] Instructions: {:?}", options.instructions);
-    }
-
-    // Set custom ignore patterns
-    set_custom_ignores(&options.custom_ignores);
-
-    let mut file_paths: Vec<FilePathInfo> = Vec::new();
-
-    // Store the original input if the keep_input flag is set
-    let mut original_input: Option<String> = None;
-
-    if options.from_clipboard {
-        // Read from clipboard
-        println!("{}", "Reading from clipboard...".bold().blue());
-        let mut clipboard = Clipboard::new()?;
-        let buffer = clipboard.get_text()?;
-
-        // Store the original input if keep_input is true
-        if options.keep_input {
-            original_input = Some(buffer.clone());
-            if debug_mode {
-                println!(
-                    "[DEBUG] Stored original clipboard input: {} bytes",
-                    original_input.as_ref().map_or(0, |s| s.len())
-                );
-            }
-        }
-
-        if debug_mode {
-            println!(
-                "[DEBUG] Reading from clipboard, content length: {} bytes",
-                buffer.len()
-            );
-        }
-
-        // Auto-detect git diff format or use explicit flag
-        let is_diff_format = options.diff || is_git_diff_format(&buffer);
-
-        if is_diff_format {
-            // Parse as git diff format
-            if debug_mode {
-                println!("[DEBUG] Parsing clipboard content as git diff format");
-            }
-            file_paths = extract_file_paths_from_git_diff(&buffer, options.allow_tests);
-        } else {
-            // Parse as regular text
-            file_paths = file_paths::extract_file_paths_from_text(&buffer, options.allow_tests);
-        }
-
-        if debug_mode {
-            println!(
-                "[DEBUG] Extracted {} file paths from clipboard",
-                file_paths.len()
-            );
-            for (path, start, end, symbol, lines) in &file_paths {
-                println!(
-                    "[DEBUG]   - {:?} (lines: {:?}-{:?}, symbol: {:?}, specific lines: {:?})",
-                    path,
-                    start,
-                    end,
-                    symbol,
-                    lines.as_ref().map(|l| l.len())
-                );
-            }
-        }
-
-        if file_paths.is_empty() {
-            println!("{}", "No file paths found in clipboard.".yellow().bold());
-            return Ok(());
-        }
-    } else if let Some(input_file_path) = &options.input_file {
-        // Read from input file
-        println!(
-            "{}",
-            format!("Reading from file: {}...", input_file_path)
-                .bold()
-                .blue()
-        );
-
-        // Check if the file exists
-        let input_path = std::path::Path::new(input_file_path);
-        if !input_path.exists() {
-            return Err(anyhow::anyhow!(
-                "Input file does not exist: {}",
-                input_file_path
-            ));
-        }
-
-        // Read the file content
-        let buffer = std::fs::read_to_string(input_path)?;
-
-        // Store the original input if keep_input is true
-        if options.keep_input {
-            original_input = Some(buffer.clone());
-            if debug_mode {
-                println!(
-                    "[DEBUG] Stored original file input: {} bytes",
-                    original_input.as_ref().map_or(0, |s| s.len())
-                );
-            }
-        }
-
-        if debug_mode {
-            println!(
-                "[DEBUG] Reading from file, content length: {} bytes",
-                buffer.len()
-            );
-        }
-
-        // Auto-detect git diff format or use explicit flag
-        let is_diff_format = options.diff || is_git_diff_format(&buffer);
-
-        if is_diff_format {
-            // Parse as git diff format
-            if debug_mode {
-                println!("[DEBUG] Parsing file content as git diff format");
-            }
-            file_paths = extract_file_paths_from_git_diff(&buffer, options.allow_tests);
-        } else {
-            // Parse as regular text
-            file_paths = file_paths::extract_file_paths_from_text(&buffer, options.allow_tests);
-        }
-
-        if debug_mode {
-            println!(
-                "[DEBUG] Extracted {} file paths from input file",
-                file_paths.len()
-            );
-            for (path, start, end, symbol, lines) in &file_paths {
-                println!(
-                    "[DEBUG]   - {:?} (lines: {:?}-{:?}, symbol: {:?}, specific lines: {:?})",
-                    path,
-                    start,
-                    end,
-                    symbol,
-                    lines.as_ref().map(|l| l.len())
-                );
-            }
-        }
-
-        if file_paths.is_empty() {
-            println!(
-                "{}",
-                format!("No file paths found in input file: {}", input_file_path)
-                    .yellow()
-                    .bold()
-            );
-            return Ok(());
-        }
-    } else if options.files.is_empty() {
-        // Check if stdin is available (not a terminal)
-        let is_stdin_available = !atty::is(atty::Stream::Stdin);
-
-        if is_stdin_available {
-            // Read from stdin
-            println!("{}", "Reading from stdin...".bold().blue());
-            let mut buffer = String::new();
-            std::io::stdin().read_to_string(&mut buffer)?;
-
-            // Store the original input if keep_input is true
-            if options.keep_input {
-                original_input = Some(buffer.clone());
-                if debug_mode {
-                    println!(
-                        "[DEBUG] Stored original stdin input: {} bytes",
-                        original_input.as_ref().map_or(0, |s| s.len())
-                    );
-                }
-            }
-
-            if debug_mode {
-                println!(
-                    "[DEBUG] Reading from stdin, content length: {} bytes",
-                    buffer.len()
-                );
-            }
-
-            // Auto-detect git diff format or use explicit flag
-            let is_diff_format = options.diff || is_git_diff_format(&buffer);
-
-            if is_diff_format {
-                // Parse as git diff format
-                if debug_mode {
-                    println!("[DEBUG] Parsing stdin content as git diff format");
-                }
-                file_paths = extract_file_paths_from_git_diff(&buffer, options.allow_tests);
-            } else {
-                // Parse as regular text
-                file_paths = file_paths::extract_file_paths_from_text(&buffer, options.allow_tests);
-            }
-        } else {
-            // No arguments and no stdin, show help
-            println!(
-                "{}",
-                "No files specified and no stdin input detected."
-                    .yellow()
-                    .bold()
-            );
-            println!("{}", "Use --help for usage information.".blue());
-            return Ok(());
-        }
-
-        if debug_mode {
-            println!(
-                "[DEBUG] Extracted {} file paths from stdin",
-                file_paths.len()
-            );
-            for (path, start, end, symbol, lines) in &file_paths {
-                println!(
-                    "[DEBUG]   - {:?} (lines: {:?}-{:?}, symbol: {:?}, specific lines: {:?})",
-                    path,
-                    start,
-                    end,
-                    symbol,
-                    lines.as_ref().map(|l| l.len())
-                );
-            }
-        }
-
-        if file_paths.is_empty() {
-            println!("{}", "No file paths found in stdin.".yellow().bold());
-            return Ok(());
-        }
-    } else {
-        // Parse command-line arguments
-        if debug_mode {
-            println!("[DEBUG] Parsing command-line arguments");
-        }
-
-        // Store the original input if keep_input is true
-        if options.keep_input {
-            original_input = Some(options.files.join(" "));
-            if debug_mode {
-                println!(
-                    "[DEBUG] Stored original command-line input: {}",
-                    original_input.as_ref().unwrap_or(&String::new())
-                );
-            }
-        }
-
-        for file in &options.files {
-            if debug_mode {
-                println!("[DEBUG] Parsing file argument: {}", file);
-            }
+/// What to extract code from
+#[derive(Debug, Clone)]
+pub enum ExtractInput {
+    /// File references such as `src/main.rs`, `src/main.rs:42`, `src/main.rs:10-20`
+    /// or `src/main.rs#symbol`, and glob patterns
+    Targets(Vec<String>),
+    /// Text mentioning file references, such as compiler output or a stack trace, or a
+    /// git diff
+    Text(String),
+}
 
-            let paths = file_paths::parse_file_with_line(file, options.allow_tests);
+/// A request to extract code, independent of where its input comes from and how its
+/// results are shown
+#[derive(Debug, Clone)]
+pub struct ExtractRequest {
+    pub input: ExtractInput,
+    /// Custom patterns to ignore
+    pub custom_ignores: Vec<String>,
+    /// Number of context lines to include
+    pub context_lines: usize,
+    /// Whether to parse text input as a git diff; diffs are also detected automatically
+    pub diff: bool,
+    /// Whether to allow test files and test code blocks
+    pub allow_tests: bool,
+    /// Optional workspace; `<root>/<path>` references resolve against its roots
+    pub workspace: Option<Workspace>,
+    /// Whether to compute the size and complexity metrics of every result
+    pub metrics: bool,
+    /// Token budget per result of the imports and referenced definitions to attach
+    pub expand_context: Option<usize>,
+    /// Prompt to render with the extracted files; results over its token budget are left out
+    pub prompt: Option<prompts::Prompt>,
+    /// Optional user instructions for LLM models
+    pub instructions: Option<String>,
+}
 
-            if debug_mode {
-                println!(
-                    "[DEBUG] Parsed {} paths from argument '{}'",
-                    paths.len(),
-                    file
-                );
-                for (path, start, end, symbol, lines) in &paths {
-                    println!(
-                        "[DEBUG]   - {:?} (lines: {:?}-{:?}, symbol: {:?}, specific lines: {:?})",
-                        path,
-                        start,
-                        end,
-                        symbol,
-                        lines.as_ref().map(|l| l.len())
-                    );
-                }
-            }
+/// A file and the part of it to extract
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExtractTarget {
+    pub path: PathBuf,
+    pub start_line: Option<usize>,
+    pub end_line: Option<usize>,
+    pub symbol: Option<String>,
+    /// Specific lines, e.g. the changed lines of a diff
+    pub lines: Option<HashSet<usize>>,
+}
 
-            file_paths.extend(paths);
+impl From<FilePathInfo> for ExtractTarget {
+    fn from((path, start_line, end_line, symbol, lines): FilePathInfo) -> Self {
+        ExtractTarget {
+            path,
+            start_line,
+            end_line,
+            symbol,
+            lines,
         }
     }
+}
 
-    // Only print file information for non-JSON/XML formats
-    if options.format != "json" && options.format != "xml" {
-        println!("{}", "Files to extract:".bold().green());
-
-        for (path, start_line, end_line, symbol, lines) in &file_paths {
-            if let (Some(start), Some(end)) = (start_line, end_line) {
-                println!("  {} (lines {}-{})", path.display(), start, end);
-            } else if let Some(line_num) = start_line {
-                println!("  {} (line {})", path.display(), line_num);
-            } else if let Some(sym) = symbol {
-                println!("  {} (symbol: {})", path.display(), sym);
-            } else if let Some(lines_set) = lines {
-                println!(
-                    "  {} (specific lines: {} lines)",
-                    path.display(),
-                    lines_set.len()
-                );
-            } else {
-                println!("  {}", path.display());
-            }
-        }
-
-        if options.context_lines > 0 {
-            println!("Context lines: {}", options.context_lines);
-        }
-
-        if options.dry_run {
-            println!("{}", "Dry run (file names and lines only)".yellow());
-        }
+/// A target nothing was extracted from
+#[derive(Debug, Clone, PartialEq)]
+pub struct SkippedTarget {
+    /// The file reference or path of the target
+    pub target: String,
+    pub reason: String,
+}
 
-        println!("Format: {}", options.format);
-        println!();
-    }
+/// The outcome of an extraction
+#[derive(Debug, Clone, Default)]
+pub struct ExtractResponse {
+    /// The files and parts of files that were extracted from, in input order
+    pub targets: Vec<ExtractTarget>,
+    /// Extracted code blocks, without blocks nested in other results
+    pub results: Vec<SearchResult>,
+    pub skipped: Vec<SkippedTarget>,
+    pub total_bytes: usize,
+    pub total_tokens: usize,
+    /// Number of results left out because they didn't fit the prompt's token budget
+    pub left_out: usize,
+    /// The request's prompt, rendered with the extracted files
+    pub system_prompt: Option<String>,
+    /// The request's instructions, unless the prompt already includes them
+    pub instructions: Option<String>,
+}
 
-    // Process prompt template and instructions if provided
-    let system_prompt = if let Some(prompt_template) = &options.prompt {
-        if debug_mode {
-            println!("[DEBUG] Processing prompt template: {:?}", prompt_template);
-        }
-        match prompt_template.get_content() {
-            Ok(content) => {
-                if debug_mode {
-                    println!(
-                        "[DEBUG] Loaded prompt template content ({} bytes)",
-                        content.len()
-                    );
-                }
-                Some(content)
-            }
-            Err(e) => {
-                eprintln!("{}", format!("Error loading prompt template: {}", e).red());
-                if debug_mode {
-                    println!("[DEBUG] Error loading prompt template: {}", e);
-                }
-                None
-            }
-        }
+/// Parse text into extraction targets, as a git diff if `diff` is set or the text is one
+fn parse_text_targets(
+    text: &str,
+    diff: bool,
+    allow_tests: bool,
+    custom_ignores: &[String],
+) -> Vec<FilePathInfo> {
+    if diff || is_git_diff_format(text) {
+        if std::env::var("DEBUG").unwrap_or_default() == "1" {
+            println!("[DEBUG] Parsing input as git diff format");
+        }
+        extract_file_paths_from_git_diff(text, allow_tests, custom_ignores)
     } else {
-        None
-    };
-
-    // Process files in parallel using Rayon
-    use rayon::prelude::*;
-    use std::sync::{Arc, Mutex};
-
-    // Create thread-safe containers for results and errors
-    let results_mutex = Arc::new(Mutex::new(Vec::<SearchResult>::new()));
-    let errors_mutex = Arc::new(Mutex::new(Vec::<String>::new()));
-
-    // Create a struct to hold all parameters for parallel processing
-    struct FileProcessingParams {
-        path: std::path::PathBuf,
-        start_line: Option<usize>,
-        end_line: Option<usize>,
-        symbol: Option<String>,
-        specific_lines: Option<HashSet<usize>>,
-        allow_tests: bool,
-        context_lines: usize,
-        debug_mode: bool,
-        format: String,
-
-        #[allow(dead_code)]
-        original_input: Option<String>,
-        #[allow(dead_code)]
-        system_prompt: Option<String>,
-        #[allow(dead_code)]
-        user_instructions: Option<String>,
+        file_paths::extract_file_paths_from_text(text, allow_tests, custom_ignores)
     }
+}
 
-    // Collect all file parameters
-    let file_params: Vec<FileProcessingParams> = file_paths
-        .into_iter()
-        .map(
-            |(path, start_line, end_line, symbol, specific_lines)| FileProcessingParams {
-                path,
-                start_line,
-                end_line,
-                symbol,
-                specific_lines,
-                allow_tests: options.allow_tests,
-                context_lines: options.context_lines,
-                debug_mode,
-                format: options.format.clone(),
-                original_input: original_input.clone(),
-                system_prompt: system_prompt.clone(),
-                user_instructions: options.instructions.clone(),
-            },
-        )
-        .collect();
-
-    // Process files in parallel
-    file_params.par_iter().for_each(|params| {
-        if params.debug_mode {
-            println!("\n[DEBUG] Processing file: {:?}", params.path);
-            println!("[DEBUG] Start line: {:?}", params.start_line);
-            println!("[DEBUG] End line: {:?}", params.end_line);
-            println!("[DEBUG] Symbol: {:?}", params.symbol);
-            println!(
-                "[DEBUG] Specific lines: {:?}",
-                params.specific_lines.as_ref().map(|l| l.len())
-            );
-
-            // Check if file exists
-            if params.path.exists() {
-                println!("[DEBUG] File exists: Yes");
-
-                // Get file extension and language
-                if let Some(ext) = params.path.extension().and_then(|e| e.to_str()) {
-                    let language = formatter::get_language_from_extension(ext);
-                    println!("[DEBUG] File extension: {}", ext);
-                    println!(
-                        "[DEBUG] Detected language: {}",
-                        if language.is_empty() {
-                            "unknown"
-                        } else {
-                            language
-                        }
-                    );
-                } else {
-                    println!("[DEBUG] File has no extension");
-                }
-            } else {
-                println!("[DEBUG] File exists: No");
-            }
-        }
-
-        // The allow_tests check is now handled in the file path extraction functions
-        // We only need to check if this is a test file for debugging purposes
-        if params.debug_mode && crate::language::is_test_file(&params.path) && !params.allow_tests {
-            println!("[DEBUG] Test file detected: {:?}", params.path);
-        }
-
-        match processor::process_file_for_extraction(
-            &params.path,
-            params.start_line,
-            params.end_line,
-            params.symbol.as_deref(),
-            params.allow_tests,
-            params.context_lines,
-            params.specific_lines.as_ref(),
-        ) {
-            Ok(result) => {
-                if params.debug_mode {
-                    println!("[DEBUG] Successfully extracted code from {:?}", params.path);
-                    println!("[DEBUG] Extracted lines: {:?}", result.lines);
-                    println!("[DEBUG] Node type: {}", result.node_type);
-                    println!("[DEBUG] Code length: {} bytes", result.code.len());
-                    println!(
-                        "[DEBUG] Estimated tokens: {}",
-                        crate::search::search_tokens::count_tokens(&result.code)
-                    );
-                }
-
-                // Thread-safe addition to results
-                let mut results = results_mutex.lock().unwrap();
-                results.push(result);
-            }
-            Err(e) => {
-                let error_msg = format!("Error processing file {:?}: {}", params.path, e);
-                if params.debug_mode {
-                    println!("[DEBUG] Error: {}", error_msg);
-                }
-                // Only print error messages for non-JSON/XML formats
-                if params.format != "json" && params.format != "xml" {
-                    eprintln!("{}", error_msg.red());
-                }
-                // Thread-safe addition to errors
-                let mut errors = errors_mutex.lock().unwrap();
-                errors.push(error_msg);
-            }
-        }
-    });
-    // Move results and errors from the mutex containers
-    let mut results = Arc::try_unwrap(results_mutex)
-        .expect("Failed to unwrap results mutex")
-        .into_inner()
-        .expect("Failed to get inner results");
-
-    let errors = Arc::try_unwrap(errors_mutex)
-        .expect("Failed to unwrap errors mutex")
-        .into_inner()
-        .expect("Failed to get inner errors");
-
-    // Deduplicate results based on file path and line range
+/// Remove exact duplicates and results nested in other results of the same file
+///
+/// Results are sorted by file path and then by line range size (largest first), so that
+/// parent blocks (like classes) are seen before nested blocks (like methods).
+fn deduplicate_results(mut results: Vec<SearchResult>, debug_mode: bool) -> Vec<SearchResult> {
     if debug_mode {
         println!("[DEBUG] Before deduplication: {} results", results.len());
     }
 
-    // First, sort results by file path and then by line range size (largest first)
-    // This ensures that parent blocks (like classes) are processed before nested blocks (like methods)
     results.sort_by(|a, b| {
         let a_file = &a.file;
         let b_file = &b.file;
@@ -648,25 +275,503 @@ pub fn handle_extract(options: ExtractOptions) -> Result<()> {
     }
 
     // Apply the retention filter
+    let results: Vec<SearchResult> = results
+        .into_iter()
+        .zip(to_retain)
+        .filter_map(|(result, retain)| retain.then_some(result))
+        .collect();
+
+    if debug_mode {
+        println!("[DEBUG] After deduplication: {} results", results.len());
+    }
+    results
+}
+
+/// Extract code blocks from the files an extraction request refers to
+///
+/// Nothing is printed and no clipboard or standard input is touched, so library users
+/// can reuse the parsing of file references, line numbers, `#symbol` references and
+/// diffs. Targets that can't be extracted are reported in `skipped` instead of failing
+/// the whole request. With a prompt, the results are trimmed to its token budget and the
+/// prompt is rendered with them.
+pub fn extract(request: ExtractRequest) -> Result<ExtractResponse> {
+    // Check if debug mode is enabled
+    let debug_mode = std::env::var("DEBUG").unwrap_or_default() == "1";
+
+    let mut skipped = Vec::new();
+    let mut file_paths: Vec<FilePathInfo> = match &request.input {
+        ExtractInput::Text(text) => {
+            if debug_mode {
+                println!("[DEBUG] Parsing text input: {} bytes", text.len());
+            }
+            parse_text_targets(
+                text,
+                request.diff,
+                request.allow_tests,
+                &request.custom_ignores,
+            )
+        }
+        ExtractInput::Targets(targets) => {
+            let mut file_paths = Vec::new();
+            for target in targets {
+                if debug_mode {
+                    println!("[DEBUG] Parsing file argument: {}", target);
+                }
+
+                // Map `<root>/<path>` references onto the workspace roots
+                let target = request
+                    .workspace
+                    .as_ref()
+                    .and_then(|workspace| workspace.resolve_reference(std::path::Path::new(target)))
+                    .map(|path| path.to_string_lossy().to_string())
+                    .unwrap_or_else(|| target.clone());
+
+                let paths = file_paths::parse_file_with_line(
+                    &target,
+                    request.allow_tests,
+                    &request.custom_ignores,
+                );
+                if paths.is_empty() {
+                    skipped.push(SkippedTarget {
+                        target: target.clone(),
+                        reason: "no files to extract (ignored and test files are skipped)"
+                            .to_string(),
+                    });
+                }
+                file_paths.extend(paths);
+            }
+            file_paths
+        }
+    };
+
+    // Paths found in text may also be workspace references
+    if let Some(workspace) = &request.workspace {
+        for (path, _, _, _, _) in file_paths.iter_mut() {
+            if let Some(resolved) = workspace.resolve_reference(path) {
+                *path = resolved;
+            }
+        }
+    }
+
+    if debug_mode {
+        println!("[DEBUG] Extracted {} file paths", file_paths.len());
+        for (path, start, end, symbol, lines) in &file_paths {
+            println!(
+                "[DEBUG]   - {:?} (lines: {:?}-{:?}, symbol: {:?}, specific lines: {:?})",
+                path,
+                start,
+                end,
+                symbol,
+                lines.as_ref().map(|l| l.len())
+            );
+        }
+    }
+
+    // Process files in parallel using Rayon; results keep the order of the targets
+    use rayon::prelude::*;
+    let outcomes: Vec<Result<SearchResult>> = file_paths
+        .par_iter()
+        .map(|(path, start_line, end_line, symbol, specific_lines)| {
+            if debug_mode {
+                println!("\n[DEBUG] Processing file: {:?}", path);
+                println!("[DEBUG] Start line: {:?}", start_line);
+                println!("[DEBUG] End line: {:?}", end_line);
+                println!("[DEBUG] Symbol: {:?}", symbol);
+                println!(
+                    "[DEBUG] Specific lines: {:?}",
+                    specific_lines.as_ref().map(|l| l.len())
+                );
+
+                // Check if file exists
+                if path.exists() {
+                    println!("[DEBUG] File exists: Yes");
+
+                    // Get file extension and language
+                    if let Some(ext) = path.extension().and_then(|e| e.to_str()) {
+                        let language = formatter::get_language_from_extension(ext);
+                        println!("[DEBUG] File extension: {}", ext);
+                        println!(
+                            "[DEBUG] Detected language: {}",
+                            if language.is_empty() {
+                                "unknown"
+                            } else {
+                                language
+                            }
+                        );
+                    } else {
+                        println!("[DEBUG] File has no extension");
+                    }
+                } else {
+                    println!("[DEBUG] File exists: No");
+                }
+            }
+
+            let result = processor::process_file_for_extraction(
+                path,
+                *start_line,
+                *end_line,
+                symbol.as_deref(),
+                request.allow_tests,
+                request.context_lines,
+                specific_lines.as_ref(),
+            );
+
+            if debug_mode {
+                match &result {
+                    Ok(result) => {
+                        println!("[DEBUG] Successfully extracted code from {:?}", path);
+                        println!("[DEBUG] Extracted lines: {:?}", result.lines);
+                        println!("[DEBUG] Node type: {}", result.node_type);
+                        println!("[DEBUG] Code length: {} bytes", result.code.len());
+                        println!("[DEBUG] Estimated tokens: {}", count_tokens(&result.code));
+                    }
+                    Err(e) => println!("[DEBUG] Error processing file {:?}: {}", path, e),
+                }
+            }
+            result
+        })
+        .collect();
+
+    let mut results = Vec::new();
+    for ((path, _, _, _, _), outcome) in file_paths.iter().zip(outcomes) {
+        match outcome {
+            Ok(result) => results.push(result),
+            Err(e) => skipped.push(SkippedTarget {
+                target: path.display().to_string(),
+                reason: e.to_string(),
+            }),
+        }
+    }
+
+    let mut results = deduplicate_results(results, debug_mode);
+    if request.metrics {
+        add_block_metrics(&mut results, None);
+    }
+
+    // Label results with their workspace root and make paths relative to it
+    if let Some(workspace) = &request.workspace {
+        for result in results.iter_mut() {
+            crate::search::search_runner::label_workspace_result(workspace, result);
+        }
+    }
+    if let Some(tokens) = request.expand_context {
+        add_block_context(&mut results, request.workspace.as_ref(), tokens);
+    }
+
+    // Send no more code with the prompt than its token budget allows
+    let left_out = match request
+        .prompt
+        .as_ref()
+        .and_then(|p| p.metadata.token_budget)
+    {
+        Some(budget) => fit_token_budget(&mut results, budget),
+        None => 0,
+    };
+
+    // Render the prompt with the extracted files; instructions it includes aren't repeated
+    let system_prompt = request.prompt.as_ref().map(|prompt| {
+        let diff = match &request.input {
+            ExtractInput::Text(text) if request.diff || is_git_diff_format(text) => text.clone(),
+            _ => String::new(),
+        };
+        prompt.render(&PromptVariables {
+            instructions: request.instructions.clone().unwrap_or_default(),
+            files: prompt_files(&results),
+            language: prompt_languages(&results),
+            diff,
+        })
+    });
+    let instructions = if request
+        .prompt
+        .as_ref()
+        .is_some_and(|p| p.uses_variable("instructions"))
+    {
+        None
+    } else {
+        request.instructions
+    };
+
+    Ok(ExtractResponse {
+        targets: file_paths.into_iter().map(ExtractTarget::from).collect(),
+        total_bytes: results.iter().map(|r| r.code.len()).sum(),
+        total_tokens: results.iter().map(|r| count_tokens(&r.code)).sum(),
+        results,
+        skipped,
+        left_out,
+        system_prompt,
+        instructions,
+    })
+}
+
+/// Keep the results that fit in a token budget and return how many were left out
+///
+/// Results are kept in order up to the first one that doesn't fit: a smaller result
+/// after it is left out too, so that the prompt never skips over part of the input.
+fn fit_token_budget(results: &mut Vec<SearchResult>, budget: usize) -> usize {
     let original_len = results.len();
-    let mut new_results = Vec::with_capacity(original_len);
+    let mut used = 0;
+    let fitting = results
+        .iter()
+        .take_while(|result| {
+            used += count_tokens(&result.code);
+            used <= budget
+        })
+        .count();
+    results.truncate(fitting);
+    original_len - fitting
+}
 
-    for i in 0..original_len {
-        if to_retain[i] {
-            new_results.push(results[i].clone());
+/// The extracted files and line ranges, one per line, for the `{{files}}` prompt variable
+fn prompt_files(results: &[SearchResult]) -> String {
+    results
+        .iter()
+        .map(|r| {
+            let file = probe::workspace::display_path(r.workspace_root.as_deref(), &r.file);
+            if r.node_type == "file" {
+                file
+            } else {
+                format!("{}:{}-{}", file, r.lines.0, r.lines.1)
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// The languages of the extracted files, most common first, for the `{{language}}`
+/// prompt variable
+fn prompt_languages(results: &[SearchResult]) -> String {
+    let mut counts: Vec<(&str, usize)> = Vec::new();
+    for result in results {
+        let extension = std::path::Path::new(&result.file)
+            .extension()
+            .and_then(|e| e.to_str())
+            .unwrap_or("");
+        let language = formatter::get_language_from_extension(extension);
+        if language.is_empty() {
+            continue;
+        }
+        match counts.iter_mut().find(|(l, _)| *l == language) {
+            Some((_, count)) => *count += 1,
+            None => counts.push((language, 1)),
+        }
+    }
+    counts.sort_by_key(|(_, count)| std::cmp::Reverse(*count));
+    counts
+        .into_iter()
+        .map(|(language, _)| language)
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Print the targets of an extraction and its settings, for the human-readable formats
+fn print_extraction_header(targets: &[ExtractTarget], options: &ExtractOptions) {
+    use colored::*;
+
+    println!("{}", "Files to extract:".bold().green());
+
+    for target in targets {
+        let path = target.path.display();
+        if let (Some(start), Some(end)) = (target.start_line, target.end_line) {
+            println!("  {} (lines {}-{})", path, start, end);
+        } else if let Some(line_num) = target.start_line {
+            println!("  {} (line {})", path, line_num);
+        } else if let Some(sym) = &target.symbol {
+            println!("  {} (symbol: {})", path, sym);
+        } else if let Some(lines_set) = &target.lines {
+            println!("  {} (specific lines: {} lines)", path, lines_set.len());
+        } else {
+            println!("  {}", path);
         }
     }
 
-    results = new_results;
+    if options.context_lines > 0 {
+        println!("Context lines: {}", options.context_lines);
+    }
+
+    if options.dry_run {
+        println!("{}", "Dry run (file names and lines only)".yellow());
+    }
+
+    println!("Format: {}", options.format);
+    println!();
+}
+
+/// Handle the extract command
+///
+/// Reads the input from the clipboard, an input file, standard input or the command
+/// line, runs `extract` and prints or copies the formatted results.
+pub fn handle_extract(options: ExtractOptions) -> Result<()> {
+    use arboard::Clipboard;
+    use colored::*;
+
+    // Check if debug mode is enabled
+    let debug_mode = std::env::var("DEBUG").unwrap_or_default() == "1";
 
     if debug_mode {
-        println!("[DEBUG] After deduplication: {} results", results.len());
+        println!("\n[DEBUG] ===== Extract Command Started =====");
+        println!("[DEBUG] Files to process: {:?}", options.files);
+        println!("[DEBUG] Custom ignores: {:?}", options.custom_ignores);
+        println!("[DEBUG] Context lines: {}", options.context_lines);
+        println!("[DEBUG] Output format: {}", options.format);
+        println!("[DEBUG] Read from clipboard: {}", options.from_clipboard);
+        println!("[DEBUG] Write to clipboard: {}", options.to_clipboard);
+        println!("[DEBUG] Dry run: {}", options.dry_run);
+        println!("[DEBUG] Parse as git diff: {}", options.diff);
+        println!("[DEBUG] Allow tests: {}", options.allow_tests);
+        println!("[DEBUG] Prompt template: {:?}", options.prompt);
+        println!("[DEBUG] Instructions: {:?}", options.instructions);
+    }
+
+    // Read the input, and the message to show when no file paths are found in it
+    let (input, no_paths_message) = if options.from_clipboard {
+        println!("{}", "Reading from clipboard...".bold().blue());
+        let mut clipboard = Clipboard::new()?;
+        (
+            ExtractInput::Text(clipboard.get_text()?),
+            "No file paths found in clipboard.".to_string(),
+        )
+    } else if let Some(input_file_path) = &options.input_file {
+        println!(
+            "{}",
+            format!("Reading from file: {}...", input_file_path)
+                .bold()
+                .blue()
+        );
+
+        // Check if the file exists
+        let input_path = std::path::Path::new(input_file_path);
+        if !input_path.exists() {
+            return Err(anyhow::anyhow!(
+                "Input file does not exist: {}",
+                input_file_path
+            ));
+        }
+        (
+            ExtractInput::Text(std::fs::read_to_string(input_path)?),
+            format!("No file paths found in input file: {}", input_file_path),
+        )
+    } else if options.files.is_empty() {
+        // Check if stdin is available (not a terminal)
+        if atty::is(atty::Stream::Stdin) {
+            // No arguments and no stdin, show help
+            println!(
+                "{}",
+                "No files specified and no stdin input detected."
+                    .yellow()
+                    .bold()
+            );
+            println!("{}", "Use --help for usage information.".blue());
+            return Ok(());
+        }
+
+        println!("{}", "Reading from stdin...".bold().blue());
+        let mut buffer = String::new();
+        std::io::stdin().read_to_string(&mut buffer)?;
+        (
+            ExtractInput::Text(buffer),
+            "No file paths found in stdin.".to_string(),
+        )
+    } else {
+        (ExtractInput::Targets(options.files.clone()), String::new())
+    };
+
+    // Store the original input if the keep_input flag is set
+    let original_input = options.keep_input.then(|| match &input {
+        ExtractInput::Text(text) => text.clone(),
+        ExtractInput::Targets(targets) => targets.join(" "),
+    });
+    let from_text = matches!(input, ExtractInput::Text(_));
+
+    // Load the prompt template
+    let prompt = options.prompt.as_ref().and_then(|prompt_template| {
+        if debug_mode {
+            println!("[DEBUG] Processing prompt template: {:?}", prompt_template);
+        }
+        match prompt_template.load() {
+            Ok(prompt) => {
+                if debug_mode {
+                    println!(
+                        "[DEBUG] Loaded prompt template content ({} bytes)",
+                        prompt.body.len()
+                    );
+                }
+                Some(prompt)
+            }
+            Err(e) => {
+                eprintln!(
+                    "{}",
+                    format!("Error loading prompt template: {:#}", e).red()
+                );
+                None
+            }
+        }
+    });
+
+    let response = extract(ExtractRequest {
+        input,
+        custom_ignores: options.custom_ignores.clone(),
+        context_lines: options.context_lines,
+        diff: options.diff,
+        allow_tests: options.allow_tests,
+        workspace: options.workspace.clone(),
+        metrics: options.metrics,
+        expand_context: options.expand_context,
+        prompt: prompt.clone(),
+        instructions: options.instructions.clone(),
+    })?;
+
+    if from_text && response.targets.is_empty() {
+        println!("{}", no_paths_message.yellow().bold());
+        return Ok(());
     }
 
+    // Only print file information for human-readable formats
+    if !is_structured_format(&options.format) {
+        print_extraction_header(&response.targets, &options);
+    }
+
+    // Only print error messages for human-readable formats
+    if !is_structured_format(&options.format) {
+        for skipped in &response.skipped {
+            eprintln!(
+                "{}",
+                format!("Error processing {}: {}", skipped.target, skipped.reason).red()
+            );
+        }
+    }
+
+    if response.left_out > 0 {
+        if let Some(prompt) = &prompt {
+            eprintln!(
+                "{}",
+                format!(
+                    "Left out {} {} over the token budget of {} of prompt '{}'",
+                    response.left_out,
+                    if response.left_out == 1 {
+                        "result"
+                    } else {
+                        "results"
+                    },
+                    prompt.metadata.token_budget.unwrap_or_default(),
+                    prompt.name
+                )
+                .yellow()
+            );
+        }
+    }
+
+    let results = &response.results;
+    let system_prompt = response.system_prompt.as_deref();
+    let user_instructions = response.instructions.as_deref();
+
     if debug_mode {
         println!("\n[DEBUG] ===== Extraction Summary =====");
         println!("[DEBUG] Total results: {}", results.len());
-        println!("[DEBUG] Total errors: {}", errors.len());
+        println!(
+            "[DEBUG] Total bytes: {}, total tokens: {}",
+            response.total_bytes, response.total_tokens
+        );
+        println!("[DEBUG] Total errors: {}", response.skipped.len());
         println!("[DEBUG] Output format: {}", options.format);
         println!("[DEBUG] Dry run: {}", options.dry_run);
     }
@@ -683,21 +788,30 @@ pub fn handle_extract(options: ExtractOptions) -> Result<()> {
         };
 
         // Format the results
-        let result = if options.dry_run {
+        let result = if let Some(template) = &options.template {
+            formatter::format_extraction_with_template(
+                results,
+                template,
+                options.dry_run,
+                original_input.as_deref(),
+                system_prompt,
+                user_instructions,
+            )
+        } else if options.dry_run {
             formatter::format_extraction_dry_run(
-                &results,
+                results,
                 &options.format,
                 original_input.as_deref(),
-                system_prompt.as_deref(),
-                options.instructions.as_deref(),
+                system_prompt,
+                user_instructions,
             )
         } else {
             formatter::format_extraction_results(
-                &results,
+                results,
                 &options.format,
                 original_input.as_deref(),
-                system_prompt.as_deref(),
-                options.instructions.as_deref(),
+                system_prompt,
+                user_instructions,
             )
         };
 
@@ -728,8 +842,8 @@ pub fn handle_extract(options: ExtractOptions) -> Result<()> {
             }
         }
         Err(e) => {
-            // Only print error messages for non-JSON/XML formats
-            if options.format != "json" && options.format != "xml" {
+            // Only print error messages for human-readable formats
+            if !is_structured_format(&options.format) {
                 eprintln!("{}", format!("Error formatting results: {}", e).red());
             }
             if debug_mode {
@@ -738,14 +852,15 @@ pub fn handle_extract(options: ExtractOptions) -> Result<()> {
         }
     }
 
-    // Print summary of errors if any (only for non-JSON/XML formats)
-    if !errors.is_empty() && options.format != "json" && options.format != "xml" {
+    // Print summary of errors if any (only for human-readable formats)
+    let errors = response.skipped.len();
+    if errors > 0 && !is_structured_format(&options.format) {
         println!();
         println!(
             "{} {} {}",
             "Encountered".red().bold(),
-            errors.len(),
-            if errors.len() == 1 { "error" } else { "errors" }
+            errors,
+            if errors == 1 { "error" } else { "errors" }
         );
     }
 
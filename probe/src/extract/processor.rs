@@ -3,11 +3,11 @@
 //! This module provides functions for processing files and extracting code blocks
 //! based on file paths and optional line numbers.
 use crate::extract::symbol_finder::find_symbol_in_file;
-use crate::language::parser::parse_file_for_code_blocks;
+use crate::language::parser::parse_file_for_code_blocks_at;
 use crate::models::SearchResult;
+use crate::search::source_files::read_source_file;
 use anyhow::{Context, Result};
 use std::collections::HashSet;
-use std::fs;
 use std::path::Path;
 
 /// Process a single file and extract code blocks
@@ -53,7 +53,7 @@ pub fn process_file_for_extraction(
     }
 
     // Read the file content
-    let content = fs::read_to_string(path).context(format!("Failed to read file: {:?}", path))?;
+    let content = read_source_file(path).context(format!("Failed to read file: {:?}", path))?;
     let lines: Vec<&str> = content.lines().collect();
 
     if debug_mode {
@@ -121,7 +121,8 @@ pub fn process_file_for_extraction(
             }
         }
 
-        let code_blocks_result = parse_file_for_code_blocks(
+        let code_blocks_result = parse_file_for_code_blocks_at(
+            &path.to_string_lossy(),
             &content,
             file_extension(path),
             &needed_lines,
@@ -163,7 +164,7 @@ pub fn process_file_for_extraction(
                     .map(|f| f.to_string_lossy().to_string())
                     .unwrap_or_default();
                 let tokenized_content =
-                    crate::ranking::preprocess_text_with_filename(&merged_content, &filename);
+                    crate::ranking::preprocess_text_with_filename(&merged_content, &filename, None);
 
                 Ok(SearchResult {
                     file: path.to_string_lossy().to_string(),
@@ -189,6 +190,15 @@ pub fn process_file_for_extraction(
                     block_id: None,
                     matched_keywords: None,
                     tokenized_content: Some(tokenized_content),
+                    workspace_root: None,
+                    changed_since_seen: None,
+                    explanation: None,
+                    match_ranges: None,
+                    metrics: None,
+                    parent_block: None,
+                    context_blocks: None,
+                    symbol: None,
+                    symbol_occurrence: None,
                 })
             }
             _ => {
@@ -208,7 +218,7 @@ pub fn process_file_for_extraction(
                     .map(|f| f.to_string_lossy().to_string())
                     .unwrap_or_default();
                 let tokenized_content =
-                    crate::ranking::preprocess_text_with_filename(&range_content, &filename);
+                    crate::ranking::preprocess_text_with_filename(&range_content, &filename, None);
 
                 Ok(SearchResult {
                     file: path.to_string_lossy().to_string(),
@@ -234,6 +244,15 @@ pub fn process_file_for_extraction(
                     block_id: None,
                     matched_keywords: None,
                     tokenized_content: Some(tokenized_content),
+                    workspace_root: None,
+                    changed_since_seen: None,
+                    explanation: None,
+                    match_ranges: None,
+                    metrics: None,
+                    parent_block: None,
+                    context_blocks: None,
+                    symbol: None,
+                    symbol_occurrence: None,
                 })
             }
         }
@@ -270,7 +289,8 @@ pub fn process_file_for_extraction(
             }
         }
 
-        match parse_file_for_code_blocks(
+        match parse_file_for_code_blocks_at(
+            &path.to_string_lossy(),
             &content,
             file_extension(path),
             &needed_lines,
@@ -309,9 +329,9 @@ pub fn process_file_for_extraction(
                     .map(|f| f.to_string_lossy().to_string())
                     .unwrap_or_default();
                 let tokenized_content =
-                    crate::ranking::preprocess_text_with_filename(&merged_content, &filename);
+                    crate::ranking::preprocess_text_with_filename(&merged_content, &filename, None);
 
-                return Ok(SearchResult {
+                Ok(SearchResult {
                     file: path.to_string_lossy().to_string(),
                     lines: (merged_start, merged_end),
                     node_type: "merged_ast_line".to_string(),
@@ -335,7 +355,16 @@ pub fn process_file_for_extraction(
                     block_id: None,
                     matched_keywords: None,
                     tokenized_content: Some(tokenized_content),
-                });
+                    workspace_root: None,
+                    changed_since_seen: None,
+                    explanation: None,
+                    match_ranges: None,
+                    metrics: None,
+                    parent_block: None,
+                    context_blocks: None,
+                    symbol: None,
+                    symbol_occurrence: None,
+                })
             }
             _ => {
                 // If no AST block found, fallback to the line + context
@@ -366,9 +395,9 @@ pub fn process_file_for_extraction(
                     .map(|f| f.to_string_lossy().to_string())
                     .unwrap_or_default();
                 let tokenized_content =
-                    crate::ranking::preprocess_text_with_filename(&context_code, &filename);
+                    crate::ranking::preprocess_text_with_filename(&context_code, &filename, None);
 
-                return Ok(SearchResult {
+                Ok(SearchResult {
                     file: path.to_string_lossy().to_string(),
                     lines: (start_ctx, end_ctx),
                     node_type: "context".to_string(),
@@ -392,7 +421,16 @@ pub fn process_file_for_extraction(
                     block_id: None,
                     matched_keywords: None,
                     tokenized_content: Some(tokenized_content),
-                });
+                    workspace_root: None,
+                    changed_since_seen: None,
+                    explanation: None,
+                    match_ranges: None,
+                    metrics: None,
+                    parent_block: None,
+                    context_blocks: None,
+                    symbol: None,
+                    symbol_occurrence: None,
+                })
             }
         }
     } else if let Some(lines_set) = specific_lines {
@@ -412,7 +450,7 @@ pub fn process_file_for_extraction(
                 .map(|f| f.to_string_lossy().to_string())
                 .unwrap_or_default();
             let tokenized_content =
-                crate::ranking::preprocess_text_with_filename(&content, &filename);
+                crate::ranking::preprocess_text_with_filename(&content, &filename, None);
 
             return Ok(SearchResult {
                 file: path.to_string_lossy().to_string(),
@@ -438,6 +476,15 @@ pub fn process_file_for_extraction(
                 block_id: None,
                 matched_keywords: None,
                 tokenized_content: Some(tokenized_content),
+                workspace_root: None,
+                changed_since_seen: None,
+                explanation: None,
+                match_ranges: None,
+                metrics: None,
+                parent_block: None,
+                context_blocks: None,
+                symbol: None,
+                symbol_occurrence: None,
             });
         }
 
@@ -468,7 +515,8 @@ pub fn process_file_for_extraction(
         let lines_set = &clamped_lines;
 
         // Parse AST for all specified lines
-        let code_blocks_result = parse_file_for_code_blocks(
+        let code_blocks_result = parse_file_for_code_blocks_at(
+            &path.to_string_lossy(),
             &content,
             file_extension(path),
             lines_set,
@@ -509,9 +557,9 @@ pub fn process_file_for_extraction(
                     .map(|f| f.to_string_lossy().to_string())
                     .unwrap_or_default();
                 let tokenized_content =
-                    crate::ranking::preprocess_text_with_filename(&merged_content, &filename);
+                    crate::ranking::preprocess_text_with_filename(&merged_content, &filename, None);
 
-                return Ok(SearchResult {
+                Ok(SearchResult {
                     file: path.to_string_lossy().to_string(),
                     lines: (merged_start, merged_end),
                     node_type: "merged_ast_specific_lines".to_string(),
@@ -535,7 +583,16 @@ pub fn process_file_for_extraction(
                     block_id: None,
                     matched_keywords: None,
                     tokenized_content: Some(tokenized_content),
-                });
+                    workspace_root: None,
+                    changed_since_seen: None,
+                    explanation: None,
+                    match_ranges: None,
+                    metrics: None,
+                    parent_block: None,
+                    context_blocks: None,
+                    symbol: None,
+                    symbol_occurrence: None,
+                })
             }
             _ => {
                 // Fallback to literal extraction of the specific lines
@@ -567,9 +624,9 @@ pub fn process_file_for_extraction(
                     .map(|f| f.to_string_lossy().to_string())
                     .unwrap_or_default();
                 let tokenized_content =
-                    crate::ranking::preprocess_text_with_filename(&range_content, &filename);
+                    crate::ranking::preprocess_text_with_filename(&range_content, &filename, None);
 
-                return Ok(SearchResult {
+                Ok(SearchResult {
                     file: path.to_string_lossy().to_string(),
                     lines: (start, end),
                     node_type: "specific_lines".to_string(),
@@ -593,7 +650,16 @@ pub fn process_file_for_extraction(
                     block_id: None,
                     matched_keywords: None,
                     tokenized_content: Some(tokenized_content),
-                });
+                    workspace_root: None,
+                    changed_since_seen: None,
+                    explanation: None,
+                    match_ranges: None,
+                    metrics: None,
+                    parent_block: None,
+                    context_blocks: None,
+                    symbol: None,
+                    symbol_occurrence: None,
+                })
             }
         }
     } else {
@@ -607,7 +673,8 @@ pub fn process_file_for_extraction(
             .file_name()
             .map(|f| f.to_string_lossy().to_string())
             .unwrap_or_default();
-        let tokenized_content = crate::ranking::preprocess_text_with_filename(&content, &filename);
+        let tokenized_content =
+            crate::ranking::preprocess_text_with_filename(&content, &filename, None);
 
         Ok(SearchResult {
             file: path.to_string_lossy().to_string(),
@@ -633,6 +700,15 @@ pub fn process_file_for_extraction(
             block_id: None,
             matched_keywords: None,
             tokenized_content: Some(tokenized_content),
+            workspace_root: None,
+            changed_since_seen: None,
+            explanation: None,
+            match_ranges: None,
+            metrics: None,
+            parent_block: None,
+            context_blocks: None,
+            symbol: None,
+            symbol_occurrence: None,
         })
     }
 }
@@ -0,0 +1,120 @@
+use anyhow::Result;
+use colored::*;
+use std::path::PathBuf;
+
+use crate::extract::prompts::{self, Prompt, PromptSource};
+
+/// The directory prompt templates are discovered from
+fn current_dir() -> PathBuf {
+    std::env::current_dir().unwrap_or_else(|_| PathBuf::from("."))
+}
+
+fn source_name(source: PromptSource) -> &'static str {
+    match source {
+        PromptSource::Builtin => "builtin",
+        PromptSource::Project => "project",
+        PromptSource::User => "user",
+        PromptSource::File => "file",
+    }
+}
+
+/// Describe the front matter settings of a template, e.g. "model: x, budget: 100 tokens"
+fn describe_settings(prompt: &Prompt) -> String {
+    let mut settings = Vec::new();
+    if let Some(model) = &prompt.metadata.model {
+        settings.push(format!("model: {}", model));
+    }
+    if let Some(budget) = prompt.metadata.token_budget {
+        settings.push(format!("budget: {} tokens", budget));
+    }
+    if let Some(format) = &prompt.metadata.format {
+        settings.push(format!("format: {}", format));
+    }
+    settings.join(", ")
+}
+
+/// Handle `probe prompts list`
+pub fn handle_prompts_list(format: &str) -> Result<()> {
+    let prompts = prompts::discover_prompts(&current_dir())?;
+
+    if format == "json" {
+        let wrapper = serde_json::json!({ "prompts": prompts });
+        println!("{}", serde_json::to_string_pretty(&wrapper)?);
+        return Ok(());
+    }
+
+    let use_color = format == "color" || format == "terminal";
+    let name_width = prompts.iter().map(|p| p.name.len()).max().unwrap_or(0);
+    for prompt in &prompts {
+        let name = format!("{:width$}", prompt.name, width = name_width);
+        let name = if use_color {
+            name.bold().cyan().to_string()
+        } else {
+            name
+        };
+        let mut line = format!("{}  {:7}", name, source_name(prompt.source));
+        if let Some(description) = &prompt.metadata.description {
+            line.push_str(&format!("  {}", description));
+        }
+        let settings = describe_settings(prompt);
+        if !settings.is_empty() {
+            line.push_str(&format!(" ({})", settings));
+        }
+        println!("{}", line);
+    }
+
+    Ok(())
+}
+
+/// Handle `probe prompts show <NAME>`
+pub fn handle_prompts_show(name: &str, format: &str) -> Result<()> {
+    let prompt = match prompts::find_prompt(&current_dir(), name)? {
+        Some(prompt) => prompt,
+        None => anyhow::bail!(
+            "No prompt template named '{}'. Run 'probe prompts list' to see the available templates.",
+            name
+        ),
+    };
+
+    if format == "json" {
+        let mut value = serde_json::to_value(&prompt)?;
+        value["variables"] = serde_json::json!(prompt.variables());
+        println!("{}", serde_json::to_string_pretty(&value)?);
+        return Ok(());
+    }
+
+    let use_color = format == "color" || format == "terminal";
+    let label = |text: &str| -> String {
+        if use_color {
+            text.bold().green().to_string()
+        } else {
+            text.to_string()
+        }
+    };
+
+    println!("{} {}", label("Name:"), prompt.name);
+    println!("{} {}", label("Source:"), source_name(prompt.source));
+    if let Some(path) = &prompt.path {
+        println!("{} {}", label("Path:"), path.display());
+    }
+    if let Some(description) = &prompt.metadata.description {
+        println!("{} {}", label("Description:"), description);
+    }
+    if let Some(model) = &prompt.metadata.model {
+        println!("{} {}", label("Model:"), model);
+    }
+    if let Some(budget) = prompt.metadata.token_budget {
+        println!("{} {} tokens", label("Token budget:"), budget);
+    }
+    if let Some(format) = &prompt.metadata.format {
+        println!("{} {}", label("Format:"), format);
+    }
+    let variables = prompt.variables();
+    if !variables.is_empty() {
+        println!("{} {}", label("Variables:"), variables.join(", "));
+    }
+    println!();
+    println!("{}", prompt.body.trim_end());
+
+    Ok(())
+}
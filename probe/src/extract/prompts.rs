@@ -1,11 +1,35 @@
 //! Prompt templates for LLM models.
 //!
-//! This module provides functionality for loading and formatting prompt templates
-//! for use with LLM models. It supports built-in templates and loading from files.
+//! This module provides functionality for loading and rendering prompt templates for use
+//! with LLM models. Besides the built-in `engineer` and `architect` templates, named
+//! templates are discovered in the project's `.probe/prompts` directory (in the current
+//! directory or one of its parents) and in `~/.config/probe/prompts`. A template file is
+//! named after its file name without the `.md` or `.txt` extension, and may start with
+//! TOML front matter between `+++` lines:
+//!
+//! ```text
+//! +++
+//! description = "Review a change for bugs"
+//! model = "claude-3-5-sonnet"
+//! token_budget = 8000
+//! format = "xml"
+//! +++
+//! Review the following {{language}} code in {{files}}.
+//! {{instructions}}
+//! ```
+//!
+//! `{{instructions}}`, `{{files}}`, `{{language}}` and `{{diff}}` are replaced with the
+//! user's instructions, the extracted files and line ranges, their languages, and the
+//! git diff that was extracted from, if any. Project templates take precedence over
+//! user templates, and both over the built-in ones.
 
 use anyhow::{Context, Result};
+use once_cell::sync::Lazy;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use std::fs;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
 /// Built-in engineer prompt template
 pub const ENGINEER_PROMPT: &str = r#"As a senior software engineer, your task is providing explicit, actionable code adjustments. For each required change:
@@ -52,6 +76,266 @@ Brief code snippets may be included for clarity, but do not produce a full imple
 
 Your analysis should strictly cover the technical implementation plan, excluding deployment, testing, or validation unless explicitly tied to architectural impact."#;
 
+/// Directory of project prompt templates, looked for in the current directory and its parents
+pub const PROJECT_PROMPTS_DIR: &str = ".probe/prompts";
+
+/// Variables prompt templates can use
+pub const PROMPT_VARIABLES: &[&str] = &["instructions", "files", "language", "diff"];
+
+/// Formats a template can ask extracted code to be printed in
+const PROMPT_FORMATS: &[&str] = &["markdown", "plain", "json", "jsonl", "xml", "color"];
+
+static VARIABLE: Lazy<Regex> = Lazy::new(|| Regex::new(r"\{\{\s*(\w+)\s*\}\}").unwrap());
+
+/// Metadata from the front matter of a prompt template
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct PromptMetadata {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    /// Model the prompt is written for
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+    /// Maximum number of tokens of extracted code to send with the prompt
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub token_budget: Option<usize>,
+    /// Output format to use when none is given on the command line
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub format: Option<String>,
+}
+
+/// Where a prompt template comes from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PromptSource {
+    Builtin,
+    /// The project's `.probe/prompts` directory
+    Project,
+    /// `~/.config/probe/prompts`
+    User,
+    /// A file given by path
+    File,
+}
+
+/// A loaded prompt template
+#[derive(Debug, Clone, Serialize)]
+pub struct Prompt {
+    pub name: String,
+    pub source: PromptSource,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub path: Option<PathBuf>,
+    #[serde(flatten)]
+    pub metadata: PromptMetadata,
+    /// The template text, without front matter
+    pub body: String,
+}
+
+/// Values of the variables of a prompt template
+#[derive(Debug, Clone, Default)]
+pub struct PromptVariables {
+    pub instructions: String,
+    /// Extracted files and line ranges, one per line
+    pub files: String,
+    /// Languages of the extracted files
+    pub language: String,
+    /// The git diff code was extracted from
+    pub diff: String,
+}
+
+impl Prompt {
+    /// Parse a prompt template, with optional front matter
+    pub fn parse(
+        name: &str,
+        source: PromptSource,
+        path: Option<PathBuf>,
+        content: &str,
+    ) -> Result<Self> {
+        let (metadata, body) = match content.strip_prefix("+++") {
+            Some(rest) if rest.starts_with('\n') || rest.starts_with("\r\n") => {
+                let (front_matter, body) = rest
+                    .split_once("\n+++")
+                    .context("Front matter is not closed with '+++'")?;
+                let metadata: PromptMetadata =
+                    toml::from_str(front_matter).context("Invalid front matter")?;
+                let body = body.split_once('\n').map(|(_, body)| body).unwrap_or("");
+                (metadata, body)
+            }
+            _ => (PromptMetadata::default(), content),
+        };
+
+        if let Some(format) = &metadata.format {
+            if !PROMPT_FORMATS.contains(&format.as_str()) {
+                anyhow::bail!(
+                    "Unknown format '{}'; use one of {}",
+                    format,
+                    PROMPT_FORMATS.join(", ")
+                );
+            }
+        }
+        for captures in VARIABLE.captures_iter(body) {
+            if !PROMPT_VARIABLES.contains(&&captures[1]) {
+                anyhow::bail!(
+                    "Unknown variable '{}'; templates can use {}",
+                    &captures[0],
+                    PROMPT_VARIABLES
+                        .iter()
+                        .map(|v| format!("{{{{{}}}}}", v))
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                );
+            }
+        }
+
+        Ok(Prompt {
+            name: name.to_string(),
+            source,
+            path,
+            metadata,
+            body: body.to_string(),
+        })
+    }
+
+    /// Load a prompt template file
+    pub fn load(path: &Path, source: PromptSource) -> Result<Self> {
+        let content = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read prompt file: {}", path.display()))?;
+        let name = path
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        Self::parse(&name, source, Some(path.to_path_buf()), &content)
+            .with_context(|| format!("Invalid prompt template: {}", path.display()))
+    }
+
+    fn builtin(name: &str, body: &str) -> Self {
+        Prompt {
+            name: name.to_string(),
+            source: PromptSource::Builtin,
+            path: None,
+            metadata: PromptMetadata {
+                description: Some(format!("Built-in {} prompt", name)),
+                ..Default::default()
+            },
+            body: body.to_string(),
+        }
+    }
+
+    /// The variables the template uses, in order of first use
+    pub fn variables(&self) -> Vec<String> {
+        let mut variables: Vec<String> = Vec::new();
+        for captures in VARIABLE.captures_iter(&self.body) {
+            if !variables.iter().any(|v| v == &captures[1]) {
+                variables.push(captures[1].to_string());
+            }
+        }
+        variables
+    }
+
+    /// Whether the template uses a variable
+    pub fn uses_variable(&self, name: &str) -> bool {
+        self.variables().iter().any(|v| v == name)
+    }
+
+    /// The output format to use when none is given: the template's `format`, or else the
+    /// format suited to its `model`
+    pub fn preferred_format(&self) -> Option<String> {
+        if let Some(format) = &self.metadata.format {
+            return Some(format.clone());
+        }
+        // Claude models are trained to read XML-tagged context, other models markdown
+        let model = self.metadata.model.as_ref()?.to_lowercase();
+        Some(
+            if model.starts_with("claude") {
+                "xml"
+            } else {
+                "markdown"
+            }
+            .to_string(),
+        )
+    }
+
+    /// Render the template, replacing its variables
+    pub fn render(&self, variables: &PromptVariables) -> String {
+        VARIABLE
+            .replace_all(&self.body, |captures: &regex::Captures| {
+                match &captures[1] {
+                    "instructions" => variables.instructions.clone(),
+                    "files" => variables.files.clone(),
+                    "language" => variables.language.clone(),
+                    "diff" => variables.diff.clone(),
+                    // Parsing rejects other variables
+                    other => other.to_string(),
+                }
+            })
+            .into_owned()
+    }
+}
+
+/// The user's prompt template directory, `~/.config/probe/prompts`
+pub fn user_prompts_dir() -> Option<PathBuf> {
+    dirs::home_dir().map(|home| home.join(".config").join("probe").join("prompts"))
+}
+
+/// The nearest `.probe/prompts` directory in `start` or one of its parents
+pub fn project_prompts_dir(start: &Path) -> Option<PathBuf> {
+    let start = start.canonicalize().unwrap_or_else(|_| start.to_path_buf());
+    start
+        .ancestors()
+        .map(|dir| dir.join(PROJECT_PROMPTS_DIR))
+        .find(|dir| dir.is_dir())
+}
+
+/// Load the templates of a prompt directory, by name
+fn load_prompt_dir(dir: &Path, source: PromptSource) -> Result<BTreeMap<String, Prompt>> {
+    let mut prompts = BTreeMap::new();
+    let Ok(entries) = fs::read_dir(dir) else {
+        return Ok(prompts);
+    };
+
+    for entry in entries.flatten() {
+        let path = entry.path();
+        let is_template = matches!(
+            path.extension().and_then(|e| e.to_str()),
+            Some("md") | Some("txt")
+        );
+        if is_template && path.is_file() {
+            let prompt = Prompt::load(&path, source)?;
+            prompts.insert(prompt.name.to_lowercase(), prompt);
+        }
+    }
+    Ok(prompts)
+}
+
+/// All prompt templates available from `dir`, sorted by name
+///
+/// Templates in the project directory replace user templates of the same name, and both
+/// replace built-in templates.
+pub fn discover_prompts(dir: &Path) -> Result<Vec<Prompt>> {
+    let mut prompts = BTreeMap::new();
+    prompts.insert(
+        "architect".to_string(),
+        Prompt::builtin("architect", ARCHITECT_PROMPT),
+    );
+    prompts.insert(
+        "engineer".to_string(),
+        Prompt::builtin("engineer", ENGINEER_PROMPT),
+    );
+    if let Some(user_dir) = user_prompts_dir() {
+        prompts.extend(load_prompt_dir(&user_dir, PromptSource::User)?);
+    }
+    if let Some(project_dir) = project_prompts_dir(dir) {
+        prompts.extend(load_prompt_dir(&project_dir, PromptSource::Project)?);
+    }
+    Ok(prompts.into_values().collect())
+}
+
+/// Find a prompt template by name, case-insensitively
+pub fn find_prompt(dir: &Path, name: &str) -> Result<Option<Prompt>> {
+    Ok(discover_prompts(dir)?
+        .into_iter()
+        .find(|prompt| prompt.name.eq_ignore_ascii_case(name)))
+}
+
 /// Enum representing different prompt template sources
 #[derive(Debug, Clone)]
 pub enum PromptTemplate {
@@ -61,37 +345,95 @@ pub enum PromptTemplate {
     Architect,
     /// Custom template loaded from a file
     Custom(String),
+    /// Named template from the project or user prompt directory
+    Named(Box<Prompt>),
 }
 
 impl PromptTemplate {
     /// Parse a prompt template string into a PromptTemplate enum
+    ///
+    /// The string is a path to a template file or the name of a template; named templates
+    /// in the prompt directories take precedence over the built-in ones.
     #[allow(clippy::should_implement_trait)]
     pub fn from_str(template_str: &str) -> Result<Self> {
-        match template_str.to_lowercase().as_str() {
-            "engineer" => Ok(PromptTemplate::Engineer),
-            "architect" => Ok(PromptTemplate::Architect),
-            path => {
-                // Check if the string is a valid file path
-                let path_obj = Path::new(path);
-                if path_obj.exists() && path_obj.is_file() {
-                    Ok(PromptTemplate::Custom(path.to_string()))
-                } else {
-                    Err(anyhow::anyhow!(
-                        "Invalid prompt template: '{}'. Use 'engineer', 'architect', or a valid file path.",
-                        template_str
-                    ))
-                }
+        // Check if the string is a valid file path
+        let path_obj = Path::new(template_str);
+        if path_obj.is_file() {
+            return Ok(PromptTemplate::Custom(template_str.to_string()));
+        }
+
+        let current_dir = std::env::current_dir().unwrap_or_else(|_| PathBuf::from("."));
+        match find_prompt(&current_dir, template_str)? {
+            Some(prompt) if prompt.source != PromptSource::Builtin => {
+                Ok(PromptTemplate::Named(Box::new(prompt)))
             }
+            _ => match template_str.to_lowercase().as_str() {
+                "engineer" => Ok(PromptTemplate::Engineer),
+                "architect" => Ok(PromptTemplate::Architect),
+                _ => Err(anyhow::anyhow!(
+                    "Invalid prompt template: '{}'. Use 'engineer', 'architect', the name of a template (see 'probe prompts list'), or a valid file path.",
+                    template_str
+                )),
+            },
         }
     }
 
-    /// Get the content of the prompt template
-    pub fn get_content(&self) -> Result<String> {
+    /// Load the prompt template
+    pub fn load(&self) -> Result<Prompt> {
         match self {
-            PromptTemplate::Engineer => Ok(ENGINEER_PROMPT.to_string()),
-            PromptTemplate::Architect => Ok(ARCHITECT_PROMPT.to_string()),
-            PromptTemplate::Custom(path) => fs::read_to_string(path)
-                .with_context(|| format!("Failed to read prompt file: {}", path)),
+            PromptTemplate::Engineer => Ok(Prompt::builtin("engineer", ENGINEER_PROMPT)),
+            PromptTemplate::Architect => Ok(Prompt::builtin("architect", ARCHITECT_PROMPT)),
+            PromptTemplate::Custom(path) => Prompt::load(Path::new(path), PromptSource::File),
+            PromptTemplate::Named(prompt) => Ok(prompt.as_ref().clone()),
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_and_render_prompt() {
+        let prompt = Prompt::parse(
+            "review",
+            PromptSource::Project,
+            None,
+            "+++\ndescription = \"Review\"\nmodel = \"claude-3-5-sonnet\"\ntoken_budget = 100\n+++\nReview {{ language }} in:\n{{files}}\n{{instructions}}\n",
+        )
+        .unwrap();
+        assert_eq!(prompt.metadata.description.as_deref(), Some("Review"));
+        assert_eq!(prompt.metadata.token_budget, Some(100));
+        assert_eq!(prompt.preferred_format().as_deref(), Some("xml"));
+        assert_eq!(
+            prompt.variables(),
+            vec!["language", "files", "instructions"]
+        );
+        assert!(!prompt.uses_variable("diff"));
+
+        let rendered = prompt.render(&PromptVariables {
+            instructions: "Be brief".to_string(),
+            files: "src/lib.rs:1-5".to_string(),
+            language: "rust".to_string(),
+            diff: String::new(),
+        });
+        assert_eq!(rendered, "Review rust in:\nsrc/lib.rs:1-5\nBe brief\n");
+
+        // Without front matter the whole content is the template
+        let plain = Prompt::parse("plain", PromptSource::File, None, "Just text").unwrap();
+        assert_eq!(plain.metadata, PromptMetadata::default());
+        assert_eq!(plain.body, "Just text");
+        assert_eq!(plain.preferred_format(), None);
+
+        assert!(Prompt::parse("bad", PromptSource::File, None, "Use {{unknown}}").is_err());
+        assert!(Prompt::parse("bad", PromptSource::File, None, "+++\nmodel = 1\n+++\n").is_err());
+        assert!(Prompt::parse(
+            "bad",
+            PromptSource::File,
+            None,
+            "+++\nformat = \"pdf\"\n+++\n"
+        )
+        .is_err());
+        assert!(Prompt::parse("bad", PromptSource::File, None, "+++\nmodel = \"x\"\n").is_err());
+    }
+}
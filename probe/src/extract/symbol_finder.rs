@@ -448,7 +448,7 @@ pub fn find_symbol_in_file(
             .unwrap_or_default();
         let node_text_str = node_text.to_string();
         let tokenized_content =
-            crate::ranking::preprocess_text_with_filename(&node_text_str, &filename);
+            crate::ranking::preprocess_text_with_filename(&node_text_str, &filename, None);
 
         return Ok(SearchResult {
             file: path.to_string_lossy().to_string(),
@@ -474,6 +474,15 @@ pub fn find_symbol_in_file(
             block_id: None,
             matched_keywords: None,
             tokenized_content: Some(tokenized_content),
+            workspace_root: None,
+            changed_since_seen: None,
+            explanation: None,
+            match_ranges: None,
+            metrics: None,
+            parent_block: None,
+            context_blocks: None,
+            symbol: Some(symbol.to_string()),
+            symbol_occurrence: None,
         });
     }
 
@@ -562,7 +571,8 @@ pub fn find_symbol_in_file(
             .file_name()
             .map(|f| f.to_string_lossy().to_string())
             .unwrap_or_default();
-        let tokenized_content = crate::ranking::preprocess_text_with_filename(&context, &filename);
+        let tokenized_content =
+            crate::ranking::preprocess_text_with_filename(&context, &filename, None);
 
         return Ok(SearchResult {
             file: path.to_string_lossy().to_string(),
@@ -588,6 +598,15 @@ pub fn find_symbol_in_file(
             block_id: None,
             matched_keywords: None,
             tokenized_content: Some(tokenized_content),
+            workspace_root: None,
+            changed_since_seen: None,
+            explanation: None,
+            match_ranges: None,
+            metrics: None,
+            parent_block: None,
+            context_blocks: None,
+            symbol: None,
+            symbol_occurrence: None,
         });
     }
 
@@ -0,0 +1,615 @@
+//! Search of a repository's commit history.
+//!
+//! `probe history` ranks the commit messages and diff hunks of a local git repository
+//! with the same elastic query syntax and BM25 ranking as `probe search`. Each matching
+//! hunk is reported with the symbol it changed, found by parsing the file as it was at
+//! that commit. The history is read with the `git` command, so nothing but the local
+//! `.git` directory is needed.
+
+use anyhow::{Context, Result};
+use colored::*;
+use serde::Serialize;
+use std::collections::HashSet;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::time::Instant;
+
+use crate::language::parser::{
+    block_definition_node, node_symbol_name, parse_file_for_code_blocks, parse_source,
+};
+use crate::ranking::{self, RankingParams};
+use crate::search::file_processing::filter_tokenized_block;
+use crate::search::query::create_query_plan_for_project;
+use crate::search::search_tokens::count_tokens;
+use crate::search::{is_structured_format, json_line, project_vocabulary, tokenization};
+
+/// Separates the commits in the `git log` output
+const COMMIT_SEPARATOR: char = '\x1e';
+/// Separates the fields of a commit header in the `git log` output
+const FIELD_SEPARATOR: char = '\x1f';
+
+/// Options for a history search
+pub struct HistoryOptions<'a> {
+    /// Repository, directory or file whose history is searched
+    pub path: &'a Path,
+    pub query: &'a str,
+    pub max_results: usize,
+    /// Number of most recent commits to search; all commits if None
+    pub max_commits: Option<usize>,
+    /// Only search commits more recent than this date (any date `git log --since` accepts)
+    pub since: Option<&'a str>,
+}
+
+/// What part of a commit a history result matched
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum HistoryMatch {
+    /// The commit message
+    Message,
+    /// A hunk of the commit's diff
+    Hunk,
+}
+
+/// A commit message or diff hunk matching a history search
+#[derive(Debug, Clone, Serialize)]
+pub struct HistoryResult {
+    pub commit: String,
+    pub author: String,
+    /// Author date in ISO 8601 format
+    pub date: String,
+    /// First line of the commit message
+    pub summary: String,
+    #[serde(rename = "match")]
+    pub kind: HistoryMatch,
+    /// File the hunk changed, relative to the repository root
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub file: Option<String>,
+    /// First and last changed line of the hunk, in the file after the commit or, for
+    /// hunks that only remove lines, before it
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub lines: Option<(usize, usize)>,
+    /// Name of the symbol enclosing the changed lines
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub symbol: Option<String>,
+    /// Node type of the code block enclosing the changed lines
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub node_type: Option<String>,
+    /// The commit message, or the hunk in unified diff format
+    pub code: String,
+    pub score: f64,
+}
+
+/// A commit read from `git log`
+#[derive(Debug)]
+struct Commit {
+    id: String,
+    author: String,
+    date: String,
+    message: String,
+    hunks: Vec<Hunk>,
+}
+
+/// A hunk of a commit's diff
+#[derive(Debug, Default)]
+struct Hunk {
+    /// Path before the commit; None for added files
+    old_path: Option<String>,
+    /// Path after the commit; None for deleted files
+    new_path: Option<String>,
+    /// The hunk's lines, without the `@@` header
+    text: String,
+    /// Line numbers of added lines in the file after the commit
+    added: Vec<usize>,
+    /// Line numbers of removed lines in the file before the commit
+    removed: Vec<usize>,
+}
+
+impl Hunk {
+    fn path(&self) -> &str {
+        self.new_path
+            .as_deref()
+            .or(self.old_path.as_deref())
+            .unwrap_or_default()
+    }
+
+    /// The changed lines, without their `+` and `-` markers
+    fn changed_text(&self) -> String {
+        self.text
+            .lines()
+            .filter(|line| line.starts_with('+') || line.starts_with('-'))
+            .map(|line| &line[1..])
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
+
+/// The error of a git command that could not be started
+fn spawn_error(e: std::io::Error) -> anyhow::Error {
+    if e.kind() == ErrorKind::NotFound {
+        anyhow::anyhow!("probe history requires a git executable, and none was found in PATH")
+    } else {
+        anyhow::anyhow!("Failed to run git: {}", e)
+    }
+}
+
+/// Fail when git can't be run, before its errors are mistaken for a missing repository
+fn require_git() -> Result<()> {
+    Command::new("git")
+        .arg("--version")
+        .output()
+        .map_err(spawn_error)?;
+    Ok(())
+}
+
+/// Run git in `dir` and return its standard output
+fn git(dir: &Path, args: &[&str]) -> Result<String> {
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(dir)
+        .args(args)
+        .output()
+        .map_err(spawn_error)?;
+
+    if !output.status.success() {
+        anyhow::bail!(
+            "git {} failed: {}",
+            args.first().unwrap_or(&""),
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// The directory to run git in and the pathspec limiting the history to `path`
+fn git_location(path: &Path) -> Result<(PathBuf, String)> {
+    if path.is_file() {
+        let dir = match path.parent() {
+            Some(parent) if !parent.as_os_str().is_empty() => parent.to_path_buf(),
+            _ => PathBuf::from("."),
+        };
+        let file = path
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        Ok((dir, file))
+    } else if path.is_dir() {
+        Ok((path.to_path_buf(), ".".to_string()))
+    } else {
+        anyhow::bail!("Path does not exist: {}", path.display())
+    }
+}
+
+/// Strip the `a/` or `b/` prefix from a path of a `---` or `+++` diff line
+fn diff_path(path: &str, prefix: &str) -> Option<String> {
+    // Paths containing spaces are followed by a tab
+    let path = path.trim_end_matches('\t');
+    if path == "/dev/null" {
+        return None;
+    }
+    Some(path.strip_prefix(prefix).unwrap_or(path).to_string())
+}
+
+/// Parse the start line of the old and new file from a `@@ -a,b +c,d @@` hunk header
+fn hunk_start(header: &str) -> Option<(usize, usize)> {
+    let mut ranges = header.trim_start_matches('@').split_whitespace();
+    let start = |range: Option<&str>, marker: char| -> Option<usize> {
+        range?.strip_prefix(marker)?.split(',').next()?.parse().ok()
+    };
+    Some((start(ranges.next(), '-')?, start(ranges.next(), '+')?))
+}
+
+/// Parse the diff of a commit into hunks
+fn parse_diff(diff: &str) -> Vec<Hunk> {
+    let mut hunks: Vec<Hunk> = Vec::new();
+    let mut old_path = None;
+    let mut new_path = None;
+    let mut in_header = false;
+    let mut old_line = 0;
+    let mut new_line = 0;
+
+    for line in diff.lines() {
+        if line.starts_with("diff --git ") {
+            in_header = true;
+            old_path = None;
+            new_path = None;
+        } else if in_header && line.starts_with("--- ") {
+            old_path = diff_path(&line[4..], "a/");
+        } else if in_header && line.starts_with("+++ ") {
+            new_path = diff_path(&line[4..], "b/");
+        } else if line.starts_with("@@") && (old_path.is_some() || new_path.is_some()) {
+            in_header = false;
+            if let Some((old_start, new_start)) = hunk_start(line) {
+                old_line = old_start;
+                new_line = new_start;
+                hunks.push(Hunk {
+                    old_path: old_path.clone(),
+                    new_path: new_path.clone(),
+                    ..Default::default()
+                });
+            }
+        } else if !in_header {
+            let Some(hunk) = hunks.last_mut() else {
+                continue;
+            };
+            match line.chars().next() {
+                Some('+') => {
+                    hunk.added.push(new_line);
+                    new_line += 1;
+                }
+                Some('-') => {
+                    hunk.removed.push(old_line);
+                    old_line += 1;
+                }
+                Some(' ') | None => {
+                    old_line += 1;
+                    new_line += 1;
+                }
+                // "\ No newline at end of file"
+                _ => continue,
+            }
+            hunk.text.push_str(line);
+            hunk.text.push('\n');
+        }
+    }
+
+    hunks
+}
+
+/// Parse the output of `git log` run with the format set by `read_commits`
+fn parse_log(log: &str) -> Vec<Commit> {
+    log.split(COMMIT_SEPARATOR)
+        .filter_map(|entry| {
+            let mut fields = entry.splitn(5, FIELD_SEPARATOR);
+            let id = fields.next()?.trim();
+            if id.is_empty() {
+                return None;
+            }
+            Some(Commit {
+                id: id.to_string(),
+                author: fields.next()?.to_string(),
+                date: fields.next()?.to_string(),
+                message: fields.next()?.trim().to_string(),
+                hunks: parse_diff(fields.next().unwrap_or_default()),
+            })
+        })
+        .collect()
+}
+
+/// Read the commits touching `pathspec`, most recent first, with their diffs
+fn read_commits(dir: &Path, pathspec: &str, options: &HistoryOptions) -> Result<Vec<Commit>> {
+    let format = format!(
+        "--format={c}%H{f}%an{f}%aI{f}%B{f}",
+        c = COMMIT_SEPARATOR,
+        f = FIELD_SEPARATOR
+    );
+    let max_count = options
+        .max_commits
+        .map(|max| format!("--max-count={}", max));
+    let since = options.since.map(|since| format!("--since={}", since));
+
+    // Prefixes are set explicitly so that diff settings in the user's config don't
+    // change the output that is parsed
+    let mut args = vec![
+        "-c",
+        "core.quotePath=false",
+        "log",
+        "-p",
+        "--no-color",
+        "--no-ext-diff",
+        "--no-textconv",
+        "--src-prefix=a/",
+        "--dst-prefix=b/",
+        &format,
+    ];
+    args.extend(max_count.as_deref());
+    args.extend(since.as_deref());
+    args.extend(["--", pathspec]);
+
+    Ok(parse_log(&git(dir, &args)?))
+}
+
+/// Find the symbol enclosing the changed lines of a hunk by parsing the file as it was
+/// after the commit or, for hunks that only remove lines, before it
+fn enclosing_symbol(dir: &Path, commit: &str, hunk: &Hunk) -> Option<(String, Option<String>)> {
+    let (revision, path, changed) = match &hunk.new_path {
+        Some(path) if !hunk.added.is_empty() => (commit.to_string(), path, &hunk.added),
+        _ => (
+            format!("{}^", commit),
+            hunk.old_path.as_ref()?,
+            &hunk.removed,
+        ),
+    };
+    let extension = Path::new(path).extension()?.to_str()?;
+    let content = git(dir, &["show", &format!("{}:{}", revision, path)]).ok()?;
+
+    // Blank lines often separate blocks, so the first changed line with code decides
+    let file_lines: Vec<&str> = content.lines().collect();
+    let line = changed
+        .iter()
+        .copied()
+        .find(|&line| {
+            file_lines
+                .get(line.wrapping_sub(1))
+                .is_some_and(|text| !text.trim().is_empty())
+        })
+        .or_else(|| changed.first().copied())?;
+
+    let line_numbers: HashSet<usize> = [line].into_iter().collect();
+    let blocks = parse_file_for_code_blocks(&content, extension, &line_numbers, true, None).ok()?;
+    let block = blocks
+        .iter()
+        .find(|block| block.start_row < line && line <= block.end_row + 1)?;
+
+    let tree = parse_source(&content, extension).ok()?;
+    let symbol = block_definition_node(&tree, extension, block.start_row, block.end_row)
+        .and_then(|node| node_symbol_name(node, content.as_bytes()));
+    Some((block.node_type.clone(), symbol))
+}
+
+/// Search the commit messages and diff hunks of a repository
+pub fn search_history(options: &HistoryOptions) -> Result<Vec<HistoryResult>> {
+    let (dir, pathspec) = git_location(options.path)?;
+    require_git()?;
+    git(&dir, &["rev-parse", "--git-dir"])
+        .with_context(|| format!("Not a git repository: {}", options.path.display()))?;
+    let vocabulary = project_vocabulary::load_for_paths(&[&dir])?;
+
+    let plan = create_query_plan_for_project(options.query, false, vocabulary)
+        .map_err(|e| anyhow::anyhow!("Invalid query '{}': {:?}", options.query, e))?;
+    let debug_mode = std::env::var("DEBUG").unwrap_or_default() == "1";
+
+    let commits = read_commits(&dir, &pathspec, options)?;
+
+    // Every commit message and every hunk is a document, kept if it satisfies the query
+    // the way search filters code blocks; hunks are matched on their changed lines and
+    // file path
+    let mut documents: Vec<(usize, Option<usize>)> = Vec::new();
+    let mut texts: Vec<String> = Vec::new();
+    let mut tokens: Vec<Vec<String>> = Vec::new();
+    let mut add_document = |document, text: String, document_tokens: Vec<String>| {
+        if filter_tokenized_block(&document_tokens, &plan.term_indices, &plan, debug_mode) {
+            documents.push(document);
+            texts.push(text);
+            tokens.push(document_tokens);
+        }
+    };
+    for (commit_index, commit) in commits.iter().enumerate() {
+        add_document(
+            (commit_index, None),
+            commit.message.clone(),
+            tokenization::tokenize_for_project(&commit.message, plan.project()),
+        );
+
+        for (hunk_index, hunk) in commit.hunks.iter().enumerate() {
+            let changed = hunk.changed_text();
+            let hunk_tokens =
+                ranking::preprocess_text_with_filename(&changed, hunk.path(), plan.project());
+            add_document((commit_index, Some(hunk_index)), changed, hunk_tokens);
+        }
+    }
+
+    let text_refs: Vec<&str> = texts.iter().map(String::as_str).collect();
+    let ranked = ranking::rank_documents(&RankingParams {
+        documents: &text_refs,
+        query: options.query,
+        pre_tokenized: Some(&tokens),
+        fuzzy_terms: None,
+        vocabulary: plan.project(),
+    });
+
+    Ok(ranked
+        .into_iter()
+        .take(options.max_results)
+        .map(|(index, score)| {
+            let (commit_index, hunk_index) = documents[index];
+            let commit = &commits[commit_index];
+            let mut result = HistoryResult {
+                commit: commit.id.clone(),
+                author: commit.author.clone(),
+                date: commit.date.clone(),
+                summary: commit
+                    .message
+                    .lines()
+                    .next()
+                    .unwrap_or_default()
+                    .to_string(),
+                kind: HistoryMatch::Message,
+                file: None,
+                lines: None,
+                symbol: None,
+                node_type: None,
+                code: commit.message.clone(),
+                score,
+            };
+
+            if let Some(hunk) = hunk_index.map(|i| &commit.hunks[i]) {
+                let changed = if hunk.added.is_empty() {
+                    &hunk.removed
+                } else {
+                    &hunk.added
+                };
+                result.kind = HistoryMatch::Hunk;
+                result.file = Some(hunk.path().to_string());
+                result.lines = changed.first().zip(changed.last()).map(|(&s, &e)| (s, e));
+                result.code = hunk.text.clone();
+                if let Some((node_type, symbol)) = enclosing_symbol(&dir, &commit.id, hunk) {
+                    result.node_type = Some(node_type);
+                    result.symbol = symbol;
+                }
+            }
+            result
+        })
+        .collect())
+}
+
+/// Describe where a history result is: the commit and, for hunks, the file, lines and symbol
+fn result_location(result: &HistoryResult) -> String {
+    let short_id = &result.commit[..result.commit.len().min(12)];
+    let mut location = short_id.to_string();
+    if let Some(file) = &result.file {
+        location.push_str(&format!(" {}", file));
+        match result.lines {
+            Some((start, end)) if start == end => location.push_str(&format!(":{}", start)),
+            Some((start, end)) => location.push_str(&format!(":{}-{}", start, end)),
+            None => {}
+        }
+        if let Some(symbol) = &result.symbol {
+            location.push_str(&format!(" in {}", symbol));
+        }
+    }
+    location
+}
+
+/// Format and print history results
+pub fn format_and_print_history_results(results: &[HistoryResult], format: &str) -> Result<()> {
+    let total_bytes: usize = results.iter().map(|r| r.code.len()).sum();
+    let total_tokens: usize = results.iter().map(|r| count_tokens(&r.code)).sum();
+
+    match format {
+        "json" => {
+            let wrapper = serde_json::json!({
+                "results": results,
+                "summary": {
+                    "count": results.len(),
+                    "total_bytes": total_bytes,
+                    "total_tokens": total_tokens
+                }
+            });
+            println!("{}", serde_json::to_string_pretty(&wrapper)?);
+        }
+        "jsonl" => {
+            for result in results {
+                println!("{}", json_line("history", result)?);
+            }
+            let summary = serde_json::json!({
+                "count": results.len(),
+                "total_bytes": total_bytes,
+                "total_tokens": total_tokens
+            });
+            println!("{}", json_line("summary", &summary)?);
+        }
+        "markdown" => {
+            for result in results {
+                println!("**{}**", result_location(result));
+                println!();
+                println!("{} ({}, {})", result.summary, result.author, result.date);
+                println!();
+                let lang = if result.kind == HistoryMatch::Hunk {
+                    "diff"
+                } else {
+                    ""
+                };
+                println!("```{}", lang);
+                println!("{}", result.code.trim_end());
+                println!("```");
+                println!();
+            }
+        }
+        "plain" => {
+            for result in results {
+                println!("{}", result_location(result));
+                println!("{} ({}, {})", result.summary, result.author, result.date);
+                println!("{}", result.code.trim_end());
+                println!();
+            }
+        }
+        _ => {
+            for result in results {
+                println!("{}", result_location(result).cyan());
+                println!(
+                    "{} {}",
+                    result.summary.bold(),
+                    format!("({}, {})", result.author, result.date).dimmed()
+                );
+                for line in result.code.trim_end().lines() {
+                    if result.kind == HistoryMatch::Message {
+                        println!("{}", line);
+                    } else if line.starts_with('+') {
+                        println!("{}", line.green());
+                    } else if line.starts_with('-') {
+                        println!("{}", line.red());
+                    } else {
+                        println!("{}", line);
+                    }
+                }
+                println!();
+            }
+        }
+    }
+
+    if !is_structured_format(format) && !results.is_empty() {
+        println!("Total bytes returned: {}", total_bytes);
+        println!("Total tokens returned: {}", total_tokens);
+    }
+    Ok(())
+}
+
+/// Handle the history command
+pub fn handle_history(options: &HistoryOptions, format: &str) -> Result<()> {
+    let start_time = Instant::now();
+    let results = search_history(options)?;
+    let duration = start_time.elapsed();
+
+    if !is_structured_format(format) {
+        if results.is_empty() {
+            println!("{}", "No results found.".yellow().bold());
+            println!("Search completed in {:.2?}", duration);
+            return Ok(());
+        }
+        println!("Found {} results in {:.2?}", results.len(), duration);
+        println!();
+    }
+    format_and_print_history_results(&results, format)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_log() {
+        let log =
+            "\x1eabc123\x1fJane Doe\x1f2024-05-01T10:00:00+02:00\x1fFix parser\n\nDetails\n\x1f\n\
+                   diff --git a/src/lib.rs b/src/lib.rs\n\
+                   --- a/src/lib.rs\n\
+                   +++ b/src/lib.rs\n\
+                   @@ -3,3 +3,4 @@ fn parse() {\n \
+                   let a = 1;\n\
+                   --- old comment\n\
+                   +++ new comment\n\
+                   +let b = 2;\n \
+                   a\n\
+                   diff --git a/old.txt b/old.txt\n\
+                   deleted file mode 100644\n\
+                   --- a/old.txt\n\
+                   +++ /dev/null\n\
+                   @@ -1 +0,0 @@\n\
+                   -gone\n\
+                   \\ No newline at end of file\n";
+
+        let commits = parse_log(log);
+        assert_eq!(commits.len(), 1);
+        let commit = &commits[0];
+        assert_eq!(commit.id, "abc123");
+        assert_eq!(commit.author, "Jane Doe");
+        assert_eq!(commit.message, "Fix parser\n\nDetails");
+        assert_eq!(commit.hunks.len(), 2);
+
+        // Changed lines that look like file headers belong to the hunk
+        let hunk = &commit.hunks[0];
+        assert_eq!(hunk.path(), "src/lib.rs");
+        assert_eq!(hunk.removed, vec![4]);
+        assert_eq!(hunk.added, vec![4, 5]);
+        assert_eq!(
+            hunk.changed_text(),
+            "-- old comment\n++ new comment\nlet b = 2;"
+        );
+
+        let deleted = &commit.hunks[1];
+        assert_eq!(deleted.new_path, None);
+        assert_eq!(deleted.path(), "old.txt");
+        assert_eq!(deleted.removed, vec![1]);
+        assert_eq!(deleted.text, "-gone\n");
+    }
+}
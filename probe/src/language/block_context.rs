@@ -0,0 +1,604 @@
+//! Context of code blocks: the import section of their file and the definitions they
+//! reference, so that an extracted function can be read without opening its file.
+//!
+//! Definitions are the top-level types, constants and functions of a file (and those
+//! of namespaces). A name is looked up in the block's own file first, then in the
+//! other files of its directory, and then, when the file imports the name, in the
+//! rest of the repository. Node kinds differ between grammars, so the kinds below are
+//! the union of the kinds the supported languages use.
+
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use tree_sitter::Node;
+
+use crate::language::parser::{node_symbol_name, parse_source};
+use crate::models::{ContextBlock, ContextKind, SearchResult};
+use crate::search::file_list_cache;
+use crate::search::search_tokens::count_tokens;
+use crate::search::source_files::read_source_file;
+use probe::workspace::Workspace;
+
+/// Import statements; a file's import section spans the first to the last of them
+const IMPORT_KINDS: &[&str] = &[
+    "use_declaration",
+    "extern_crate_declaration",
+    "import_statement",
+    "import_from_statement",
+    "future_import_statement",
+    "import_declaration",
+    "package_clause",
+    "package_declaration",
+    "preproc_include",
+    "using_directive",
+    "namespace_use_declaration",
+];
+
+/// Type definitions
+const TYPE_KINDS: &[&str] = &[
+    "struct_item",
+    "enum_item",
+    "union_item",
+    "trait_item",
+    "type_item",
+    "class_definition",
+    "class_declaration",
+    "class_specifier",
+    "interface_declaration",
+    "trait_declaration",
+    "enum_declaration",
+    "struct_declaration",
+    "record_declaration",
+    "protocol_declaration",
+    "type_alias_declaration",
+    "type_declaration",
+    "type_definition",
+    "struct_specifier",
+    "enum_specifier",
+    "class",
+    "module",
+];
+
+/// Constant definitions; JavaScript `const` and Python assignments are told apart below
+const CONSTANT_KINDS: &[&str] = &[
+    "const_item",
+    "static_item",
+    "const_declaration",
+    "preproc_def",
+];
+
+/// Function definitions
+const FUNCTION_KINDS: &[&str] = &[
+    "function_item",
+    "function_definition",
+    "function_declaration",
+    "generator_function_declaration",
+    "method_declaration",
+    "method",
+];
+
+/// Blocks whose body holds more definitions
+const NAMESPACE_KINDS: &[&str] = &["namespace_declaration", "namespace_definition", "mod_item"];
+
+/// Identifiers a block can reference a definition with; member names, like the field of
+/// `self.items`, are left out
+const REFERENCE_KINDS: &[&str] = &["identifier", "type_identifier", "constant", "name"];
+
+/// A definition of a file
+#[derive(Debug, Clone)]
+struct Definition {
+    kind: ContextKind,
+    names: Vec<String>,
+    /// First and last line, 1-based
+    lines: (usize, usize),
+    code: String,
+}
+
+/// The import section and definitions of a parsed file
+#[derive(Debug, Default)]
+struct FileOutline {
+    imports: Option<Definition>,
+    /// Names the import statements mention
+    imported_names: HashSet<String>,
+    definitions: Vec<Definition>,
+    /// Identifier nodes, as (row, name), to find the references of a block
+    identifiers: Vec<(usize, String)>,
+}
+
+fn node_text<'a>(node: Node<'_>, source: &'a [u8]) -> &'a str {
+    node.utf8_text(source).unwrap_or("")
+}
+
+/// The identifier a declarator declares, e.g. `parse` in C's `int parse(char *s)`
+fn declarator_name(mut node: Node<'_>, source: &[u8]) -> Option<String> {
+    loop {
+        if REFERENCE_KINDS.contains(&node.kind()) || node.kind() == "field_identifier" {
+            return Some(node_text(node, source).to_string());
+        }
+        node = node
+            .child_by_field_name("declarator")
+            .or_else(|| node.child_by_field_name("name"))?;
+    }
+}
+
+/// Classify a top-level node and find the names it defines
+fn classify(node: Node<'_>, source: &[u8]) -> Option<(ContextKind, Vec<String>)> {
+    let kind = node.kind();
+    let name = |node: Node<'_>| {
+        node.child_by_field_name("name")
+            .map(|name| node_text(name, source).to_string())
+    };
+    // Go declares several types or constants in one block
+    let spec_names = |node: Node<'_>| -> Vec<String> {
+        let mut cursor = node.walk();
+        let names = node
+            .named_children(&mut cursor)
+            .filter_map(name)
+            .collect::<Vec<_>>();
+        names
+    };
+
+    if TYPE_KINDS.contains(&kind) {
+        let names = match kind {
+            "type_declaration" => spec_names(node),
+            "type_definition" => node
+                .child_by_field_name("declarator")
+                .and_then(|d| declarator_name(d, source))
+                .into_iter()
+                .collect(),
+            _ => name(node).into_iter().collect(),
+        };
+        return Some((ContextKind::Type, names));
+    }
+    if CONSTANT_KINDS.contains(&kind) {
+        let names = if kind == "const_declaration" {
+            spec_names(node)
+        } else {
+            name(node).into_iter().collect()
+        };
+        return Some((ContextKind::Constant, names));
+    }
+    if FUNCTION_KINDS.contains(&kind) {
+        let names = match node.child_by_field_name("declarator") {
+            Some(declarator) => declarator_name(declarator, source).into_iter().collect(),
+            None => name(node).into_iter().collect(),
+        };
+        return Some((ContextKind::Function, names));
+    }
+
+    match kind {
+        // const LIMIT = 10; const helper = () => ...
+        "lexical_declaration" if node_text(node, source).starts_with("const") => {
+            let mut cursor = node.walk();
+            let declarators: Vec<Node> = node
+                .named_children(&mut cursor)
+                .filter(|child| child.kind() == "variable_declarator")
+                .collect();
+            let is_function = declarators.iter().any(|declarator| {
+                declarator
+                    .child_by_field_name("value")
+                    .is_some_and(|value| {
+                        matches!(
+                            value.kind(),
+                            "arrow_function" | "function_expression" | "function"
+                        )
+                    })
+            });
+            let names = declarators.into_iter().filter_map(name).collect();
+            let kind = if is_function {
+                ContextKind::Function
+            } else {
+                ContextKind::Constant
+            };
+            Some((kind, names))
+        }
+        // MAX_RETRIES = 3
+        "expression_statement" => {
+            let assignment = node.named_child(0).filter(|n| n.kind() == "assignment")?;
+            let left = assignment.child_by_field_name("left")?;
+            let text = node_text(left, source);
+            let is_constant = left.kind() == "identifier"
+                && text.chars().any(|c| c.is_ascii_uppercase())
+                && !text.chars().any(|c| c.is_ascii_lowercase());
+            is_constant.then(|| (ContextKind::Constant, vec![text.to_string()]))
+        }
+        _ => None,
+    }
+}
+
+/// Collect the definitions among the children of a node, descending into namespaces
+///
+/// Only the imports of the top level make up the import section: a namespace's own
+/// imports, like the `use super::*;` of a Rust `mod tests`, would stretch it over the
+/// code in between. Their names still count as imported.
+fn collect_definitions(node: Node<'_>, source: &[u8], outline: &mut FileOutline, top_level: bool) {
+    let mut cursor = node.walk();
+    for child in node.named_children(&mut cursor) {
+        if IMPORT_KINDS.contains(&child.kind()) {
+            if top_level {
+                let lines = (child.start_position().row + 1, child.end_position().row + 1);
+                let imports = outline.imports.get_or_insert(Definition {
+                    kind: ContextKind::Imports,
+                    names: Vec::new(),
+                    lines,
+                    code: String::new(),
+                });
+                imports.lines = (imports.lines.0.min(lines.0), imports.lines.1.max(lines.1));
+            }
+            collect_identifiers(child, source, &mut |_, name| {
+                outline.imported_names.insert(name.to_string());
+            });
+            continue;
+        }
+
+        if NAMESPACE_KINDS.contains(&child.kind()) {
+            if let Some(body) = child.child_by_field_name("body") {
+                collect_definitions(body, source, outline, false);
+            }
+            continue;
+        }
+
+        // Decorators and `export` wrap the definition they apply to
+        let definition = match child.kind() {
+            "decorated_definition" => child.child_by_field_name("definition"),
+            "export_statement" => child.child_by_field_name("declaration"),
+            _ => Some(child),
+        };
+        let Some((kind, mut names)) = definition.and_then(|d| classify(d, source)) else {
+            continue;
+        };
+        if names.is_empty() {
+            names.extend(node_symbol_name(child, source));
+        }
+        if names.is_empty() {
+            continue;
+        }
+        outline.definitions.push(Definition {
+            kind,
+            names,
+            lines: (child.start_position().row + 1, child.end_position().row + 1),
+            code: node_text(child, source).to_string(),
+        });
+    }
+}
+
+/// Call `visit` with the row and text of every identifier under a node
+fn collect_identifiers(node: Node<'_>, source: &[u8], visit: &mut dyn FnMut(usize, &str)) {
+    if REFERENCE_KINDS.contains(&node.kind()) {
+        visit(node.start_position().row, node_text(node, source));
+        return;
+    }
+    let mut cursor = node.walk();
+    for child in node.named_children(&mut cursor) {
+        collect_identifiers(child, source, visit);
+    }
+}
+
+/// Parse a file and outline it; None when its language is not supported
+fn outline_file(path: &Path) -> Option<FileOutline> {
+    let extension = path.extension().and_then(|e| e.to_str()).unwrap_or("");
+    let content = read_source_file(path).ok()?;
+    let tree = parse_source(&content, extension).ok()?;
+    let source = content.as_bytes();
+
+    let mut outline = FileOutline::default();
+    collect_definitions(tree.root_node(), source, &mut outline, true);
+    let lines: Vec<&str> = content.lines().collect();
+    if let Some(imports) = outline.imports.as_mut() {
+        let (start, end) = imports.lines;
+        imports.code = lines[start - 1..end.min(lines.len())].join("\n");
+    }
+    let mut identifiers = Vec::new();
+    collect_identifiers(tree.root_node(), source, &mut |row, name| {
+        identifiers.push((row, name.to_string()));
+    });
+    outline.identifiers = identifiers;
+    Some(outline)
+}
+
+/// The root of the repository a file is in: the closest directory above it with a
+/// `.git` entry, or else its own directory
+fn repository_root(path: &Path) -> PathBuf {
+    let dir = path.parent().unwrap_or(Path::new("."));
+    dir.ancestors()
+        .find(|ancestor| ancestor.join(".git").exists())
+        .unwrap_or(dir)
+        .to_path_buf()
+}
+
+/// Outlines of parsed files, and of the files of a repository with a given extension
+#[derive(Default)]
+struct OutlineCache {
+    outlines: HashMap<PathBuf, Option<FileOutline>>,
+    repository_files: HashMap<(PathBuf, String), Vec<PathBuf>>,
+    contents: HashMap<PathBuf, Option<String>>,
+}
+
+impl OutlineCache {
+    fn outline(&mut self, path: &Path) -> Option<&FileOutline> {
+        self.outlines
+            .entry(path.to_path_buf())
+            .or_insert_with(|| outline_file(path))
+            .as_ref()
+    }
+
+    /// Files of the repository of `path` with the same extension, the files of its
+    /// directory first
+    fn candidate_files(&mut self, path: &Path) -> Vec<PathBuf> {
+        let root = repository_root(path);
+        let extension = path
+            .extension()
+            .and_then(|e| e.to_str())
+            .unwrap_or("")
+            .to_string();
+        let files = self
+            .repository_files
+            .entry((root.clone(), extension.clone()))
+            .or_insert_with(|| {
+                let Ok(list) = file_list_cache::get_file_list(&root, false, &[]) else {
+                    return Vec::new();
+                };
+                let mut files: Vec<PathBuf> = list
+                    .files
+                    .iter()
+                    .filter(|file| file.extension().and_then(|e| e.to_str()) == Some(&extension))
+                    .cloned()
+                    .collect();
+                files.sort();
+                files
+            });
+        let dir = path.parent();
+        let (mut siblings, others): (Vec<PathBuf>, Vec<PathBuf>) = files
+            .iter()
+            .filter(|file| file.as_path() != path)
+            .cloned()
+            .partition(|file| file.parent() == dir);
+        siblings.extend(others);
+        siblings
+    }
+
+    /// Whether a file contains a name, to parse only the files that may define it
+    fn mentions(&mut self, path: &Path, name: &str) -> bool {
+        self.contents
+            .entry(path.to_path_buf())
+            .or_insert_with(|| read_source_file(path).ok())
+            .as_deref()
+            .is_some_and(|content| content.contains(name))
+    }
+}
+
+/// How a context block's file is shown: relative to the workspace root of its result
+/// when it has one, like `SearchResult::file`
+fn display_file(path: &Path, root_path: Option<&Path>) -> String {
+    root_path
+        .and_then(|root| path.strip_prefix(root).ok())
+        .unwrap_or(path)
+        .to_string_lossy()
+        .to_string()
+}
+
+/// Find the context of one result, in the order it is attached: the import section,
+/// then the definitions of the file, then those of other files, each in the order the
+/// block first references them
+fn find_context(
+    cache: &mut OutlineCache,
+    path: &Path,
+    lines: (usize, usize),
+) -> Vec<(ContextBlock, PathBuf)> {
+    let Some(outline) = cache.outline(path) else {
+        return Vec::new();
+    };
+    let mut found = Vec::new();
+    let context_block = |definition: &Definition, name: Option<&String>| ContextBlock {
+        kind: definition.kind,
+        name: name.cloned(),
+        file: String::new(),
+        lines: definition.lines,
+        code: definition.code.clone(),
+    };
+
+    let overlaps =
+        |definition: &Definition| definition.lines.0 <= lines.1 && lines.0 <= definition.lines.1;
+
+    // The result already shows imports it overlaps, or they are interleaved with its code
+    if let Some(imports) = outline
+        .imports
+        .as_ref()
+        .filter(|imports| !overlaps(imports))
+    {
+        found.push((context_block(imports, None), path.to_path_buf()));
+    }
+
+    // Names referenced by the block, in order
+    let mut referenced: Vec<&String> = Vec::new();
+    let mut seen = HashSet::new();
+    for (row, name) in &outline.identifiers {
+        if *row + 1 >= lines.0 && *row < lines.1 && seen.insert(name) {
+            referenced.push(name);
+        }
+    }
+
+    let mut unresolved = Vec::new();
+    let mut attached_lines = HashSet::new();
+    for name in referenced {
+        let local = outline
+            .definitions
+            .iter()
+            .find(|definition| definition.names.contains(name));
+        match local {
+            Some(definition) if overlaps(definition) => {}
+            Some(definition) => {
+                if attached_lines.insert(definition.lines) {
+                    found.push((context_block(definition, Some(name)), path.to_path_buf()));
+                }
+            }
+            None => unresolved.push((name.clone(), outline.imported_names.contains(name))),
+        }
+    }
+
+    // Other files of the directory define names without importing them, as Go packages
+    // do; the rest of the repository is only searched for imported names
+    let dir = path.parent().map(Path::to_path_buf);
+    for (name, imported) in unresolved {
+        for file in cache.candidate_files(path) {
+            if !imported && file.parent() != dir.as_deref() {
+                break;
+            }
+            if !cache.mentions(&file, &name) {
+                continue;
+            }
+            let definition = cache.outline(&file).and_then(|outline| {
+                outline
+                    .definitions
+                    .iter()
+                    .find(|definition| definition.names.contains(&name))
+                    .cloned()
+            });
+            if let Some(definition) = definition {
+                found.push((context_block(&definition, Some(&name)), file));
+                break;
+            }
+        }
+    }
+    found
+}
+
+/// Attach the context of every result whose file can be parsed, within `token_budget`
+/// tokens per result: blocks that don't fit in what is left of the budget are skipped
+///
+/// Results labelled with a workspace root have paths relative to that root of `workspace`.
+pub fn add_block_context(
+    results: &mut [SearchResult],
+    workspace: Option<&Workspace>,
+    token_budget: usize,
+) {
+    let debug_mode = std::env::var("DEBUG").unwrap_or_default() == "1";
+    let mut cache = OutlineCache::default();
+
+    for result in results.iter_mut() {
+        if result.node_type == "file" {
+            continue;
+        }
+        let root_path = match (workspace, &result.workspace_root) {
+            (Some(workspace), Some(root)) => workspace.root(root).map(|root| root.path.clone()),
+            // Relative paths are shown relative to the current directory
+            _ if Path::new(&result.file).is_relative() => std::env::current_dir().ok(),
+            _ => None,
+        };
+        let path = match &root_path {
+            Some(root) => root.join(&result.file),
+            None => PathBuf::from(&result.file),
+        };
+        // Absolute, so that the repository root can be found above the file
+        let path = std::path::absolute(&path).unwrap_or(path);
+        let root_path = root_path.map(|root| std::path::absolute(&root).unwrap_or(root));
+
+        let mut remaining = token_budget;
+        let mut blocks = Vec::new();
+        for (mut block, file) in find_context(&mut cache, &path, result.lines) {
+            let tokens = count_tokens(&block.code);
+            if tokens > remaining {
+                if debug_mode {
+                    println!(
+                        "DEBUG: Context block {:?} {:?} of {} tokens does not fit in {} tokens",
+                        block.kind, block.name, tokens, remaining
+                    );
+                }
+                continue;
+            }
+            remaining -= tokens;
+            block.file = if file == path {
+                result.file.clone()
+            } else {
+                display_file(&file, root_path.as_deref())
+            };
+            blocks.push(block);
+        }
+        result.context_blocks = Some(blocks);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn outline(content: &str, extension: &str) -> FileOutline {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(format!("source.{}", extension));
+        std::fs::write(&path, content).unwrap();
+        outline_file(&path).unwrap()
+    }
+
+    fn definitions(outline: &FileOutline) -> Vec<(ContextKind, String)> {
+        outline
+            .definitions
+            .iter()
+            .flat_map(|d| d.names.iter().map(|name| (d.kind, name.clone())))
+            .collect()
+    }
+
+    #[test]
+    fn test_rust_and_go_outlines() {
+        let rust = outline(
+            "use std::fmt;\nuse crate::models::Cart;\n\nconst LIMIT: usize = 3;\n\nstruct Item;\n\nmod inner {\n    pub fn helper() {}\n}\n\nfn total() {}\n",
+            "rs",
+        );
+        assert_eq!(rust.imports.as_ref().unwrap().lines, (1, 2));
+        assert!(rust.imported_names.contains("Cart"));
+        assert_eq!(
+            definitions(&rust),
+            [
+                (ContextKind::Constant, "LIMIT".to_string()),
+                (ContextKind::Type, "Item".to_string()),
+                (ContextKind::Function, "helper".to_string()),
+                (ContextKind::Function, "total".to_string()),
+            ]
+        );
+
+        let go = outline(
+            "package cart\n\nimport \"fmt\"\n\nconst (\n\tLimit = 3\n\tStep = 1\n)\n\ntype Item struct{}\n\nfunc Total() {}\n",
+            "go",
+        );
+        assert_eq!(go.imports.as_ref().unwrap().lines, (1, 3));
+        assert_eq!(
+            definitions(&go),
+            [
+                (ContextKind::Constant, "Limit".to_string()),
+                (ContextKind::Constant, "Step".to_string()),
+                (ContextKind::Type, "Item".to_string()),
+                (ContextKind::Function, "Total".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_python_and_javascript_outlines() {
+        let python = outline(
+            "import os\nfrom cart import Cart\n\nMAX_ITEMS = 10\nlabel = 'x'\n\n@dataclass\nclass Item:\n    pass\n\ndef total():\n    pass\n",
+            "py",
+        );
+        assert_eq!(python.imports.as_ref().unwrap().lines, (1, 2));
+        assert_eq!(
+            definitions(&python),
+            [
+                (ContextKind::Constant, "MAX_ITEMS".to_string()),
+                (ContextKind::Type, "Item".to_string()),
+                (ContextKind::Function, "total".to_string()),
+            ]
+        );
+
+        let javascript = outline(
+            "import { Cart } from './cart';\n\nexport const LIMIT = 3;\nconst helper = () => 1;\n\nexport class Item {}\n\nfunction total() {}\n",
+            "js",
+        );
+        assert!(javascript.imported_names.contains("Cart"));
+        assert_eq!(
+            definitions(&javascript),
+            [
+                (ContextKind::Constant, "LIMIT".to_string()),
+                (ContextKind::Function, "helper".to_string()),
+                (ContextKind::Type, "Item".to_string()),
+                (ContextKind::Function, "total".to_string()),
+            ]
+        );
+    }
+}
@@ -0,0 +1,346 @@
+//! Size and complexity metrics of code blocks, computed from the tree-sitter tree.
+//!
+//! Node kinds differ between grammars, so the kinds below are the union of the kinds the
+//! supported languages use for branches, loops and `switch`/`match` cases.
+
+use std::collections::{HashMap, HashSet};
+use std::path::PathBuf;
+use tree_sitter::{Node, Tree};
+
+use crate::language::parser::parse_source;
+use crate::models::{CodeMetrics, SearchResult};
+use probe::workspace::Workspace;
+
+/// Nodes that add a path through the code, each adding one to the cyclomatic complexity
+const BRANCH_KINDS: &[&str] = &[
+    "if_statement",
+    "if_expression",
+    "if",
+    "unless",
+    "elif_clause",
+    "elsif",
+    "if_modifier",
+    "unless_modifier",
+    "for_statement",
+    "for_expression",
+    "for_in_statement",
+    "enhanced_for_statement",
+    "foreach_statement",
+    "for",
+    "while_statement",
+    "while_expression",
+    "while",
+    "until",
+    "while_modifier",
+    "until_modifier",
+    "do_statement",
+    "repeat_while_statement",
+    "guard_statement",
+    "catch_clause",
+    "except_clause",
+    "rescue",
+    "conditional_expression",
+    "ternary_expression",
+    "conditional",
+];
+
+/// Cases of `switch` and `match` statements, each adding one unless it's the default case
+const CASE_KINDS: &[&str] = &[
+    "match_arm",
+    "case_clause",
+    "switch_case",
+    "switch_section",
+    "switch_block_statement_group",
+    "switch_rule",
+    "switch_entry",
+    "case_statement",
+    "expression_case",
+    "type_case",
+    "communication_case",
+    "when",
+];
+
+/// Binary expressions, which add one to the complexity when they are `&&` or `||`
+const BINARY_KINDS: &[&str] = &["binary_expression", "boolean_operator", "binary"];
+
+const LOGICAL_OPERATORS: &[&str] = &["&&", "||", "and", "or"];
+
+/// Control flow statements that nest the code inside them
+const NESTING_KINDS: &[&str] = &[
+    "if_statement",
+    "if_expression",
+    "if",
+    "unless",
+    "for_statement",
+    "for_expression",
+    "for_in_statement",
+    "enhanced_for_statement",
+    "foreach_statement",
+    "for",
+    "while_statement",
+    "while_expression",
+    "while",
+    "until",
+    "do_statement",
+    "repeat_while_statement",
+    "loop_expression",
+    "match_expression",
+    "match_statement",
+    "switch_statement",
+    "switch_expression",
+    "expression_switch_statement",
+    "type_switch_statement",
+    "select_statement",
+    "case",
+    "try_statement",
+    "try_expression",
+    "begin",
+];
+
+/// Metrics being collected while walking a block
+#[derive(Default)]
+struct MetricsState {
+    complexity: usize,
+    max_nesting: usize,
+    code_rows: HashSet<usize>,
+}
+
+/// Whether an `if` is the `else if` branch of another `if`, which is not nested deeper
+fn is_else_if(node: Node) -> bool {
+    let Some(parent) = node.parent() else {
+        return false;
+    };
+    parent.kind() == "else_clause"
+        || (NESTING_KINDS.contains(&parent.kind())
+            && parent.child_by_field_name("alternative") == Some(node))
+}
+
+/// Whether a case of a `switch` or `match` is the default case
+fn is_default_case(node: Node, source: &[u8]) -> bool {
+    if node.kind() == "match_arm" {
+        return node
+            .child_by_field_name("pattern")
+            .and_then(|pattern| pattern.utf8_text(source).ok())
+            .is_some_and(|pattern| pattern.trim() == "_");
+    }
+    node.utf8_text(source)
+        .is_ok_and(|text| text.trim_start().starts_with("default"))
+}
+
+fn is_logical_operation(node: Node) -> bool {
+    let mut cursor = node.walk();
+    let is_logical = node
+        .children(&mut cursor)
+        .any(|child| !child.is_named() && LOGICAL_OPERATORS.contains(&child.kind()));
+    is_logical
+}
+
+fn visit(
+    node: Node,
+    source: &[u8],
+    rows: (usize, usize),
+    nesting: usize,
+    state: &mut MetricsState,
+) {
+    let (start, end) = (node.start_position().row, node.end_position().row);
+    if end < rows.0 || start > rows.1 || node.kind().contains("comment") {
+        return;
+    }
+
+    // Nodes partly outside the block only contain the block; they don't count themselves
+    let mut nesting = nesting;
+    if rows.0 <= start && end <= rows.1 && node.is_named() {
+        let kind = node.kind();
+        if BRANCH_KINDS.contains(&kind)
+            || (CASE_KINDS.contains(&kind) && !is_default_case(node, source))
+            || (BINARY_KINDS.contains(&kind) && is_logical_operation(node))
+        {
+            state.complexity += 1;
+        }
+        if NESTING_KINDS.contains(&kind) && !is_else_if(node) {
+            nesting += 1;
+            state.max_nesting = state.max_nesting.max(nesting);
+        }
+    }
+
+    if node.child_count() == 0 {
+        state.code_rows.extend(start.max(rows.0)..=end.min(rows.1));
+        return;
+    }
+    let mut cursor = node.walk();
+    for child in node.children(&mut cursor) {
+        visit(child, source, rows, nesting, state);
+    }
+}
+
+/// The parameter list of a function, looked for in its signature but not in its body
+fn find_parameters(node: Node, depth: usize) -> Option<Node> {
+    if let Some(parameters) = node
+        .child_by_field_name("parameters")
+        .or_else(|| node.child_by_field_name("parameter"))
+    {
+        return Some(parameters);
+    }
+    if depth == 0 {
+        return None;
+    }
+    let body = node.child_by_field_name("body");
+    let mut cursor = node.walk();
+    let children: Vec<Node> = node.named_children(&mut cursor).collect();
+    children
+        .into_iter()
+        .filter(|child| Some(*child) != body)
+        .find_map(|child| find_parameters(child, depth - 1))
+}
+
+fn count_parameters(parameters: Node) -> usize {
+    // A single parameter without parentheses, as in `x => x + 1`
+    if parameters.named_child_count() == 0 || parameters.kind() == "identifier" {
+        return usize::from(parameters.kind() == "identifier");
+    }
+    let mut cursor = parameters.walk();
+    let children: Vec<Node> = parameters.named_children(&mut cursor).collect();
+    children
+        .into_iter()
+        .filter(|child| !child.kind().contains("comment"))
+        .map(|child| {
+            // Go declares several parameters of one type together, as in `a, b int`
+            let mut cursor = child.walk();
+            child
+                .children_by_field_name("name", &mut cursor)
+                .count()
+                .max(1)
+        })
+        .sum()
+}
+
+/// The parameter count of the function a block starts with, if it starts with one
+fn block_parameters(tree: &Tree, rows: (usize, usize)) -> Option<usize> {
+    let mut node = tree.root_node();
+    // Descend to the first node that starts inside the block and is not a comment or an
+    // attribute of the definition
+    loop {
+        let mut cursor = node.walk();
+        let next = node.named_children(&mut cursor).find(|child| {
+            child.end_position().row >= rows.0
+                && child.start_position().row <= rows.1
+                && !child.kind().contains("comment")
+                && !child.kind().contains("attribute")
+                && child.kind() != "decorator"
+        })?;
+        node = next;
+        if node.start_position().row >= rows.0 {
+            break;
+        }
+    }
+    if node.end_position().row > rows.1 {
+        return None;
+    }
+    find_parameters(node, 3).map(count_parameters)
+}
+
+/// Compute the metrics of a block of a parsed file, given by its first and last line
+/// (1-based)
+pub fn compute_block_metrics(tree: &Tree, source: &[u8], lines: (usize, usize)) -> CodeMetrics {
+    let rows = (lines.0.saturating_sub(1), lines.1.saturating_sub(1));
+    let mut state = MetricsState::default();
+    visit(tree.root_node(), source, rows, 0, &mut state);
+
+    CodeMetrics {
+        lines: lines.1 + 1 - lines.0.min(lines.1),
+        code_lines: state.code_rows.len(),
+        cyclomatic_complexity: state.complexity + 1,
+        max_nesting: state.max_nesting,
+        parameters: block_parameters(tree, rows),
+    }
+}
+
+/// Set the metrics of every result whose file can be parsed; each file is parsed once
+///
+/// Results labelled with a workspace root have paths relative to that root of `workspace`.
+pub fn add_block_metrics(results: &mut [SearchResult], workspace: Option<&Workspace>) {
+    let mut by_file: HashMap<PathBuf, Vec<usize>> = HashMap::new();
+    for (index, result) in results.iter().enumerate() {
+        let path = match (workspace, &result.workspace_root) {
+            (Some(workspace), Some(root)) => match workspace.root(root) {
+                Some(root) => root.path.join(&result.file),
+                None => PathBuf::from(&result.file),
+            },
+            _ => PathBuf::from(&result.file),
+        };
+        by_file.entry(path).or_default().push(index);
+    }
+
+    for (path, indices) in by_file {
+        let extension = path.extension().and_then(|e| e.to_str()).unwrap_or("");
+        let Ok(content) = std::fs::read_to_string(&path) else {
+            continue;
+        };
+        let Ok(tree) = parse_source(&content, extension) else {
+            continue;
+        };
+        for index in indices {
+            let lines = results[index].lines;
+            results[index].metrics = Some(compute_block_metrics(&tree, content.as_bytes(), lines));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn metrics(content: &str, extension: &str, lines: (usize, usize)) -> CodeMetrics {
+        let tree = parse_source(content, extension).unwrap();
+        compute_block_metrics(&tree, content.as_bytes(), lines)
+    }
+
+    #[test]
+    fn test_rust_function_metrics() {
+        let content = r#"struct Cart;
+
+/// Price of the items
+#[inline]
+fn price(items: &[u64], discount: u64, express: bool) -> u64 {
+    // Sum the items
+    let mut total = 0;
+    for item in items {
+        if *item > 100 && discount > 0 {
+            total += item - discount;
+        } else if *item > 0 {
+            total += item;
+        }
+    }
+
+    match express {
+        true => total + 10,
+        _ => total,
+    }
+}
+"#;
+        assert_eq!(
+            metrics(content, "rs", (3, 20)),
+            CodeMetrics {
+                lines: 18,
+                code_lines: 15,
+                // for, if, &&, else if, one non-default match arm
+                cyclomatic_complexity: 6,
+                max_nesting: 2,
+                parameters: Some(3),
+            }
+        );
+        assert_eq!(metrics(content, "rs", (1, 1)).parameters, None);
+    }
+
+    #[test]
+    fn test_python_and_go_parameters() {
+        let python = "def visit(self, node, depth=0):\n    while node:\n        if depth > 3 or not node.children:\n            return\n        node = node.parent\n";
+        let python_metrics = metrics(python, "py", (1, 5));
+        assert_eq!(python_metrics.parameters, Some(3));
+        assert_eq!(python_metrics.cyclomatic_complexity, 4);
+        assert_eq!(python_metrics.max_nesting, 2);
+
+        let go = "package main\n\nfunc add(a, b int, c string) int {\n\treturn a + b\n}\n";
+        assert_eq!(metrics(go, "go", (3, 5)).parameters, Some(3));
+    }
+}
@@ -0,0 +1,207 @@
+// Incremental reparsing for files that are edited repeatedly.
+//
+// Instead of discarding the old tree, the edits are applied with `Tree::edit` and the
+// new content is parsed with the edited tree as a starting point. The line map used by
+// `parser::parse_file_for_code_blocks` is then updated in place of being rebuilt, so
+// only rows touched by the edit are recomputed.
+
+use anyhow::{Context, Result};
+use std::collections::BTreeSet;
+use std::path::Path;
+use tree_sitter::{InputEdit, Parser as TSParser, Point, Range};
+
+use crate::language::factory::get_language_impl;
+use crate::language::parser::update_line_map_incrementally;
+use crate::language::tree_cache;
+
+/// Result of an incremental reparse
+///
+/// The tree for the new content replaces the old one in the tree cache, where
+/// [`tree_cache::get_cached_tree`] finds it.
+#[derive(Debug)]
+pub struct IncrementalParse {
+    /// Ranges whose syntactic structure changed, in new content coordinates
+    pub changed_ranges: Vec<Range>,
+    /// Whether the old tree came from the tree cache (false means it had to be parsed)
+    pub reused_tree: bool,
+    /// Number of line map rows carried over from the old content
+    pub reused_lines: usize,
+    /// Number of line map rows rebuilt from the new tree
+    pub recomputed_lines: usize,
+}
+
+/// Get the tree-sitter position (row and byte column) of a byte offset
+pub fn point_at(content: &str, byte: usize) -> Point {
+    let prefix = &content.as_bytes()[..byte.min(content.len())];
+    let row = prefix.iter().filter(|&&b| b == b'\n').count();
+    let column = match prefix.iter().rposition(|&b| b == b'\n') {
+        Some(newline) => prefix.len() - newline - 1,
+        None => prefix.len(),
+    };
+    Point { row, column }
+}
+
+/// Compute the single edit that turns `old_content` into `new_content`
+///
+/// The edit covers everything between the longest common prefix and the longest
+/// common suffix of the two contents. Returns None if the contents are identical.
+pub fn compute_edit(old_content: &str, new_content: &str) -> Option<InputEdit> {
+    if old_content == new_content {
+        return None;
+    }
+
+    let old_bytes = old_content.as_bytes();
+    let new_bytes = new_content.as_bytes();
+
+    let prefix = old_bytes
+        .iter()
+        .zip(new_bytes)
+        .take_while(|(a, b)| a == b)
+        .count();
+
+    // The suffix must not overlap the prefix in either content
+    let max_suffix = old_bytes.len().min(new_bytes.len()) - prefix;
+    let suffix = old_bytes
+        .iter()
+        .rev()
+        .zip(new_bytes.iter().rev())
+        .take(max_suffix)
+        .take_while(|(a, b)| a == b)
+        .count();
+
+    let start_byte = prefix;
+    let old_end_byte = old_bytes.len() - suffix;
+    let new_end_byte = new_bytes.len() - suffix;
+
+    Some(InputEdit {
+        start_byte,
+        old_end_byte,
+        new_end_byte,
+        start_position: point_at(old_content, start_byte),
+        old_end_position: point_at(old_content, old_end_byte),
+        new_end_position: point_at(new_content, new_end_byte),
+    })
+}
+
+/// Reparse a file after its content changed, reusing the cached tree and line map
+///
+/// The edit is derived from the difference between `old_content` and `new_content`.
+/// Use [`reparse_with_edits`] when the exact list of edits is known.
+///
+/// # Arguments
+///
+/// * `namespace` - The tree cache namespace (see [`tree_cache::namespace_for_file`])
+/// * `file_path` - The path of the file; its extension selects the language
+/// * `old_content` - The content the cached tree and line map were built from
+/// * `new_content` - The content after the change
+/// * `allow_tests` - Which line map variant to update
+pub fn reparse_incremental(
+    namespace: &str,
+    file_path: &str,
+    old_content: &str,
+    new_content: &str,
+    allow_tests: bool,
+) -> Result<IncrementalParse> {
+    let edits: Vec<InputEdit> = compute_edit(old_content, new_content).into_iter().collect();
+    reparse_with_edits(
+        namespace,
+        file_path,
+        old_content,
+        new_content,
+        &edits,
+        allow_tests,
+    )
+}
+
+/// Reparse a file after applying a list of edits to its old tree
+///
+/// Edits follow tree-sitter semantics: each one is expressed in the coordinates of
+/// the content produced by the edits before it. The new tree replaces the old one in
+/// the tree cache, and the line map for `new_content` is derived from the one cached
+/// for `old_content`.
+pub fn reparse_with_edits(
+    namespace: &str,
+    file_path: &str,
+    old_content: &str,
+    new_content: &str,
+    edits: &[InputEdit],
+    allow_tests: bool,
+) -> Result<IncrementalParse> {
+    let debug_mode = std::env::var("DEBUG").unwrap_or_default() == "1";
+
+    let extension = Path::new(file_path)
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .unwrap_or("");
+    let language_impl = get_language_impl(extension)
+        .ok_or_else(|| anyhow::anyhow!("Unsupported file type: {}", extension))?;
+
+    let mut parser = TSParser::new();
+    parser.set_language(&language_impl.get_tree_sitter_language())?;
+
+    let old_hash = tree_cache::compute_content_hash(old_content);
+    let (old_tree, reused_tree) = match tree_cache::get_cached_tree(namespace, file_path, old_hash)
+    {
+        Some(tree) => (tree, true),
+        None => {
+            if debug_mode {
+                println!(
+                    "[DEBUG] No cached tree for {}, parsing old content first",
+                    file_path
+                );
+            }
+            let tree = parser
+                .parse(old_content, None)
+                .context(format!("Failed to parse file: {}", file_path))?;
+            (tree, false)
+        }
+    };
+
+    let mut edited_tree = old_tree;
+    for edit in edits {
+        edited_tree.edit(edit);
+    }
+
+    let new_tree = parser
+        .parse(new_content, Some(&edited_tree))
+        .context(format!("Failed to reparse file: {}", file_path))?;
+    let changed_ranges: Vec<Range> = edited_tree.changed_ranges(&new_tree).collect();
+
+    if debug_mode {
+        println!(
+            "[DEBUG] Incrementally reparsed {} ({} edits, {} changed ranges)",
+            file_path,
+            edits.len(),
+            changed_ranges.len()
+        );
+    }
+
+    tree_cache::store_tree(namespace, file_path, new_content, new_tree.clone());
+
+    // The line map is updated from the overall difference, which bounds every edit
+    let (reused_lines, recomputed_lines) = match compute_edit(old_content, new_content) {
+        Some(edit) => {
+            let changed_rows: BTreeSet<usize> = changed_ranges
+                .iter()
+                .flat_map(|range| range.start_point.row..=range.end_point.row)
+                .collect();
+            update_line_map_incrementally(
+                old_content,
+                new_content,
+                &new_tree,
+                &edit,
+                &changed_rows,
+                extension,
+                allow_tests,
+            )?
+        }
+        None => (new_content.lines().count(), 0),
+    };
+
+    Ok(IncrementalParse {
+        changed_ranges,
+        reused_tree,
+        reused_lines,
+        recomputed_lines,
+    })
+}
@@ -0,0 +1,294 @@
+use crate::language::incremental::{compute_edit, point_at, reparse_incremental};
+use crate::language::parser::{clear_line_map_cache, parse_file_for_code_blocks};
+use crate::language::tree_cache;
+use crate::models::CodeBlock;
+use std::collections::HashSet;
+
+/// Extract the blocks for every line individually, so the whole line map is compared
+fn blocks_per_line(content: &str, allow_tests: bool) -> Vec<Vec<CodeBlock>> {
+    (1..=content.lines().count())
+        .map(|line| {
+            let line_numbers: HashSet<usize> = [line].into_iter().collect();
+            parse_file_for_code_blocks(content, "rs", &line_numbers, allow_tests, None).unwrap()
+        })
+        .collect()
+}
+
+/// Reparse incrementally and check the updated line map against a full rebuild
+fn assert_incremental_matches_full(old_content: &str, new_content: &str) -> (usize, usize) {
+    // Other tests clear the tree cache, which would drop the tree we rely on
+    let _guard = tree_cache::acquire_test_mutex();
+    let file_path = format!(
+        "incremental_{:x}.rs",
+        tree_cache::compute_content_hash(old_content)
+    );
+
+    // Populate the tree cache and the line map for the old content
+    let mut parser = tree_sitter::Parser::new();
+    parser
+        .set_language(&tree_sitter_rust::LANGUAGE.into())
+        .unwrap();
+    tree_cache::get_or_parse_tree(&file_path, old_content, &mut parser).unwrap();
+    blocks_per_line(old_content, false);
+
+    let result = reparse_incremental(
+        &tree_cache::namespace_for_file(&file_path),
+        &file_path,
+        old_content,
+        new_content,
+        false,
+    )
+    .unwrap();
+    assert!(result.reused_tree, "The cached tree should be reused");
+    let new_tree = tree_cache::get_cached_tree(
+        &tree_cache::namespace_for_file(&file_path),
+        &file_path,
+        tree_cache::compute_content_hash(new_content),
+    )
+    .expect("The new tree should replace the old one in the cache");
+    assert_eq!(
+        new_tree.root_node().end_byte(),
+        new_content.len(),
+        "The new tree should cover the new content"
+    );
+
+    // The incremental line map is now cached for the new content
+    let incremental = blocks_per_line(new_content, false);
+
+    clear_line_map_cache();
+    let full = blocks_per_line(new_content, false);
+    assert_eq!(incremental, full);
+
+    tree_cache::invalidate_cache_entry(&file_path);
+    (result.reused_lines, result.recomputed_lines)
+}
+
+#[test]
+fn test_compute_edit() {
+    let edit = compute_edit("fn a() {}\nfn b() {}\n", "fn a() {}\nfn bc() {}\n").unwrap();
+    assert_eq!(edit.start_byte, 14);
+    assert_eq!(edit.old_end_byte, 14);
+    assert_eq!(edit.new_end_byte, 15);
+    assert_eq!(edit.start_position.row, 1);
+    assert_eq!(edit.start_position.column, 4);
+
+    // Repeated characters must not make the prefix and suffix overlap
+    let edit = compute_edit("aaa", "aaaa").unwrap();
+    assert_eq!(edit.start_byte, 3);
+    assert_eq!(edit.old_end_byte, 3);
+    assert_eq!(edit.new_end_byte, 4);
+
+    assert!(compute_edit("same", "same").is_none());
+}
+
+#[test]
+fn test_point_at() {
+    let content = "ab\ncd\n";
+    assert_eq!(
+        point_at(content, 0),
+        tree_sitter::Point { row: 0, column: 0 }
+    );
+    assert_eq!(
+        point_at(content, 4),
+        tree_sitter::Point { row: 1, column: 1 }
+    );
+    assert_eq!(
+        point_at(content, 6),
+        tree_sitter::Point { row: 2, column: 0 }
+    );
+}
+
+#[test]
+fn test_incremental_edit_inside_function() {
+    let old_content = r#"
+/// First function
+fn first() {
+    let a = 1;
+}
+
+struct Middle {
+    field: i32,
+}
+
+/// Last function
+fn last() {
+    let b = 2;
+}
+"#;
+    let new_content = r#"
+/// First function
+fn first() {
+    let a = 1;
+}
+
+struct Middle {
+    field: i32,
+    other: String,
+    third: bool,
+}
+
+/// Last function
+fn last() {
+    let b = 2;
+}
+"#;
+
+    let (reused, recomputed) = assert_incremental_matches_full(old_content, new_content);
+    assert!(reused > 0, "Rows outside the struct should be reused");
+    assert!(recomputed > 0);
+}
+
+#[test]
+fn test_incremental_edit_removing_lines() {
+    let old_content = r#"
+fn keep() {
+    let a = 1;
+}
+
+fn shrink() {
+    let x = 1;
+    let y = 2;
+    let z = 3;
+}
+
+fn after() {
+    let c = 3;
+}
+"#;
+    let new_content = r#"
+fn keep() {
+    let a = 1;
+}
+
+fn shrink() {
+    let x = 1;
+}
+
+fn after() {
+    let c = 3;
+}
+"#;
+
+    assert_incremental_matches_full(old_content, new_content);
+}
+
+#[test]
+fn test_incremental_edit_appending_after_trailing_comment() {
+    let old_content = r#"
+fn existing() {
+    let a = 1;
+}
+
+// Trailing comment
+"#;
+    let new_content = r#"
+fn existing() {
+    let a = 1;
+}
+
+// Trailing comment
+fn appended() {
+    let b = 2;
+}
+"#;
+
+    assert_incremental_matches_full(old_content, new_content);
+}
+
+#[test]
+fn test_incremental_edit_changing_structure() {
+    let old_content = r#"
+fn outer() {
+    let a = 1;
+}
+
+fn sibling() {
+    let b = 2;
+}
+"#;
+    // Wrapping the sibling in a module changes the structure after the edit
+    let new_content = r#"
+fn outer() {
+    let a = 1;
+}
+
+mod wrapper {
+fn sibling() {
+    let b = 2;
+}
+}
+"#;
+
+    assert_incremental_matches_full(old_content, new_content);
+}
+
+#[test]
+fn test_search_reuses_incrementally_reparsed_tree() {
+    use crate::search::file_processing::{process_file_with_results, FileProcessingParams};
+    use crate::search::query::create_query_plan;
+    use std::collections::HashMap;
+
+    let _guard = tree_cache::acquire_test_mutex();
+    let temp_dir = tempfile::TempDir::new().unwrap();
+    let path = temp_dir.path().join("checkout.rs");
+    let file_path = path.to_string_lossy().to_string();
+    let old_content = "fn checkout() {\n    let subtotal = 1;\n}\n";
+    let new_content = "fn checkout() {\n    let subtotal = 1;\n    let total = subtotal;\n}\n";
+
+    let search = |line: usize| {
+        let line_numbers: HashSet<usize> = [line].into_iter().collect();
+        let term_matches = HashMap::from([(0, line_numbers.clone())]);
+        let query_plan = create_query_plan("subtotal", false).unwrap();
+        let params = FileProcessingParams {
+            path: &path,
+            line_numbers: &line_numbers,
+            allow_tests: false,
+            term_matches: &term_matches,
+            num_queries: 1,
+            filename_matched_queries: HashSet::new(),
+            queries_terms: &[vec![("subtotal".to_string(), "subtotal".to_string())]],
+            preprocessed_queries: None,
+            query_plan: &query_plan,
+            no_merge: false,
+            batch: None,
+        };
+        process_file_with_results(&params).unwrap().0
+    };
+
+    // The search caches the tree under the same key the incremental reparse looks up
+    std::fs::write(&path, old_content).unwrap();
+    assert_eq!(search(2).len(), 1);
+    std::fs::write(&path, new_content).unwrap();
+    let result = reparse_incremental(
+        &tree_cache::namespace_for_file(&file_path),
+        &file_path,
+        old_content,
+        new_content,
+        false,
+    )
+    .unwrap();
+    assert!(
+        result.reused_tree,
+        "The tree cached by the search should be edited"
+    );
+    let cached_tree = || {
+        tree_cache::get_cached_tree(
+            &tree_cache::namespace_for_file(&file_path),
+            &file_path,
+            tree_cache::compute_content_hash(new_content),
+        )
+        .unwrap()
+    };
+    let reparsed = cached_tree();
+
+    // Searching the new content uses the edited tree instead of parsing again
+    clear_line_map_cache();
+    let results = search(3);
+    assert_eq!(results.len(), 1);
+    assert_eq!(results[0].lines, (1, 4));
+    // Copies of a tree share their nodes, a new parse would have allocated new ones
+    let first_node = |tree: &tree_sitter::Tree| tree.root_node().child(0).unwrap().id();
+    assert_eq!(first_node(&cached_tree()), first_node(&reparsed));
+
+    tree_cache::invalidate_cache_entry(&file_path);
+}
@@ -2,9 +2,12 @@
 // using tree-sitter and extracting code blocks.
 
 // Import submodules
+pub mod block_context;
 pub mod block_handling;
+pub mod block_metrics;
 pub mod common;
 pub mod factory;
+pub mod incremental;
 pub mod language_trait;
 pub mod parser;
 pub mod test_detection;
@@ -25,10 +28,14 @@ pub mod swift;
 pub mod typescript;
 
 // Re-export items for backward compatibility
-pub use parser::parse_file_for_code_blocks;
+#[allow(unused_imports)]
+pub use parser::{parse_file_for_code_blocks, parse_file_for_code_blocks_at};
 pub use test_detection::is_test_file;
 #[allow(unused_imports)]
-pub use tree_cache::{clear_tree_cache, get_cache_size, invalidate_cache_entry};
+pub use tree_cache::{
+    clear_tree_cache, get_cache_size, get_tree_cache_stats, invalidate_cache_entry,
+    TreeCacheConfig, TreeCacheStats,
+};
 
 #[cfg(test)]
 mod tests;
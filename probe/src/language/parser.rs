@@ -1,10 +1,9 @@
 use anyhow::{Context, Result};
 use dashmap::DashMap;
 use once_cell::sync::Lazy;
-use std::collections::hash_map::DefaultHasher;
-use std::collections::{HashMap, HashSet};
-use std::hash::{Hash, Hasher};
-use tree_sitter::{Node, Parser as TSParser};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeSet, HashMap, HashSet};
+use tree_sitter::{InputEdit, Node, Parser as TSParser, Tree};
 
 use crate::language::factory::get_language_impl;
 use crate::language::language_trait::LanguageImpl;
@@ -14,15 +13,17 @@ use crate::models::CodeBlock;
 // Define a static cache for line maps
 static LINE_MAP_CACHE: Lazy<DashMap<String, Vec<Option<CachedNodeInfo>>>> = Lazy::new(DashMap::new);
 
-/// Calculate a hash of the content for cache validation
-fn calculate_content_hash(content: &str) -> u64 {
-    let mut hasher = DefaultHasher::new();
-    content.hash(&mut hasher);
-    hasher.finish()
+/// Drop every in-memory line map
+///
+/// Persisted block boundaries on disk are left untouched, so this is also a way
+/// to simulate a cold start.
+#[cfg(test)]
+pub fn clear_line_map_cache() {
+    LINE_MAP_CACHE.clear();
 }
 
 /// A version of NodeInfo without lifetimes for caching
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 struct CachedNodeInfo {
     // Original node info
     start_byte: usize,
@@ -31,8 +32,7 @@ struct CachedNodeInfo {
     end_row: usize,
     node_kind: String,
     is_comment: bool,
-    is_test: bool,                     // Test status of the original node
-    original_node_is_acceptable: bool, // Was the original node an acceptable parent type?
+    is_test: bool, // Test status of the original node
     // Context node info (if any)
     context_node_bytes: Option<(usize, usize)>,
     context_node_rows: Option<(usize, usize)>,
@@ -64,16 +64,21 @@ impl CachedNodeInfo {
         let mut rep_node = info.node; // Default to self
                                       // let mut is_merged = false; // Removed unused variable
 
+        // process_cached_line_map uses the context node unless it is test code that isn't
+        // wanted, so with tests allowed the context is always used
+        let context_is_used =
+            |ctx: &Node<'_>| allow_tests || !language_impl.is_test_node(ctx, content);
+
         if info.is_comment {
             if let Some(ctx) = info.context_node {
-                if !allow_tests && !language_impl.is_test_node(&ctx, content) {
+                if context_is_used(&ctx) {
                     rep_node = ctx; // Context represents the merged block
                                     // is_merged = true; // Removed assignment to unused variable
                 }
             }
         } else if !info.is_test {
             if let Some(ctx) = info.context_node {
-                if !allow_tests && !language_impl.is_test_node(&ctx, content) {
+                if context_is_used(&ctx) {
                     rep_node = ctx; // Use context ancestor
                 }
             }
@@ -81,21 +86,7 @@ impl CachedNodeInfo {
         // If info.is_test is true, rep_node remains info.node
 
         // Get parent function info if applicable (e.g., for struct_type nodes)
-        let parent_info = if rep_node.kind() == "struct_type" {
-            language_impl
-                .find_parent_function(rep_node)
-                .map(|parent_node| {
-                    let parent_type = parent_node.kind().to_string();
-                    let parent_start = parent_node.start_position().row;
-                    let parent_end = parent_node.end_position().row;
-                    (parent_type, parent_start, parent_end)
-                })
-        } else {
-            None
-        };
-
-        // Check if original node is acceptable
-        let original_acceptable = language_impl.is_acceptable_parent(&info.node);
+        let parent_info = parent_block_info(rep_node, language_impl);
 
         // Check if context node is a test node
         let context_test = info
@@ -111,7 +102,6 @@ impl CachedNodeInfo {
             node_kind: info.node.kind().to_string(),
             is_comment: info.is_comment,
             is_test: info.is_test, // Original node test status
-            original_node_is_acceptable: original_acceptable,
             // Context node details
             context_node_bytes: info.context_node.map(|n| (n.start_byte(), n.end_byte())),
             context_node_rows: info
@@ -135,6 +125,52 @@ impl CachedNodeInfo {
     }
 }
 
+impl CachedNodeInfo {
+    /// Check whether every range recorded for this line ends before the given byte and row
+    fn ends_before(&self, byte: usize, row: usize) -> bool {
+        self.end_byte < byte
+            && self.context_node_bytes.is_none_or(|(_, end)| end < byte)
+            && self.parent_end_row.is_none_or(|end| end < row)
+    }
+
+    /// Check whether every range recorded for this line starts after the given byte and row
+    fn starts_after(&self, byte: usize, row: usize) -> bool {
+        self.start_byte > byte
+            && self
+                .context_node_bytes
+                .is_none_or(|(start, _)| start > byte)
+            && self.parent_start_row.is_none_or(|start| start > row)
+    }
+
+    /// Check whether a comment's context could change because of an edit elsewhere
+    ///
+    /// A comment without context, or one attached to its previous sibling, depends on
+    /// which siblings follow it, so it can't be reused across an edit.
+    fn has_unstable_comment_context(&self) -> bool {
+        self.is_comment
+            && self
+                .context_node_bytes
+                .is_none_or(|(_, end)| end <= self.start_byte)
+    }
+
+    /// Move every recorded position by the given byte and row offsets
+    fn shifted(&self, byte_delta: isize, row_delta: isize) -> Self {
+        let byte = |b: usize| (b as isize + byte_delta) as usize;
+        let row = |r: usize| (r as isize + row_delta) as usize;
+
+        let mut info = self.clone();
+        info.start_byte = byte(self.start_byte);
+        info.end_byte = byte(self.end_byte);
+        info.start_row = row(self.start_row);
+        info.end_row = row(self.end_row);
+        info.context_node_bytes = self.context_node_bytes.map(|(s, e)| (byte(s), byte(e)));
+        info.context_node_rows = self.context_node_rows.map(|(s, e)| (row(s), row(e)));
+        info.parent_start_row = self.parent_start_row.map(row);
+        info.parent_end_row = self.parent_end_row.map(row);
+        info
+    }
+}
+
 /// Structure to hold node information for a specific line
 #[derive(Clone, Copy)]
 struct NodeInfo<'a> {
@@ -217,6 +253,170 @@ fn find_acceptable_child<'a>(node: Node<'a>, language_impl: &dyn LanguageImpl) -
     None // No acceptable child found
 }
 
+/// The function a Go struct is declared in, as (node type, start row, end row)
+fn parent_block_info(
+    node: Node<'_>,
+    language_impl: &dyn LanguageImpl,
+) -> Option<(String, usize, usize)> {
+    if node.kind() != "struct_type" {
+        return None;
+    }
+    language_impl.find_parent_function(node).map(|parent_node| {
+        (
+            parent_node.kind().to_string(),
+            parent_node.start_position().row,
+            parent_node.end_position().row,
+        )
+    })
+}
+
+/// Parse the whole of a source file with the grammar of its extension
+pub fn parse_source(content: &str, extension: &str) -> Result<Tree> {
+    let language_impl = get_language_impl(extension)
+        .with_context(|| format!("Unsupported file type: {}", extension))?;
+    let mut parser = TSParser::new();
+    parser.set_language(&language_impl.get_tree_sitter_language())?;
+    parser
+        .parse(content, None)
+        .context("Failed to parse the file")
+}
+
+/// Fields that lead from a node to the name it defines, directly or through a wrapper:
+/// C declarators, `export`, decorators, and the type of a Rust `impl`
+const SYMBOL_NAME_FIELDS: &[&str] = &["name", "declarator", "declaration", "definition", "type"];
+
+/// Children that hold the name of a declaration which has no field for it, like Go type
+/// specs and JavaScript variable declarators
+const SYMBOL_DECLARATOR_KINDS: &[&str] =
+    &["type_spec", "const_spec", "var_spec", "variable_declarator"];
+
+/// The name of the symbol a definition node defines, e.g. "total" for `fn total()`
+pub fn node_symbol_name(node: Node<'_>, source: &[u8]) -> Option<String> {
+    if node.kind().ends_with("identifier") || matches!(node.kind(), "name" | "constant") {
+        return node.utf8_text(source).ok().map(String::from);
+    }
+    let inner = SYMBOL_NAME_FIELDS
+        .iter()
+        .find_map(|field| node.child_by_field_name(field))
+        .or_else(|| {
+            let mut cursor = node.walk();
+            let inner = node
+                .named_children(&mut cursor)
+                .find(|child| SYMBOL_DECLARATOR_KINDS.contains(&child.kind()));
+            inner
+        })?;
+    node_symbol_name(inner, source)
+}
+
+/// The outermost definition that lies within a block's rows (0-based, inclusive)
+pub fn block_definition_node<'a>(
+    tree: &'a Tree,
+    extension: &str,
+    start_row: usize,
+    end_row: usize,
+) -> Option<Node<'a>> {
+    fn find<'a>(
+        node: Node<'a>,
+        language_impl: &dyn LanguageImpl,
+        start_row: usize,
+        end_row: usize,
+    ) -> Option<Node<'a>> {
+        if node.start_position().row >= start_row
+            && node.end_position().row <= end_row
+            && language_impl.is_acceptable_parent(&node)
+        {
+            return Some(node);
+        }
+        let mut cursor = node.walk();
+        let children: Vec<Node<'a>> = node
+            .named_children(&mut cursor)
+            .filter(|child| {
+                child.start_position().row <= end_row && child.end_position().row >= start_row
+            })
+            .collect();
+        children
+            .into_iter()
+            .find_map(|child| find(child, language_impl, start_row, end_row))
+    }
+
+    let language_impl = get_language_impl(extension)?;
+    find(tree.root_node(), language_impl.as_ref(), start_row, end_row)
+}
+
+/// The block the definition within a block's rows (0-based, inclusive) is nested in,
+/// e.g. the `impl` of a method
+///
+/// Block merging uses this to keep siblings together and to show where a nested block
+/// lives.
+pub fn enclosing_block_node<'a>(
+    tree: &'a Tree,
+    extension: &str,
+    start_row: usize,
+    end_row: usize,
+) -> Option<Node<'a>> {
+    let language_impl = get_language_impl(extension)?;
+    let mut ancestor = block_definition_node(tree, extension, start_row, end_row)?.parent();
+    while let Some(candidate) = ancestor {
+        if language_impl.is_acceptable_parent(&candidate) {
+            return Some(candidate);
+        }
+        ancestor = candidate.parent();
+    }
+    None
+}
+
+/// The symbol path of a definition node: its name after the names of the definitions it
+/// is nested in, joined with dots like the symbols `extract` takes, e.g. "Cart.total"
+pub fn node_symbol_path(node: Node<'_>, source: &[u8], extension: &str) -> Option<String> {
+    let name = node_symbol_name(node, source)?;
+    let Some(language_impl) = get_language_impl(extension) else {
+        return Some(name);
+    };
+    let mut names = vec![name];
+    let mut parent = node.parent();
+    while let Some(ancestor) = parent {
+        if language_impl.is_acceptable_parent(&ancestor) {
+            names.extend(node_symbol_name(ancestor, source));
+        }
+        parent = ancestor.parent();
+    }
+    names.reverse();
+    Some(names.join("."))
+}
+
+/// The position of a definition among the definitions of its file that share its kind and
+/// symbol path, such as overloads, in source order; the first is 1
+pub fn symbol_occurrence(tree: &Tree, node: Node<'_>, source: &[u8], extension: &str) -> usize {
+    fn count_before(
+        current: Node<'_>,
+        node: Node<'_>,
+        name: &str,
+        path: &Option<String>,
+        source: &[u8],
+        extension: &str,
+    ) -> usize {
+        if current.start_byte() >= node.start_byte() {
+            return 0;
+        }
+        let mut count = usize::from(
+            current.kind() == node.kind()
+                && node_symbol_name(current, source).as_deref() == Some(name)
+                && node_symbol_path(current, source, extension) == *path,
+        );
+        let mut cursor = current.walk();
+        for child in current.named_children(&mut cursor) {
+            count += count_before(child, node, name, path, source, extension);
+        }
+        count
+    }
+
+    let Some(name) = node_symbol_name(node, source) else {
+        return 1;
+    };
+    let path = node_symbol_path(node, source, extension);
+    1 + count_before(tree.root_node(), node, &name, &path, source, extension)
+}
+
 /// Finds the immediate next node that follows a given node in the AST
 fn find_immediate_next_node(node: Node<'_>) -> Option<Node<'_>> {
     let debug_mode = std::env::var("DEBUG").unwrap_or_default() == "1";
@@ -421,6 +621,7 @@ fn process_node<'a>(
     allow_tests: bool,
     debug_mode: bool,
     current_ancestor: Option<Node<'a>>, // The nearest acceptable ancestor found so far
+    rows: Option<&BTreeSet<usize>>,     // Only update these rows (None means every row)
 ) {
     let start_row = node.start_position().row;
     let end_row = node.end_position().row;
@@ -430,6 +631,13 @@ fn process_node<'a>(
         return;
     }
 
+    // Skip subtrees that don't cover any of the requested rows
+    if let Some(rows) = rows {
+        if rows.range(start_row..=end_row).next().is_none() {
+            return;
+        }
+    }
+
     // Determine node type and test status
     let is_comment = node.kind() == "comment"
         || node.kind() == "line_comment"
@@ -464,6 +672,10 @@ fn process_node<'a>(
     // Ensure end_row does not exceed line_map bounds
     let effective_end_row = std::cmp::min(end_row, line_map.len().saturating_sub(1));
     for line in start_row..=effective_end_row {
+        if rows.is_some_and(|rows| !rows.contains(&line)) {
+            continue;
+        }
+
         // Determine if this node is a better fit for the line than the current entry
         let should_update =
             should_update_line_map(line_map, line, node, is_comment, context_node, specificity);
@@ -501,283 +713,220 @@ fn process_node<'a>(
             allow_tests,
             debug_mode,
             next_ancestor, // Pass the determined ancestor context down
+            rows,
         );
     }
 }
 
-/// Process a cached line map to extract code blocks
+/// Process a line map to extract the code blocks for the given lines
+///
+/// This is the only place blocks are built from a line map, whether it was just built
+/// from the tree, kept in memory or loaded from disk, so a cache hit returns exactly
+/// the blocks the cache miss returned.
 fn process_cached_line_map(
     cached_line_map: &[Option<CachedNodeInfo>],
     line_numbers: &HashSet<usize>,
-    _language_impl: &dyn LanguageImpl, // Not used directly, logic relies on cached info
-    _content: &str,                    // Not used directly, logic relies on cached info
     allow_tests: bool,
     debug_mode: bool,
 ) -> Result<Vec<CodeBlock>> {
     let mut code_blocks: Vec<CodeBlock> = Vec::new();
-    // Use a HashSet to track the start/end rows of blocks already added
-    let mut seen_block_spans: HashSet<(usize, usize)> = HashSet::new();
+    // Row spans of the nodes already processed
+    let mut seen_nodes: HashSet<(usize, usize)> = HashSet::new();
+
+    // Visit the lines in order, so that which of two overlapping nodes is kept
+    // doesn't depend on the iteration order of the set
+    let mut lines: Vec<usize> = line_numbers.iter().copied().collect();
+    lines.sort_unstable();
 
-    // Process each line number using the cached map
-    for &line in line_numbers {
+    for line in lines {
         let line_idx = line.saturating_sub(1); // Adjust for 0-based indexing
 
         if debug_mode {
-            println!("DEBUG: Processing line {} from cache", line);
+            println!("DEBUG: Processing line {}", line);
         }
 
         if line_idx >= cached_line_map.len() {
             if debug_mode {
-                println!("DEBUG: Line {} is out of bounds (Cache)", line);
+                println!("DEBUG: Line {} is out of bounds", line);
             }
             continue;
         }
 
-        if let Some(info) = &cached_line_map[line_idx] {
+        let Some(info) = &cached_line_map[line_idx] else {
+            if debug_mode {
+                println!("DEBUG: No node info found for line {}", line);
+            }
+            continue;
+        };
+
+        if debug_mode {
+            println!(
+                "DEBUG: Found node for line {}: type='{}', lines={}-{}, is_comment={}, is_test={}, context_kind={:?}, context_lines={:?}",
+                line,
+                info.node_kind,
+                info.start_row + 1,
+                info.end_row + 1,
+                info.is_comment,
+                info.is_test,
+                info.context_node_kind,
+                info.context_node_rows.map(|(s, e)| (s + 1, e + 1))
+            );
+        }
+
+        // Skip if we've already processed this node
+        if !seen_nodes.insert((info.start_row, info.end_row)) {
             if debug_mode {
                 println!(
-                    "DEBUG: Found cached node info for line {}: original_type='{}', original_lines={}-{}, is_comment={}, is_test={}, context_kind={:?}, context_lines={:?}",
-                    line,
-                    info.node_kind,
+                    "DEBUG: Already processed node at lines {}-{}, type: {}",
                     info.start_row + 1,
                     info.end_row + 1,
-                    info.is_comment,
-                    info.is_test,
-                    info.context_node_kind,
-                    info.context_node_rows.map(|(s, e)| (s + 1, e + 1))
+                    info.node_kind
                 );
             }
+            continue;
+        }
 
-            // Determine which block to potentially create based on cached info
-            let mut potential_block: Option<CodeBlock> = None;
-            let mut block_key: Option<(usize, usize)> = None; // Key for seen_block_spans
-
-            // --- Replicate Cache Miss Logic using CachedNodeInfo ---
-
-            // 1. Handle Comments
-            if info.is_comment {
-                if debug_mode {
-                    println!("DEBUG: Cache: Handling comment node at line {}", line);
-                }
-                // Check for context node
-                if let (Some(ctx_rows), Some(ctx_bytes), Some(ctx_kind), Some(ctx_is_test)) = (
-                    info.context_node_rows,
-                    info.context_node_bytes,
-                    &info.context_node_kind,
-                    info.context_node_is_test,
-                ) {
-                    // Check test status of the context node
-                    if !allow_tests && ctx_is_test {
-                        if debug_mode {
-                            println!(
-                                "DEBUG: Cache: Skipping test context node at lines {}-{}, type: {}",
-                                ctx_rows.0 + 1,
-                                ctx_rows.1 + 1,
-                                ctx_kind
-                            );
-                        }
-                        // Fall through to potentially add individual comment if context is skipped
-                    } else {
-                        // Create a merged block
-                        let merged_start_row = std::cmp::min(info.start_row, ctx_rows.0);
-                        let merged_end_row = std::cmp::max(info.end_row, ctx_rows.1);
-                        let merged_start_byte = std::cmp::min(info.start_byte, ctx_bytes.0);
-                        let merged_end_byte = std::cmp::max(info.end_byte, ctx_bytes.1);
-
-                        block_key = Some((merged_start_row, merged_end_row));
-                        if !seen_block_spans.contains(&block_key.unwrap()) {
-                            potential_block = Some(CodeBlock {
-                                start_row: merged_start_row,
-                                end_row: merged_end_row,
-                                start_byte: merged_start_byte,
-                                end_byte: merged_end_byte,
-                                node_type: ctx_kind.clone(), // Use context kind for merged block
-                                parent_node_type: None, // Consistent with original miss path logic
-                                parent_start_row: None,
-                                parent_end_row: None,
-                            });
-                            if debug_mode {
-                                println!(
-                                    "DEBUG: Cache: Potential merged block (comment + context) at lines {}-{}, type: {}",
-                                    merged_start_row + 1, merged_end_row + 1, ctx_kind
-                                );
-                            }
-                        }
-                        // If we created a merged block, we don't add the individual comment later
-                        // So we continue the outer loop here if the block_key was already seen or if we created a potential block
-                        if seen_block_spans.contains(&block_key.unwrap())
-                            || potential_block.is_some()
-                        {
-                            if seen_block_spans.contains(&block_key.unwrap()) && debug_mode {
-                                println!(
-                                    "DEBUG: Cache: Merged block span {}-{} already seen",
-                                    block_key.unwrap().0 + 1,
-                                    block_key.unwrap().1 + 1
-                                );
-                            }
-                            // Add the key even if block wasn't added, to prevent reprocessing context
-                            seen_block_spans.insert(block_key.unwrap());
-                            // Also mark original comment span as seen if merged
-                            seen_block_spans.insert((info.start_row, info.end_row));
-                            if let Some(block) = potential_block {
-                                code_blocks.push(block);
-                            }
-                            continue; // Move to next line number
-                        }
-                    }
-                }
-
-                // Add individual comment if not merged or if context was skipped
-                if potential_block.is_none() {
-                    block_key = Some((info.start_row, info.end_row));
-                    if !seen_block_spans.contains(&block_key.unwrap()) {
-                        potential_block = Some(CodeBlock {
-                            start_row: info.start_row,
-                            end_row: info.end_row,
-                            start_byte: info.start_byte,
-                            end_byte: info.end_byte,
-                            node_type: info.node_kind.clone(),
-                            parent_node_type: None,
-                            parent_start_row: None,
-                            parent_end_row: None,
-                        });
-                        if debug_mode {
-                            println!(
-                                "DEBUG: Cache: Potential individual comment block at lines {}-{}",
-                                info.start_row + 1,
-                                info.end_row + 1
-                            );
-                        }
-                    } else if debug_mode {
-                        println!(
-                            "DEBUG: Cache: Individual comment span {}-{} already seen",
-                            block_key.unwrap().0 + 1,
-                            block_key.unwrap().1 + 1
-                        );
-                    }
-                }
-            }
-            // 2. Handle Non-Comments
-            else {
-                // Skip original test nodes if not allowed
-                if !allow_tests && info.is_test {
+        // The context node, unless it is test code that isn't wanted
+        let context = match (
+            info.context_node_rows,
+            info.context_node_bytes,
+            &info.context_node_kind,
+        ) {
+            (Some(rows), Some(bytes), Some(kind)) => {
+                if !allow_tests && info.context_node_is_test.unwrap_or(false) {
                     if debug_mode {
                         println!(
-                            "DEBUG: Cache: Skipping original test node at lines {}-{}",
-                            info.start_row + 1,
-                            info.end_row + 1
+                            "DEBUG: Skipping test context node at lines {}-{}, type: {}",
+                            rows.0 + 1,
+                            rows.1 + 1,
+                            kind
                         );
                     }
-                    continue; // Move to next line number
+                    None
+                } else {
+                    Some((rows, bytes, kind))
                 }
+            }
+            _ => None,
+        };
 
-                // Check for context node (ancestor)
-                if let (Some(ctx_rows), Some(ctx_bytes), Some(ctx_kind), Some(ctx_is_test)) = (
-                    info.context_node_rows,
-                    info.context_node_bytes,
-                    &info.context_node_kind,
-                    info.context_node_is_test,
-                ) {
-                    // Check test status of the context node
-                    if !allow_tests && ctx_is_test {
-                        if debug_mode {
-                            println!(
-                                "DEBUG: Cache: Skipping test context node (ancestor) at lines {}-{}",
-                                ctx_rows.0 + 1, ctx_rows.1 + 1
-                            );
-                        }
-                        // Fall through to check original node if context is skipped
-                    } else {
-                        // Use context node
-                        block_key = Some((ctx_rows.0, ctx_rows.1));
-                        if !seen_block_spans.contains(&block_key.unwrap()) {
-                            potential_block = Some(CodeBlock {
-                                start_row: ctx_rows.0,
-                                end_row: ctx_rows.1,
-                                start_byte: ctx_bytes.0,
-                                end_byte: ctx_bytes.1,
-                                node_type: ctx_kind.clone(),
-                                // Parent info comes from CachedNodeInfo, which derived it based on the representative node (potentially the context)
-                                parent_node_type: info.parent_node_type.clone(),
-                                parent_start_row: info.parent_start_row,
-                                parent_end_row: info.parent_end_row,
-                            });
-                            if debug_mode {
-                                println!(
-                                    "DEBUG: Cache: Potential context node (ancestor) block at lines {}-{}",
-                                    ctx_rows.0 + 1, ctx_rows.1 + 1
-                                );
-                            }
-                        } else if debug_mode {
-                            println!(
-                                "DEBUG: Cache: Context node span {}-{} already seen",
-                                block_key.unwrap().0 + 1,
-                                block_key.unwrap().1 + 1
-                            );
-                        }
-                        // If we used the context node (or it was already seen), skip checking the original node
-                        if seen_block_spans.contains(&block_key.unwrap())
-                            || potential_block.is_some()
-                        {
-                            seen_block_spans.insert(block_key.unwrap()); // Mark context as seen
-                            if let Some(block) = potential_block {
-                                code_blocks.push(block);
-                            }
-                            continue; // Move to next line number
-                        }
-                    }
-                }
+        // Comments are merged with the code they document
+        if info.is_comment {
+            if let Some((ctx_rows, ctx_bytes, ctx_kind)) = context {
+                let merged_start_row = std::cmp::min(info.start_row, ctx_rows.0);
+                let merged_end_row = std::cmp::max(info.end_row, ctx_rows.1);
+                seen_nodes.insert(ctx_rows); // Mark context as seen too
 
-                // Check if original node itself is acceptable (and wasn't skipped as test)
-                // This check happens if there was no context node, or if the context node was skipped (e.g., test)
-                if potential_block.is_none() && info.original_node_is_acceptable {
-                    block_key = Some((info.start_row, info.end_row));
-                    if !seen_block_spans.contains(&block_key.unwrap()) {
-                        potential_block = Some(CodeBlock {
-                            start_row: info.start_row,
-                            end_row: info.end_row,
-                            start_byte: info.start_byte,
-                            end_byte: info.end_byte,
-                            node_type: info.node_kind.clone(),
-                            // Parent info comes from CachedNodeInfo, derived based on representative node (original node in this case)
-                            parent_node_type: info.parent_node_type.clone(),
-                            parent_start_row: info.parent_start_row,
-                            parent_end_row: info.parent_end_row,
-                        });
-                        if debug_mode {
-                            println!(
-                                "DEBUG: Cache: Potential acceptable original node block at lines {}-{}",
-                                info.start_row + 1, info.end_row + 1
-                            );
-                        }
-                    } else if debug_mode {
-                        println!(
-                            "DEBUG: Cache: Original acceptable node span {}-{} already seen",
-                            block_key.unwrap().0 + 1,
-                            block_key.unwrap().1 + 1
-                        );
-                    }
+                code_blocks.push(CodeBlock {
+                    start_row: merged_start_row,
+                    end_row: merged_end_row,
+                    start_byte: std::cmp::min(info.start_byte, ctx_bytes.0),
+                    end_byte: std::cmp::max(info.end_byte, ctx_bytes.1),
+                    node_type: ctx_kind.clone(),
+                    parent_node_type: info.parent_node_type.clone(),
+                    parent_start_row: info.parent_start_row,
+                    parent_end_row: info.parent_end_row,
+                });
+                if debug_mode {
+                    println!(
+                        "DEBUG: Added merged block (comment + context) at lines {}-{}, type: {}",
+                        merged_start_row + 1,
+                        merged_end_row + 1,
+                        ctx_kind
+                    );
+                }
+            } else {
+                code_blocks.push(CodeBlock {
+                    start_row: info.start_row,
+                    end_row: info.end_row,
+                    start_byte: info.start_byte,
+                    end_byte: info.end_byte,
+                    node_type: info.node_kind.clone(),
+                    parent_node_type: None,
+                    parent_start_row: None,
+                    parent_end_row: None,
+                });
+                if debug_mode {
+                    println!(
+                        "DEBUG: Added individual comment block at lines {}-{}",
+                        info.start_row + 1,
+                        info.end_row + 1
+                    );
                 }
             }
+            continue;
+        }
+
+        // Skip test nodes
+        if info.is_test {
+            if debug_mode {
+                println!(
+                    "DEBUG: Skipping test node at lines {}-{}",
+                    info.start_row + 1,
+                    info.end_row + 1
+                );
+            }
+            continue;
+        }
 
-            // Add the potential block if one was determined and not already seen
-            if let (Some(block), Some(key)) = (potential_block, block_key) {
-                if seen_block_spans.insert(key) {
-                    // Returns true if the value was not present
-                    code_blocks.push(block);
+        // Skip lines inside a block that was already added
+        if let Some(block) = code_blocks
+            .iter()
+            .find(|block| line > block.start_row + 1 && line <= block.end_row + 1)
+        {
+            if debug_mode {
+                println!(
+                    "DEBUG: Line {} is within existing block: type='{}', lines={}-{}",
+                    line,
+                    block.node_type,
+                    block.start_row + 1,
+                    block.end_row + 1
+                );
+            }
+            continue;
+        }
+
+        // Use the acceptable ancestor, or else the node itself
+        let block = match context {
+            Some((ctx_rows, ctx_bytes, ctx_kind)) => {
+                seen_nodes.insert(ctx_rows); // Mark context as seen
+                CodeBlock {
+                    start_row: ctx_rows.0,
+                    end_row: ctx_rows.1,
+                    start_byte: ctx_bytes.0,
+                    end_byte: ctx_bytes.1,
+                    node_type: ctx_kind.clone(),
+                    parent_node_type: info.parent_node_type.clone(),
+                    parent_start_row: info.parent_start_row,
+                    parent_end_row: info.parent_end_row,
                 }
             }
-        } else if debug_mode {
-            println!("DEBUG: Cache: No cached node info found for line {}", line);
+            None => CodeBlock {
+                start_row: info.start_row,
+                end_row: info.end_row,
+                start_byte: info.start_byte,
+                end_byte: info.end_byte,
+                node_type: info.node_kind.clone(),
+                parent_node_type: info.parent_node_type.clone(),
+                parent_start_row: info.parent_start_row,
+                parent_end_row: info.parent_end_row,
+            },
+        };
+        if debug_mode {
+            println!(
+                "DEBUG: Added block at lines {}-{}, type: {}",
+                block.start_row + 1,
+                block.end_row + 1,
+                block.node_type
+            );
         }
+        code_blocks.push(block);
     }
 
-    // Removed extra closing brace that was here
-
-    // Sort the blocks generated from the cache
+    // Sort the blocks generated from the line map
     code_blocks.sort_by_key(|block| block.start_row);
 
-    // --- Apply the exact same deduplication logic as the cache miss path ---
+    // Deduplicate overlapping blocks
     let mut final_code_blocks: Vec<CodeBlock> = Vec::new();
 
     // Add comments first
@@ -825,28 +974,26 @@ fn process_cached_line_map(
                 if block.start_row >= prev_block.start_row && block.end_row <= prev_block.end_row {
                     if debug_mode {
                         println!(
-                            "DEBUG: Cache Dedupe: Current block contained: type='{}', lines={}-{} (in type='{}', lines={}-{})",
+                            "DEBUG: Dedupe: Current block contained: type='{}', lines={}-{} (in type='{}', lines={}-{})",
                             block.node_type, block.start_row + 1, block.end_row + 1,
                             prev_block.node_type, prev_block.start_row + 1, prev_block.end_row + 1
                         );
                     }
                     if is_important && !prev_is_important {
                         if debug_mode {
-                            println!("DEBUG: Cache Dedupe: Keeping important contained block");
+                            println!("DEBUG: Dedupe: Keeping important contained block");
                         }
                         // Keep both - don't remove, don't skip add
                     } else if !is_important && prev_is_important {
                         if debug_mode {
-                            println!("DEBUG: Cache Dedupe: Skipping non-important contained block");
+                            println!("DEBUG: Dedupe: Skipping non-important contained block");
                         }
                         should_add = false;
                         break;
                     } else {
                         // Both important or both not - prefer contained (current)
                         if debug_mode {
-                            println!(
-                                "DEBUG: Cache Dedupe: Replacing outer block with contained block"
-                            );
+                            println!("DEBUG: Dedupe: Replacing outer block with contained block");
                         }
                         blocks_to_remove.push(idx);
                     }
@@ -857,26 +1004,28 @@ fn process_cached_line_map(
                 {
                     if debug_mode {
                         println!(
-                            "DEBUG: Cache Dedupe: Previous block contained: type='{}', lines={}-{} (contains type='{}', lines={}-{})",
+                            "DEBUG: Dedupe: Previous block contained: type='{}', lines={}-{} (contains type='{}', lines={}-{})",
                             block.node_type, block.start_row + 1, block.end_row + 1,
                             prev_block.node_type, prev_block.start_row + 1, prev_block.end_row + 1
                         );
                     }
                     if is_important && !prev_is_important {
                         if debug_mode {
-                            println!("DEBUG: Cache Dedupe: Keeping important outer block");
+                            println!("DEBUG: Dedupe: Keeping important outer block");
                         }
                         // Keep both - don't skip add, continue checking
                     } else if !is_important && prev_is_important {
                         if debug_mode {
-                            println!("DEBUG: Cache Dedupe: Skipping non-important outer block");
+                            println!("DEBUG: Dedupe: Skipping non-important outer block");
                         }
                         should_add = false;
                         break;
                     } else {
                         // Both important or both not - prefer contained (previous)
                         if debug_mode {
-                            println!("DEBUG: Cache Dedupe: Skipping outer block (already have contained)");
+                            println!(
+                                "DEBUG: Dedupe: Skipping outer block (already have contained)"
+                            );
                         }
                         should_add = false;
                         break;
@@ -886,7 +1035,7 @@ fn process_cached_line_map(
                 else {
                     if debug_mode {
                         println!(
-                            "DEBUG: Cache Dedupe: Partial overlap: type='{}', lines={}-{} (overlaps type='{}', lines={}-{})",
+                            "DEBUG: Dedupe: Partial overlap: type='{}', lines={}-{} (overlaps type='{}', lines={}-{})",
                             block.node_type, block.start_row + 1, block.end_row + 1,
                             prev_block.node_type, prev_block.start_row + 1, prev_block.end_row + 1
                         );
@@ -912,16 +1061,70 @@ fn process_cached_line_map(
     // Final sort to maintain correct order after deduplication
     final_code_blocks.sort_by_key(|block| block.start_row);
     Ok(final_code_blocks)
-} // Added missing closing brace for process_cached_line_map
-  // Removed unexpected closing brace that was here
+}
+
+/// Version of the `CachedNodeInfo` layout, bumped when the cached fields change meaning
+/// so that persisted line maps of an older layout are not reused
+const LINE_MAP_LAYOUT_VERSION: u32 = 4;
+
+/// Build the line map cache key for a piece of content
+fn line_map_cache_key(extension: &str, content: &str, allow_tests: bool) -> String {
+    let content_hash = tree_cache::compute_content_hash(content);
+    format!(
+        "{}_{}_{}_v{}",
+        extension, content_hash, allow_tests, LINE_MAP_LAYOUT_VERSION
+    )
+}
 
 /// Function to parse a file and extract code blocks for the given line numbers
+#[allow(dead_code)] // Only used by the library's history module
 pub fn parse_file_for_code_blocks(
     content: &str,
     extension: &str,
     line_numbers: &HashSet<usize>,
     allow_tests: bool,
-    _term_matches: Option<&HashMap<usize, HashSet<usize>>>, // Query index to line numbers
+    term_matches: Option<&HashMap<usize, HashSet<usize>>>, // Query index to line numbers
+) -> Result<Vec<CodeBlock>> {
+    parse_code_blocks(
+        None,
+        content,
+        extension,
+        line_numbers,
+        allow_tests,
+        term_matches,
+    )
+}
+
+/// Same as [`parse_file_for_code_blocks`], for the content of a file
+///
+/// The tree is cached under the file's path, the key searches, extraction and
+/// incremental reparsing share, so a tree edited by `reparse_with_edits` is reused here
+/// and a changed file is reparsed from its previous tree.
+pub fn parse_file_for_code_blocks_at(
+    file_path: &str,
+    content: &str,
+    extension: &str,
+    line_numbers: &HashSet<usize>,
+    allow_tests: bool,
+    term_matches: Option<&HashMap<usize, HashSet<usize>>>,
+) -> Result<Vec<CodeBlock>> {
+    parse_code_blocks(
+        Some(file_path),
+        content,
+        extension,
+        line_numbers,
+        allow_tests,
+        term_matches,
+    )
+}
+
+fn parse_code_blocks(
+    file_path: Option<&str>,
+    content: &str,
+    extension: &str,
+    line_numbers: &HashSet<usize>,
+    allow_tests: bool,
+    _term_matches: Option<&HashMap<usize, HashSet<usize>>>,
 ) -> Result<Vec<CodeBlock>> {
     // Get the appropriate language implementation
     let language_impl = match get_language_impl(extension) {
@@ -938,8 +1141,7 @@ pub fn parse_file_for_code_blocks(
     let debug_mode = std::env::var("DEBUG").unwrap_or_default() == "1";
 
     // Calculate content hash for cache key
-    let content_hash = calculate_content_hash(content);
-    let cache_key = format!("{}_{}_{}", extension, content_hash, allow_tests);
+    let cache_key = line_map_cache_key(extension, content, allow_tests);
 
     // Check if we have a cached line map
     if let Some(cached_entry) = LINE_MAP_CACHE.get(&cache_key) {
@@ -951,13 +1153,25 @@ pub fn parse_file_for_code_blocks(
         return process_cached_line_map(
             cached_entry.value(),
             line_numbers,
-            language_impl.as_ref(),
-            content,
             allow_tests,
             debug_mode,
         );
     }
 
+    // On a cold start, block boundaries may have been persisted by an earlier run
+    if let Some(persisted_line_map) =
+        tree_cache::load_persisted_blocks::<Vec<Option<CachedNodeInfo>>>(&cache_key, content)
+    {
+        if debug_mode {
+            println!("DEBUG: Persisted line_map found for key: {}", cache_key);
+        }
+
+        let code_blocks =
+            process_cached_line_map(&persisted_line_map, line_numbers, allow_tests, debug_mode);
+        LINE_MAP_CACHE.insert(cache_key, persisted_line_map);
+        return code_blocks;
+    }
+
     if debug_mode {
         println!(
             "DEBUG: Cache miss for line_map key: {}. Generating...",
@@ -973,10 +1187,18 @@ pub fn parse_file_for_code_blocks(
     parser.set_language(&language)?;
 
     // Use the tree cache to get or parse the tree
-    // We use a stable identifier for the file
-    let tree_cache_key = format!("file_{}", extension);
-    let tree = tree_cache::get_or_parse_tree(&tree_cache_key, content, &mut parser)
-        .context("Failed to parse the file")?;
+    // Content without a file is identified by its hash, so that the trees of different
+    // contents don't replace each other and later calls with the same content reuse them
+    let tree = match file_path {
+        Some(file_path) => tree_cache::get_or_parse_tree(file_path, content, &mut parser),
+        None => tree_cache::get_or_parse_tree_in_namespace(
+            tree_cache::DEFAULT_NAMESPACE,
+            &format!("file_{}", cache_key),
+            content,
+            &mut parser,
+        ),
+    }
+    .context("Failed to parse the file")?;
 
     let root_node = tree.root_node();
 
@@ -1019,490 +1241,193 @@ pub fn parse_file_for_code_blocks(
         allow_tests,
         debug_mode,
         None, // Initial ancestor context is None
-              // REMOVED: &mut ancestor_cache,
+        None, // Build the map for every row
     );
 
     if debug_mode {
         println!("DEBUG: Line-to-node map built successfully");
     }
 
-    // ====================================================================
-    // START: Inserted Original Block Processing Logic (Cache Miss Path)
-    // ====================================================================
-    // This code runs ONLY on a cache miss, after process_node generates the live line_map.
-    // It generates the CodeBlocks for *this specific request* from the live NodeInfo data.
-
-    let mut code_blocks: Vec<CodeBlock> = Vec::new();
-    let mut seen_nodes: HashSet<(usize, usize)> = HashSet::new(); // Use row-based key for this original logic
-
-    // Process each line number using the *live* precomputed map (line_map)
-    for &line in line_numbers {
-        // Adjust for 0-based indexing
-        let line_idx = line.saturating_sub(1);
+    // Convert the original line_map to a cacheable format with representative node info
+    let cacheable_line_map: Vec<Option<CachedNodeInfo>> = line_map
+        .iter()
+        .map(|opt_node_info| {
+            opt_node_info.map(|node_info| {
+                CachedNodeInfo::from_node_info(
+                    &node_info,
+                    language_impl.as_ref(),
+                    content.as_bytes(),
+                    allow_tests,
+                )
+            })
+        })
+        .collect();
 
+    // Persist the block boundaries so a later run can skip parsing this content
+    if let Err(e) = tree_cache::persist_blocks(&cache_key, content, &cacheable_line_map) {
         if debug_mode {
-            println!("DEBUG: Processing line {} (Live NodeInfo)", line);
+            println!("DEBUG: Failed to persist line_map for {}: {}", cache_key, e);
         }
+    }
 
-        // Skip if line is out of bounds
-        if line_idx >= line_map.len() {
-            if debug_mode {
-                println!("DEBUG: Line {} is out of bounds (Live NodeInfo)", line);
-            }
-            continue;
-        }
-
-        // Get the node info for this line from the live map
-        if let Some(info) = &line_map[line_idx] {
-            if debug_mode {
-                println!(
-                    "DEBUG: Found node for line {}: type='{}', lines={}-{}",
-                    line,
-                    info.node.kind(),
-                    info.node.start_position().row + 1,
-                    info.node.end_position().row + 1
-                );
-            }
-            let target_node = info.node;
-            let start_pos = target_node.start_position();
-            let end_pos = target_node.end_position();
-            // Use row key consistent with original logic for seen_nodes in this block
-            let node_key = (start_pos.row, end_pos.row);
-
-            // Skip if we've already processed this node
-            if seen_nodes.contains(&node_key) {
-                if debug_mode {
-                    println!(
-                        "DEBUG: Already processed node at lines {}-{}, type: {}",
-                        start_pos.row + 1,
-                        end_pos.row + 1,
-                        target_node.kind()
-                    );
-                }
-                continue;
-            }
-
-            // Mark this node as seen
-            seen_nodes.insert(node_key);
-
-            // Special handling for comments (using live NodeInfo and context_node)
-            if info.is_comment {
-                if debug_mode {
-                    println!(
-                        "DEBUG: Found comment node at line {}: {}",
-                        line,
-                        target_node.kind()
-                    );
-                }
-
-                // If we have a context node for this comment
-                if let Some(context_node) = info.context_node {
-                    let rel_start_pos = context_node.start_position();
-                    let rel_end_pos = context_node.end_position();
-                    let rel_key = (rel_start_pos.row, rel_end_pos.row);
-
-                    // Check test status using live node and language_impl
-                    // Ensure content is available here if needed by is_test_node
-                    if !allow_tests && language_impl.is_test_node(&context_node, content.as_bytes())
-                    {
-                        if debug_mode {
-                            println!(
-                                "DEBUG: Skipping test context node at lines {}-{}, type: {}",
-                                rel_start_pos.row + 1,
-                                rel_end_pos.row + 1,
-                                context_node.kind()
-                            );
-                        }
-                    } else {
-                        // Create a merged block
-                        let merged_start_row = std::cmp::min(start_pos.row, rel_start_pos.row);
-                        let merged_end_row = std::cmp::max(end_pos.row, rel_end_pos.row);
-                        let merged_start_byte =
-                            std::cmp::min(target_node.start_byte(), context_node.start_byte());
-                        let merged_end_byte =
-                            std::cmp::max(target_node.end_byte(), context_node.end_byte());
-                        let merged_node_type = context_node.kind().to_string();
-
-                        seen_nodes.insert(rel_key); // Mark context as seen too
-
-                        code_blocks.push(CodeBlock {
-                            start_row: merged_start_row,
-                            end_row: merged_end_row,
-                            start_byte: merged_start_byte,
-                            end_byte: merged_end_byte,
-                            node_type: merged_node_type.clone(),
-                            parent_node_type: None, // Keep consistent with original logic here
-                            parent_start_row: None,
-                            parent_end_row: None,
-                        });
-
-                        if debug_mode {
-                            println!(
-                                "DEBUG: Added merged block (comment + context) at lines {}-{}, type: {}",
-                                merged_start_row + 1,
-                                merged_end_row + 1,
-                                merged_node_type
-                            );
-                        }
-                        continue; // Skip adding individual comment
-                    }
-                }
-
-                // Add individual comment if not merged
-                code_blocks.push(CodeBlock {
-                    start_row: start_pos.row,
-                    end_row: end_pos.row,
-                    start_byte: target_node.start_byte(),
-                    end_byte: target_node.end_byte(),
-                    node_type: target_node.kind().to_string(),
-                    parent_node_type: None,
-                    parent_start_row: None,
-                    parent_end_row: None,
-                });
-                if debug_mode {
-                    println!(
-                        "DEBUG: Added individual comment block at lines {}-{}",
-                        start_pos.row + 1,
-                        end_pos.row + 1
-                    );
-                }
-                continue; // Skip rest for comments
-            }
-
-            // Skip test nodes (using live check)
-            if info.is_test {
-                // is_test flag was set during process_node
-                if debug_mode {
-                    println!(
-                        "DEBUG: Skipping test node at lines {}-{}",
-                        start_pos.row + 1,
-                        end_pos.row + 1
-                    );
-                }
-                continue;
-            }
-
-            // Check if line is within an existing block (this check might be redundant with seen_nodes)
-            // Keep consistent with original logic if it was there
-            let mut existing_block = false;
-            for block in &code_blocks {
-                if line > block.start_row + 1 && line <= block.end_row + 1 {
-                    if debug_mode {
-                        println!(
-                            "DEBUG: Line {} is within existing block: type='{}', lines={}-{}",
-                            line,
-                            block.node_type,
-                            block.start_row + 1,
-                            block.end_row + 1
-                        );
-                    }
-                    existing_block = true;
-                    break;
-                }
-            }
-            if existing_block {
-                continue;
-            }
-
-            // Check context node (acceptable ancestor)
-            if let Some(context_node) = info.context_node {
-                // context_node was set during process_node
-                let rel_start_pos = context_node.start_position();
-                let rel_end_pos = context_node.end_position();
-                let rel_key = (rel_start_pos.row, rel_end_pos.row);
-
-                // Ensure content is available if needed by is_test_node
-                if !allow_tests && language_impl.is_test_node(&context_node, content.as_bytes()) {
-                    if debug_mode {
-                        println!(
-                            "DEBUG: Skipping test context node (ancestor) at lines {}-{}",
-                            rel_start_pos.row + 1,
-                            rel_end_pos.row + 1
-                        );
-                    }
-                } else {
-                    if debug_mode {
-                        println!(
-                            "DEBUG: Using context node (ancestor) at lines {}-{}",
-                            rel_start_pos.row + 1,
-                            rel_end_pos.row + 1
-                        );
-                    }
-                    seen_nodes.insert(rel_key); // Mark context as seen
-
-                    // Get parent function info if applicable (e.g., for struct_type nodes)
-                    let parent_info = if context_node.kind() == "struct_type" {
-                        language_impl
-                            .find_parent_function(context_node)
-                            .map(|parent_node| {
-                                let parent_type = parent_node.kind().to_string();
-                                let parent_start = parent_node.start_position().row;
-                                let parent_end = parent_node.end_position().row;
-                                (parent_type, parent_start, parent_end)
-                            })
-                    } else {
-                        None
-                    };
-
-                    code_blocks.push(CodeBlock {
-                        start_row: rel_start_pos.row,
-                        end_row: rel_end_pos.row,
-                        start_byte: context_node.start_byte(),
-                        end_byte: context_node.end_byte(),
-                        node_type: context_node.kind().to_string(),
-                        parent_node_type: parent_info.as_ref().map(|(t, _, _)| t.clone()),
-                        parent_start_row: parent_info.as_ref().map(|(_, s, _)| *s),
-                        parent_end_row: parent_info.as_ref().map(|(_, _, e)| *e),
-                    });
-                    continue; // Skip adding target_node if context was added
-                }
-            }
-
-            // Check if target_node itself is acceptable (using live check)
-            if language_impl.is_acceptable_parent(&target_node) {
-                if debug_mode {
-                    println!(
-                        "DEBUG: Adding acceptable parent node at lines {}-{}",
-                        start_pos.row + 1,
-                        end_pos.row + 1
-                    );
-                }
-
-                // Get parent function info if applicable (e.g., for struct_type nodes)
-                let parent_info = if target_node.kind() == "struct_type" {
-                    language_impl
-                        .find_parent_function(target_node)
-                        .map(|parent_node| {
-                            let parent_type = parent_node.kind().to_string();
-                            let parent_start = parent_node.start_position().row;
-                            let parent_end = parent_node.end_position().row;
-                            (parent_type, parent_start, parent_end)
-                        })
-                } else {
-                    None
-                };
+    // Build the blocks from the same map a later cache hit will use
+    let code_blocks =
+        process_cached_line_map(&cacheable_line_map, line_numbers, allow_tests, debug_mode);
 
-                code_blocks.push(CodeBlock {
-                    start_row: start_pos.row,
-                    end_row: end_pos.row,
-                    start_byte: target_node.start_byte(),
-                    end_byte: target_node.end_byte(),
-                    node_type: target_node.kind().to_string(),
-                    parent_node_type: parent_info.as_ref().map(|(t, _, _)| t.clone()),
-                    parent_start_row: parent_info.as_ref().map(|(_, s, _)| *s),
-                    parent_end_row: parent_info.as_ref().map(|(_, _, e)| *e),
-                });
-                continue; // Skip fallback if acceptable parent added
-            }
+    LINE_MAP_CACHE.insert(cache_key.clone(), cacheable_line_map);
+    if debug_mode {
+        println!(
+            "DEBUG: Stored generated line_map in cache key: {}",
+            cache_key
+        );
+    }
 
-            // Fallback: Add the node found for the line if no context/acceptable parent logic applied
-            if debug_mode {
-                println!(
-                    "DEBUG: Adding node via fallback at lines {}-{}",
-                    start_pos.row + 1,
-                    end_pos.row + 1
-                );
-            }
+    code_blocks
+}
 
-            // Get parent function info if applicable (e.g., for struct_type nodes)
-            let parent_info = if target_node.kind() == "struct_type" {
-                language_impl
-                    .find_parent_function(target_node)
-                    .map(|parent_node| {
-                        let parent_type = parent_node.kind().to_string();
-                        let parent_start = parent_node.start_position().row;
-                        let parent_end = parent_node.end_position().row;
-                        (parent_type, parent_start, parent_end)
-                    })
-            } else {
-                None
-            };
-
-            code_blocks.push(CodeBlock {
-                start_row: start_pos.row,
-                end_row: end_pos.row,
-                start_byte: target_node.start_byte(),
-                end_byte: target_node.end_byte(),
-                node_type: target_node.kind().to_string(),
-                parent_node_type: parent_info.as_ref().map(|(t, _, _)| t.clone()),
-                parent_start_row: parent_info.as_ref().map(|(_, s, _)| *s),
-                parent_end_row: parent_info.as_ref().map(|(_, _, e)| *e),
-            });
-        } else if debug_mode {
-            println!(
-                "DEBUG: No node info found for line {} (Live NodeInfo)",
-                line
-            );
+/// Update the cached line map after an edit, recomputing only the affected rows
+///
+/// Rows of the line map cached for `old_content` are reused when every node they
+/// reference lies entirely before the edit, or entirely after it (in which case their
+/// positions are shifted). Rows inside the edited region, rows in `changed_rows`, and
+/// rows whose enclosing nodes span the edit are rebuilt from `new_tree`. When nothing
+/// is cached for `old_content`, the whole map is built from `new_tree` without parsing.
+///
+/// Returns the number of reused and recomputed rows.
+pub(crate) fn update_line_map_incrementally(
+    old_content: &str,
+    new_content: &str,
+    new_tree: &Tree,
+    edit: &InputEdit,
+    changed_rows: &BTreeSet<usize>,
+    extension: &str,
+    allow_tests: bool,
+) -> Result<(usize, usize)> {
+    let language_impl = match get_language_impl(extension) {
+        Some(lang) => lang,
+        None => {
+            return Err(anyhow::anyhow!(format!(
+                "Unsupported file type: {}",
+                extension
+            )))
         }
-    } // End loop over line_numbers
+    };
 
-    // Sort and deduplicate the blocks generated from live data
-    code_blocks.sort_by_key(|block| block.start_row);
+    let debug_mode = std::env::var("DEBUG").unwrap_or_default() == "1";
 
-    // Apply the improved deduplication logic
-    let mut final_code_blocks: Vec<CodeBlock> = Vec::new();
+    let old_key = line_map_cache_key(extension, old_content, allow_tests);
+    let new_key = line_map_cache_key(extension, new_content, allow_tests);
+    let line_count = new_content.lines().count();
 
-    // Add comments first
-    for block in code_blocks
-        .iter()
-        .filter(|b| b.node_type.contains("comment") || b.node_type == "/*" || b.node_type == "*/")
-    {
-        final_code_blocks.push(block.clone());
-    }
+    let mut new_line_map: Vec<Option<CachedNodeInfo>> = vec![None; line_count];
+    let mut affected_rows: BTreeSet<usize> = BTreeSet::new();
 
-    // Add non-comments, using the improved deduplication logic
-    for block in code_blocks
-        .iter()
-        .filter(|b| !b.node_type.contains("comment") && b.node_type != "/*" && b.node_type != "*/")
+    // The old map is superseded by the new one, so take it out of the cache
+    match LINE_MAP_CACHE
+        .remove(&old_key)
+        .map(|(_, line_map)| line_map)
     {
-        let mut should_add = true;
-        let mut blocks_to_remove: Vec<usize> = Vec::new();
-
-        // Define important block types that should be preserved
-        let important_block_types = [
-            "function_declaration",
-            "method_declaration",
-            "function_item",
-            "impl_item",
-            "type_declaration",
-            "struct_item",
-            "block_comment",
-        ];
-        let is_important = important_block_types.contains(&block.node_type.as_str());
-
-        // Check if this block overlaps with any of the previous blocks
-        for (idx, prev_block) in final_code_blocks.iter().enumerate() {
-            if prev_block.node_type.contains("comment")
-                || prev_block.node_type == "/*"
-                || prev_block.node_type == "*/"
-            {
-                continue; // Skip comments
-            }
-
-            let prev_is_important = important_block_types.contains(&prev_block.node_type.as_str());
-
-            // Check if blocks overlap
-            if block.start_row <= prev_block.end_row && block.end_row >= prev_block.start_row {
-                // Case 1: Current block is contained within previous block
-                if block.start_row >= prev_block.start_row && block.end_row <= prev_block.end_row {
-                    if debug_mode {
-                        println!(
-                            "DEBUG: Current block is contained within previous block: type='{}', lines={}-{} (contained in type='{}', lines={}-{})",
-                            block.node_type, block.start_row + 1, block.end_row + 1,
-                            prev_block.node_type, prev_block.start_row + 1, prev_block.end_row + 1
-                        );
-                    }
+        Some(old_line_map) => {
+            let start_row = edit.start_position.row;
+            let old_end_row = edit.old_end_position.row;
+            let new_end_row = edit.new_end_position.row;
+            let byte_delta = edit.new_end_byte as isize - edit.old_end_byte as isize;
+            let row_delta = new_end_row as isize - old_end_row as isize;
+
+            affected_rows.extend(start_row..=new_end_row);
+            affected_rows.extend(changed_rows.iter().copied());
+
+            for (old_row, entry) in old_line_map.into_iter().enumerate() {
+                let new_row = if old_row < start_row {
+                    old_row
+                } else if old_row > old_end_row {
+                    (old_row as isize + row_delta) as usize
+                } else {
+                    continue; // Inside the edited region, always rebuilt
+                };
 
-                    // If current block is important and previous block is not, keep both
-                    if is_important && !prev_is_important {
-                        if debug_mode {
-                            println!("DEBUG: Keeping important block type: {}", block.node_type);
-                        }
-                        // Don't remove any blocks, don't set should_add to false
-                    }
-                    // If previous block is important and current block is not, skip current block
-                    else if !is_important && prev_is_important {
-                        if debug_mode {
-                            println!("DEBUG: Skipping non-important block in favor of important block: {}", prev_block.node_type);
-                        }
-                        should_add = false;
-                        break;
-                    }
-                    // Otherwise, prefer the more specific (contained) block
-                    else {
-                        blocks_to_remove.push(idx);
-                    }
+                if new_row >= line_count {
+                    continue;
                 }
-                // Case 2: Previous block is contained within current block
-                else if prev_block.start_row >= block.start_row
-                    && prev_block.end_row <= block.end_row
-                {
-                    if debug_mode {
-                        println!(
-                            "DEBUG: Previous block is contained within current block: type='{}', lines={}-{} (contains type='{}', lines={}-{})",
-                            block.node_type, block.start_row + 1, block.end_row + 1,
-                            prev_block.node_type, prev_block.start_row + 1, prev_block.end_row + 1
-                        );
-                    }
 
-                    // If current block is important and previous block is not, keep both
-                    if is_important && !prev_is_important {
-                        if debug_mode {
-                            println!("DEBUG: Keeping important block type: {}", block.node_type);
-                        }
-                        // Don't set should_add to false, continue checking other blocks
+                match entry {
+                    Some(info) if info.has_unstable_comment_context() => {
+                        affected_rows.insert(new_row);
                     }
-                    // If previous block is important and current block is not, skip current block
-                    else if !is_important && prev_is_important {
-                        if debug_mode {
-                            println!("DEBUG: Skipping non-important block in favor of important block: {}", prev_block.node_type);
+                    Some(info) if old_row < start_row => {
+                        if info.ends_before(edit.start_byte, start_row) {
+                            new_line_map[new_row] = Some(info);
+                        } else {
+                            affected_rows.insert(new_row);
                         }
-                        should_add = false;
-                        break;
                     }
-                    // Otherwise, skip current block as it's less specific
-                    else {
-                        should_add = false;
-                        break;
-                    }
-                }
-                // Case 3: Blocks partially overlap
-                else {
-                    if debug_mode {
-                        println!(
-                            "DEBUG: Blocks partially overlap: type='{}', lines={}-{} (overlaps with type='{}', lines={}-{})",
-                            block.node_type, block.start_row + 1, block.end_row + 1,
-                            prev_block.node_type, prev_block.start_row + 1, prev_block.end_row + 1
-                        );
+                    Some(info) => {
+                        if info.starts_after(edit.old_end_byte, old_end_row) {
+                            new_line_map[new_row] = Some(info.shifted(byte_delta, row_delta));
+                        } else {
+                            affected_rows.insert(new_row);
+                        }
                     }
-                    // Skip current block in case of partial overlap
-                    should_add = false;
-                    break;
+                    None => {}
                 }
             }
         }
-
-        // Remove any blocks that should be replaced
-        for idx in blocks_to_remove.iter().rev() {
-            final_code_blocks.remove(*idx);
-        }
-
-        if should_add {
-            final_code_blocks.push(block.clone());
+        None => {
+            if debug_mode {
+                println!(
+                    "DEBUG: No cached line_map for key: {}, building from the new tree",
+                    old_key
+                );
+            }
+            affected_rows.extend(0..line_count);
         }
     }
 
-    // Final sort to maintain correct order
-    final_code_blocks.sort_by_key(|block| block.start_row);
+    affected_rows.retain(|&row| row < line_count);
 
-    // ====================================================================
-    // END: Inserted Original Block Processing Logic (Cache Miss Path)
-    // ====================================================================
+    // Rebuild the affected rows from the new tree
+    if !affected_rows.is_empty() {
+        let mut live_line_map: Vec<Option<NodeInfo>> = vec![None; line_count];
+        process_node(
+            new_tree.root_node(),
+            &mut live_line_map,
+            extension,
+            language_impl.as_ref(),
+            new_content.as_bytes(),
+            allow_tests,
+            debug_mode,
+            None,
+            Some(&affected_rows),
+        );
 
-    // Convert the original line_map to a cacheable format with representative node info
-    let cacheable_line_map: Vec<Option<CachedNodeInfo>> = line_map
-        .iter()
-        .map(|opt_node_info| {
-            opt_node_info.map(|node_info| {
+        for &row in &affected_rows {
+            new_line_map[row] = live_line_map[row].map(|node_info| {
                 CachedNodeInfo::from_node_info(
                     &node_info,
                     language_impl.as_ref(),
-                    content.as_bytes(),
+                    new_content.as_bytes(),
                     allow_tests,
                 )
-            })
-        })
-        .collect();
+            });
+        }
+    }
+
+    let recomputed = affected_rows.len();
+    let reused = line_count - recomputed;
 
-    // Store the cacheable version in the cache (as you already have)
-    LINE_MAP_CACHE.insert(cache_key.clone(), cacheable_line_map);
     if debug_mode {
         println!(
-            "DEBUG: Stored generated line_map in cache key: {}",
-            cache_key
+            "DEBUG: Incremental line_map update for key {}: {} rows reused, {} rows recomputed",
+            new_key, reused, recomputed
         );
     }
 
-    // Return the blocks generated from the LIVE data in this cache miss path
-    Ok(final_code_blocks)
+    if let Err(e) = tree_cache::persist_blocks(&new_key, new_content, &new_line_map) {
+        if debug_mode {
+            println!("DEBUG: Failed to persist line_map for {}: {}", new_key, e);
+        }
+    }
+    LINE_MAP_CACHE.insert(new_key, new_line_map);
+
+    Ok((reused, recomputed))
 }
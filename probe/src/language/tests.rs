@@ -765,6 +765,70 @@ fn test_csharp_language_implementation() {
     );
 }
 
+#[test]
+fn test_block_symbol_paths() {
+    use crate::language::parser::{block_definition_node, node_symbol_path, parse_source};
+
+    // (extension, code, rows of the block, expected symbol path)
+    let cases = [
+        (
+            "rs",
+            "struct Cart;\n\nimpl Cart {\n    /// Sum\n    fn total(&self) -> u32 {\n        0\n    }\n}\n",
+            (3, 6),
+            Some("Cart.total"),
+        ),
+        (
+            "go",
+            "package main\n\nfunc (s *Server) Start() error {\n\treturn nil\n}\n",
+            (2, 4),
+            Some("Start"),
+        ),
+        (
+            "c",
+            "static int *parse(const char *input) {\n    return 0;\n}\n",
+            (0, 2),
+            Some("parse"),
+        ),
+        (
+            "js",
+            "export const run = async () => {\n  go();\n};\n",
+            (0, 2),
+            Some("run"),
+        ),
+        (
+            "py",
+            "class Shop:\n    @cached\n    def price(self):\n        return 1\n",
+            (1, 3),
+            Some("Shop.price"),
+        ),
+        ("rs", "fn main() {\n    let x = 1;\n}\n", (1, 1), None),
+    ];
+
+    for (extension, code, (start_row, end_row), expected) in cases {
+        let tree = parse_source(code, extension).unwrap();
+        let symbol = block_definition_node(&tree, extension, start_row, end_row)
+            .and_then(|node| node_symbol_path(node, code.as_bytes(), extension));
+        assert_eq!(symbol.as_deref(), expected, "{}", code);
+    }
+}
+
+#[test]
+fn test_symbol_occurrences_of_overloads() {
+    use crate::language::parser::{block_definition_node, parse_source, symbol_occurrence};
+
+    let code = "class Calc {\n    int add(int a) { return a; }\n    int sub(int a) { return a; }\n    double add(double a) { return a; }\n}\n\nclass Other {\n    int add(int a) { return a; }\n}\n";
+    let tree = parse_source(code, "java").unwrap();
+    let occurrence = |row: usize| {
+        let node = block_definition_node(&tree, "java", row, row).unwrap();
+        symbol_occurrence(&tree, node, code.as_bytes(), "java")
+    };
+    assert_eq!(occurrence(1), 1);
+    assert_eq!(occurrence(2), 1);
+    assert_eq!(occurrence(3), 2);
+    // Other.add has its own symbol path
+    assert_eq!(occurrence(7), 1);
+}
+
 // Helper function to print the AST structure
 fn print_ast_structure(node: tree_sitter::Node, depth: usize) {
     let indent = " ".repeat(depth * 2);
@@ -785,3 +849,165 @@ fn print_ast_structure(node: tree_sitter::Node, depth: usize) {
 // Include tree cache tests
 #[path = "tree_cache_tests.rs"]
 mod tree_cache_tests;
+
+// Include incremental reparsing tests
+#[path = "incremental_tests.rs"]
+mod incremental_tests;
+
+#[test]
+fn test_cached_line_map_matches_fresh_parse() {
+    use crate::language::parser::clear_line_map_cache;
+
+    // Attributes, plain identifiers and literals are blocks of their own, which only
+    // the fresh parse used to return
+    let content = r#"use std::fmt;
+
+#[derive(Debug)]
+enum LineMapShape {
+    Square,
+    Circle(u32),
+}
+
+/// Documented function
+fn line_map_area(shape: &LineMapShape) -> u32 {
+    let label = "area";
+    match shape {
+        LineMapShape::Square => 4,
+        LineMapShape::Circle(r) => r * 3,
+    }
+}
+
+#[test]
+fn line_map_area_test() {
+    assert_eq!(line_map_area(&LineMapShape::Square), 4);
+}
+"#;
+    let line_count = content.lines().count();
+
+    for allow_tests in [false, true] {
+        for first in 1..=line_count {
+            for second in [first, first + 3, first + 7] {
+                let line_numbers: HashSet<usize> =
+                    [first, second.min(line_count)].into_iter().collect();
+
+                clear_line_map_cache();
+                let fresh =
+                    parse_file_for_code_blocks(content, "rs", &line_numbers, allow_tests, None)
+                        .unwrap();
+                let cached =
+                    parse_file_for_code_blocks(content, "rs", &line_numbers, allow_tests, None)
+                        .unwrap();
+
+                assert_eq!(fresh, cached, "lines {:?}", line_numbers);
+            }
+        }
+    }
+}
+
+#[test]
+fn test_cache_hit_keeps_blocks_of_first_parse() {
+    use crate::language::parser::clear_line_map_cache;
+
+    let content = "use std::fmt;\n\n#[derive(Debug)]\nenum CacheHitShape {\n    Square,\n}\n\nfn cache_hit_area(shape: &CacheHitShape) -> u32 {\n    let label = \"area\";\n    4\n}\n";
+    let cases = [
+        (vec![1], vec![(0, 0, "use_declaration")]),
+        (vec![3], vec![(2, 2, "attribute_item")]),
+        (
+            vec![3, 9],
+            vec![(2, 2, "attribute_item"), (7, 10, "function_item")],
+        ),
+    ];
+
+    for (lines, expected) in cases {
+        let line_numbers: HashSet<usize> = lines.iter().copied().collect();
+        clear_line_map_cache();
+
+        // The first parse and the cache hit after it return the same blocks; the cache
+        // hit used to drop blocks that aren't nested in a definition
+        for _ in 0..2 {
+            let blocks = parse_file_for_code_blocks(content, "rs", &line_numbers, false, None)
+                .unwrap()
+                .into_iter()
+                .map(|b| (b.start_row, b.end_row, b.node_type))
+                .collect::<Vec<_>>();
+            let expected = expected
+                .iter()
+                .map(|&(start, end, kind)| (start, end, kind.to_string()))
+                .collect::<Vec<_>>();
+            assert_eq!(blocks, expected, "lines {:?}", lines);
+        }
+    }
+}
+
+#[test]
+fn test_only_nested_go_structs_have_a_parent_block() {
+    // Methods keep no parent in the line map; block merging finds their enclosing block
+    // from the tree instead
+    let cases = [
+        (
+            "rs",
+            "struct Cart;\n\nimpl Cart {\n    fn total(&self) -> u32 {\n        1\n    }\n}\n",
+            4,
+        ),
+        (
+            "py",
+            "class Shop:\n    def price(self):\n        return 1\n",
+            3,
+        ),
+        (
+            "js",
+            "class Shop {\n  price() {\n    return 1;\n  }\n}\n",
+            3,
+        ),
+    ];
+    for (extension, content, line) in cases {
+        let line_numbers: HashSet<usize> = [line].into_iter().collect();
+        let blocks =
+            parse_file_for_code_blocks(content, extension, &line_numbers, false, None).unwrap();
+        assert!(!blocks.is_empty(), "{}", extension);
+        for block in blocks {
+            assert_eq!(block.parent_node_type, None, "{}: {:?}", extension, block);
+            assert_eq!(block.parent_start_row, None, "{}: {:?}", extension, block);
+        }
+    }
+
+    let content = "package main\n\nfunc handler() {\n\ttype request struct {\n\t\tID int\n\t}\n\t_ = request{}\n}\n";
+    let line_numbers: HashSet<usize> = [5].into_iter().collect();
+    let blocks = parse_file_for_code_blocks(content, "go", &line_numbers, false, None).unwrap();
+    let nested = blocks
+        .iter()
+        .find(|block| block.node_type == "struct_type")
+        .expect("struct block");
+    assert_eq!(
+        nested.parent_node_type.as_deref(),
+        Some("function_declaration")
+    );
+    assert_eq!(
+        (nested.parent_start_row, nested.parent_end_row),
+        (Some(2), Some(7))
+    );
+}
+
+#[test]
+fn test_nested_go_struct_keeps_its_function_with_tests_allowed() {
+    let content = "package main\n\nfunc handlerWithTests() {\n\ttype request struct {\n\t\tID int\n\t}\n\t_ = request{}\n}\n";
+    let line_numbers: HashSet<usize> = [5].into_iter().collect();
+    for allow_tests in [false, true] {
+        let blocks =
+            parse_file_for_code_blocks(content, "go", &line_numbers, allow_tests, None).unwrap();
+        let nested = blocks
+            .iter()
+            .find(|block| block.node_type == "struct_type")
+            .expect("struct block");
+        assert_eq!(
+            (
+                nested.parent_node_type.as_deref(),
+                nested.parent_start_row,
+                nested.parent_end_row
+            ),
+            (Some("function_declaration"), Some(2), Some(7)),
+            "allow_tests: {}",
+            allow_tests
+        );
+    }
+}
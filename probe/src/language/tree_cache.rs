@@ -1,30 +1,388 @@
 use anyhow::{Context, Result};
-use std::collections::hash_map::DefaultHasher;
-use std::collections::HashMap;
-use std::hash::{Hash, Hasher};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
 use std::sync::Mutex;
+use std::time::{Duration, SystemTime};
 use tree_sitter::Tree;
 
+use crate::config::CONFIG_FILE_NAME;
+use crate::language::incremental::compute_edit;
+
+/// Namespace of the trees of files outside any project, and of content without a file
+pub const DEFAULT_NAMESPACE: &str = "default";
+
+/// Default maximum number of trees kept in memory
+const DEFAULT_MAX_ENTRIES: usize = 1000;
+
+/// Default approximate memory budget for cached trees (256 MiB)
+const DEFAULT_MAX_BYTES: usize = 256 * 1024 * 1024;
+
+/// Default maximum size of the persisted block boundaries on disk (64 MiB)
+const DEFAULT_PERSIST_MAX_BYTES: u64 = 64 * 1024 * 1024;
+
+/// How often the persisted block boundaries are checked against their size cap
+const PERSIST_GC_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+/// Rough per-node memory cost of a tree-sitter tree, used for the byte budget
+const ESTIMATED_BYTES_PER_NODE: usize = 64;
+
+/// Configuration for the tree cache
+///
+/// The defaults can be overridden with the `PROBE_TREE_CACHE_MAX_ENTRIES` and
+/// `PROBE_TREE_CACHE_MAX_BYTES` environment variables. Setting `PROBE_PERSIST_BLOCKS=1`
+/// enables on-disk persistence of block boundaries under `~/.cache/probe/blocks`,
+/// capped at `PROBE_PERSIST_BLOCKS_MAX_BYTES`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TreeCacheConfig {
+    /// Maximum number of trees kept across all namespaces
+    pub max_entries: usize,
+    /// Approximate maximum memory used by cached trees, in bytes
+    pub max_bytes: usize,
+    /// Directory where block boundaries are persisted (None disables persistence)
+    pub persist_dir: Option<PathBuf>,
+    /// Maximum size of the persisted block boundaries, in bytes
+    pub persist_max_bytes: u64,
+}
+
+impl Default for TreeCacheConfig {
+    fn default() -> Self {
+        Self {
+            max_entries: DEFAULT_MAX_ENTRIES,
+            max_bytes: DEFAULT_MAX_BYTES,
+            persist_dir: None,
+            persist_max_bytes: DEFAULT_PERSIST_MAX_BYTES,
+        }
+    }
+}
+
+impl TreeCacheConfig {
+    /// Build a configuration from the defaults and any `PROBE_*` environment overrides
+    pub fn from_env() -> Self {
+        let mut config = Self::default();
+
+        if let Some(max_entries) = std::env::var("PROBE_TREE_CACHE_MAX_ENTRIES")
+            .ok()
+            .and_then(|v| v.parse::<usize>().ok())
+        {
+            config.max_entries = max_entries;
+        }
+
+        if let Some(max_bytes) = std::env::var("PROBE_TREE_CACHE_MAX_BYTES")
+            .ok()
+            .and_then(|v| v.parse::<usize>().ok())
+        {
+            config.max_bytes = max_bytes;
+        }
+
+        if std::env::var("PROBE_PERSIST_BLOCKS").unwrap_or_default() == "1" {
+            config.persist_dir = Some(default_persist_dir());
+        }
+
+        if let Some(persist_max_bytes) = std::env::var("PROBE_PERSIST_BLOCKS_MAX_BYTES")
+            .ok()
+            .and_then(|v| v.parse::<u64>().ok())
+        {
+            config.persist_max_bytes = persist_max_bytes;
+        }
+
+        config
+    }
+}
+
+/// Get the default directory for persisted block boundaries
+///
+/// The crate version is part of the path so that a new release never reads
+/// entries written with a different hashing or line map layout.
+pub fn default_persist_dir() -> PathBuf {
+    let home_dir = dirs::home_dir().unwrap_or_else(|| PathBuf::from("."));
+    home_dir
+        .join(".cache")
+        .join("probe")
+        .join("blocks")
+        .join(env!("CARGO_PKG_VERSION"))
+}
+
+/// Hit/miss counters for a single namespace
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct NamespaceStats {
+    pub entries: usize,
+    pub estimated_bytes: usize,
+    pub hits: usize,
+    pub misses: usize,
+}
+
+/// Snapshot of the tree cache state and its counters
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct TreeCacheStats {
+    pub entries: usize,
+    pub estimated_bytes: usize,
+    pub max_entries: usize,
+    pub max_bytes: usize,
+    pub hits: usize,
+    pub misses: usize,
+    pub evictions: usize,
+    /// Number of line maps loaded from disk instead of being rebuilt
+    pub persisted_hits: usize,
+    /// Number of line maps written to disk
+    pub persisted_writes: usize,
+    pub namespaces: BTreeMap<String, NamespaceStats>,
+}
+
+impl TreeCacheStats {
+    /// Fraction of lookups served from memory (0.0 when nothing was looked up yet)
+    pub fn hit_rate(&self) -> f64 {
+        let lookups = self.hits + self.misses;
+        if lookups == 0 {
+            0.0
+        } else {
+            self.hits as f64 / lookups as f64
+        }
+    }
+}
+
+/// A single cached tree with the bookkeeping needed for LRU eviction
+struct CacheEntry {
+    tree: Tree,
+    /// The content the tree was parsed from, to edit the tree when the file changes
+    content: String,
+    content_hash: u64,
+    estimated_bytes: usize,
+    last_access: u64,
+}
+
+/// Key of a cached tree: (namespace, file path)
+///
+/// Searches, extraction and incremental reparsing all use the path of the file as it
+/// was given, so a tree stored by one is found by the others.
+type CacheKey = (String, String);
+
+/// Bounded LRU cache of parsed trees, grouped by namespace
+struct TreeCache {
+    config: TreeCacheConfig,
+    entries: HashMap<CacheKey, CacheEntry>,
+    /// Access order, oldest first, keyed by the access tick
+    lru: BTreeMap<u64, CacheKey>,
+    tick: u64,
+    total_bytes: usize,
+    hits: usize,
+    misses: usize,
+    evictions: usize,
+    persisted_hits: usize,
+    persisted_writes: usize,
+    namespace_counters: HashMap<String, (usize, usize)>,
+}
+
+impl TreeCache {
+    fn new(config: TreeCacheConfig) -> Self {
+        Self {
+            config,
+            entries: HashMap::new(),
+            lru: BTreeMap::new(),
+            tick: 0,
+            total_bytes: 0,
+            hits: 0,
+            misses: 0,
+            evictions: 0,
+            persisted_hits: 0,
+            persisted_writes: 0,
+            namespace_counters: HashMap::new(),
+        }
+    }
+
+    fn next_tick(&mut self) -> u64 {
+        self.tick += 1;
+        self.tick
+    }
+
+    fn record_lookup(&mut self, namespace: &str, hit: bool) {
+        let counters = self
+            .namespace_counters
+            .entry(namespace.to_string())
+            .or_insert((0, 0));
+        if hit {
+            self.hits += 1;
+            counters.0 += 1;
+        } else {
+            self.misses += 1;
+            counters.1 += 1;
+        }
+    }
+
+    /// Return the cached tree if its content hash matches, refreshing its LRU position
+    fn get(&mut self, key: &CacheKey, content_hash: u64) -> Option<Tree> {
+        let last_access = match self.entries.get(key) {
+            Some(entry) if entry.content_hash == content_hash => entry.last_access,
+            _ => return None,
+        };
+
+        let tick = self.next_tick();
+        self.lru.remove(&last_access);
+        self.lru.insert(tick, key.clone());
+
+        let entry = self.entries.get_mut(key)?;
+        entry.last_access = tick;
+        Some(entry.tree.clone())
+    }
+
+    /// Take the entry out of the cache, whatever content it was parsed from
+    fn take(&mut self, key: &CacheKey) -> Option<CacheEntry> {
+        let entry = self.entries.remove(key)?;
+        self.lru.remove(&entry.last_access);
+        self.total_bytes -= entry.estimated_bytes;
+        Some(entry)
+    }
+
+    fn insert(&mut self, key: CacheKey, tree: Tree, content: &str) {
+        self.remove(&key);
+
+        // The content is kept next to the tree, so it counts against the budget too
+        let estimated_bytes = estimate_tree_bytes(&tree, content.len()) + content.len();
+        let tick = self.next_tick();
+        self.lru.insert(tick, key.clone());
+        self.total_bytes += estimated_bytes;
+        self.entries.insert(
+            key,
+            CacheEntry {
+                tree,
+                content: content.to_string(),
+                content_hash: compute_content_hash(content),
+                estimated_bytes,
+                last_access: tick,
+            },
+        );
+
+        self.evict_to_budget();
+    }
+
+    fn remove(&mut self, key: &CacheKey) -> bool {
+        self.take(key).is_some()
+    }
+
+    /// Evict least recently used entries until both budgets are respected
+    fn evict_to_budget(&mut self) {
+        let debug_mode = std::env::var("DEBUG").unwrap_or_default() == "1";
+
+        while self.entries.len() > self.config.max_entries
+            || (self.total_bytes > self.config.max_bytes && !self.entries.is_empty())
+        {
+            let oldest = match self.lru.iter().next() {
+                Some((_, key)) => key.clone(),
+                None => break,
+            };
+
+            if debug_mode {
+                println!(
+                    "[DEBUG] Evicting tree from cache: {} (namespace: {})",
+                    oldest.1, oldest.0
+                );
+            }
+
+            self.remove(&oldest);
+            self.evictions += 1;
+        }
+    }
+
+    fn clear(&mut self) {
+        self.entries.clear();
+        self.lru.clear();
+        self.total_bytes = 0;
+    }
+
+    fn reset_counters(&mut self) {
+        self.hits = 0;
+        self.misses = 0;
+        self.evictions = 0;
+        self.persisted_hits = 0;
+        self.persisted_writes = 0;
+        self.namespace_counters.clear();
+    }
+
+    fn stats(&self) -> TreeCacheStats {
+        let mut namespaces: BTreeMap<String, NamespaceStats> = BTreeMap::new();
+
+        for ((namespace, _), entry) in &self.entries {
+            let ns = namespaces.entry(namespace.clone()).or_default();
+            ns.entries += 1;
+            ns.estimated_bytes += entry.estimated_bytes;
+        }
+
+        for (namespace, (hits, misses)) in &self.namespace_counters {
+            let ns = namespaces.entry(namespace.clone()).or_default();
+            ns.hits = *hits;
+            ns.misses = *misses;
+        }
+
+        TreeCacheStats {
+            entries: self.entries.len(),
+            estimated_bytes: self.total_bytes,
+            max_entries: self.config.max_entries,
+            max_bytes: self.config.max_bytes,
+            hits: self.hits,
+            misses: self.misses,
+            evictions: self.evictions,
+            persisted_hits: self.persisted_hits,
+            persisted_writes: self.persisted_writes,
+            namespaces,
+        }
+    }
+}
+
 lazy_static::lazy_static! {
     /// A cache for parsed syntax trees to avoid redundant parsing
     ///
-    /// This cache stores parsed ASTs keyed by file path and content hash.
-    /// When the same file is parsed multiple times, this avoids the overhead
-    /// of re-parsing unchanged files.
-    static ref TREE_CACHE: Mutex<HashMap<String, (Tree, u64)>> = Mutex::new(HashMap::new());
+    /// This cache stores parsed ASTs keyed by namespace and file path, together with
+    /// a content hash. When the same file is parsed multiple times, this avoids the
+    /// overhead of re-parsing unchanged files. The cache is bounded by entry count
+    /// and an estimated byte budget, evicting the least recently used trees first.
+    static ref TREE_CACHE: Mutex<TreeCache> = Mutex::new(TreeCache::new(TreeCacheConfig::from_env()));
 
-    /// A counter for cache hits, used for testing
-    static ref CACHE_HITS: Mutex<usize> = Mutex::new(0);
+    /// The namespace of each directory trees were cached from, see [`namespace_for_file`]
+    static ref DIRECTORY_NAMESPACES: Mutex<HashMap<PathBuf, String>> = Mutex::new(HashMap::new());
 
     /// A mutex for test synchronization to prevent concurrent test execution
     static ref TEST_MUTEX: Mutex<()> = Mutex::new(());
 }
 
+/// FNV-1a offset basis and prime for 64-bit hashes
+const FNV_OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+const FNV_PRIME: u64 = 0x0000_0100_0000_01b3;
+
 /// Compute a hash of the content for cache validation
-fn compute_content_hash(content: &str) -> u64 {
-    let mut hasher = DefaultHasher::new();
-    content.hash(&mut hasher);
-    hasher.finish()
+///
+/// This is a fixed FNV-1a rather than the standard library's hasher, whose output
+/// may change between Rust releases: the hash names the block boundaries
+/// persisted on disk, so it has to be stable across builds.
+pub fn compute_content_hash(content: &str) -> u64 {
+    content.bytes().fold(FNV_OFFSET_BASIS, |hash, byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(FNV_PRIME)
+    })
+}
+
+/// FNV-1a offset basis and prime for 128-bit hashes
+const FNV_OFFSET_BASIS_128: u128 = 0x6c62_272e_07bb_0142_62b8_2175_6295_c58d;
+const FNV_PRIME_128: u128 = 0x0000_0000_0100_0000_0000_0000_0000_013b;
+
+/// Compute a 128-bit digest of the content
+///
+/// Persisted block boundaries are named after the 64-bit [`compute_content_hash`],
+/// and store this wider digest so that a key collision is not mistaken for a hit.
+pub fn compute_content_digest(content: &str) -> u128 {
+    content.bytes().fold(FNV_OFFSET_BASIS_128, |hash, byte| {
+        (hash ^ u128::from(byte)).wrapping_mul(FNV_PRIME_128)
+    })
+}
+
+/// Estimate the memory held by a tree
+///
+/// tree-sitter doesn't expose allocation sizes, so this approximates the cost from
+/// the number of nodes, with the content length as a lower bound.
+fn estimate_tree_bytes(tree: &Tree, content_len: usize) -> usize {
+    let node_count = tree.root_node().descendant_count();
+    std::cmp::max(node_count * ESTIMATED_BYTES_PER_NODE, content_len)
 }
 
 /// Get a cached tree if available, otherwise parse and cache the result
@@ -32,6 +390,8 @@ fn compute_content_hash(content: &str) -> u64 {
 /// This function checks if a valid cached tree exists for the given file path
 /// and content. If found and the content hash matches, it returns the cached tree.
 /// Otherwise, it parses the content, caches the result, and returns the new tree.
+/// When the file's content changed since its tree was cached, the old tree is
+/// edited and reused as the starting point of the new parse.
 ///
 /// # Arguments
 ///
@@ -46,49 +406,96 @@ pub fn get_or_parse_tree(
     file_path: &str,
     content: &str,
     parser: &mut tree_sitter::Parser,
+) -> Result<Tree> {
+    get_or_parse_tree_in_namespace(&namespace_for_file(file_path), file_path, content, parser)
+}
+
+/// The namespace of the project a file belongs to
+///
+/// A project is the nearest directory above the file with a `.git` directory or a
+/// `.probe.toml`; its canonical path names the namespace. Files outside any project
+/// belong to [`DEFAULT_NAMESPACE`].
+pub fn namespace_for_file(file_path: &str) -> String {
+    let directory = match Path::new(file_path).parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent.to_path_buf(),
+        _ => PathBuf::from("."),
+    };
+
+    let mut namespaces = DIRECTORY_NAMESPACES
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner());
+    if let Some(namespace) = namespaces.get(&directory) {
+        return namespace.clone();
+    }
+
+    let namespace = directory
+        .canonicalize()
+        .ok()
+        .and_then(|canonical| {
+            canonical
+                .ancestors()
+                .find(|dir| dir.join(".git").exists() || dir.join(CONFIG_FILE_NAME).is_file())
+                .map(|root| root.to_string_lossy().to_string())
+        })
+        .unwrap_or_else(|| DEFAULT_NAMESPACE.to_string());
+    namespaces.insert(directory, namespace.clone());
+    namespace
+}
+
+/// Same as [`get_or_parse_tree`], but stores the tree under the given namespace
+///
+/// Namespaces let a long-running process that serves several projects track
+/// and clear each project's trees independently. All namespaces share the
+/// global entry and byte budget.
+pub fn get_or_parse_tree_in_namespace(
+    namespace: &str,
+    file_path: &str,
+    content: &str,
+    parser: &mut tree_sitter::Parser,
 ) -> Result<Tree> {
     let content_hash = compute_content_hash(content);
+    let key = (namespace.to_string(), file_path.to_string());
 
     // Check if debug mode is enabled
     let debug_mode = std::env::var("DEBUG").unwrap_or_default() == "1";
 
     // Try to get from cache first
-    {
+    let stale = {
         let mut cache = TREE_CACHE
             .lock()
             .unwrap_or_else(|poisoned| poisoned.into_inner());
-        if let Some((cached_tree, cached_hash)) = cache.get(file_path) {
-            if cached_hash == &content_hash {
-                // Increment cache hit counter
-                {
-                    let mut hits = CACHE_HITS
-                        .lock()
-                        .unwrap_or_else(|poisoned| poisoned.into_inner());
-                    *hits += 1;
-                }
-
-                if debug_mode {
-                    println!("[DEBUG] Cache hit for file: {}", file_path);
-                }
-                return Ok(cached_tree.clone());
-            } else {
-                // Content changed, explicitly remove the old entry
-                cache.remove(file_path);
-                if debug_mode {
-                    println!(
-                        "[DEBUG] Cache invalidated for file: {} (content changed)",
-                        file_path
-                    );
-                }
+        if let Some(tree) = cache.get(&key, content_hash) {
+            cache.record_lookup(namespace, true);
+            if debug_mode {
+                println!("[DEBUG] Cache hit for file: {}", file_path);
             }
-        } else if debug_mode {
+            return Ok(tree);
+        }
+
+        cache.record_lookup(namespace, false);
+        cache.take(&key)
+    };
+
+    // Content changed: apply the difference to the stale tree and reparse from it
+    let old_tree = stale.and_then(|entry| {
+        let edit = compute_edit(&entry.content, content)?;
+        let mut tree = entry.tree;
+        tree.edit(&edit);
+        Some(tree)
+    });
+    if debug_mode {
+        if old_tree.is_some() {
+            println!(
+                "[DEBUG] Cache invalidated for file: {} (content changed, reparsing incrementally)",
+                file_path
+            );
+        } else {
             println!("[DEBUG] Cache miss for file: {}", file_path);
         }
     }
 
-    // Not in cache or content changed, parse and store
     let tree = parser
-        .parse(content, None)
+        .parse(content, old_tree.as_ref())
         .context(format!("Failed to parse file: {}", file_path))?;
 
     // Store in cache
@@ -96,17 +503,71 @@ pub fn get_or_parse_tree(
         let mut cache = TREE_CACHE
             .lock()
             .unwrap_or_else(|poisoned| poisoned.into_inner());
-        cache.insert(file_path.to_string(), (tree.clone(), content_hash));
+        cache.insert(key, tree.clone(), content);
 
         if debug_mode {
             println!("[DEBUG] Cached parsed tree for file: {}", file_path);
-            println!("[DEBUG] Current cache size: {} entries", cache.len());
+            println!(
+                "[DEBUG] Current cache size: {} entries (~{} bytes)",
+                cache.entries.len(),
+                cache.total_bytes
+            );
         }
     }
 
     Ok(tree)
 }
 
+/// Look up a cached tree for content with the given hash, without parsing on a miss
+///
+/// # Arguments
+///
+/// * `namespace` - The project namespace the tree was stored under
+/// * `file_path` - The path of the file
+/// * `content_hash` - The hash of the content the tree must have been parsed from,
+///   as returned by [`compute_content_hash`]
+pub fn get_cached_tree(namespace: &str, file_path: &str, content_hash: u64) -> Option<Tree> {
+    let mut cache = TREE_CACHE
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner());
+    let key = (namespace.to_string(), file_path.to_string());
+    let tree = cache.get(&key, content_hash);
+    cache.record_lookup(namespace, tree.is_some());
+    tree
+}
+
+/// Get the content the tree cached for a file was parsed from
+///
+/// Unlike [`get_cached_tree`], this doesn't count as a lookup.
+pub fn get_cached_content(namespace: &str, file_path: &str) -> Option<String> {
+    let cache = TREE_CACHE
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner());
+    cache
+        .entries
+        .get(&(namespace.to_string(), file_path.to_string()))
+        .map(|entry| entry.content.clone())
+}
+
+/// Store a tree that was parsed outside of [`get_or_parse_tree`], replacing any previous entry
+///
+/// # Arguments
+///
+/// * `namespace` - The project namespace to store the tree under
+/// * `file_path` - The path of the file
+/// * `content` - The content the tree was parsed from
+/// * `tree` - The parsed tree
+pub fn store_tree(namespace: &str, file_path: &str, content: &str, tree: Tree) {
+    let mut cache = TREE_CACHE
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner());
+    cache.insert(
+        (namespace.to_string(), file_path.to_string()),
+        tree,
+        content,
+    );
+}
+
 /// Clear the entire tree cache
 ///
 /// This function can be used to free memory or force re-parsing of all files.
@@ -118,20 +579,41 @@ pub fn clear_tree_cache() {
     let debug_mode = std::env::var("DEBUG").unwrap_or_default() == "1";
 
     if debug_mode {
-        println!("[DEBUG] Clearing tree cache ({} entries)", cache.len());
+        println!(
+            "[DEBUG] Clearing tree cache ({} entries)",
+            cache.entries.len()
+        );
     }
 
     cache.clear();
 
-    // Also reset the cache hit counter
-    let mut hits = CACHE_HITS
+    // Also reset the counters
+    cache.reset_counters();
+}
+
+/// Remove the trees of one namespace from the cache, e.g. when a project is closed
+///
+/// The trees of other namespaces and the hit/miss counters are kept.
+#[allow(dead_code)]
+pub fn clear_namespace(namespace: &str) {
+    let mut cache = TREE_CACHE
         .lock()
         .unwrap_or_else(|poisoned| poisoned.into_inner());
-    *hits = 0;
+    let keys: Vec<CacheKey> = cache
+        .entries
+        .keys()
+        .filter(|(entry_namespace, _)| entry_namespace == namespace)
+        .cloned()
+        .collect();
+    for key in &keys {
+        cache.remove(key);
+    }
 }
 
 /// Remove a specific file from the tree cache
 ///
+/// The file is removed from every namespace it was cached under.
+///
 /// # Arguments
 ///
 /// * `file_path` - The path of the file to remove from the cache
@@ -142,11 +624,232 @@ pub fn invalidate_cache_entry(file_path: &str) {
         .unwrap_or_else(|poisoned| poisoned.into_inner());
     let debug_mode = std::env::var("DEBUG").unwrap_or_default() == "1";
 
-    if cache.remove(file_path).is_some() && debug_mode {
+    let keys: Vec<CacheKey> = cache
+        .entries
+        .keys()
+        .filter(|(_, path)| path == file_path)
+        .cloned()
+        .collect();
+    for key in &keys {
+        cache.remove(key);
+    }
+
+    if !keys.is_empty() && debug_mode {
         println!("[DEBUG] Removed file from cache: {}", file_path);
     }
 }
 
+/// Replace the cache configuration, evicting entries if the new budget is smaller
+///
+/// Outside of tests the configuration comes from [`TreeCacheConfig::from_env`].
+#[cfg(test)]
+pub fn set_tree_cache_config(config: TreeCacheConfig) {
+    let mut cache = TREE_CACHE
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner());
+    cache.config = config;
+    cache.evict_to_budget();
+}
+
+/// Get the current cache configuration
+#[cfg(test)]
+pub fn get_tree_cache_config() -> TreeCacheConfig {
+    let cache = TREE_CACHE
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner());
+    cache.config.clone()
+}
+
+/// Get a snapshot of the cache size, budgets and hit/miss counters
+pub fn get_tree_cache_stats() -> TreeCacheStats {
+    let cache = TREE_CACHE
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner());
+    cache.stats()
+}
+
+/// On-disk representation of persisted block boundaries
+#[derive(Serialize, Deserialize)]
+struct PersistedBlocks<T> {
+    /// Length of the content the boundaries were computed from
+    content_len: usize,
+    /// Hex [`compute_content_digest`] of that content
+    content_digest: String,
+    blocks: T,
+}
+
+/// Get the path of a persisted entry, if persistence is enabled
+fn persisted_path(key: &str) -> Option<PathBuf> {
+    let cache = TREE_CACHE
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner());
+    cache
+        .config
+        .persist_dir
+        .as_ref()
+        .map(|dir| dir.join(format!("{}.json", key)))
+}
+
+/// Load block boundaries persisted for a content-hash based key
+///
+/// Returns None when persistence is disabled, nothing was stored for the key,
+/// or the stored entry was computed from different content.
+pub fn load_persisted_blocks<T: DeserializeOwned>(key: &str, content: &str) -> Option<T> {
+    let path = persisted_path(key)?;
+    let debug_mode = std::env::var("DEBUG").unwrap_or_default() == "1";
+
+    let data = fs::read_to_string(&path).ok()?;
+    let persisted: PersistedBlocks<T> = match serde_json::from_str(&data) {
+        Ok(persisted) => persisted,
+        Err(e) => {
+            if debug_mode {
+                println!(
+                    "[DEBUG] Ignoring unreadable persisted blocks {:?}: {}",
+                    path, e
+                );
+            }
+            return None;
+        }
+    };
+
+    if persisted.content_len != content.len()
+        || persisted.content_digest != format!("{:032x}", compute_content_digest(content))
+    {
+        if debug_mode {
+            println!(
+                "[DEBUG] Ignoring persisted blocks {:?} of different content",
+                path
+            );
+        }
+        return None;
+    }
+
+    // Refresh the modification time, which the size cap evicts by
+    if let Ok(file) = fs::File::options().write(true).open(&path) {
+        let _ = file.set_modified(SystemTime::now());
+    }
+
+    let mut cache = TREE_CACHE
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner());
+    cache.persisted_hits += 1;
+
+    if debug_mode {
+        println!("[DEBUG] Loaded persisted blocks from {:?}", path);
+    }
+
+    Some(persisted.blocks)
+}
+
+/// Persist block boundaries for a content-hash based key
+///
+/// This is a no-op when persistence is disabled. The entry is written to a
+/// temporary file of its own first, so neither concurrent readers nor other
+/// writers of the same key see a partial file.
+pub fn persist_blocks<T: Serialize>(key: &str, content: &str, blocks: &T) -> Result<()> {
+    let path = match persisted_path(key) {
+        Some(path) => path,
+        None => return Ok(()),
+    };
+    let dir = path.parent().unwrap_or(Path::new(".")).to_path_buf();
+
+    fs::create_dir_all(&dir)?;
+
+    let json = serde_json::to_string(&PersistedBlocks {
+        content_len: content.len(),
+        content_digest: format!("{:032x}", compute_content_digest(content)),
+        blocks,
+    })?;
+
+    let mut tmp_file = tempfile::Builder::new()
+        .prefix(".blocks")
+        .suffix(".tmp")
+        .tempfile_in(&dir)?;
+    tmp_file.write_all(json.as_bytes())?;
+    tmp_file.persist(&path)?;
+
+    let max_bytes = {
+        let mut cache = TREE_CACHE
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        cache.persisted_writes += 1;
+        cache.config.persist_max_bytes
+    };
+
+    collect_persisted_garbage_if_due(&dir, max_bytes);
+
+    Ok(())
+}
+
+/// Keep the persisted block boundaries under their size cap, at most once per PERSIST_GC_INTERVAL
+///
+/// The least recently used entries are removed first. Entries of other probe
+/// versions in the default location are never read again, so they are removed
+/// as a whole. Errors are only reported in debug mode, since garbage collection
+/// must never make a search fail.
+fn collect_persisted_garbage_if_due(dir: &Path, max_bytes: u64) {
+    let debug_mode = std::env::var("DEBUG").unwrap_or_default() == "1";
+    let marker = dir.join(".last_gc");
+
+    let due = fs::metadata(&marker)
+        .and_then(|metadata| metadata.modified())
+        .ok()
+        .and_then(|modified| modified.elapsed().ok())
+        .is_none_or(|elapsed| elapsed >= PERSIST_GC_INTERVAL);
+    if !due {
+        return;
+    }
+    if let Err(e) = fs::File::create(&marker) {
+        if debug_mode {
+            println!("[DEBUG] Error writing persisted blocks GC marker: {}", e);
+        }
+        return;
+    }
+
+    if dir == default_persist_dir() {
+        if let Some(versions) = dir.parent().and_then(|parent| fs::read_dir(parent).ok()) {
+            for version in versions.flatten() {
+                let version_dir = version.path();
+                if version_dir != dir && version_dir.is_dir() {
+                    let _ = fs::remove_dir_all(&version_dir);
+                }
+            }
+        }
+    }
+
+    let mut entries: Vec<(SystemTime, u64, PathBuf)> = match fs::read_dir(dir) {
+        Ok(entries) => entries
+            .flatten()
+            .filter(|entry| entry.path().extension().is_some_and(|ext| ext == "json"))
+            .filter_map(|entry| {
+                let metadata = entry.metadata().ok()?;
+                Some((metadata.modified().ok()?, metadata.len(), entry.path()))
+            })
+            .collect(),
+        Err(_) => return,
+    };
+
+    let mut total: u64 = entries.iter().map(|(_, len, _)| len).sum();
+    entries.sort();
+    let mut removed = 0;
+    for (_, len, path) in entries {
+        if total <= max_bytes {
+            break;
+        }
+        if fs::remove_file(&path).is_ok() {
+            total -= len;
+            removed += 1;
+        }
+    }
+
+    if debug_mode {
+        println!(
+            "[DEBUG] Persisted blocks GC removed {} entries, {} bytes left",
+            removed, total
+        );
+    }
+}
+
 /// Acquire the test mutex for test synchronization
 ///
 /// This function is used by tests to prevent concurrent access to the cache
@@ -164,32 +867,43 @@ pub fn get_cache_size() -> usize {
     let cache = TREE_CACHE
         .lock()
         .unwrap_or_else(|poisoned| poisoned.into_inner());
-    cache.len()
+    cache.entries.len()
 }
 
-/// Check if a specific file exists in the cache
+/// Check if a specific file exists in the cache (in any namespace)
 #[allow(dead_code)]
 pub fn is_in_cache(file_path: &str) -> bool {
     let cache = TREE_CACHE
         .lock()
         .unwrap_or_else(|poisoned| poisoned.into_inner());
-    cache.contains_key(file_path)
+    cache.entries.keys().any(|(_, path)| path == file_path)
+}
+
+/// Check if a specific file exists in the cache under a namespace
+#[cfg(test)]
+pub fn is_in_namespace_cache(namespace: &str, file_path: &str) -> bool {
+    let cache = TREE_CACHE
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner());
+    cache
+        .entries
+        .contains_key(&(namespace.to_string(), file_path.to_string()))
 }
 
 /// Reset the cache hit counter (for testing)
 #[allow(dead_code)]
 pub fn reset_cache_hit_counter() {
-    let mut hits = CACHE_HITS
+    let mut cache = TREE_CACHE
         .lock()
         .unwrap_or_else(|poisoned| poisoned.into_inner());
-    *hits = 0;
+    cache.reset_counters();
 }
 
 /// Get the current cache hit count (for testing)
 #[allow(dead_code)]
 pub fn get_cache_hit_count() -> usize {
-    let hits = CACHE_HITS
+    let cache = TREE_CACHE
         .lock()
         .unwrap_or_else(|poisoned| poisoned.into_inner());
-    *hits
+    cache.hits
 }
@@ -1,23 +1,15 @@
 use crate::language::tree_cache;
-use std::sync::Mutex;
 use std::thread;
 use std::time::Duration;
 use tree_sitter::Parser;
 
-// Create a test mutex for synchronization
-lazy_static::lazy_static! {
-    static ref TEST_MUTEX: Mutex<()> = Mutex::new(());
-}
-
 #[test]
 fn test_tree_cache_basic() {
     // Use a unique file name for this test to avoid interference with other tests
     let unique_file_name = "test_file_basic_unique.rs";
 
     // Acquire the test mutex to prevent concurrent test execution
-    let _guard = TEST_MUTEX
-        .lock()
-        .unwrap_or_else(|poisoned| poisoned.into_inner());
+    let _guard = tree_cache::acquire_test_mutex();
 
     // Clear the cache before starting the test
     tree_cache::clear_tree_cache();
@@ -100,9 +92,7 @@ fn test_tree_cache_basic() {
 #[test]
 fn test_tree_cache_invalidation() {
     // Acquire the test mutex to prevent concurrent test execution
-    let _guard = TEST_MUTEX
-        .lock()
-        .unwrap_or_else(|poisoned| poisoned.into_inner());
+    let _guard = tree_cache::acquire_test_mutex();
     // Clear the cache before starting the test
     tree_cache::clear_tree_cache();
 
@@ -159,9 +149,7 @@ fn test_tree_cache_invalidation() {
 #[test]
 fn test_tree_cache_clear() {
     // Acquire the test mutex to prevent concurrent test execution
-    let _guard = TEST_MUTEX
-        .lock()
-        .unwrap_or_else(|poisoned| poisoned.into_inner());
+    let _guard = tree_cache::acquire_test_mutex();
     // Clear the cache before starting the test
     tree_cache::clear_tree_cache();
 
@@ -200,9 +188,7 @@ fn test_tree_cache_invalidate_entry() {
     let unique_file_name = "test_file_invalidate_unique.rs";
 
     // Acquire the test mutex to prevent concurrent test execution
-    let _guard = TEST_MUTEX
-        .lock()
-        .unwrap_or_else(|poisoned| poisoned.into_inner());
+    let _guard = tree_cache::acquire_test_mutex();
 
     // Clear the cache before starting the test
     tree_cache::clear_tree_cache();
@@ -259,9 +245,7 @@ fn test_tree_cache_invalidate_entry() {
 #[test]
 fn test_tree_cache_concurrent_access() {
     // Acquire the test mutex to prevent concurrent test execution
-    let _guard = TEST_MUTEX
-        .lock()
-        .unwrap_or_else(|poisoned| poisoned.into_inner());
+    let _guard = tree_cache::acquire_test_mutex();
     // Clear the cache before starting the test
     tree_cache::clear_tree_cache();
 
@@ -308,3 +292,344 @@ fn test_tree_cache_concurrent_access() {
     // Clean up
     tree_cache::clear_tree_cache();
 }
+
+/// Create a Rust parser for the cache tests
+fn rust_parser() -> Parser {
+    let mut parser = Parser::new();
+    parser
+        .set_language(&tree_sitter_rust::LANGUAGE.into())
+        .unwrap();
+    parser
+}
+
+#[test]
+fn test_tree_cache_lru_eviction_by_entries() {
+    let _guard = tree_cache::acquire_test_mutex();
+    tree_cache::clear_tree_cache();
+    let original_config = tree_cache::get_tree_cache_config();
+    tree_cache::set_tree_cache_config(tree_cache::TreeCacheConfig {
+        max_entries: 2,
+        ..original_config.clone()
+    });
+
+    let mut parser = rust_parser();
+    let content = "fn lru() {}";
+
+    tree_cache::get_or_parse_tree("lru_a.rs", content, &mut parser).unwrap();
+    tree_cache::get_or_parse_tree("lru_b.rs", content, &mut parser).unwrap();
+
+    // Touch a so that b becomes the least recently used entry
+    tree_cache::get_or_parse_tree("lru_a.rs", content, &mut parser).unwrap();
+    tree_cache::get_or_parse_tree("lru_c.rs", content, &mut parser).unwrap();
+
+    assert!(tree_cache::is_in_cache("lru_a.rs"));
+    assert!(!tree_cache::is_in_cache("lru_b.rs"));
+    assert!(tree_cache::is_in_cache("lru_c.rs"));
+
+    let stats = tree_cache::get_tree_cache_stats();
+    assert_eq!(stats.entries, 2);
+    assert_eq!(stats.evictions, 1);
+    assert_eq!(stats.hits, 1);
+    assert_eq!(stats.misses, 3);
+
+    tree_cache::set_tree_cache_config(original_config);
+    tree_cache::clear_tree_cache();
+}
+
+#[test]
+fn test_tree_cache_byte_budget() {
+    let _guard = tree_cache::acquire_test_mutex();
+    tree_cache::clear_tree_cache();
+    let original_config = tree_cache::get_tree_cache_config();
+
+    let mut parser = rust_parser();
+    let content = "fn budget(a: i32, b: i32) -> i32 { a + b }";
+
+    tree_cache::get_or_parse_tree("budget_a.rs", content, &mut parser).unwrap();
+    let single_entry_bytes = tree_cache::get_tree_cache_stats().estimated_bytes;
+    assert!(single_entry_bytes >= content.len());
+
+    // Shrinking the budget below two entries evicts on the next insert
+    tree_cache::set_tree_cache_config(tree_cache::TreeCacheConfig {
+        max_bytes: single_entry_bytes * 2 - 1,
+        ..original_config.clone()
+    });
+    tree_cache::get_or_parse_tree("budget_b.rs", content, &mut parser).unwrap();
+
+    let stats = tree_cache::get_tree_cache_stats();
+    assert_eq!(stats.entries, 1);
+    assert!(stats.estimated_bytes <= stats.max_bytes);
+    assert!(!tree_cache::is_in_cache("budget_a.rs"));
+    assert!(tree_cache::is_in_cache("budget_b.rs"));
+
+    tree_cache::set_tree_cache_config(original_config);
+    tree_cache::clear_tree_cache();
+}
+
+#[test]
+fn test_tree_cache_namespaces() {
+    let _guard = tree_cache::acquire_test_mutex();
+    tree_cache::clear_tree_cache();
+
+    let mut parser = rust_parser();
+    let content = "fn namespaced() {}";
+
+    tree_cache::get_or_parse_tree_in_namespace("project_a", "lib.rs", content, &mut parser)
+        .unwrap();
+    tree_cache::get_or_parse_tree_in_namespace("project_b", "lib.rs", content, &mut parser)
+        .unwrap();
+    tree_cache::get_or_parse_tree_in_namespace("project_a", "lib.rs", content, &mut parser)
+        .unwrap();
+
+    let stats = tree_cache::get_tree_cache_stats();
+    assert_eq!(stats.entries, 2);
+    assert_eq!(stats.namespaces["project_a"].entries, 1);
+    assert_eq!(stats.namespaces["project_a"].hits, 1);
+    assert_eq!(stats.namespaces["project_a"].misses, 1);
+    assert_eq!(stats.namespaces["project_b"].hits, 0);
+    assert_eq!(stats.namespaces["project_b"].misses, 1);
+    assert!((stats.hit_rate() - 1.0 / 3.0).abs() < f64::EPSILON);
+
+    // The same path is cached once per project
+    assert!(tree_cache::is_in_namespace_cache("project_a", "lib.rs"));
+    assert!(tree_cache::is_in_namespace_cache("project_b", "lib.rs"));
+
+    // Invalidating a path removes it from every namespace
+    tree_cache::invalidate_cache_entry("lib.rs");
+    assert_eq!(tree_cache::get_cache_size(), 0);
+}
+
+#[test]
+fn test_tree_cache_project_namespaces() {
+    let _guard = tree_cache::acquire_test_mutex();
+    tree_cache::clear_tree_cache();
+
+    let project_a = tempfile::TempDir::new().unwrap();
+    let project_b = tempfile::TempDir::new().unwrap();
+    std::fs::write(project_a.path().join(".probe.toml"), "").unwrap();
+    std::fs::create_dir(project_b.path().join(".git")).unwrap();
+    std::fs::create_dir(project_b.path().join("src")).unwrap();
+    let file_a = project_a
+        .path()
+        .join("lib.rs")
+        .to_string_lossy()
+        .to_string();
+    let file_b = project_b
+        .path()
+        .join("src")
+        .join("lib.rs")
+        .to_string_lossy()
+        .to_string();
+
+    // Trees are grouped by the project the file belongs to
+    let namespace_a = tree_cache::namespace_for_file(&file_a);
+    let namespace_b = tree_cache::namespace_for_file(&file_b);
+    assert_eq!(
+        namespace_a,
+        project_a.path().canonicalize().unwrap().to_string_lossy()
+    );
+    assert_eq!(
+        namespace_b,
+        project_b.path().canonicalize().unwrap().to_string_lossy()
+    );
+    let outside = tempfile::TempDir::new().unwrap();
+    assert_eq!(
+        tree_cache::namespace_for_file(&outside.path().join("lib.rs").to_string_lossy()),
+        tree_cache::DEFAULT_NAMESPACE
+    );
+
+    let mut parser = rust_parser();
+    tree_cache::get_or_parse_tree(&file_a, "fn a() {}", &mut parser).unwrap();
+    tree_cache::get_or_parse_tree(&file_b, "fn b() {}", &mut parser).unwrap();
+    assert!(tree_cache::is_in_namespace_cache(&namespace_a, &file_a));
+    assert!(tree_cache::is_in_namespace_cache(&namespace_b, &file_b));
+    assert_eq!(
+        tree_cache::get_tree_cache_stats().namespaces[&namespace_a].misses,
+        1
+    );
+
+    // Closing a project drops its trees only
+    tree_cache::clear_namespace(&namespace_a);
+    assert!(!tree_cache::is_in_cache(&file_a));
+    assert!(tree_cache::is_in_cache(&file_b));
+
+    tree_cache::clear_tree_cache();
+}
+
+#[test]
+fn test_persisted_block_boundaries() {
+    let _guard = tree_cache::acquire_test_mutex();
+    tree_cache::clear_tree_cache();
+    let original_config = tree_cache::get_tree_cache_config();
+    let persist_dir = tempfile::TempDir::new().unwrap();
+    tree_cache::set_tree_cache_config(tree_cache::TreeCacheConfig {
+        persist_dir: Some(persist_dir.path().to_path_buf()),
+        ..original_config.clone()
+    });
+
+    // Unique content so no other test has a line map for it in memory
+    let content = r#"
+fn persisted_block_boundaries_unique() {
+    let value = 42;
+    println!("{}", value);
+}
+"#;
+    let line_numbers: std::collections::HashSet<usize> = [3].into_iter().collect();
+
+    let first = crate::language::parser::parse_file_for_code_blocks(
+        content,
+        "rs",
+        &line_numbers,
+        true,
+        None,
+    )
+    .unwrap();
+    assert!(tree_cache::get_tree_cache_stats().persisted_writes >= 1);
+
+    // Simulate a cold start: only the persisted boundaries remain
+    crate::language::parser::clear_line_map_cache();
+    tree_cache::clear_tree_cache();
+
+    let second = crate::language::parser::parse_file_for_code_blocks(
+        content,
+        "rs",
+        &line_numbers,
+        true,
+        None,
+    )
+    .unwrap();
+    assert!(tree_cache::get_tree_cache_stats().persisted_hits >= 1);
+    assert_eq!(first, second);
+
+    tree_cache::set_tree_cache_config(original_config);
+    tree_cache::clear_tree_cache();
+}
+
+#[test]
+fn test_persist_blocks_from_concurrent_writers() {
+    let _guard = tree_cache::acquire_test_mutex();
+    let original_config = tree_cache::get_tree_cache_config();
+    let persist_dir = tempfile::TempDir::new().unwrap();
+    tree_cache::set_tree_cache_config(tree_cache::TreeCacheConfig {
+        persist_dir: Some(persist_dir.path().to_path_buf()),
+        ..original_config.clone()
+    });
+
+    // Threads of one process persisting the same key each write a file of their own
+    let blocks: Vec<usize> = (0..10_000).collect();
+    thread::scope(|scope| {
+        for _ in 0..8 {
+            scope.spawn(|| {
+                for _ in 0..10 {
+                    tree_cache::persist_blocks("concurrent_key", "content", &blocks).unwrap();
+                }
+            });
+        }
+    });
+
+    let loaded = tree_cache::load_persisted_blocks::<Vec<usize>>("concurrent_key", "content");
+    assert_eq!(loaded, Some(blocks));
+    let leftovers: Vec<_> = std::fs::read_dir(persist_dir.path())
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name())
+        .filter(|name| name != "concurrent_key.json" && name != ".last_gc")
+        .collect();
+    assert!(leftovers.is_empty(), "{:?}", leftovers);
+
+    tree_cache::set_tree_cache_config(original_config);
+}
+
+#[test]
+fn test_content_hash_is_stable() {
+    // Persisted entries are named after this hash, so it must not depend on the
+    // Rust release: these are the published FNV-1a 64-bit test vectors
+    assert_eq!(tree_cache::compute_content_hash(""), 0xcbf29ce484222325);
+    assert_eq!(tree_cache::compute_content_hash("a"), 0xaf63dc4c8601ec8c);
+    assert_eq!(
+        tree_cache::compute_content_hash("foobar"),
+        0x85944171f73967e8
+    );
+}
+
+#[test]
+fn test_content_digest_is_stable() {
+    // The published FNV-1a 128-bit test vectors
+    assert_eq!(
+        tree_cache::compute_content_digest(""),
+        0x6c62272e07bb014262b821756295c58d
+    );
+    assert_eq!(
+        tree_cache::compute_content_digest("a"),
+        0xd228cb696f1a8caf78912b704e4a8964
+    );
+    assert_eq!(
+        tree_cache::compute_content_digest("foobar"),
+        0x343e1662793c64bf6f0d3597ba446f18
+    );
+}
+
+#[test]
+fn test_persisted_blocks_of_other_content_are_ignored() {
+    let _guard = tree_cache::acquire_test_mutex();
+    let original_config = tree_cache::get_tree_cache_config();
+    let persist_dir = tempfile::TempDir::new().unwrap();
+    tree_cache::set_tree_cache_config(tree_cache::TreeCacheConfig {
+        persist_dir: Some(persist_dir.path().to_path_buf()),
+        ..original_config.clone()
+    });
+
+    tree_cache::persist_blocks("colliding_key", "fn a() {}", &vec![1usize]).unwrap();
+
+    // Same key and length, different content: a hash collision must not be a hit
+    assert_eq!(
+        tree_cache::load_persisted_blocks::<Vec<usize>>("colliding_key", "fn b() {}"),
+        None
+    );
+    assert_eq!(
+        tree_cache::load_persisted_blocks::<Vec<usize>>("colliding_key", "fn a() {}"),
+        Some(vec![1])
+    );
+
+    tree_cache::set_tree_cache_config(original_config);
+}
+
+#[test]
+fn test_persisted_blocks_are_capped() {
+    let _guard = tree_cache::acquire_test_mutex();
+    let original_config = tree_cache::get_tree_cache_config();
+    let persist_dir = tempfile::TempDir::new().unwrap();
+    tree_cache::set_tree_cache_config(tree_cache::TreeCacheConfig {
+        persist_dir: Some(persist_dir.path().to_path_buf()),
+        persist_max_bytes: 1000,
+        ..original_config.clone()
+    });
+
+    let blocks: Vec<usize> = (0..100).collect();
+    let now = std::time::SystemTime::now();
+    for i in 0..5u64 {
+        let key = format!("capped_{}", i);
+        tree_cache::persist_blocks(&key, &key, &blocks).unwrap();
+        let file = std::fs::File::options()
+            .write(true)
+            .open(persist_dir.path().join(format!("{}.json", key)))
+            .unwrap();
+        file.set_modified(now - std::time::Duration::from_secs(100 - i))
+            .unwrap();
+    }
+
+    // The next write is due for GC, which keeps the most recently used entries
+    std::fs::remove_file(persist_dir.path().join(".last_gc")).unwrap();
+    tree_cache::persist_blocks("capped_5", "capped_5", &blocks).unwrap();
+
+    let remaining: Vec<bool> = (0..6)
+        .map(|i| {
+            persist_dir
+                .path()
+                .join(format!("capped_{}.json", i))
+                .exists()
+        })
+        .collect();
+    assert_eq!(remaining, vec![false, false, false, false, true, true]);
+
+    tree_cache::set_tree_cache_config(original_config);
+}
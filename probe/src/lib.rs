@@ -6,21 +6,34 @@
 // Make the library available as `probe` within itself
 extern crate self as probe;
 
+pub mod config;
 pub mod extract;
+pub mod history;
 pub mod language;
+pub mod metrics;
 pub mod models;
+pub mod output_template;
 pub mod path_resolver;
 pub mod query;
 pub mod ranking;
 pub mod search;
+pub mod similarity;
+pub mod todos;
+pub mod watch;
+pub mod workspace;
 
 // Re-export commonly used types for convenience
 pub use extract::{
-    format_and_print_extraction_results, handle_extract, process_file_for_extraction,
+    format_and_print_extraction_results, handle_extract, process_file_for_extraction, ExtractInput,
+    ExtractRequest, ExtractResponse, ExtractTarget, SkippedTarget,
 };
 pub use models::{CodeBlock, LimitedSearchResults, SearchLimits, SearchResult};
 pub use path_resolver::resolve_path;
-pub use query::{format_and_print_query_results, perform_query, AstMatch, QueryOptions};
-pub use search::perform_probe;
+pub use query::{
+    format_and_print_query_results, perform_query, query_results_to_sarif, AstMatch, QueryOptions,
+    QueryRequest, QueryResponse,
+};
+pub use search::{perform_probe, perform_workspace_probe};
+pub use workspace::Workspace;
 
 // Tests are defined in their respective modules with #[cfg(test)]
@@ -1,7 +1,9 @@
 use anyhow::Result;
-use clap::{CommandFactory, Parser as ClapParser};
+use clap::parser::ValueSource;
+use clap::{ArgAction, ArgMatches, CommandFactory, FromArgMatches};
 use colored::*;
-use std::path::PathBuf;
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
 use std::time::Instant;
 
 mod cli;
@@ -11,9 +13,32 @@ mod models;
 mod query;
 mod ranking;
 mod search;
+mod watch;
 
-use cli::{Args, Commands};
-use search::{format_and_print_search_results, perform_probe, SearchOptions};
+use cli::{Args, Commands, ConfigCommands, PromptsCommands, SessionCommands};
+use language::block_context::add_block_context;
+use language::block_metrics::add_block_metrics;
+use models::{LimitedSearchResults, SearchResult};
+use probe::config::{self, ConfigOption, ProbeConfig, CONFIG_FILE_NAME};
+use probe::history::{self, HistoryOptions};
+use probe::metrics::{self, MetricsOptions, MetricsThresholds};
+use probe::output_template::OutputTemplate;
+use probe::similarity::{self, SimilarityOptions};
+use probe::todos::{self, TodosOptions};
+use probe::workspace::Workspace;
+use search::batch::{load_batch_file, perform_batch_probe};
+use search::query::create_query_plan_for_project;
+use search::source_files::{describe_skipped_files, FileLimits};
+use search::{
+    format_and_print_batch_results, format_and_print_search_changes,
+    format_and_print_search_results, format_and_print_search_results_with_template,
+    is_structured_format, perform_probe, perform_workspace_probe, project_vocabulary, QueryMode,
+    SearchOptions,
+};
+use watch::{
+    check_watch_format, diff_results, print_changed_files, reparse_changed_files, watched_paths,
+    FileWatcher,
+};
 
 struct SearchParams {
     pattern: String,
@@ -31,21 +56,65 @@ struct SearchParams {
     allow_tests: bool,
     no_merge: bool,
     merge_threshold: Option<usize>,
+    parent_context: bool,
     dry_run: bool,
     format: String,
     session: Option<String>,
     timeout: u64,
+    page: Option<usize>,
+    explain: bool,
+    metrics: bool,
+    expand_context: Option<usize>,
+    fuzzy: bool,
+    query_mode: QueryMode,
+    file_limits: FileLimits,
+    workspace: Option<Workspace>,
+    template: Option<OutputTemplate>,
+    /// Queries file of `--batch`, run instead of `pattern`
+    batch: Option<PathBuf>,
+    dedupe: bool,
+    /// Rerun the search whenever the searched files change
+    watch: bool,
 }
 
-fn handle_search(params: SearchParams) -> Result<()> {
-    let use_frequency = params.frequency_search;
+/// The query mode selected by `--regex` or `--literal`
+fn query_mode(regex: bool, literal: bool) -> QueryMode {
+    if regex {
+        QueryMode::Regex
+    } else if literal {
+        QueryMode::Literal
+    } else {
+        QueryMode::Terms
+    }
+}
 
-    println!("{} {}", "Pattern:".bold().green(), params.pattern);
-    println!(
-        "{} {}",
-        "Path:".bold().green(),
-        params.paths.first().unwrap().display()
-    );
+/// Load the `--template` of a command, if any
+///
+/// Commands run with a template use the "template" format instead of `format`.
+fn output_template(
+    format: String,
+    template: Option<&str>,
+) -> Result<(String, Option<OutputTemplate>)> {
+    match template {
+        Some(spec) => Ok(("template".to_string(), Some(OutputTemplate::load(spec)?))),
+        None => Ok((format, None)),
+    }
+}
+
+/// Print the pattern, path and non-default options of a search
+fn print_search_header(params: &SearchParams) {
+    match &params.batch {
+        Some(batch) => println!("{} {}", "Batch:".bold().green(), batch.display()),
+        None => println!("{} {}", "Pattern:".bold().green(), params.pattern),
+    }
+    match &params.workspace {
+        Some(workspace) => println!("{} {}", "Workspace:".bold().green(), workspace.describe()),
+        None => println!(
+            "{} {}",
+            "Path:".bold().green(),
+            params.paths.first().unwrap().display()
+        ),
+    }
 
     // Show advanced options if they differ from defaults
     let mut advanced_options = Vec::<String>::new();
@@ -58,7 +127,7 @@ fn handle_search(params: SearchParams) -> Result<()> {
     if params.reranker != "hybrid" {
         advanced_options.push(format!("Reranker: {}", params.reranker));
     }
-    if !use_frequency {
+    if !params.frequency_search {
         advanced_options.push("Frequency search disabled".to_string());
     }
     if let Some(lang) = &params.language {
@@ -73,12 +142,29 @@ fn handle_search(params: SearchParams) -> Result<()> {
     if let Some(threshold) = params.merge_threshold {
         advanced_options.push(format!("Merge threshold: {}", threshold));
     }
+    if params.parent_context {
+        advanced_options.push("Parent context".to_string());
+    }
     if params.dry_run {
         advanced_options.push("Dry run (file names and lines only)".to_string());
     }
     if let Some(session) = &params.session {
         advanced_options.push(format!("Session: {}", session));
     }
+    if let Some(page) = params.page {
+        advanced_options.push(format!("Page: {}", page));
+    }
+    match params.query_mode {
+        QueryMode::Terms => {}
+        QueryMode::Regex => advanced_options.push("Regex".to_string()),
+        QueryMode::Literal => advanced_options.push("Literal".to_string()),
+    }
+    if params.dedupe {
+        advanced_options.push("Deduplicated across queries".to_string());
+    }
+    if params.watch {
+        advanced_options.push("Watch".to_string());
+    }
 
     // Show timeout if it's not the default value of 30 seconds
     if params.timeout != 30 {
@@ -92,20 +178,18 @@ fn handle_search(params: SearchParams) -> Result<()> {
             advanced_options.join(", ")
         );
     }
+}
 
-    let start_time = Instant::now();
-
-    // Create a vector with the pattern
-    let query = vec![params.pattern.clone()];
-
-    let search_options = SearchOptions {
+/// The search options of a search, for the given queries
+fn search_options<'a>(params: &'a SearchParams, queries: &'a [String]) -> SearchOptions<'a> {
+    SearchOptions {
         path: params.paths.first().unwrap(),
-        queries: &query,
+        queries,
         files_only: params.files_only,
         custom_ignores: &params.ignore,
         exclude_filenames: params.exclude_filenames,
         reranker: &params.reranker,
-        frequency_search: use_frequency,
+        frequency_search: params.frequency_search,
         exact: params.exact,
         language: params.language.as_deref(),
         max_results: params.max_results,
@@ -117,27 +201,170 @@ fn handle_search(params: SearchParams) -> Result<()> {
         dry_run: params.dry_run,
         session: params.session.as_deref(),
         timeout: params.timeout,
+        page: params.page,
+        explain: params.explain,
+        fuzzy: params.fuzzy,
+        query_mode: params.query_mode,
+        file_limits: params.file_limits,
+        parent_context: params.parent_context,
+        structured_output: is_structured_format(&params.format),
+    }
+}
+
+/// The query plan used to highlight the results of a search; regex and literal patterns
+/// aren't parsed into one
+fn display_query_plan(
+    params: &SearchParams,
+    queries: &[String],
+) -> Option<crate::search::query::QueryPlan> {
+    if params.query_mode != QueryMode::Terms {
+        return None;
+    }
+
+    // Highlight with the vocabulary the search used; a vocabulary that fails to load
+    // already failed the search
+    let vocabulary = match &params.workspace {
+        Some(workspace) => {
+            let paths: Vec<&Path> = workspace
+                .roots
+                .iter()
+                .map(|root| root.path.as_path())
+                .collect();
+            project_vocabulary::load_for_paths(&paths)
+        }
+        None => project_vocabulary::load_for_paths(&params.paths),
+    }
+    .ok()
+    .flatten();
+
+    if queries.len() > 1 {
+        // Join multiple queries with AND
+        let combined_query = queries.join(" AND ");
+        create_query_plan_for_project(&combined_query, false, vocabulary).ok()
+    } else {
+        create_query_plan_for_project(&queries[0], false, vocabulary).ok()
+    }
+}
+
+/// Run a search, and fill in the metrics and context of its results when asked to
+fn search_results(
+    params: &SearchParams,
+    search_options: &SearchOptions,
+) -> Result<LimitedSearchResults> {
+    let mut limited_results = match &params.workspace {
+        Some(workspace) => perform_workspace_probe(workspace, search_options)?,
+        None => perform_probe(search_options)?,
+    };
+    if params.metrics {
+        add_block_metrics(&mut limited_results.results, params.workspace.as_ref());
+    }
+    if let Some(tokens) = params.expand_context {
+        add_block_context(
+            &mut limited_results.results,
+            params.workspace.as_ref(),
+            tokens,
+        );
+    }
+    Ok(limited_results)
+}
+
+/// Rerun a search whenever `watcher` sees files change, printing how its results changed
+fn watch_search(
+    params: &SearchParams,
+    watcher: &FileWatcher,
+    mut previous: Vec<SearchResult>,
+) -> Result<()> {
+    let structured = is_structured_format(&params.format);
+    if !structured {
+        println!();
+        println!("{}", "Watching for changes (Ctrl+C to stop)".bold());
+    }
+
+    let query = vec![params.pattern.clone()];
+    let search_options = search_options(params, &query);
+    let query_plan = display_query_plan(params, search_options.queries);
+    loop {
+        let changed = watcher.wait_for_changes()?;
+        reparse_changed_files(&changed, params.allow_tests);
+        let results = search_results(params, &search_options)?.results;
+        let changes = diff_results(&previous, &results);
+        // Reruns that change nothing print nothing, so that writing the output to a watched
+        // file doesn't keep triggering reruns
+        if !changes.is_empty() {
+            if !structured {
+                print_changed_files(&changed);
+            }
+            format_and_print_search_changes(
+                &changes,
+                &results,
+                params.dry_run,
+                &params.format,
+                query_plan.as_ref(),
+            )?;
+        }
+        previous = results;
+    }
+}
+
+fn handle_search(params: SearchParams) -> Result<()> {
+    // Watch before the first search, so that no change made during it is missed
+    let watcher = if params.watch {
+        check_watch_format(&params.format)?;
+        Some(FileWatcher::new(&watched_paths(
+            params.workspace.as_ref(),
+            &params.paths,
+        ))?)
+    } else {
+        None
     };
 
-    let limited_results = perform_probe(&search_options)?;
+    // Machine-readable formats only print the results
+    if !is_structured_format(&params.format) {
+        print_search_header(&params);
+    }
+
+    if let Some(batch) = &params.batch {
+        return handle_batch_search(&params, batch);
+    }
+
+    let start_time = Instant::now();
+
+    // Create a vector with the pattern
+    let query = vec![params.pattern.clone()];
+    let search_options = search_options(&params, &query);
+
+    let limited_results = search_results(&params, &search_options)?;
 
     // Calculate search time
     let duration = start_time.elapsed();
 
     // Create the query plan regardless of whether we have results
-    let query_plan = if search_options.queries.len() > 1 {
-        // Join multiple queries with AND
-        let combined_query = search_options.queries.join(" AND ");
-        crate::search::query::create_query_plan(&combined_query, false).ok()
-    } else {
-        crate::search::query::create_query_plan(&search_options.queries[0], false).ok()
-    };
+    let query_plan = display_query_plan(&params, search_options.queries);
+
+    // Templates render every result set, including an empty one
+    if let Some(template) = &params.template {
+        return format_and_print_search_results_with_template(
+            &limited_results.results,
+            search_options.dry_run,
+            template,
+            &params.pattern,
+        );
+    }
 
     if limited_results.results.is_empty() {
-        // For JSON and XML formats, still call format_and_print_search_results
-        if params.format == "json" || params.format == "xml" {
+        // For machine-readable formats, still call format_and_print_search_results
+        if is_structured_format(&params.format) {
+            // Suggestions go to stderr, so that stdout only carries the formatted results
+            for suggestion in &limited_results.suggestions {
+                eprintln!(
+                    "Did you mean: {} (for \"{}\")",
+                    suggestion.suggestions.join(", "),
+                    suggestion.term
+                );
+            }
             format_and_print_search_results(
                 &limited_results.results,
+                &limited_results.skipped_files,
                 search_options.dry_run,
                 &params.format,
                 query_plan.as_ref(),
@@ -145,23 +372,32 @@ fn handle_search(params: SearchParams) -> Result<()> {
         } else {
             // For other formats, print the "No results found" message
             println!("{}", "No results found.".yellow().bold());
+            for suggestion in &limited_results.suggestions {
+                println!(
+                    "{} {} (for \"{}\")",
+                    "Did you mean:".yellow(),
+                    suggestion.suggestions.join(", "),
+                    suggestion.term
+                );
+            }
             println!("Search completed in {:.2?}", duration);
         }
     } else {
-        // For non-JSON/XML formats, print search time
-        if params.format != "json" && params.format != "xml" {
+        // For human-readable formats, print search time
+        if !is_structured_format(&params.format) {
             println!("Search completed in {:.2?}", duration);
             println!();
         }
 
         format_and_print_search_results(
             &limited_results.results,
+            &limited_results.skipped_files,
             search_options.dry_run,
             &params.format,
             query_plan.as_ref(),
         );
 
-        if !limited_results.skipped_files.is_empty() {
+        if !limited_results.skipped_results.is_empty() && !is_structured_format(&params.format) {
             if let Some(limits) = &limited_results.limits_applied {
                 println!();
                 println!("{}", "Limits applied:".yellow().bold());
@@ -179,14 +415,14 @@ fn handle_search(params: SearchParams) -> Result<()> {
                 println!(
                     "{} {}",
                     "Skipped files due to limits:".yellow().bold(),
-                    limited_results.skipped_files.len()
+                    limited_results.skipped_results.len()
                 );
             }
         }
 
         // Display information about cached blocks
         if let Some(cached_skipped) = limited_results.cached_blocks_skipped {
-            if cached_skipped > 0 {
+            if cached_skipped > 0 && !is_structured_format(&params.format) {
                 println!();
                 println!(
                     "{} {}",
@@ -197,17 +433,393 @@ fn handle_search(params: SearchParams) -> Result<()> {
         }
     }
 
+    // Report the files the walker left out (see --max-file-size and --include-generated)
+    if !limited_results.skipped_files.is_empty() && !is_structured_format(&params.format) {
+        println!();
+        println!(
+            "{} {} ({})",
+            "Skipped files:".yellow().bold(),
+            limited_results.skipped_files.len(),
+            describe_skipped_files(&limited_results.skipped_files)
+        );
+    }
+
+    if let Some(watcher) = &watcher {
+        return watch_search(&params, watcher, limited_results.results);
+    }
     Ok(())
 }
 
+/// Run the queries of a `--batch` file and print their results grouped by query
+fn handle_batch_search(params: &SearchParams, batch: &Path) -> Result<()> {
+    if !matches!(
+        params.format.as_str(),
+        "color" | "terminal" | "markdown" | "plain" | "json" | "jsonl"
+    ) {
+        anyhow::bail!(
+            "--batch supports the color, terminal, markdown, plain, json and jsonl formats"
+        );
+    }
+
+    let queries = load_batch_file(batch)?;
+    let start_time = Instant::now();
+    let mut batch_results = perform_batch_probe(
+        &search_options(params, &[]),
+        params.workspace.as_ref(),
+        &queries,
+        params.dedupe,
+    )?;
+    if params.metrics {
+        for entry in batch_results.iter_mut() {
+            add_block_metrics(&mut entry.results.results, params.workspace.as_ref());
+        }
+    }
+    if let Some(tokens) = params.expand_context {
+        for entry in batch_results.iter_mut() {
+            add_block_context(
+                &mut entry.results.results,
+                params.workspace.as_ref(),
+                tokens,
+            );
+        }
+    }
+    let duration = start_time.elapsed();
+
+    if is_structured_format(&params.format) {
+        return format_and_print_batch_results(&batch_results, &params.format);
+    }
+
+    println!(
+        "Searched {} queries in {:.2?}",
+        batch_results.len(),
+        duration
+    );
+    for (i, entry) in batch_results.iter().enumerate() {
+        println!();
+        println!(
+            "{} {}",
+            format!("Query {}:", i + 1).bold().cyan(),
+            entry.query
+        );
+
+        let limited = &entry.results;
+        if limited.results.is_empty() {
+            println!("{}", "No results found.".yellow().bold());
+            for suggestion in &limited.suggestions {
+                println!(
+                    "{} {} (for \"{}\")",
+                    "Did you mean:".yellow(),
+                    suggestion.suggestions.join(", "),
+                    suggestion.term
+                );
+            }
+        } else {
+            let query_plan = display_query_plan(params, std::slice::from_ref(&entry.query));
+            format_and_print_search_results(
+                &limited.results,
+                &limited.skipped_files,
+                params.dry_run,
+                &params.format,
+                query_plan.as_ref(),
+            );
+        }
+        if entry.duplicates_skipped > 0 {
+            println!(
+                "{} {}",
+                "Skipped blocks returned for earlier queries:"
+                    .yellow()
+                    .bold(),
+                entry.duplicates_skipped
+            );
+        }
+    }
+
+    Ok(())
+}
+
+/// The commands a configuration can set options of: those without subcommands, by name
+fn configurable_commands() -> Vec<(String, clap::Command)> {
+    Args::command()
+        .get_subcommands()
+        .filter(|command| !command.has_subcommands())
+        .map(|command| (command.get_name().to_string(), command.clone()))
+        .collect()
+}
+
+/// The flag of a command that a configuration option sets
+fn config_arg<'a>(command: &'a clap::Command, name: &str) -> Option<&'a clap::Arg> {
+    command
+        .get_arguments()
+        .find(|arg| arg.get_long() == Some(name) && !arg.is_global_set())
+}
+
+/// Check that every section of a configuration names a command, and every option a flag
+/// of the command it applies to
+fn validate_config(config: &ProbeConfig) -> Result<()> {
+    let commands = configurable_commands();
+    for section in config.sections() {
+        let accepted: Vec<&clap::Command> = match &section.command {
+            Some(name) => {
+                let command = commands
+                    .iter()
+                    .find(|(command, _)| command == name)
+                    .map(|(_, command)| command)
+                    .ok_or_else(|| {
+                        anyhow::anyhow!(
+                            "Unknown command section {} in {}",
+                            section.label,
+                            config.file.display()
+                        )
+                    })?;
+                vec![command]
+            }
+            None => commands.iter().map(|(_, command)| command).collect(),
+        };
+        for option in &section.options {
+            if !accepted
+                .iter()
+                .any(|command| config_arg(command, option).is_some())
+            {
+                anyhow::bail!(
+                    "Unknown option '{}' in {} of {}",
+                    option,
+                    section.label,
+                    config.file.display()
+                );
+            }
+        }
+    }
+    Ok(())
+}
+
+/// The path a command works on, which its configuration is looked up from
+fn config_start_path(matches: &ArgMatches) -> PathBuf {
+    for id in ["paths", "path", "files"] {
+        if let Ok(Some(mut values)) = matches.try_get_raw(id) {
+            if let Some(value) = values.next() {
+                return PathBuf::from(value);
+            }
+        }
+    }
+    PathBuf::from(".")
+}
+
+/// Whether a flag can be turned off with `--no-<flag>`: flags that are only switched on,
+/// or that take an optional value, like --expand-context
+fn is_negatable(arg: &clap::Arg) -> bool {
+    let optional_value = arg
+        .get_num_args()
+        .is_some_and(|range| range.min_values() == 0 && range.takes_values());
+    (matches!(arg.get_action(), ArgAction::SetTrue) || optional_value)
+        && !arg.is_global_set()
+        && arg.get_long().is_some_and(|long| !long.starts_with("no-"))
+}
+
+/// Add a `--no-<flag>` for every negatable flag of a command, so that a flag turned on
+/// in `.probe.toml` can be turned off on the command line
+fn add_negations(command: clap::Command) -> clap::Command {
+    let negations: Vec<clap::Arg> = command
+        .get_arguments()
+        .filter(|arg| is_negatable(arg))
+        .filter_map(|arg| {
+            let long = arg.get_long()?;
+            let name = format!("no-{}", long);
+            // Keep flags that are already called that
+            if command.get_arguments().any(|a| a.get_long() == Some(&name)) {
+                return None;
+            }
+            Some(
+                clap::Arg::new(name.clone())
+                    .long(name)
+                    .action(ArgAction::SetTrue)
+                    .conflicts_with(arg.get_id().clone())
+                    .help(format!(
+                        "Turn off --{}, even if {} turns it on",
+                        long, CONFIG_FILE_NAME
+                    )),
+            )
+        })
+        .collect();
+    command.args(negations)
+}
+
+/// The command line of probe, with the `--no-<flag>` flags of every command
+fn cli_command() -> clap::Command {
+    let command = add_negations(Args::command());
+    let names: Vec<String> = command
+        .get_subcommands()
+        .map(|subcommand| subcommand.get_name().to_string())
+        .collect();
+    names.into_iter().fold(command, |command, name| {
+        command.mut_subcommand(name, add_negations)
+    })
+}
+
+/// The command-line arguments setting a configuration option that the command line
+/// doesn't set itself; lists are added to those of the command line
+fn config_arguments(
+    command: &clap::Command,
+    matches: &ArgMatches,
+    option: &ConfigOption,
+) -> Result<Vec<OsString>> {
+    let Some(arg) = config_arg(command, &option.name) else {
+        // An option of every command that this command doesn't take
+        return Ok(Vec::new());
+    };
+    let flag = format!("--{}", option.name);
+    let scalar = |value: &toml::Value| match value {
+        toml::Value::String(text) => Ok(text.clone()),
+        toml::Value::Integer(_) | toml::Value::Float(_) | toml::Value::Boolean(_) => {
+            Ok(value.to_string())
+        }
+        _ => Err(anyhow::anyhow!(
+            "Option '{}' ({}) must be a string, number or boolean",
+            option.name,
+            option.sources.join(", ")
+        )),
+    };
+
+    if let ArgAction::Append = arg.get_action() {
+        let values = match &option.value {
+            toml::Value::Array(values) => values.iter().collect(),
+            value => vec![value],
+        };
+        return values
+            .into_iter()
+            .map(|value| Ok(format!("{}={}", flag, scalar(value)?).into()))
+            .collect();
+    }
+    if matches.value_source(arg.get_id().as_str()) == Some(ValueSource::CommandLine) {
+        return Ok(Vec::new());
+    }
+    // The flag was turned off on the command line
+    if is_negatable(arg)
+        && matches!(
+            matches.try_get_one::<bool>(&format!("no-{}", option.name)),
+            Ok(Some(true))
+        )
+    {
+        return Ok(Vec::new());
+    }
+    // Options with an optional value, like --expand-context, can be turned on with true
+    let optional_value = arg
+        .get_num_args()
+        .is_some_and(|range| range.min_values() == 0 && range.takes_values());
+    if let (true, Some(enabled)) = (optional_value, option.value.as_bool()) {
+        return Ok(if enabled {
+            vec![flag.into()]
+        } else {
+            Vec::new()
+        });
+    }
+    if let ArgAction::SetTrue = arg.get_action() {
+        return match option.value.as_bool() {
+            Some(true) => Ok(vec![flag.into()]),
+            Some(false) => Ok(Vec::new()),
+            None => anyhow::bail!(
+                "Option '{}' ({}) must be true or false",
+                option.name,
+                option.sources.join(", ")
+            ),
+        };
+    }
+    Ok(vec![format!("{}={}", flag, scalar(&option.value)?).into()])
+}
+
+/// Parse the command line, adding the options of the project's `.probe.toml` that the
+/// command line doesn't set
+fn parse_args() -> Result<Args> {
+    let argv: Vec<OsString> = std::env::args_os().collect();
+    let matches = cli_command().get_matches_from(&argv);
+    let (name, command, command_matches) = match matches.subcommand() {
+        Some((name, command_matches)) => {
+            let command = Args::command().find_subcommand(name).cloned().unwrap();
+            // Commands with subcommands of their own take no options from the configuration
+            if command.has_subcommands() {
+                return Ok(Args::from_arg_matches(&matches)?);
+            }
+            (name.to_string(), command, command_matches)
+        }
+        // Without a subcommand, probe searches
+        None => ("search".to_string(), Args::command(), &matches),
+    };
+
+    let profile = matches.get_one::<String>("profile");
+    let start = config_start_path(command_matches);
+    let Some(config) = ProbeConfig::discover(&start)? else {
+        if let Some(profile) = profile {
+            anyhow::bail!(
+                "No {} found for profile '{}' from {}",
+                CONFIG_FILE_NAME,
+                profile,
+                start.display()
+            );
+        }
+        return Ok(Args::from_arg_matches(&matches)?);
+    };
+    validate_config(&config)?;
+
+    let mut extra = Vec::new();
+    for option in config.options_for(&name, profile.map(String::as_str))? {
+        extra.extend(config_arguments(&command, command_matches, &option)?);
+    }
+    if std::env::var("DEBUG").unwrap_or_default() == "1" {
+        println!("DEBUG: Options from {}: {:?}", config.file.display(), extra);
+    }
+
+    // The options go before a `--` that ends the options of the command line
+    let end = argv
+        .iter()
+        .position(|arg| arg == "--")
+        .unwrap_or(argv.len());
+    let mut argv = argv;
+    argv.splice(end..end, extra);
+    let matches = cli_command().get_matches_from(argv);
+    Ok(Args::from_arg_matches(&matches)?)
+}
+
+/// Handle `probe config show`: the effective options of every command
+fn handle_config_show(path: &Path, profile: Option<&str>, format: &str) -> Result<()> {
+    let Some(config) = ProbeConfig::discover(path)? else {
+        if format == "json" {
+            println!("{}", serde_json::json!({ "file": null }));
+        } else {
+            println!(
+                "{}",
+                format!("No {} found from {}", CONFIG_FILE_NAME, path.display())
+                    .yellow()
+                    .bold()
+            );
+        }
+        return Ok(());
+    };
+    validate_config(&config)?;
+
+    let mut commands = Vec::new();
+    for (name, command) in configurable_commands() {
+        let options: Vec<ConfigOption> = config
+            .options_for(&name, profile)?
+            .into_iter()
+            .filter(|option| config_arg(&command, &option.name).is_some())
+            .collect();
+        if !options.is_empty() {
+            commands.push((name, options));
+        }
+    }
+    config::format_and_print_config(&config, profile, &commands, format)
+}
+
 #[tokio::main]
 async fn main() -> Result<()> {
-    let args = Args::parse();
+    let args = parse_args()?;
+
+    // Load the workspace up front so that an invalid file is reported before any work starts
+    let workspace = args.workspace.as_deref().map(Workspace::load).transpose()?;
 
     match args.command {
         // When no subcommand provided and no pattern, show help
         None if args.pattern.is_none() || args.pattern.as_ref().unwrap().is_empty() => {
-            Args::command().print_help()?;
+            cli_command().print_help()?;
             return Ok(());
         }
         // When no subcommand but pattern is provided, fallback to search mode
@@ -222,6 +834,7 @@ async fn main() -> Result<()> {
                 args.paths
             };
 
+            let (format, template) = output_template(args.format, args.template.as_deref())?;
             handle_search(SearchParams {
                 pattern,
                 paths,
@@ -238,10 +851,26 @@ async fn main() -> Result<()> {
                 allow_tests: args.allow_tests,
                 no_merge: args.no_merge,
                 merge_threshold: args.merge_threshold,
+                parent_context: args.parent_context,
                 dry_run: args.dry_run,
-                format: args.format,
+                format,
                 session: args.session,
                 timeout: args.timeout,
+                page: args.page,
+                explain: args.explain,
+                metrics: args.metrics,
+                expand_context: args.expand_context,
+                fuzzy: args.fuzzy,
+                query_mode: query_mode(args.regex, args.literal),
+                file_limits: FileLimits {
+                    max_file_size: args.max_file_size,
+                    skip_generated: !args.include_generated,
+                },
+                workspace,
+                template,
+                batch: None,
+                dedupe: false,
+                watch: false,
             })?
         }
         Some(Commands::Search {
@@ -260,36 +889,81 @@ async fn main() -> Result<()> {
             allow_tests,
             no_merge,
             merge_threshold,
+            parent_context,
             dry_run,
             format,
+            template,
             session,
             timeout,
-        }) => handle_search(SearchParams {
-            pattern,
-            paths,
-            files_only,
-            ignore,
-            exclude_filenames,
-            reranker,
-            frequency_search,
-            exact,
-            language,
-            max_results,
-            max_bytes,
-            max_tokens,
-            allow_tests,
-            no_merge,
-            merge_threshold,
-            dry_run,
-            format,
-            session,
-            timeout,
-        })?,
+            page,
+            explain,
+            metrics,
+            expand_context,
+            fuzzy,
+            regex,
+            literal,
+            max_file_size,
+            include_generated,
+            batch,
+            dedupe,
+            watch,
+        }) => {
+            let (format, template) = output_template(format, template.as_deref())?;
+            // A batch has no pattern, so a first positional argument is a path
+            let (pattern, paths) = match (pattern, &batch) {
+                (Some(path), Some(_)) if paths == [PathBuf::from(".")] => {
+                    (String::new(), vec![PathBuf::from(path)])
+                }
+                (Some(path), Some(_)) => (
+                    String::new(),
+                    std::iter::once(PathBuf::from(path)).chain(paths).collect(),
+                ),
+                (pattern, _) => (pattern.unwrap_or_default(), paths),
+            };
+            handle_search(SearchParams {
+                pattern,
+                paths,
+                files_only,
+                ignore,
+                exclude_filenames,
+                reranker,
+                frequency_search,
+                exact,
+                language,
+                max_results,
+                max_bytes,
+                max_tokens,
+                allow_tests,
+                no_merge,
+                merge_threshold,
+                parent_context,
+                dry_run,
+                format,
+                session,
+                timeout,
+                page,
+                explain,
+                metrics,
+                expand_context,
+                fuzzy,
+                query_mode: query_mode(regex, literal),
+                file_limits: FileLimits {
+                    max_file_size,
+                    skip_generated: !include_generated,
+                },
+                workspace,
+                template,
+                batch,
+                dedupe,
+                watch,
+            })?
+        }
         Some(Commands::Extract {
             files,
             ignore,
             context_lines,
             format,
+            template,
             from_clipboard,
             input_file,
             to_clipboard,
@@ -297,28 +971,47 @@ async fn main() -> Result<()> {
             diff,
             allow_tests,
             keep_input,
+            metrics,
+            expand_context,
             prompt,
             instructions,
-        }) => extract::handle_extract(extract::ExtractOptions {
-            files,
-            custom_ignores: ignore,
-            context_lines,
-            format,
-            from_clipboard,
-            input_file,
-            to_clipboard,
-            dry_run,
-            diff,
-            allow_tests,
-            keep_input,
-            prompt: prompt.map(|p| {
+        }) => {
+            let prompt = prompt.map(|p| {
                 crate::extract::PromptTemplate::from_str(&p).unwrap_or_else(|e| {
                     eprintln!("Warning: {}", e);
                     crate::extract::PromptTemplate::Engineer
                 })
-            }),
-            instructions,
-        })?,
+            });
+            // Without --format, the prompt template may choose one for its model
+            let format = format
+                .or_else(|| {
+                    prompt
+                        .as_ref()
+                        .and_then(|p| p.load().ok())
+                        .and_then(|p| p.preferred_format())
+                })
+                .unwrap_or_else(|| "color".to_string());
+            let (format, template) = output_template(format, template.as_deref())?;
+            extract::handle_extract(extract::ExtractOptions {
+                files,
+                custom_ignores: ignore,
+                context_lines,
+                format,
+                from_clipboard,
+                input_file,
+                to_clipboard,
+                dry_run,
+                diff,
+                allow_tests,
+                keep_input,
+                prompt,
+                instructions,
+                workspace,
+                template,
+                metrics,
+                expand_context,
+            })?
+        }
         Some(Commands::Query {
             pattern,
             path,
@@ -327,28 +1020,162 @@ async fn main() -> Result<()> {
             allow_tests,
             max_results,
             format,
-        }) => query::handle_query(
-            &pattern,
-            &path,
-            language.as_deref().map(|lang| {
-                // Normalize language aliases
-                match lang.to_lowercase().as_str() {
-                    "rs" => "rust",
-                    "js" | "jsx" => "javascript",
-                    "ts" | "tsx" => "typescript",
-                    "py" => "python",
-                    "h" => "c",
-                    "cc" | "cxx" | "hpp" | "hxx" => "cpp",
-                    "rb" => "ruby",
-                    "cs" => "csharp",
-                    _ => lang, // Return the original language if no alias is found
-                }
-            }),
-            &ignore,
+            template,
+            watch,
+        }) => {
+            let (format, template) = output_template(format, template.as_deref())?;
+            query::handle_query(
+                &pattern,
+                &path,
+                workspace.as_ref(),
+                language.as_deref().map(|lang| {
+                    // Normalize language aliases
+                    match lang.to_lowercase().as_str() {
+                        "rs" => "rust",
+                        "js" | "jsx" => "javascript",
+                        "ts" | "tsx" => "typescript",
+                        "py" => "python",
+                        "h" => "c",
+                        "cc" | "cxx" | "hpp" | "hxx" => "cpp",
+                        "rb" => "ruby",
+                        "cs" => "csharp",
+                        _ => lang, // Return the original language if no alias is found
+                    }
+                }),
+                &ignore,
+                allow_tests,
+                max_results,
+                &format,
+                template.as_ref(),
+                watch,
+            )?
+        }
+        Some(Commands::History {
+            pattern,
+            path,
+            max_results,
+            max_commits,
+            since,
+            format,
+        }) => history::handle_history(
+            &HistoryOptions {
+                path: &path,
+                query: &pattern,
+                max_results,
+                max_commits: (max_commits > 0).then_some(max_commits),
+                since: since.as_deref(),
+            },
+            &format,
+        )?,
+        Some(Commands::Similar {
+            target,
+            path,
+            threshold,
+            min_lines,
+            max_results,
+            ignore,
             allow_tests,
+            format,
+        }) => similarity::handle_similar(
+            &target,
+            &SimilarityOptions {
+                path: &path,
+                threshold,
+                min_lines,
+                max_results,
+                ignore: &ignore,
+                allow_tests,
+            },
+            &format,
+        )?,
+        Some(Commands::Dupes {
+            path,
+            threshold,
+            min_lines,
             max_results,
+            ignore,
+            allow_tests,
+            format,
+        }) => similarity::handle_dupes(
+            &SimilarityOptions {
+                path: &path,
+                threshold,
+                min_lines,
+                max_results,
+                ignore: &ignore,
+                allow_tests,
+            },
             &format,
         )?,
+        Some(Commands::Metrics {
+            path,
+            max_complexity,
+            max_nesting,
+            max_params,
+            max_lines,
+            all,
+            max_results,
+            ignore,
+            allow_tests,
+            format,
+        }) => metrics::handle_metrics(
+            &MetricsOptions {
+                path: &path,
+                thresholds: MetricsThresholds {
+                    complexity: max_complexity,
+                    nesting: max_nesting,
+                    parameters: max_params,
+                    lines: max_lines,
+                },
+                all,
+                max_results,
+                ignore: &ignore,
+                allow_tests,
+            },
+            &format,
+        )?,
+        Some(Commands::Todos {
+            path,
+            tags,
+            no_blame,
+            ignore,
+            allow_tests,
+            format,
+        }) => todos::handle_todos(
+            &TodosOptions {
+                path: &path,
+                tags: &tags,
+                blame: !no_blame,
+                ignore: &ignore,
+                allow_tests,
+            },
+            &format,
+        )?,
+        Some(Commands::Prompts { command }) => match command {
+            PromptsCommands::List { format } => {
+                extract::prompt_commands::handle_prompts_list(&format)?
+            }
+            PromptsCommands::Show { name, format } => {
+                extract::prompt_commands::handle_prompts_show(&name, &format)?
+            }
+        },
+        Some(Commands::Session { command }) => match command {
+            SessionCommands::List { format } => {
+                search::session_commands::handle_session_list(&format)?
+            }
+            SessionCommands::Show { session_id, format } => {
+                search::session_commands::handle_session_show(&session_id, &format)?
+            }
+            SessionCommands::Clear {
+                session_id,
+                expired,
+            } => search::session_commands::handle_session_clear(session_id.as_deref(), expired)?,
+        },
+        Some(Commands::Config { command }) => match command {
+            ConfigCommands::Show { path, format } => {
+                handle_config_show(&path, args.profile.as_deref(), &format)?
+            }
+        },
     }
 
     Ok(())
@@ -0,0 +1,285 @@
+//! The `probe metrics` report: the size and complexity of every function of a project,
+//! flagging the functions over configurable thresholds.
+
+use anyhow::Result;
+use colored::*;
+use rayon::prelude::*;
+use serde::Serialize;
+use std::collections::HashSet;
+use std::path::Path;
+use std::time::Instant;
+
+use crate::language::block_metrics::compute_block_metrics;
+use crate::language::parser::{block_definition_node, node_symbol_name, parse_source};
+use crate::models::CodeMetrics;
+use crate::search::file_list_cache;
+use crate::search::source_files::read_source_file;
+use crate::search::{is_structured_format, json_line};
+use crate::similarity::collect_code_blocks;
+
+/// Limits above which a function is reported
+#[derive(Debug, Clone, Copy)]
+pub struct MetricsThresholds {
+    pub complexity: usize,
+    pub nesting: usize,
+    pub parameters: usize,
+    pub lines: usize,
+}
+
+/// Options of the metrics report
+pub struct MetricsOptions<'a> {
+    /// Directory or file whose functions are measured
+    pub path: &'a Path,
+    pub thresholds: MetricsThresholds,
+    /// Report every function, not only those over a threshold
+    pub all: bool,
+    /// Maximum number of functions to report
+    pub max_results: usize,
+    /// Custom patterns of files to ignore
+    pub ignore: &'a [String],
+    /// Whether test files and test functions are measured
+    pub allow_tests: bool,
+}
+
+/// The metrics of one function
+#[derive(Debug, Clone, Serialize)]
+pub struct FunctionMetrics {
+    pub file: String,
+    pub lines: (usize, usize),
+    pub node_type: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub symbol: Option<String>,
+    pub metrics: CodeMetrics,
+    /// The thresholds the function is over: "complexity", "nesting", "parameters", "lines"
+    pub exceeds: Vec<&'static str>,
+}
+
+/// The result of a metrics report
+#[derive(Debug, Clone)]
+pub struct MetricsReport {
+    /// Reported functions, the most complex first
+    pub functions: Vec<FunctionMetrics>,
+    /// Number of functions measured
+    pub analyzed: usize,
+    /// Number of functions over a threshold
+    pub over_threshold: usize,
+}
+
+impl MetricsThresholds {
+    /// The thresholds the metrics are over
+    pub fn exceeded(&self, metrics: &CodeMetrics) -> Vec<&'static str> {
+        let mut exceeds = Vec::new();
+        if metrics.cyclomatic_complexity > self.complexity {
+            exceeds.push("complexity");
+        }
+        if metrics.max_nesting > self.nesting {
+            exceeds.push("nesting");
+        }
+        if metrics.parameters.unwrap_or(0) > self.parameters {
+            exceeds.push("parameters");
+        }
+        if metrics.code_lines > self.lines {
+            exceeds.push("lines");
+        }
+        exceeds
+    }
+}
+
+/// The metrics of the functions of a file
+fn file_functions(path: &Path, options: &MetricsOptions) -> Vec<FunctionMetrics> {
+    let extension = path.extension().and_then(|e| e.to_str()).unwrap_or("");
+    let Ok(content) = read_source_file(path) else {
+        return Vec::new();
+    };
+    let Ok(tree) = parse_source(&content, extension) else {
+        return Vec::new();
+    };
+    let file_lines: Vec<&str> = content.lines().collect();
+    let blocks = collect_code_blocks(&tree, &content, extension, options.allow_tests);
+
+    let file = path.to_string_lossy().to_string();
+    let mut seen = HashSet::new();
+    blocks
+        .into_iter()
+        .filter(|block| block.end_row < file_lines.len())
+        .filter(|block| seen.insert((block.start_row, block.end_row)))
+        .filter_map(|block| {
+            let lines = (block.start_row + 1, block.end_row + 1);
+            let metrics = compute_block_metrics(&tree, content.as_bytes(), lines);
+            // Only functions have parameters
+            metrics.parameters?;
+            Some(FunctionMetrics {
+                file: file.clone(),
+                lines,
+                node_type: block.node_type,
+                symbol: block_definition_node(&tree, extension, block.start_row, block.end_row)
+                    .and_then(|node| node_symbol_name(node, content.as_bytes())),
+                exceeds: options.thresholds.exceeded(&metrics),
+                metrics,
+            })
+        })
+        .collect()
+}
+
+/// Measure the functions under a path
+pub fn measure_functions(options: &MetricsOptions) -> Result<MetricsReport> {
+    if !options.path.exists() {
+        anyhow::bail!("Path does not exist: {}", options.path.display());
+    }
+    let files = if options.path.is_file() {
+        vec![options.path.to_path_buf()]
+    } else {
+        file_list_cache::get_file_list(options.path, options.allow_tests, options.ignore)?
+            .files
+            .clone()
+    };
+
+    let functions: Vec<FunctionMetrics> = files
+        .par_iter()
+        .flat_map_iter(|file| file_functions(file, options))
+        .collect();
+    let analyzed = functions.len();
+    let mut functions: Vec<FunctionMetrics> = functions
+        .into_iter()
+        .filter(|function| options.all || !function.exceeds.is_empty())
+        .collect();
+    let over_threshold = functions.iter().filter(|f| !f.exceeds.is_empty()).count();
+
+    functions.sort_by(|a, b| {
+        b.metrics
+            .cyclomatic_complexity
+            .cmp(&a.metrics.cyclomatic_complexity)
+            .then_with(|| b.metrics.max_nesting.cmp(&a.metrics.max_nesting))
+            .then_with(|| b.metrics.code_lines.cmp(&a.metrics.code_lines))
+            .then_with(|| a.file.cmp(&b.file))
+            .then_with(|| a.lines.cmp(&b.lines))
+    });
+    functions.truncate(options.max_results);
+
+    Ok(MetricsReport {
+        functions,
+        analyzed,
+        over_threshold,
+    })
+}
+
+fn function_location(function: &FunctionMetrics) -> String {
+    let mut location = format!(
+        "{}:{}-{}",
+        function.file, function.lines.0, function.lines.1
+    );
+    if let Some(symbol) = &function.symbol {
+        location.push_str(&format!(" {}", symbol));
+    }
+    location
+}
+
+/// Format and print a metrics report
+pub fn format_and_print_metrics(report: &MetricsReport, format: &str) -> Result<()> {
+    let summary = serde_json::json!({
+        "count": report.functions.len(),
+        "analyzed": report.analyzed,
+        "over_threshold": report.over_threshold,
+    });
+
+    match format {
+        "json" => {
+            let wrapper = serde_json::json!({
+                "results": report.functions,
+                "summary": summary,
+            });
+            println!("{}", serde_json::to_string_pretty(&wrapper)?);
+        }
+        "jsonl" => {
+            for function in &report.functions {
+                println!("{}", json_line("metrics", function)?);
+            }
+            println!(
+                "{}",
+                json_line(
+                    "summary",
+                    &serde_json::json!({ "count": report.functions.len() })
+                )?
+            );
+        }
+        "markdown" => {
+            println!("| Complexity | Nesting | Parameters | Code lines | Function |");
+            println!("|---:|---:|---:|---:|---|");
+            for function in &report.functions {
+                let metrics = &function.metrics;
+                println!(
+                    "| {} | {} | {} | {} | `{}` |",
+                    metrics.cyclomatic_complexity,
+                    metrics.max_nesting,
+                    metrics.parameters.unwrap_or(0),
+                    metrics.code_lines,
+                    function_location(function)
+                );
+            }
+        }
+        _ => {
+            let use_color = format == "color";
+            let header = format!(
+                "{:>10} {:>7} {:>6} {:>6}  Function",
+                "Complexity", "Nesting", "Params", "Lines"
+            );
+            if use_color {
+                println!("{}", header.bold());
+            } else {
+                println!("{}", header);
+            }
+            for function in &report.functions {
+                let metrics = &function.metrics;
+                // Values over a threshold stand out in color output
+                let column = |value: usize, width: usize, threshold: &str| {
+                    let text = format!("{:>width$}", value, width = width);
+                    if use_color && function.exceeds.contains(&threshold) {
+                        text.red().bold().to_string()
+                    } else {
+                        text
+                    }
+                };
+                let location = function_location(function);
+                println!(
+                    "{} {} {} {}  {}",
+                    column(metrics.cyclomatic_complexity, 10, "complexity"),
+                    column(metrics.max_nesting, 7, "nesting"),
+                    column(metrics.parameters.unwrap_or(0), 6, "parameters"),
+                    column(metrics.code_lines, 6, "lines"),
+                    if use_color {
+                        location.cyan().to_string()
+                    } else {
+                        location
+                    }
+                );
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Handle the metrics command
+pub fn handle_metrics(options: &MetricsOptions, format: &str) -> Result<()> {
+    let start_time = Instant::now();
+    let report = measure_functions(options)?;
+    let duration = start_time.elapsed();
+
+    if !is_structured_format(format) {
+        let thresholds = &options.thresholds;
+        println!(
+            "Measured {} functions in {:.2?}; {} over the thresholds (complexity {}, nesting {}, parameters {}, code lines {})",
+            report.analyzed,
+            duration,
+            report.over_threshold,
+            thresholds.complexity,
+            thresholds.nesting,
+            thresholds.parameters,
+            thresholds.lines
+        );
+        if report.functions.is_empty() {
+            return Ok(());
+        }
+        println!();
+    }
+    format_and_print_metrics(&report, format)
+}
@@ -1,10 +1,24 @@
+use crate::search::source_files::SkippedFile;
+
 // Structure to hold both limited search results and skipped files
 #[derive(Debug)]
 pub struct LimitedSearchResults {
     pub results: Vec<SearchResult>,
-    pub skipped_files: Vec<SearchResult>,
+    /// Results left out by the result, byte and token limits
+    pub skipped_results: Vec<SearchResult>,
+    /// Files the walker left out: too large, binary, minified or generated
+    pub skipped_files: Vec<SkippedFile>,
     pub limits_applied: Option<SearchLimits>,
     pub cached_blocks_skipped: Option<usize>,
+    /// "Did you mean" corrections for query terms, filled in when nothing was found
+    pub suggestions: Vec<TermSuggestion>,
+}
+
+/// Suggested corrections for a query term that occurs in none of the searched files
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TermSuggestion {
+    pub term: String,
+    pub suggestions: Vec<String>,
 }
 
 // Structure to track which limits were applied
@@ -21,7 +35,7 @@ pub struct SearchLimits {
 }
 
 // Structure to hold search results
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Default, serde::Serialize)]
 pub struct SearchResult {
     pub file: String,
     pub lines: (usize, usize),
@@ -67,6 +81,176 @@ pub struct SearchResult {
     /// Tokenized version of the code block with filename prepended
     #[allow(dead_code)]
     pub tokenized_content: Option<Vec<String>>,
+    /// Name of the workspace root the file belongs to; `file` is then relative to that root
+    pub workspace_root: Option<String>,
+    /// Set when the session has seen this block before, but its content changed since then
+    pub changed_since_seen: Option<bool>,
+    /// Why the result ranked where it did, filled in by `--explain`
+    pub explanation: Option<RankingExplanation>,
+    /// Where the search patterns matched in `code`
+    pub match_ranges: Option<Vec<MatchRange>>,
+    /// Size and complexity of the block, filled in by `--metrics`
+    pub metrics: Option<CodeMetrics>,
+    /// The block this one is nested in, e.g. the `impl` of a method
+    pub parent_block: Option<ParentBlock>,
+    /// Imports and referenced definitions, filled in by `--expand-context`
+    pub context_blocks: Option<Vec<ContextBlock>>,
+    /// Symbol path of the definition the block holds, e.g. "Cart.total", from its syntax tree
+    pub symbol: Option<String>,
+    /// Position of the definition among those of its file with the same symbol path, e.g. 2
+    /// for the second overload
+    pub symbol_occurrence: Option<usize>,
+}
+
+/// What a context block of a result is (see `language::block_context`)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ContextKind {
+    Imports,
+    Type,
+    Constant,
+    Function,
+}
+
+impl std::fmt::Display for ContextKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            ContextKind::Imports => "imports",
+            ContextKind::Type => "type",
+            ContextKind::Constant => "constant",
+            ContextKind::Function => "function",
+        })
+    }
+}
+
+/// The import section of a result's file, or a definition the result references
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
+pub struct ContextBlock {
+    pub kind: ContextKind,
+    /// Name the result references the definition by; None for imports
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    /// File of the block, shown like the file of the result
+    pub file: String,
+    /// 1-based start and end lines
+    pub lines: (usize, usize),
+    pub code: String,
+}
+
+impl ContextBlock {
+    /// One-line description, e.g. "type Cart (src/cart.rs:5-9)"
+    pub fn heading(&self) -> String {
+        match &self.name {
+            Some(name) => format!(
+                "{} {} ({}:{}-{})",
+                self.kind, name, self.file, self.lines.0, self.lines.1
+            ),
+            None => format!(
+                "{} ({}:{}-{})",
+                self.kind, self.file, self.lines.0, self.lines.1
+            ),
+        }
+    }
+}
+
+/// The enclosing block of a search result, used by `merge_ranked_blocks` to keep
+/// siblings together and to show where a nested block lives
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
+pub struct ParentBlock {
+    pub node_type: String,
+    /// 1-based start and end lines
+    pub lines: (usize, usize),
+}
+
+/// Size and complexity metrics of a code block (see `language::block_metrics`)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize)]
+pub struct CodeMetrics {
+    /// Number of lines of the block
+    pub lines: usize,
+    /// Number of lines with code, leaving out blank and comment-only lines
+    pub code_lines: usize,
+    /// One plus the number of branches, loops, cases and `&&`/`||` operators
+    pub cyclomatic_complexity: usize,
+    /// Deepest nesting of control flow statements
+    pub max_nesting: usize,
+    /// Number of parameters, when the block is a function
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub parameters: Option<usize>,
+}
+
+impl std::fmt::Display for CodeMetrics {
+    /// "complexity 6, nesting 2, 3 parameters, 18 lines (15 code)"
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "complexity {}, nesting {}, ",
+            self.cyclomatic_complexity, self.max_nesting
+        )?;
+        if let Some(parameters) = self.parameters {
+            write!(f, "{} parameters, ", parameters)?;
+        }
+        write!(f, "{} lines ({} code)", self.lines, self.code_lines)
+    }
+}
+
+/// The position of one pattern match within a result's code
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
+pub struct MatchRange {
+    /// Byte offset of the match in the result's code
+    pub start_byte: usize,
+    /// Byte offset just past the match
+    pub end_byte: usize,
+    /// Line of the file the match is on (1-based)
+    pub line: usize,
+    /// Character column the match starts at (1-based)
+    pub column_start: usize,
+    /// Character column just past the match
+    pub column_end: usize,
+}
+
+/// Breakdown of a result's BM25 ranking, reported by `--explain`
+#[derive(Debug, Clone, Default, serde::Serialize)]
+pub struct RankingExplanation {
+    /// The parsed query the result was ranked against
+    pub query: String,
+    /// One entry per query term
+    pub terms: Vec<TermExplanation>,
+    /// Total BM25 score, or None when the block doesn't satisfy the query
+    pub bm25_score: Option<f64>,
+    /// Part of the BM25 score contributed by terms in the file name
+    pub filename_boost: f64,
+    /// Whether the file was found by matching its name
+    pub matched_by_filename: bool,
+    /// Blocks that were merged into this one after ranking
+    pub merged_blocks: Vec<MergedBlock>,
+}
+
+/// A single query term's contribution to a result's BM25 score
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct TermExplanation {
+    /// The stemmed term as it appears in the index
+    pub term: String,
+    /// The words in the block that stem to this term
+    pub raw_matches: Vec<String>,
+    /// "required", "excluded" or "optional", or "synonym" or "fuzzy" for a synonym or
+    /// fuzzy variant of the term before it
+    pub role: &'static str,
+    /// Occurrences in the block, including the file name
+    pub frequency: usize,
+    /// Occurrences in the file name
+    pub filename_frequency: usize,
+    /// Inverse document frequency over all candidate blocks
+    pub idf: f64,
+    /// BM25 contribution of this term, reduced for fuzzy variants
+    pub bm25: f64,
+}
+
+/// A block merged into a ranked result by `merge_ranked_blocks`
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct MergedBlock {
+    pub lines: (usize, usize),
+    pub rank: Option<usize>,
+    pub score: Option<f64>,
 }
 
 // Structure to hold node information for merging
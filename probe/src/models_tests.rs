@@ -11,16 +11,7 @@ mod tests {
             lines: (1, 10),
             node_type: "function".to_string(),
             code: "fn test() {}".to_string(),
-            matched_by_filename: None,
-            rank: None,
-            score: None,
-            tfidf_score: None,
-            bm25_score: None,
-            tfidf_rank: None,
-            bm25_rank: None,
-            file_unique_terms: None,
-            file_total_matches: None,
-            file_match_rank: None,
+            ..Default::default()
         };
         
         assert_eq!(result.file, "test.rs");
@@ -65,7 +56,6 @@ mod tests {
                 lines: (1, 10),
                 node_type: "function".to_string(),
                 code: "fn test1() {}".to_string(),
-                matched_by_filename: None,
                 rank: Some(1),
                 score: Some(0.9),
                 tfidf_score: Some(0.9),
@@ -75,13 +65,13 @@ mod tests {
                 file_unique_terms: Some(2),
                 file_total_matches: Some(5),
                 file_match_rank: Some(1),
+                ..Default::default()
             },
             SearchResult {
                 file: "test2.rs".to_string(),
                 lines: (1, 10),
                 node_type: "function".to_string(),
                 code: "fn test2() {}".to_string(),
-                matched_by_filename: None,
                 rank: Some(2),
                 score: Some(0.8),
                 tfidf_score: Some(0.8),
@@ -91,6 +81,7 @@ mod tests {
                 file_unique_terms: Some(1),
                 file_total_matches: Some(3),
                 file_match_rank: Some(2),
+                ..Default::default()
             },
         ];
         
@@ -101,7 +92,6 @@ mod tests {
                 lines: (1, 10),
                 node_type: "function".to_string(),
                 code: "fn test3() {}".to_string(),
-                matched_by_filename: None,
                 rank: Some(3),
                 score: Some(0.7),
                 tfidf_score: Some(0.7),
@@ -111,6 +101,7 @@ mod tests {
                 file_unique_terms: Some(1),
                 file_total_matches: Some(2),
                 file_match_rank: Some(3),
+                ..Default::default()
             },
         ];
         
@@ -126,13 +117,14 @@ mod tests {
         // Create the limited search results
         let limited_results = LimitedSearchResults {
             results: results.clone(),
-            skipped_files: skipped_files.clone(),
+            skipped_results: skipped_files.clone(),
+            skipped_files: Vec::new(),
             limits_applied: Some(limits),
         };
         
         // Check the contents
         assert_eq!(limited_results.results.len(), 2);
-        assert_eq!(limited_results.skipped_files.len(), 1);
+        assert_eq!(limited_results.skipped_results.len(), 1);
         
         // Check that the limits are correctly stored
         let limits = limited_results.limits_applied.unwrap();
@@ -0,0 +1,270 @@
+//! Custom output templates.
+//!
+//! `--template <FILE>` renders search, extract and query results with a logic-light
+//! [TinyTemplate](https://docs.rs/tinytemplate) template instead of a built-in format:
+//!
+//! ```text
+//! {{ for result in results -}}
+//! {result.file}:{result.start_line} ({result.language}, {result.node_type})
+//! {{ endfor -}}
+//! {totals.count} results, {totals.total_tokens} tokens
+//! ```
+//!
+//! The context has these top-level fields:
+//!
+//! * `command` - "search", "extract" or "query"
+//! * `query` - the search query or query pattern (null for extract)
+//! * `dry_run` - whether only file names and line numbers were requested
+//! * `results` - one object per result, see below
+//! * `totals` - `count`, `total_bytes` and `total_tokens`
+//! * `original_input`, `system_prompt`, `user_instructions` - extract only, null otherwise
+//!
+//! Every result has `file`, `root`, `lines`, `start_line`, `end_line`, `node_type`, `code`
+//! and `language`, plus every other field of the underlying `SearchResult` (search and
+//! extract) or `AstMatch` (query). Missing values are null, so `{{ if result.score }}`
+//! can be used to test for them.
+//!
+//! Values are inserted as is. The `xml`, `json`, `join` and `trim` formatters escape,
+//! JSON-encode, comma-join or trim a value, e.g. `{result.code | xml}`.
+//!
+//! A template name without a path, such as `markdown` or `xml`, selects one of the
+//! templates shipped with probe when no such file exists.
+
+use anyhow::{Context, Result};
+use serde::Serialize;
+use serde_json::Value;
+use std::path::Path;
+use tinytemplate::TinyTemplate;
+
+/// Templates shipped with probe, selectable by name
+const BUILTIN_TEMPLATES: &[(&str, &str)] = &[
+    ("markdown", include_str!("output_templates/markdown.tmpl")),
+    ("xml", include_str!("output_templates/xml.tmpl")),
+];
+
+/// Name under which the template text is registered for rendering
+const TEMPLATE_NAME: &str = "output";
+
+/// A loaded output template
+#[derive(Debug, Clone)]
+pub struct OutputTemplate {
+    /// File path or built-in name the template was loaded from
+    pub name: String,
+    source: String,
+}
+
+/// The data a template is rendered with
+#[derive(Debug, Serialize)]
+pub struct TemplateContext {
+    pub command: &'static str,
+    pub query: Option<String>,
+    pub dry_run: bool,
+    pub results: Vec<Value>,
+    pub totals: TemplateTotals,
+    pub original_input: Option<String>,
+    pub system_prompt: Option<String>,
+    pub user_instructions: Option<String>,
+}
+
+/// Totals over all results
+#[derive(Debug, Serialize)]
+pub struct TemplateTotals {
+    pub count: usize,
+    pub total_bytes: usize,
+    pub total_tokens: usize,
+}
+
+impl OutputTemplate {
+    /// Load a template from a file, or a built-in template by name
+    pub fn load(spec: &str) -> Result<Self> {
+        let path = Path::new(spec);
+        if path.is_file() {
+            let source = std::fs::read_to_string(path)
+                .with_context(|| format!("Failed to read template file: {}", spec))?;
+            return Self::from_source(spec, source);
+        }
+
+        match Self::builtin(spec) {
+            Some(template) => Ok(template),
+            None => anyhow::bail!(
+                "Template file not found: {} (built-in templates: {})",
+                spec,
+                builtin_template_names().join(", ")
+            ),
+        }
+    }
+
+    /// Get a template shipped with probe
+    pub fn builtin(name: &str) -> Option<Self> {
+        BUILTIN_TEMPLATES
+            .iter()
+            .find(|(builtin, _)| *builtin == name)
+            .map(|(name, source)| OutputTemplate {
+                name: name.to_string(),
+                source: source.to_string(),
+            })
+    }
+
+    /// Create a template from its text, reporting syntax errors up front
+    pub fn from_source(name: &str, source: String) -> Result<Self> {
+        let template = OutputTemplate {
+            name: name.to_string(),
+            source,
+        };
+        template.registry()?;
+        Ok(template)
+    }
+
+    /// Render the template with the given context
+    pub fn render(&self, context: &TemplateContext) -> Result<String> {
+        self.registry()?
+            .render(TEMPLATE_NAME, context)
+            .map_err(|e| anyhow::anyhow!("Failed to render template {}: {}", self.name, e))
+    }
+
+    fn registry(&self) -> Result<TinyTemplate<'_>> {
+        let mut registry = TinyTemplate::new();
+        registry.set_default_formatter(&tinytemplate::format_unescaped);
+        registry.add_formatter("xml", |value, output| {
+            let mut text = String::new();
+            tinytemplate::format_unescaped(value, &mut text)?;
+            output.push_str(&escape_xml(&text));
+            Ok(())
+        });
+        registry.add_formatter("json", |value, output| {
+            output.push_str(&value.to_string());
+            Ok(())
+        });
+        registry.add_formatter("join", |value, output| match value {
+            Value::Array(items) => {
+                for (i, item) in items.iter().enumerate() {
+                    if i > 0 {
+                        output.push_str(", ");
+                    }
+                    tinytemplate::format_unescaped(item, output)?;
+                }
+                Ok(())
+            }
+            _ => tinytemplate::format_unescaped(value, output),
+        });
+        registry.add_formatter("trim", |value, output| {
+            let mut text = String::new();
+            tinytemplate::format_unescaped(value, &mut text)?;
+            output.push_str(text.trim());
+            Ok(())
+        });
+        registry
+            .add_template(TEMPLATE_NAME, &self.source)
+            .map_err(|e| anyhow::anyhow!("Invalid template {}: {}", self.name, e))?;
+        Ok(registry)
+    }
+}
+
+/// Names of the templates shipped with probe
+pub fn builtin_template_names() -> Vec<&'static str> {
+    BUILTIN_TEMPLATES.iter().map(|(name, _)| *name).collect()
+}
+
+/// Add the fields every template result has to a serialized result object
+///
+/// `start_line`, `end_line` and `language` are derived from `file` and `lines`, and
+/// `root` is set to null when the result doesn't come from a workspace root.
+pub fn add_common_fields(result: &mut Value, file: &str, lines: (usize, usize)) {
+    let extension = Path::new(file)
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .unwrap_or("");
+    result["start_line"] = lines.0.into();
+    result["end_line"] = lines.1.into();
+    result["language"] = crate::extract::get_language_from_extension(extension).into();
+    if result.get("root").is_none() {
+        result["root"] = Value::Null;
+    }
+}
+
+fn escape_xml(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn context(results: Vec<Value>) -> TemplateContext {
+        TemplateContext {
+            command: "search",
+            query: Some("parse".to_string()),
+            dry_run: false,
+            totals: TemplateTotals {
+                count: results.len(),
+                total_bytes: 12,
+                total_tokens: 4,
+            },
+            results,
+            original_input: None,
+            system_prompt: None,
+            user_instructions: None,
+        }
+    }
+
+    fn result(file: &str, code: &str) -> Value {
+        let mut result = serde_json::json!({
+            "file": file,
+            "lines": [3, 5],
+            "node_type": "function_item",
+            "code": code,
+            "matched_keywords": ["parse", "input"],
+        });
+        add_common_fields(&mut result, file, (3, 5));
+        result
+    }
+
+    #[test]
+    fn test_render_fields_and_formatters() {
+        let template = OutputTemplate::from_source(
+            "test",
+            "{{ for r in results }}{r.file}:{r.start_line}-{r.lines.1} {r.language} \
+             [{r.matched_keywords | join}]{{ if r.root }} in {r.root}{{ endif }} {r.code | xml}\n\
+             {{ endfor }}{query}: {totals.count} results"
+                .to_string(),
+        )
+        .unwrap();
+
+        let output = template
+            .render(&context(vec![result("src/lib.rs", "a < b && c")]))
+            .unwrap();
+        assert_eq!(
+            output,
+            "src/lib.rs:3-5 rust [parse, input] a &lt; b &amp;&amp; c\nparse: 1 results"
+        );
+    }
+
+    #[test]
+    fn test_invalid_and_builtin_templates() {
+        assert!(OutputTemplate::from_source("bad", "{{ for r in results }}".to_string()).is_err());
+        assert!(OutputTemplate::load("no-such-template").is_err());
+
+        // Unknown fields are reported when rendering
+        let template = OutputTemplate::from_source("unknown", "{missing}".to_string()).unwrap();
+        assert!(template.render(&context(vec![])).is_err());
+
+        for name in builtin_template_names() {
+            let template = OutputTemplate::load(name).unwrap();
+            let output = template
+                .render(&context(vec![result("src/main.py", "def parse(): pass")]))
+                .unwrap();
+            assert!(output.contains("src/main.py"), "{} template", name);
+        }
+    }
+}
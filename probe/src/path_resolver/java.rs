@@ -0,0 +1,353 @@
+//! Java-specific path resolver implementation.
+//!
+//! Artifacts are looked up in the local Maven repository (`~/.m2/repository`) and the
+//! Gradle module cache (`~/.gradle/caches/modules-2/files-2.1`). When a `-sources.jar`
+//! is available it is unpacked once into `~/.cache/probe/java-sources` so that its
+//! contents can be searched like any other directory.
+
+use super::{compare_versions, PathResolver};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// A path resolver for Java artifacts identified by Maven coordinates.
+pub struct JavaPathResolver {
+    /// Local Maven repository; None disables Maven lookups
+    maven_repo: Option<PathBuf>,
+    /// Gradle `files-2.1` cache directory; None disables Gradle lookups
+    gradle_cache: Option<PathBuf>,
+    /// Directory source jars are unpacked into
+    sources_dir: PathBuf,
+}
+
+/// Parsed `group:artifact[:version]` coordinates.
+struct Coordinates<'a> {
+    group: &'a str,
+    artifact: &'a str,
+    version: Option<&'a str>,
+}
+
+impl Default for JavaPathResolver {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl JavaPathResolver {
+    /// Creates a new Java path resolver using the default Maven and Gradle locations.
+    ///
+    /// The Maven repository honours `<localRepository>` in `~/.m2/settings.xml`, and
+    /// the Gradle cache honours `GRADLE_USER_HOME`.
+    pub fn new() -> Self {
+        let home = dirs::home_dir();
+
+        let maven_repo = home.as_ref().map(|home| {
+            let m2 = home.join(".m2");
+            fs::read_to_string(m2.join("settings.xml"))
+                .ok()
+                .and_then(|settings| local_repository_from_settings(&settings))
+                .unwrap_or_else(|| m2.join("repository"))
+        });
+
+        let gradle_home = std::env::var_os("GRADLE_USER_HOME")
+            .map(PathBuf::from)
+            .or_else(|| home.as_ref().map(|home| home.join(".gradle")));
+        let gradle_cache = gradle_home.map(|gradle_home| {
+            gradle_home
+                .join("caches")
+                .join("modules-2")
+                .join("files-2.1")
+        });
+
+        let sources_dir = home
+            .unwrap_or_else(std::env::temp_dir)
+            .join(".cache")
+            .join("probe")
+            .join("java-sources");
+
+        JavaPathResolver {
+            maven_repo,
+            gradle_cache,
+            sources_dir,
+        }
+    }
+
+    /// Creates a Java path resolver with explicit cache locations.
+    ///
+    /// # Arguments
+    ///
+    /// * `maven_repo` - The local Maven repository (the directory containing `org/`, `com/`, ...)
+    /// * `gradle_cache` - The Gradle `caches/modules-2/files-2.1` directory
+    /// * `sources_dir` - Where source jars are unpacked
+    pub fn with_locations(
+        maven_repo: Option<PathBuf>,
+        gradle_cache: Option<PathBuf>,
+        sources_dir: PathBuf,
+    ) -> Self {
+        JavaPathResolver {
+            maven_repo,
+            gradle_cache,
+            sources_dir,
+        }
+    }
+
+    /// Lists the version directories available for an artifact in both caches.
+    fn version_dirs(&self, coords: &Coordinates) -> Vec<(String, PathBuf)> {
+        let mut dirs = Vec::new();
+
+        let mut artifact_dirs = Vec::new();
+        if let Some(repo) = &self.maven_repo {
+            let mut dir = repo.clone();
+            for segment in coords.group.split('.') {
+                dir.push(segment);
+            }
+            artifact_dirs.push(dir.join(coords.artifact));
+        }
+        if let Some(cache) = &self.gradle_cache {
+            artifact_dirs.push(cache.join(coords.group).join(coords.artifact));
+        }
+
+        for artifact_dir in artifact_dirs {
+            let Ok(entries) = fs::read_dir(&artifact_dir) else {
+                continue;
+            };
+            for entry in entries.filter_map(Result::ok) {
+                let path = entry.path();
+                if !path.is_dir() {
+                    continue;
+                }
+                let version = entry.file_name().to_string_lossy().to_string();
+                if coords.version.is_none_or(|wanted| wanted == version) {
+                    dirs.push((version, path));
+                }
+            }
+        }
+
+        dirs
+    }
+
+    /// Unpacks a source jar into the sources directory, reusing a previous extraction.
+    fn unpack_sources(
+        &self,
+        coords: &Coordinates,
+        version: &str,
+        jar: &Path,
+    ) -> Result<PathBuf, String> {
+        let target = self
+            .sources_dir
+            .join(coords.group)
+            .join(coords.artifact)
+            .join(version);
+        let marker = target.join(".probe-unpacked");
+        if marker.exists() {
+            return Ok(target);
+        }
+
+        let file = fs::File::open(jar).map_err(|e| format!("Failed to open {:?}: {}", jar, e))?;
+        let mut archive = zip::ZipArchive::new(file)
+            .map_err(|e| format!("Failed to read source jar {:?}: {}", jar, e))?;
+
+        let parent = target
+            .parent()
+            .ok_or_else(|| format!("Invalid sources directory: {:?}", target))?;
+        fs::create_dir_all(parent).map_err(|e| format!("Failed to create {:?}: {}", parent, e))?;
+
+        // Processes unpacking the same version take turns, so that none of them replaces
+        // a tree another one has just unpacked and is reading
+        let lock_path = parent.join(format!(".{}.lock", version));
+        let lock = fs::OpenOptions::new()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(&lock_path)
+            .map_err(|e| format!("Failed to open {:?}: {}", lock_path, e))?;
+        lock.lock()
+            .map_err(|e| format!("Failed to lock {:?}: {}", lock_path, e))?;
+        if marker.exists() {
+            return Ok(target);
+        }
+
+        // Unpack next to the target and rename, so a partial extraction is never reused.
+        // The staging directory is removed when dropped, whichever way this returns.
+        let staging = tempfile::Builder::new()
+            .prefix(&format!(".{}.tmp-", version))
+            .tempdir_in(parent)
+            .map_err(|e| format!("Failed to create a directory in {:?}: {}", parent, e))?;
+
+        for i in 0..archive.len() {
+            let mut entry = archive
+                .by_index(i)
+                .map_err(|e| format!("Failed to read entry in {:?}: {}", jar, e))?;
+            // enclosed_name rejects absolute paths and '..' components
+            let Some(relative) = entry.enclosed_name() else {
+                continue;
+            };
+            let out_path = staging.path().join(relative);
+            if entry.is_dir() {
+                fs::create_dir_all(&out_path)
+                    .map_err(|e| format!("Failed to create {:?}: {}", out_path, e))?;
+                continue;
+            }
+            if let Some(dir) = out_path.parent() {
+                fs::create_dir_all(dir)
+                    .map_err(|e| format!("Failed to create {:?}: {}", dir, e))?;
+            }
+            let mut out = fs::File::create(&out_path)
+                .map_err(|e| format!("Failed to create {:?}: {}", out_path, e))?;
+            std::io::copy(&mut entry, &mut out)
+                .map_err(|e| format!("Failed to unpack {:?}: {}", out_path, e))?;
+        }
+
+        fs::write(
+            staging.path().join(".probe-unpacked"),
+            jar.to_string_lossy().as_bytes(),
+        )
+        .map_err(|e| format!("Failed to write unpack marker: {}", e))?;
+
+        // Without its marker, the target is what an interrupted unpack left behind
+        let _ = fs::remove_dir_all(&target);
+        if let Err(e) = fs::rename(staging.path(), &target) {
+            // Keep a complete tree that another unpack moved there first
+            if marker.exists() {
+                return Ok(target);
+            }
+            return Err(format!(
+                "Failed to move unpacked sources to {:?}: {}",
+                target, e
+            ));
+        }
+
+        Ok(target)
+    }
+}
+
+/// Extracts `<localRepository>` from a Maven settings.xml file.
+fn local_repository_from_settings(settings: &str) -> Option<PathBuf> {
+    let start = settings.find("<localRepository>")? + "<localRepository>".len();
+    let end = start + settings[start..].find("</localRepository>")?;
+    let value = settings[start..end].trim();
+    if value.is_empty() {
+        return None;
+    }
+    let value = match (value.strip_prefix("${user.home}"), dirs::home_dir()) {
+        (Some(rest), Some(home)) => format!("{}{}", home.display(), rest),
+        _ => value.to_string(),
+    };
+    Some(PathBuf::from(value))
+}
+
+/// Parses `group:artifact[:version]` coordinates.
+fn parse_coordinates(module_name: &str) -> Result<Coordinates<'_>, String> {
+    let parts: Vec<&str> = module_name.split(':').collect();
+    if parts.len() < 2 || parts.len() > 3 || parts.iter().any(|part| part.is_empty()) {
+        return Err(format!(
+            "Invalid Java coordinates (expected group:artifact[:version]): {}",
+            module_name
+        ));
+    }
+    Ok(Coordinates {
+        group: parts[0],
+        artifact: parts[1],
+        version: parts.get(2).copied(),
+    })
+}
+
+/// Finds a jar with the given file name directly in a version directory or, for the
+/// Gradle layout, in one of its checksum subdirectories.
+fn find_jar(version_dir: &Path, file_name: &str) -> Option<PathBuf> {
+    let direct = version_dir.join(file_name);
+    if direct.is_file() {
+        return Some(direct);
+    }
+    let mut hashed: Vec<PathBuf> = fs::read_dir(version_dir)
+        .ok()?
+        .filter_map(Result::ok)
+        .map(|entry| entry.path().join(file_name))
+        .filter(|path| path.is_file())
+        .collect();
+    hashed.sort();
+    hashed.into_iter().next()
+}
+
+impl PathResolver for JavaPathResolver {
+    fn prefix(&self) -> &'static str {
+        "java:"
+    }
+
+    fn split_module_and_subpath(
+        &self,
+        full_path_after_prefix: &str,
+    ) -> Result<(String, Option<String>), String> {
+        if full_path_after_prefix.is_empty() {
+            return Err("Java path cannot be empty".to_string());
+        }
+        if full_path_after_prefix.contains("..") {
+            return Err("Java path cannot contain '..'".to_string());
+        }
+
+        // Trim potential trailing slash
+        let path = full_path_after_prefix.trim_end_matches('/');
+
+        // Coordinates come first: group:artifact[:version]/sub/path
+        let mut parts = path.splitn(2, '/');
+        let module_name = parts.next().unwrap_or("").to_string();
+        parse_coordinates(&module_name)?;
+        let subpath_opt = parts.next().filter(|s| !s.is_empty()).map(String::from);
+        Ok((module_name, subpath_opt))
+    }
+
+    fn resolve(&self, module_name: &str) -> Result<PathBuf, String> {
+        let coords = parse_coordinates(module_name)?;
+
+        let mut version_dirs = self.version_dirs(&coords);
+        if version_dirs.is_empty() {
+            return Err(format!(
+                "Could not find Java artifact {} in the Maven or Gradle caches",
+                module_name
+            ));
+        }
+
+        // Newest version first; for equal versions Maven comes before Gradle
+        version_dirs.sort_by(|(a, _), (b, _)| compare_versions(b, a));
+        let newest = version_dirs[0].0.clone();
+
+        let sources_name = format!("{}-{}-sources.jar", coords.artifact, newest);
+        for (_, dir) in version_dirs.iter().filter(|(v, _)| *v == newest) {
+            if let Some(jar) = find_jar(dir, &sources_name) {
+                return self.unpack_sources(&coords, &newest, &jar);
+            }
+        }
+
+        // Without sources, fall back to the version directory (POM and binary jar)
+        Ok(version_dirs[0].1.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_coordinates() {
+        let coords = parse_coordinates("org.slf4j:slf4j-api:2.0.9").unwrap();
+        assert_eq!(coords.group, "org.slf4j");
+        assert_eq!(coords.artifact, "slf4j-api");
+        assert_eq!(coords.version, Some("2.0.9"));
+
+        let coords = parse_coordinates("com.google.guava:guava").unwrap();
+        assert_eq!(coords.version, None);
+
+        assert!(parse_coordinates("guava").is_err());
+        assert!(parse_coordinates("a:b:c:d").is_err());
+        assert!(parse_coordinates("a::1.0").is_err());
+    }
+
+    #[test]
+    fn test_local_repository_from_settings() {
+        let settings = "<settings>\n  <localRepository>/opt/m2</localRepository>\n</settings>";
+        assert_eq!(
+            local_repository_from_settings(settings),
+            Some(PathBuf::from("/opt/m2"))
+        );
+        assert_eq!(local_repository_from_settings("<settings/>"), None);
+    }
+}
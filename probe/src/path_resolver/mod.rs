@@ -4,13 +4,20 @@
 //! "go:github.com/user/repo", "js:express", or "rust:serde" to actual filesystem paths.
 
 mod go;
+mod java;
 mod javascript;
+mod npm;
+mod python;
 mod rust;
 
+use std::cmp::Ordering;
 use std::path::{Path, PathBuf};
 
 pub use go::GoPathResolver;
+pub use java::JavaPathResolver;
 pub use javascript::JavaScriptPathResolver;
+pub use npm::NpmPathResolver;
+pub use python::PythonPathResolver;
 pub use rust::RustPathResolver;
 
 /// A trait for language-specific path resolvers.
@@ -66,9 +73,13 @@ pub trait PathResolver {
 /// - "go:github.com/user/repo" - Resolves to the Go module's filesystem path
 /// - "js:express" - Resolves to the JavaScript/Node.js package's filesystem path
 /// - "rust:serde" - Resolves to the Rust crate's filesystem path
+/// - "py:requests" - Resolves to the Python package in the active virtual environment
+/// - "java:org.slf4j:slf4j-api" - Resolves to the unpacked sources of a Maven/Gradle artifact
+/// - "npm:react@18.2.0" - Resolves to a package in a pnpm `node_modules/.pnpm` layout or workspace
 /// - "/dep/go/fmt" - Alternative notation for "go:fmt"
 /// - "/dep/js/express" - Alternative notation for "js:express"
 /// - "/dep/rust/serde" - Alternative notation for "rust:serde"
+/// - "/dep/py/requests", "/dep/java/...", "/dep/npm/..." - Alternative notations for the above
 ///
 /// # Arguments
 ///
@@ -84,6 +95,9 @@ pub fn resolve_path(path: &str) -> Result<PathBuf, String> {
         Box::new(GoPathResolver::new()),
         Box::new(JavaScriptPathResolver::new()),
         Box::new(RustPathResolver::new()),
+        Box::new(PythonPathResolver::new()),
+        Box::new(JavaPathResolver::new()),
+        Box::new(NpmPathResolver::new()),
     ];
 
     // Check for /dep/ prefix notation
@@ -102,6 +116,9 @@ pub fn resolve_path(path: &str) -> Result<PathBuf, String> {
             "go" => "go:",
             "js" => "js:",
             "rust" => "rust:",
+            "py" => "py:",
+            "java" => "java:",
+            "npm" => "npm:",
             _ => {
                 return Err(format!(
                     "Unknown language identifier in /dep/ path: {}",
@@ -201,6 +218,52 @@ pub fn resolve_path(path: &str) -> Result<PathBuf, String> {
     Ok(PathBuf::from(path))
 }
 
+/// Compares two version strings component by component.
+///
+/// Numeric components are compared as numbers and everything else lexically, so
+/// "1.10.0" sorts after "1.9.2". A release sorts after its pre-releases
+/// ("2.0.0" > "2.0.0-rc1").
+pub(crate) fn compare_versions(a: &str, b: &str) -> Ordering {
+    let split = |v: &str| -> Vec<String> {
+        v.split(['.', '-', '+', '_'])
+            .filter(|part| !part.is_empty())
+            .map(str::to_string)
+            .collect()
+    };
+    let (a_parts, b_parts) = (split(a), split(b));
+
+    for (x, y) in a_parts.iter().zip(b_parts.iter()) {
+        let ordering = match (x.parse::<u64>(), y.parse::<u64>()) {
+            (Ok(x), Ok(y)) => x.cmp(&y),
+            (Ok(_), Err(_)) => Ordering::Greater,
+            (Err(_), Ok(_)) => Ordering::Less,
+            (Err(_), Err(_)) => x.cmp(y),
+        };
+        if ordering != Ordering::Equal {
+            return ordering;
+        }
+    }
+
+    // Extra numeric components make a version newer, extra labels make it a pre-release
+    match a_parts.len().cmp(&b_parts.len()) {
+        Ordering::Equal => Ordering::Equal,
+        Ordering::Greater => {
+            if a_parts[b_parts.len()].parse::<u64>().is_ok() {
+                Ordering::Greater
+            } else {
+                Ordering::Less
+            }
+        }
+        Ordering::Less => {
+            if b_parts[a_parts.len()].parse::<u64>().is_ok() {
+                Ordering::Less
+            } else {
+                Ordering::Greater
+            }
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -253,4 +316,12 @@ mod tests {
         let result = resolve_path("/dep/unknown/package");
         assert!(result.is_err());
     }
+
+    #[test]
+    fn test_compare_versions() {
+        assert_eq!(compare_versions("1.10.0", "1.9.2"), Ordering::Greater);
+        assert_eq!(compare_versions("1.0.200", "1.0.200"), Ordering::Equal);
+        assert_eq!(compare_versions("2.0.0", "2.0.0-rc1"), Ordering::Greater);
+        assert_eq!(compare_versions("1.2", "1.2.1"), Ordering::Less);
+    }
 }
@@ -0,0 +1,255 @@
+//! pnpm-aware npm path resolver implementation.
+//!
+//! pnpm keeps every installed package in a flat `node_modules/.pnpm` store with
+//! entries named `<name>@<version>[_<peers>]`, where scoped names use `+` instead
+//! of `/`. Only direct dependencies are linked into `node_modules`, so transitive
+//! dependencies and specific versions have to be looked up in the store. Packages
+//! that belong to the workspace itself are found through `pnpm-workspace.yaml`.
+
+use super::{compare_versions, JavaScriptPathResolver, PathResolver};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// A path resolver for npm packages installed with pnpm.
+pub struct NpmPathResolver {
+    /// Directory to start the upward search from; the current directory when None
+    root: Option<PathBuf>,
+}
+
+impl Default for NpmPathResolver {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl NpmPathResolver {
+    /// Creates a new npm path resolver that searches upwards from the current directory.
+    pub fn new() -> Self {
+        NpmPathResolver { root: None }
+    }
+
+    /// Creates an npm path resolver that searches upwards from the given directory.
+    pub fn with_root(root: PathBuf) -> Self {
+        NpmPathResolver { root: Some(root) }
+    }
+
+    /// Returns the directory the upward search starts from.
+    fn start_dir(&self) -> Result<PathBuf, String> {
+        match &self.root {
+            Some(root) => Ok(root.clone()),
+            None => std::env::current_dir()
+                .map_err(|e| format!("Failed to get current directory: {}", e)),
+        }
+    }
+}
+
+/// Splits `name[@version]` into the package name and optional version.
+fn split_name_and_version(module_name: &str) -> (&str, Option<&str>) {
+    // Skip the leading '@' of scoped packages
+    let search_from = usize::from(module_name.starts_with('@'));
+    match module_name[search_from..].find('@') {
+        Some(at) => {
+            let at = at + search_from;
+            let version = &module_name[at + 1..];
+            (
+                &module_name[..at],
+                Some(version).filter(|version| !version.is_empty()),
+            )
+        }
+        None => (module_name, None),
+    }
+}
+
+/// Finds a package in a pnpm virtual store (`node_modules/.pnpm`).
+///
+/// Without a version the newest stored version wins.
+fn find_in_pnpm_store(store: &Path, name: &str, version: Option<&str>) -> Option<PathBuf> {
+    let entry_prefix = format!("{}@", name.replace('/', "+"));
+
+    let mut matches: Vec<(String, PathBuf)> = fs::read_dir(store)
+        .ok()?
+        .filter_map(Result::ok)
+        .filter_map(|entry| {
+            let entry_name = entry.file_name().to_string_lossy().to_string();
+            let rest = entry_name.strip_prefix(&entry_prefix)?;
+            // Peer dependency suffixes: "1.0.0_react@18.2.0" or "1.0.0(react@18.2.0)"
+            let entry_version = rest.split(['_', '(']).next().unwrap_or(rest).to_string();
+            if version.is_some_and(|wanted| wanted != entry_version) {
+                return None;
+            }
+            let package_dir = entry.path().join("node_modules").join(name);
+            package_dir.is_dir().then_some((entry_version, package_dir))
+        })
+        .collect();
+
+    // Newest first; for the same version prefer the entry without peer suffixes
+    matches.sort_by(|(a, a_path), (b, b_path)| compare_versions(b, a).then(a_path.cmp(b_path)));
+    matches.into_iter().next().map(|(_, path)| path)
+}
+
+/// Reads the `packages:` globs from a pnpm-workspace.yaml file.
+fn workspace_patterns(workspace_file: &Path) -> Vec<String> {
+    let Ok(content) = fs::read_to_string(workspace_file) else {
+        return Vec::new();
+    };
+
+    let mut patterns = Vec::new();
+    let mut in_packages = false;
+    for line in content.lines() {
+        let trimmed = line.trim();
+        if trimmed.is_empty() || trimmed.starts_with('#') {
+            continue;
+        }
+        if !line.starts_with(' ') && !line.starts_with('-') {
+            in_packages = trimmed == "packages:";
+            continue;
+        }
+        if in_packages {
+            if let Some(item) = trimmed.strip_prefix('-') {
+                let pattern = item.trim().trim_matches(|c| c == '\'' || c == '"');
+                if !pattern.is_empty() {
+                    patterns.push(pattern.to_string());
+                }
+            }
+        }
+    }
+    patterns
+}
+
+/// Reads the `name` and `version` fields of a package.json file.
+fn package_identity(package_dir: &Path) -> Option<(String, Option<String>)> {
+    let content = fs::read_to_string(package_dir.join("package.json")).ok()?;
+    let json: serde_json::Value = serde_json::from_str(&content).ok()?;
+    let name = json["name"].as_str()?.to_string();
+    let version = json["version"].as_str().map(str::to_string);
+    Some((name, version))
+}
+
+/// Finds a workspace package by name among the directories matched by the workspace globs.
+fn find_workspace_package(
+    workspace_root: &Path,
+    name: &str,
+    version: Option<&str>,
+) -> Option<PathBuf> {
+    let patterns = workspace_patterns(&workspace_root.join("pnpm-workspace.yaml"));
+    let excluded: Vec<glob::Pattern> = patterns
+        .iter()
+        .filter_map(|pattern| pattern.strip_prefix('!'))
+        .filter_map(|pattern| glob::Pattern::new(pattern).ok())
+        .collect();
+
+    for pattern in patterns.iter().filter(|pattern| !pattern.starts_with('!')) {
+        let full_pattern = workspace_root.join(pattern.trim_end_matches('/'));
+        let Ok(paths) = glob::glob(&full_pattern.to_string_lossy()) else {
+            continue;
+        };
+        for package_dir in paths.filter_map(Result::ok) {
+            let relative = package_dir
+                .strip_prefix(workspace_root)
+                .unwrap_or(&package_dir);
+            if excluded
+                .iter()
+                .any(|exclude| exclude.matches_path(relative))
+            {
+                continue;
+            }
+            if let Some((package_name, package_version)) = package_identity(&package_dir) {
+                if package_name == name
+                    && version.is_none_or(|wanted| package_version.as_deref() == Some(wanted))
+                {
+                    return Some(package_dir);
+                }
+            }
+        }
+    }
+    None
+}
+
+impl PathResolver for NpmPathResolver {
+    fn prefix(&self) -> &'static str {
+        "npm:"
+    }
+
+    fn split_module_and_subpath(
+        &self,
+        full_path_after_prefix: &str,
+    ) -> Result<(String, Option<String>), String> {
+        // Package names follow the same rules as "js:"; a version may be appended to the name
+        JavaScriptPathResolver::new().split_module_and_subpath(full_path_after_prefix)
+    }
+
+    fn resolve(&self, module_name: &str) -> Result<PathBuf, String> {
+        let (name, version) = split_name_and_version(module_name);
+        let mut current_dir = self.start_dir()?;
+
+        loop {
+            let node_modules = current_dir.join("node_modules");
+
+            // Direct dependencies are symlinked into node_modules
+            if version.is_none() {
+                let linked = node_modules.join(name);
+                if linked.is_dir() {
+                    return Ok(linked.canonicalize().unwrap_or(linked));
+                }
+            }
+
+            if current_dir.join("pnpm-workspace.yaml").is_file() {
+                if let Some(package_dir) = find_workspace_package(&current_dir, name, version) {
+                    return Ok(package_dir);
+                }
+            }
+
+            if let Some(package_dir) =
+                find_in_pnpm_store(&node_modules.join(".pnpm"), name, version)
+            {
+                return Ok(package_dir);
+            }
+
+            // The workspace root is the last place pnpm would install to
+            if current_dir.join("pnpm-workspace.yaml").is_file() || !current_dir.pop() {
+                break;
+            }
+        }
+
+        Err(format!(
+            "Could not find npm package {} in a pnpm node_modules layout",
+            module_name
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_name_and_version() {
+        assert_eq!(split_name_and_version("react"), ("react", None));
+        assert_eq!(
+            split_name_and_version("react@18.2.0"),
+            ("react", Some("18.2.0"))
+        );
+        assert_eq!(split_name_and_version("@types/node"), ("@types/node", None));
+        assert_eq!(
+            split_name_and_version("@types/node@20.1.0"),
+            ("@types/node", Some("20.1.0"))
+        );
+        assert_eq!(split_name_and_version("react@"), ("react", None));
+    }
+
+    #[test]
+    fn test_workspace_patterns() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let file = temp_dir.path().join("pnpm-workspace.yaml");
+        fs::write(
+            &file,
+            "packages:\n  - 'packages/*'\n  - \"apps/*\"\n  # comment\n  - '!**/test/**'\ncatalog:\n  - ignored\n",
+        )
+        .unwrap();
+
+        assert_eq!(
+            workspace_patterns(&file),
+            vec!["packages/*", "apps/*", "!**/test/**"]
+        );
+    }
+}
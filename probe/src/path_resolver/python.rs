@@ -0,0 +1,370 @@
+//! Python-specific path resolver implementation.
+//!
+//! Packages are looked up in the site-packages directories of the active virtual
+//! environment without running an interpreter. Distribution names are mapped to
+//! import names through `*.dist-info` / `*.egg-info` metadata, and `.pth` files are
+//! followed so that editable installs resolve to their source checkout.
+
+use super::PathResolver;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Directory names checked when looking for a project-local virtual environment.
+const LOCAL_VENV_DIRS: &[&str] = &[".venv", "venv", "env"];
+
+/// A path resolver for Python packages.
+pub struct PythonPathResolver {
+    /// Explicit site-packages directories; discovered from the environment when None
+    site_packages: Option<Vec<PathBuf>>,
+}
+
+impl Default for PythonPathResolver {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl PythonPathResolver {
+    /// Creates a new Python path resolver that discovers site-packages from
+    /// `VIRTUAL_ENV`, `CONDA_PREFIX` or a `.venv` / `venv` / `env` directory
+    /// above the current directory.
+    pub fn new() -> Self {
+        PythonPathResolver {
+            site_packages: None,
+        }
+    }
+
+    /// Creates a Python path resolver that only searches the given site-packages directories.
+    pub fn with_site_packages(site_packages: Vec<PathBuf>) -> Self {
+        PythonPathResolver {
+            site_packages: Some(site_packages),
+        }
+    }
+
+    /// Returns the site-packages directories to search, in priority order.
+    fn site_packages_dirs(&self) -> Vec<PathBuf> {
+        if let Some(dirs) = &self.site_packages {
+            return dirs.clone();
+        }
+
+        let mut env_roots: Vec<PathBuf> = ["VIRTUAL_ENV", "CONDA_PREFIX"]
+            .iter()
+            .filter_map(std::env::var_os)
+            .map(PathBuf::from)
+            .collect();
+
+        // Fall back to a project-local virtual environment
+        if env_roots.is_empty() {
+            if let Ok(mut current_dir) = std::env::current_dir() {
+                loop {
+                    if let Some(venv) = LOCAL_VENV_DIRS
+                        .iter()
+                        .map(|name| current_dir.join(name))
+                        .find(|dir| dir.join("pyvenv.cfg").is_file())
+                    {
+                        env_roots.push(venv);
+                        break;
+                    }
+                    if !current_dir.pop() {
+                        break;
+                    }
+                }
+            }
+        }
+
+        env_roots
+            .iter()
+            .flat_map(|root| site_packages_in_env(root))
+            .collect()
+    }
+}
+
+/// Finds the site-packages directories of a virtual environment or prefix.
+fn site_packages_in_env(env_root: &Path) -> Vec<PathBuf> {
+    let mut dirs = Vec::new();
+
+    // POSIX layout: lib/pythonX.Y/site-packages
+    for lib in ["lib", "lib64"] {
+        if let Ok(entries) = fs::read_dir(env_root.join(lib)) {
+            let mut versions: Vec<PathBuf> = entries
+                .filter_map(Result::ok)
+                .map(|entry| entry.path())
+                .filter(|path| {
+                    path.file_name()
+                        .is_some_and(|name| name.to_string_lossy().starts_with("python"))
+                })
+                .map(|path| path.join("site-packages"))
+                .filter(|path| path.is_dir())
+                .collect();
+            versions.sort();
+            dirs.extend(versions);
+        }
+    }
+
+    // Windows layout: Lib/site-packages
+    let windows = env_root.join("Lib").join("site-packages");
+    if windows.is_dir() && !dirs.contains(&windows) {
+        dirs.push(windows);
+    }
+
+    dirs
+}
+
+/// Normalizes a distribution name as described in PEP 503.
+fn normalize_name(name: &str) -> String {
+    let mut normalized = String::with_capacity(name.len());
+    let mut last_was_separator = false;
+    for c in name.chars() {
+        if c == '-' || c == '_' || c == '.' {
+            if !last_was_separator {
+                normalized.push('-');
+            }
+            last_was_separator = true;
+        } else {
+            normalized.extend(c.to_lowercase());
+            last_was_separator = false;
+        }
+    }
+    normalized
+}
+
+/// Gets the distribution name recorded in a `*.dist-info` or `*.egg-info` directory.
+fn distribution_name(metadata_dir: &Path) -> Option<String> {
+    // The Name field is authoritative; the directory name is a fallback
+    for file in ["METADATA", "PKG-INFO"] {
+        if let Ok(content) = fs::read_to_string(metadata_dir.join(file)) {
+            if let Some(name) = content
+                .lines()
+                .take_while(|line| !line.is_empty())
+                .find_map(|line| line.strip_prefix("Name:"))
+            {
+                return Some(name.trim().to_string());
+            }
+        }
+    }
+
+    let stem = metadata_dir.file_stem()?.to_string_lossy().to_string();
+    stem.split('-').next().map(str::to_string)
+}
+
+/// Gets the top-level import names provided by a distribution.
+fn top_level_names(metadata_dir: &Path) -> Vec<String> {
+    if let Ok(content) = fs::read_to_string(metadata_dir.join("top_level.txt")) {
+        let names: Vec<String> = content
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty())
+            .map(str::to_string)
+            .collect();
+        if !names.is_empty() {
+            return names;
+        }
+    }
+
+    // Wheels built by newer tools omit top_level.txt, so derive the names from RECORD
+    let mut names: Vec<String> = Vec::new();
+    if let Ok(content) = fs::read_to_string(metadata_dir.join("RECORD")) {
+        for line in content.lines() {
+            let path = line.split(',').next().unwrap_or("");
+            if path.starts_with("..") || path.starts_with('/') {
+                continue;
+            }
+            let first = path.split('/').next().unwrap_or("");
+            let name = first.strip_suffix(".py").unwrap_or(first);
+            if name.is_empty()
+                || name == "__pycache__"
+                || name.ends_with(".dist-info")
+                || name.ends_with(".pth")
+                || name.contains('.')
+            {
+                continue;
+            }
+            if !names.iter().any(|existing| existing == name) {
+                names.push(name.to_string());
+            }
+        }
+    }
+    names
+}
+
+/// Reads the directories added to `sys.path` by the `.pth` files in a site-packages directory.
+fn pth_directories(site_packages: &Path) -> Vec<PathBuf> {
+    let mut pth_files: Vec<PathBuf> = match fs::read_dir(site_packages) {
+        Ok(entries) => entries
+            .filter_map(Result::ok)
+            .map(|entry| entry.path())
+            .filter(|path| path.extension().is_some_and(|ext| ext == "pth"))
+            .collect(),
+        Err(_) => return Vec::new(),
+    };
+    // Python processes .pth files in alphabetical order
+    pth_files.sort();
+
+    let mut dirs = Vec::new();
+    for pth_file in pth_files {
+        let Ok(content) = fs::read_to_string(&pth_file) else {
+            continue;
+        };
+        for line in content.lines() {
+            let line = line.trim();
+            // Lines starting with "import" are executed, not added to the path
+            if line.is_empty()
+                || line.starts_with('#')
+                || line.starts_with("import ")
+                || line.starts_with("import\t")
+            {
+                continue;
+            }
+            let dir = site_packages.join(line);
+            if dir.is_dir() && !dirs.contains(&dir) {
+                dirs.push(dir);
+            }
+        }
+    }
+    dirs
+}
+
+/// Finds an import name inside a search root, as a package directory or single module file.
+fn find_import(root: &Path, import_name: &str) -> Option<PathBuf> {
+    let relative = import_name.replace('.', "/");
+    let package_dir = root.join(&relative);
+    if package_dir.is_dir() {
+        return Some(package_dir);
+    }
+    let module_file = root.join(format!("{}.py", relative));
+    if module_file.is_file() {
+        return Some(module_file);
+    }
+    None
+}
+
+impl PathResolver for PythonPathResolver {
+    fn prefix(&self) -> &'static str {
+        "py:"
+    }
+
+    fn split_module_and_subpath(
+        &self,
+        full_path_after_prefix: &str,
+    ) -> Result<(String, Option<String>), String> {
+        if full_path_after_prefix.is_empty() {
+            return Err("Python path cannot be empty".to_string());
+        }
+        if full_path_after_prefix.contains("..") {
+            return Err("Python path cannot contain '..'".to_string());
+        }
+
+        // Trim potential trailing slash
+        let path = full_path_after_prefix.trim_end_matches('/');
+
+        // The first segment is the distribution or (dotted) import name
+        let mut parts = path.splitn(2, '/');
+        let module_name = parts.next().unwrap_or("").to_string();
+        if module_name.is_empty() {
+            return Err(format!("Invalid Python package format: {}", path));
+        }
+        let subpath_opt = parts.next().filter(|s| !s.is_empty()).map(String::from);
+        Ok((module_name, subpath_opt))
+    }
+
+    fn resolve(&self, module_name: &str) -> Result<PathBuf, String> {
+        // A local project directory is returned as is
+        let project_dir = PathBuf::from(module_name);
+        if project_dir.is_dir()
+            && (project_dir.join("pyproject.toml").exists()
+                || project_dir.join("setup.py").exists())
+        {
+            return Ok(project_dir);
+        }
+
+        let site_packages = self.site_packages_dirs();
+        if site_packages.is_empty() {
+            return Err(
+                "No Python site-packages found. Activate a virtual environment or set VIRTUAL_ENV."
+                    .to_string(),
+            );
+        }
+
+        // Candidate import names: the name itself, then whatever a matching distribution provides
+        let wanted = normalize_name(module_name);
+        let mut candidates = vec![module_name.to_string()];
+        for site in &site_packages {
+            let Ok(entries) = fs::read_dir(site) else {
+                continue;
+            };
+            let mut metadata_dirs: Vec<PathBuf> = entries
+                .filter_map(Result::ok)
+                .map(|entry| entry.path())
+                .filter(|path| {
+                    path.is_dir()
+                        && path
+                            .extension()
+                            .is_some_and(|ext| ext == "dist-info" || ext == "egg-info")
+                })
+                .collect();
+            metadata_dirs.sort();
+
+            for metadata_dir in metadata_dirs {
+                if distribution_name(&metadata_dir)
+                    .is_some_and(|name| normalize_name(&name) == wanted)
+                {
+                    for name in top_level_names(&metadata_dir) {
+                        if !candidates.contains(&name) {
+                            candidates.push(name);
+                        }
+                    }
+                }
+            }
+        }
+
+        // Search site-packages first, then directories added by .pth files (editable installs)
+        let mut roots = site_packages.clone();
+        for site in &site_packages {
+            for dir in pth_directories(site) {
+                if !roots.contains(&dir) {
+                    roots.push(dir);
+                }
+            }
+        }
+
+        for candidate in &candidates {
+            for root in &roots {
+                if let Some(path) = find_import(root, candidate) {
+                    return Ok(path);
+                }
+            }
+        }
+
+        Err(format!("Could not find Python package: {}", module_name))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalize_name() {
+        assert_eq!(normalize_name("Django"), "django");
+        assert_eq!(normalize_name("zope.interface"), "zope-interface");
+        assert_eq!(normalize_name("typing__extensions"), "typing-extensions");
+        assert_eq!(normalize_name("Flask-SQLAlchemy"), "flask-sqlalchemy");
+    }
+
+    #[test]
+    fn test_top_level_names_from_record() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let dist_info = temp_dir.path().join("attrs-23.1.0.dist-info");
+        fs::create_dir(&dist_info).unwrap();
+        fs::write(
+            dist_info.join("RECORD"),
+            "attr/__init__.py,sha256=abc,100\n\
+             attrs/__init__.py,sha256=def,100\n\
+             attrs-23.1.0.dist-info/METADATA,,\n\
+             ../../bin/attrs,,\n",
+        )
+        .unwrap();
+
+        assert_eq!(top_level_names(&dist_info), vec!["attr", "attrs"]);
+    }
+}
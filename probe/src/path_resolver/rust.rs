@@ -1,12 +1,57 @@
 //! Rust-specific path resolver implementation.
-
-use super::PathResolver;
+//!
+//! Crates are resolved offline first: the nearest `Cargo.lock` pins the exact version
+//! in use (the one the root package depends on when several are locked), and its source
+//! decides where the code lives (a workspace member or path
+//! dependency, `$CARGO_HOME/registry/src/*`, or `$CARGO_HOME/git/checkouts`). Only
+//! when that fails, and offline mode is off, does the resolver fall back to
+//! `cargo metadata`, which can be slow and may access the network.
+
+use super::{compare_versions, PathResolver};
+use lazy_static::lazy_static;
+use regex::Regex;
+use serde::Deserialize;
 use serde_json::Value;
-use std::path::PathBuf;
+use std::fs;
+use std::path::{Path, PathBuf};
 use std::process::Command;
 
 /// A path resolver for Rust crates.
-pub struct RustPathResolver;
+pub struct RustPathResolver {
+    /// Directory to look for Cargo.lock from; the current directory when None
+    project_dir: Option<PathBuf>,
+    /// Cargo home; `CARGO_HOME` or `~/.cargo` when None
+    cargo_home: Option<PathBuf>,
+    /// Never run `cargo metadata`
+    offline: bool,
+}
+
+/// A `[[package]]` entry from Cargo.lock.
+#[derive(Debug, Clone, Deserialize)]
+struct LockedPackage {
+    name: String,
+    version: String,
+    /// None for workspace members and path dependencies
+    source: Option<String>,
+    /// "name", "name version" or "name version (source)" of each dependency
+    #[serde(default)]
+    dependencies: Vec<String>,
+}
+
+/// The parts of Cargo.lock the resolver reads.
+#[derive(Deserialize)]
+struct CargoLock {
+    #[serde(default)]
+    package: Vec<LockedPackage>,
+}
+
+/// Dependency tables of a manifest (or of a `[target.*]` table) that may hold path dependencies
+const DEPENDENCY_TABLES: &[&str] = &["dependencies", "dev-dependencies", "build-dependencies"];
+
+lazy_static! {
+    /// The "X.Y.Z" a version starts with in a registry directory name
+    static ref SEMVER_START: Regex = Regex::new(r"^\d+\.\d+\.\d+").unwrap();
+}
 
 impl Default for RustPathResolver {
     fn default() -> Self {
@@ -16,8 +61,120 @@ impl Default for RustPathResolver {
 
 impl RustPathResolver {
     /// Creates a new Rust path resolver.
+    ///
+    /// Offline lookups are tried first; set `PROBE_RUST_OFFLINE=1` to never fall back
+    /// to `cargo metadata`.
     pub fn new() -> Self {
-        RustPathResolver
+        RustPathResolver {
+            project_dir: None,
+            cargo_home: None,
+            offline: std::env::var("PROBE_RUST_OFFLINE").unwrap_or_default() == "1",
+        }
+    }
+
+    /// Creates a Rust path resolver that never runs `cargo metadata`.
+    pub fn offline() -> Self {
+        RustPathResolver {
+            offline: true,
+            ..Self::new()
+        }
+    }
+
+    /// Creates an offline Rust path resolver with explicit locations.
+    ///
+    /// # Arguments
+    ///
+    /// * `project_dir` - Directory to look for Cargo.lock from (searching upwards)
+    /// * `cargo_home` - The Cargo home containing `registry/src` and `git/checkouts`
+    pub fn with_locations(project_dir: PathBuf, cargo_home: PathBuf) -> Self {
+        RustPathResolver {
+            project_dir: Some(project_dir),
+            cargo_home: Some(cargo_home),
+            offline: true,
+        }
+    }
+
+    /// Gets the Cargo home directory.
+    fn cargo_home(&self) -> Result<PathBuf, String> {
+        if let Some(cargo_home) = &self.cargo_home {
+            return Ok(cargo_home.clone());
+        }
+        std::env::var("CARGO_HOME")
+            .or_else(|_| {
+                let home = std::env::var("HOME")
+                    .map_err(|e| format!("Failed to get HOME environment variable: {}", e))?;
+                Ok::<String, String>(format!("{}/.cargo", home))
+            })
+            .map(PathBuf::from)
+            .map_err(|e| format!("Failed to determine CARGO_HOME: {}", e))
+    }
+
+    /// Resolves a crate without running cargo, using Cargo.lock and the local caches.
+    fn resolve_offline(&self, crate_name: &str, version: Option<&str>) -> Result<PathBuf, String> {
+        let start_dir = match &self.project_dir {
+            Some(dir) => dir.clone(),
+            None => std::env::current_dir()
+                .map_err(|e| format!("Failed to get current directory: {}", e))?,
+        };
+        let cargo_home = self.cargo_home()?;
+
+        if let Some(lockfile) = find_lockfile(&start_dir) {
+            let content = fs::read_to_string(&lockfile)
+                .map_err(|e| format!("Failed to read {:?}: {}", lockfile, e))?;
+            let workspace_root = lockfile.parent().unwrap_or(Path::new("."));
+
+            let packages = parse_cargo_lock(&content);
+            let required = root_package(&start_dir, workspace_root)
+                .and_then(|root| required_version(&packages, &root, crate_name));
+            let mut candidates: Vec<LockedPackage> = packages
+                .into_iter()
+                .filter(|package| same_crate_name(&package.name, crate_name))
+                .filter(|package| version.is_none_or(|wanted| wanted == package.version))
+                .collect();
+            // Several versions may be locked at once; prefer the one the root package
+            // depends on, then the newest
+            candidates.sort_by(|a, b| {
+                let not_required =
+                    |package: &LockedPackage| required.as_deref() != Some(package.version.as_str());
+                not_required(a)
+                    .cmp(&not_required(b))
+                    .then_with(|| compare_versions(&b.version, &a.version))
+            });
+
+            for package in &candidates {
+                let found = match package.source.as_deref() {
+                    None => workspace_crates(workspace_root)
+                        .into_iter()
+                        .find(|(name, _)| *name == package.name)
+                        .map(|(_, dir)| dir),
+                    Some(source) if source.starts_with("git+") => {
+                        find_in_git_checkouts(&cargo_home, &package.name, source)
+                    }
+                    Some(_) => {
+                        find_registry_dir(&cargo_home, &package.name, Some(&package.version))
+                    }
+                };
+                if let Some(dir) = found {
+                    return Ok(dir);
+                }
+            }
+
+            // Another cached version would not be the code the project builds with
+            if !candidates.is_empty() {
+                let versions: Vec<&str> = candidates.iter().map(|p| p.version.as_str()).collect();
+                return Err(format!(
+                    "Rust crate {} is locked at {} but not downloaded (run `cargo fetch`)",
+                    crate_name,
+                    versions.join(", ")
+                ));
+            }
+        }
+
+        // Not locked: any cached registry copy will do
+        find_registry_dir(&cargo_home, crate_name, version).ok_or_else(|| match version {
+            Some(version) => format!("Could not find Rust crate: {}@{}", crate_name, version),
+            None => format!("Could not find Rust crate: {}", crate_name),
+        })
     }
 
     /// Gets the path to a Rust crate using cargo metadata.
@@ -87,66 +244,251 @@ impl RustPathResolver {
 
     /// Finds a crate in the Cargo registry cache.
     fn find_in_registry_cache(&self, crate_name: &str) -> Result<PathBuf, String> {
-        // Get the cargo home directory
-        let cargo_home = std::env::var("CARGO_HOME")
-            .or_else(|_| {
-                let home = std::env::var("HOME")
-                    .map_err(|e| format!("Failed to get HOME environment variable: {}", e))?;
-                Ok::<String, String>(format!("{}/.cargo", home))
-            })
-            .map_err(|e| format!("Failed to determine CARGO_HOME: {}", e))?;
+        let cargo_home = self.cargo_home()?;
+        find_registry_dir(&cargo_home, crate_name, None)
+            .ok_or_else(|| format!("Could not find Rust crate: {}", crate_name))
+    }
+}
 
-        // The registry cache is in $CARGO_HOME/registry/src
-        let registry_dir = PathBuf::from(cargo_home).join("registry").join("src");
+/// Finds the nearest Cargo.lock at or above a directory.
+fn find_lockfile(start_dir: &Path) -> Option<PathBuf> {
+    start_dir
+        .ancestors()
+        .map(|dir| dir.join("Cargo.lock"))
+        .find(|lockfile| lockfile.is_file())
+}
 
-        if !registry_dir.exists() {
-            return Err(format!(
-                "Cargo registry directory not found: {:?}",
-                registry_dir
-            ));
+/// Crate names are compared with `-` and `_` treated as equal, like cargo does.
+fn same_crate_name(a: &str, b: &str) -> bool {
+    a.len() == b.len()
+        && a.bytes()
+            .zip(b.bytes())
+            .all(|(x, y)| x == y || (matches!(x, b'-' | b'_') && matches!(y, b'-' | b'_')))
+}
+
+/// Splits `name[@version]` into the crate name and optional version.
+fn split_name_and_version(spec: &str) -> (&str, Option<&str>) {
+    match spec.split_once('@') {
+        Some((name, version)) if !version.is_empty() => (name, Some(version)),
+        Some((name, _)) => (name, None),
+        None => (spec, None),
+    }
+}
+
+/// Parses the `[[package]]` entries of a Cargo.lock file.
+fn parse_cargo_lock(content: &str) -> Vec<LockedPackage> {
+    match toml::from_str::<CargoLock>(content) {
+        Ok(lock) => lock.package,
+        Err(e) => {
+            if std::env::var("DEBUG").unwrap_or_default() == "1" {
+                println!("DEBUG: Failed to parse Cargo.lock: {}", e);
+            }
+            Vec::new()
         }
+    }
+}
 
-        // Look for the crate in all registry indices
-        let registry_indices = std::fs::read_dir(&registry_dir)
-            .map_err(|e| format!("Failed to read registry directory: {}", e))?;
+/// The package resolving happens for: the nearest package manifest at or above a
+/// directory, up to the workspace root.
+fn root_package(start_dir: &Path, workspace_root: &Path) -> Option<String> {
+    for dir in start_dir.ancestors() {
+        let manifest = fs::read_to_string(dir.join("Cargo.toml"))
+            .ok()
+            .and_then(|content| content.parse::<toml::Table>().ok());
+        if let Some(name) = manifest.as_ref().and_then(manifest_package_name) {
+            return Some(name);
+        }
+        if dir == workspace_root {
+            break;
+        }
+    }
+    None
+}
 
-        for index_entry in registry_indices {
-            let index_dir = index_entry
-                .map_err(|e| format!("Failed to read registry index entry: {}", e))?
-                .path();
+/// The version of a crate a package depends on, from the package's Cargo.lock entry.
+///
+/// Cargo.lock only writes the version when several versions of the crate are locked.
+fn required_version(packages: &[LockedPackage], package: &str, crate_name: &str) -> Option<String> {
+    packages
+        .iter()
+        .find(|locked| locked.name == package && locked.source.is_none())?
+        .dependencies
+        .iter()
+        .find_map(|dependency| {
+            let mut parts = dependency.split_whitespace();
+            let name = parts.next()?;
+            let version = parts.next()?;
+            same_crate_name(name, crate_name).then(|| version.to_string())
+        })
+}
 
-            if !index_dir.is_dir() {
-                continue;
-            }
+/// Reads the `[package] name` of a Cargo.toml.
+fn manifest_package_name(manifest: &toml::Table) -> Option<String> {
+    manifest
+        .get("package")?
+        .get("name")?
+        .as_str()
+        .map(String::from)
+}
 
-            // Look for directories that contain the crate name
-            let crates = std::fs::read_dir(&index_dir)
-                .map_err(|e| format!("Failed to read index directory: {}", e))?;
+/// The `path` of every dependency in a table of dependencies.
+fn dependency_paths(dependencies: Option<&toml::Value>) -> impl Iterator<Item = &str> {
+    dependencies
+        .and_then(toml::Value::as_table)
+        .into_iter()
+        .flat_map(|table| table.values())
+        .filter_map(|dependency| dependency.get("path")?.as_str())
+}
 
-            for crate_entry in crates {
-                let crate_dir = crate_entry
-                    .map_err(|e| format!("Failed to read crate entry: {}", e))?
-                    .path();
+/// Reads the directories a Cargo.toml links to: workspace members and path dependencies.
+fn manifest_linked_dirs(manifest_dir: &Path, manifest: &toml::Table) -> Vec<PathBuf> {
+    let mut dirs = Vec::new();
+
+    // Workspace members may use globs
+    let workspace = manifest.get("workspace");
+    let members = workspace
+        .and_then(|workspace| workspace.get("members"))
+        .and_then(toml::Value::as_array);
+    for member in members
+        .into_iter()
+        .flatten()
+        .filter_map(toml::Value::as_str)
+    {
+        let pattern = manifest_dir.join(member);
+        if let Ok(paths) = glob::glob(&pattern.to_string_lossy()) {
+            dirs.extend(paths.filter_map(Result::ok).filter(|path| path.is_dir()));
+        }
+    }
 
-                if !crate_dir.is_dir() {
-                    continue;
-                }
+    // `path` also appears in [lib] and [[bin]], so only dependency tables are followed:
+    // the package's own, per target, shared by the workspace, and patches per source
+    let targets = manifest
+        .get("target")
+        .and_then(toml::Value::as_table)
+        .into_iter()
+        .flat_map(|targets| targets.values())
+        .filter_map(toml::Value::as_table);
+    let mut tables: Vec<Option<&toml::Value>> = Vec::new();
+    for owner in std::iter::once(manifest).chain(targets) {
+        tables.extend(DEPENDENCY_TABLES.iter().map(|name| owner.get(*name)));
+    }
+    tables.push(workspace.and_then(|workspace| workspace.get("dependencies")));
+    let patches = manifest
+        .get("patch")
+        .and_then(toml::Value::as_table)
+        .into_iter()
+        .flat_map(|patches| patches.values());
+    tables.extend(patches.map(Some));
+
+    for table in tables {
+        dirs.extend(dependency_paths(table).map(|path| manifest_dir.join(path)));
+    }
 
-                // Check if this directory contains our crate
-                let dir_name = crate_dir
-                    .file_name()
-                    .ok_or_else(|| "Invalid directory name".to_string())?
-                    .to_string_lossy();
+    dirs
+}
 
-                if dir_name.starts_with(&format!("{}-", crate_name)) {
-                    // Found a matching crate directory
-                    return Ok(crate_dir);
-                }
+/// Lists the crates of a workspace: the root package, members and (transitive) path dependencies.
+fn workspace_crates(workspace_root: &Path) -> Vec<(String, PathBuf)> {
+    let mut crates = Vec::new();
+    let mut visited: Vec<PathBuf> = Vec::new();
+    let mut queue = vec![workspace_root.to_path_buf()];
+
+    while let Some(dir) = queue.pop() {
+        let dir = dir.canonicalize().unwrap_or(dir);
+        if visited.contains(&dir) {
+            continue;
+        }
+        visited.push(dir.clone());
+
+        let Ok(content) = fs::read_to_string(dir.join("Cargo.toml")) else {
+            continue;
+        };
+        let Ok(manifest) = content.parse::<toml::Table>() else {
+            continue;
+        };
+        if let Some(name) = manifest_package_name(&manifest) {
+            crates.push((name, dir.clone()));
+        }
+        queue.extend(manifest_linked_dirs(&dir, &manifest));
+    }
+
+    crates
+}
+
+/// Finds `name-version` in `$CARGO_HOME/registry/src/*`; the newest version when unpinned.
+fn find_registry_dir(
+    cargo_home: &Path,
+    crate_name: &str,
+    version: Option<&str>,
+) -> Option<PathBuf> {
+    let registry_dir = cargo_home.join("registry").join("src");
+    let mut found: Vec<(String, PathBuf)> = Vec::new();
+
+    for index_entry in fs::read_dir(registry_dir).ok()?.filter_map(Result::ok) {
+        let Ok(crates) = fs::read_dir(index_entry.path()) else {
+            continue;
+        };
+        for crate_entry in crates.filter_map(Result::ok) {
+            let dir_name = crate_entry.file_name().to_string_lossy().to_string();
+            // The version starts at the first '-' followed by "X.Y.Z"; names like
+            // "md-5" and versions like "1.0.0-alpha-2" also contain '-' and digits
+            let Some(split) = dir_name
+                .match_indices('-')
+                .map(|(i, _)| i)
+                .find(|&i| SEMVER_START.is_match(&dir_name[i + 1..]))
+            else {
+                continue;
+            };
+            let (name, dir_version) = (&dir_name[..split], &dir_name[split + 1..]);
+            if same_crate_name(name, crate_name)
+                && version.is_none_or(|wanted| wanted == dir_version)
+                && crate_entry.path().is_dir()
+            {
+                found.push((dir_version.to_string(), crate_entry.path()));
             }
         }
+    }
+
+    found.sort_by(|(a, _), (b, _)| compare_versions(b, a));
+    found.into_iter().next().map(|(_, path)| path)
+}
 
-        Err(format!("Could not find Rust crate: {}", crate_name))
+/// Finds a crate checked out from git, given its Cargo.lock source
+/// (`git+https://host/owner/repo?branch=x#<commit>`).
+fn find_in_git_checkouts(cargo_home: &Path, crate_name: &str, source: &str) -> Option<PathBuf> {
+    let (url, commit) = source.trim_start_matches("git+").split_once('#')?;
+    let url = url.split('?').next().unwrap_or(url);
+    let repo_name = url
+        .trim_end_matches('/')
+        .rsplit('/')
+        .next()?
+        .trim_end_matches(".git");
+
+    // Checkouts live in <repo>-<hash>/<short commit>/
+    let checkouts = cargo_home.join("git").join("checkouts");
+    for repo_entry in fs::read_dir(checkouts).ok()?.filter_map(Result::ok) {
+        let repo_dir_name = repo_entry.file_name().to_string_lossy().to_string();
+        if !repo_dir_name.starts_with(&format!("{}-", repo_name)) {
+            continue;
+        }
+        let Ok(revisions) = fs::read_dir(repo_entry.path()) else {
+            continue;
+        };
+        for revision in revisions.filter_map(Result::ok) {
+            let short = revision.file_name().to_string_lossy().to_string();
+            if short.is_empty() || !commit.starts_with(&short) {
+                continue;
+            }
+            // The checkout may be a whole workspace; find the crate inside it
+            if let Some((_, dir)) = workspace_crates(&revision.path())
+                .into_iter()
+                .find(|(name, _)| same_crate_name(name, crate_name))
+            {
+                return Some(dir);
+            }
+        }
     }
+    None
 }
 
 impl PathResolver for RustPathResolver {
@@ -162,9 +504,29 @@ impl PathResolver for RustPathResolver {
             return Err("Rust path (to Cargo.toml) cannot be empty".to_string());
         }
 
-        // For Rust, the entire path is treated as the module identifier
-        // We don't split into module/subpath for Rust paths
-        Ok((full_path_after_prefix.to_string(), None))
+        // Filesystem paths (to a crate directory or Cargo.toml) are kept whole
+        let path = Path::new(full_path_after_prefix);
+        if path.is_absolute()
+            || full_path_after_prefix.starts_with('.')
+            || full_path_after_prefix.ends_with("Cargo.toml")
+            || path.exists()
+        {
+            return Ok((full_path_after_prefix.to_string(), None));
+        }
+
+        if full_path_after_prefix.contains("..") {
+            return Err("Rust crate path cannot contain '..'".to_string());
+        }
+
+        // Otherwise: crate[@version]/sub/path
+        let path = full_path_after_prefix.trim_end_matches('/');
+        let mut parts = path.splitn(2, '/');
+        let module_name = parts.next().unwrap_or("").to_string();
+        if module_name.is_empty() || module_name.starts_with('@') {
+            return Err(format!("Invalid Rust crate format: {}", path));
+        }
+        let subpath_opt = parts.next().filter(|s| !s.is_empty()).map(String::from);
+        Ok((module_name, subpath_opt))
     }
 
     fn resolve(&self, crate_name: &str) -> Result<PathBuf, String> {
@@ -188,8 +550,14 @@ impl PathResolver for RustPathResolver {
             return Ok(crate_dir);
         }
 
-        // Otherwise, try to resolve it as a crate name
-        self.get_crate_path(crate_name)
+        // Otherwise, try to resolve it as a crate name, pinned to a version if given
+        let (name, version) = split_name_and_version(crate_name);
+        match self.resolve_offline(name, version) {
+            Ok(dir) => Ok(dir),
+            // `cargo metadata` cannot select a specific version, so pins stay offline
+            Err(e) if self.offline || version.is_some() => Err(e),
+            Err(_) => self.get_crate_path(name),
+        }
     }
 }
 
@@ -250,6 +618,114 @@ edition = "2021"
         assert_eq!(result.unwrap(), temp_dir.path());
     }
 
+    #[test]
+    fn test_find_registry_dir_splits_name_and_version() {
+        let cargo_home = tempfile::tempdir().unwrap();
+        let index = cargo_home.path().join("registry/src/index");
+        for dir in [
+            "md-5-0.10.6",
+            "md-5-0.9.1",
+            "serde-1.0.0-alpha-2",
+            "serde_json-1.0.1",
+        ] {
+            fs::create_dir_all(index.join(dir)).unwrap();
+        }
+
+        assert_eq!(
+            find_registry_dir(cargo_home.path(), "md-5", None),
+            Some(index.join("md-5-0.10.6"))
+        );
+        assert_eq!(
+            find_registry_dir(cargo_home.path(), "serde", None),
+            Some(index.join("serde-1.0.0-alpha-2"))
+        );
+        assert_eq!(find_registry_dir(cargo_home.path(), "md", None), None);
+    }
+
+    #[test]
+    fn test_parse_cargo_lock() {
+        let packages = parse_cargo_lock(
+            "version = 3\n\n[[package]]\nname = \"a\"\nversion = \"0.1.0\"\n\n[[package]]\nname = \"b\"\nversion = \"1.2.3\"\nsource = \"registry+https://example.com\"\n\n[metadata]\n",
+        );
+        assert_eq!(packages.len(), 2);
+        assert_eq!(packages[0].name, "a");
+        assert_eq!(packages[0].source, None);
+        assert_eq!(packages[1].version, "1.2.3");
+        assert_eq!(
+            packages[1].source.as_deref(),
+            Some("registry+https://example.com")
+        );
+    }
+
+    #[test]
+    fn test_workspace_crates_follows_manifest_tables() {
+        let workspace = tempfile::tempdir().unwrap();
+        let root = workspace.path();
+        let crates = [
+            ("crates/core", "core"),
+            ("crates/cli", "cli"),
+            ("vendor/inline", "inline-dep"),
+            ("vendor/section", "section-dep"),
+            ("vendor/unix", "unix-dep"),
+            ("vendor/patched", "patched"),
+            ("examples/demo", "demo"),
+        ];
+        for (dir, name) in crates {
+            fs::create_dir_all(root.join(dir)).unwrap();
+            fs::write(
+                root.join(dir).join("Cargo.toml"),
+                format!("[package]\nname = \"{}\"\nversion = \"0.1.0\"\n", name),
+            )
+            .unwrap();
+        }
+        fs::write(
+            root.join("Cargo.toml"),
+            r#"[package]
+name = "app"
+version = "0.1.0"
+
+[lib]
+path = "examples/demo/lib.rs"
+
+[workspace]
+members = [
+    "crates/*", # every crate
+]
+
+[dependencies]
+inline-dep = { version = "0.1", path = "vendor/inline" }
+
+[dependencies.section-dep]
+path = "vendor/section"
+
+[target.'cfg(unix)'.dependencies]
+unix-dep = { path = "vendor/unix" }
+
+[patch.crates-io]
+patched = { path = "vendor/patched" }
+"#,
+        )
+        .unwrap();
+
+        let mut names: Vec<String> = workspace_crates(root)
+            .into_iter()
+            .map(|(name, _)| name)
+            .collect();
+        names.sort();
+        assert_eq!(
+            names,
+            [
+                "app",
+                "cli",
+                "core",
+                "inline-dep",
+                "patched",
+                "section-dep",
+                "unix-dep"
+            ]
+        );
+    }
+
     #[test]
     fn test_rust_path_resolver_crate() {
         // Skip this test if cargo is not installed
@@ -3,13 +3,21 @@ use ast_grep_core::AstGrep;
 use ast_grep_language::SupportLang;
 use colored::*;
 use ignore::Walk;
+use probe::output_template::{add_common_fields, OutputTemplate, TemplateContext, TemplateTotals};
 use probe::path_resolver::resolve_path;
+use probe::workspace::{display_path, Workspace};
 use rayon::prelude::*; // Added import
-use std::fs;
 use std::path::{Path, PathBuf};
-use std::time::Instant;
+use std::time::{Duration, Instant};
+
+use crate::search::is_structured_format;
+use crate::search::source_files::read_source_file;
+use crate::watch::{
+    check_watch_format, diff_results, print_changed_files, watched_paths, FileWatcher, ResultChange,
+};
 
 /// Represents a match found by ast-grep
+#[derive(Debug, Clone)]
 pub struct AstMatch {
     pub file_path: PathBuf,
     pub line_start: usize,
@@ -17,6 +25,8 @@ pub struct AstMatch {
     pub column_start: usize,
     pub column_end: usize,
     pub matched_text: String,
+    /// Name of the workspace root the file belongs to; `file_path` is then relative to it
+    pub root: Option<String>,
 }
 
 /// Options for the ast-grep query
@@ -85,9 +95,16 @@ fn should_ignore_file(file_path: &Path, options: &QueryOptions) -> bool {
         return true;
     }
 
-    // Skip files that match custom ignore patterns
+    // Skip files that match custom ignore patterns; glob patterns are matched like
+    // the search command's ignores, anywhere in the path
     for pattern in options.ignore {
-        if path_str.contains(pattern) {
+        if pattern.contains(['*', '?', '[']) {
+            if glob::Pattern::new(&format!("**/{}", pattern))
+                .is_ok_and(|glob| glob.matches_path(file_path))
+            {
+                return true;
+            }
+        } else if path_str.contains(pattern.as_str()) {
             return true;
         }
     }
@@ -113,7 +130,7 @@ fn query_file(file_path: &Path, options: &QueryOptions) -> Result<Vec<AstMatch>>
     }
 
     // Read the file content
-    let content = fs::read_to_string(file_path)
+    let content = read_source_file(file_path)
         .with_context(|| format!("Failed to read file: {}", file_path.display()))?;
 
     // Get the language for ast-grep
@@ -208,6 +225,7 @@ fn query_file(file_path: &Path, options: &QueryOptions) -> Result<Vec<AstMatch>>
             column_start,
             column_end,
             matched_text: node.text().to_string(),
+            root: None,
         });
     }
 
@@ -293,6 +311,53 @@ pub fn perform_query(options: &QueryOptions) -> Result<Vec<AstMatch>> {
     Ok(all_matches)
 }
 
+/// Run a query across every root of a workspace
+///
+/// Each root is queried with the workspace and root ignore patterns added to `options.ignore`,
+/// and with its own languages unless `options.language` is set. Matches are labelled with their
+/// root and `max_results` applies to the combined matches.
+pub fn perform_workspace_query(
+    workspace: &Workspace,
+    options: &QueryOptions,
+) -> Result<Vec<AstMatch>> {
+    let mut all_matches = Vec::new();
+
+    for root in &workspace.roots {
+        let mut ignore = options.ignore.to_vec();
+        ignore.extend(workspace.ignores_for(root));
+
+        let languages: Vec<Option<&str>> = match options.language {
+            Some(language) => vec![Some(language)],
+            None if root.languages.is_empty() => vec![None],
+            None => root.languages.iter().map(|l| Some(l.as_str())).collect(),
+        };
+
+        for language in languages {
+            let root_options = QueryOptions {
+                path: &root.path,
+                language,
+                ignore: &ignore,
+                max_results: None,
+                ..*options
+            };
+
+            for mut m in perform_query(&root_options)? {
+                if let Some((labelled_root, relative)) = workspace.label_path(&m.file_path) {
+                    m.root = Some(labelled_root.name.clone());
+                    m.file_path = relative;
+                }
+                all_matches.push(m);
+            }
+        }
+    }
+
+    if let Some(max) = options.max_results {
+        all_matches.truncate(max);
+    }
+
+    Ok(all_matches)
+}
+
 /// Helper function to escape XML special characters
 fn escape_xml(s: &str) -> String {
     s.replace("&", "&amp;")
@@ -302,8 +367,132 @@ fn escape_xml(s: &str) -> String {
         .replace("'", "&apos;")
 }
 
+/// Convert a match to the JSON object shared by the JSON and JSON Lines formats
+fn match_to_json(m: &AstMatch) -> serde_json::Value {
+    let mut json_match = serde_json::json!({
+        "file": m.file_path.to_string_lossy(),
+        "lines": [m.line_start, m.line_end],
+        "node_type": "match",
+        "code": m.matched_text,
+        "column_start": m.column_start,
+        "column_end": m.column_end
+    });
+    if let Some(root) = &m.root {
+        json_match["root"] = serde_json::json!(root);
+    }
+    json_match
+}
+
+/// Render query matches with an output template
+///
+/// Each match has the common template fields and every `AstMatch` field.
+pub fn format_query_results_with_template(
+    matches: &[AstMatch],
+    template: &OutputTemplate,
+    pattern: &str,
+) -> Result<String> {
+    use crate::search::search_tokens::count_tokens;
+
+    let results = matches
+        .iter()
+        .map(|m| {
+            let mut value = match_to_json(m);
+            value["file_path"] = serde_json::json!(m.file_path.to_string_lossy());
+            value["line_start"] = m.line_start.into();
+            value["line_end"] = m.line_end.into();
+            value["matched_text"] = serde_json::json!(m.matched_text);
+            add_common_fields(
+                &mut value,
+                &m.file_path.to_string_lossy(),
+                (m.line_start, m.line_end),
+            );
+            value
+        })
+        .collect();
+
+    template.render(&TemplateContext {
+        command: "query",
+        query: Some(pattern.to_string()),
+        dry_run: false,
+        results,
+        totals: TemplateTotals {
+            count: matches.len(),
+            total_bytes: matches.iter().map(|m| m.matched_text.len()).sum(),
+            total_tokens: matches.iter().map(|m| count_tokens(&m.matched_text)).sum(),
+        },
+        original_input: None,
+        system_prompt: None,
+        user_instructions: None,
+    })
+}
+
+/// Rule ID reported for ast-grep pattern matches in SARIF output
+const SARIF_RULE_ID: &str = "probe/query";
+
+/// Build a SARIF 2.1.0 log with one result per match of `pattern`
+///
+/// Matches from a workspace root are relative to the `uriBaseId` named after that root.
+pub fn query_results_to_sarif(matches: &[AstMatch], pattern: &str) -> serde_json::Value {
+    let results: Vec<serde_json::Value> = matches
+        .iter()
+        .map(|m| {
+            let mut artifact_location = serde_json::json!({
+                "uri": m.file_path.to_string_lossy().replace('\\', "/"),
+            });
+            if let Some(root) = &m.root {
+                artifact_location["uriBaseId"] = serde_json::json!(root);
+            }
+            serde_json::json!({
+                "ruleId": SARIF_RULE_ID,
+                "ruleIndex": 0,
+                "level": "note",
+                "message": { "text": format!("Matched pattern: {}", pattern) },
+                "locations": [{
+                    "physicalLocation": {
+                        "artifactLocation": artifact_location,
+                        "region": {
+                            "startLine": m.line_start,
+                            "startColumn": m.column_start,
+                            "endLine": m.line_end,
+                            "endColumn": m.column_end,
+                            "snippet": { "text": m.matched_text },
+                        }
+                    }
+                }]
+            })
+        })
+        .collect();
+
+    serde_json::json!({
+        "$schema": "https://json.schemastore.org/sarif-2.1.0.json",
+        "version": "2.1.0",
+        "runs": [{
+            "tool": {
+                "driver": {
+                    "name": "probe",
+                    "version": env!("CARGO_PKG_VERSION"),
+                    "rules": [{
+                        "id": SARIF_RULE_ID,
+                        "name": "StructuralPattern",
+                        "shortDescription": { "text": pattern },
+                        "defaultConfiguration": { "level": "note" }
+                    }]
+                }
+            },
+            "columnKind": "unicodeCodePoints",
+            "results": results
+        }]
+    })
+}
+
 /// Format and print the query results
-pub fn format_and_print_query_results(matches: &[AstMatch], format: &str) -> Result<()> {
+///
+/// `pattern` is only used by the SARIF format, where it describes the rule.
+pub fn format_and_print_query_results(
+    matches: &[AstMatch],
+    format: &str,
+    pattern: &str,
+) -> Result<()> {
     match format {
         "color" | "terminal" => {
             for m in matches {
@@ -311,7 +500,7 @@ pub fn format_and_print_query_results(matches: &[AstMatch], format: &str) -> Res
                     "{}",
                     format!(
                         "{}:{}:{}",
-                        m.file_path.display(),
+                        display_path(m.root.as_deref(), &m.file_path.to_string_lossy()),
                         m.line_start,
                         m.column_start
                     )
@@ -325,7 +514,7 @@ pub fn format_and_print_query_results(matches: &[AstMatch], format: &str) -> Res
             for m in matches {
                 println!(
                     "{}:{}:{}",
-                    m.file_path.display(),
+                    display_path(m.root.as_deref(), &m.file_path.to_string_lossy()),
                     m.line_start,
                     m.column_start
                 );
@@ -337,7 +526,7 @@ pub fn format_and_print_query_results(matches: &[AstMatch], format: &str) -> Res
             for m in matches {
                 println!(
                     "**{}:{}:{}**",
-                    m.file_path.display(),
+                    display_path(m.root.as_deref(), &m.file_path.to_string_lossy()),
                     m.line_start,
                     m.column_start
                 );
@@ -364,19 +553,7 @@ pub fn format_and_print_query_results(matches: &[AstMatch], format: &str) -> Res
                 .sum::<usize>();
 
             // Create standardized results
-            let json_matches_standardized: Vec<_> = matches
-                .iter()
-                .map(|m| {
-                    serde_json::json!({
-                        "file": m.file_path.to_string_lossy(),
-                        "lines": [m.line_start, m.line_end],
-                        "node_type": "match",
-                        "code": m.matched_text,
-                        "column_start": m.column_start,
-                        "column_end": m.column_end
-                    })
-                })
-                .collect();
+            let json_matches_standardized: Vec<_> = matches.iter().map(match_to_json).collect();
 
             // Create the wrapper object
             let wrapper = serde_json::json!({
@@ -390,12 +567,26 @@ pub fn format_and_print_query_results(matches: &[AstMatch], format: &str) -> Res
 
             println!("{}", serde_json::to_string_pretty(&wrapper)?);
         }
+        "jsonl" => {
+            use crate::search::json_line;
+            for m in matches {
+                println!("{}", json_line("result", &match_to_json(m))?);
+            }
+            println!("{}", json_line("summary", &jsonl_summary(matches))?);
+        }
+        "sarif" => {
+            let sarif = query_results_to_sarif(matches, pattern);
+            println!("{}", serde_json::to_string_pretty(&sarif)?);
+        }
         "xml" => {
             println!("<?xml version=\"1.0\" encoding=\"UTF-8\"?>");
             println!("<probe_results>");
 
             for m in matches {
                 println!("  <result>");
+                if let Some(root) = &m.root {
+                    println!("    <root>{}</root>", escape_xml(root));
+                }
                 println!(
                     "    <file>{}</file>",
                     escape_xml(&m.file_path.to_string_lossy())
@@ -431,105 +622,276 @@ pub fn format_and_print_query_results(matches: &[AstMatch], format: &str) -> Res
         }
         _ => {
             // Default to color format
-            format_and_print_query_results(matches, "color")?;
+            format_and_print_query_results(matches, "color", pattern)?;
         }
     }
 
     Ok(())
 }
 
-/// Handle the query command
-pub fn handle_query(
-    pattern: &str,
-    path: &Path,
-    language: Option<&str>,
-    ignore: &[String],
-    allow_tests: bool,
-    max_results: Option<usize>,
+/// Summary record of the JSON Lines format
+fn jsonl_summary(matches: &[AstMatch]) -> serde_json::Value {
+    use crate::search::search_tokens::count_tokens;
+    serde_json::json!({
+        "count": matches.len(),
+        "total_bytes": matches.iter().map(|m| m.matched_text.len()).sum::<usize>(),
+        "total_tokens": matches.iter().map(|m| count_tokens(&m.matched_text)).sum::<usize>()
+    })
+}
+
+/// Format and print how the matches of a rerun differ from the previous run (see
+/// `query --watch`)
+///
+/// JSON Lines output has a result record with a `change` field per changed match, then
+/// the summary of all `matches` of the rerun. Text formats show the added and changed
+/// matches like query results, and where the removed ones were.
+pub fn format_and_print_query_changes(
+    changes: &[(ResultChange, &AstMatch)],
+    matches: &[AstMatch],
     format: &str,
+    pattern: &str,
 ) -> Result<()> {
-    // Only print information for non-JSON/XML formats
-    if format != "json" && format != "xml" {
-        println!("{} {}", "Pattern:".bold().green(), pattern);
-        println!("{} {}", "Path:".bold().green(), path.display());
-
-        // Print language if provided, otherwise show auto-detect
-        if let Some(lang) = language {
-            println!("{} {}", "Language:".bold().green(), lang);
-        } else {
-            println!("{} auto-detect", "Language:".bold().green());
+    if format == "jsonl" {
+        use crate::search::json_line;
+        for (change, m) in changes {
+            let mut record = match_to_json(m);
+            record["change"] = serde_json::to_value(change)?;
+            println!("{}", json_line("result", &record)?);
         }
+        println!("{}", json_line("summary", &jsonl_summary(matches))?);
+        return Ok(());
+    }
 
-        // Show advanced options if they differ from defaults
-        let mut advanced_options = Vec::<String>::new();
-        if allow_tests {
-            advanced_options.push("Including tests".to_string());
+    for (change, heading) in [
+        (ResultChange::Added, "Added:"),
+        (ResultChange::Changed, "Changed:"),
+        (ResultChange::Removed, "Removed:"),
+    ] {
+        let group: Vec<AstMatch> = changes
+            .iter()
+            .filter(|(c, _)| *c == change)
+            .map(|(_, m)| (*m).clone())
+            .collect();
+        if group.is_empty() {
+            continue;
         }
-        if let Some(max) = max_results {
-            advanced_options.push(format!("Max results: {}", max));
+        println!("{} {}", heading.bold().green(), group.len());
+        if change == ResultChange::Removed {
+            for m in &group {
+                let file = display_path(m.root.as_deref(), &m.file_path.to_string_lossy());
+                println!("  {}:{}:{}", file, m.line_start, m.column_start);
+            }
+        } else {
+            format_and_print_query_results(&group, format, pattern)?;
         }
+    }
+    println!("Now {} matches", matches.len());
+    Ok(())
+}
 
-        if !advanced_options.is_empty() {
-            println!(
-                "{} {}",
-                "Options:".bold().green(),
-                advanced_options.join(", ")
-            );
+/// Rerun a query whenever `watcher` sees files change, printing how its matches changed
+fn watch_query(
+    request: &QueryRequest,
+    watcher: &FileWatcher,
+    mut previous: Vec<AstMatch>,
+    format: &str,
+) -> Result<()> {
+    if !is_structured_format(format) {
+        println!();
+        println!("{}", "Watching for changes (Ctrl+C to stop)".bold());
+    }
+
+    loop {
+        let changed = watcher.wait_for_changes()?;
+        let matches = query(request.clone())?.matches;
+        let changes = diff_results(&previous, &matches);
+        // Print nothing when no match changed, or output redirected into a watched
+        // directory would trigger rerun after rerun
+        if !changes.is_empty() {
+            if !is_structured_format(format) {
+                print_changed_files(&changed);
+            }
+            format_and_print_query_changes(&changes, &matches, format, &request.pattern)?;
         }
+        previous = matches;
     }
+}
+
+/// A request for an ast-grep query, independent of how its results are shown
+#[derive(Debug, Clone)]
+pub struct QueryRequest {
+    /// AST pattern to search for
+    pub pattern: String,
+    /// File or directory to search; ignored when a workspace is given
+    pub path: PathBuf,
+    /// Optional workspace whose roots are all queried
+    pub workspace: Option<Workspace>,
+    /// Language to parse files as; detected from file extensions if None
+    pub language: Option<String>,
+    /// Custom patterns to ignore
+    pub ignore: Vec<String>,
+    pub allow_tests: bool,
+    pub max_results: Option<usize>,
+}
+
+/// The outcome of an ast-grep query
+#[derive(Debug, Clone)]
+pub struct QueryResponse {
+    pub matches: Vec<AstMatch>,
+    pub total_bytes: usize,
+    pub total_tokens: usize,
+    /// How long the query took
+    pub duration: Duration,
+}
+
+/// Run an ast-grep query without printing anything
+pub fn query(request: QueryRequest) -> Result<QueryResponse> {
+    use crate::search::search_tokens::count_tokens;
 
     let start_time = Instant::now();
 
     let options = QueryOptions {
-        path,
-        pattern,
-        language,
-        ignore,
+        path: &request.path,
+        pattern: &request.pattern,
+        language: request.language.as_deref(),
+        ignore: &request.ignore,
+        allow_tests: request.allow_tests,
+        max_results: request.max_results,
+        format: "",
+    };
+
+    let matches = match &request.workspace {
+        Some(workspace) => perform_workspace_query(workspace, &options)?,
+        None => perform_query(&options)?,
+    };
+
+    Ok(QueryResponse {
+        total_bytes: matches.iter().map(|m| m.matched_text.len()).sum(),
+        total_tokens: matches.iter().map(|m| count_tokens(&m.matched_text)).sum(),
+        matches,
+        duration: start_time.elapsed(),
+    })
+}
+
+/// Print the pattern, path and options of a query, for the human-readable formats
+fn print_query_header(request: &QueryRequest) {
+    println!("{} {}", "Pattern:".bold().green(), request.pattern);
+    match &request.workspace {
+        Some(workspace) => {
+            println!("{} {}", "Workspace:".bold().green(), workspace.describe())
+        }
+        None => println!("{} {}", "Path:".bold().green(), request.path.display()),
+    }
+
+    // Print language if provided, otherwise show auto-detect
+    if let Some(lang) = &request.language {
+        println!("{} {}", "Language:".bold().green(), lang);
+    } else {
+        println!("{} auto-detect", "Language:".bold().green());
+    }
+
+    // Show advanced options if they differ from defaults
+    let mut advanced_options = Vec::<String>::new();
+    if request.allow_tests {
+        advanced_options.push("Including tests".to_string());
+    }
+    if let Some(max) = request.max_results {
+        advanced_options.push(format!("Max results: {}", max));
+    }
+
+    if !advanced_options.is_empty() {
+        println!(
+            "{} {}",
+            "Options:".bold().green(),
+            advanced_options.join(", ")
+        );
+    }
+}
+
+/// Handle the query command
+///
+/// When a workspace is given, `path` is ignored and all of its roots are queried.
+/// When a template is given, it is used instead of `format`. With `watch`, the query is
+/// rerun whenever the searched files change, until the process is stopped.
+#[allow(clippy::too_many_arguments)]
+pub fn handle_query(
+    pattern: &str,
+    path: &Path,
+    workspace: Option<&Workspace>,
+    language: Option<&str>,
+    ignore: &[String],
+    allow_tests: bool,
+    max_results: Option<usize>,
+    format: &str,
+    template: Option<&OutputTemplate>,
+    watch: bool,
+) -> Result<()> {
+    let request = QueryRequest {
+        pattern: pattern.to_string(),
+        path: path.to_path_buf(),
+        workspace: workspace.cloned(),
+        language: language.map(str::to_string),
+        ignore: ignore.to_vec(),
         allow_tests,
         max_results,
-        format,
     };
 
-    let matches = perform_query(&options)?;
+    // Watch before the first run, so that no change made during it is missed
+    let watcher = if watch {
+        check_watch_format(format)?;
+        let paths = watched_paths(workspace, std::slice::from_ref(&request.path));
+        Some(FileWatcher::new(&paths)?)
+    } else {
+        None
+    };
+
+    // Only print information for human-readable formats
+    if !is_structured_format(format) {
+        print_query_header(&request);
+    }
 
-    // Calculate search time
-    let duration = start_time.elapsed();
+    let QueryResponse {
+        matches,
+        total_bytes,
+        total_tokens,
+        duration,
+    } = query(request.clone())?;
+
+    if let Some(template) = template {
+        print!(
+            "{}",
+            format_query_results_with_template(&matches, template, pattern)?
+        );
+        return Ok(());
+    }
 
     if matches.is_empty() {
-        // For JSON and XML formats, still call format_and_print_query_results
-        if format == "json" || format == "xml" {
-            format_and_print_query_results(&matches, format)?;
+        // For machine-readable formats, still call format_and_print_query_results
+        if is_structured_format(format) {
+            format_and_print_query_results(&matches, format, pattern)?;
         } else {
             // For other formats, print the "No results found" message
             println!("{}", "No results found.".yellow().bold());
             println!("Search completed in {:.2?}", duration);
         }
     } else {
-        // For non-JSON/XML formats, print search time
-        if format != "json" && format != "xml" {
+        // For human-readable formats, print search time
+        if !is_structured_format(format) {
             println!("Found {} matches in {:.2?}", matches.len(), duration);
             println!();
         }
 
-        format_and_print_query_results(&matches, format)?;
-
-        // Skip summary for JSON and XML formats
-        if format != "json" && format != "xml" {
-            // Calculate and display total bytes and tokens
-            let total_bytes: usize = matches.iter().map(|m| m.matched_text.len()).sum();
-            let total_tokens: usize = matches
-                .iter()
-                .map(|m| {
-                    // Import the count_tokens function locally to avoid unused import warning
-                    use crate::search::search_tokens::count_tokens;
-                    count_tokens(&m.matched_text)
-                })
-                .sum();
+        format_and_print_query_results(&matches, format, pattern)?;
 
+        // Skip summary for machine-readable formats
+        if !is_structured_format(format) {
             println!("Total bytes returned: {}", total_bytes);
             println!("Total tokens returned: {}", total_tokens);
         }
     }
 
+    if let Some(watcher) = &watcher {
+        return watch_query(&request, watcher, matches, format);
+    }
     Ok(())
 }
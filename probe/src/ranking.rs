@@ -1,4 +1,6 @@
+use crate::models::{RankingExplanation, TermExplanation};
 use crate::search::elastic_query::Expr;
+use crate::search::project_vocabulary::ProjectVocabulary;
 use crate::search::tokenization;
 use ahash::{AHashMap, AHashSet};
 use rust_stemmers::{Algorithm, Stemmer};
@@ -29,6 +31,10 @@ pub struct RankingParams<'a> {
     pub query: &'a str,
     /// Pre-tokenized content (optional)
     pub pre_tokenized: Option<&'a [Vec<String>]>,
+    /// Fuzzy variants of query terms, which count at `FUZZY_WEIGHT` (optional)
+    pub fuzzy_terms: Option<&'a std::collections::HashMap<String, Vec<String>>>,
+    /// Vocabulary of the searched project, for tokenizing and synonyms (optional)
+    pub vocabulary: Option<&'a ProjectVocabulary>,
 }
 
 /// Returns a reference to the global stemmer instance
@@ -39,15 +45,22 @@ pub fn get_stemmer() -> &'static Stemmer {
 
 /// Tokenizes text into lowercase words by splitting on whitespace and non-alphanumeric characters,
 /// removes stop words, and applies stemming. Also splits camelCase/PascalCase identifiers.
+#[allow(dead_code)]
 pub fn tokenize(text: &str) -> Vec<String> {
     tokenization::tokenize(text)
 }
 
 /// Preprocesses text with filename for search by tokenizing and removing duplicates
 /// This is used for filename matching - it adds the filename and its directory structure to the tokens
-pub fn preprocess_text_with_filename(text: &str, filename: &str) -> Vec<String> {
-    let mut tokens = tokenize(text);
-    let filename_tokens = tokenize(filename);
+///
+/// Text is tokenized with the word lists of the project vocabulary, if any.
+pub fn preprocess_text_with_filename(
+    text: &str,
+    filename: &str,
+    vocabulary: Option<&ProjectVocabulary>,
+) -> Vec<String> {
+    let mut tokens = tokenization::tokenize_for_project(text, vocabulary);
+    let filename_tokens = tokenization::tokenize_for_project(filename, vocabulary);
     tokens.extend(filename_tokens);
     tokens
 }
@@ -83,6 +96,10 @@ pub struct PrecomputedBm25Params<'a> {
     pub k1: f64,
     /// BM25 b parameter
     pub b: f64,
+    /// Fuzzy variants of query terms (optional)
+    pub fuzzy_terms: Option<&'a HashMap<String, Vec<String>>>,
+    /// Synonyms of query terms from the project vocabulary (optional)
+    pub synonyms: Option<&'a HashMap<String, Vec<String>>>,
 }
 
 /// Extracts unique terms from a query expression
@@ -188,6 +205,11 @@ fn bm25_single_token_optimized(token: &str, params: &PrecomputedBm25Params) -> f
     // Use precomputed IDF value (still using string for IDF lookup)
     let idf = *params.idfs.get(token).unwrap_or(&0.0);
 
+    bm25_weight(freq_in_doc, idf, params)
+}
+
+/// BM25 weight of a token occurring `freq_in_doc` times in the document
+fn bm25_weight(freq_in_doc: f64, idf: f64, params: &PrecomputedBm25Params) -> f64 {
     let tf_part = (freq_in_doc * (params.k1 + 1.0))
         / (freq_in_doc
             + params.k1 * (1.0 - params.b + params.b * (params.doc_len as f64 / params.avgdl)));
@@ -195,11 +217,28 @@ fn bm25_single_token_optimized(token: &str, params: &PrecomputedBm25Params) -> f
     idf * tf_part
 }
 
+/// BM25 of a query keyword: its own score and that of its synonyms, plus the reduced
+/// score of its fuzzy variants
+fn bm25_keyword(keyword: &str, params: &PrecomputedBm25Params) -> f64 {
+    let mut score = bm25_single_token_optimized(keyword, params);
+    if let Some(synonyms) = params.synonyms.and_then(|synonyms| synonyms.get(keyword)) {
+        for synonym in synonyms {
+            score += bm25_single_token_optimized(synonym, params);
+        }
+    }
+    if let Some(variants) = params.fuzzy_terms.and_then(|fuzzy| fuzzy.get(keyword)) {
+        for variant in variants {
+            score += FUZZY_WEIGHT * bm25_single_token_optimized(variant, params);
+        }
+    }
+    score
+}
+
 /// Sum BM25 for all keywords in a single "Term" node using precomputed IDF values
 fn score_term_bm25_optimized(keywords: &[String], params: &PrecomputedBm25Params) -> f64 {
     let mut total = 0.0;
     for kw in keywords {
-        total += bm25_single_token_optimized(kw, params);
+        total += bm25_keyword(kw, params);
     }
     total
 }
@@ -261,18 +300,57 @@ pub fn score_expr_bm25_optimized(expr: &Expr, params: &PrecomputedBm25Params) ->
     }
 }
 
-// -------------------------------------------------------------------------
-// This is your main entry point for ranking. It now does "pure BM25 like ES."
-// -------------------------------------------------------------------------
-pub fn rank_documents(params: &RankingParams) -> Vec<(usize, f64)> {
-    use rayon::prelude::*;
-    use std::cmp::Ordering;
+// BM25 parameters
+// These values are standard defaults for BM25 as established in academic literature:
+// k1=1.2 controls term frequency saturation (higher values give more weight to term frequency)
+// b=0.75 controls document length normalization (higher values give more penalty to longer documents)
+// See: Robertson, S. E., & Zaragoza, H. (2009). The Probabilistic Relevance Framework: BM25 and Beyond
+const BM25_K1: f64 = 1.2;
+const BM25_B: f64 = 0.75;
+
+/// Weight of a match on a fuzzy variant of a query term, relative to the term itself
+pub const FUZZY_WEIGHT: f64 = 0.5;
+
+/// Everything needed to score the documents of one ranking request
+struct Bm25Corpus {
+    parsed_expr: Expr,
+    query_token_map: QueryTokenMap,
+    tf_df_result: TfDfResult,
+    avgdl: f64,
+    precomputed_idfs: HashMap<String, f64>,
+    fuzzy_terms: Option<HashMap<String, Vec<String>>>,
+    synonyms: HashMap<String, Vec<String>>,
+}
 
+impl Bm25Corpus {
+    /// BM25 parameters for the document with the given index
+    fn params(&self, i: usize) -> PrecomputedBm25Params<'_> {
+        PrecomputedBm25Params {
+            doc_tf: &self.tf_df_result.term_frequencies[i],
+            doc_len: self.tf_df_result.document_lengths[i],
+            avgdl: self.avgdl,
+            idfs: &self.precomputed_idfs,
+            query_token_map: &self.query_token_map,
+            k1: BM25_K1,
+            b: BM25_B,
+            fuzzy_terms: self.fuzzy_terms.as_ref(),
+            synonyms: Some(&self.synonyms),
+        }
+    }
+}
+
+/// Parse the query and compute term and document frequencies, IDF values and the
+/// average document length. Returns None when the query can't be used for ranking.
+fn prepare_corpus(params: &RankingParams) -> Option<Bm25Corpus> {
     let debug_mode = std::env::var("DEBUG").unwrap_or_default() == "1";
 
     // 1) Parse the user query into an AST (Expr)
     //    If your code uses parse_query(...) from `elastic_query.rs`, do:
-    let parsed_expr = match crate::search::elastic_query::parse_query(params.query, false) {
+    let parsed_expr = match crate::search::elastic_query::parse_query_for_project(
+        params.query,
+        false,
+        params.vocabulary,
+    ) {
         Ok(expr) => expr,
         Err(e) => {
             if debug_mode {
@@ -286,12 +364,28 @@ pub fn rank_documents(params: &RankingParams) -> Vec<(usize, f64)> {
             );
             // In a future version, consider changing the return type to Result<Vec<(usize, f64)>, QueryError>
             // to properly propagate errors to the caller
-            return vec![];
+            return None;
         }
     };
 
     // 3) Extract query terms, create token mapping
-    let query_terms = extract_query_terms(&parsed_expr);
+    let mut query_terms = extract_query_terms(&parsed_expr);
+
+    // Fuzzy variants are scored like query terms, so they need indices and IDF values too
+    let fuzzy_terms: Option<HashMap<String, Vec<String>>> = params.fuzzy_terms.map(|fuzzy| {
+        fuzzy
+            .iter()
+            .filter(|(term, _)| query_terms.contains(*term))
+            .map(|(term, variants)| (term.clone(), variants.clone()))
+            .collect()
+    });
+    if let Some(fuzzy) = &fuzzy_terms {
+        query_terms.extend(fuzzy.values().flatten().cloned());
+    }
+
+    // So are synonyms from the project vocabulary
+    let synonyms = query_term_synonyms(&query_terms, params.vocabulary);
+    query_terms.extend(synonyms.values().flatten().cloned());
 
     // Generate query token map (maps each unique query term to a unique u8 index)
     let query_token_map = match generate_query_token_map(&query_terms) {
@@ -301,7 +395,7 @@ pub fn rank_documents(params: &RankingParams) -> Vec<(usize, f64)> {
                 eprintln!("DEBUG: Failed to generate query token map: {}", e);
             }
             eprintln!("WARNING: {}", e);
-            return vec![];
+            return None;
         }
     };
 
@@ -325,8 +419,11 @@ pub fn rank_documents(params: &RankingParams) -> Vec<(usize, f64)> {
             println!("DEBUG: Tokenizing documents for ranking");
         }
         // Tokenize documents on the fly
-        let tokenized_docs: Vec<Vec<String>> =
-            params.documents.iter().map(|doc| tokenize(doc)).collect();
+        let tokenized_docs: Vec<Vec<String>> = params
+            .documents
+            .iter()
+            .map(|doc| tokenization::tokenize_for_project(doc, params.vocabulary))
+            .collect();
         compute_tf_df_from_tokenized(&tokenized_docs, &query_token_map)
     };
 
@@ -345,13 +442,55 @@ pub fn rank_documents(params: &RankingParams) -> Vec<(usize, f64)> {
         );
     }
 
-    // 4) BM25 parameters
-    // These values are standard defaults for BM25 as established in academic literature:
-    // k1=1.2 controls term frequency saturation (higher values give more weight to term frequency)
-    // b=0.75 controls document length normalization (higher values give more penalty to longer documents)
-    // See: Robertson, S. E., & Zaragoza, H. (2009). The Probabilistic Relevance Framework: BM25 and Beyond
-    let k1 = 1.2;
-    let b = 0.75;
+    Some(Bm25Corpus {
+        parsed_expr,
+        query_token_map,
+        tf_df_result,
+        avgdl,
+        precomputed_idfs,
+        fuzzy_terms,
+        synonyms,
+    })
+}
+
+/// The synonyms of each query term that aren't query terms themselves
+fn query_term_synonyms(
+    query_terms: &HashSet<String>,
+    vocabulary: Option<&ProjectVocabulary>,
+) -> HashMap<String, Vec<String>> {
+    let Some(vocabulary) = vocabulary else {
+        return HashMap::new();
+    };
+    query_terms
+        .iter()
+        .filter_map(|term| {
+            let mut synonyms = vocabulary.synonyms(term);
+            if synonyms.is_empty() {
+                synonyms = vocabulary.synonyms(get_stemmer().stem(term).as_ref());
+            }
+            let synonyms: Vec<String> = synonyms
+                .iter()
+                .filter(|synonym| !query_terms.contains(*synonym))
+                .cloned()
+                .collect();
+            (!synonyms.is_empty()).then(|| (term.clone(), synonyms))
+        })
+        .collect()
+}
+
+// -------------------------------------------------------------------------
+// This is your main entry point for ranking. It now does "pure BM25 like ES."
+// -------------------------------------------------------------------------
+pub fn rank_documents(params: &RankingParams) -> Vec<(usize, f64)> {
+    use rayon::prelude::*;
+    use std::cmp::Ordering;
+
+    let debug_mode = std::env::var("DEBUG").unwrap_or_default() == "1";
+
+    let Some(corpus) = prepare_corpus(params) else {
+        return vec![];
+    };
+    let n_docs = params.documents.len();
 
     if debug_mode {
         println!(
@@ -362,26 +501,12 @@ pub fn rank_documents(params: &RankingParams) -> Vec<(usize, f64)> {
 
     // 5) Compute BM25 bool logic score for each doc in parallel
     // Use a stable collection method to ensure deterministic ordering
-    let scored_docs: Vec<(usize, Option<f64>)> = (0..tf_df_result.term_frequencies.len())
+    let scored_docs: Vec<(usize, Option<f64>)> = (0..corpus.tf_df_result.term_frequencies.len())
         .collect::<Vec<_>>() // Collect indices first to ensure stable ordering
         .par_iter() // Then parallelize
         .map(|&i| {
-            let doc_tf = &tf_df_result.term_frequencies[i];
-            let doc_len = tf_df_result.document_lengths[i];
-
-            // Create optimized BM25 parameters with precomputed IDF values
-            let precomputed_bm25_params = PrecomputedBm25Params {
-                doc_tf,
-                doc_len,
-                avgdl,
-                idfs: &precomputed_idfs,
-                query_token_map: &query_token_map,
-                k1,
-                b,
-            };
-
             // Evaluate doc's BM25 sum or None if excluded using optimized function
-            let bm25_score_opt = score_expr_bm25_optimized(&parsed_expr, &precomputed_bm25_params);
+            let bm25_score_opt = score_expr_bm25_optimized(&corpus.parsed_expr, &corpus.params(i));
 
             (i, bm25_score_opt)
         })
@@ -422,6 +547,148 @@ pub fn rank_documents(params: &RankingParams) -> Vec<(usize, f64)> {
     filtered_docs
 }
 
+/// Explains how `rank_documents` scores each document, for `--explain`
+///
+/// Returns one explanation per document, in document order. `filenames` holds the file
+/// name of each document, so that the part of the score coming from file name matches
+/// can be reported separately.
+pub fn explain_documents(params: &RankingParams, filenames: &[&str]) -> Vec<RankingExplanation> {
+    let Some(corpus) = prepare_corpus(params) else {
+        return vec![
+            RankingExplanation {
+                query: params.query.to_string(),
+                ..Default::default()
+            };
+            params.documents.len()
+        ];
+    };
+
+    let mut term_roles = Vec::new();
+    collect_term_roles(&corpus.parsed_expr, &mut term_roles);
+
+    // Each term is followed by its synonyms, and by its fuzzy variants, which count at a
+    // reduced weight
+    let mut query_terms: Vec<(String, &'static str, f64)> = Vec::new();
+    for (term, role) in term_roles {
+        let variants = corpus
+            .fuzzy_terms
+            .as_ref()
+            .and_then(|fuzzy| fuzzy.get(&term))
+            .cloned()
+            .unwrap_or_default();
+        let synonyms = corpus.synonyms.get(&term).cloned().unwrap_or_default();
+        query_terms.push((term, role, 1.0));
+        if role != "excluded" {
+            for synonym in synonyms {
+                query_terms.push((synonym, "synonym", 1.0));
+            }
+            for variant in variants {
+                query_terms.push((variant, "fuzzy", FUZZY_WEIGHT));
+            }
+        }
+    }
+
+    (0..params.documents.len())
+        .map(|i| {
+            let bm25_params = corpus.params(i);
+            let filename_tokens = filenames
+                .get(i)
+                .map(|f| tokenization::tokenize_for_project(f, params.vocabulary))
+                .unwrap_or_default();
+            let mut filename_boost = 0.0;
+
+            let terms = query_terms
+                .iter()
+                .map(|(term, role, weight)| {
+                    let frequency = corpus
+                        .query_token_map
+                        .get(term)
+                        .and_then(|index| bm25_params.doc_tf.get(index))
+                        .copied()
+                        .unwrap_or(0);
+                    let filename_frequency = filename_tokens.iter().filter(|t| *t == term).count();
+                    let idf = *corpus.precomputed_idfs.get(term).unwrap_or(&0.0);
+                    let bm25 = weight * bm25_single_token_optimized(term, &bm25_params);
+
+                    if *role != "excluded" && filename_frequency > 0 {
+                        let without_filename = frequency.saturating_sub(filename_frequency);
+                        filename_boost += bm25
+                            - if without_filename > 0 {
+                                weight * bm25_weight(without_filename as f64, idf, &bm25_params)
+                            } else {
+                                0.0
+                            };
+                    }
+
+                    TermExplanation {
+                        term: term.clone(),
+                        raw_matches: raw_matches(params.documents[i], term, params.vocabulary),
+                        role,
+                        frequency,
+                        filename_frequency,
+                        idf,
+                        bm25,
+                    }
+                })
+                .collect();
+
+            RankingExplanation {
+                query: corpus.parsed_expr.to_string(),
+                terms,
+                bm25_score: score_expr_bm25_optimized(&corpus.parsed_expr, &bm25_params),
+                filename_boost,
+                ..Default::default()
+            }
+        })
+        .collect()
+}
+
+/// Collect each distinct query term with its role, in query order
+fn collect_term_roles(expr: &Expr, terms: &mut Vec<(String, &'static str)>) {
+    match expr {
+        Expr::Term {
+            keywords,
+            required,
+            excluded,
+            ..
+        } => {
+            let role = if *excluded {
+                "excluded"
+            } else if *required {
+                "required"
+            } else {
+                "optional"
+            };
+            for keyword in keywords {
+                if !terms.iter().any(|(term, _)| term == keyword) {
+                    terms.push((keyword.clone(), role));
+                }
+            }
+        }
+        Expr::And(left, right) | Expr::Or(left, right) => {
+            collect_term_roles(left, terms);
+            collect_term_roles(right, terms);
+        }
+    }
+}
+
+/// The distinct words of a document that tokenize to the given term
+fn raw_matches(document: &str, term: &str, vocabulary: Option<&ProjectVocabulary>) -> Vec<String> {
+    let mut matches: Vec<String> = Vec::new();
+    for word in document.split(|c: char| !c.is_alphanumeric() && c != '_') {
+        if word.is_empty() || matches.iter().any(|m| m == word) {
+            continue;
+        }
+        if tokenization::tokenize_for_project(word, vocabulary)
+            .iter()
+            .any(|token| token == term)
+        {
+            matches.push(word.to_string());
+        }
+    }
+    matches
+}
+
 /// Computes term frequencies (TF) for each document, document frequencies (DF) for each term,
 /// and document lengths from pre-tokenized content.
 ///
@@ -548,6 +815,8 @@ mod tests {
             documents: &docs,
             query,
             pre_tokenized: None,
+            fuzzy_terms: None,
+            vocabulary: None,
         };
 
         let results = rank_documents(&params);
@@ -561,6 +830,86 @@ mod tests {
         assert!(results[0].1 < 10.0); // Upper bound based on typical BM25 behavior with small documents
     }
 
+    #[test]
+    fn test_explain_documents_matches_ranking() {
+        let docs = vec![
+            "// Filename: src/parser.rs\nfn parse_input(input: &str) {}",
+            "// Filename: src/main.rs\nfn main() { let parsed = 1; }",
+            "// Filename: src/other.rs\nfn unrelated() {}",
+        ];
+        let params = RankingParams {
+            documents: &docs,
+            query: "+parser input -main",
+            pre_tokenized: None,
+            fuzzy_terms: None,
+            vocabulary: None,
+        };
+
+        let ranked = rank_documents(&params);
+        let explanations =
+            explain_documents(&params, &["src/parser.rs", "src/main.rs", "src/other.rs"]);
+        assert_eq!(explanations.len(), 3);
+
+        // The explained score is the score the document is ranked with
+        for (i, score) in &ranked {
+            assert_eq!(explanations[*i].bm25_score, Some(*score));
+        }
+        // main.rs contains the excluded term
+        assert_eq!(explanations[1].bm25_score, None);
+
+        let parser = &explanations[0];
+        assert_eq!(parser.query, "((+parser OR input) AND -main)");
+        let roles: Vec<(&str, &str)> = parser
+            .terms
+            .iter()
+            .map(|t| (t.term.as_str(), t.role))
+            .collect();
+        assert_eq!(
+            roles,
+            vec![
+                ("parser", "required"),
+                ("input", "optional"),
+                ("main", "excluded")
+            ]
+        );
+
+        // "parser" only occurs in the file name, so its whole contribution is a file name boost
+        let term = &parser.terms[0];
+        assert_eq!(term.raw_matches, vec!["parser"]);
+        assert_eq!((term.frequency, term.filename_frequency), (1, 1));
+        assert!(term.idf > 0.0);
+        assert!((parser.filename_boost - term.bm25).abs() < 1e-9);
+        assert_eq!(parser.terms[1].raw_matches, vec!["parse_input", "input"]);
+
+        let total: f64 = parser.terms.iter().map(|t| t.bm25).sum();
+        assert!((parser.bm25_score.unwrap() - total).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_fuzzy_variants_count_at_reduced_weight() {
+        let docs = vec!["session cache", "other text"];
+        let exact = rank_documents(&RankingParams {
+            documents: &docs,
+            query: "session",
+            pre_tokenized: None,
+            fuzzy_terms: None,
+            vocabulary: None,
+        });
+
+        let fuzzy_terms =
+            std::collections::HashMap::from([("sesion".to_string(), vec!["session".to_string()])]);
+        let fuzzy = rank_documents(&RankingParams {
+            documents: &docs,
+            query: "sesion",
+            pre_tokenized: None,
+            fuzzy_terms: Some(&fuzzy_terms),
+            vocabulary: None,
+        });
+
+        assert_eq!((exact[0].0, fuzzy[0].0), (0, 0));
+        assert!((fuzzy[0].1 - FUZZY_WEIGHT * exact[0].1).abs() < 1e-9);
+    }
+
     #[test]
     fn test_bm25_scoring_with_pre_tokenized() {
         // A trivial test: 2 docs, 1 query, with pre-tokenized content
@@ -583,6 +932,8 @@ mod tests {
             documents: &docs,
             query,
             pre_tokenized: Some(&pre_tokenized),
+            fuzzy_terms: None,
+            vocabulary: None,
         };
 
         let results = rank_documents(&params);
@@ -610,6 +961,8 @@ mod tests {
             documents: &docs,
             query,
             pre_tokenized: None,
+            fuzzy_terms: None,
+            vocabulary: None,
         };
 
         let results = rank_documents(&params);
@@ -799,6 +1152,8 @@ mod tests {
             query_token_map: &query_token_map,
             k1: 1.2,
             b: 0.75,
+            fuzzy_terms: None,
+            synonyms: None,
         };
 
         // Test bm25_single_token_optimized
@@ -0,0 +1,393 @@
+//! Batches of searches run in a single pass over the files.
+//!
+//! Planners often issue many related searches at once. A batch prepares every query
+//! first, then walks the searched roots and reads each candidate file once, matching the
+//! patterns of all queries together. While the queries are then ranked and limited one
+//! after another, the contents of the matched files and the tokens of their blocks are
+//! shared between them, and parse trees are shared through the tree cache.
+//!
+//! A batch file is a JSON array of queries, each either a string or an object:
+//!
+//! ```json
+//! ["parse config", {"query": "load settings", "max_results": 5}]
+//! ```
+
+use anyhow::{Context, Result};
+use rayon::prelude::*;
+use regex::{Regex, RegexSet};
+use serde::Deserialize;
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::Ordering;
+use std::sync::{Arc, Mutex};
+
+use crate::models::{LimitedSearchResults, SearchResult};
+use crate::ranking;
+use crate::search::project_vocabulary::ProjectVocabulary;
+use crate::search::search_runner::{
+    candidate_files, label_workspace_result, pattern_lines_in_content, prepare_query,
+    read_searchable_file, run_probe, workspace_roots, FileTermMap, PreparedQuery, SearchRoot,
+    SearchTimings,
+};
+use crate::search::source_files::{read_source_file, FileLimits};
+use crate::search::{timeout, SearchOptions};
+use probe::workspace::Workspace;
+
+/// Contents of searched files, by the path they were found under
+type FileContents = HashMap<PathBuf, Arc<str>>;
+
+/// A query of a batch
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(from = "BatchQueryEntry")]
+pub struct BatchQuery {
+    pub query: String,
+    /// Overrides the batch's `max_results` for this query
+    pub max_results: Option<usize>,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum BatchQueryEntry {
+    Query(String),
+    Options(BatchQueryOptions),
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct BatchQueryOptions {
+    query: String,
+    #[serde(default)]
+    max_results: Option<usize>,
+}
+
+impl From<BatchQueryEntry> for BatchQuery {
+    fn from(entry: BatchQueryEntry) -> Self {
+        match entry {
+            BatchQueryEntry::Query(query) => BatchQuery {
+                query,
+                max_results: None,
+            },
+            BatchQueryEntry::Options(options) => BatchQuery {
+                query: options.query,
+                max_results: options.max_results,
+            },
+        }
+    }
+}
+
+/// The results of one query of a batch
+pub struct BatchResult {
+    pub query: String,
+    pub results: LimitedSearchResults,
+    /// Blocks dropped because an earlier query of the batch already returned them
+    pub duplicates_skipped: usize,
+}
+
+/// Parse the queries of a batch file
+pub fn parse_batch(json: &str) -> Result<Vec<BatchQuery>> {
+    let queries: Vec<BatchQuery> = serde_json::from_str(json)
+        .context("Expected a JSON array of query strings or {\"query\": ...} objects")?;
+    if queries.is_empty() {
+        anyhow::bail!("The batch contains no queries");
+    }
+    if let Some(position) = queries.iter().position(|q| q.query.trim().is_empty()) {
+        anyhow::bail!("Query {} of the batch is empty", position + 1);
+    }
+    Ok(queries)
+}
+
+/// Load the queries of a batch file, or of standard input when `path` is "-"
+pub fn load_batch_file(path: &Path) -> Result<Vec<BatchQuery>> {
+    let json = if path == Path::new("-") {
+        std::io::read_to_string(std::io::stdin()).context("Failed to read batch from stdin")?
+    } else {
+        std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read batch file: {}", path.display()))?
+    };
+    parse_batch(&json).with_context(|| format!("Invalid batch file: {}", path.display()))
+}
+
+/// Run a batch of queries with the same options, returning their results in order
+///
+/// `options.queries` is ignored. With a workspace every workspace root is searched, as
+/// in `perform_workspace_probe`. With `dedupe`, a block that overlaps a block returned
+/// for an earlier query is left out of the later query's results.
+pub fn perform_batch_probe(
+    options: &SearchOptions,
+    workspace: Option<&Workspace>,
+    queries: &[BatchQuery],
+    dedupe: bool,
+) -> Result<Vec<BatchResult>> {
+    let debug_mode = std::env::var("DEBUG").unwrap_or_default() == "1";
+    let roots = match workspace {
+        Some(workspace) => workspace_roots(workspace, options),
+        None => vec![SearchRoot {
+            path: options.path.to_path_buf(),
+            custom_ignores: options.custom_ignores.to_vec(),
+            language: options.language.map(str::to_string),
+        }],
+    };
+
+    let timeout_handle = timeout::start_timeout_thread(options.timeout);
+
+    let query_texts: Vec<Vec<String>> = queries.iter().map(|q| vec![q.query.clone()]).collect();
+    let query_options: Vec<SearchOptions> = queries
+        .iter()
+        .zip(&query_texts)
+        .map(|(query, texts)| SearchOptions {
+            queries: texts,
+            max_results: query.max_results.or(options.max_results),
+            ..*options
+        })
+        .collect();
+
+    let mut prepared = Vec::new();
+    for (i, query_options) in query_options.iter().enumerate() {
+        let query = prepare_query(query_options, &roots, &mut SearchTimings::default())
+            .with_context(|| format!("Query {} of the batch: {}", i + 1, queries[i].query))?;
+        prepared.push(query);
+    }
+
+    let start = std::time::Instant::now();
+    let (file_term_maps, contents) =
+        search_files_once(&roots, options.allow_tests, &options.file_limits, &prepared)?;
+    if debug_mode {
+        println!(
+            "DEBUG: Batch of {} queries searched the files in {:?} - {} files matched",
+            queries.len(),
+            start.elapsed(),
+            contents.len()
+        );
+    }
+
+    let context = BatchContext::new(contents);
+    let mut batch = Vec::new();
+    for ((query, query_options), (prepared, file_term_map)) in queries
+        .iter()
+        .zip(&query_options)
+        .zip(prepared.into_iter().zip(file_term_maps))
+    {
+        let results = match prepared {
+            Some(prepared) => run_probe(
+                query_options,
+                &roots,
+                Some((prepared, file_term_map)),
+                Some(&context),
+            )?,
+            // The query couldn't be parsed, so it has no results
+            None => LimitedSearchResults {
+                results: Vec::new(),
+                skipped_results: Vec::new(),
+                skipped_files: Vec::new(),
+                limits_applied: None,
+                cached_blocks_skipped: None,
+                suggestions: Vec::new(),
+            },
+        };
+        batch.push(BatchResult {
+            query: query.query.clone(),
+            results,
+            duplicates_skipped: 0,
+        });
+    }
+    timeout_handle.store(true, Ordering::SeqCst);
+
+    if dedupe {
+        dedupe_blocks(&mut batch);
+    }
+    if let Some(workspace) = workspace {
+        for entry in &mut batch {
+            let limited = &mut entry.results;
+            for result in limited
+                .results
+                .iter_mut()
+                .chain(limited.skipped_results.iter_mut())
+            {
+                label_workspace_result(workspace, result);
+            }
+        }
+    }
+    Ok(batch)
+}
+
+/// Read every candidate file once and match the patterns of all queries against it
+///
+/// Returns the file term map of every query, and the contents of the files any query
+/// matched.
+fn search_files_once(
+    roots: &[SearchRoot],
+    allow_tests: bool,
+    limits: &FileLimits,
+    prepared: &[Option<PreparedQuery>],
+) -> Result<(Vec<FileTermMap>, FileContents)> {
+    // The query and term indices of every pattern in the combined set
+    let mut owners: Vec<(usize, &HashSet<usize>)> = Vec::new();
+    let mut pattern_strings = Vec::new();
+    for (query_idx, query) in prepared.iter().enumerate() {
+        for (pattern, terms) in query.iter().flat_map(|q| &q.patterns) {
+            owners.push((query_idx, terms));
+            pattern_strings.push(format!("(?i){}", pattern));
+        }
+    }
+
+    let mut file_term_maps = vec![FileTermMap::new(); prepared.len()];
+    let mut contents = HashMap::new();
+    if pattern_strings.is_empty() {
+        return Ok((file_term_maps, contents));
+    }
+
+    let regex_set = RegexSet::new(&pattern_strings)?;
+    let individual_regexes = pattern_strings
+        .iter()
+        .map(|pattern| Regex::new(pattern))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let matched: Vec<_> = candidate_files(roots, allow_tests, limits)
+        .into_par_iter()
+        .filter_map(|file| {
+            let content = read_searchable_file(&file).ok()?;
+            let pattern_lines =
+                pattern_lines_in_content(&file, &content, &regex_set, &individual_regexes);
+            (!pattern_lines.is_empty()).then_some((file, content, pattern_lines))
+        })
+        .collect();
+
+    for (file, content, pattern_lines) in matched {
+        for (pattern_idx, lines) in pattern_lines {
+            let (query_idx, terms) = owners[pattern_idx];
+            let term_map = file_term_maps[query_idx].entry(file.clone()).or_default();
+            for &term in terms {
+                term_map.entry(term).or_default().extend(&lines);
+            }
+        }
+        contents.insert(file, Arc::from(content));
+    }
+
+    Ok((file_term_maps, contents))
+}
+
+/// Drop the blocks of every query that overlap a block of an earlier query
+fn dedupe_blocks(batch: &mut [BatchResult]) {
+    let mut seen: HashMap<String, Vec<(usize, usize)>> = HashMap::new();
+    let overlaps = |seen: &HashMap<String, Vec<(usize, usize)>>, result: &SearchResult| {
+        seen.get(&result.file).is_some_and(|ranges| {
+            ranges
+                .iter()
+                .any(|&(start, end)| result.lines.0 <= end && start <= result.lines.1)
+        })
+    };
+
+    for entry in batch {
+        let before = entry.results.results.len();
+        entry
+            .results
+            .results
+            .retain(|result| !overlaps(&seen, result));
+        entry.duplicates_skipped = before - entry.results.results.len();
+
+        for result in &entry.results.results {
+            seen.entry(result.file.clone())
+                .or_default()
+                .push(result.lines);
+        }
+    }
+}
+
+/// File contents and block tokens shared by the queries of a batch
+///
+/// A batch passes its context to every search it runs; searches outside of a batch read
+/// and tokenize their files themselves.
+pub struct BatchContext {
+    contents: FileContents,
+    /// File path and block code -> the block's tokens
+    tokens: Mutex<HashMap<(String, String), Vec<String>>>,
+}
+
+impl BatchContext {
+    fn new(contents: FileContents) -> Self {
+        BatchContext {
+            contents,
+            tokens: Mutex::new(HashMap::new()),
+        }
+    }
+}
+
+/// Read a file being searched, from the contents its batch already read if it can
+pub fn read_source(batch: Option<&BatchContext>, path: &Path) -> std::io::Result<Arc<str>> {
+    if let Some(content) = batch.and_then(|batch| batch.contents.get(path)) {
+        return Ok(content.clone());
+    }
+    read_source_file(path).map(Arc::from)
+}
+
+/// Tokenize a block of a file, reusing the tokens of its batch's earlier queries
+///
+/// The queries of a batch search the same roots, so they share one project vocabulary.
+pub fn tokenize_block(
+    batch: Option<&BatchContext>,
+    code: &str,
+    path: &str,
+    vocabulary: Option<&ProjectVocabulary>,
+) -> Vec<String> {
+    let Some(batch) = batch else {
+        return ranking::preprocess_text_with_filename(code, path, vocabulary);
+    };
+
+    let key = (path.to_string(), code.to_string());
+    if let Some(tokens) = batch.tokens.lock().unwrap().get(&key) {
+        return tokens.clone();
+    }
+    let tokens = ranking::preprocess_text_with_filename(code, path, vocabulary);
+    batch.tokens.lock().unwrap().insert(key, tokens.clone());
+    tokens
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_batch() {
+        let queries =
+            parse_batch(r#"["parse config", {"query": "load", "max_results": 2}]"#).unwrap();
+        assert_eq!(
+            queries,
+            vec![
+                BatchQuery {
+                    query: "parse config".to_string(),
+                    max_results: None,
+                },
+                BatchQuery {
+                    query: "load".to_string(),
+                    max_results: Some(2),
+                },
+            ]
+        );
+
+        assert!(parse_batch("[]").is_err());
+        assert!(parse_batch(r#"[" "]"#).is_err());
+        assert!(parse_batch(r#"[{"query": "a", "limit": 2}]"#).is_err());
+        assert!(parse_batch(r#"{"query": "a"}"#).is_err());
+    }
+
+    #[test]
+    fn test_tokenize_block_per_path_and_code() {
+        let batch = BatchContext::new(FileContents::new());
+        let blocks = [
+            ("src/cart.rs", "fn cart_total() {}"),
+            ("src/cart.rs", "fn shipping_cost() {}"),
+            ("src/order.rs", "fn cart_total() {}"),
+        ];
+
+        // Each block gets its own tokens, also when they are reused
+        for _ in 0..2 {
+            for (path, code) in blocks {
+                assert_eq!(
+                    tokenize_block(Some(&batch), code, path, None),
+                    ranking::preprocess_text_with_filename(code, path, None)
+                );
+            }
+        }
+        assert_eq!(batch.tokens.lock().unwrap().len(), blocks.len());
+    }
+}
@@ -1,8 +1,23 @@
-use crate::models::SearchResult;
+use crate::models::{MergedBlock, ParentBlock, RankingExplanation, SearchResult};
+use crate::search::search_tokens::count_tokens;
+use crate::search::source_files::read_source_file;
 use std::collections::HashMap;
-use std::fs::File;
-use std::io::{BufRead, BufReader};
-use std::path::{Path, PathBuf};
+use std::path::Path;
+
+/// Largest parent, in tokens, that blocks sharing it are expanded to
+pub const MAX_PARENT_TOKENS: usize = 2000;
+
+/// How `merge_ranked_blocks_with_options` merges blocks
+#[derive(Debug, Clone, Copy, Default)]
+pub struct MergeOptions {
+    /// Maximum number of lines between blocks to consider them adjacent (default: 5)
+    pub threshold: Option<usize>,
+    /// How many tokens expanding blocks to their shared parent may add to the results;
+    /// None when the search has no token limit
+    pub token_budget: Option<usize>,
+    /// Start nested blocks with the signature line of the block they are in
+    pub parent_context: bool,
+}
 
 /// Merges ranked search results that are adjacent or overlapping
 ///
@@ -15,12 +30,32 @@ use std::path::{Path, PathBuf};
 ///
 /// # Returns
 /// A new vector of SearchResult objects with adjacent blocks merged
+#[allow(dead_code)] // The search runner sets all the MergeOptions
 pub fn merge_ranked_blocks(
     results: Vec<SearchResult>,
     threshold: Option<usize>,
+) -> Vec<SearchResult> {
+    merge_ranked_blocks_with_options(
+        results,
+        MergeOptions {
+            threshold,
+            ..Default::default()
+        },
+    )
+}
+
+/// Merges ranked search results, see `merge_ranked_blocks`
+///
+/// Blocks nested in the same parent, like two methods of an `impl`, are first expanded
+/// to the whole parent when it fits `MAX_PARENT_TOKENS` and the token budget. Blocks
+/// are then merged by distance, but never across parents: a method is not glued to the
+/// function after its `impl`.
+pub fn merge_ranked_blocks_with_options(
+    results: Vec<SearchResult>,
+    options: MergeOptions,
 ) -> Vec<SearchResult> {
     let debug_mode = std::env::var("DEBUG").unwrap_or_default() == "1";
-    let threshold = threshold.unwrap_or(5); // Default to 5 lines if not specified
+    let threshold = options.threshold.unwrap_or(5); // Default to 5 lines if not specified
 
     if results.is_empty() {
         return results;
@@ -49,6 +84,11 @@ pub fn merge_ranked_blocks(
 
     let mut merged_results = Vec::new();
 
+    // Files with the best ranked blocks get the token budget first
+    let mut file_blocks: Vec<(String, Vec<SearchResult>)> = file_blocks.into_iter().collect();
+    file_blocks.sort_by_key(|(file, blocks)| (blocks.iter().map(rank_of).min(), file.clone()));
+    let mut token_budget = options.token_budget;
+
     // Process each file's blocks
     for (file_path, mut blocks) in file_blocks {
         if debug_mode {
@@ -59,16 +99,28 @@ pub fn merge_ranked_blocks(
             );
         }
 
+        // The file is only read when a parent is expanded or its signature is shown
+        let nested = blocks.iter().filter(|b| b.parent_block.is_some()).count();
+        let source_lines: Vec<String> = if nested > 1 || (nested > 0 && options.parent_context) {
+            read_source_file(Path::new(&file_path))
+                .map(|content| content.lines().map(str::to_string).collect())
+                .unwrap_or_default()
+        } else {
+            Vec::new()
+        };
+
         // If file only has one block, no need to merge
         if blocks.len() == 1 {
+            if options.parent_context {
+                add_parent_context(&mut blocks[0], &source_lines);
+            }
             merged_results.push(blocks.remove(0));
             continue;
         }
 
-        // Sort blocks by start line for merging
-        blocks.sort_by_key(|block| block.lines.0);
+        let mut blocks = expand_to_shared_parents(blocks, &source_lines, &mut token_budget);
 
-        // Sort blocks by start line
+        // Sort blocks by start line for merging
         blocks.sort_by_key(|block| block.lines.0);
 
         // Keep track of blocks we've already processed
@@ -113,7 +165,8 @@ pub fn merge_ranked_blocks(
                         // Merge the blocks
                         let merged_start = current_block.lines.0.min(next_block.lines.0);
                         let merged_end = current_block.lines.1.max(next_block.lines.1);
-                        let merged_code = merge_block_content(&current_block, next_block);
+                        let merged_code =
+                            merge_block_content(&current_block, next_block, &source_lines);
 
                         // Use node type from the highest-ranked block
                         let merged_node_type = if current_block.rank.unwrap_or(usize::MAX)
@@ -124,20 +177,21 @@ pub fn merge_ranked_blocks(
                             next_block.node_type.clone()
                         };
 
-                        // Combine scores and term statistics
-                        let merged_score = merge_scores(&current_block, next_block);
-                        let merged_term_stats = merge_term_statistics(&current_block, next_block);
+                        // The merged block is nested in whichever parent still encloses it
+                        let merged_parent = [&current_block.parent_block, &next_block.parent_block]
+                            .into_iter()
+                            .flatten()
+                            .find(|parent| {
+                                parent.lines.0 <= merged_start && merged_end <= parent.lines.1
+                            })
+                            .cloned();
 
                         // Update the current block
+                        absorb_block(&mut current_block, next_block);
                         current_block.lines = (merged_start, merged_end);
                         current_block.code = merged_code;
                         current_block.node_type = merged_node_type;
-                        current_block.score = merged_score.0;
-                        current_block.tfidf_score = merged_score.1;
-                        current_block.bm25_score = merged_score.2;
-                        current_block.new_score = merged_score.3;
-                        current_block.block_unique_terms = merged_term_stats.0;
-                        current_block.block_total_matches = merged_term_stats.1;
+                        current_block.parent_block = merged_parent;
 
                         // Mark this block as processed
                         processed_indices.insert(j);
@@ -147,6 +201,10 @@ pub fn merge_ranked_blocks(
                 }
             }
 
+            if options.parent_context {
+                add_parent_context(&mut current_block, &source_lines);
+            }
+
             // Add the merged block to results
             merged_blocks.push(current_block);
         }
@@ -221,9 +279,15 @@ pub fn should_merge_blocks(block1: &SearchResult, block2: &SearchResult, thresho
         && is_function_like(&block2.node_type))
         || (block2.node_type.contains("comment") && is_function_like(&block1.node_type));
 
+    // Nearby blocks nested in different parents are unrelated, e.g. the last method of
+    // an `impl` and the function after it; standalone comments have no parent recorded
+    let different_parents = block1.parent_block != block2.parent_block
+        && !block1.node_type.contains("comment")
+        && !block2.node_type.contains("comment");
+
     let should_merge = overlapping
-        || distance <= threshold
-        || (comment_with_function && distance <= threshold * 2);
+        || (!different_parents
+            && (distance <= threshold || (comment_with_function && distance <= threshold * 2)));
 
     if debug_mode {
         println!("DEBUG: Considering merging blocks - Block1: type='{}' lines {}-{}, Block2: type='{}' lines {}-{}, threshold: {}",
@@ -237,6 +301,217 @@ pub fn should_merge_blocks(block1: &SearchResult, block2: &SearchResult, thresho
     should_merge
 }
 
+/// Rank of a block for ordering, unranked blocks last
+fn rank_of(block: &SearchResult) -> usize {
+    block.rank.unwrap_or(usize::MAX)
+}
+
+/// Combine the scores, term statistics and explanation of `next` into `current`
+fn absorb_block(current: &mut SearchResult, next: &SearchResult) {
+    let merged_score = merge_scores(current, next);
+    let merged_term_stats = merge_term_statistics(current, next);
+    current.score = merged_score.0;
+    current.tfidf_score = merged_score.1;
+    current.bm25_score = merged_score.2;
+    current.new_score = merged_score.3;
+    current.block_unique_terms = merged_term_stats.0;
+    current.block_total_matches = merged_term_stats.1;
+    current.changed_since_seen = current.changed_since_seen.or(next.changed_since_seen);
+    if let Some(explanation) = current.explanation.as_mut() {
+        if let Some(next_explanation) = &next.explanation {
+            *explanation = merge_explanations(explanation, next_explanation);
+        }
+        explanation.merged_blocks.push(MergedBlock {
+            lines: next.lines,
+            rank: next.rank,
+            score: next.score,
+        });
+        if let Some(next_explanation) = &next.explanation {
+            explanation
+                .merged_blocks
+                .extend(next_explanation.merged_blocks.iter().cloned());
+        }
+    }
+}
+
+/// Combine the explanations of two merged blocks
+///
+/// A merged block keeps the best BM25 score of its blocks, so the scores come from the
+/// explanation of the block that scored higher, while the occurrences of each term are
+/// counted over the code of both blocks. The file name is shared and counted once.
+fn merge_explanations(
+    current: &RankingExplanation,
+    next: &RankingExplanation,
+) -> RankingExplanation {
+    let score = |explanation: &RankingExplanation| explanation.bm25_score.unwrap_or(f64::MIN);
+    let (best, other) = if score(next) > score(current) {
+        (next, current)
+    } else {
+        (current, next)
+    };
+
+    let mut merged = best.clone();
+    for term in &mut merged.terms {
+        let Some(other_term) = other
+            .terms
+            .iter()
+            .find(|t| t.term == term.term && t.role == term.role)
+        else {
+            continue;
+        };
+        term.frequency += other_term
+            .frequency
+            .saturating_sub(other_term.filename_frequency);
+        for raw_match in &other_term.raw_matches {
+            if !term.raw_matches.contains(raw_match) {
+                term.raw_matches.push(raw_match.clone());
+            }
+        }
+    }
+    merged.matched_by_filename = current.matched_by_filename || next.matched_by_filename;
+    merged.merged_blocks = current.merged_blocks.clone();
+    merged
+}
+
+/// Replace the blocks of a file that share a parent with the whole parent
+///
+/// A parent is used when at least two blocks are nested in it, it is at most
+/// `MAX_PARENT_TOKENS` long, and the tokens it adds fit the remaining `token_budget`,
+/// which is reduced accordingly. The best ranked block of the group keeps its rank.
+fn expand_to_shared_parents(
+    blocks: Vec<SearchResult>,
+    source_lines: &[String],
+    token_budget: &mut Option<usize>,
+) -> Vec<SearchResult> {
+    let debug_mode = std::env::var("DEBUG").unwrap_or_default() == "1";
+
+    let mut groups: Vec<(ParentBlock, Vec<usize>)> = Vec::new();
+    for (idx, block) in blocks.iter().enumerate() {
+        let Some(parent) = &block.parent_block else {
+            continue;
+        };
+        match groups.iter_mut().find(|(p, _)| p == parent) {
+            Some((_, members)) => members.push(idx),
+            None => groups.push((parent.clone(), vec![idx])),
+        }
+    }
+    groups.retain(|(_, members)| members.len() > 1);
+    groups.sort_by_key(|(_, members)| members.iter().map(|&i| rank_of(&blocks[i])).min());
+
+    let mut expanded = Vec::new();
+    let mut absorbed = vec![false; blocks.len()];
+    for (parent, mut members) in groups {
+        let (start, end) = parent.lines;
+        if start == 0 || end > source_lines.len() {
+            continue;
+        }
+        let code = source_lines[start - 1..end].join("\n");
+        let tokens = count_tokens(&code);
+        let replaced: usize = members.iter().map(|&i| count_tokens(&blocks[i].code)).sum();
+        let added = tokens.saturating_sub(replaced);
+        let fits_budget = token_budget.is_none_or(|budget| added <= budget);
+        if tokens > MAX_PARENT_TOKENS || !fits_budget {
+            if debug_mode {
+                println!(
+                    "DEBUG: Not expanding {} blocks to parent {} at lines {}-{} ({} tokens)",
+                    members.len(),
+                    parent.node_type,
+                    start,
+                    end,
+                    tokens
+                );
+            }
+            continue;
+        }
+        if let Some(budget) = token_budget.as_mut() {
+            *budget -= added;
+        }
+        if debug_mode {
+            println!(
+                "DEBUG: Expanding {} blocks to parent {} at lines {}-{}",
+                members.len(),
+                parent.node_type,
+                start,
+                end
+            );
+        }
+
+        members.sort_by_key(|&i| rank_of(&blocks[i]));
+        let mut block = blocks[members[0]].clone();
+        for &idx in &members[1..] {
+            absorb_block(&mut block, &blocks[idx]);
+        }
+        for &idx in &members {
+            absorbed[idx] = true;
+        }
+        block.lines = parent.lines;
+        block.code = code;
+        block.node_type = parent.node_type;
+        block.parent_block = None;
+        expanded.push(block);
+    }
+
+    blocks
+        .into_iter()
+        .zip(absorbed)
+        .filter(|(_, absorbed)| !absorbed)
+        .map(|(block, _)| block)
+        .chain(expanded)
+        .collect()
+}
+
+/// Start a nested block with the signature line of its parent, marking the lines between
+/// them as skipped like `merge_block_content` does
+fn add_parent_context(block: &mut SearchResult, source_lines: &[String]) {
+    let Some(parent) = &block.parent_block else {
+        return;
+    };
+    let (parent_start, start) = (parent.lines.0, block.lines.0);
+    if parent_start == 0 || parent_start >= start || parent_start > source_lines.len() {
+        return;
+    }
+    let mut code = source_lines[parent_start - 1].trim_end().to_string();
+    if start - parent_start > 1 {
+        code.push_str(&format!(
+            "\n... lines {}-{} skipped...",
+            parent_start + 1,
+            start - 1
+        ));
+    }
+    code.push('\n');
+    code.push_str(&block.code);
+    block.code = code;
+    block.lines.0 = parent_start;
+}
+
+/// The source line of each line of a block's code, `None` for the placeholders that
+/// `merge_block_content` and `add_parent_context` put in place of skipped lines
+pub fn source_line_numbers(code: &str, first_line: usize) -> Vec<Option<usize>> {
+    let mut next_line = first_line;
+    code.split('\n')
+        .map(|line| match skipped_lines(line) {
+            Some((_, last_skipped)) => {
+                next_line = last_skipped + 1;
+                None
+            }
+            None => {
+                next_line += 1;
+                Some(next_line - 1)
+            }
+        })
+        .collect()
+}
+
+/// The range of lines a placeholder line stands for
+fn skipped_lines(line: &str) -> Option<(usize, usize)> {
+    let range = line.strip_prefix("... lines ")?;
+    let range = range
+        .strip_suffix(" skipped...")
+        .or_else(|| range.strip_suffix(" should be included ..."))?;
+    let (first, last) = range.split_once('-')?;
+    Some((first.parse().ok()?, last.parse().ok()?))
+}
+
 /// Helper function to check if a node type represents a function-like construct
 fn is_function_like(node_type: &str) -> bool {
     node_type.contains("function")
@@ -250,10 +525,15 @@ fn is_function_like(node_type: &str) -> bool {
 /// # Arguments
 /// * `block1` - First search result
 /// * `block2` - Second search result
+/// * `source_lines` - Lines of the file, or empty when the caller didn't read it
 ///
 /// # Returns
 /// The merged code content
-fn merge_block_content(block1: &SearchResult, block2: &SearchResult) -> String {
+fn merge_block_content(
+    block1: &SearchResult,
+    block2: &SearchResult,
+    source_lines: &[String],
+) -> String {
     // Extract line ranges
     let (start1, end1) = block1.lines;
     let (start2, end2) = block2.lines;
@@ -271,53 +551,35 @@ fn merge_block_content(block1: &SearchResult, block2: &SearchResult) -> String {
         return block2.code.clone();
     }
 
-    // We need to extract the merged content from the file
-    // For simplicity, we'll use the content from the blocks we have
-    // This is not perfect, as we might be missing some lines in between,
-    // but it's a reasonable approximation without loading the file again
-
-    // Convert content to lines
-    let lines1: Vec<&str> = block1.code.lines().collect();
-    let lines2: Vec<&str> = block2.code.lines().collect();
-
-    // Map lines to their absolute positions in the file
+    // Map lines to their absolute positions in the file; the placeholders of blocks
+    // merged before are left out, their gaps are filled or marked again below
     let mut line_map: HashMap<usize, String> = HashMap::new();
 
-    for (i, line) in lines1.iter().enumerate() {
-        let abs_pos = start1 + i;
-        line_map.insert(abs_pos, line.to_string());
+    for (line, abs_pos) in block1
+        .code
+        .lines()
+        .zip(source_line_numbers(&block1.code, start1))
+    {
+        if let Some(abs_pos) = abs_pos {
+            line_map.insert(abs_pos, line.to_string());
+        }
     }
 
-    for (i, line) in lines2.iter().enumerate() {
-        let abs_pos = start2 + i;
-        line_map.entry(abs_pos).or_insert_with(|| line.to_string());
+    for (line, abs_pos) in block2
+        .code
+        .lines()
+        .zip(source_line_numbers(&block2.code, start2))
+    {
+        if let Some(abs_pos) = abs_pos {
+            line_map.entry(abs_pos).or_insert_with(|| line.to_string());
+        }
     }
 
     // Build the merged content from the line map
     let mut merged_lines = Vec::new();
     let mut current_line = merged_start;
-    let debug_mode = std::env::var("DEBUG").unwrap_or_default() == "1";
-
-    // Try to open the file to fill small gaps
-    let file_path = Path::new(&block1.file);
-    let file_result = File::open(file_path);
-    let file_content_available = file_result.is_ok();
-    let _reader = file_result.map(BufReader::new).ok(); // Used for debugging purposes only
-
-    if debug_mode {
-        println!(
-            "DEBUG: Current working directory: {:?}",
-            std::env::current_dir().unwrap_or_else(|_| PathBuf::from("unknown"))
-        );
-        println!(
-            "DEBUG: Attempting to read file: {:?}",
-            file_path
-                .canonicalize()
-                .unwrap_or_else(|_| PathBuf::from(file_path))
-        );
-        println!("DEBUG: File exists: {}", file_path.exists());
-        println!("DEBUG: File can be opened: {}", file_content_available);
-    }
+    // The file is only read for a small gap when the caller didn't load it
+    let mut loaded_lines: Option<Vec<String>> = None;
 
     while current_line <= merged_end {
         if let Some(line_content) = line_map.get(&current_line) {
@@ -335,67 +597,25 @@ fn merge_block_content(block1: &SearchResult, block2: &SearchResult) -> String {
 
             let gap_size = gap_end - gap_start + 1;
 
-            // For small gaps (less than 10 lines), try to read the actual content
+            // For small gaps (less than 10 lines), use the actual content of the file
             if gap_size < 10 {
-                if file_content_available {
-                    if debug_mode {
-                        println!(
-                            "DEBUG: Attempting to fill small gap from line {} to {} from file {}",
-                            gap_start, gap_end, block1.file
-                        );
-                    }
-
-                    // Read the file content directly instead of using a reader clone
-                    // which might have its position already moved forward
-                    let file_result = File::open(Path::new(&block1.file));
-
-                    if let Ok(file) = file_result {
-                        let reader = BufReader::new(file);
-
-                        if debug_mode {
-                            println!("DEBUG: Created fresh file reader for gap");
-                        }
-
-                        // Read the file line by line
-                        let mut lines_read = Vec::new();
-                        let mut current_line_in_file = 1;
-
-                        for line_content in reader.lines().map_while(Result::ok) {
-                            if current_line_in_file >= gap_start && current_line_in_file <= gap_end
-                            {
-                                lines_read.push(line_content);
-                            }
-
-                            current_line_in_file += 1;
-
-                            if current_line_in_file > gap_end {
-                                break;
-                            }
-                        }
-
-                        // Add the actual content for the gap
-                        if !lines_read.is_empty() {
-                            if debug_mode {
-                                println!(
-                                    "DEBUG: Successfully read {} lines for gap",
-                                    lines_read.len()
-                                );
-                            }
-                            merged_lines.extend(lines_read);
-                            current_line = gap_end + 1;
-                            continue;
-                        } else if debug_mode {
-                            println!("DEBUG: No lines were read for the gap (empty lines)");
-                        }
-                    } else if debug_mode {
-                        println!("DEBUG: Could not create fresh file reader");
-                    }
-                } else if debug_mode {
-                    println!("DEBUG: File content not available for {}", block1.file);
+                let file_lines: &[String] = if source_lines.is_empty() {
+                    loaded_lines.get_or_insert_with(|| {
+                        read_source_file(Path::new(&block1.file))
+                            .map(|content| content.lines().map(str::to_string).collect())
+                            .unwrap_or_default()
+                    })
+                } else {
+                    source_lines
+                };
+                if gap_start >= 1 && gap_end <= file_lines.len() {
+                    merged_lines.extend_from_slice(&file_lines[gap_start - 1..gap_end]);
+                    current_line = gap_end + 1;
+                    continue;
                 }
 
-                // For small gaps where we couldn't read the file or no lines were read,
-                // include a special placeholder indicating we want to include these lines
+                // When the file couldn't be read, include a placeholder marking the
+                // lines as wanted
                 merged_lines.push(format!(
                     "... lines {}-{} should be included ...",
                     gap_start, gap_end
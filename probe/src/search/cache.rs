@@ -1,21 +1,79 @@
 use anyhow::Result;
 use rand::{distributions::Alphanumeric, Rng};
 use serde::{Deserialize, Serialize};
-use std::collections::hash_map::DefaultHasher;
 use std::collections::{HashMap, HashSet};
-use std::fs::{create_dir_all, File};
-use std::hash::{Hash, Hasher};
+use std::fs::{create_dir_all, File, OpenOptions};
 use std::io::{Read, Write};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
-use crate::models::SearchResult;
+use crate::language::tree_cache::compute_content_hash;
+use crate::models::{LimitedSearchResults, SearchResult};
+
+/// Default time after which an unused session expires (24 hours).
+/// Can be overridden with the PROBE_SESSION_TTL environment variable (in seconds).
+const DEFAULT_SESSION_TTL_SECS: u64 = 24 * 60 * 60;
+
+/// Minimum time between two garbage collection runs over the sessions directory
+const GC_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+/// Number of blocks per page in `--page` mode when no `--max-results` is given
+pub const DEFAULT_PAGE_SIZE: usize = 10;
+
+/// Current time as seconds since the Unix epoch
+fn now_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+/// Get the session time-to-live, honouring PROBE_SESSION_TTL (in seconds)
+pub fn session_ttl() -> Duration {
+    let secs = std::env::var("PROBE_SESSION_TTL")
+        .ok()
+        .and_then(|value| value.parse::<u64>().ok())
+        .unwrap_or(DEFAULT_SESSION_TTL_SECS);
+    Duration::from_secs(secs)
+}
+
+/// Get the directory holding the session cache files
+pub fn sessions_dir() -> PathBuf {
+    let home_dir = dirs::home_dir().unwrap_or_else(|| PathBuf::from("."));
+    home_dir.join(".cache").join("probe").join("sessions")
+}
+
+/// An exclusive lock on the sessions directory, released when dropped
+///
+/// Every read-modify-write of a session file happens under this lock, so concurrent
+/// searches in the same session don't lose each other's updates.
+pub struct SessionLock {
+    _file: File,
+}
+
+impl SessionLock {
+    /// Block until the lock on the given sessions directory is acquired
+    pub fn acquire(dir: &Path) -> Result<Self> {
+        create_dir_all(dir)?;
+        let file = OpenOptions::new()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(dir.join(".lock"))?;
+        file.lock()?;
+        Ok(Self { _file: file })
+    }
+}
 
 /// Generate a hash for a query string
 /// This is used to create a unique identifier for each query
+///
+/// The hash names the session cache files, so it has to be stable across builds. Files
+/// named by the standard library's hasher, which earlier versions used, are not found
+/// under it; they hold block identifiers of the old "file.rs:23-45" form, which would
+/// match nothing anyway, and are removed by the session GC once they expire.
 pub fn hash_query(query: &str) -> String {
-    let mut hasher = DefaultHasher::new();
-    query.hash(&mut hasher);
-    format!("{:x}", hasher.finish())
+    format!("{:x}", compute_content_hash(query))
 }
 
 /// Structure to hold cache data for a session
@@ -25,22 +83,72 @@ pub struct SessionCache {
     pub session_id: String,
     /// Query hash for this cache
     pub query_hash: String,
-    /// Set of block identifiers that have been seen in this session
-    /// Format: "file.rs:23-45" (file path with start-end line numbers)
+    /// Keys of the blocks that have been seen in this session, as made by `block_keys`:
+    /// the symbol path of a block ("file.rs#function:parse") or a hash of its content
     pub block_identifiers: HashSet<String>,
+    /// The query this cache belongs to
+    #[serde(default)]
+    pub query: String,
+    /// When the cache was created (seconds since the Unix epoch)
+    #[serde(default)]
+    pub created_at: u64,
+    /// When the cache was last written (seconds since the Unix epoch)
+    #[serde(default)]
+    pub updated_at: u64,
+    /// Block keys served by `--page`, in order: page N is `pages[N - 1]`
+    #[serde(default)]
+    pub pages: Vec<Vec<String>>,
+    /// Blocks seen in this session by block key (see `block_keys`)
+    #[serde(default)]
+    pub blocks: HashMap<String, SeenBlock>,
+}
+
+/// A block recorded in a session
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SeenBlock {
+    /// Normalized path of the file the block is in
+    pub file: String,
+    /// Line range where the block was last seen
+    pub lines: (usize, usize),
+    /// Hash of the block content with whitespace normalized (see `normalized_content_hash`)
+    pub content_hash: String,
+}
+
+/// Whether a block was seen before in a session
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BlockStatus {
+    /// Not seen before
+    New,
+    /// Seen before with the same content, possibly at other lines
+    Seen,
+    /// Seen before, but its content has changed since then
+    Changed,
 }
 
 impl SessionCache {
     /// Create a new session cache with the given ID and query hash
     pub fn new(session_id: String, query_hash: String) -> Self {
+        let now = now_secs();
         Self {
             session_id,
             query_hash,
             block_identifiers: HashSet::new(),
+            query: String::new(),
+            created_at: now,
+            updated_at: now,
+            pages: Vec::new(),
+            blocks: HashMap::new(),
         }
     }
 
+    /// Whether the cache has not been written for longer than `ttl`
+    pub fn is_expired(&self, ttl: Duration, now: u64) -> bool {
+        self.updated_at.saturating_add(ttl.as_secs()) < now
+    }
+
     /// Load a session cache from disk
+    ///
+    /// Missing, unreadable and expired caches are replaced by a new empty cache.
     pub fn load(session_id: &str, query_hash: &str) -> Result<Self> {
         let debug_mode = std::env::var("DEBUG").unwrap_or_default() == "1";
         let cache_path = Self::get_cache_path(session_id, query_hash);
@@ -83,6 +191,15 @@ impl SessionCache {
         match serde_json::from_str(&contents) {
             Ok(cache) => {
                 let cache: SessionCache = cache;
+                if cache.is_expired(session_ttl(), now_secs()) {
+                    if debug_mode {
+                        println!(
+                            "DEBUG: Cache at {:?} has expired, creating new cache",
+                            cache_path
+                        );
+                    }
+                    return Ok(Self::new(session_id.to_string(), query_hash.to_string()));
+                }
                 if debug_mode {
                     println!(
                         "DEBUG: Successfully loaded cache with {} entries",
@@ -134,13 +251,15 @@ impl SessionCache {
             }
         };
 
-        // Write to the cache file
-        match File::create(&cache_path) {
+        // Write to a temporary file and rename it, so readers never see a partial file
+        let tmp_path = cache_path.with_extension(format!("json.tmp-{}", std::process::id()));
+        match File::create(&tmp_path) {
             Ok(mut file) => {
                 if let Err(e) = file.write_all(json.as_bytes()) {
                     if debug_mode {
                         println!("DEBUG: Error writing to cache file: {}", e);
                     }
+                    let _ = std::fs::remove_file(&tmp_path);
                     return Err(e.into());
                 }
             }
@@ -151,6 +270,13 @@ impl SessionCache {
                 return Err(e.into());
             }
         }
+        if let Err(e) = std::fs::rename(&tmp_path, &cache_path) {
+            if debug_mode {
+                println!("DEBUG: Error replacing cache file: {}", e);
+            }
+            let _ = std::fs::remove_file(&tmp_path);
+            return Err(e.into());
+        }
 
         if debug_mode {
             println!("DEBUG: Successfully saved cache to disk");
@@ -169,14 +295,73 @@ impl SessionCache {
         self.block_identifiers.insert(block_id);
     }
 
+    /// Check whether a block was seen before, given its block key
+    ///
+    /// A block is seen when a block with the same key and content was recorded, or when
+    /// identical content was recorded elsewhere in the same file under another key. Caches
+    /// written before blocks were keyed by content fall back to the line range.
+    pub fn block_status(&self, key: &str, result: &SearchResult) -> BlockStatus {
+        let content_hash = normalized_content_hash(&result.code);
+
+        if let Some(seen) = self.blocks.get(key) {
+            return if seen.content_hash == content_hash {
+                BlockStatus::Seen
+            } else {
+                BlockStatus::Changed
+            };
+        }
+
+        let file = normalize_path(&result.file);
+        if self
+            .blocks
+            .values()
+            .any(|seen| seen.content_hash == content_hash && seen.file == file)
+        {
+            return BlockStatus::Seen;
+        }
+
+        if self.blocks.is_empty() && self.is_cached(&generate_cache_key(result)) {
+            return BlockStatus::Seen;
+        }
+
+        BlockStatus::New
+    }
+
+    /// Number of blocks seen in this session
+    ///
+    /// Caches written before blocks were keyed by content only have line-range keys.
+    pub fn block_count(&self) -> usize {
+        if self.blocks.is_empty() {
+            self.block_identifiers.len()
+        } else {
+            self.blocks.len()
+        }
+    }
+
+    /// The page `--page` served a block on, by block key
+    pub fn block_page(&self, key: &str) -> Option<usize> {
+        self.pages
+            .iter()
+            .position(|page| page.iter().any(|served| served == key))
+            .map(|index| index + 1)
+    }
+
+    /// Record a block as seen under the given block key
+    pub fn record_block(&mut self, key: String, result: &SearchResult) {
+        self.add_to_cache(generate_cache_key(result));
+        self.blocks.insert(
+            key,
+            SeenBlock {
+                file: normalize_path(&result.file),
+                lines: result.lines,
+                content_hash: normalized_content_hash(&result.code),
+            },
+        );
+    }
+
     /// Get the path to the cache file
     pub fn get_cache_path(session_id: &str, query_hash: &str) -> PathBuf {
-        let home_dir = dirs::home_dir().unwrap_or_else(|| PathBuf::from("."));
-        home_dir
-            .join(".cache")
-            .join("probe")
-            .join("sessions")
-            .join(format!("{}_{}.json", session_id, query_hash))
+        sessions_dir().join(format!("{}_{}.json", session_id, query_hash))
     }
 }
 /// Normalize a file path for consistent cache keys
@@ -199,7 +384,54 @@ pub fn generate_cache_key(result: &SearchResult) -> String {
     format!("{}:{}-{}", normalized_path, result.lines.0, result.lines.1)
 }
 
+/// Hash block content with whitespace normalized, so that re-indenting or re-wrapping
+/// blank lines doesn't count as a change
+///
+/// Session files store these hashes, so they use the stable content hash of the tree cache
+/// rather than the standard library's hasher.
+pub fn normalized_content_hash(code: &str) -> String {
+    let normalized: Vec<String> = code
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| line.split_whitespace().collect::<Vec<_>>().join(" "))
+        .collect();
+    format!("{:x}", compute_content_hash(&normalized.join("\n")))
+}
+
+/// Generate the block keys for a set of results
+///
+/// A block key is the symbol path the parser found for the block, "file.rs#function:parse"
+/// or "file.rs#function_item:Parser.parse", so that it survives edits that shift lines.
+/// Blocks without a symbol are keyed by their content instead ("file.rs#block@<hash>").
+/// Definitions sharing a symbol path in the same file, such as overloads, get a "#2",
+/// "#3", ... suffix by their position in the file, so that every search keys them alike.
+pub fn block_keys(results: &[SearchResult]) -> Vec<String> {
+    results
+        .iter()
+        .map(|result| {
+            let file = normalize_path(&result.file);
+            match &result.symbol {
+                Some(name) => match result.symbol_occurrence {
+                    Some(occurrence) if occurrence > 1 => {
+                        format!("{}#{}:{}#{}", file, result.node_type, name, occurrence)
+                    }
+                    _ => format!("{}#{}:{}", file, result.node_type, name),
+                },
+                None => format!(
+                    "{}#{}@{}",
+                    file,
+                    result.node_type,
+                    normalized_content_hash(&result.code)
+                ),
+            }
+        })
+        .collect()
+}
+
 /// Filter search results using the cache without adding to the cache
+///
+/// Blocks seen before with the same content are removed, and blocks seen before whose
+/// content changed are kept with `changed_since_seen` set.
 pub fn filter_results_with_cache(
     results: &[SearchResult],
     session_id: &str,
@@ -244,23 +476,28 @@ pub fn filter_results_with_cache(
     let mut skipped_count = 0;
 
     // For existing sessions, filter the results
+    let keys = block_keys(results);
     let filtered_results: Vec<SearchResult> = results
         .iter()
-        .filter(|result| {
-            let cache_key = generate_cache_key(result);
-            let is_cached = cache.is_cached(&cache_key);
-
-            if is_cached {
+        .zip(&keys)
+        .filter_map(|(result, key)| match cache.block_status(key, result) {
+            BlockStatus::Seen => {
                 if debug_mode && skipped_count < 5 {
-                    println!("DEBUG: Skipping cached block: {}", cache_key);
+                    println!("DEBUG: Skipping cached block: {}", key);
                 }
                 skipped_count += 1;
-                false
-            } else {
-                true
+                None
+            }
+            BlockStatus::Changed => {
+                if debug_mode {
+                    println!("DEBUG: Cached block changed since last seen: {}", key);
+                }
+                let mut result = result.clone();
+                result.changed_since_seen = Some(true);
+                Some(result)
             }
+            BlockStatus::New => Some(result.clone()),
         })
-        .cloned()
         .collect();
 
     if debug_mode {
@@ -338,45 +575,62 @@ pub fn filter_matched_lines_with_cache(
             );
         }
 
+        // Line ranges of this file that hold blocks seen before. Content-keyed blocks only
+        // count when the file still has the same content at those lines; caches written
+        // before blocks were keyed by content use the recorded line ranges as they are.
+        let path_str = file_path.to_string_lossy();
+        let normalized_path = normalize_path(&path_str);
+        let cached_ranges: Vec<(usize, usize)> = if cache.blocks.is_empty() {
+            cache
+                .block_identifiers
+                .iter()
+                .filter_map(|block_id| {
+                    let (file_part, range) = block_id.rsplit_once(':')?;
+                    let (start, end) = range.split_once('-')?;
+                    (normalize_path(file_part) == normalized_path)
+                        .then_some((start.parse().ok()?, end.parse().ok()?))
+                })
+                .collect()
+        } else {
+            let seen_in_file: Vec<&SeenBlock> = cache
+                .blocks
+                .values()
+                .filter(|seen| seen.file == normalized_path)
+                .collect();
+            if seen_in_file.is_empty() {
+                Vec::new()
+            } else {
+                let content =
+                    crate::search::source_files::read_source_file(file_path).unwrap_or_default();
+                let lines: Vec<&str> = content.lines().collect();
+                seen_in_file
+                    .into_iter()
+                    .filter(|seen| {
+                        let (start, end) = seen.lines;
+                        start >= 1
+                            && start <= end
+                            && end <= lines.len()
+                            && normalized_content_hash(&lines[start - 1..end].join("\n"))
+                                == seen.content_hash
+                    })
+                    .map(|seen| seen.lines)
+                    .collect()
+            }
+        };
+
         // Check each line against the cache
         let mut lines_to_remove = HashSet::new();
         for &line_num in &all_lines {
-            // Create a simple cache key for this line
-            // Format: "file.rs:line_num"
-            let path_str = file_path.to_string_lossy();
-            let normalized_path = normalize_path(&path_str);
-            let line_cache_key = format!("{}:{}", normalized_path, line_num);
-
-            // Check if this line is part of a cached block
-            let is_cached = cache.block_identifiers.iter().any(|block_id| {
-                // Parse the block ID to get file and line range
-                if let Some(colon_pos) = block_id.find(':') {
-                    if let Some(dash_pos) = block_id[colon_pos + 1..].find('-') {
-                        let file_part = &block_id[..colon_pos];
-                        let start_line_str = &block_id[colon_pos + 1..colon_pos + 1 + dash_pos];
-                        let end_line_str = &block_id[colon_pos + 1 + dash_pos + 1..];
-
-                        if let (Ok(start_line), Ok(end_line)) = (
-                            start_line_str.parse::<usize>(),
-                            end_line_str.parse::<usize>(),
-                        ) {
-                            // Check if this line is within a cached block from the same file
-                            let path_str = file_path.to_string_lossy();
-                            let normalized_path = normalize_path(&path_str);
-                            let normalized_file_part = normalize_path(file_part);
-
-                            return normalized_file_part == normalized_path
-                                && line_num >= start_line
-                                && line_num <= end_line;
-                        }
-                    }
-                }
-                false
-            });
+            let is_cached = cached_ranges
+                .iter()
+                .any(|&(start, end)| line_num >= start && line_num <= end);
 
             if is_cached {
                 if debug_mode && skipped_count < 5 {
-                    println!("DEBUG: Skipping cached line: {}", line_cache_key);
+                    println!(
+                        "DEBUG: Skipping cached line: {}:{}",
+                        normalized_path, line_num
+                    );
                 }
                 lines_to_remove.insert(line_num);
                 skipped_count += 1;
@@ -430,8 +684,12 @@ pub fn add_results_to_cache(results: &[SearchResult], session_id: &str, query: &
     let debug_mode = std::env::var("DEBUG").unwrap_or_default() == "1";
     let query_hash = hash_query(query);
 
+    // Hold the lock across load and save so concurrent writers don't lose updates
+    let _lock = SessionLock::acquire(&sessions_dir())?;
+
     // Load or create the cache
     let mut cache = SessionCache::load(session_id, &query_hash)?;
+    cache.query = query.to_string();
 
     if debug_mode {
         println!(
@@ -447,15 +705,14 @@ pub fn add_results_to_cache(results: &[SearchResult], session_id: &str, query: &
 
     // Add all results to the cache
     let mut new_entries = 0;
-    for result in results {
-        let cache_key = generate_cache_key(result);
-        if !cache.is_cached(&cache_key) {
+    for (result, key) in results.iter().zip(block_keys(results)) {
+        if cache.block_status(&key, result) != BlockStatus::Seen {
             new_entries += 1;
             if debug_mode && new_entries <= 5 {
-                println!("DEBUG: Adding new cache entry: {}", cache_key);
+                println!("DEBUG: Adding new cache entry: {}", key);
             }
         }
-        cache.add_to_cache(cache_key);
+        cache.record_block(key, result);
     }
 
     if debug_mode {
@@ -467,11 +724,324 @@ pub fn add_results_to_cache(results: &[SearchResult], session_id: &str, query: &
     }
 
     // Save the updated cache
+    cache.updated_at = now_secs();
     cache.save()?;
 
+    collect_garbage_if_due(&sessions_dir());
+
     Ok(())
 }
 
+/// The outcome of selecting a page in `--page` mode
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PageSelection {
+    /// The page that was returned
+    pub page: usize,
+    /// Number of matching blocks that have not been served on any page yet
+    pub remaining: usize,
+    /// Whether the page had been served before and was returned again unchanged
+    pub replayed: bool,
+}
+
+/// Select a page of results for `--page` mode
+///
+/// Pages are recorded in the session, which makes the page number a stable cursor:
+/// asking for a page that was already served returns the same blocks again, and asking
+/// for the page after the last one served claims the next blocks that haven't been seen,
+/// limited by `limit`. Claiming happens under the session lock, so concurrent callers
+/// never get the same blocks for different pages.
+///
+/// # Arguments
+/// * `results` - All ranked results for the query, best first
+/// * `session_id` - The session the pages belong to
+/// * `query` - The raw query string
+/// * `page` - The requested page number, starting at 1
+/// * `limit` - Applies the page size and other limits to the unseen results
+pub fn select_page<F>(
+    results: Vec<SearchResult>,
+    session_id: &str,
+    query: &str,
+    page: usize,
+    limit: F,
+) -> Result<(LimitedSearchResults, PageSelection)>
+where
+    F: FnOnce(Vec<SearchResult>) -> LimitedSearchResults,
+{
+    if page == 0 {
+        anyhow::bail!("Page numbers start at 1");
+    }
+
+    let query_hash = hash_query(query);
+    let _lock = SessionLock::acquire(&sessions_dir())?;
+    let mut cache = SessionCache::load(session_id, &query_hash)?;
+
+    let keys = block_keys(&results);
+    let mut remaining = 0;
+    let mut candidates: Vec<(String, SearchResult)> = Vec::with_capacity(results.len());
+    for (mut result, key) in results.into_iter().zip(keys) {
+        match cache.block_status(&key, &result) {
+            BlockStatus::Seen => {}
+            BlockStatus::Changed => {
+                result.changed_since_seen = Some(true);
+                remaining += 1;
+            }
+            BlockStatus::New => remaining += 1,
+        }
+        candidates.push((key, result));
+    }
+
+    if page <= cache.pages.len() {
+        // Replay a page that was served before, in its original order
+        let mut by_key: HashMap<String, SearchResult> = candidates.into_iter().collect();
+        let page_results = cache.pages[page - 1]
+            .iter()
+            .filter_map(|key| by_key.remove(key))
+            .collect();
+
+        return Ok((
+            LimitedSearchResults {
+                results: page_results,
+                skipped_results: Vec::new(),
+                skipped_files: Vec::new(),
+                limits_applied: None,
+                cached_blocks_skipped: None,
+                suggestions: Vec::new(),
+            },
+            PageSelection {
+                page,
+                remaining,
+                replayed: true,
+            },
+        ));
+    }
+
+    if page > cache.pages.len() + 1 {
+        anyhow::bail!(
+            "Page {} is not available yet; the next page for this query is {}",
+            page,
+            cache.pages.len() + 1
+        );
+    }
+
+    // Claim the next page from the blocks that are new or changed since they were seen
+    let mut key_by_location: HashMap<String, String> = HashMap::new();
+    let unseen: Vec<SearchResult> = candidates
+        .into_iter()
+        .filter(|(key, result)| cache.block_status(key, result) != BlockStatus::Seen)
+        .map(|(key, result)| {
+            key_by_location.insert(generate_cache_key(&result), key);
+            result
+        })
+        .collect();
+    let unseen_count = unseen.len();
+
+    let mut limited = limit(unseen);
+    limited.skipped_results = Vec::new();
+
+    let mut page_keys = Vec::with_capacity(limited.results.len());
+    for result in &limited.results {
+        if let Some(key) = key_by_location.remove(&generate_cache_key(result)) {
+            cache.record_block(key.clone(), result);
+            page_keys.push(key);
+        }
+    }
+    cache.pages.push(page_keys);
+    cache.query = query.to_string();
+    cache.updated_at = now_secs();
+    cache.save()?;
+
+    collect_garbage_if_due(&sessions_dir());
+
+    let remaining = unseen_count - limited.results.len();
+    Ok((
+        limited,
+        PageSelection {
+            page,
+            remaining,
+            replayed: false,
+        },
+    ))
+}
+
+/// Summary of one session across all of its queries
+#[derive(Debug, Clone, Serialize)]
+pub struct SessionSummary {
+    pub session_id: String,
+    /// Number of distinct queries cached in the session
+    pub queries: usize,
+    /// Number of blocks seen across all queries
+    pub blocks: usize,
+    /// Number of pages served across all queries
+    pub pages: usize,
+    pub created_at: u64,
+    pub updated_at: u64,
+}
+
+/// List the session cache files in a directory
+fn session_files(dir: &Path) -> Vec<PathBuf> {
+    let mut files: Vec<PathBuf> = match std::fs::read_dir(dir) {
+        Ok(entries) => entries
+            .filter_map(|entry| entry.ok())
+            .map(|entry| entry.path())
+            .filter(|path| path.extension().is_some_and(|ext| ext == "json"))
+            .collect(),
+        Err(_) => Vec::new(),
+    };
+    files.sort();
+    files
+}
+
+/// Read a session cache file, using the file modification time for caches written
+/// before timestamps were recorded
+fn read_session_file(path: &Path) -> Option<SessionCache> {
+    let contents = std::fs::read_to_string(path).ok()?;
+    let mut cache: SessionCache = serde_json::from_str(&contents).ok()?;
+    if cache.updated_at == 0 {
+        cache.updated_at = std::fs::metadata(path)
+            .and_then(|metadata| metadata.modified())
+            .ok()
+            .and_then(|modified| modified.duration_since(UNIX_EPOCH).ok())
+            .map(|d| d.as_secs())
+            .unwrap_or(0);
+    }
+    Some(cache)
+}
+
+/// Read all non-expired session caches in a directory
+fn live_session_caches(dir: &Path, ttl: Duration) -> Vec<SessionCache> {
+    let now = now_secs();
+    session_files(dir)
+        .iter()
+        .filter_map(|path| read_session_file(path))
+        .filter(|cache| !cache.is_expired(ttl, now))
+        .collect()
+}
+
+/// List the sessions in a directory, most recently used first
+pub fn list_sessions(dir: &Path, ttl: Duration) -> Vec<SessionSummary> {
+    let mut sessions: Vec<SessionSummary> = Vec::new();
+    for cache in live_session_caches(dir, ttl) {
+        match sessions
+            .iter_mut()
+            .find(|summary| summary.session_id == cache.session_id)
+        {
+            Some(summary) => {
+                summary.queries += 1;
+                summary.blocks += cache.block_count();
+                summary.pages += cache.pages.len();
+                summary.created_at = summary.created_at.min(cache.created_at);
+                summary.updated_at = summary.updated_at.max(cache.updated_at);
+            }
+            None => sessions.push(SessionSummary {
+                session_id: cache.session_id.clone(),
+                queries: 1,
+                blocks: cache.block_count(),
+                pages: cache.pages.len(),
+                created_at: cache.created_at,
+                updated_at: cache.updated_at,
+            }),
+        }
+    }
+    sessions.sort_by(|a, b| {
+        b.updated_at
+            .cmp(&a.updated_at)
+            .then_with(|| a.session_id.cmp(&b.session_id))
+    });
+    sessions
+}
+
+/// Get the caches of every query in a session, most recently used first
+pub fn session_queries(dir: &Path, session_id: &str, ttl: Duration) -> Vec<SessionCache> {
+    let mut caches: Vec<SessionCache> = live_session_caches(dir, ttl)
+        .into_iter()
+        .filter(|cache| cache.session_id == session_id)
+        .collect();
+    caches.sort_by(|a, b| {
+        b.updated_at
+            .cmp(&a.updated_at)
+            .then_with(|| a.query.cmp(&b.query))
+    });
+    caches
+}
+
+/// Remove session caches from a directory
+///
+/// # Arguments
+/// * `dir` - The sessions directory
+/// * `session_id` - Only remove this session's caches; all sessions when None
+/// * `expired_only` - Only remove caches that have expired
+/// * `ttl` - The session time-to-live
+///
+/// Returns the number of cache files removed.
+pub fn clear_sessions(
+    dir: &Path,
+    session_id: Option<&str>,
+    expired_only: bool,
+    ttl: Duration,
+) -> Result<usize> {
+    let _lock = SessionLock::acquire(dir)?;
+    let now = now_secs();
+
+    let mut removed = 0;
+    for path in session_files(dir) {
+        let cache = read_session_file(&path);
+        let matches_session = match (session_id, &cache) {
+            (None, _) => true,
+            (Some(id), Some(cache)) => cache.session_id == id,
+            // Unreadable files can't be attributed to a session
+            (Some(_), None) => false,
+        };
+        // Unreadable files are treated as expired
+        let expired = cache
+            .as_ref()
+            .is_none_or(|cache| cache.is_expired(ttl, now));
+
+        if matches_session && (!expired_only || expired) {
+            std::fs::remove_file(&path)?;
+            removed += 1;
+        }
+    }
+    Ok(removed)
+}
+
+/// Remove expired session caches, at most once per GC_INTERVAL
+///
+/// The caller must hold the session lock. Errors are only reported in debug mode,
+/// since garbage collection must never make a search fail.
+fn collect_garbage_if_due(dir: &Path) {
+    let debug_mode = std::env::var("DEBUG").unwrap_or_default() == "1";
+    let marker = dir.join(".last_gc");
+
+    let due = std::fs::metadata(&marker)
+        .and_then(|metadata| metadata.modified())
+        .ok()
+        .and_then(|modified| modified.elapsed().ok())
+        .is_none_or(|elapsed| elapsed >= GC_INTERVAL);
+    if !due {
+        return;
+    }
+    if let Err(e) = File::create(&marker) {
+        if debug_mode {
+            println!("DEBUG: Error writing session GC marker: {}", e);
+        }
+        return;
+    }
+
+    let ttl = session_ttl();
+    let now = now_secs();
+    let mut removed = 0;
+    for path in session_files(dir) {
+        let expired = read_session_file(&path).is_none_or(|cache| cache.is_expired(ttl, now));
+        if expired && std::fs::remove_file(&path).is_ok() {
+            removed += 1;
+        }
+    }
+
+    if debug_mode {
+        println!("DEBUG: Session GC removed {} expired caches", removed);
+    }
+}
+
 /// Debug function to print cache contents (only used when DEBUG=1)
 pub fn debug_print_cache(session_id: &str, query: &str) -> Result<()> {
     let debug_mode = std::env::var("DEBUG").unwrap_or_default() == "1";
@@ -544,6 +1114,16 @@ mod tests {
     use super::*;
     use crate::models::SearchResult;
 
+    fn result_for_key(file: &str, lines: (usize, usize)) -> SearchResult {
+        SearchResult {
+            file: file.to_string(),
+            lines,
+            node_type: "function".to_string(),
+            code: "".to_string(),
+            ..Default::default()
+        }
+    }
+
     #[test]
     fn test_path_normalization() {
         // Test that normalize_path removes leading "./"
@@ -571,25 +1151,7 @@ mod tests {
             lines: (10, 20),
             node_type: "function".to_string(),
             code: "".to_string(),
-            matched_by_filename: None,
-            rank: None,
-            score: None,
-            tfidf_score: None,
-            bm25_score: None,
-            tfidf_rank: None,
-            bm25_rank: None,
-            new_score: None,
-            hybrid2_rank: None,
-            combined_score_rank: None,
-            file_unique_terms: None,
-            file_total_matches: None,
-            file_match_rank: None,
-            block_unique_terms: None,
-            block_total_matches: None,
-            parent_file_id: None,
-            block_id: None,
-            matched_keywords: None,
-            tokenized_content: None,
+            ..Default::default()
         };
 
         let result2 = SearchResult {
@@ -597,25 +1159,7 @@ mod tests {
             lines: (10, 20),
             node_type: "function".to_string(),
             code: "".to_string(),
-            matched_by_filename: None,
-            rank: None,
-            score: None,
-            tfidf_score: None,
-            bm25_score: None,
-            tfidf_rank: None,
-            bm25_rank: None,
-            new_score: None,
-            hybrid2_rank: None,
-            combined_score_rank: None,
-            file_unique_terms: None,
-            file_total_matches: None,
-            file_match_rank: None,
-            block_unique_terms: None,
-            block_total_matches: None,
-            parent_file_id: None,
-            block_id: None,
-            matched_keywords: None,
-            tokenized_content: None,
+            ..Default::default()
         };
 
         // Generate cache keys for both results
@@ -627,6 +1171,86 @@ mod tests {
         assert_eq!(key1, "path/to/file.rs:10-20");
     }
 
+    #[test]
+    fn test_normalized_content_hash() {
+        // Indentation and blank lines don't change the hash, code changes do
+        let code = "fn a() {\n    b();\n}";
+        assert_eq!(
+            normalized_content_hash(code),
+            normalized_content_hash("fn a() {\n\n        b();\n}")
+        );
+        assert_ne!(
+            normalized_content_hash(code),
+            normalized_content_hash("fn a() {\n    c();\n}")
+        );
+    }
+
+    #[test]
+    fn test_block_keys_disambiguate_duplicates() {
+        let block = |lines: (usize, usize), code: &str, symbol: Option<(&str, usize)>| {
+            let mut result = result_for_key("src/lib.rs", lines);
+            result.code = code.to_string();
+            result.symbol = symbol.map(|(name, _)| name.to_string());
+            result.symbol_occurrence = symbol.map(|(_, occurrence)| occurrence);
+            result
+        };
+        let results = vec![
+            block((20, 22), "fn new(a: u32) {}", Some(("new", 2))),
+            block((1, 3), "fn new() {}", Some(("new", 1))),
+            block((5, 6), "let x = 1;", None),
+        ];
+
+        let keys = block_keys(&results);
+        assert_eq!(keys[1], "src/lib.rs#function:new");
+        assert_eq!(keys[0], "src/lib.rs#function:new#2");
+        assert_eq!(
+            keys[2],
+            format!(
+                "src/lib.rs#function@{}",
+                normalized_content_hash("let x = 1;")
+            )
+        );
+    }
+
+    #[test]
+    fn test_session_expiry() {
+        let mut cache = SessionCache::new("s".to_string(), "h".to_string());
+        cache.updated_at = 1_000;
+        let ttl = Duration::from_secs(100);
+        assert!(!cache.is_expired(ttl, 1_100));
+        assert!(cache.is_expired(ttl, 1_101));
+    }
+
+    #[test]
+    fn test_session_lock_serializes_writers() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let dir = temp_dir.path().to_path_buf();
+        let counter = dir.join("counter");
+        std::fs::write(&counter, "0").unwrap();
+
+        // Each thread does an unsynchronized read-modify-write under the lock
+        let handles: Vec<_> = (0..8)
+            .map(|_| {
+                let dir = dir.clone();
+                let counter = counter.clone();
+                std::thread::spawn(move || {
+                    for _ in 0..10 {
+                        let _lock = SessionLock::acquire(&dir).unwrap();
+                        let value: u32 =
+                            std::fs::read_to_string(&counter).unwrap().parse().unwrap();
+                        std::thread::yield_now();
+                        std::fs::write(&counter, (value + 1).to_string()).unwrap();
+                    }
+                })
+            })
+            .collect();
+        for handle in handles {
+            handle.join().unwrap();
+        }
+
+        assert_eq!(std::fs::read_to_string(&counter).unwrap(), "80");
+    }
+
     #[test]
     fn test_session_cache_with_query_hash() {
         // Test that different queries for the same session have different cache paths
@@ -478,16 +478,23 @@ fn lex_identifier(chars: &mut Peekable<Chars>) -> String {
 }
 
 // Adjust paths to match your project structure
-use crate::search::tokenization::{add_special_term, tokenize as custom_tokenize};
+use crate::search::project_vocabulary::ProjectVocabulary;
+use crate::search::tokenization::{add_special_term, tokenize_for_project as custom_tokenize};
 
-struct Parser {
+struct Parser<'a> {
     tokens: Vec<Token>,
     pos: usize,
+    /// Vocabulary of the searched project, used to tokenize the keywords
+    project: Option<&'a ProjectVocabulary>,
 }
 
-impl Parser {
-    fn new(tokens: Vec<Token>) -> Self {
-        Parser { tokens, pos: 0 }
+impl<'a> Parser<'a> {
+    fn new(tokens: Vec<Token>, project: Option<&'a ProjectVocabulary>) -> Self {
+        Parser {
+            tokens,
+            pos: 0,
+            project,
+        }
     }
 
     fn peek(&self) -> Option<&Token> {
@@ -629,7 +636,7 @@ impl Parser {
                 // Apply your custom tokenization
                 let mut expanded = Vec::new();
                 for kw in &keywords {
-                    let splitted = custom_tokenize(kw);
+                    let splitted = custom_tokenize(kw, self.project);
                     // Only add non-empty terms
                     expanded.extend(splitted.into_iter().filter(|s| !s.is_empty()));
                 }
@@ -740,6 +747,15 @@ impl Parser {
 
 /// Parse the query string into an AST
 pub fn parse_query(input: &str, exact: bool) -> Result<Expr, ParseError> {
+    parse_query_for_project(input, exact, None)
+}
+
+/// Same as [`parse_query`], tokenizing the keywords with a project vocabulary
+pub fn parse_query_for_project(
+    input: &str,
+    exact: bool,
+    project: Option<&ProjectVocabulary>,
+) -> Result<Expr, ParseError> {
     let debug_mode = std::env::var("DEBUG").unwrap_or_default() == "1";
 
     if debug_mode {
@@ -792,7 +808,7 @@ pub fn parse_query(input: &str, exact: bool) -> Result<Expr, ParseError> {
     };
 
     // Parse into AST
-    let mut parser = Parser::new(tokens);
+    let mut parser = Parser::new(tokens, project);
     let parsed = parser.parse_expr();
 
     if parsed.is_err() {
@@ -1,7 +1,10 @@
+use crate::search::project_vocabulary::ProjectVocabulary;
+use crate::search::source_files::{self, FileLimits, GeneratedAttributes, SkippedFile};
 use crate::search::tokenization;
 use anyhow::Result;
 use ignore::WalkBuilder;
 use lazy_static::lazy_static;
+use rayon::prelude::*;
 use std::collections::{HashMap, HashSet};
 use std::path::{Path, PathBuf};
 use std::sync::{Arc, RwLock};
@@ -12,6 +15,8 @@ use std::time::Instant;
 pub struct FileList {
     /// The list of files in the directory (respecting ignore patterns)
     pub files: Vec<PathBuf>,
+    /// Files left out by the file limits, sorted by path
+    pub skipped: Vec<SkippedFile>,
     /// When this cache was created
     #[allow(dead_code)]
     pub created_at: Instant,
@@ -33,7 +38,12 @@ fn format_duration(duration: std::time::Duration) -> String {
 }
 
 /// Generate a cache key for a specific directory and options
-fn generate_cache_key(path: &Path, allow_tests: bool, custom_ignores: &[String]) -> String {
+fn generate_cache_key(
+    path: &Path,
+    allow_tests: bool,
+    custom_ignores: &[String],
+    limits: &FileLimits,
+) -> String {
     // Create a unique identifier for this cache based on the path and options
     let path_str = path.to_string_lossy();
     let allow_tests_str = if allow_tests {
@@ -56,15 +66,30 @@ fn generate_cache_key(path: &Path, allow_tests: bool, custom_ignores: &[String])
         format!("ignores_{:x}", hash)
     };
 
-    format!("{}_{}_{}", path_str, allow_tests_str, ignores_hash)
+    format!(
+        "{}_{}_{}_{}_{}",
+        path_str, allow_tests_str, ignores_hash, limits.max_file_size, limits.skip_generated
+    )
 }
 
 /// Get a list of files in a directory, respecting ignore patterns and test file exclusions.
 /// This function will use a cached list if available, or build and cache a new list if not.
+#[allow(dead_code)] // Only used by the library's metrics, similarity and todos modules
 pub fn get_file_list(
     path: &Path,
     allow_tests: bool,
     custom_ignores: &[String],
+) -> Result<Arc<FileList>> {
+    get_file_list_with_limits(path, allow_tests, custom_ignores, &FileLimits::default())
+}
+
+/// Get a list of files in a directory like `get_file_list`, leaving out the files over
+/// the given limits
+pub fn get_file_list_with_limits(
+    path: &Path,
+    allow_tests: bool,
+    custom_ignores: &[String],
+    limits: &FileLimits,
 ) -> Result<Arc<FileList>> {
     let debug_mode = std::env::var("DEBUG").unwrap_or_default() == "1";
     let start_time = Instant::now();
@@ -76,7 +101,7 @@ pub fn get_file_list(
     }
 
     // Create a cache key for this request
-    let cache_key = generate_cache_key(path, allow_tests, custom_ignores);
+    let cache_key = generate_cache_key(path, allow_tests, custom_ignores, limits);
 
     // Check if we have this file list in the cache
     {
@@ -99,7 +124,7 @@ pub fn get_file_list(
         println!("DEBUG: File list not found in cache, building new list");
     }
 
-    let file_list = build_file_list(path, allow_tests, custom_ignores)?;
+    let file_list = build_file_list(path, allow_tests, custom_ignores, limits)?;
     let file_count = file_list.files.len();
 
     // Cache the file list
@@ -121,8 +146,38 @@ pub fn get_file_list(
     Ok(file_list)
 }
 
+/// Drop the cached file lists of every directory containing `path`, so that the next
+/// search lists them again
+///
+/// `path` must be written the way the listed directory was, e.g. `./src/main.rs` for `.`.
+pub fn invalidate_file_lists(path: &Path) {
+    let debug_mode = std::env::var("DEBUG").unwrap_or_default() == "1";
+    // Cache keys start with the listed directory (see generate_cache_key)
+    let prefixes: Vec<String> = path
+        .ancestors()
+        .filter(|ancestor| !ancestor.as_os_str().is_empty())
+        .map(|ancestor| format!("{}_", ancestor.to_string_lossy()))
+        .collect();
+
+    let mut cache = FILE_LIST_CACHE.write().unwrap();
+    let before = cache.len();
+    cache.retain(|key, _| !prefixes.iter().any(|prefix| key.starts_with(prefix)));
+    if debug_mode && cache.len() < before {
+        println!(
+            "DEBUG: Dropped {} cached file lists containing {:?}",
+            before - cache.len(),
+            path
+        );
+    }
+}
+
 /// Build a list of files in a directory, respecting ignore patterns and test file exclusions.
-fn build_file_list(path: &Path, allow_tests: bool, custom_ignores: &[String]) -> Result<FileList> {
+fn build_file_list(
+    path: &Path,
+    allow_tests: bool,
+    custom_ignores: &[String],
+    limits: &FileLimits,
+) -> Result<FileList> {
     let debug_mode = std::env::var("DEBUG").unwrap_or_default() == "1";
     let start_time = Instant::now();
 
@@ -303,6 +358,44 @@ fn build_file_list(path: &Path, allow_tests: bool, custom_ignores: &[String]) ->
         );
     }
 
+    // Leave out files over the size limit, binary files, and minified or generated code
+    let attributes = if limits.skip_generated {
+        GeneratedAttributes::for_files(path, &files)
+    } else {
+        GeneratedAttributes::default()
+    };
+    let checked: Vec<(PathBuf, Option<(source_files::SkipReason, u64)>)> = files
+        .into_par_iter()
+        .map(|file| {
+            let reason = source_files::skip_reason(&file, limits, &attributes);
+            (file, reason)
+        })
+        .collect();
+    let mut files = Vec::new();
+    let mut skipped = Vec::new();
+    for (file, reason) in checked {
+        match reason {
+            Some((reason, size)) => skipped.push(SkippedFile {
+                file: file.to_string_lossy().to_string(),
+                reason,
+                size,
+            }),
+            None => files.push(file),
+        }
+    }
+    skipped.sort_by(|a, b| a.file.cmp(&b.file));
+
+    if debug_mode {
+        for file in &skipped {
+            println!(
+                "DEBUG: Skipping {} ({}, {} bytes)",
+                file.file,
+                file.reason.description(),
+                file.size
+            );
+        }
+    }
+
     let total_duration = start_time.elapsed();
 
     if debug_mode {
@@ -314,12 +407,16 @@ fn build_file_list(path: &Path, allow_tests: bool, custom_ignores: &[String]) ->
 
     Ok(FileList {
         files,
+        skipped,
         created_at: Instant::now(),
     })
 }
 
 /// Find files whose names match query words
 /// Returns a map of file paths to the term indices that matched the filename
+///
+/// Queries and paths are tokenized with the project vocabulary of the search, if any.
+#[allow(clippy::too_many_arguments)]
 pub fn find_matching_filenames(
     path: &Path,
     queries: &[String],
@@ -327,7 +424,9 @@ pub fn find_matching_filenames(
     custom_ignores: &[String],
     allow_tests: bool,
     term_indices: &HashMap<String, usize>,
+    vocabulary: Option<&ProjectVocabulary>,
     language: Option<&str>,
+    limits: &FileLimits,
 ) -> Result<HashMap<PathBuf, HashSet<usize>>> {
     let debug_mode = std::env::var("DEBUG").unwrap_or_default() == "1";
     let start_time = Instant::now();
@@ -343,7 +442,7 @@ pub fn find_matching_filenames(
     }
 
     // Get the cached file list, with language filtering if specified
-    let file_list = get_file_list_by_language(path, allow_tests, custom_ignores, language)?;
+    let file_list = get_file_list_by_language(path, allow_tests, custom_ignores, language, limits)?;
 
     if debug_mode {
         println!(
@@ -355,7 +454,7 @@ pub fn find_matching_filenames(
     // Tokenize query terms for matching using the standard tokenizer
     let query_tokens: Vec<String> = queries
         .iter()
-        .flat_map(|q| tokenization::tokenize(q))
+        .flat_map(|q| tokenization::tokenize_for_project(q, vocabulary))
         .collect();
 
     if debug_mode {
@@ -378,7 +477,7 @@ pub fn find_matching_filenames(
         let relative_path = file_path.to_string_lossy().to_string();
 
         // Tokenize the full relative path using the standard tokenizer
-        let filename_tokens = tokenization::tokenize(&relative_path);
+        let filename_tokens = tokenization::tokenize_for_project(&relative_path, vocabulary);
 
         if debug_mode && !filename_tokens.is_empty() {
             println!(
@@ -390,7 +489,7 @@ pub fn find_matching_filenames(
         let mut matched_terms = HashSet::new();
 
         for (term, &idx) in term_indices {
-            let term_tokens = tokenization::tokenize(term);
+            let term_tokens = tokenization::tokenize_for_project(term, vocabulary);
 
             // Check if any term token matches any filename token
             let matched = term_tokens.iter().any(|term_token| {
@@ -461,10 +560,11 @@ pub fn get_file_list_by_language(
     allow_tests: bool,
     custom_ignores: &[String],
     language: Option<&str>,
+    limits: &FileLimits,
 ) -> Result<Arc<FileList>> {
     // If no language is specified, use the regular get_file_list function
     if language.is_none() {
-        return get_file_list(path, allow_tests, custom_ignores);
+        return get_file_list_with_limits(path, allow_tests, custom_ignores, limits);
     }
 
     let debug_mode = std::env::var("DEBUG").unwrap_or_default() == "1";
@@ -478,7 +578,7 @@ pub fn get_file_list_by_language(
     }
 
     // Get the full file list first
-    let full_file_list = get_file_list(path, allow_tests, custom_ignores)?;
+    let full_file_list = get_file_list_with_limits(path, allow_tests, custom_ignores, limits)?;
 
     // Get the extensions for the specified language
     let extensions = get_language_extensions(language.unwrap());
@@ -506,6 +606,18 @@ pub fn get_file_list_by_language(
             .cloned()
             .collect()
     };
+    let matches_language = |file: &str| {
+        extensions.is_empty()
+            || Path::new(file)
+                .extension()
+                .is_some_and(|ext| extensions.contains(&format!(".{}", ext.to_string_lossy())))
+    };
+    let skipped = full_file_list
+        .skipped
+        .iter()
+        .filter(|skipped| matches_language(&skipped.file))
+        .cloned()
+        .collect();
 
     let elapsed = start_time.elapsed();
     if debug_mode {
@@ -520,6 +632,7 @@ pub fn get_file_list_by_language(
     // Create a new FileList with the filtered files
     Ok(Arc::new(FileList {
         files: filtered_files,
+        skipped,
         created_at: Instant::now(),
     }))
 }
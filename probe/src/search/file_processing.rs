@@ -1,15 +1,17 @@
 use anyhow::{Context, Result};
 use rayon::prelude::*;
 use std::collections::{HashMap, HashSet};
-use std::fs;
 use std::path::Path;
 use std::sync::{Arc, Mutex};
 use std::time::{Duration, Instant};
 use tree_sitter;
 
-use crate::language::{is_test_file, parse_file_for_code_blocks};
-use crate::models::SearchResult;
-use crate::ranking;
+use crate::language::parser::{
+    block_definition_node, enclosing_block_node, node_symbol_path, symbol_occurrence,
+};
+use crate::language::{is_test_file, parse_file_for_code_blocks_at};
+use crate::models::{ParentBlock, SearchResult};
+use crate::search::batch::{self, BatchContext};
 use crate::search::tokenization;
 
 /// Structure to hold timing information for file processing stages
@@ -54,6 +56,8 @@ pub struct FileProcessingParams<'a> {
 
     #[allow(dead_code)]
     pub no_merge: bool,
+    /// The batch the search is part of, whose queries share files and tokens
+    pub batch: Option<&'a BatchContext>,
 }
 
 /// Evaluate whether a block of lines satisfies a complex AST query
@@ -170,9 +174,10 @@ pub fn filter_tokenized_block(
     // Create a set of matched term indices based on tokenized content
     let mut matched_terms = HashSet::new();
 
-    // For each token in the tokenized content, check if it's in the term_indices
+    // For each token in the tokenized content, check if it's in the term_indices,
+    // or a fuzzy variant or synonym of one of the terms
     for token in tokenized_content {
-        if let Some(&idx) = term_indices.get(token) {
+        if let Some(&idx) = term_indices.get(token).or_else(|| plan.alias_index(token)) {
             matched_terms.insert(idx);
         }
     }
@@ -186,7 +191,7 @@ pub fn filter_tokenized_block(
         }
 
         // Check if this term is a special case that should be treated as a single token
-        if crate::search::tokenization::is_special_case(term) {
+        if crate::search::tokenization::is_special_case_for_project(term, plan.project()) {
             // If the tokenized content contains this special case term, add it to matched terms
             if tokenized_content.contains(&term.to_lowercase()) {
                 matched_terms.insert(idx);
@@ -364,7 +369,7 @@ pub fn process_file_with_results(
 
     // Measure file I/O time
     let file_io_start = Instant::now();
-    let content = fs::read_to_string(params.path)
+    let content = batch::read_source(params.batch, params.path)
         .context(format!("Failed to read file: {:?}", params.path))?;
     let file_io_duration = file_io_start.elapsed();
     timings.file_io = Some(file_io_duration);
@@ -411,7 +416,10 @@ pub fn process_file_with_results(
             .flat_map(|pairs| pairs.iter().map(|(_, s)| s.clone()))
             .collect()
     };
-    let unique_query_terms: HashSet<String> = query_terms.into_iter().collect();
+    let mut unique_query_terms: HashSet<String> = query_terms.into_iter().collect();
+    // Fuzzy variants and synonyms count as matches of the terms they stand in for
+    unique_query_terms.extend(params.query_plan.fuzzy_terms.keys().cloned());
+    unique_query_terms.extend(params.query_plan.synonym_terms.keys().cloned());
 
     if debug_mode {
         println!("DEBUG: Processing file: {:?}", params.path);
@@ -440,10 +448,9 @@ pub fn process_file_with_results(
     // Measure tree parsing time
     let tree_parsing_start = Instant::now();
     let file_path = params.path.to_string_lossy().to_string();
-    let cache_key = format!("{}_{}", file_path, extension);
 
-    let _ = if language_impl.is_some() {
-        crate::language::tree_cache::get_or_parse_tree(&cache_key, &content, &mut parser).ok()
+    let tree = if language_impl.is_some() {
+        crate::language::tree_cache::get_or_parse_tree(&file_path, &content, &mut parser).ok()
     } else {
         None
     };
@@ -453,8 +460,9 @@ pub fn process_file_with_results(
     // Measure line map building time (this is an approximation since we can't directly measure it)
     let line_map_building_start = Instant::now();
 
-    // Call the original parse_file_for_code_blocks function
-    let code_blocks_result = parse_file_for_code_blocks(
+    // Find the blocks with the tree parsed above, cached under the file's path
+    let code_blocks_result = parse_file_for_code_blocks_at(
+        &file_path,
         &content,
         extension,
         params.line_numbers,
@@ -569,9 +577,11 @@ pub fn process_file_with_results(
                 let term_matching_start = Instant::now();
 
                 // Early tokenization with full path prepended
-                let block_terms = ranking::preprocess_text_with_filename(
+                let block_terms = batch::tokenize_block(
+                    params.batch,
                     &full_code,
                     &params.path.to_string_lossy(),
+                    params.query_plan.project(),
                 );
 
                 // End term matching time measurement
@@ -674,7 +684,11 @@ pub fn process_file_with_results(
                         if block_terms.iter().any(|bt| bt == qterm) {
                             continue;
                         }
-                        let parts = tokenization::split_compound_word(qterm, vocabulary);
+                        let parts = tokenization::split_compound_word_for_project(
+                            qterm,
+                            vocabulary,
+                            params.query_plan.project(),
+                        );
                         if parts.len() > 1 && parts.iter().all(|part| block_terms.contains(part)) {
                             compound_matches.insert(qterm);
                         }
@@ -734,6 +748,23 @@ pub fn process_file_with_results(
                     // Start measuring result creation time
                     let result_creation_start = Instant::now();
 
+                    let definition = tree.as_ref().and_then(|tree| {
+                        let node = block_definition_node(
+                            tree,
+                            extension,
+                            final_start_line - 1,
+                            final_end_line - 1,
+                        )?;
+                        Some((tree, node))
+                    });
+                    let symbol = definition
+                        .and_then(|(_, node)| node_symbol_path(node, content.as_bytes(), extension));
+                    // Overloads share a symbol path, so they are told apart by their position
+                    // in the file
+                    let symbol_occurrence = definition.filter(|_| symbol.is_some()).map(
+                        |(tree, node)| symbol_occurrence(tree, node, content.as_bytes(), extension),
+                    );
+
                     let result = SearchResult {
                         file: params.path.to_string_lossy().to_string(),
                         lines: (final_start_line, final_end_line),
@@ -769,6 +800,33 @@ pub fn process_file_with_results(
                             Some(matched_keywords)
                         },
                         tokenized_content: Some(block_terms),
+                        workspace_root: None,
+                        changed_since_seen: None,
+                        explanation: None,
+                        match_ranges: None,
+                        metrics: None,
+                        // A nested Go struct is shown as the function it is declared in
+                        parent_block: tree
+                            .as_ref()
+                            .filter(|_| !is_nested_struct)
+                            .and_then(|tree| {
+                                enclosing_block_node(
+                                    tree,
+                                    extension,
+                                    final_start_line - 1,
+                                    final_end_line - 1,
+                                )
+                            })
+                            .map(|parent| ParentBlock {
+                                node_type: parent.kind().to_string(),
+                                lines: (
+                                    parent.start_position().row + 1,
+                                    parent.end_position().row + 1,
+                                ),
+                            }),
+                        context_blocks: None,
+                        symbol,
+                        symbol_occurrence,
                     };
 
                     let result_creation_duration_value = result_creation_start.elapsed();
@@ -993,8 +1051,12 @@ pub fn process_file_with_results(
         let term_matching_start = Instant::now();
 
         // Early tokenization for fallback context
-        let context_terms =
-            ranking::preprocess_text_with_filename(&context_code, &params.path.to_string_lossy());
+        let context_terms = batch::tokenize_block(
+            params.batch,
+            &context_code,
+            &params.path.to_string_lossy(),
+            params.query_plan.project(),
+        );
 
         // Add to term matching time
         let term_matching_duration_value = term_matching_start.elapsed();
@@ -1073,7 +1135,11 @@ pub fn process_file_with_results(
                 if context_terms.iter().any(|bt| bt == qterm) {
                     continue;
                 }
-                let parts = tokenization::split_compound_word(qterm, vocabulary);
+                let parts = tokenization::split_compound_word_for_project(
+                    qterm,
+                    vocabulary,
+                    params.query_plan.project(),
+                );
                 if parts.len() > 1 && parts.iter().all(|part| context_terms.contains(part)) {
                     compound_matches.insert(qterm);
                 }
@@ -1165,6 +1231,15 @@ pub fn process_file_with_results(
                     Some(matched_keywords)
                 },
                 tokenized_content: Some(context_terms),
+                workspace_root: None,
+                changed_since_seen: None,
+                explanation: None,
+                match_ranges: None,
+                metrics: None,
+                parent_block: None,
+                context_blocks: None,
+                symbol: None,
+                symbol_occurrence: None,
             };
 
             // Add to result creation time
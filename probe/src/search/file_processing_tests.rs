@@ -37,6 +37,9 @@ pub fn create_test_query_plan(terms: &[&str]) -> QueryPlan {
         term_indices,
         excluded_terms: HashSet::new(),
         exact: false,
+        fuzzy_terms: HashMap::new(),
+        synonym_terms: HashMap::new(),
+        vocabulary: None,
     }
 }
 
@@ -83,6 +86,7 @@ mod tests {
             preprocessed_queries: None,
             query_plan: &query_plan,
             no_merge: false,
+            batch: None,
         };
 
         let (results, _) =
@@ -134,6 +138,7 @@ mod tests {
             preprocessed_queries: None,
             query_plan: &query_plan,
             no_merge: false,
+            batch: None,
         };
 
         // Capture the results to check them
@@ -184,6 +189,7 @@ mod tests {
             preprocessed_queries: None,
             query_plan: &query_plan,
             no_merge: false,
+            batch: None,
         };
 
         let (results, _) =
@@ -261,6 +267,7 @@ function test3() {
             preprocessed_queries: None, // No preprocessed queries
             query_plan: &query_plan,
             no_merge: false,
+            batch: None,
         };
 
         let (results, _) =
@@ -356,10 +363,11 @@ function processResults(results) {
             term_matches: &term_matches,
             num_queries: 2, // "process" and "data"
             filename_matched_queries: HashSet::new(),
-            queries_terms: &[term_pairs.clone()],
+            queries_terms: std::slice::from_ref(&term_pairs),
             preprocessed_queries: Some(&preprocessed_queries),
             query_plan: &query_plan,
             no_merge: false,
+            batch: None,
         };
 
         let (results, _) =
@@ -425,6 +433,7 @@ fn test_long_lines_are_ignored() {
         preprocessed_queries: None,
         query_plan: &query_plan,
         no_merge: false,
+        batch: None,
     };
 
     let (results, _) =
@@ -0,0 +1,264 @@
+//! Typo-tolerant term matching.
+//!
+//! With `--fuzzy`, query terms that don't occur in any candidate file are matched against
+//! the vocabulary of identifiers in those files, allowing a bounded edit distance. The
+//! closest vocabulary terms are searched for in place of the misspelled term, and count
+//! at a reduced weight when ranking. When a search finds nothing, the same vocabulary is
+//! used to suggest corrections.
+
+use crate::models::TermSuggestion;
+use crate::ranking::get_stemmer;
+use crate::search::project_vocabulary::ProjectVocabulary;
+use crate::search::source_files::read_source_file;
+use crate::search::tokenization;
+use rayon::prelude::*;
+use regex::Regex;
+use std::collections::{HashMap, HashSet};
+use std::path::PathBuf;
+
+/// Fuzzy variants considered per query term
+pub const MAX_FUZZY_VARIANTS: usize = 3;
+
+/// Suggestions shown per query term when a search finds nothing
+const MAX_SUGGESTIONS: usize = 3;
+
+/// The terms occurring in a set of files
+#[derive(Debug, Default)]
+pub struct Vocabulary {
+    /// Stemmed term -> the word it was first seen as, e.g. "session" -> "session"
+    terms: HashMap<String, String>,
+    /// Lowercase words, before stemming
+    words: HashSet<String>,
+}
+
+impl Vocabulary {
+    /// Collect the identifiers of the given files, split and stemmed the way search
+    /// tokenizes code
+    ///
+    /// The files are those of the file list, which already left out the files over the
+    /// limits (see `source_files::FileLimits`). Identifiers are split with the word lists
+    /// of the project vocabulary of the search, if any.
+    pub fn from_files(files: &[PathBuf], project: Option<&ProjectVocabulary>) -> Self {
+        let identifiers: HashSet<String> = files
+            .par_iter()
+            .filter_map(|path| read_source_file(path).ok())
+            .map(|content| identifiers(&content))
+            .reduce(HashSet::new, |mut all, some| {
+                all.extend(some);
+                all
+            });
+
+        let mut vocabulary = Vocabulary::default();
+        let mut identifiers: Vec<String> = identifiers.into_iter().collect();
+        // Sorted, so that the word a term is shown as doesn't depend on file order
+        identifiers.sort();
+        for identifier in &identifiers {
+            vocabulary.add_identifier(identifier, project);
+        }
+        vocabulary
+    }
+
+    fn add_identifier(&mut self, identifier: &str, project: Option<&ProjectVocabulary>) {
+        let stemmer = get_stemmer();
+        for piece in identifier.split('_') {
+            for part in tokenization::split_camel_case_for_project(piece, project) {
+                let word = part.to_lowercase();
+                if word.len() < 3 || tokenization::is_stop_word_for_project(&word, project) {
+                    continue;
+                }
+                let term = stemmer.stem(&word).to_string();
+                self.terms.entry(term).or_insert_with(|| word.clone());
+                self.words.insert(word);
+            }
+        }
+    }
+
+    /// Whether a query term occurs in the vocabulary, stemmed or as written
+    pub fn contains(&self, term: &str) -> bool {
+        self.terms.contains_key(term) || self.words.contains(term)
+    }
+
+    /// Vocabulary terms within `max_distance` edits of `term`, closest first
+    fn closest_terms(&self, term: &str, max_distance: usize) -> Vec<(usize, &String)> {
+        let mut candidates: Vec<(usize, &String)> = self
+            .terms
+            .keys()
+            .filter_map(|candidate| {
+                edit_distance(term, candidate, max_distance).map(|distance| (distance, candidate))
+            })
+            .collect();
+        candidates.sort();
+        candidates
+    }
+
+    /// Fuzzy variants of a query term: the closest vocabulary terms within the edit
+    /// distance allowed for the term's length
+    ///
+    /// Terms that occur in the vocabulary themselves have no variants.
+    pub fn fuzzy_variants(&self, term: &str) -> Vec<String> {
+        if self.contains(term) {
+            return Vec::new();
+        }
+        self.closest_terms(term, max_edit_distance(term))
+            .into_iter()
+            .take(MAX_FUZZY_VARIANTS)
+            .map(|(_, candidate)| candidate.clone())
+            .collect()
+    }
+
+    /// "Did you mean" suggestions for the query terms that occur nowhere
+    ///
+    /// Suggestions allow one more edit than fuzzy matching does, and are shown as the
+    /// words they occur as in the code rather than as stemmed terms.
+    pub fn suggestions<'a>(
+        &self,
+        terms: impl IntoIterator<Item = &'a String>,
+    ) -> Vec<TermSuggestion> {
+        let mut terms: Vec<&String> = terms.into_iter().collect();
+        terms.sort();
+        terms.dedup();
+
+        terms
+            .into_iter()
+            .filter(|term| !self.contains(term))
+            .filter_map(|term| {
+                let mut suggestions: Vec<String> = Vec::new();
+                for (_, candidate) in self.closest_terms(term, max_edit_distance(term) + 1) {
+                    let word = &self.terms[candidate];
+                    if !suggestions.contains(word) {
+                        suggestions.push(word.clone());
+                    }
+                    if suggestions.len() == MAX_SUGGESTIONS {
+                        break;
+                    }
+                }
+                (!suggestions.is_empty()).then(|| TermSuggestion {
+                    term: term.clone(),
+                    suggestions,
+                })
+            })
+            .collect()
+    }
+}
+
+/// The distinct identifiers in a piece of code
+fn identifiers(content: &str) -> HashSet<String> {
+    lazy_static::lazy_static! {
+        static ref IDENTIFIER: Regex = Regex::new(r"[A-Za-z_][A-Za-z0-9_]{2,}").unwrap();
+    }
+    IDENTIFIER
+        .find_iter(content)
+        .map(|m| m.as_str().to_string())
+        .collect()
+}
+
+/// Edits allowed for a term, following Elasticsearch's "AUTO" fuzziness: none for terms
+/// of up to 2 characters, one for 3 to 5 characters and two for longer terms
+pub fn max_edit_distance(term: &str) -> usize {
+    match term.chars().count() {
+        0..=2 => 0,
+        3..=5 => 1,
+        _ => 2,
+    }
+}
+
+/// Optimal string alignment distance between two strings (insertions, deletions,
+/// substitutions and transpositions of adjacent characters), or None if it exceeds
+/// `max_distance`
+pub fn edit_distance(a: &str, b: &str, max_distance: usize) -> Option<usize> {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    if a.len().abs_diff(b.len()) > max_distance {
+        return None;
+    }
+
+    // Three rows of the dynamic programming matrix: two back, previous and current
+    let mut two_back: Vec<usize> = vec![0; b.len() + 1];
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    let mut current: Vec<usize> = vec![0; b.len() + 1];
+
+    for i in 1..=a.len() {
+        current[0] = i;
+        let mut row_min = i;
+        for j in 1..=b.len() {
+            let cost = usize::from(a[i - 1] != b[j - 1]);
+            let mut distance = (previous[j] + 1)
+                .min(current[j - 1] + 1)
+                .min(previous[j - 1] + cost);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                distance = distance.min(two_back[j - 2] + 1);
+            }
+            current[j] = distance;
+            row_min = row_min.min(distance);
+        }
+        // Every later row is at least as far apart
+        if row_min > max_distance {
+            return None;
+        }
+        std::mem::swap(&mut two_back, &mut previous);
+        std::mem::swap(&mut previous, &mut current);
+    }
+
+    let distance = previous[b.len()];
+    (distance <= max_distance).then_some(distance)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_edit_distance() {
+        assert_eq!(edit_distance("session", "session", 2), Some(0));
+        assert_eq!(edit_distance("sesion", "session", 2), Some(1));
+        assert_eq!(edit_distance("tokenis", "tokeniz", 2), Some(1));
+        // A transposition is a single edit
+        assert_eq!(edit_distance("hadnler", "handler", 1), Some(1));
+        assert_eq!(edit_distance("kitten", "sitting", 3), Some(3));
+        assert_eq!(edit_distance("kitten", "sitting", 2), None);
+        assert_eq!(edit_distance("ab", "abcdef", 2), None);
+        assert_eq!(edit_distance("", "abc", 3), Some(3));
+    }
+
+    #[test]
+    fn test_max_edit_distance() {
+        assert_eq!(max_edit_distance("id"), 0);
+        assert_eq!(max_edit_distance("pars"), 1);
+        assert_eq!(max_edit_distance("sesion"), 2);
+    }
+
+    #[test]
+    fn test_fuzzy_variants_and_suggestions() {
+        let mut vocabulary = Vocabulary::default();
+        for identifier in ["SessionCache", "tokenize_query", "parse_input", "id"] {
+            vocabulary.add_identifier(identifier, None);
+        }
+
+        assert!(vocabulary.contains("session"));
+        assert!(vocabulary.contains("cach"));
+        assert!(vocabulary.contains("cache"));
+        assert!(vocabulary.fuzzy_variants("session").is_empty());
+        assert_eq!(vocabulary.fuzzy_variants("sesion"), vec!["session"]);
+        assert_eq!(vocabulary.fuzzy_variants("tokenis"), vec!["token"]);
+        assert!(vocabulary.fuzzy_variants("unrelated").is_empty());
+
+        let terms = [
+            "sesion".to_string(),
+            "cach".to_string(),
+            "qeury".to_string(),
+        ];
+        assert_eq!(
+            vocabulary.suggestions(&terms),
+            vec![
+                TermSuggestion {
+                    term: "qeury".to_string(),
+                    suggestions: vec!["query".to_string()],
+                },
+                TermSuggestion {
+                    term: "sesion".to_string(),
+                    suggestions: vec!["session".to_string()],
+                },
+            ]
+        );
+    }
+}
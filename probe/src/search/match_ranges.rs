@@ -0,0 +1,113 @@
+//! Positions of pattern matches within search results.
+//!
+//! Every result carries the byte, line and column ranges of the places its search
+//! patterns matched, so that output formats and front ends can highlight exactly what
+//! matched instead of guessing from the matched keywords.
+
+use crate::models::{MatchRange, SearchResult};
+use crate::search::block_merging::source_line_numbers;
+use regex::Regex;
+
+/// Find the matches of `patterns` in a block of code starting at line `first_line`
+///
+/// Patterns are matched line by line, the way files are searched. Overlapping matches of
+/// different patterns are merged into one range. Lines are numbered as in the file:
+/// the placeholders of lines skipped by merging are not searched, and the lines after
+/// them continue from the end of the skipped range.
+pub fn find_match_ranges(code: &str, first_line: usize, patterns: &[Regex]) -> Vec<MatchRange> {
+    let mut ranges = Vec::new();
+    let mut line_offset = 0;
+
+    for (line, line_number) in code.split('\n').zip(source_line_numbers(code, first_line)) {
+        let Some(line_number) = line_number else {
+            line_offset += line.len() + 1;
+            continue;
+        };
+
+        let mut matches: Vec<(usize, usize)> = patterns
+            .iter()
+            .flat_map(|pattern| pattern.find_iter(line))
+            .filter(|m| !m.is_empty())
+            .map(|m| (m.start(), m.end()))
+            .collect();
+        matches.sort();
+
+        let mut merged: Vec<(usize, usize)> = Vec::new();
+        for (start, end) in matches {
+            match merged.last_mut() {
+                Some((_, last_end)) if start <= *last_end => *last_end = (*last_end).max(end),
+                _ => merged.push((start, end)),
+            }
+        }
+
+        for (start, end) in merged {
+            let column_start = line[..start].chars().count() + 1;
+            ranges.push(MatchRange {
+                start_byte: line_offset + start,
+                end_byte: line_offset + end,
+                line: line_number,
+                column_start,
+                column_end: column_start + line[start..end].chars().count(),
+            });
+        }
+        line_offset += line.len() + 1;
+    }
+
+    ranges
+}
+
+/// Set the match ranges of every result
+pub fn add_match_ranges(results: &mut [SearchResult], patterns: &[Regex]) {
+    for result in results {
+        result.match_ranges = Some(find_match_ranges(&result.code, result.lines.0, patterns));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_find_match_ranges() {
+        let code = "fn parse() {\n    let é = parse_input();\n}";
+        let patterns = [
+            Regex::new("parse").unwrap(),
+            Regex::new(r"parse_\w+").unwrap(),
+        ];
+
+        let ranges = find_match_ranges(code, 10, &patterns);
+        assert_eq!(
+            ranges,
+            vec![
+                MatchRange {
+                    start_byte: 3,
+                    end_byte: 8,
+                    line: 10,
+                    column_start: 4,
+                    column_end: 9,
+                },
+                // Overlapping matches are merged, and columns count characters
+                MatchRange {
+                    start_byte: 26,
+                    end_byte: 37,
+                    line: 11,
+                    column_start: 13,
+                    column_end: 24,
+                },
+            ]
+        );
+        assert_eq!(&code[26..37], "parse_input");
+    }
+
+    #[test]
+    fn test_find_match_ranges_skips_placeholders() {
+        let code = "fn lines() {\n... lines 3-20 skipped...\n    lines();\n}";
+        let patterns = [Regex::new("lines").unwrap()];
+
+        // The placeholder isn't matched, and the line after it is line 21
+        let ranges = find_match_ranges(code, 1, &patterns);
+        let lines: Vec<usize> = ranges.iter().map(|range| range.line).collect();
+        assert_eq!(lines, vec![1, 21]);
+        assert_eq!(&code[ranges[1].start_byte..ranges[1].end_byte], "lines");
+    }
+}
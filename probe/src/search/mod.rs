@@ -1,3 +1,4 @@
+pub mod batch;
 pub mod file_processing;
 pub mod query;
 mod result_ranking;
@@ -6,11 +7,16 @@ pub mod block_merging;
 pub mod cache; // New module for caching search results
 pub mod elastic_query;
 pub mod file_list_cache; // New module for caching file lists
+pub mod fuzzy;
+pub mod match_ranges;
+pub mod project_vocabulary;
 mod search_limiter;
 mod search_options;
 mod search_output;
 pub mod search_runner;
 pub mod search_tokens;
+pub mod session_commands;
+pub mod source_files;
 pub mod term_exceptions; // New module for term exceptions
 pub mod timeout; // New module for timeout functionality
 pub mod tokenization; // New elastic search query parser
@@ -21,6 +27,10 @@ pub mod tokenization; // New elastic search query parser
 mod file_processing_tests;
 
 // Public exports
-pub use search_options::SearchOptions;
-pub use search_output::format_and_print_search_results;
-pub use search_runner::perform_probe;
+pub use search_options::{QueryMode, SearchOptions};
+pub use search_output::{
+    context_lines, context_xml, format_and_print_batch_results, format_and_print_search_changes,
+    format_and_print_search_results, format_and_print_search_results_with_template,
+    is_structured_format, json_line, metrics_xml, search_result_template_value,
+};
+pub use search_runner::{perform_probe, perform_workspace_probe};
@@ -0,0 +1,261 @@
+//! Per-project vocabulary.
+//!
+//! A `.probe-vocabulary.toml` file in the searched directory or one of its parents
+//! extends the word lists the tokenizer is built with:
+//!
+//! ```toml
+//! # Words compound identifiers are split into, e.g. "ledgerentry" -> ledger, entry
+//! terms = ["ledger", "entry"]
+//! # Words ignored in queries and code
+//! stop_words = ["acme"]
+//! # Words that are never split into parts
+//! exceptions = ["kubernetes"]
+//! # Groups of interchangeable words; searching for one also finds the others
+//! synonyms = [["auth", "authentication", "login"]]
+//! ```
+//!
+//! `PROBE_VOCABULARY` names a vocabulary file to use instead. The file is read each time
+//! a search starts, so edits apply to the next search without rebuilding probe.
+//!
+//! A search loads the vocabulary of each of its roots and uses them together: the query
+//! is tokenized once for all roots. The vocabulary travels with the `QueryPlan` to the
+//! tokenizer and the ranking, so searches of different projects in one process don't
+//! share it.
+
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use crate::ranking::get_stemmer;
+
+/// Name of the vocabulary file looked for in the searched directory and its parents
+pub const VOCABULARY_FILE: &str = ".probe-vocabulary.toml";
+
+/// A loaded project vocabulary
+#[derive(Debug, Clone, Default)]
+pub struct ProjectVocabulary {
+    /// Files the vocabulary was loaded from
+    pub files: Vec<PathBuf>,
+    terms: HashSet<String>,
+    stop_words: HashSet<String>,
+    exceptions: HashSet<String>,
+    /// Stemmed word -> the stemmed words of its synonym groups, excluding itself
+    synonyms: HashMap<String, Vec<String>>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct VocabularyFile {
+    #[serde(default)]
+    terms: Vec<String>,
+    #[serde(default)]
+    stop_words: Vec<String>,
+    #[serde(default)]
+    exceptions: Vec<String>,
+    #[serde(default)]
+    synonyms: Vec<Vec<String>>,
+}
+
+impl ProjectVocabulary {
+    /// Load a vocabulary from a TOML file
+    pub fn load(file: &Path) -> Result<Self> {
+        let content = std::fs::read_to_string(file)
+            .with_context(|| format!("Failed to read vocabulary file: {}", file.display()))?;
+        let mut vocabulary = Self::from_toml_str(&content)
+            .with_context(|| format!("Invalid vocabulary file: {}", file.display()))?;
+        vocabulary.files.push(file.to_path_buf());
+        Ok(vocabulary)
+    }
+
+    /// Parse a vocabulary definition
+    pub fn from_toml_str(content: &str) -> Result<Self> {
+        let parsed: VocabularyFile = toml::from_str(content)?;
+        let lowercase = |words: Vec<String>| -> HashSet<String> {
+            words
+                .into_iter()
+                .map(|word| word.trim().to_lowercase())
+                .filter(|word| !word.is_empty())
+                .collect()
+        };
+
+        let stemmer = get_stemmer();
+        let mut synonyms: HashMap<String, Vec<String>> = HashMap::new();
+        for group in parsed.synonyms {
+            let mut stems: Vec<String> = Vec::new();
+            for word in &group {
+                let word = word.trim().to_lowercase();
+                if word.is_empty() {
+                    continue;
+                }
+                if word.contains(|c: char| !c.is_alphanumeric()) {
+                    anyhow::bail!("Synonym '{}' must be a single word", word);
+                }
+                let stem = stemmer.stem(&word).to_string();
+                if !stems.contains(&stem) {
+                    stems.push(stem);
+                }
+            }
+            for stem in &stems {
+                let others = synonyms.entry(stem.clone()).or_default();
+                for other in &stems {
+                    if other != stem && !others.contains(other) {
+                        others.push(other.clone());
+                    }
+                }
+            }
+        }
+
+        Ok(ProjectVocabulary {
+            files: Vec::new(),
+            terms: lowercase(parsed.terms),
+            stop_words: lowercase(parsed.stop_words),
+            exceptions: lowercase(parsed.exceptions),
+            synonyms,
+        })
+    }
+
+    /// Add the words of another vocabulary to this one
+    pub fn merge(&mut self, other: ProjectVocabulary) {
+        self.files.extend(other.files);
+        self.terms.extend(other.terms);
+        self.stop_words.extend(other.stop_words);
+        self.exceptions.extend(other.exceptions);
+        for (stem, others) in other.synonyms {
+            let synonyms = self.synonyms.entry(stem).or_default();
+            for other in others {
+                if !synonyms.contains(&other) {
+                    synonyms.push(other);
+                }
+            }
+        }
+    }
+
+    /// Whether a lowercase word is one of the project's domain terms
+    pub fn is_term(&self, word: &str) -> bool {
+        self.terms.contains(word)
+    }
+
+    /// Whether a lowercase word is one of the project's stop words
+    pub fn is_stop_word(&self, word: &str) -> bool {
+        self.stop_words.contains(word)
+    }
+
+    /// Whether a lowercase word must never be split
+    pub fn is_exception(&self, word: &str) -> bool {
+        self.exceptions.contains(word)
+    }
+
+    /// The stemmed synonyms of a stemmed query term
+    pub fn synonyms(&self, term: &str) -> &[String] {
+        self.synonyms.get(term).map(Vec::as_slice).unwrap_or(&[])
+    }
+}
+
+/// Find the vocabulary file for a search path: the file named by `PROBE_VOCABULARY`,
+/// or the nearest `.probe-vocabulary.toml` in the path or one of its parents
+pub fn find_vocabulary_file(path: &Path) -> Option<PathBuf> {
+    if let Ok(file) = std::env::var("PROBE_VOCABULARY") {
+        if !file.is_empty() {
+            return Some(PathBuf::from(file));
+        }
+    }
+
+    let start = path.canonicalize().unwrap_or_else(|_| path.to_path_buf());
+    let mut dir = if start.is_file() {
+        start.parent().map(Path::to_path_buf)
+    } else {
+        Some(start)
+    };
+    while let Some(current) = dir {
+        let candidate = current.join(VOCABULARY_FILE);
+        if candidate.is_file() {
+            return Some(candidate);
+        }
+        dir = current.parent().map(Path::to_path_buf);
+    }
+    None
+}
+
+/// Load the vocabularies of the search paths, merged into one
+///
+/// Paths that share a vocabulary file load it once. Without any vocabulary file only
+/// the built-in word lists are used, and None is returned.
+pub fn load_for_paths<P: AsRef<Path>>(paths: &[P]) -> Result<Option<Arc<ProjectVocabulary>>> {
+    let mut files: Vec<PathBuf> = Vec::new();
+    for path in paths {
+        if let Some(file) = find_vocabulary_file(path.as_ref()) {
+            if !files.contains(&file) {
+                files.push(file);
+            }
+        }
+    }
+
+    let mut merged: Option<ProjectVocabulary> = None;
+    for file in &files {
+        let vocabulary = ProjectVocabulary::load(file)?;
+        match merged.as_mut() {
+            Some(merged) => merged.merge(vocabulary),
+            None => merged = Some(vocabulary),
+        }
+    }
+
+    if std::env::var("DEBUG").unwrap_or_default() == "1" && !files.is_empty() {
+        println!("DEBUG: Using project vocabulary {:?}", files);
+    }
+    Ok(merged.map(Arc::new))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_vocabulary() {
+        let vocabulary = ProjectVocabulary::from_toml_str(
+            r#"
+            terms = ["Ledger", "entry"]
+            stop_words = ["acme"]
+            exceptions = ["kubernetes"]
+            synonyms = [["auth", "authentication", "login"], ["login", "signin"]]
+            "#,
+        )
+        .unwrap();
+
+        assert!(vocabulary.is_term("ledger"));
+        assert!(vocabulary.is_stop_word("acme"));
+        assert!(vocabulary.is_exception("kubernetes"));
+        assert_eq!(vocabulary.synonyms("auth"), ["authent", "login"]);
+        assert_eq!(vocabulary.synonyms("login"), ["auth", "authent", "signin"]);
+        assert!(vocabulary.synonyms("signin").contains(&"login".to_string()));
+        assert!(vocabulary.synonyms("ledger").is_empty());
+
+        assert!(ProjectVocabulary::from_toml_str("synonyms = [[\"sign in\", \"login\"]]").is_err());
+        assert!(ProjectVocabulary::from_toml_str("unknown = []").is_err());
+    }
+
+    #[test]
+    fn test_merge_vocabularies() {
+        let mut vocabulary = ProjectVocabulary::from_toml_str(
+            r#"
+            terms = ["ledger"]
+            synonyms = [["auth", "login"]]
+            "#,
+        )
+        .unwrap();
+        vocabulary.merge(
+            ProjectVocabulary::from_toml_str(
+                r#"
+                stop_words = ["acme"]
+                synonyms = [["login", "signin"]]
+                "#,
+            )
+            .unwrap(),
+        );
+
+        assert!(vocabulary.is_term("ledger"));
+        assert!(vocabulary.is_stop_word("acme"));
+        assert_eq!(vocabulary.synonyms("login"), ["auth", "signin"]);
+    }
+}
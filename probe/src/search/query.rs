@@ -1,6 +1,9 @@
+use crate::ranking::get_stemmer;
 use crate::search::elastic_query;
+use crate::search::project_vocabulary::ProjectVocabulary;
 // No term_exceptions import needed
 use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
 use std::time::Instant;
 
 /// Escapes special regex characters in a string
@@ -24,6 +27,9 @@ pub fn regex_escape(s: &str) -> String {
 // NEW CODE: Full AST-based planning and pattern generation
 // ----------------------------------------------------------------------------
 
+/// A regex files are searched with, and the indices of the terms it matches
+pub type SearchPattern = (String, HashSet<usize>);
+
 /// A unified plan holding the parsed AST and a mapping of each AST term to an index.
 /// We store a map for quick lookups of term indices.
 #[derive(Debug)]
@@ -32,6 +38,47 @@ pub struct QueryPlan {
     pub term_indices: HashMap<String, usize>,
     pub excluded_terms: HashSet<String>,
     pub exact: bool,
+    /// Fuzzy variants of query terms (see `fuzzy`), mapped to the index of the term they
+    /// stand in for. Empty unless searching with `--fuzzy`.
+    pub fuzzy_terms: HashMap<String, usize>,
+    /// Synonyms of query terms from the project vocabulary (see `project_vocabulary`),
+    /// mapped to the index of the term they stand in for
+    pub synonym_terms: HashMap<String, usize>,
+    /// The project vocabulary the query was planned with; files and results of the
+    /// search are tokenized with it too
+    pub vocabulary: Option<Arc<ProjectVocabulary>>,
+}
+
+impl QueryPlan {
+    /// The index of the query term a token matches in place of, if it is a fuzzy
+    /// variant or synonym of one
+    pub fn alias_index(&self, token: &str) -> Option<&usize> {
+        self.synonym_terms
+            .get(token)
+            .or_else(|| self.fuzzy_terms.get(token))
+    }
+
+    /// The project vocabulary of the search, if any
+    pub fn project(&self) -> Option<&ProjectVocabulary> {
+        self.vocabulary.as_deref()
+    }
+
+    /// The fuzzy variants of each query term
+    pub fn fuzzy_variants(&self) -> HashMap<String, Vec<String>> {
+        let mut variants: HashMap<String, Vec<String>> = HashMap::new();
+        for (variant, idx) in &self.fuzzy_terms {
+            if let Some((term, _)) = self.term_indices.iter().find(|(_, i)| *i == idx) {
+                variants
+                    .entry(term.clone())
+                    .or_default()
+                    .push(variant.clone());
+            }
+        }
+        for term_variants in variants.values_mut() {
+            term_variants.sort();
+        }
+        variants
+    }
 }
 
 /// Helper function to format duration in a human-readable way
@@ -45,7 +92,18 @@ fn format_duration(duration: std::time::Duration) -> String {
 
 /// Create a QueryPlan from a raw query string. This fully parses the query into an AST,
 /// then extracts all terms (including excluded), and prepares a term-index map.
+#[allow(dead_code)]
 pub fn create_query_plan(query: &str, exact: bool) -> Result<QueryPlan, elastic_query::ParseError> {
+    create_query_plan_for_project(query, exact, None)
+}
+
+/// Same as [`create_query_plan`], tokenizing the query with a project vocabulary and
+/// expanding its terms with the vocabulary's synonyms
+pub fn create_query_plan_for_project(
+    query: &str,
+    exact: bool,
+    vocabulary: Option<Arc<ProjectVocabulary>>,
+) -> Result<QueryPlan, elastic_query::ParseError> {
     let debug_mode = std::env::var("DEBUG").unwrap_or_default() == "1";
     let start_time = Instant::now();
 
@@ -65,7 +123,7 @@ pub fn create_query_plan(query: &str, exact: bool) -> Result<QueryPlan, elastic_
 
     // Parse the query into an AST with processed terms
     // We use standard Elasticsearch behavior (AND for implicit combinations)
-    let mut ast = elastic_query::parse_query(query, exact)?;
+    let mut ast = elastic_query::parse_query_for_project(query, exact, vocabulary.as_deref())?;
 
     // If exact search is enabled, update the AST to mark all terms as exact
     if exact {
@@ -116,6 +174,15 @@ pub fn create_query_plan(query: &str, exact: bool) -> Result<QueryPlan, elastic_
         term_indices.insert(term.clone(), i);
     }
 
+    // Synonyms match in place of the term they belong to
+    let mut synonym_terms = HashMap::new();
+    if let Some(vocabulary) = &vocabulary {
+        collect_synonym_terms(&ast, &term_indices, vocabulary, &mut synonym_terms);
+        if debug_mode && !synonym_terms.is_empty() {
+            println!("DEBUG: Expanded synonyms: {:?}", synonym_terms);
+        }
+    }
+
     let index_building_duration = index_building_start.elapsed();
 
     if debug_mode {
@@ -138,9 +205,111 @@ pub fn create_query_plan(query: &str, exact: bool) -> Result<QueryPlan, elastic_
         term_indices,
         excluded_terms,
         exact,
+        fuzzy_terms: HashMap::new(),
+        synonym_terms,
+        vocabulary,
     })
 }
 
+/// Create a QueryPlan for `--regex` and `--literal` searches, along with the patterns to
+/// search files with
+///
+/// Every query is one required exact term, so a file must match all of them, and blocks
+/// around the matching lines are kept without checking their tokens. Patterns match
+/// case-sensitively unless they turn on case-insensitivity themselves with `(?i)`.
+pub fn create_pattern_query_plan(
+    queries: &[String],
+    literal: bool,
+) -> Result<(QueryPlan, Vec<SearchPattern>), regex::Error> {
+    let mut term_indices = HashMap::new();
+    let mut patterns = Vec::new();
+    let mut ast: Option<elastic_query::Expr> = None;
+
+    for query in queries {
+        if term_indices.contains_key(query) {
+            continue;
+        }
+        let idx = term_indices.len();
+        term_indices.insert(query.clone(), idx);
+
+        let source = if literal {
+            regex::escape(query)
+        } else {
+            query.clone()
+        };
+        regex::Regex::new(&source)?;
+        // Files are searched case-insensitively by default, which this turns off
+        patterns.push((format!("(?-i){}", source), HashSet::from([idx])));
+
+        let term = elastic_query::Expr::Term {
+            keywords: vec![query.clone()],
+            field: None,
+            required: true,
+            excluded: false,
+            exact: true,
+        };
+        ast = Some(match ast {
+            Some(left) => elastic_query::Expr::And(Box::new(left), Box::new(term)),
+            None => term,
+        });
+    }
+
+    let plan = QueryPlan {
+        ast: ast.unwrap_or(elastic_query::Expr::Term {
+            keywords: Vec::new(),
+            field: None,
+            required: false,
+            excluded: false,
+            exact: true,
+        }),
+        term_indices,
+        excluded_terms: HashSet::new(),
+        exact: true,
+        fuzzy_terms: HashMap::new(),
+        synonym_terms: HashMap::new(),
+        vocabulary: None,
+    };
+    Ok((plan, patterns))
+}
+
+/// Map the synonyms of every non-exact query term to that term's index. Synonyms that
+/// are query terms themselves keep their own index.
+fn collect_synonym_terms(
+    expr: &elastic_query::Expr,
+    term_indices: &HashMap<String, usize>,
+    vocabulary: &ProjectVocabulary,
+    synonym_terms: &mut HashMap<String, usize>,
+) {
+    match expr {
+        elastic_query::Expr::Term {
+            keywords, exact, ..
+        } => {
+            if *exact {
+                return;
+            }
+            let stemmer = get_stemmer();
+            for keyword in keywords {
+                let Some(&idx) = term_indices.get(keyword) else {
+                    continue;
+                };
+                let mut synonyms = vocabulary.synonyms(keyword);
+                if synonyms.is_empty() {
+                    synonyms = vocabulary.synonyms(&stemmer.stem(keyword));
+                }
+                for synonym in synonyms {
+                    if !term_indices.contains_key(synonym) {
+                        synonym_terms.entry(synonym.clone()).or_insert(idx);
+                    }
+                }
+            }
+        }
+        elastic_query::Expr::And(left, right) | elastic_query::Expr::Or(left, right) => {
+            collect_synonym_terms(left, term_indices, vocabulary, synonym_terms);
+            collect_synonym_terms(right, term_indices, vocabulary, synonym_terms);
+        }
+    }
+}
+
 /// Recursively update the AST to mark all terms as exact
 fn update_ast_exact(expr: &mut elastic_query::Expr) {
     match expr {
@@ -397,7 +566,10 @@ pub fn create_structured_patterns(plan: &QueryPlan) -> Vec<(String, HashSet<usiz
                         // Only tokenize if not exact
                         if !*exact {
                             // Generate patterns for each token of the term to match AST tokenization
-                            let tokens = crate::search::tokenization::tokenize_and_stem(keyword);
+                            let tokens = crate::search::tokenization::tokenize_and_stem_for_project(
+                                keyword,
+                                plan.project(),
+                            );
 
                             if debug_mode && tokens.len() > 1 {
                                 println!("DEBUG: Term '{}' tokenized into: {:?}", keyword, tokens);
@@ -518,12 +690,14 @@ pub fn create_structured_patterns(plan: &QueryPlan) -> Vec<(String, HashSet<usiz
         // Skip compound word processing if exact search is enabled
         if keyword.len() > 3 && !is_exact_search(&plan.ast) {
             // Check if it's a camelCase word or a known compound word from vocabulary
-            let camel_parts = crate::search::tokenization::split_camel_case(keyword);
+            let camel_parts =
+                crate::search::tokenization::split_camel_case_for_project(keyword, plan.project());
             let compound_parts = if camel_parts.len() <= 1 {
                 // Not a camelCase word, check if it's in vocabulary
-                crate::search::tokenization::split_compound_word(
+                crate::search::tokenization::split_compound_word_for_project(
                     keyword,
                     crate::search::tokenization::load_vocabulary(),
+                    plan.project(),
                 )
             } else {
                 camel_parts
@@ -641,6 +815,27 @@ pub fn create_structured_patterns(plan: &QueryPlan) -> Vec<(String, HashSet<usiz
         }
     }
 
+    // Fuzzy variants and synonyms match in place of the term they stand in for. They are
+    // added after deduplication, which would otherwise drop them in favour of the term's
+    // own patterns.
+    let mut aliases: Vec<(&String, &usize)> = plan
+        .fuzzy_terms
+        .iter()
+        .chain(plan.synonym_terms.iter())
+        .collect();
+    aliases.sort();
+    aliases.dedup_by(|a, b| a.0 == b.0);
+    for (variant, &idx) in aliases {
+        let pattern = format!("({})", regex_escape(variant));
+        if debug_mode {
+            println!(
+                "DEBUG: Adding fuzzy variant or synonym pattern: '{}' for term index {}",
+                pattern, idx
+            );
+        }
+        deduplicated_results.push((pattern, HashSet::from([idx])));
+    }
+
     let dedup_duration = dedup_start.elapsed();
 
     if debug_mode {
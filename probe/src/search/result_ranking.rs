@@ -1,5 +1,7 @@
 use crate::models::SearchResult;
 use crate::ranking;
+use crate::search::project_vocabulary::ProjectVocabulary;
+use std::collections::HashMap;
 use std::time::Instant;
 
 /// Helper function to format duration in a human-readable way
@@ -11,8 +13,58 @@ fn format_duration(duration: std::time::Duration) -> String {
     }
 }
 
+/// The text a result is ranked by: its code, preceded by the file name
+fn ranking_document(result: &SearchResult) -> String {
+    format!("// Filename: {}\n{}", result.file, result.code)
+}
+
+/// Attach a `RankingExplanation` to every result, for `--explain`
+///
+/// Explains the ranking `rank_search_results` computes for the same results and queries,
+/// so it has to run before the results are ranked.
+pub fn explain_search_results(
+    results: &mut [SearchResult],
+    queries: &[String],
+    fuzzy_terms: Option<&HashMap<String, Vec<String>>>,
+    vocabulary: Option<&ProjectVocabulary>,
+) {
+    let combined_query = queries.join(" ");
+    let documents: Vec<String> = results.iter().map(ranking_document).collect();
+    let documents_refs: Vec<&str> = documents.iter().map(|s| s.as_str()).collect();
+    let filenames: Vec<&str> = results.iter().map(|r| r.file.as_str()).collect();
+    let pre_tokenized: Option<Vec<Vec<String>>> = results
+        .iter()
+        .map(|r| r.tokenized_content.clone())
+        .collect();
+
+    let explanations = ranking::explain_documents(
+        &ranking::RankingParams {
+            documents: &documents_refs,
+            query: &combined_query,
+            pre_tokenized: pre_tokenized.as_deref(),
+            fuzzy_terms,
+            vocabulary,
+        },
+        &filenames,
+    );
+
+    for (result, mut explanation) in results.iter_mut().zip(explanations) {
+        explanation.matched_by_filename = result.matched_by_filename.unwrap_or(false);
+        result.explanation = Some(explanation);
+    }
+}
+
 /// Function to rank search results based on query relevance using BM25 algorithm
-pub fn rank_search_results(results: &mut [SearchResult], queries: &[String], reranker: &str) {
+///
+/// `fuzzy_terms` maps query terms to their fuzzy variants, which count at a reduced weight.
+/// `vocabulary` is the project vocabulary the results were found with.
+pub fn rank_search_results(
+    results: &mut [SearchResult],
+    queries: &[String],
+    reranker: &str,
+    fuzzy_terms: Option<&HashMap<String, Vec<String>>>,
+    vocabulary: Option<&ProjectVocabulary>,
+) {
     let start_time = Instant::now();
 
     // Check if debug mode is enabled
@@ -43,10 +95,7 @@ pub fn rank_search_results(results: &mut [SearchResult], queries: &[String], rer
     // Extract document texts for ranking, including filename in each document
     let document_extraction_start = Instant::now();
     // This ensures filename terms are considered in the ranking algorithms
-    let documents: Vec<String> = results
-        .iter()
-        .map(|r| format!("// Filename: {}\n{}", r.file, r.code))
-        .collect();
+    let documents: Vec<String> = results.iter().map(ranking_document).collect();
     let documents_refs: Vec<&str> = documents.iter().map(|s| s.as_str()).collect();
     let document_extraction_duration = document_extraction_start.elapsed();
 
@@ -119,6 +168,8 @@ pub fn rank_search_results(results: &mut [SearchResult], queries: &[String], rer
         } else {
             None
         },
+        fuzzy_terms,
+        vocabulary,
     };
 
     let document_ranking_start = Instant::now();
@@ -171,7 +222,8 @@ pub fn rank_search_results(results: &mut [SearchResult], queries: &[String], rer
     if debug_mode {
         println!("DEBUG: Using BM25 ranking (Okapi BM25 algorithm)");
     } else {
-        println!("Using BM25 ranking (Okapi BM25 algorithm)");
+        // Status goes to stderr, so that stdout only carries the formatted results
+        eprintln!("Using BM25 ranking (Okapi BM25 algorithm)");
     }
 
     // Sort by BM25 score in descending order
@@ -241,3 +293,26 @@ pub fn rank_search_results(results: &mut [SearchResult], queries: &[String], rer
         );
     }
 }
+
+/// Rank `--regex` and `--literal` results, which have no terms to compute BM25 with
+///
+/// Results with more matches rank first, then shorter blocks. The match ranges have to
+/// be filled in before ranking.
+pub fn rank_by_match_count(results: &mut [SearchResult]) {
+    for result in results.iter_mut() {
+        result.score = Some(result.match_ranges.as_ref().map_or(0, Vec::len) as f64);
+    }
+
+    results.sort_by(|a, b| {
+        let matches = |r: &SearchResult| r.match_ranges.as_ref().map_or(0, Vec::len);
+        matches(b)
+            .cmp(&matches(a))
+            .then_with(|| (a.lines.1 - a.lines.0).cmp(&(b.lines.1 - b.lines.0)))
+            .then_with(|| a.file.cmp(&b.file))
+            .then_with(|| a.lines.cmp(&b.lines))
+    });
+
+    for (rank, result) in results.iter_mut().enumerate() {
+        result.rank = Some(rank + 1);
+    }
+}
@@ -11,9 +11,11 @@ pub fn apply_limits(
     if max_results.is_none() && max_bytes.is_none() && max_tokens.is_none() {
         return LimitedSearchResults {
             results,
+            skipped_results: Vec::new(),
             skipped_files: Vec::new(),
             limits_applied: None,
             cached_blocks_skipped: None,
+            suggestions: Vec::new(),
         };
     }
 
@@ -54,7 +56,8 @@ pub fn apply_limits(
 
     LimitedSearchResults {
         results: limited,
-        skipped_files: skipped,
+        skipped_results: skipped,
+        skipped_files: Vec::new(),
         limits_applied: Some(SearchLimits {
             max_results,
             max_bytes,
@@ -63,5 +66,6 @@ pub fn apply_limits(
             total_tokens,
         }),
         cached_blocks_skipped: None,
+        suggestions: Vec::new(),
     }
 }
@@ -1,3 +1,4 @@
+use crate::search::source_files::FileLimits;
 use std::path::Path;
 
 /// Options for performing a search
@@ -21,4 +22,64 @@ pub struct SearchOptions<'a> {
     pub dry_run: bool,
     pub session: Option<&'a str>,
     pub timeout: u64,
+    /// Page of not-yet-seen blocks to return within the session (see `cache::select_page`)
+    pub page: Option<usize>,
+    /// Attach a `RankingExplanation` to every result
+    pub explain: bool,
+    /// Match misspelled terms against the identifiers in the searched files (see `fuzzy`)
+    pub fuzzy: bool,
+    /// Whether queries are parsed into terms or used as regular expressions or literal text
+    pub query_mode: QueryMode,
+    /// Which files the walker leaves out: too large, binary, minified or generated
+    pub file_limits: FileLimits,
+    /// Start nested blocks with the signature line of the block they are in
+    pub parent_context: bool,
+    /// Results are printed in a machine-readable format, so the session and page status
+    /// lines go to stderr
+    pub structured_output: bool,
+}
+
+impl Default for SearchOptions<'_> {
+    /// The options of `probe search` without arguments, searching the current directory
+    fn default() -> Self {
+        SearchOptions {
+            path: Path::new("."),
+            queries: &[],
+            files_only: false,
+            custom_ignores: &[],
+            exclude_filenames: false,
+            reranker: "bm25",
+            frequency_search: true,
+            exact: false,
+            language: None,
+            max_results: None,
+            max_bytes: None,
+            max_tokens: None,
+            allow_tests: false,
+            no_merge: false,
+            merge_threshold: None,
+            dry_run: false,
+            session: None,
+            timeout: 30,
+            page: None,
+            explain: false,
+            fuzzy: false,
+            query_mode: QueryMode::Terms,
+            file_limits: FileLimits::default(),
+            parent_context: false,
+            structured_output: false,
+        }
+    }
+}
+
+/// How search queries are turned into the patterns files are matched with
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum QueryMode {
+    /// Parse queries into stemmed terms combined with boolean operators
+    #[default]
+    Terms,
+    /// Use every query as a regular expression
+    Regex,
+    /// Match every query as literal text
+    Literal,
 }
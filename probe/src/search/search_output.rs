@@ -1,13 +1,19 @@
 use anyhow::Result;
 use std::path::Path;
 
-use crate::models::SearchResult;
+use crate::models::{CodeMetrics, ContextBlock, MatchRange, RankingExplanation, SearchResult};
+use crate::search::batch::BatchResult;
 use crate::search::query::QueryPlan;
 use crate::search::search_tokens::count_tokens;
+use crate::search::source_files::SkippedFile;
+use crate::watch::ResultChange;
+use probe::output_template::{add_common_fields, OutputTemplate, TemplateContext, TemplateTotals};
+use probe::workspace::display_path;
 
 /// Function to format and print search results according to the specified format
 pub fn format_and_print_search_results(
     results: &[SearchResult],
+    skipped_files: &[SkippedFile],
     dry_run: bool,
     format: &str,
     query_plan: Option<&QueryPlan>,
@@ -29,11 +35,17 @@ pub fn format_and_print_search_results(
             format_and_print_color_results(&valid_results, dry_run, query_plan, debug_mode);
         }
         "json" => {
-            if let Err(e) = format_and_print_json_results(&valid_results) {
+            if let Err(e) = format_and_print_json_results(&valid_results, skipped_files) {
                 eprintln!("Error formatting JSON: {}", e);
             }
             return; // Skip the summary output at the end
         }
+        "jsonl" => {
+            if let Err(e) = format_and_print_jsonl_results(&valid_results, skipped_files) {
+                eprintln!("Error formatting JSON Lines: {}", e);
+            }
+            return; // Skip the summary output at the end
+        }
         "xml" => {
             if let Err(e) = format_and_print_xml_results(&valid_results) {
                 eprintln!("Error formatting XML: {}", e);
@@ -42,116 +54,7 @@ pub fn format_and_print_search_results(
         }
         _ => {
             // Default format (terminal)
-            for result in &valid_results {
-                let file_path = Path::new(&result.file);
-                let extension = file_path
-                    .extension()
-                    .and_then(|ext| ext.to_str())
-                    .unwrap_or("");
-                let is_full_file = result.node_type == "file";
-
-                if dry_run {
-                    // In dry-run mode, only print file names and line numbers
-                    if is_full_file {
-                        println!("File: {}", result.file);
-                    } else {
-                        println!(
-                            "File: {}, Lines: {}-{}",
-                            result.file, result.lines.0, result.lines.1
-                        );
-                    }
-                } else {
-                    // Normal mode with full content
-                    if is_full_file {
-                        println!("File: {}", result.file);
-                        println!("```{}", extension);
-                        println!("{}", result.code);
-                        println!("```");
-                    } else {
-                        println!("File: {}", result.file);
-                        println!("Lines: {}-{}", result.lines.0, result.lines.1);
-                        println!("```{}", extension);
-                        println!("{}", result.code);
-                        println!("```");
-                    }
-                }
-                if debug_mode {
-                    if let Some(rank) = result.rank {
-                        // Add a display order field to show the actual ordering of results
-                        println!(
-                            "Display Order: {}",
-                            results
-                                .iter()
-                                .position(|r| r.file == result.file && r.lines == result.lines)
-                                .unwrap_or(0)
-                                + 1
-                        );
-
-                        println!("Rank: {}", rank);
-
-                        if let Some(score) = result.score {
-                            println!("Combined Score: {:.4}", score);
-                        }
-
-                        // Display the combined score rank if available, otherwise calculate it
-                        if let Some(combined_rank) = result.combined_score_rank {
-                            println!("Combined Score Rank: {}", combined_rank);
-                        } else {
-                            // Fall back to the old behavior if the field isn't set
-                            println!("Combined Score Rank: {}", rank);
-                        }
-
-                        if let Some(tfidf_score) = result.tfidf_score {
-                            println!("TF-IDF Score: {:.4}", tfidf_score);
-                        }
-
-                        if let Some(tfidf_rank) = result.tfidf_rank {
-                            println!("TF-IDF Rank: {}", tfidf_rank);
-                        }
-
-                        if let Some(bm25_score) = result.bm25_score {
-                            println!("BM25 Score: {:.4}", bm25_score);
-                        }
-
-                        if let Some(bm25_rank) = result.bm25_rank {
-                            println!("BM25 Rank: {}", bm25_rank);
-                        }
-
-                        // Display Hybrid 2 score and rank with more prominence
-                        if let Some(new_score) = result.new_score {
-                            println!("Hybrid 2 Score: {:.4}", new_score);
-                        }
-
-                        if let Some(hybrid2_rank) = result.hybrid2_rank {
-                            println!("Hybrid 2 Rank: {}", hybrid2_rank);
-                        } else if result.new_score.is_some() {
-                            println!("Hybrid 2 Rank: N/A");
-                        }
-
-                        if let Some(file_unique_terms) = result.file_unique_terms {
-                            println!("File Unique Terms: {}", file_unique_terms);
-                        }
-
-                        if let Some(file_total_matches) = result.file_total_matches {
-                            println!("File Total Matches: {}", file_total_matches);
-                        }
-
-                        if let Some(file_match_rank) = result.file_match_rank {
-                            println!("File Match Rank: {}", file_match_rank);
-                        }
-
-                        if let Some(block_unique_terms) = result.block_unique_terms {
-                            println!("Block Unique Terms: {}", block_unique_terms);
-                        }
-
-                        if let Some(block_total_matches) = result.block_total_matches {
-                            println!("Block Total Matches: {}", block_total_matches);
-                        }
-
-                        println!("Type: {}", result.node_type);
-                    }
-                }
-            }
+            format_and_print_text_results(&valid_results, dry_run, debug_mode);
         }
     }
 
@@ -163,6 +66,138 @@ pub fn format_and_print_search_results(
     println!("Total tokens returned: {}", total_tokens);
 }
 
+/// Print search results as plain text: the file, lines and code of every result
+fn format_and_print_text_results(results: &[&SearchResult], dry_run: bool, debug_mode: bool) {
+    for result in results {
+        let file_path = Path::new(&result.file);
+        let extension = file_path
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .unwrap_or("");
+        let is_full_file = result.node_type == "file";
+        let display_file = display_path(result.workspace_root.as_deref(), &result.file);
+
+        if dry_run {
+            // In dry-run mode, only print file names and line numbers
+            if is_full_file {
+                println!("File: {}", display_file);
+            } else {
+                println!(
+                    "File: {}, Lines: {}-{}",
+                    display_file, result.lines.0, result.lines.1
+                );
+            }
+        } else {
+            // Normal mode with full content
+            if is_full_file {
+                println!("File: {}", display_file);
+                println!("```{}", extension);
+                println!("{}", result.code);
+                println!("```");
+            } else {
+                println!("File: {}", display_file);
+                println!("Lines: {}-{}", result.lines.0, result.lines.1);
+                println!("```{}", extension);
+                println!("{}", result.code);
+                println!("```");
+            }
+        }
+        if result.changed_since_seen == Some(true) {
+            println!("Changed since last seen in this session");
+        }
+        if let Some(metrics) = &result.metrics {
+            println!("Metrics: {}", metrics);
+        }
+        if let Some(explanation) = &result.explanation {
+            println!("Explanation:");
+            for line in explanation_lines(explanation) {
+                println!("  {}", line);
+            }
+        }
+        if let Some(blocks) = &result.context_blocks {
+            for line in context_lines(blocks, dry_run) {
+                println!("{}", line);
+            }
+        }
+        if debug_mode {
+            if let Some(rank) = result.rank {
+                // Add a display order field to show the actual ordering of results
+                println!(
+                    "Display Order: {}",
+                    results
+                        .iter()
+                        .position(|r| r.file == result.file && r.lines == result.lines)
+                        .unwrap_or(0)
+                        + 1
+                );
+
+                println!("Rank: {}", rank);
+
+                if let Some(score) = result.score {
+                    println!("Combined Score: {:.4}", score);
+                }
+
+                // Display the combined score rank if available, otherwise calculate it
+                if let Some(combined_rank) = result.combined_score_rank {
+                    println!("Combined Score Rank: {}", combined_rank);
+                } else {
+                    // Fall back to the old behavior if the field isn't set
+                    println!("Combined Score Rank: {}", rank);
+                }
+
+                if let Some(tfidf_score) = result.tfidf_score {
+                    println!("TF-IDF Score: {:.4}", tfidf_score);
+                }
+
+                if let Some(tfidf_rank) = result.tfidf_rank {
+                    println!("TF-IDF Rank: {}", tfidf_rank);
+                }
+
+                if let Some(bm25_score) = result.bm25_score {
+                    println!("BM25 Score: {:.4}", bm25_score);
+                }
+
+                if let Some(bm25_rank) = result.bm25_rank {
+                    println!("BM25 Rank: {}", bm25_rank);
+                }
+
+                // Display Hybrid 2 score and rank with more prominence
+                if let Some(new_score) = result.new_score {
+                    println!("Hybrid 2 Score: {:.4}", new_score);
+                }
+
+                if let Some(hybrid2_rank) = result.hybrid2_rank {
+                    println!("Hybrid 2 Rank: {}", hybrid2_rank);
+                } else if result.new_score.is_some() {
+                    println!("Hybrid 2 Rank: N/A");
+                }
+
+                if let Some(file_unique_terms) = result.file_unique_terms {
+                    println!("File Unique Terms: {}", file_unique_terms);
+                }
+
+                if let Some(file_total_matches) = result.file_total_matches {
+                    println!("File Total Matches: {}", file_total_matches);
+                }
+
+                if let Some(file_match_rank) = result.file_match_rank {
+                    println!("File Match Rank: {}", file_match_rank);
+                }
+
+                if let Some(block_unique_terms) = result.block_unique_terms {
+                    println!("Block Unique Terms: {}", block_unique_terms);
+                }
+
+                if let Some(block_total_matches) = result.block_total_matches {
+                    println!("Block Total Matches: {}", block_total_matches);
+                }
+
+                println!("Type: {}", result.node_type);
+            }
+        }
+    }
+}
+
 /// Format and print search results with color highlighting for matching words
 fn format_and_print_color_results(
     results: &[&SearchResult],
@@ -193,6 +228,7 @@ fn format_and_print_color_results(
 
         // Check if this is a full file or partial file
         let is_full_file = result.node_type == "file";
+        let display_file = display_path(result.workspace_root.as_deref(), &result.file);
 
         // Print result number
         println!(
@@ -203,12 +239,12 @@ fn format_and_print_color_results(
 
         // Print the file path and node info with color
         if is_full_file {
-            println!("{} {}", "File:".bold().green(), result.file.yellow());
+            println!("{} {}", "File:".bold().green(), display_file.yellow());
         } else {
             println!(
                 "{} {} ({})",
                 "File:".bold().green(),
-                result.file.yellow(),
+                display_file.yellow(),
                 result.node_type.cyan()
             );
             println!(
@@ -218,6 +254,18 @@ fn format_and_print_color_results(
                 result.lines.1
             );
         }
+        if result.changed_since_seen == Some(true) {
+            println!("{}", "Changed since last seen in this session".yellow());
+        }
+        if let Some(metrics) = &result.metrics {
+            println!("{} {}", "Metrics:".bold().green(), metrics);
+        }
+        if let Some(explanation) = &result.explanation {
+            println!("{}", "Explanation:".bold().green());
+            for line in explanation_lines(explanation) {
+                println!("  {}", line.dimmed());
+            }
+        }
 
         // Print additional debug information if in debug mode
         if debug_mode {
@@ -313,15 +361,29 @@ fn format_and_print_color_results(
         }
 
         // Process the code line by line with inline highlighting
-        for line in result.code.lines() {
+        for (line_index, line) in result.code.lines().enumerate() {
             let mut output_line = String::new();
             let mut last_end = 0;
             let mut matches = Vec::new();
 
-            // Collect all matches from all patterns
-            for pattern in &patterns {
-                for mat in pattern.find_iter(line) {
-                    matches.push((mat.start(), mat.end()));
+            // Highlight the ranges the search matched, or else the matched keywords
+            if let Some(ranges) = &result.match_ranges {
+                for range in ranges
+                    .iter()
+                    .filter(|range| range.line == result.lines.0 + line_index)
+                {
+                    let byte_at = |column: usize| {
+                        line.char_indices()
+                            .nth(column - 1)
+                            .map_or(line.len(), |(i, _)| i)
+                    };
+                    matches.push((byte_at(range.column_start), byte_at(range.column_end)));
+                }
+            } else {
+                for pattern in &patterns {
+                    for mat in pattern.find_iter(line) {
+                        matches.push((mat.start(), mat.end()));
+                    }
                 }
             }
 
@@ -371,6 +433,12 @@ fn format_and_print_color_results(
 
         println!();
 
+        if let Some(blocks) = &result.context_blocks {
+            for line in context_lines(blocks, dry_run) {
+                println!("{}", line);
+            }
+        }
+
         // Print a separator between results
         if index < results.len() - 1 {
             println!();
@@ -466,30 +534,147 @@ fn escape_xml(s: &str) -> String {
         .replace("'", "&apos;")
 }
 
-/// Format and print search results in JSON format
-fn format_and_print_json_results(results: &[&SearchResult]) -> Result<()> {
-    // Create a simplified version of the results for JSON output
+/// Whether a format is machine-readable, so headers and summaries must not be printed
+///
+/// "template" is the format of commands run with `--template`.
+pub fn is_structured_format(format: &str) -> bool {
+    matches!(format, "json" | "jsonl" | "xml" | "sarif" | "template")
+}
+
+/// Convert a result to the object templates see: every field plus the common ones
+pub fn search_result_template_value(result: &SearchResult) -> serde_json::Value {
+    let mut value = serde_json::to_value(result).unwrap_or_default();
+    value["root"] = serde_json::json!(result.workspace_root);
+    add_common_fields(&mut value, &result.file, result.lines);
+    value
+}
+
+/// Render search results with an output template and print them
+pub fn format_and_print_search_results_with_template(
+    results: &[SearchResult],
+    dry_run: bool,
+    template: &OutputTemplate,
+    query: &str,
+) -> Result<()> {
+    let valid_results: Vec<&SearchResult> = results.iter().filter(|r| !r.file.is_empty()).collect();
+    let context = TemplateContext {
+        command: "search",
+        query: Some(query.to_string()),
+        dry_run,
+        results: valid_results
+            .iter()
+            .map(|r| search_result_template_value(r))
+            .collect(),
+        totals: TemplateTotals {
+            count: valid_results.len(),
+            total_bytes: valid_results.iter().map(|r| r.code.len()).sum(),
+            total_tokens: valid_results.iter().map(|r| count_tokens(&r.code)).sum(),
+        },
+        original_input: None,
+        system_prompt: None,
+        user_instructions: None,
+    };
+    print!("{}", template.render(&context)?);
+    Ok(())
+}
+
+/// Serialize one JSON Lines record: a `"type"` tag followed by the fields of `item`
+pub fn json_line<T: serde::Serialize>(kind: &str, item: &T) -> Result<String> {
     #[derive(serde::Serialize)]
-    struct JsonResult<'a> {
-        file: &'a str,
-        lines: [usize; 2],
-        node_type: &'a str,
-        code: &'a str,
-        // Include other relevant fields
-        matched_keywords: Option<&'a Vec<String>>,
-        score: Option<f64>,
-        tfidf_score: Option<f64>,
-        bm25_score: Option<f64>,
-        file_unique_terms: Option<usize>,
-        file_total_matches: Option<usize>,
-        block_unique_terms: Option<usize>,
-        block_total_matches: Option<usize>,
+    struct JsonLine<'a, T> {
+        #[serde(rename = "type")]
+        kind: &'a str,
+        #[serde(flatten)]
+        item: &'a T,
     }
 
-    let json_results: Vec<JsonResult> = results
-        .iter()
-        .map(|r| JsonResult {
+    Ok(serde_json::to_string(&JsonLine { kind, item })?)
+}
+
+/// Describe a ranking explanation, one line per item, for the text output formats
+fn explanation_lines(explanation: &RankingExplanation) -> Vec<String> {
+    let mut lines = vec![format!("Query: {}", explanation.query)];
+    lines.push(match explanation.bm25_score {
+        Some(score) => format!("BM25 score: {:.4}", score),
+        None => "BM25 score: none, the block doesn't satisfy the query".to_string(),
+    });
+    lines.push(format!(
+        "File name boost: {:.4}{}",
+        explanation.filename_boost,
+        if explanation.matched_by_filename {
+            " (file matched by name)"
+        } else {
+            ""
+        }
+    ));
+    for term in &explanation.terms {
+        let matches = if term.raw_matches.is_empty() {
+            "no match".to_string()
+        } else {
+            format!("matched {}", term.raw_matches.join(", "))
+        };
+        lines.push(format!(
+            "Term \"{}\" ({}): {}; tf {} ({} in file name), idf {:.4}, bm25 {:.4}",
+            term.term,
+            term.role,
+            matches,
+            term.frequency,
+            term.filename_frequency,
+            term.idf,
+            term.bm25
+        ));
+    }
+    for merged in &explanation.merged_blocks {
+        lines.push(format!(
+            "Merged block: lines {}-{} (rank {}, score {})",
+            merged.lines.0,
+            merged.lines.1,
+            merged
+                .rank
+                .map_or_else(|| "none".to_string(), |r| r.to_string()),
+            merged
+                .score
+                .map_or_else(|| "none".to_string(), |s| format!("{:.4}", s))
+        ));
+    }
+    lines
+}
+
+/// A simplified version of a search result for JSON output
+#[derive(serde::Serialize)]
+struct JsonResult<'a> {
+    file: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    root: Option<&'a str>,
+    lines: [usize; 2],
+    node_type: &'a str,
+    code: &'a str,
+    // Include other relevant fields
+    matched_keywords: Option<&'a Vec<String>>,
+    score: Option<f64>,
+    tfidf_score: Option<f64>,
+    bm25_score: Option<f64>,
+    file_unique_terms: Option<usize>,
+    file_total_matches: Option<usize>,
+    block_unique_terms: Option<usize>,
+    block_total_matches: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    changed_since_seen: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    explanation: Option<&'a RankingExplanation>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    match_ranges: Option<&'a Vec<MatchRange>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    metrics: Option<&'a CodeMetrics>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    context: Option<&'a Vec<ContextBlock>>,
+}
+
+impl<'a> JsonResult<'a> {
+    fn new(r: &'a SearchResult) -> Self {
+        JsonResult {
             file: &r.file,
+            root: r.workspace_root.as_deref(),
             lines: [r.lines.0, r.lines.1],
             node_type: &r.node_type,
             code: &r.code,
@@ -501,23 +686,186 @@ fn format_and_print_json_results(results: &[&SearchResult]) -> Result<()> {
             file_total_matches: r.file_total_matches,
             block_unique_terms: r.block_unique_terms,
             block_total_matches: r.block_total_matches,
-        })
-        .collect();
+            changed_since_seen: r.changed_since_seen,
+            explanation: r.explanation.as_ref(),
+            match_ranges: r.match_ranges.as_ref(),
+            metrics: r.metrics.as_ref(),
+            context: r.context_blocks.as_ref(),
+        }
+    }
+}
+
+/// Summary of a result set, shared by the JSON and JSON Lines formats
+fn json_summary(results: &[&SearchResult]) -> serde_json::Value {
+    serde_json::json!({
+        "count": results.len(),
+        "total_bytes": results.iter().map(|r| r.code.len()).sum::<usize>(),
+        "total_tokens": results.iter().map(|r| count_tokens(&r.code)).sum::<usize>(),
+    })
+}
+
+/// The summary of search results with the files the walker left out
+fn json_search_summary(
+    results: &[&SearchResult],
+    skipped_files: &[SkippedFile],
+) -> serde_json::Value {
+    let mut summary = json_summary(results);
+    summary["skipped_files"] = serde_json::json!(skipped_files);
+    summary
+}
+
+/// Format and print search results in JSON format
+fn format_and_print_json_results(
+    results: &[&SearchResult],
+    skipped_files: &[SkippedFile],
+) -> Result<()> {
+    let json_results: Vec<JsonResult> = results.iter().map(|r| JsonResult::new(r)).collect();
 
     // Create a wrapper object with results and summary
     let wrapper = serde_json::json!({
         "results": json_results,
-        "summary": {
-            "count": results.len(),
-            "total_bytes": results.iter().map(|r| r.code.len()).sum::<usize>(),
-            "total_tokens": results.iter().map(|r| count_tokens(&r.code)).sum::<usize>(),
-        }
+        "summary": json_search_summary(results, skipped_files),
     });
 
     println!("{}", serde_json::to_string_pretty(&wrapper)?);
     Ok(())
 }
 
+/// Format and print search results as JSON Lines: one result per line, then a record
+/// per skipped file, then the summary
+fn format_and_print_jsonl_results(
+    results: &[&SearchResult],
+    skipped_files: &[SkippedFile],
+) -> Result<()> {
+    for result in results {
+        println!("{}", json_line("result", &JsonResult::new(result))?);
+    }
+    for skipped in skipped_files {
+        println!("{}", json_line("skipped_file", skipped)?);
+    }
+    println!("{}", json_line("summary", &json_summary(results))?);
+    Ok(())
+}
+
+/// A result of a rerun with how it changed, for JSON Lines output
+#[derive(serde::Serialize)]
+struct JsonChangedResult<'a> {
+    change: ResultChange,
+    #[serde(flatten)]
+    result: JsonResult<'a>,
+}
+
+/// Format and print how the results of a rerun differ from the previous run (see
+/// `search --watch`)
+///
+/// JSON Lines output has a result record with a `change` field per changed result, then
+/// the summary of all `results` of the rerun. Text formats show the added and changed
+/// results like search results, and the file and lines of the removed ones.
+pub fn format_and_print_search_changes(
+    changes: &[(ResultChange, &SearchResult)],
+    results: &[SearchResult],
+    dry_run: bool,
+    format: &str,
+    query_plan: Option<&QueryPlan>,
+) -> Result<()> {
+    use colored::*;
+
+    if format == "jsonl" {
+        for (change, result) in changes {
+            let record = JsonChangedResult {
+                change: *change,
+                result: JsonResult::new(result),
+            };
+            println!("{}", json_line("result", &record)?);
+        }
+        let results: Vec<&SearchResult> = results.iter().collect();
+        println!("{}", json_line("summary", &json_summary(&results))?);
+        return Ok(());
+    }
+
+    let debug_mode = std::env::var("DEBUG").unwrap_or_default() == "1";
+    let use_color = format == "color" && colored::control::SHOULD_COLORIZE.should_colorize();
+    for (change, heading) in [
+        (ResultChange::Added, "Added:"),
+        (ResultChange::Changed, "Changed:"),
+        (ResultChange::Removed, "Removed:"),
+    ] {
+        let group: Vec<&SearchResult> = changes
+            .iter()
+            .filter(|(c, _)| *c == change)
+            .map(|(_, result)| *result)
+            .collect();
+        if group.is_empty() {
+            continue;
+        }
+        println!("{} {}", heading.bold().green(), group.len());
+        if change == ResultChange::Removed {
+            for result in &group {
+                let display_file = display_path(result.workspace_root.as_deref(), &result.file);
+                println!("  {}:{}-{}", display_file, result.lines.0, result.lines.1);
+            }
+        } else if use_color {
+            format_and_print_color_results(&group, dry_run, query_plan, debug_mode);
+        } else {
+            format_and_print_text_results(&group, dry_run, debug_mode);
+        }
+    }
+    println!("Now {} search results", results.len());
+    Ok(())
+}
+
+/// Format and print the results of a batch, grouped by query, as JSON or JSON Lines
+///
+/// JSON output holds one `{query, results, summary}` object per query. JSON Lines output
+/// starts every query's results with a `"query"` record and ends them with its summary.
+pub fn format_and_print_batch_results(batch: &[BatchResult], format: &str) -> Result<()> {
+    fn valid_results(entry: &BatchResult) -> Vec<&SearchResult> {
+        let results = entry.results.results.iter();
+        results.filter(|r| !r.file.is_empty()).collect()
+    }
+
+    match format {
+        "json" => {
+            let queries: Vec<serde_json::Value> = batch
+                .iter()
+                .map(|entry| {
+                    let results = valid_results(entry);
+                    let json_results: Vec<JsonResult> =
+                        results.iter().map(|r| JsonResult::new(r)).collect();
+                    let summary = json_search_summary(&results, &entry.results.skipped_files);
+                    serde_json::json!({
+                        "query": entry.query,
+                        "results": json_results,
+                        "summary": summary,
+                        "duplicates_skipped": entry.duplicates_skipped,
+                    })
+                })
+                .collect();
+            let all_results: Vec<&SearchResult> = batch.iter().flat_map(valid_results).collect();
+            let wrapper = serde_json::json!({
+                "queries": queries,
+                "summary": json_summary(&all_results),
+            });
+            println!("{}", serde_json::to_string_pretty(&wrapper)?);
+        }
+        "jsonl" => {
+            for entry in batch {
+                let query = serde_json::json!({
+                    "query": entry.query,
+                    "duplicates_skipped": entry.duplicates_skipped,
+                });
+                println!("{}", json_line("query", &query)?);
+                format_and_print_jsonl_results(
+                    &valid_results(entry),
+                    &entry.results.skipped_files,
+                )?;
+            }
+        }
+        _ => anyhow::bail!("Batch results can't be printed as {}", format),
+    }
+    Ok(())
+}
+
 /// Format and print search results in XML format
 fn format_and_print_xml_results(results: &[&SearchResult]) -> Result<()> {
     println!("<?xml version=\"1.0\" encoding=\"UTF-8\"?>");
@@ -526,11 +874,17 @@ fn format_and_print_xml_results(results: &[&SearchResult]) -> Result<()> {
     for result in results {
         println!("  <result>");
         println!("    <file>{}</file>", escape_xml(&result.file));
+        if let Some(root) = &result.workspace_root {
+            println!("    <root>{}</root>", escape_xml(root));
+        }
         println!("    <lines>{}-{}</lines>", result.lines.0, result.lines.1);
         println!(
             "    <node_type>{}</node_type>",
             escape_xml(&result.node_type)
         );
+        if result.changed_since_seen == Some(true) {
+            println!("    <changed_since_seen>true</changed_since_seen>");
+        }
 
         if let Some(keywords) = &result.matched_keywords {
             println!("    <matched_keywords>");
@@ -580,7 +934,14 @@ fn format_and_print_xml_results(results: &[&SearchResult]) -> Result<()> {
             );
         }
 
+        if let Some(metrics) = &result.metrics {
+            println!("{}", metrics_xml(metrics, "    "));
+        }
+
         println!("    <code><![CDATA[{}]]></code>", result.code);
+        if let Some(blocks) = &result.context_blocks {
+            println!("{}", context_xml(blocks, "    "));
+        }
         println!("  </result>");
     }
 
@@ -600,3 +961,73 @@ fn format_and_print_xml_results(results: &[&SearchResult]) -> Result<()> {
     println!("</probe_results>");
     Ok(())
 }
+
+/// Text lines listing a result's context blocks: a heading each, followed by the code
+/// unless this is a dry run
+pub fn context_lines(blocks: &[ContextBlock], dry_run: bool) -> Vec<String> {
+    let mut lines = Vec::new();
+    for block in blocks {
+        lines.push(format!("Context: {}", block.heading()));
+        if dry_run {
+            continue;
+        }
+        let extension = Path::new(&block.file)
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .unwrap_or("");
+        lines.push(format!("```{}", extension));
+        lines.push(block.code.clone());
+        lines.push("```".to_string());
+    }
+    lines
+}
+
+/// The XML element of a result's context blocks, indented by `indent`
+pub fn context_xml(blocks: &[ContextBlock], indent: &str) -> String {
+    let mut xml = format!("{}<context>\n", indent);
+    for block in blocks {
+        let name = block
+            .name
+            .as_ref()
+            .map(|name| format!(" name=\"{}\"", escape_xml(name)))
+            .unwrap_or_default();
+        xml.push_str(&format!(
+            "{}  <block kind=\"{}\"{} file=\"{}\" lines=\"{}-{}\"><![CDATA[{}]]></block>\n",
+            indent,
+            block.kind,
+            name,
+            escape_xml(&block.file),
+            block.lines.0,
+            block.lines.1,
+            block.code
+        ));
+    }
+    xml.push_str(&format!("{}</context>", indent));
+    xml
+}
+
+/// The XML element of a result's metrics, indented by `indent`
+pub fn metrics_xml(metrics: &CodeMetrics, indent: &str) -> String {
+    let mut xml = format!("{}<metrics>\n", indent);
+    xml.push_str(&format!("{}  <lines>{}</lines>\n", indent, metrics.lines));
+    xml.push_str(&format!(
+        "{}  <code_lines>{}</code_lines>\n",
+        indent, metrics.code_lines
+    ));
+    xml.push_str(&format!(
+        "{}  <cyclomatic_complexity>{}</cyclomatic_complexity>\n",
+        indent, metrics.cyclomatic_complexity
+    ));
+    xml.push_str(&format!(
+        "{}  <max_nesting>{}</max_nesting>\n",
+        indent, metrics.max_nesting
+    ));
+    if let Some(parameters) = metrics.parameters {
+        xml.push_str(&format!(
+            "{}  <parameters>{}</parameters>\n",
+            indent, parameters
+        ));
+    }
+    xml.push_str(&format!("{}</metrics>", indent));
+    xml
+}
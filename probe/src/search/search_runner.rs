@@ -1,24 +1,39 @@
-use crate::search::file_list_cache;
+use crate::search::file_list_cache::{self, FileList};
 use anyhow::Result;
+use regex::Regex;
 use std::collections::{HashMap, HashSet};
 use std::path::{Path, PathBuf};
+use std::sync::Arc;
 use std::time::{Duration, Instant};
 // No need for term_exceptions import
 
+use crate::language::tree_cache::get_tree_cache_stats;
 use crate::models::{LimitedSearchResults, SearchResult};
 use crate::search::{
+    batch::BatchContext,
     cache,
+    elastic_query::Expr,
     // file_list_cache, // Add the new file_list_cache module (unused)
     file_processing::{process_file_with_results, FileProcessingParams},
-    query::{create_query_plan, create_structured_patterns, QueryPlan},
-    result_ranking::rank_search_results,
+    fuzzy::Vocabulary,
+    match_ranges::add_match_ranges,
+    project_vocabulary,
+    query::{
+        create_pattern_query_plan, create_query_plan_for_project, create_structured_patterns,
+        QueryPlan, SearchPattern,
+    },
+    result_ranking::{explain_search_results, rank_by_match_count, rank_search_results},
     search_limiter::apply_limits,
-    search_options::SearchOptions,
+    search_options::{QueryMode, SearchOptions},
+    search_tokens::count_tokens,
+    source_files::{read_source_file, FileLimits, SkippedFile},
     timeout,
 };
 use probe::path_resolver::resolve_path;
+use probe::workspace::Workspace;
 
 /// Struct to hold timing information for different stages of the search process
+#[derive(Default)]
 pub struct SearchTimings {
     pub query_preprocessing: Option<Duration>,
     pub pattern_generation: Option<Duration>,
@@ -192,6 +207,14 @@ pub fn print_timings(timings: &SearchTimings) {
         println!("Total search time:     {}", format_duration(duration));
     }
 
+    let tree_cache_stats = get_tree_cache_stats();
+    println!(
+        "Tree cache:            {} trees, {:.0}% hits, {} line maps from disk",
+        tree_cache_stats.entries,
+        tree_cache_stats.hit_rate() * 100.0,
+        tree_cache_stats.persisted_hits
+    );
+
     println!("===================================\n");
 }
 
@@ -202,19 +225,108 @@ pub fn print_timings(timings: &SearchTimings) {
 /// For simplicity, we won't fully replace the existing logic. Instead, we'll demonstrate
 /// how you'd do it if you wanted to leverage the new approach.
 pub fn perform_probe(options: &SearchOptions) -> Result<LimitedSearchResults> {
+    let root = SearchRoot {
+        path: options.path.to_path_buf(),
+        custom_ignores: options.custom_ignores.to_vec(),
+        language: options.language.map(str::to_string),
+    };
+    perform_probe_in_roots(options, std::slice::from_ref(&root))
+}
+
+/// Search all roots of a workspace in a single ranking pass
+///
+/// `options.path` is ignored; every workspace root is searched with the workspace and
+/// root ignore patterns added to `options.custom_ignores`. A language given in
+/// `options.language` applies to all roots, otherwise each root is limited to its own
+/// languages. Results are labelled with their root name and their paths are made
+/// relative to that root.
+pub fn perform_workspace_probe(
+    workspace: &Workspace,
+    options: &SearchOptions,
+) -> Result<LimitedSearchResults> {
+    let roots = workspace_roots(workspace, options);
+    let mut limited = perform_probe_in_roots(options, &roots)?;
+    for result in limited
+        .results
+        .iter_mut()
+        .chain(limited.skipped_results.iter_mut())
+    {
+        label_workspace_result(workspace, result);
+    }
+    Ok(limited)
+}
+
+/// The roots a workspace search covers, see `perform_workspace_probe`
+pub(crate) fn workspace_roots(workspace: &Workspace, options: &SearchOptions) -> Vec<SearchRoot> {
+    let mut roots = Vec::new();
+    for workspace_root in &workspace.roots {
+        let mut custom_ignores = options.custom_ignores.to_vec();
+        custom_ignores.extend(workspace.ignores_for(workspace_root));
+
+        let languages: Vec<Option<String>> = match options.language {
+            Some(language) => vec![Some(language.to_string())],
+            None if workspace_root.languages.is_empty() => vec![None],
+            None => workspace_root.languages.iter().cloned().map(Some).collect(),
+        };
+        for language in languages {
+            roots.push(SearchRoot {
+                path: workspace_root.path.clone(),
+                custom_ignores: custom_ignores.clone(),
+                language,
+            });
+        }
+    }
+
+    roots
+}
+
+/// Label a result with its workspace root and make its path relative to that root
+pub fn label_workspace_result(workspace: &Workspace, result: &mut SearchResult) {
+    if let Some((root, relative)) = workspace.label_path(Path::new(&result.file)) {
+        result.workspace_root = Some(root.name.clone());
+        result.file = relative.to_string_lossy().to_string();
+    }
+}
+
+/// A directory to search, with the ignore patterns and language that apply to it
+pub struct SearchRoot {
+    pub path: PathBuf,
+    pub custom_ignores: Vec<String>,
+    pub language: Option<String>,
+}
+
+/// Run a search over one or more roots, ranking and limiting all their results together
+pub fn perform_probe_in_roots(
+    options: &SearchOptions,
+    roots: &[SearchRoot],
+) -> Result<LimitedSearchResults> {
+    run_probe(options, roots, None, None)
+}
+
+/// Run a search, optionally with a query prepared and its files already searched
+///
+/// Batches prepare all their queries and search the files for them in one pass (see
+/// `batch`), then run the rest of every search here, sharing the files they read and the
+/// blocks they tokenized through their `BatchContext`.
+pub(crate) fn run_probe(
+    options: &SearchOptions,
+    roots: &[SearchRoot],
+    prepared: Option<(PreparedQuery, FileTermMap)>,
+    batch: Option<&BatchContext>,
+) -> Result<LimitedSearchResults> {
     // Start timing the entire search process
     let total_start = Instant::now();
 
     let SearchOptions {
-        path,
+        path: _, // Each root carries its own path, ignores and language
         queries,
         files_only,
-        custom_ignores,
+        custom_ignores: _,
         exclude_filenames,
         reranker,
         frequency_search: _,
         exact,
-        language,
+        language: _,
         max_results,
         max_bytes,
         max_tokens,
@@ -224,7 +336,17 @@ pub fn perform_probe(options: &SearchOptions) -> Result<LimitedSearchResults> {
         dry_run: _, // We don't need this in perform_probe, but need to include it in the pattern
         session,
         timeout,
+        page,
+        explain,
+        fuzzy: _, // Fuzzy variants are added to the plan in prepare_query
+        query_mode,
+        file_limits,
+        parent_context,
+        structured_output,
     } = options;
+    // Regex and literal patterns are used as given, without terms to rank or correct
+    let pattern_mode = *query_mode != QueryMode::Terms;
+
     // Start the timeout thread
     let timeout_handle = timeout::start_timeout_thread(*timeout);
 
@@ -301,6 +423,11 @@ pub fn perform_probe(options: &SearchOptions) -> Result<LimitedSearchResults> {
         }
     };
 
+    // Pages are recorded in the session, so paging without one has no cursor to follow
+    if page.is_some() && effective_session.is_none() {
+        anyhow::bail!("--page requires a session (use --session <ID> or --session new)");
+    }
+
     let mut timings = SearchTimings {
         query_preprocessing: None,
         pattern_generation: None,
@@ -341,67 +468,28 @@ pub fn perform_probe(options: &SearchOptions) -> Result<LimitedSearchResults> {
         total_search_time: None,
     };
 
-    // Combine multiple queries with AND or just parse single query
-    let qp_start = Instant::now();
-    if debug_mode {
-        println!("DEBUG: Starting query preprocessing...");
-    }
-
-    let parse_res = if queries.len() > 1 {
-        // Join multiple queries with AND
-        let combined_query = queries.join(" AND ");
-        create_query_plan(&combined_query, *exact)
-    } else {
-        create_query_plan(&queries[0], *exact)
+    let (prepared, precomputed_matches) = match prepared {
+        Some((prepared, file_term_map)) => (prepared, Some(file_term_map)),
+        None => match prepare_query(options, roots, &mut timings)? {
+            Some(prepared) => (prepared, None),
+            // If the query fails to parse, return empty results
+            None => {
+                return Ok(LimitedSearchResults {
+                    results: Vec::new(),
+                    skipped_results: Vec::new(),
+                    skipped_files: Vec::new(),
+                    limits_applied: None,
+                    cached_blocks_skipped: None,
+                    suggestions: Vec::new(),
+                })
+            }
+        },
     };
-
-    let qp_duration = qp_start.elapsed();
-    timings.query_preprocessing = Some(qp_duration);
-
-    if debug_mode {
-        println!(
-            "DEBUG: Query preprocessing completed in {}",
-            format_duration(qp_duration)
-        );
-    }
-
-    // If the query fails to parse, return empty results
-    if parse_res.is_err() {
-        println!("Failed to parse query as AST expression");
-        return Ok(LimitedSearchResults {
-            results: Vec::new(),
-            skipped_files: Vec::new(),
-            limits_applied: None,
-            cached_blocks_skipped: None,
-        });
-    }
-
-    // All queries go through the AST path
-    let plan = parse_res.unwrap();
-
-    // Pattern generation timing
-    let pg_start = Instant::now();
-    if debug_mode {
-        println!("DEBUG: Starting pattern generation...");
-        println!("DEBUG: Using combined pattern approach for more efficient searching");
-    }
-
-    // Use combined pattern approach for more efficient searching
-    let structured_patterns = create_structured_patterns(&plan);
-
-    let pg_duration = pg_start.elapsed();
-    timings.pattern_generation = Some(pg_duration);
-
-    if debug_mode {
-        println!(
-            "DEBUG: Pattern generation completed in {}",
-            format_duration(pg_duration)
-        );
-        println!("DEBUG: Generated {} patterns", structured_patterns.len());
-        if structured_patterns.len() == 1 {
-            println!("DEBUG: Successfully created a single combined pattern for all terms");
-        }
-    }
+    let PreparedQuery {
+        plan,
+        patterns: structured_patterns,
+        vocabulary,
+    } = prepared;
 
     // File searching timing
     let fs_start = Instant::now();
@@ -421,17 +509,31 @@ pub fn perform_probe(options: &SearchOptions) -> Result<LimitedSearchResults> {
       likely culprit.
     */
 
-    // Normalize language parameter to handle aliases
-    let lang_param = language.as_ref().map(|lang| normalize_language_alias(lang));
-
-    let mut file_term_map = search_with_structured_patterns(
-        path,
-        &plan,
-        &structured_patterns,
-        custom_ignores,
-        *allow_tests,
-        lang_param,
-    )?;
+    // A batch has already searched the files for its queries
+    let already_searched = precomputed_matches.is_some();
+    let mut file_term_map: FileTermMap = precomputed_matches.unwrap_or_default();
+    for root in roots.iter().filter(|_| !already_searched) {
+        // Normalize language parameter to handle aliases
+        let lang_param = root.language.as_deref().map(normalize_language_alias);
+
+        let root_term_map = search_with_structured_patterns(
+            &root.path,
+            &plan,
+            &structured_patterns,
+            &root.custom_ignores,
+            *allow_tests,
+            lang_param,
+            file_limits,
+        )?;
+
+        // Roots may overlap (nested roots, or the same root with several languages)
+        for (file, term_map) in root_term_map {
+            let existing = file_term_map.entry(file).or_default();
+            for (term, lines) in term_map {
+                existing.entry(term).or_default().extend(lines);
+            }
+        }
+    }
 
     let fs_duration = fs_start.elapsed();
     timings.file_searching = Some(fs_duration);
@@ -460,47 +562,56 @@ pub fn perform_probe(options: &SearchOptions) -> Result<LimitedSearchResults> {
 
     // Add filename matches if enabled
     let fm_start = Instant::now();
-    if include_filenames && !exact {
+    if include_filenames && !exact && !pattern_mode {
         if debug_mode {
             println!("DEBUG: Starting filename matching...");
         }
         // Find all files that match our patterns by filename, along with the terms that matched
-        // Resolve the path if it's a special format (e.g., "go:github.com/user/repo")
-        let resolved_path = if let Some(path_str) = path.to_str() {
-            match resolve_path(path_str) {
-                Ok(resolved_path) => {
-                    if debug_mode {
-                        println!(
-                            "DEBUG: Resolved path '{}' to '{}'",
-                            path_str,
-                            resolved_path.display()
-                        );
+        let mut filename_matches: HashMap<PathBuf, HashSet<usize>> = HashMap::new();
+        for root in roots {
+            let path = root.path.as_path();
+            // Resolve the path if it's a special format (e.g., "go:github.com/user/repo")
+            let resolved_path = if let Some(path_str) = path.to_str() {
+                match resolve_path(path_str) {
+                    Ok(resolved_path) => {
+                        if debug_mode {
+                            println!(
+                                "DEBUG: Resolved path '{}' to '{}'",
+                                path_str,
+                                resolved_path.display()
+                            );
+                        }
+                        resolved_path
                     }
-                    resolved_path
-                }
-                Err(err) => {
-                    if debug_mode {
-                        println!("DEBUG: Failed to resolve path '{}': {}", path_str, err);
+                    Err(err) => {
+                        if debug_mode {
+                            println!("DEBUG: Failed to resolve path '{}': {}", path_str, err);
+                        }
+                        // Fall back to the original path
+                        path.to_path_buf()
                     }
-                    // Fall back to the original path
-                    path.to_path_buf()
                 }
-            }
-        } else {
-            // If we can't convert the path to a string, use it as is
-            path.to_path_buf()
-        };
+            } else {
+                // If we can't convert the path to a string, use it as is
+                path.to_path_buf()
+            };
 
-        let filename_matches: HashMap<PathBuf, HashSet<usize>> =
-            file_list_cache::find_matching_filenames(
+            let lang_param = root.language.as_deref().map(normalize_language_alias);
+            let root_matches = file_list_cache::find_matching_filenames(
                 &resolved_path,
                 queries,
                 &all_files,
-                custom_ignores,
+                &root.custom_ignores,
                 *allow_tests,
                 &plan.term_indices,
+                plan.project(),
                 lang_param,
+                file_limits,
             )?;
+            for (file, terms) in root_matches {
+                filename_matches.entry(file).or_default().extend(terms);
+            }
+        }
 
         if debug_mode {
             println!(
@@ -511,10 +622,8 @@ pub fn perform_probe(options: &SearchOptions) -> Result<LimitedSearchResults> {
 
         // Process files that matched by filename
         for (pathbuf, matched_terms) in &filename_matches {
-            // Define a reasonable maximum file size (e.g., 10MB)
-            const MAX_FILE_SIZE: u64 = 1024 * 1024;
-
-            // Check file metadata and resolve symlinks before reading
+            // Resolve symlinks before reading; the file list already left out the files
+            // over the limits
             let resolved_path = match std::fs::canonicalize(pathbuf.as_path()) {
                 Ok(path) => path,
                 Err(e) => {
@@ -525,44 +634,12 @@ pub fn perform_probe(options: &SearchOptions) -> Result<LimitedSearchResults> {
                 }
             };
 
-            // Get file metadata to check size and file type
-            let metadata = match std::fs::metadata(&resolved_path) {
-                Ok(meta) => meta,
-                Err(e) => {
-                    if debug_mode {
-                        println!(
-                            "DEBUG: Error getting metadata for {:?}: {:?}",
-                            resolved_path, e
-                        );
-                    }
-                    continue;
-                }
-            };
-
-            // Check if the file is too large
-            if metadata.len() > MAX_FILE_SIZE {
-                if debug_mode {
-                    println!(
-                        "DEBUG: Skipping file {:?} - file too large ({} bytes > {} bytes limit)",
-                        resolved_path,
-                        metadata.len(),
-                        MAX_FILE_SIZE
-                    );
-                }
-                continue;
-            }
-
             // Read the file content to get the total number of lines
-            let file_content = match std::fs::read_to_string(&resolved_path) {
+            let file_content = match read_source_file(&resolved_path) {
                 Ok(content) => content,
                 Err(e) => {
                     if debug_mode {
-                        println!(
-                            "DEBUG: Error reading file {:?}: {:?} (size: {} bytes)",
-                            resolved_path,
-                            e,
-                            metadata.len()
-                        );
+                        println!("DEBUG: Error reading file {:?}: {:?}", resolved_path, e);
                     }
                     continue;
                 }
@@ -700,26 +777,7 @@ pub fn perform_probe(options: &SearchOptions) -> Result<LimitedSearchResults> {
                 file: f.to_string_lossy().to_string(),
                 lines: (1, 1),
                 node_type: "file".to_string(),
-                code: String::new(),
-                matched_by_filename: None,
-                rank: None,
-                score: None,
-                tfidf_score: None,
-                bm25_score: None,
-                tfidf_rank: None,
-                bm25_rank: None,
-                new_score: None,
-                hybrid2_rank: None,
-                combined_score_rank: None,
-                file_unique_terms: None,
-                file_total_matches: None,
-                file_match_rank: None,
-                block_unique_terms: None,
-                block_total_matches: None,
-                parent_file_id: None,
-                block_id: None,
-                matched_keywords: None,
-                tokenized_content: None,
+                ..Default::default()
             });
         }
         let mut limited = apply_limits(res, *max_results, *max_bytes, *max_tokens);
@@ -738,8 +796,9 @@ pub fn perform_probe(options: &SearchOptions) -> Result<LimitedSearchResults> {
 
     // Apply early caching if session is provided - AFTER getting ripgrep results but BEFORE processing
     let ec_start = Instant::now();
+    // In page mode every candidate is kept, since served pages are replayed from the full results
     let mut early_skipped_count = 0;
-    if let Some(session_id) = effective_session {
+    if let (Some(session_id), None) = (effective_session, page) {
         // Get the raw query string for caching
         let raw_query = if queries.len() > 1 {
             queries.join(" AND ")
@@ -877,6 +936,7 @@ pub fn perform_probe(options: &SearchOptions) -> Result<LimitedSearchResults> {
                 preprocessed_queries: None,
                 no_merge: *no_merge,
                 query_plan: &plan,
+                batch,
             };
 
             if debug_mode {
@@ -1161,9 +1221,24 @@ pub fn perform_probe(options: &SearchOptions) -> Result<LimitedSearchResults> {
         }
     }
 
-    if !*exact {
+    let highlight_patterns = match_patterns(&plan, &structured_patterns);
+    if pattern_mode {
+        add_match_ranges(&mut final_results, &highlight_patterns);
+        rank_by_match_count(&mut final_results);
+    } else if !*exact {
+        let fuzzy_variants = plan.fuzzy_variants();
+        let fuzzy_variants = (!fuzzy_variants.is_empty()).then_some(&fuzzy_variants);
+        if *explain {
+            explain_search_results(&mut final_results, queries, fuzzy_variants, plan.project());
+        }
         // Only perform ranking if exact flag is not set
-        rank_search_results(&mut final_results, queries, reranker);
+        rank_search_results(
+            &mut final_results,
+            queries,
+            reranker,
+            fuzzy_variants,
+            plan.project(),
+        );
     }
 
     let rr_duration = rr_start.elapsed();
@@ -1183,9 +1258,7 @@ pub fn perform_probe(options: &SearchOptions) -> Result<LimitedSearchResults> {
         }
     }
 
-    // We'll move the caching step AFTER limiting results
     let mut skipped_count = early_skipped_count;
-    let filtered_results = final_results;
 
     // Apply limits
     let la_start = Instant::now();
@@ -1193,13 +1266,63 @@ pub fn perform_probe(options: &SearchOptions) -> Result<LimitedSearchResults> {
         println!("DEBUG: Starting limit application...");
     }
 
-    // First apply limits to the results
-    let mut limited = apply_limits(filtered_results, *max_results, *max_bytes, *max_tokens);
-
-    // Then apply caching AFTER limiting results
     let fc_start = Instant::now();
 
-    if let Some(session_id) = effective_session {
+    // Remove blocks the session has already seen unchanged before limiting, so that the
+    // limits apply to new blocks; blocks that changed since they were seen are marked
+    let filtered_results = match (effective_session, page) {
+        (Some(session_id), None) => {
+            let raw_query = if queries.len() > 1 {
+                queries.join(" AND ")
+            } else {
+                queries[0].clone()
+            };
+            match cache::filter_results_with_cache(&final_results, session_id, &raw_query) {
+                Ok((filtered, cached_skipped)) => {
+                    if debug_mode {
+                        println!(
+                            "DEBUG: Final caching found {} cached blocks",
+                            cached_skipped
+                        );
+                        println!(
+                            "DEBUG: Total skipped (early + final): {}",
+                            early_skipped_count + cached_skipped
+                        );
+                    }
+                    skipped_count += cached_skipped;
+                    filtered
+                }
+                Err(e) => {
+                    // Log the error but continue without caching
+                    eprintln!("Error checking cache: {}", e);
+                    final_results
+                }
+            }
+        }
+        _ => final_results,
+    };
+
+    // In page mode the session decides which blocks make up the page
+    let mut page_selection = None;
+    let mut limited = match (page, effective_session) {
+        (Some(page), Some(session_id)) => {
+            let raw_query = if queries.len() > 1 {
+                queries.join(" AND ")
+            } else {
+                queries[0].clone()
+            };
+            let page_size = max_results.unwrap_or(cache::DEFAULT_PAGE_SIZE);
+            let (limited, selection) =
+                cache::select_page(filtered_results, session_id, &raw_query, *page, |unseen| {
+                    apply_limits(unseen, Some(page_size), *max_bytes, *max_tokens)
+                })?;
+            page_selection = Some(selection);
+            limited
+        }
+        _ => apply_limits(filtered_results, *max_results, *max_bytes, *max_tokens),
+    };
+
+    if let (Some(session_id), None) = (effective_session, page) {
         // Get the raw query string for caching
         let raw_query = if queries.len() > 1 {
             queries.join(" AND ")
@@ -1222,28 +1345,6 @@ pub fn perform_probe(options: &SearchOptions) -> Result<LimitedSearchResults> {
             }
         }
 
-        // Filter results using the cache - but only to count skipped blocks, not to filter
-        match cache::filter_results_with_cache(&limited.results, session_id, &raw_query) {
-            Ok((_, cached_skipped)) => {
-                if debug_mode {
-                    println!(
-                        "DEBUG: Final caching found {} cached blocks",
-                        cached_skipped
-                    );
-                    println!(
-                        "DEBUG: Total skipped (early + final): {}",
-                        early_skipped_count + cached_skipped
-                    );
-                }
-
-                skipped_count += cached_skipped;
-            }
-            Err(e) => {
-                // Log the error but continue without caching
-                eprintln!("Error checking cache: {}", e);
-            }
-        }
-
         // Update the cache with the limited results
         if let Err(e) = cache::add_results_to_cache(&limited.results, session_id, &raw_query) {
             eprintln!("Error adding results to cache: {}", e);
@@ -1293,8 +1394,20 @@ pub fn perform_probe(options: &SearchOptions) -> Result<LimitedSearchResults> {
     }
 
     let final_results = if !limited.results.is_empty() && !*no_merge {
-        use crate::search::block_merging::merge_ranked_blocks;
-        let merged = merge_ranked_blocks(limited.results.clone(), *merge_threshold);
+        use crate::search::block_merging::{merge_ranked_blocks_with_options, MergeOptions};
+        // Expanding blocks to their parents may use what the results leave of --max-tokens
+        let token_budget = max_tokens.map(|max| {
+            let used: usize = limited.results.iter().map(|r| count_tokens(&r.code)).sum();
+            max.saturating_sub(used)
+        });
+        let merged = merge_ranked_blocks_with_options(
+            limited.results.clone(),
+            MergeOptions {
+                threshold: *merge_threshold,
+                token_budget,
+                parent_context: *parent_context,
+            },
+        );
 
         let bm_duration = bm_start.elapsed();
         timings.block_merging = Some(bm_duration);
@@ -1307,37 +1420,14 @@ pub fn perform_probe(options: &SearchOptions) -> Result<LimitedSearchResults> {
             );
         }
 
-        // Create the merged results
-        let merged_results = LimitedSearchResults {
-            results: merged.clone(),
+        LimitedSearchResults {
+            results: merged,
+            skipped_results: limited.skipped_results,
             skipped_files: limited.skipped_files,
             limits_applied: limited.limits_applied,
             cached_blocks_skipped: limited.cached_blocks_skipped,
-        };
-
-        // Update the cache with the merged results (after merging)
-        if let Some(session_id) = effective_session {
-            // Get the raw query string for caching
-            let raw_query = if queries.len() > 1 {
-                queries.join(" AND ")
-            } else {
-                queries[0].clone()
-            };
-
-            if let Err(e) = cache::add_results_to_cache(&merged, session_id, &raw_query) {
-                eprintln!("Error adding merged results to cache: {}", e);
-            }
-
-            if debug_mode {
-                println!("DEBUG: Added merged results to cache after merging");
-                // Print cache contents after adding merged results
-                if let Err(e) = cache::debug_print_cache(session_id, &raw_query) {
-                    eprintln!("Error printing updated cache: {}", e);
-                }
-            }
+            suggestions: limited.suggestions,
         }
-
-        merged_results
     } else {
         let bm_duration = bm_start.elapsed();
         timings.block_merging = Some(bm_duration);
@@ -1352,15 +1442,71 @@ pub fn perform_probe(options: &SearchOptions) -> Result<LimitedSearchResults> {
         limited
     };
 
-    // Print the session ID to the console if it was generated or provided
+    // Merging changes the code of results, so ranges are found in the final blocks
+    let mut final_results = final_results;
+    add_match_ranges(&mut final_results.results, &highlight_patterns);
+
+    // Suggest corrections when nothing was found, unless the session already served it all
+    if final_results.results.is_empty()
+        && final_results.cached_blocks_skipped.unwrap_or(0) == 0
+        && !*exact
+        && !pattern_mode
+    {
+        let vocabulary = vocabulary.unwrap_or_else(|| {
+            Vocabulary::from_files(
+                &candidate_files(roots, *allow_tests, file_limits),
+                plan.project(),
+            )
+        });
+        final_results.suggestions = vocabulary.suggestions(
+            plan.term_indices
+                .keys()
+                .filter(|term| !plan.excluded_terms.contains(*term)),
+        );
+    }
+
+    // Report the files the walker left out, from the cached file lists
+    final_results.skipped_files = skipped_files(roots, *allow_tests, file_limits);
+
+    // Print the session ID to the console if it was generated or provided; with
+    // machine-readable output it goes to stderr, so that stdout only carries the results
+    let print_status = |line: String| {
+        if *structured_output {
+            eprintln!("{}", line);
+        } else {
+            println!("{}", line);
+        }
+    };
     if let Some(session_id) = effective_session {
         if session_was_generated {
-            println!(
+            print_status(format!(
                 "Session ID: {} (generated - ALWAYS USE IT in future sessions for caching)",
                 session_id
-            );
+            ));
         } else {
-            println!("Session ID: {}", session_id);
+            print_status(format!("Session ID: {}", session_id));
+        }
+    }
+
+    if let Some(selection) = page_selection {
+        let served = if selection.replayed {
+            "served before"
+        } else {
+            "new"
+        };
+        if selection.remaining > 0 {
+            print_status(format!(
+                "Page {} ({}); {} more unseen blocks, use --page {} for the next page",
+                selection.page,
+                served,
+                selection.remaining,
+                selection.page + 1
+            ));
+        } else {
+            print_status(format!(
+                "Page {} ({}); no more unseen blocks",
+                selection.page, served
+            ));
         }
     }
 
@@ -1376,6 +1522,243 @@ pub fn perform_probe(options: &SearchOptions) -> Result<LimitedSearchResults> {
     Ok(final_results)
 }
 
+/// A query's plan and the patterns files are searched with, ready to run
+pub(crate) struct PreparedQuery {
+    pub plan: QueryPlan,
+    pub patterns: Vec<SearchPattern>,
+    /// The vocabulary of the candidate files, when fuzzy matching needed it
+    pub vocabulary: Option<Vocabulary>,
+}
+
+/// The lines each query term matched, per file
+pub(crate) type FileTermMap = HashMap<PathBuf, HashMap<usize, HashSet<usize>>>;
+
+/// Parse the queries of a search into a plan and generate the patterns to search with
+///
+/// Returns None when the query can't be parsed, which makes a search without results.
+pub(crate) fn prepare_query(
+    options: &SearchOptions,
+    roots: &[SearchRoot],
+    timings: &mut SearchTimings,
+) -> Result<Option<PreparedQuery>> {
+    let SearchOptions {
+        queries,
+        exact,
+        allow_tests,
+        fuzzy,
+        query_mode,
+        file_limits,
+        ..
+    } = options;
+    let pattern_mode = *query_mode != QueryMode::Terms;
+    let debug_mode = std::env::var("DEBUG").unwrap_or_default() == "1";
+
+    // Combine multiple queries with AND or just parse single query
+    let qp_start = Instant::now();
+    if debug_mode {
+        println!("DEBUG: Starting query preprocessing...");
+    }
+
+    // The project vocabularies of all roots are read before the query is tokenized, so
+    // that edits to them apply to this search
+    let root_paths: Vec<&Path> = roots.iter().map(|root| root.path.as_path()).collect();
+    let project_vocabulary = project_vocabulary::load_for_paths(&root_paths)?;
+
+    let mut pattern_mode_patterns = None;
+    let parse_res = if pattern_mode {
+        let (plan, patterns) =
+            create_pattern_query_plan(queries, *query_mode == QueryMode::Literal)
+                .map_err(|e| anyhow::anyhow!("Invalid regex: {}", e))?;
+        pattern_mode_patterns = Some(patterns);
+        Ok(plan)
+    } else if queries.len() > 1 {
+        // Join multiple queries with AND
+        let combined_query = queries.join(" AND ");
+        create_query_plan_for_project(&combined_query, *exact, project_vocabulary)
+    } else {
+        create_query_plan_for_project(&queries[0], *exact, project_vocabulary)
+    };
+
+    let qp_duration = qp_start.elapsed();
+    timings.query_preprocessing = Some(qp_duration);
+
+    if debug_mode {
+        println!(
+            "DEBUG: Query preprocessing completed in {}",
+            format_duration(qp_duration)
+        );
+    }
+
+    // If the query fails to parse, the search has no results
+    if parse_res.is_err() {
+        println!("Failed to parse query as AST expression");
+        return Ok(None);
+    }
+
+    // All queries go through the AST path
+    let mut plan = parse_res.unwrap();
+
+    // The vocabulary of the candidate files, for fuzzy matching and "did you mean" suggestions
+    let mut vocabulary: Option<Vocabulary> = None;
+    if *fuzzy && !*exact && !pattern_mode {
+        let fz_start = Instant::now();
+        let vocab = Vocabulary::from_files(
+            &candidate_files(roots, *allow_tests, file_limits),
+            plan.project(),
+        );
+        add_fuzzy_terms(&mut plan, &vocab);
+
+        if debug_mode {
+            println!(
+                "DEBUG: Fuzzy matching prepared in {} - Variants: {:?}",
+                format_duration(fz_start.elapsed()),
+                plan.fuzzy_terms
+            );
+        }
+        vocabulary = Some(vocab);
+    }
+
+    // Pattern generation timing
+    let pg_start = Instant::now();
+    if debug_mode {
+        println!("DEBUG: Starting pattern generation...");
+        println!("DEBUG: Using combined pattern approach for more efficient searching");
+    }
+
+    // Use combined pattern approach for more efficient searching
+    let structured_patterns =
+        pattern_mode_patterns.unwrap_or_else(|| create_structured_patterns(&plan));
+
+    let pg_duration = pg_start.elapsed();
+    timings.pattern_generation = Some(pg_duration);
+
+    if debug_mode {
+        println!(
+            "DEBUG: Pattern generation completed in {}",
+            format_duration(pg_duration)
+        );
+        println!("DEBUG: Generated {} patterns", structured_patterns.len());
+        if structured_patterns.len() == 1 {
+            println!("DEBUG: Successfully created a single combined pattern for all terms");
+        }
+    }
+
+    Ok(Some(PreparedQuery {
+        plan,
+        patterns: structured_patterns,
+        vocabulary,
+    }))
+}
+
+/// Compile the patterns files were searched with, for finding match ranges, leaving out
+/// the patterns of excluded terms
+fn match_patterns(plan: &QueryPlan, patterns: &[(String, HashSet<usize>)]) -> Vec<Regex> {
+    let excluded: HashSet<usize> = plan
+        .excluded_terms
+        .iter()
+        .filter_map(|term| plan.term_indices.get(term).copied())
+        .collect();
+    patterns
+        .iter()
+        .filter(|(_, indices)| !indices.iter().all(|idx| excluded.contains(idx)))
+        .filter_map(|(pattern, _)| Regex::new(&format!("(?i){}", pattern)).ok())
+        .collect()
+}
+
+/// The file lists of the given roots
+fn root_file_lists(
+    roots: &[SearchRoot],
+    allow_tests: bool,
+    limits: &FileLimits,
+) -> Vec<Arc<FileList>> {
+    roots
+        .iter()
+        .filter_map(|root| {
+            let root_path = root
+                .path
+                .to_str()
+                .and_then(|path| resolve_path(path).ok())
+                .unwrap_or_else(|| root.path.clone());
+            let language = root.language.as_deref().map(normalize_language_alias);
+            crate::search::file_list_cache::get_file_list_by_language(
+                &root_path,
+                allow_tests,
+                &root.custom_ignores,
+                language,
+                limits,
+            )
+            .ok()
+        })
+        .collect()
+}
+
+/// The files searched under the given roots
+pub(crate) fn candidate_files(
+    roots: &[SearchRoot],
+    allow_tests: bool,
+    limits: &FileLimits,
+) -> Vec<PathBuf> {
+    let mut files: Vec<PathBuf> = root_file_lists(roots, allow_tests, limits)
+        .iter()
+        .flat_map(|file_list| file_list.files.iter().cloned())
+        .collect();
+    files.sort();
+    files.dedup();
+    files
+}
+
+/// The files under the given roots that the limits left out
+pub(crate) fn skipped_files(
+    roots: &[SearchRoot],
+    allow_tests: bool,
+    limits: &FileLimits,
+) -> Vec<SkippedFile> {
+    let mut skipped: Vec<SkippedFile> = root_file_lists(roots, allow_tests, limits)
+        .iter()
+        .flat_map(|file_list| file_list.skipped.iter().cloned())
+        .collect();
+    skipped.sort_by(|a, b| a.file.cmp(&b.file));
+    skipped.dedup_by(|a, b| a.file == b.file);
+    skipped
+}
+
+/// Add the fuzzy variants of the plan's terms that don't occur in the vocabulary
+///
+/// Excluded and exact terms are never matched fuzzily.
+fn add_fuzzy_terms(plan: &mut QueryPlan, vocabulary: &Vocabulary) {
+    let mut exact_terms = HashSet::new();
+    collect_exact_terms(&plan.ast, &mut exact_terms);
+
+    let mut terms: Vec<(&String, &usize)> = plan.term_indices.iter().collect();
+    terms.sort();
+    for (term, &idx) in terms {
+        if plan.excluded_terms.contains(term) || exact_terms.contains(term) {
+            continue;
+        }
+        for variant in vocabulary.fuzzy_variants(term) {
+            if !plan.term_indices.contains_key(&variant) {
+                plan.fuzzy_terms.entry(variant).or_insert(idx);
+            }
+        }
+    }
+}
+
+fn collect_exact_terms(expr: &Expr, exact_terms: &mut HashSet<String>) {
+    match expr {
+        Expr::Term {
+            keywords, exact, ..
+        } => {
+            if *exact {
+                exact_terms.extend(keywords.iter().cloned());
+            }
+        }
+        Expr::And(left, right) | Expr::Or(left, right) => {
+            collect_exact_terms(left, exact_terms);
+            collect_exact_terms(right, exact_terms);
+        }
+    }
+}
+
 /// Helper function to search files using structured patterns from a QueryPlan.
 /// This function uses a RegexSet approach for deterministic pattern matching
 /// and collects matches by term indices. It uses the file_list_cache to get a filtered
@@ -1387,6 +1770,8 @@ pub fn perform_probe(options: &SearchOptions) -> Result<LimitedSearchResults> {
 /// * `patterns` - The generated regex patterns with their term indices
 /// * `custom_ignores` - Custom ignore patterns
 /// * `allow_tests` - Whether to include test files
+/// * `language` - Language whose files are searched, if not all
+/// * `limits` - Which files are left out as too large, binary, minified or generated
 pub fn search_with_structured_patterns(
     root_path_str: &Path,
     _plan: &QueryPlan,
@@ -1394,6 +1779,7 @@ pub fn search_with_structured_patterns(
     custom_ignores: &[String],
     allow_tests: bool,
     language: Option<&str>,
+    limits: &FileLimits,
 ) -> Result<HashMap<PathBuf, HashMap<usize, HashSet<usize>>>> {
     // Resolve the path if it's a special format (e.g., "go:github.com/user/repo")
     let root_path = if let Some(path_str) = root_path_str.to_str() {
@@ -1422,7 +1808,7 @@ pub fn search_with_structured_patterns(
     };
     use rayon::prelude::*;
     use regex::RegexSet;
-    use std::sync::{Arc, Mutex};
+    use std::sync::Mutex;
 
     let debug_mode = std::env::var("DEBUG").unwrap_or_default() == "1";
     let search_start = Instant::now();
@@ -1459,6 +1845,7 @@ pub fn search_with_structured_patterns(
         allow_tests,
         custom_ignores,
         language,
+        limits,
     )?;
 
     if debug_mode {
@@ -1545,13 +1932,28 @@ fn search_file_with_regex_set(
     individual_regexes: &[regex::Regex],
     pattern_to_terms: &[HashSet<usize>],
 ) -> Result<HashMap<usize, HashSet<usize>>> {
-    let mut term_map = HashMap::new();
-    let debug_mode = std::env::var("DEBUG").unwrap_or_default() == "1";
+    let content = read_searchable_file(file_path)?;
+
+    let mut term_map: HashMap<usize, HashSet<usize>> = HashMap::new();
+    for (pattern_idx, lines) in
+        pattern_lines_in_content(file_path, &content, regex_set, individual_regexes)
+    {
+        // Add matches for all terms associated with this pattern
+        for &term_idx in &pattern_to_terms[pattern_idx] {
+            term_map.entry(term_idx).or_default().extend(&lines);
+        }
+    }
 
-    // Define a reasonable maximum file size (e.g., 1MB)
-    const MAX_FILE_SIZE: u64 = 1024 * 1024;
+    Ok(term_map)
+}
+
+/// Read a file to search, decoding it with `read_source_file`
+///
+/// The size, binary and generated-code limits are applied when the file list is built.
+pub(crate) fn read_searchable_file(file_path: &Path) -> Result<String> {
+    let debug_mode = std::env::var("DEBUG").unwrap_or_default() == "1";
 
-    // Check file metadata and resolve symlinks before reading
+    // Resolve symlinks before reading
     let resolved_path = match std::fs::canonicalize(file_path) {
         Ok(path) => path,
         Err(e) => {
@@ -1562,52 +1964,27 @@ fn search_file_with_regex_set(
         }
     };
 
-    // Get file metadata to check size and file type
-    let metadata = match std::fs::metadata(&resolved_path) {
-        Ok(meta) => meta,
+    // Read the file content with proper error handling
+    match read_source_file(&resolved_path) {
+        Ok(content) => Ok(content),
         Err(e) => {
             if debug_mode {
-                println!(
-                    "DEBUG: Error getting metadata for {:?}: {:?}",
-                    resolved_path, e
-                );
+                println!("DEBUG: Error reading file {:?}: {:?}", resolved_path, e);
             }
-            return Err(anyhow::anyhow!("Failed to get file metadata: {}", e));
+            Err(anyhow::anyhow!("Failed to read file: {}", e))
         }
-    };
-
-    // Check if the file is too large
-    if metadata.len() > MAX_FILE_SIZE {
-        if debug_mode {
-            println!(
-                "DEBUG: Skipping file {:?} - file too large ({} bytes > {} bytes limit)",
-                resolved_path,
-                metadata.len(),
-                MAX_FILE_SIZE
-            );
-        }
-        return Err(anyhow::anyhow!(
-            "File too large: {} bytes (limit: {} bytes)",
-            metadata.len(),
-            MAX_FILE_SIZE
-        ));
     }
+}
 
-    // Read the file content with proper error handling
-    let content = match std::fs::read_to_string(&resolved_path) {
-        Ok(content) => content,
-        Err(e) => {
-            if debug_mode {
-                println!(
-                    "DEBUG: Error reading file {:?}: {:?} (size: {} bytes)",
-                    resolved_path,
-                    e,
-                    metadata.len()
-                );
-            }
-            return Err(anyhow::anyhow!("Failed to read file: {}", e));
-        }
-    };
+/// The 1-based lines each pattern of a RegexSet matches in a file's content
+pub(crate) fn pattern_lines_in_content(
+    file_path: &Path,
+    content: &str,
+    regex_set: &regex::RegexSet,
+    individual_regexes: &[regex::Regex],
+) -> HashMap<usize, HashSet<usize>> {
+    let mut pattern_lines: HashMap<usize, HashSet<usize>> = HashMap::new();
+    let debug_mode = std::env::var("DEBUG").unwrap_or_default() == "1";
 
     // Process each line
     for (line_number, line) in content.lines().enumerate() {
@@ -1631,19 +2008,16 @@ fn search_file_with_regex_set(
             for pattern_idx in matches.iter() {
                 // Use the individual regex to find all matches in the line
                 if individual_regexes[pattern_idx].is_match(line) {
-                    // Add matches for all terms associated with this pattern
-                    for &term_idx in &pattern_to_terms[pattern_idx] {
-                        term_map
-                            .entry(term_idx)
-                            .or_insert_with(HashSet::new)
-                            .insert(line_number + 1); // Convert to 1-based line numbers
-                    }
+                    pattern_lines
+                        .entry(pattern_idx)
+                        .or_default()
+                        .insert(line_number + 1); // Convert to 1-based line numbers
                 }
             }
         }
     }
 
-    Ok(term_map)
+    pattern_lines
 }
 
 /// Normalize language aliases to their canonical names
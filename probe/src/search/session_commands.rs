@@ -0,0 +1,219 @@
+use anyhow::Result;
+use colored::*;
+
+use crate::search::cache::{self, SessionCache};
+
+/// Format a Unix timestamp as a coarse age relative to now, e.g. "5m ago"
+fn format_age(timestamp: u64) -> String {
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+    let secs = now.saturating_sub(timestamp);
+
+    if secs < 60 {
+        format!("{}s ago", secs)
+    } else if secs < 60 * 60 {
+        format!("{}m ago", secs / 60)
+    } else if secs < 24 * 60 * 60 {
+        format!("{}h ago", secs / (60 * 60))
+    } else {
+        format!("{}d ago", secs / (24 * 60 * 60))
+    }
+}
+
+/// Handle `probe session list`
+pub fn handle_session_list(format: &str) -> Result<()> {
+    let sessions = cache::list_sessions(&cache::sessions_dir(), cache::session_ttl());
+
+    if format == "json" {
+        let wrapper = serde_json::json!({ "sessions": sessions });
+        println!("{}", serde_json::to_string_pretty(&wrapper)?);
+        return Ok(());
+    }
+
+    if sessions.is_empty() {
+        println!("{}", "No active sessions.".yellow().bold());
+        return Ok(());
+    }
+
+    let use_color = format == "color" || format == "terminal";
+    for session in &sessions {
+        let id = if use_color {
+            session.session_id.bold().cyan().to_string()
+        } else {
+            session.session_id.clone()
+        };
+        println!(
+            "{}  {} {}, {} blocks, {} pages, last used {}",
+            id,
+            session.queries,
+            if session.queries == 1 {
+                "query"
+            } else {
+                "queries"
+            },
+            session.blocks,
+            session.pages,
+            format_age(session.updated_at)
+        );
+    }
+
+    Ok(())
+}
+
+/// Handle `probe session show <SESSION>`
+pub fn handle_session_show(session_id: &str, format: &str) -> Result<()> {
+    let caches = cache::session_queries(&cache::sessions_dir(), session_id, cache::session_ttl());
+
+    if format == "json" {
+        let queries: Vec<serde_json::Value> = caches.iter().map(query_to_json).collect();
+        let wrapper = serde_json::json!({
+            "session_id": session_id,
+            "queries": queries,
+        });
+        println!("{}", serde_json::to_string_pretty(&wrapper)?);
+        return Ok(());
+    }
+
+    if caches.is_empty() {
+        println!(
+            "{}",
+            format!("No active session with ID: {}", session_id)
+                .yellow()
+                .bold()
+        );
+        return Ok(());
+    }
+
+    let use_color = format == "color" || format == "terminal";
+    println!("{} {}", "Session:".bold().green(), session_id);
+
+    for cache in &caches {
+        println!();
+        let query = if cache.query.is_empty() {
+            format!("(query hash {})", cache.query_hash)
+        } else {
+            cache.query.clone()
+        };
+        if use_color {
+            println!("{} {}", "Query:".bold().green(), query);
+        } else {
+            println!("Query: {}", query);
+        }
+        println!(
+            "  {} blocks, {} pages, last used {}",
+            cache.block_count(),
+            cache.pages.len(),
+            format_age(cache.updated_at)
+        );
+
+        for (i, page) in cache.pages.iter().enumerate() {
+            println!("  Page {}: {} blocks", i + 1, page.len());
+        }
+
+        for block in session_blocks(cache) {
+            let location = match block.lines {
+                Some((start, end)) => format!(" ({}:{}-{})", block.file, start, end),
+                None => String::new(),
+            };
+            let status = match block.page {
+                Some(page) => format!("page {}", page),
+                None => "seen".to_string(),
+            };
+            println!("    {}{} [{}]", block.key, location, status);
+        }
+    }
+
+    Ok(())
+}
+
+/// A block recorded in a session, as `session show` lists it
+struct SessionBlock<'a> {
+    /// Block key (see `cache::block_keys`)
+    key: &'a str,
+    file: &'a str,
+    /// None for blocks of caches written before blocks were keyed by content
+    lines: Option<(usize, usize)>,
+    /// The page `--page` served the block on, if any
+    page: Option<usize>,
+}
+
+/// The blocks of a cached query, sorted by key
+fn session_blocks(cache: &SessionCache) -> Vec<SessionBlock<'_>> {
+    let mut blocks: Vec<SessionBlock> = if cache.blocks.is_empty() {
+        cache
+            .block_identifiers
+            .iter()
+            .map(|key| SessionBlock {
+                key,
+                file: key.rsplit_once(':').map_or(key.as_str(), |(file, _)| file),
+                lines: None,
+                page: None,
+            })
+            .collect()
+    } else {
+        cache
+            .blocks
+            .iter()
+            .map(|(key, seen)| SessionBlock {
+                key,
+                file: &seen.file,
+                lines: Some(seen.lines),
+                page: cache.block_page(key),
+            })
+            .collect()
+    };
+    blocks.sort_by(|a, b| a.key.cmp(b.key));
+    blocks
+}
+
+/// Convert a cached query to its JSON representation
+///
+/// Every block is listed with its status: "seen" by a search, or "paged" when `--page`
+/// served it, with the page it was served on.
+fn query_to_json(cache: &SessionCache) -> serde_json::Value {
+    let blocks: Vec<serde_json::Value> = session_blocks(cache)
+        .iter()
+        .map(|block| {
+            serde_json::json!({
+                "key": block.key,
+                "file": block.file,
+                "lines": block.lines,
+                "status": if block.page.is_some() { "paged" } else { "seen" },
+                "page": block.page,
+            })
+        })
+        .collect();
+    serde_json::json!({
+        "query": cache.query,
+        "query_hash": cache.query_hash,
+        "created_at": cache.created_at,
+        "updated_at": cache.updated_at,
+        "blocks": blocks,
+        "pages": cache.pages,
+    })
+}
+
+/// Handle `probe session clear [SESSION] [--expired]`
+pub fn handle_session_clear(session_id: Option<&str>, expired: bool) -> Result<()> {
+    let removed = cache::clear_sessions(
+        &cache::sessions_dir(),
+        session_id,
+        expired,
+        cache::session_ttl(),
+    )?;
+
+    let what = match (session_id, expired) {
+        (Some(id), true) => format!("expired caches of session {}", id),
+        (Some(id), false) => format!("caches of session {}", id),
+        (None, true) => "expired session caches".to_string(),
+        (None, false) => "session caches".to_string(),
+    };
+    println!(
+        "{}",
+        format!("Removed {} {}.", removed, what).green().bold()
+    );
+
+    Ok(())
+}
@@ -0,0 +1,361 @@
+//! Which files are worth searching, and how to read them.
+//!
+//! The walker leaves out files over the size limit, binary files, minified bundles and
+//! generated code, and reports each with the reason (see `SkippedFile`). Files that are
+//! not valid UTF-8 are still read: UTF-16 files with a byte order mark are decoded, and
+//! anything else is read as Latin-1.
+
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+use ignore::Match;
+use serde::Serialize;
+use std::collections::BTreeSet;
+use std::fs::File;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+/// Default maximum size of the files searched
+pub const DEFAULT_MAX_FILE_SIZE: u64 = 1024 * 1024;
+
+/// Bytes read from the start of every file to sniff its kind
+const SNIFF_SIZE: usize = 16 * 1024;
+
+/// Bytes at the start of a file that are searched for a generated-code marker
+const HEADER_SIZE: usize = 1024;
+
+/// Lines longer than this are candidates for minified code
+const MINIFIED_LINE: usize = 500;
+
+/// Minified code has at least one punctuation character in this many bytes, where a long
+/// string or data literal has hardly any
+const MINIFIED_PUNCTUATION_SPACING: usize = 20;
+
+/// Limits on the files a search reads
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct FileLimits {
+    /// Largest file to read, in bytes; 0 for no limit
+    pub max_file_size: u64,
+    /// Whether minified and generated files are left out
+    pub skip_generated: bool,
+}
+
+impl Default for FileLimits {
+    fn default() -> Self {
+        FileLimits {
+            max_file_size: DEFAULT_MAX_FILE_SIZE,
+            skip_generated: true,
+        }
+    }
+}
+
+/// Parse a file size such as `500000`, `512K`, `2M` or `1G` (binary units)
+pub fn parse_file_size(size: &str) -> Result<u64, String> {
+    let size = size.trim();
+    let digits = size.trim_end_matches(|c: char| c.is_ascii_alphabetic());
+    let multiplier: u64 = match size[digits.len()..].to_ascii_uppercase().as_str() {
+        "" | "B" => 1,
+        "K" | "KB" | "KIB" => 1024,
+        "M" | "MB" | "MIB" => 1024 * 1024,
+        "G" | "GB" | "GIB" => 1024 * 1024 * 1024,
+        unit => return Err(format!("unknown size unit '{}'", unit)),
+    };
+    let value: u64 = digits
+        .trim()
+        .parse()
+        .map_err(|_| format!("invalid file size '{}'", size))?;
+    value
+        .checked_mul(multiplier)
+        .ok_or_else(|| format!("file size '{}' is too large", size))
+}
+
+/// Why a file was not searched
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SkipReason {
+    /// Larger than `FileLimits::max_file_size`
+    TooLarge,
+    /// Contains NUL bytes and no UTF-16 byte order mark
+    Binary,
+    /// Named `*.min.*` or made of very long lines of code
+    Minified,
+    /// Has a generated-code header, or is `linguist-generated` in `.gitattributes`
+    Generated,
+}
+
+impl SkipReason {
+    pub fn description(&self) -> &'static str {
+        match self {
+            SkipReason::TooLarge => "too large",
+            SkipReason::Binary => "binary",
+            SkipReason::Minified => "minified",
+            SkipReason::Generated => "generated",
+        }
+    }
+}
+
+/// A file the walker left out
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct SkippedFile {
+    pub file: String,
+    pub reason: SkipReason,
+    /// Size of the file in bytes
+    pub size: u64,
+}
+
+/// Describe skipped files by reason, e.g. "2 too large, 1 binary"
+pub fn describe_skipped_files(skipped: &[SkippedFile]) -> String {
+    let reasons: BTreeSet<SkipReason> = skipped.iter().map(|file| file.reason).collect();
+    reasons
+        .into_iter()
+        .map(|reason| {
+            let count = skipped.iter().filter(|file| file.reason == reason).count();
+            format!("{} {}", count, reason.description())
+        })
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Decode the contents of a source file: UTF-8, UTF-16 with a byte order mark, or else
+/// Latin-1, so that no file is refused for its encoding
+pub fn decode_source(bytes: Vec<u8>) -> String {
+    let utf16 = |bytes: &[u8], from_bytes: fn([u8; 2]) -> u16| {
+        let units: Vec<u16> = bytes
+            .chunks_exact(2)
+            .map(|pair| from_bytes([pair[0], pair[1]]))
+            .collect();
+        String::from_utf16_lossy(&units)
+    };
+    if let Some(rest) = bytes.strip_prefix(&[0xFF, 0xFE]) {
+        return utf16(rest, u16::from_le_bytes);
+    }
+    if let Some(rest) = bytes.strip_prefix(&[0xFE, 0xFF]) {
+        return utf16(rest, u16::from_be_bytes);
+    }
+    match String::from_utf8(bytes) {
+        Ok(content) => content,
+        // Every byte is a Latin-1 character
+        Err(err) => err.into_bytes().into_iter().map(char::from).collect(),
+    }
+}
+
+/// Read a source file, decoding it with `decode_source`
+pub fn read_source_file(path: &Path) -> std::io::Result<String> {
+    std::fs::read(path).map(decode_source)
+}
+
+fn is_binary(sample: &[u8]) -> bool {
+    let utf16 = sample.starts_with(&[0xFF, 0xFE]) || sample.starts_with(&[0xFE, 0xFF]);
+    !utf16 && sample.contains(&0)
+}
+
+fn is_minified(path: &Path, sample: &[u8]) -> bool {
+    let name = path.file_name().map(|name| name.to_string_lossy());
+    if name.is_some_and(|name| name.contains(".min.")) {
+        return true;
+    }
+    if sample.len() < HEADER_SIZE {
+        return false;
+    }
+    // Most of the file is in long lines, and those lines are dense code
+    let long_lines: Vec<&[u8]> = sample
+        .split(|&byte| byte == b'\n')
+        .filter(|line| line.len() > MINIFIED_LINE)
+        .collect();
+    let long_bytes: usize = long_lines.iter().map(|line| line.len()).sum();
+    if long_bytes * 2 < sample.len() {
+        return false;
+    }
+    let punctuation = long_lines
+        .iter()
+        .flat_map(|line| line.iter())
+        .filter(|byte| b";,{}()=:".contains(byte))
+        .count();
+    punctuation * MINIFIED_PUNCTUATION_SPACING >= long_bytes
+}
+
+/// Whether the header of a file says it is generated, as `@generated` tags and Go's
+/// "Code generated ... DO NOT EDIT." comments do
+fn has_generated_header(sample: &[u8]) -> bool {
+    let header = String::from_utf8_lossy(&sample[..sample.len().min(HEADER_SIZE)]);
+    header.lines().any(|line| {
+        line.contains("@generated")
+            || line.contains("<auto-generated")
+            || (line.contains("Code generated") && line.contains("DO NOT EDIT"))
+    })
+}
+
+/// The paths `.gitattributes` files mark as `linguist-generated`
+#[derive(Default)]
+pub struct GeneratedAttributes {
+    /// Matchers of the `.gitattributes` files, from the outermost directory inwards
+    matchers: Vec<Gitignore>,
+}
+
+impl GeneratedAttributes {
+    /// Read the `.gitattributes` files that apply to the files under `root`: those of
+    /// the directories of the files, and of the directories above `root` up to the
+    /// repository root
+    pub fn for_files(root: &Path, files: &[PathBuf]) -> Self {
+        let mut directories = BTreeSet::new();
+        let root = root.canonicalize().unwrap_or_else(|_| root.to_path_buf());
+        for ancestor in root.ancestors() {
+            directories.insert(ancestor.to_path_buf());
+            if ancestor.join(".git").exists() {
+                break;
+            }
+        }
+        let parents: BTreeSet<&Path> = files.iter().filter_map(|file| file.parent()).collect();
+        directories.extend(
+            parents
+                .into_iter()
+                .filter_map(|dir| dir.canonicalize().ok()),
+        );
+
+        let mut directories: Vec<PathBuf> = directories
+            .into_iter()
+            .filter(|dir| dir.join(".gitattributes").is_file())
+            .collect();
+        directories.sort_by_key(|dir| dir.components().count());
+        let matchers = directories
+            .iter()
+            .filter_map(|dir| Self::matcher(dir))
+            .collect();
+        GeneratedAttributes { matchers }
+    }
+
+    /// The matcher of the `linguist-generated` patterns of a directory's `.gitattributes`
+    fn matcher(dir: &Path) -> Option<Gitignore> {
+        let content = std::fs::read_to_string(dir.join(".gitattributes")).ok()?;
+        let mut builder = GitignoreBuilder::new(dir);
+        let mut any = false;
+        for line in content.lines().map(str::trim) {
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let mut fields = line.split_whitespace();
+            let Some(pattern) = fields.next() else {
+                continue;
+            };
+            for attribute in fields {
+                let generated = match attribute {
+                    "linguist-generated" | "linguist-generated=true" => true,
+                    "-linguist-generated" | "!linguist-generated" | "linguist-generated=false" => {
+                        false
+                    }
+                    _ => continue,
+                };
+                let pattern = if generated {
+                    pattern.to_string()
+                } else {
+                    format!("!{}", pattern)
+                };
+                any |= builder.add_line(None, &pattern).is_ok();
+            }
+        }
+        if !any {
+            return None;
+        }
+        builder.build().ok()
+    }
+
+    pub fn is_generated(&self, path: &Path) -> bool {
+        if self.matchers.is_empty() {
+            return false;
+        }
+        let Ok(path) = path.canonicalize() else {
+            return false;
+        };
+        // Deeper .gitattributes files override the outer ones
+        for matcher in self.matchers.iter().rev() {
+            if !path.starts_with(matcher.path()) {
+                continue;
+            }
+            match matcher.matched(&path, false) {
+                Match::Ignore(_) => return true,
+                Match::Whitelist(_) => return false,
+                Match::None => {}
+            }
+        }
+        false
+    }
+}
+
+/// Why a file should not be searched, if it shouldn't
+pub fn skip_reason(
+    path: &Path,
+    limits: &FileLimits,
+    attributes: &GeneratedAttributes,
+) -> Option<(SkipReason, u64)> {
+    let size = std::fs::metadata(path).ok()?.len();
+    if limits.max_file_size > 0 && size > limits.max_file_size {
+        return Some((SkipReason::TooLarge, size));
+    }
+
+    let mut sample = Vec::with_capacity(SNIFF_SIZE);
+    File::open(path)
+        .ok()?
+        .take(SNIFF_SIZE as u64)
+        .read_to_end(&mut sample)
+        .ok()?;
+    if is_binary(&sample) {
+        return Some((SkipReason::Binary, size));
+    }
+    if limits.skip_generated {
+        if is_minified(path, &sample) {
+            return Some((SkipReason::Minified, size));
+        }
+        if has_generated_header(&sample) || attributes.is_generated(path) {
+            return Some((SkipReason::Generated, size));
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decode_source() {
+        assert_eq!(decode_source("héllo".as_bytes().to_vec()), "héllo");
+        // Latin-1
+        assert_eq!(decode_source(vec![b'h', 0xE9, b'l']), "hél");
+        // UTF-16 with byte order marks
+        assert_eq!(decode_source(vec![0xFF, 0xFE, b'h', 0, 0xE9, 0]), "hé");
+        assert_eq!(decode_source(vec![0xFE, 0xFF, 0, b'h', 0, 0xE9]), "hé");
+    }
+
+    #[test]
+    fn test_parse_file_size() {
+        assert_eq!(parse_file_size("0"), Ok(0));
+        assert_eq!(parse_file_size("1500"), Ok(1500));
+        assert_eq!(parse_file_size("512K"), Ok(512 * 1024));
+        assert_eq!(parse_file_size("2mb"), Ok(2 * 1024 * 1024));
+        assert_eq!(parse_file_size("1G"), Ok(1024 * 1024 * 1024));
+        assert!(parse_file_size("2X").is_err());
+        assert!(parse_file_size("M").is_err());
+    }
+
+    #[test]
+    fn test_sniffing() {
+        assert!(is_binary(b"\x7fELF\x02\x01\x00\x00"));
+        assert!(!is_binary(&[0xFF, 0xFE, b'a', 0]));
+        assert!(!is_binary(b"fn main() {}\n"));
+
+        let bundle = format!("var a={};", "b+c;".repeat(1000));
+        assert!(is_minified(Path::new("app.js"), bundle.as_bytes()));
+        assert!(is_minified(Path::new("app.min.js"), b"var a;"));
+        // A long string literal in hand-written code is not minified code
+        let literal = format!("const data = \"{}\";\n", "x".repeat(10000));
+        assert!(!is_minified(Path::new("data.js"), literal.as_bytes()));
+        let code = "fn main() {\n    println!(\"hi\");\n}\n".repeat(100);
+        assert!(!is_minified(Path::new("main.rs"), code.as_bytes()));
+
+        assert!(has_generated_header(
+            b"// Code generated by protoc-gen-go. DO NOT EDIT.\npackage pb\n"
+        ));
+        assert!(has_generated_header(
+            b"/**\n * @generated SignedSource<<abc>>\n */\n"
+        ));
+        assert!(!has_generated_header(code.as_bytes()));
+    }
+}
@@ -1,3 +1,4 @@
+use crate::search::project_vocabulary::ProjectVocabulary;
 use once_cell::sync::Lazy;
 use std::collections::HashSet;
 
@@ -60,6 +61,13 @@ pub static EXCEPTION_TERMS: Lazy<HashSet<String>> = Lazy::new(|| {
 });
 
 /// Checks if a term is in the exception list
+#[allow(dead_code)]
 pub fn is_exception_term(term: &str) -> bool {
-    EXCEPTION_TERMS.contains(&term.to_lowercase())
+    is_exception_term_for_project(term, None)
+}
+
+/// Checks if a term is in the exception list or protected by the project vocabulary
+pub fn is_exception_term_for_project(term: &str, project: Option<&ProjectVocabulary>) -> bool {
+    let term = term.to_lowercase();
+    EXCEPTION_TERMS.contains(&term) || project.is_some_and(|project| project.is_exception(&term))
 }
@@ -1,5 +1,6 @@
 use crate::ranking::get_stemmer;
-use crate::search::term_exceptions::{is_exception_term, EXCEPTION_TERMS};
+use crate::search::project_vocabulary::ProjectVocabulary;
+use crate::search::term_exceptions::{is_exception_term_for_project, EXCEPTION_TERMS};
 use decompound::{decompound, DecompositionOptions};
 use once_cell::sync::Lazy;
 use std::collections::HashSet;
@@ -335,7 +336,13 @@ fn is_number(c: char) -> bool {
 }
 
 /// Checks if a word is a special case that should be treated as a single token
+#[allow(dead_code)]
 pub fn is_special_case(word: &str) -> bool {
+    is_special_case_for_project(word, None)
+}
+
+/// Same as [`is_special_case`], also keeping the exceptions of a project vocabulary whole
+pub fn is_special_case_for_project(word: &str, project: Option<&ProjectVocabulary>) -> bool {
     // Convert to lowercase for case-insensitive comparison
     let lowercase = word.to_lowercase();
 
@@ -344,6 +351,11 @@ pub fn is_special_case(word: &str) -> bool {
         return true;
     }
 
+    // Check if the word is protected by the project vocabulary
+    if project.is_some_and(|project| project.is_exception(&lowercase)) {
+        return true;
+    }
+
     // Check if the word is in the dynamic special terms list
     let special_terms = DYNAMIC_SPECIAL_TERMS.lock().unwrap();
     if special_terms.contains(&lowercase) {
@@ -364,7 +376,16 @@ pub fn is_special_case(word: &str) -> bool {
 /// - acronyms and numbers -> ["parse", "json", "to", "html", "5"]
 /// - special cases like OAuth2 -> ["oauth2"]
 /// - also attempts to split lowercase identifiers that might have been camelCase originally
+#[allow(dead_code)]
 pub fn split_camel_case(input: &str) -> Vec<String> {
+    split_camel_case_for_project(input, None)
+}
+
+/// Same as [`split_camel_case`], keeping the exceptions of a project vocabulary whole
+pub fn split_camel_case_for_project(
+    input: &str,
+    project: Option<&ProjectVocabulary>,
+) -> Vec<String> {
     let _debug_mode = std::env::var("DEBUG").unwrap_or_default() == "1";
 
     if input.is_empty() {
@@ -372,7 +393,7 @@ pub fn split_camel_case(input: &str) -> Vec<String> {
     }
 
     // Check if the input is a special case word
-    if is_special_case(input) {
+    if is_special_case_for_project(input, project) {
         return vec![input.to_lowercase()];
     }
 
@@ -384,7 +405,7 @@ pub fn split_camel_case(input: &str) -> Vec<String> {
         let remaining = &input[6..]; // "oauth2".len() = 6
         if !remaining.is_empty() {
             let mut result = vec!["oauth2".to_string()];
-            result.extend(split_camel_case(remaining));
+            result.extend(split_camel_case_for_project(remaining, project));
             return result;
         }
     }
@@ -403,7 +424,7 @@ pub fn split_camel_case(input: &str) -> Vec<String> {
 
             if !remaining.is_empty() {
                 let mut result = vec![special_case.clone()];
-                result.extend(split_camel_case(remaining));
+                result.extend(split_camel_case_for_project(remaining, project));
 
                 return result;
             }
@@ -514,16 +535,35 @@ pub fn is_programming_stop_word(word: &str) -> bool {
     PROGRAMMING_STOP_WORDS.contains(word)
 }
 
-/// Checks if a word is either an English or programming stop word
+/// Checks if a word is an English or programming stop word
+#[allow(dead_code)]
 pub fn is_stop_word(word: &str) -> bool {
-    is_english_stop_word(word) || is_programming_stop_word(word)
+    is_stop_word_for_project(word, None)
+}
+
+/// Checks if a word is an English, programming or project stop word
+pub fn is_stop_word_for_project(word: &str, project: Option<&ProjectVocabulary>) -> bool {
+    is_english_stop_word(word)
+        || is_programming_stop_word(word)
+        || project.is_some_and(|project| project.is_stop_word(word))
 }
 
 /// Attempts to split a compound word into its constituent parts using a vocabulary
 /// Returns the original word if it cannot be split
+#[allow(dead_code)]
 pub fn split_compound_word(word: &str, vocab: &HashSet<String>) -> Vec<String> {
+    split_compound_word_for_project(word, vocab, None)
+}
+
+/// Same as [`split_compound_word`], also splitting into the domain terms of a project
+/// vocabulary and keeping its exceptions whole
+pub fn split_compound_word_for_project(
+    word: &str,
+    vocab: &HashSet<String>,
+    project: Option<&ProjectVocabulary>,
+) -> Vec<String> {
     // First check if this is a special case word that should never be split
-    if is_special_case(word) {
+    if is_special_case_for_project(word, project) {
         return vec![word.to_lowercase()];
     }
 
@@ -545,7 +585,11 @@ pub fn split_compound_word(word: &str, vocab: &HashSet<String>) -> Vec<String> {
         return vec![word.to_string()];
     }
 
-    let is_valid_word = |w: &str| vocab.contains(&w.to_lowercase());
+    // Domain terms from the project vocabulary are valid parts too
+    let is_valid_word = |w: &str| {
+        let w = w.to_lowercase();
+        vocab.contains(&w) || project.is_some_and(|project| project.is_term(&w))
+    };
 
     match decompound(word, &is_valid_word, DecompositionOptions::empty()) {
         Ok(parts) if !parts.is_empty() => parts,
@@ -1055,28 +1099,36 @@ pub fn load_vocabulary() -> &'static HashSet<String> {
 /// This function is used by the elastic query parser to process terms in the AST
 #[allow(dead_code)]
 pub fn tokenize_and_stem(keyword: &str) -> Vec<String> {
+    tokenize_and_stem_for_project(keyword, None)
+}
+
+/// Same as [`tokenize_and_stem`], with the word lists of a project vocabulary
+pub fn tokenize_and_stem_for_project(
+    keyword: &str,
+    project: Option<&ProjectVocabulary>,
+) -> Vec<String> {
     let stemmer = get_stemmer();
     let vocabulary = load_vocabulary();
 
     // First try camel case splitting
-    let camel_parts = split_camel_case(keyword);
+    let camel_parts = split_camel_case_for_project(keyword, project);
 
     if camel_parts.len() > 1 {
         // Return stemmed camel case parts, filtering out stop words
         camel_parts
             .into_iter()
-            .filter(|part| !is_stop_word(part))
+            .filter(|part| !is_stop_word_for_project(part, project))
             .map(|part| stemmer.stem(&part).to_string())
             .collect()
     } else {
         // Try compound word splitting
-        let compound_parts = split_compound_word(keyword, vocabulary);
+        let compound_parts = split_compound_word_for_project(keyword, vocabulary, project);
 
         if compound_parts.len() > 1 {
             // Return stemmed compound parts, filtering out stop words
             compound_parts
                 .into_iter()
-                .filter(|part| !is_stop_word(part))
+                .filter(|part| !is_stop_word_for_project(part, project))
                 .map(|part| stemmer.stem(&part).to_string())
                 .collect()
         } else {
@@ -1099,7 +1151,14 @@ pub fn tokenize_and_stem(keyword: &str) -> Vec<String> {
 /// 6. Process each part: remove stop words and apply stemming
 /// 7. Collect unique tokens
 /// 8. Exclude terms that were negated with a "-" prefix
+#[allow(dead_code)]
 pub fn tokenize(text: &str) -> Vec<String> {
+    tokenize_for_project(text, None)
+}
+
+/// Same as [`tokenize`], with the word lists of a project vocabulary: its stop words are
+/// removed, its domain terms split compound words and its exceptions are kept whole
+pub fn tokenize_for_project(text: &str, project: Option<&ProjectVocabulary>) -> Vec<String> {
     let stemmer = get_stemmer();
     let vocabulary = load_vocabulary();
 
@@ -1156,14 +1215,14 @@ pub fn tokenize(text: &str) -> Vec<String> {
     for token in tokens {
         // Always try to split using camel case rules, even for lowercase tokens
         // This allows us to handle tokens that were already lowercased
-        let parts = split_camel_case(&token);
+        let parts = split_camel_case_for_project(&token, project);
 
         // Process each part
         for part in parts {
             let lowercase_part = part.to_lowercase();
 
             // Skip both English and programming stop words
-            if is_stop_word(&lowercase_part) {
+            if is_stop_word_for_project(&lowercase_part, project) {
                 continue;
             }
 
@@ -1173,11 +1232,12 @@ pub fn tokenize(text: &str) -> Vec<String> {
             }
 
             // Try to split compound words
-            let compound_parts = split_compound_word(&lowercase_part, vocabulary);
+            let compound_parts =
+                split_compound_word_for_project(&lowercase_part, vocabulary, project);
 
             for compound_part in compound_parts {
                 // Skip stop words in compound parts
-                if is_stop_word(&compound_part) {
+                if is_stop_word_for_project(&compound_part, project) {
                     continue;
                 }
 
@@ -1187,7 +1247,7 @@ pub fn tokenize(text: &str) -> Vec<String> {
                 }
 
                 // Preserve the original form for all exception terms
-                if is_exception_term(&compound_part)
+                if is_exception_term_for_project(&compound_part, project)
                     && processed_tokens.insert(compound_part.clone())
                 {
                     result.push(compound_part.clone());
@@ -0,0 +1,831 @@
+//! Near-duplicate and similar code detection.
+//!
+//! Code blocks produced by the language parser are reduced to a sequence of normalized
+//! tokens: identifiers become `ID`, numbers `NUM` and string literals `STR`, so that a
+//! copy with renamed variables still matches the original. Overlapping runs of tokens
+//! (shingles) are compared with the Jaccard similarity of their sets. Finding the
+//! duplicates among all blocks of a project uses MinHash signatures with locality
+//! sensitive hashing, so that only blocks likely to be similar are compared.
+
+use anyhow::{Context, Result};
+use colored::*;
+use rayon::prelude::*;
+use serde::Serialize;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{HashMap, HashSet};
+use std::hash::{Hash, Hasher};
+use std::path::Path;
+use std::time::Instant;
+use tree_sitter::{Node, Tree};
+
+use crate::extract::{parse_file_with_line, process_file_for_extraction};
+use crate::language::factory::get_language_impl;
+use crate::language::language_trait::LanguageImpl;
+use crate::language::parser::{block_definition_node, node_symbol_name, parse_source};
+use crate::models::CodeBlock;
+use crate::search::file_list_cache;
+use crate::search::source_files::read_source_file;
+use crate::search::{is_structured_format, json_line};
+
+/// Number of consecutive normalized tokens in a shingle
+const SHINGLE_SIZE: usize = 4;
+
+/// Number of hash functions in a MinHash signature
+const SIGNATURE_SIZE: usize = 128;
+
+/// Blocks with fewer distinct shingles are too small or too repetitive to compare, like
+/// lists of fields or constants, whose normalized tokens repeat the same few shingles
+const MIN_SHINGLES: usize = 24;
+
+/// Words kept as they are when normalizing, because they carry the structure of the code
+const KEYWORDS: &[&str] = &[
+    "as",
+    "async",
+    "await",
+    "break",
+    "case",
+    "catch",
+    "class",
+    "const",
+    "continue",
+    "def",
+    "default",
+    "defer",
+    "del",
+    "do",
+    "elif",
+    "else",
+    "end",
+    "enum",
+    "except",
+    "extends",
+    "false",
+    "finally",
+    "fn",
+    "for",
+    "func",
+    "function",
+    "go",
+    "if",
+    "impl",
+    "import",
+    "in",
+    "interface",
+    "lambda",
+    "let",
+    "loop",
+    "match",
+    "mut",
+    "new",
+    "nil",
+    "none",
+    "null",
+    "of",
+    "pass",
+    "pub",
+    "raise",
+    "return",
+    "self",
+    "static",
+    "struct",
+    "switch",
+    "this",
+    "throw",
+    "trait",
+    "true",
+    "try",
+    "type",
+    "unless",
+    "var",
+    "while",
+    "with",
+    "yield",
+];
+
+/// File extensions of languages with `#` line comments
+const HASH_COMMENT_EXTENSIONS: &[&str] = &["py", "rb", "sh", "bash"];
+
+/// Options for finding similar and duplicated code
+pub struct SimilarityOptions<'a> {
+    /// Directory or file whose code blocks are compared
+    pub path: &'a Path,
+    /// Lowest similarity, between 0 and 1, for blocks to be reported
+    pub threshold: f64,
+    /// Blocks with fewer lines are ignored
+    pub min_lines: usize,
+    /// Maximum number of similar blocks or duplicate clusters to return
+    pub max_results: usize,
+    /// Custom patterns of files to ignore
+    pub ignore: &'a [String],
+    /// Whether test files and test code blocks are compared
+    pub allow_tests: bool,
+}
+
+/// A code block similar to another block
+#[derive(Debug, Clone, Serialize)]
+pub struct SimilarBlock {
+    pub file: String,
+    pub lines: (usize, usize),
+    pub node_type: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub symbol: Option<String>,
+    /// Similarity to the searched block, or to the closest block of its cluster
+    pub similarity: f64,
+    pub code: String,
+}
+
+/// A group of code blocks that are near-duplicates of each other
+#[derive(Debug, Clone, Serialize)]
+pub struct DuplicateCluster {
+    /// Lowest similarity of a block of the cluster to its closest block
+    pub similarity: f64,
+    pub blocks: Vec<SimilarBlock>,
+}
+
+/// A code block with the shingles of its normalized tokens
+struct Block {
+    file: String,
+    lines: (usize, usize),
+    node_type: String,
+    /// Name of the symbol the block defines, from its syntax tree
+    symbol: Option<String>,
+    code: String,
+    /// Sorted, deduplicated shingle hashes
+    shingles: Vec<u64>,
+}
+
+impl Block {
+    fn new(
+        file: String,
+        lines: (usize, usize),
+        node_type: String,
+        symbol: Option<String>,
+        code: String,
+    ) -> Option<Self> {
+        let extension = Path::new(&file)
+            .extension()
+            .and_then(|e| e.to_str())
+            .unwrap_or("");
+        let shingles = shingles(&normalized_tokens(&code, extension));
+        if shingles.is_empty() {
+            return None;
+        }
+        Some(Block {
+            file,
+            lines,
+            node_type,
+            symbol,
+            code,
+            shingles,
+        })
+    }
+
+    /// Whether two blocks are the same code or one contains the other
+    fn overlaps(&self, other: &Block) -> bool {
+        self.file == other.file && self.lines.0 <= other.lines.1 && other.lines.0 <= self.lines.1
+    }
+
+    fn to_similar(&self, similarity: f64) -> SimilarBlock {
+        SimilarBlock {
+            file: self.file.clone(),
+            lines: self.lines,
+            node_type: self.node_type.clone(),
+            symbol: self.symbol.clone(),
+            similarity,
+            code: self.code.clone(),
+        }
+    }
+}
+
+fn collect_acceptable_nodes(
+    node: Node,
+    source: &[u8],
+    language_impl: &dyn LanguageImpl,
+    allow_tests: bool,
+    blocks: &mut Vec<CodeBlock>,
+) {
+    let mut cursor = node.walk();
+    for child in node.named_children(&mut cursor) {
+        if language_impl.is_acceptable_parent(&child) {
+            if !allow_tests && language_impl.is_test_node(&child, source) {
+                continue;
+            }
+            blocks.push(CodeBlock {
+                start_row: child.start_position().row,
+                end_row: child.end_position().row,
+                start_byte: child.start_byte(),
+                end_byte: child.end_byte(),
+                node_type: child.kind().to_string(),
+                parent_node_type: None,
+                parent_start_row: None,
+                parent_end_row: None,
+            });
+        }
+        collect_acceptable_nodes(child, source, language_impl, allow_tests, blocks);
+    }
+}
+
+/// Every block of a parsed file, outer blocks before the blocks nested in them
+///
+/// Unlike `parse_file_for_code_blocks`, which finds the blocks around given lines, this
+/// lists all the nodes the language accepts as blocks, including nested ones.
+pub fn collect_code_blocks(
+    tree: &Tree,
+    content: &str,
+    extension: &str,
+    allow_tests: bool,
+) -> Vec<CodeBlock> {
+    let Some(language_impl) = get_language_impl(extension) else {
+        return Vec::new();
+    };
+    let mut blocks = Vec::new();
+    collect_acceptable_nodes(
+        tree.root_node(),
+        content.as_bytes(),
+        language_impl.as_ref(),
+        allow_tests,
+        &mut blocks,
+    );
+    blocks
+}
+
+/// Split code into tokens, normalizing identifiers, numbers and string literals and
+/// dropping whitespace and comments
+pub fn normalized_tokens(code: &str, extension: &str) -> Vec<String> {
+    let hash_comments = HASH_COMMENT_EXTENSIONS.contains(&extension);
+    let chars: Vec<char> = code.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+
+    while i < chars.len() {
+        let c = chars[i];
+        let next = chars.get(i + 1).copied();
+
+        if c.is_whitespace() {
+            i += 1;
+        } else if (c == '/' && next == Some('/')) || (c == '#' && hash_comments) {
+            while i < chars.len() && chars[i] != '\n' {
+                i += 1;
+            }
+        } else if c == '/' && next == Some('*') {
+            i += 2;
+            while i < chars.len() && !(chars[i] == '*' && chars.get(i + 1) == Some(&'/')) {
+                i += 1;
+            }
+            i += 2;
+        } else if c == '"'
+            || c == '`'
+            || (c == '\'' && (extension != "rs" || is_char_literal(&chars[i..])))
+        {
+            i += 1;
+            while i < chars.len() && chars[i] != c {
+                if chars[i] == '\\' {
+                    i += 1;
+                }
+                i += 1;
+            }
+            i += 1;
+            tokens.push("STR".to_string());
+        } else if c.is_alphabetic() || c == '_' {
+            let start = i;
+            while i < chars.len() && (chars[i].is_alphanumeric() || chars[i] == '_') {
+                i += 1;
+            }
+            let word: String = chars[start..i].iter().collect::<String>().to_lowercase();
+            if KEYWORDS.contains(&word.as_str()) {
+                tokens.push(word);
+            } else {
+                tokens.push("ID".to_string());
+            }
+        } else if c.is_ascii_digit() {
+            while i < chars.len()
+                && (chars[i].is_alphanumeric() || chars[i] == '_' || chars[i] == '.')
+            {
+                i += 1;
+            }
+            tokens.push("NUM".to_string());
+        } else {
+            tokens.push(c.to_string());
+            i += 1;
+        }
+    }
+
+    tokens
+}
+
+/// Whether a single quote in Rust code starts a character literal rather than a lifetime
+fn is_char_literal(chars: &[char]) -> bool {
+    chars.get(1) == Some(&'\\') || chars.get(2) == Some(&'\'')
+}
+
+fn hash_value<T: Hash>(value: T) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    value.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// The sorted hashes of the shingles of a token sequence; empty for blocks too small or too
+/// repetitive to compare
+fn shingles(tokens: &[String]) -> Vec<u64> {
+    let mut shingles: Vec<u64> = tokens.windows(SHINGLE_SIZE).map(hash_value).collect();
+    shingles.sort_unstable();
+    shingles.dedup();
+    if shingles.len() < MIN_SHINGLES {
+        shingles.clear();
+    }
+    shingles
+}
+
+/// Jaccard similarity of two sorted sets of shingles
+fn jaccard(a: &[u64], b: &[u64]) -> f64 {
+    let (mut i, mut j, mut shared) = (0, 0, 0);
+    while i < a.len() && j < b.len() {
+        match a[i].cmp(&b[j]) {
+            std::cmp::Ordering::Less => i += 1,
+            std::cmp::Ordering::Greater => j += 1,
+            std::cmp::Ordering::Equal => {
+                shared += 1;
+                i += 1;
+                j += 1;
+            }
+        }
+    }
+    let union = a.len() + b.len() - shared;
+    if union == 0 {
+        0.0
+    } else {
+        shared as f64 / union as f64
+    }
+}
+
+/// Mix a shingle hash with a seed, one of the hash functions of a MinHash signature
+fn mix(value: u64, seed: u64) -> u64 {
+    // splitmix64 finalizer
+    let mut z = value ^ seed.wrapping_mul(0x9e37_79b9_7f4a_7c15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
+
+fn minhash_signature(shingles: &[u64]) -> Vec<u64> {
+    (0..SIGNATURE_SIZE as u64)
+        .map(|seed| {
+            shingles
+                .iter()
+                .map(|&shingle| mix(shingle, seed))
+                .min()
+                .unwrap_or(u64::MAX)
+        })
+        .collect()
+}
+
+/// Rows per band of the locality sensitive hashing, chosen so that pairs of blocks at the
+/// similarity threshold almost always share a band
+fn rows_per_band(threshold: f64) -> usize {
+    [8, 4, 2, 1]
+        .into_iter()
+        .find(|&rows| {
+            let bands = (SIGNATURE_SIZE / rows) as f64;
+            // Probability that a pair with the threshold similarity shares no band
+            let missed = (1.0 - threshold.powi(rows as i32)).powf(bands);
+            missed < 0.001
+        })
+        .unwrap_or(1)
+}
+
+/// The code blocks of a file
+fn file_blocks(path: &Path, options: &SimilarityOptions) -> Vec<Block> {
+    let extension = path.extension().and_then(|e| e.to_str()).unwrap_or("");
+    let content = match read_source_file(path) {
+        Ok(content) => content,
+        Err(_) => return Vec::new(),
+    };
+    let tree = match parse_source(&content, extension) {
+        Ok(tree) => tree,
+        Err(_) => return Vec::new(),
+    };
+    let file_lines: Vec<&str> = content.lines().collect();
+    let code_blocks = collect_code_blocks(&tree, &content, extension, options.allow_tests);
+
+    // Blocks come before the blocks nested in them; only the innermost ones are compared,
+    // so a class isn't reported along with its methods
+    let innermost: Vec<bool> = (0..code_blocks.len())
+        .map(|index| {
+            let block = &code_blocks[index];
+            code_blocks.get(index + 1).is_none_or(|next| {
+                next.start_byte < block.start_byte || next.end_byte > block.end_byte
+            })
+        })
+        .collect();
+
+    let file = path.to_string_lossy().to_string();
+    let mut seen = HashSet::new();
+    code_blocks
+        .into_iter()
+        .zip(innermost)
+        .filter(|(_, innermost)| *innermost)
+        .map(|(block, _)| block)
+        .filter(|block| block.end_row < file_lines.len())
+        .filter(|block| block.end_row - block.start_row + 1 >= options.min_lines)
+        .filter(|block| seen.insert((block.start_row, block.end_row)))
+        .filter_map(|block| {
+            let code = file_lines[block.start_row..=block.end_row].join("\n");
+            let symbol = block_definition_node(&tree, extension, block.start_row, block.end_row)
+                .and_then(|node| node_symbol_name(node, content.as_bytes()));
+            Block::new(
+                file.clone(),
+                (block.start_row + 1, block.end_row + 1),
+                block.node_type,
+                symbol,
+                code,
+            )
+        })
+        .collect()
+}
+
+/// The code blocks of every file under the searched path
+fn collect_blocks(options: &SimilarityOptions) -> Result<Vec<Block>> {
+    if !options.path.exists() {
+        anyhow::bail!("Path does not exist: {}", options.path.display());
+    }
+    let files = if options.path.is_file() {
+        vec![options.path.to_path_buf()]
+    } else {
+        file_list_cache::get_file_list(options.path, options.allow_tests, options.ignore)?
+            .files
+            .clone()
+    };
+    Ok(files
+        .par_iter()
+        .flat_map_iter(|file| file_blocks(file, options))
+        .collect())
+}
+
+/// Find the code blocks similar to a target, given as `file#symbol`, `file:line` or a
+/// line range, most similar first
+pub fn find_similar(target: &str, options: &SimilarityOptions) -> Result<Vec<SimilarBlock>> {
+    let (path, start_line, end_line, symbol, lines) = parse_file_with_line(target, true, &[])
+        .into_iter()
+        .next()
+        .with_context(|| {
+            format!(
+                "Invalid target '{}', expected file#symbol or file:line",
+                target
+            )
+        })?;
+    let extracted = process_file_for_extraction(
+        &path,
+        start_line,
+        end_line,
+        symbol.as_deref(),
+        true,
+        0,
+        lines.as_ref(),
+    )?;
+    let target_block = Block::new(
+        path.to_string_lossy().to_string(),
+        extracted.lines,
+        extracted.node_type,
+        extracted.symbol,
+        extracted.code,
+    )
+    .with_context(|| format!("'{}' is too small to compare", target))?;
+    let target_path = path.canonicalize().unwrap_or(path);
+
+    let mut results: Vec<SimilarBlock> = collect_blocks(options)?
+        .par_iter()
+        .filter(|block| {
+            let same_file = Path::new(&block.file)
+                .canonicalize()
+                .map(|p| p == target_path)
+                .unwrap_or(false);
+            !(same_file
+                && block.lines.0 <= target_block.lines.1
+                && target_block.lines.0 <= block.lines.1)
+        })
+        .filter_map(|block| {
+            let similarity = jaccard(&target_block.shingles, &block.shingles);
+            (similarity >= options.threshold).then(|| block.to_similar(similarity))
+        })
+        .collect();
+
+    results.sort_by(|a, b| {
+        b.similarity
+            .total_cmp(&a.similarity)
+            .then_with(|| a.file.cmp(&b.file))
+            .then_with(|| a.lines.cmp(&b.lines))
+    });
+    results.truncate(options.max_results);
+    Ok(results)
+}
+
+/// Find clusters of near-duplicate code blocks, the most similar clusters first
+pub fn find_duplicates(options: &SimilarityOptions) -> Result<Vec<DuplicateCluster>> {
+    let blocks = collect_blocks(options)?;
+    let signatures: Vec<Vec<u64>> = blocks
+        .par_iter()
+        .map(|block| minhash_signature(&block.shingles))
+        .collect();
+
+    // Blocks whose signatures agree on all rows of a band are candidate pairs
+    let rows = rows_per_band(options.threshold);
+    let mut candidates: HashSet<(usize, usize)> = HashSet::new();
+    for band in 0..SIGNATURE_SIZE / rows {
+        let mut buckets: HashMap<u64, Vec<usize>> = HashMap::new();
+        for (index, signature) in signatures.iter().enumerate() {
+            let key = hash_value(&signature[band * rows..(band + 1) * rows]);
+            buckets.entry(key).or_default().push(index);
+        }
+        for bucket in buckets.values().filter(|bucket| bucket.len() > 1) {
+            for (n, &a) in bucket.iter().enumerate() {
+                for &b in &bucket[n + 1..] {
+                    candidates.insert((a, b));
+                }
+            }
+        }
+    }
+
+    let pairs: Vec<(usize, usize, f64)> = candidates
+        .into_par_iter()
+        .filter(|&(a, b)| !blocks[a].overlaps(&blocks[b]))
+        .map(|(a, b)| (a, b, jaccard(&blocks[a].shingles, &blocks[b].shingles)))
+        .filter(|&(_, _, similarity)| similarity >= options.threshold)
+        .collect();
+
+    // Union-find over the similar pairs
+    let mut parent: Vec<usize> = (0..blocks.len()).collect();
+    fn root(parent: &mut [usize], mut index: usize) -> usize {
+        while parent[index] != index {
+            parent[index] = parent[parent[index]];
+            index = parent[index];
+        }
+        index
+    }
+    let mut closest: HashMap<usize, f64> = HashMap::new();
+    for &(a, b, similarity) in &pairs {
+        let (root_a, root_b) = (root(&mut parent, a), root(&mut parent, b));
+        parent[root_a.max(root_b)] = root_a.min(root_b);
+        for index in [a, b] {
+            let best = closest.entry(index).or_insert(0.0);
+            *best = best.max(similarity);
+        }
+    }
+
+    let mut members: HashMap<usize, Vec<usize>> = HashMap::new();
+    let mut indices: Vec<usize> = closest.keys().copied().collect();
+    indices.sort_unstable();
+    for index in indices {
+        let cluster_root = root(&mut parent, index);
+        members.entry(cluster_root).or_default().push(index);
+    }
+
+    let mut clusters: Vec<DuplicateCluster> = members
+        .into_values()
+        .map(|indices| {
+            let mut cluster_blocks: Vec<SimilarBlock> = indices
+                .iter()
+                .map(|&index| blocks[index].to_similar(closest[&index]))
+                .collect();
+            cluster_blocks.sort_by(|a, b| a.file.cmp(&b.file).then(a.lines.cmp(&b.lines)));
+            DuplicateCluster {
+                similarity: cluster_blocks
+                    .iter()
+                    .map(|block| block.similarity)
+                    .fold(1.0, f64::min),
+                blocks: cluster_blocks,
+            }
+        })
+        .collect();
+
+    clusters.sort_by(|a, b| {
+        b.similarity
+            .total_cmp(&a.similarity)
+            .then_with(|| b.blocks.len().cmp(&a.blocks.len()))
+            .then_with(|| a.blocks[0].file.cmp(&b.blocks[0].file))
+            .then_with(|| a.blocks[0].lines.cmp(&b.blocks[0].lines))
+    });
+    clusters.truncate(options.max_results);
+    Ok(clusters)
+}
+
+/// Describe where a block is: "file:start-end symbol (node type)"
+fn block_location(block: &SimilarBlock) -> String {
+    let mut location = format!("{}:{}-{}", block.file, block.lines.0, block.lines.1);
+    if let Some(symbol) = &block.symbol {
+        location.push_str(&format!(" {}", symbol));
+    }
+    location.push_str(&format!(" ({})", block.node_type));
+    location
+}
+
+fn code_language(block: &SimilarBlock) -> &str {
+    Path::new(&block.file)
+        .extension()
+        .and_then(|e| e.to_str())
+        .unwrap_or("")
+}
+
+/// Format and print the blocks similar to a target
+pub fn format_and_print_similar_blocks(blocks: &[SimilarBlock], format: &str) -> Result<()> {
+    match format {
+        "json" => {
+            let wrapper = serde_json::json!({
+                "results": blocks,
+                "summary": { "count": blocks.len() }
+            });
+            println!("{}", serde_json::to_string_pretty(&wrapper)?);
+        }
+        "jsonl" => {
+            for block in blocks {
+                println!("{}", json_line("similar", block)?);
+            }
+            println!(
+                "{}",
+                json_line("summary", &serde_json::json!({ "count": blocks.len() }))?
+            );
+        }
+        "markdown" => {
+            for block in blocks {
+                println!(
+                    "**{}** similarity {:.2}",
+                    block_location(block),
+                    block.similarity
+                );
+                println!();
+                println!("```{}", code_language(block));
+                println!("{}", block.code.trim_end());
+                println!("```");
+                println!();
+            }
+        }
+        "plain" => {
+            for block in blocks {
+                println!("{:.2} {}", block.similarity, block_location(block));
+                println!("{}", block.code.trim_end());
+                println!();
+            }
+        }
+        _ => {
+            for block in blocks {
+                println!(
+                    "{} {}",
+                    format!("{:.2}", block.similarity).bold().green(),
+                    block_location(block).cyan()
+                );
+                println!("{}", block.code.trim_end());
+                println!();
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Format and print clusters of duplicated code
+pub fn format_and_print_duplicate_clusters(
+    clusters: &[DuplicateCluster],
+    format: &str,
+) -> Result<()> {
+    match format {
+        "json" => {
+            let wrapper = serde_json::json!({
+                "clusters": clusters,
+                "summary": { "count": clusters.len() }
+            });
+            println!("{}", serde_json::to_string_pretty(&wrapper)?);
+        }
+        "jsonl" => {
+            for cluster in clusters {
+                println!("{}", json_line("cluster", cluster)?);
+            }
+            println!(
+                "{}",
+                json_line("summary", &serde_json::json!({ "count": clusters.len() }))?
+            );
+        }
+        "markdown" => {
+            for (number, cluster) in clusters.iter().enumerate() {
+                println!(
+                    "### Cluster {} (similarity {:.2}, {} blocks)",
+                    number + 1,
+                    cluster.similarity,
+                    cluster.blocks.len()
+                );
+                println!();
+                for block in &cluster.blocks {
+                    println!("- `{}` {:.2}", block_location(block), block.similarity);
+                }
+                println!();
+            }
+        }
+        "plain" => {
+            for (number, cluster) in clusters.iter().enumerate() {
+                println!(
+                    "Cluster {} (similarity {:.2}, {} blocks)",
+                    number + 1,
+                    cluster.similarity,
+                    cluster.blocks.len()
+                );
+                for block in &cluster.blocks {
+                    println!("  {:.2} {}", block.similarity, block_location(block));
+                }
+                println!();
+            }
+        }
+        _ => {
+            for (number, cluster) in clusters.iter().enumerate() {
+                println!(
+                    "{} {}",
+                    format!("Cluster {}", number + 1).bold(),
+                    format!(
+                        "(similarity {:.2}, {} blocks)",
+                        cluster.similarity,
+                        cluster.blocks.len()
+                    )
+                    .dimmed()
+                );
+                for block in &cluster.blocks {
+                    println!(
+                        "  {} {}",
+                        format!("{:.2}", block.similarity).green(),
+                        block_location(block).cyan()
+                    );
+                }
+                println!();
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Handle the similar command
+pub fn handle_similar(target: &str, options: &SimilarityOptions, format: &str) -> Result<()> {
+    let start_time = Instant::now();
+    let results = find_similar(target, options)?;
+    let duration = start_time.elapsed();
+
+    if !is_structured_format(format) {
+        if results.is_empty() {
+            println!("{}", "No similar code found.".yellow().bold());
+            println!("Search completed in {:.2?}", duration);
+            return Ok(());
+        }
+        println!("Found {} similar blocks in {:.2?}", results.len(), duration);
+        println!();
+    }
+    format_and_print_similar_blocks(&results, format)
+}
+
+/// Handle the dupes command
+pub fn handle_dupes(options: &SimilarityOptions, format: &str) -> Result<()> {
+    let start_time = Instant::now();
+    let clusters = find_duplicates(options)?;
+    let duration = start_time.elapsed();
+
+    if !is_structured_format(format) {
+        if clusters.is_empty() {
+            println!("{}", "No duplicated code found.".yellow().bold());
+            println!("Search completed in {:.2?}", duration);
+            return Ok(());
+        }
+        println!(
+            "Found {} clusters of duplicated code in {:.2?}",
+            clusters.len(),
+            duration
+        );
+        println!();
+    }
+    format_and_print_duplicate_clusters(&clusters, format)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_renamed_copies_have_the_same_shingles() {
+        let original = "fn total(items: &[Item]) -> u64 {\n    // Sum the prices\n    let mut sum = 0;\n    for item in items {\n        sum += item.price * 2;\n    }\n    sum\n}";
+        let renamed = "fn cost(lines: &[Line]) -> u64 {\n    let mut acc = 10;\n    for line in lines {\n        acc += line.amount * 3;\n    }\n    acc\n}";
+        let different = "fn label(&self) -> String {\n    match self.kind {\n        Kind::A => \"a\".to_string(),\n        _ => format!(\"{}\", self.name),\n    }\n}";
+
+        let tokens = normalized_tokens(original, "rs");
+        assert_eq!(&tokens[..4], ["fn", "ID", "(", "ID"]);
+        assert!(!tokens.iter().any(|t| t == "Sum"));
+
+        let original = shingles(&tokens);
+        let renamed = shingles(&normalized_tokens(renamed, "rs"));
+        let different = shingles(&normalized_tokens(different, "rs"));
+        assert_eq!(jaccard(&original, &renamed), 1.0);
+        assert!(jaccard(&original, &different) < 0.3);
+    }
+
+    #[test]
+    fn test_lifetimes_are_not_strings() {
+        assert_eq!(
+            normalized_tokens("fn f<'a>(s: &'a str) -> char { 'x' }", "rs"),
+            [
+                "fn", "ID", "<", "'", "ID", ">", "(", "ID", ":", "&", "'", "ID", "ID", ")", "-",
+                ">", "ID", "{", "STR", "}"
+            ]
+        );
+    }
+}
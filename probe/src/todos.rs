@@ -0,0 +1,699 @@
+//! Harvesting of `TODO`, `FIXME` and other work markers from code comments.
+//!
+//! `probe todos` parses every file and only looks at the comment nodes of its syntax tree,
+//! so markers in strings and identifiers are not reported. Each marker is reported with the
+//! symbol enclosing it, the issues it references and, when the file is in a git
+//! repository, the author and date of the line from `git blame`.
+
+use anyhow::Result;
+use colored::*;
+use once_cell::sync::Lazy;
+use rayon::prelude::*;
+use regex::Regex;
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+use std::path::Path;
+use std::process::Command;
+use std::time::Instant;
+use tree_sitter::Node;
+
+use crate::language::factory::get_language_impl;
+use crate::language::language_trait::LanguageImpl;
+use crate::language::parser::{node_symbol_name, parse_source};
+use crate::search::file_list_cache;
+use crate::search::source_files::read_source_file;
+use crate::search::{is_structured_format, json_line};
+
+/// Tags harvested in addition to the custom ones
+pub const DEFAULT_TAGS: &[&str] = &["TODO", "FIXME", "HACK", "XXX"];
+
+/// Issue references: issue and pull request URLs, `owner/repo#123`, `#123` and
+/// tracker keys such as `PROJ-123`
+static ISSUE_REFERENCE: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(
+        r"https?://[^\s)>\]]+/(?:issues|pull|pulls|merge_requests)/\d+|(?:[\w.-]+/[\w.-]+)?#\d+\b|\b[A-Z][A-Z0-9]+-\d+\b",
+    )
+    .unwrap()
+});
+
+/// Prefixes of `ABC-123` words that are standards rather than tracker keys
+const NOT_ISSUE_KEYS: &[&str] = &["UTF", "ISO", "SHA", "RFC", "AES", "MD", "CVE", "X"];
+
+/// Options of a todos harvest
+pub struct TodosOptions<'a> {
+    /// Directory or file to harvest
+    pub path: &'a Path,
+    /// Custom tags, harvested in addition to the default ones
+    pub tags: &'a [String],
+    /// Whether to look up the author and date of every marker with `git blame`
+    pub blame: bool,
+    /// Custom patterns of files to ignore
+    pub ignore: &'a [String],
+    /// Whether test files and test code are harvested
+    pub allow_tests: bool,
+}
+
+/// The last commit that changed the line of a marker
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct TodoBlame {
+    pub commit: String,
+    pub author: String,
+    /// Author date in ISO 8601 format
+    pub date: String,
+}
+
+/// A work marker found in a comment
+#[derive(Debug, Clone, Serialize)]
+pub struct TodoItem {
+    pub file: String,
+    pub line: usize,
+    /// 1-based column of the tag
+    pub column: usize,
+    pub tag: String,
+    /// The text after the tag, up to the end of the comment line
+    pub text: String,
+    /// The name in parentheses after the tag, as in `TODO(alice)`; issues in the
+    /// parentheses, as in `TODO(#12)`, are listed in `issues` instead
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub owner: Option<String>,
+    /// Name of the symbol enclosing the comment
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub symbol: Option<String>,
+    /// Node type of the code block enclosing the comment
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub node_type: Option<String>,
+    /// Issues referenced in the text, in order of appearance
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub issues: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub blame: Option<TodoBlame>,
+}
+
+/// Patterns of the tags, capturing the tag and the owner in `TODO(alice)`
+struct TagPattern {
+    /// A tag starting the text of a comment line
+    leading: Regex,
+    /// A tag elsewhere in the line, which needs an owner or a `:` to tell it from prose
+    /// such as "the FIXME markers"
+    anywhere: Regex,
+}
+
+impl TagPattern {
+    fn new(tags: &[String]) -> Result<Self> {
+        let mut alternatives: Vec<String> = DEFAULT_TAGS
+            .iter()
+            .map(|tag| tag.to_string())
+            .chain(tags.iter().cloned())
+            .filter(|tag| !tag.is_empty())
+            .map(|tag| regex::escape(&tag))
+            .collect();
+        alternatives.sort();
+        alternatives.dedup();
+        let tags = alternatives.join("|");
+        Ok(TagPattern {
+            leading: Regex::new(&format!(
+                r"^[\s/*#!;<@{{%-]*({})(?:\(([^)]*)\))?(?:[:\s]|$)",
+                tags
+            ))?,
+            anywhere: Regex::new(&format!(r"(?:^|[^\w`])({})(?:\(([^)]*)\):?|:)", tags))?,
+        })
+    }
+
+    fn captures<'t>(&self, line: &'t str) -> Option<regex::Captures<'t>> {
+        self.leading
+            .captures(line)
+            .or_else(|| self.anywhere.captures(line))
+    }
+}
+
+/// Issues referenced in a text
+fn issue_references(text: &str) -> Vec<String> {
+    let mut issues: Vec<String> = Vec::new();
+    for reference in ISSUE_REFERENCE.find_iter(text) {
+        let reference = reference.as_str();
+        let key = reference.split('-').next().unwrap_or("");
+        let is_tracker_key = !reference.contains('#') && !reference.contains("://");
+        if is_tracker_key && NOT_ISSUE_KEYS.contains(&key) {
+            continue;
+        }
+        if !issues.iter().any(|issue| issue == reference) {
+            issues.push(reference.to_string());
+        }
+    }
+    issues
+}
+
+/// Strip the comment syntax closing a comment line, such as `*/` or `-->`
+fn trim_comment_end(text: &str) -> &str {
+    text.trim()
+        .trim_end_matches("*/")
+        .trim_end_matches("-->")
+        .trim_end_matches("#}")
+        .trim_end()
+}
+
+/// Strip the comment syntax opening a continuation line, such as `*` or `//`
+fn trim_comment_start(text: &str) -> &str {
+    let text = text.trim_start();
+    ["///", "//!", "//", "--", "#", ";", "*"]
+        .iter()
+        .find_map(|prefix| text.strip_prefix(prefix))
+        .unwrap_or(text)
+}
+
+/// A marker found in a line of a comment
+#[derive(Debug, PartialEq)]
+struct Marker {
+    line: usize,
+    column: usize,
+    tag: String,
+    owner: Option<String>,
+    /// Issues in the parentheses after the tag, then those in the text
+    issues: Vec<String>,
+    text: String,
+}
+
+/// Split the parentheses after a tag into its owner and the issues it references, so
+/// that `TODO(#12)` and `TODO(PAY-7)` name an issue rather than an owner
+fn owner_and_issues(parenthetical: &str) -> (Option<String>, Vec<String>) {
+    let issues = issue_references(parenthetical);
+    let mut owner = parenthetical.to_string();
+    for issue in &issues {
+        owner = owner.replace(issue.as_str(), "");
+    }
+    let owner = owner.trim_matches(|c: char| c.is_whitespace() || matches!(c, ',' | ';' | '/'));
+    ((!owner.is_empty()).then(|| owner.to_string()), issues)
+}
+
+/// The markers of a comment node, starting at line `row` (0-based)
+fn comment_markers(
+    comment: &str,
+    row: usize,
+    start_column: usize,
+    pattern: &TagPattern,
+) -> Vec<Marker> {
+    let mut markers: Vec<Marker> = Vec::new();
+    for (offset, line) in comment.lines().enumerate() {
+        let Some(captures) = pattern.captures(line) else {
+            // Lines after a marker continue its text, up to the next marker
+            if let Some(marker) = markers.last_mut() {
+                let text = trim_comment_end(trim_comment_start(line));
+                if !text.is_empty() {
+                    if !marker.text.is_empty() {
+                        marker.text.push(' ');
+                    }
+                    marker.text.push_str(text);
+                    for issue in issue_references(text) {
+                        if !marker.issues.contains(&issue) {
+                            marker.issues.push(issue);
+                        }
+                    }
+                }
+            }
+            continue;
+        };
+        let tag = captures.get(1).unwrap();
+        let column = if offset == 0 {
+            start_column + line[..tag.start()].chars().count() + 1
+        } else {
+            line[..tag.start()].chars().count() + 1
+        };
+        let (owner, mut issues) = captures
+            .get(2)
+            .map(|parenthetical| owner_and_issues(parenthetical.as_str().trim()))
+            .unwrap_or_default();
+        let rest = &line[captures.get(0).unwrap().end()..];
+        let text = trim_comment_end(rest.trim_start_matches([':', ' ', '\t']));
+        for issue in issue_references(text) {
+            if !issues.contains(&issue) {
+                issues.push(issue);
+            }
+        }
+        markers.push(Marker {
+            line: row + offset + 1,
+            column,
+            tag: tag.as_str().to_string(),
+            owner,
+            issues,
+            text: text.to_string(),
+        });
+    }
+    markers
+}
+
+#[allow(clippy::too_many_arguments)]
+fn visit<'a>(
+    node: Node<'a>,
+    enclosing: Option<Node<'a>>,
+    source: &[u8],
+    file: &str,
+    language_impl: &dyn LanguageImpl,
+    pattern: &TagPattern,
+    allow_tests: bool,
+    items: &mut Vec<TodoItem>,
+) {
+    if node.kind().contains("comment") {
+        let Ok(comment) = node.utf8_text(source) else {
+            return;
+        };
+        let (symbol, node_type) = match enclosing {
+            Some(block) => (
+                node_symbol_name(block, source),
+                Some(block.kind().to_string()),
+            ),
+            None => (None, None),
+        };
+        let position = node.start_position();
+        for marker in comment_markers(comment, position.row, position.column, pattern) {
+            items.push(TodoItem {
+                file: file.to_string(),
+                line: marker.line,
+                column: marker.column,
+                tag: marker.tag,
+                issues: marker.issues,
+                text: marker.text,
+                owner: marker.owner,
+                symbol: symbol.clone(),
+                node_type: node_type.clone(),
+                blame: None,
+            });
+        }
+        return;
+    }
+
+    let mut enclosing = enclosing;
+    if language_impl.is_acceptable_parent(&node) {
+        if !allow_tests && language_impl.is_test_node(&node, source) {
+            return;
+        }
+        enclosing = Some(node);
+    }
+    let mut cursor = node.walk();
+    for child in node.children(&mut cursor) {
+        visit(
+            child,
+            enclosing,
+            source,
+            file,
+            language_impl,
+            pattern,
+            allow_tests,
+            items,
+        );
+    }
+}
+
+/// Days since 1970-01-01 to a (year, month, day) date of the proleptic Gregorian calendar
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let day_of_era = z.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = (day_of_year - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    (year, month, day)
+}
+
+/// Format a Unix timestamp and a `+hhmm` time zone as an ISO 8601 date, as `git log %aI`
+fn iso_date(timestamp: i64, timezone: &str) -> String {
+    let (sign, digits) = timezone.split_at(timezone.len().min(1));
+    let hours: i64 = digits.get(..2).and_then(|h| h.parse().ok()).unwrap_or(0);
+    let minutes: i64 = digits.get(2..4).and_then(|m| m.parse().ok()).unwrap_or(0);
+    let offset = (hours * 60 + minutes) * 60 * if sign == "-" { -1 } else { 1 };
+    let local = timestamp + offset;
+    let (year, month, day) = civil_from_days(local.div_euclid(86_400));
+    let seconds = local.rem_euclid(86_400);
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}{}{:02}:{:02}",
+        year,
+        month,
+        day,
+        seconds / 3600,
+        seconds % 3600 / 60,
+        seconds % 60,
+        if sign == "-" { "-" } else { "+" },
+        hours,
+        minutes
+    )
+}
+
+/// Parse `git blame --line-porcelain` output into the blame of every final line
+fn parse_blame(output: &str) -> HashMap<usize, TodoBlame> {
+    let mut blames = HashMap::new();
+    let mut header: Option<(String, usize)> = None;
+    let (mut author, mut time, mut timezone) = (String::new(), 0i64, String::new());
+    for line in output.lines() {
+        if line.starts_with('\t') {
+            if let Some((commit, final_line)) = header.take() {
+                // Lines changed in the working tree have not been committed yet
+                if !commit.chars().all(|c| c == '0') {
+                    blames.insert(
+                        final_line,
+                        TodoBlame {
+                            commit,
+                            author: author.clone(),
+                            date: iso_date(time, &timezone),
+                        },
+                    );
+                }
+            }
+        } else if let Some(value) = line.strip_prefix("author ") {
+            author = value.to_string();
+        } else if let Some(value) = line.strip_prefix("author-time ") {
+            time = value.parse().unwrap_or(0);
+        } else if let Some(value) = line.strip_prefix("author-tz ") {
+            timezone = value.to_string();
+        } else if header.is_none() {
+            let mut fields = line.split(' ');
+            let commit = fields.next().unwrap_or("");
+            let final_line = fields.nth(1).and_then(|n| n.parse().ok());
+            if commit.len() >= 40 && commit.chars().all(|c| c.is_ascii_hexdigit()) {
+                if let Some(final_line) = final_line {
+                    header = Some((commit.to_string(), final_line));
+                }
+            }
+        }
+    }
+    blames
+}
+
+/// Blame the given lines of a file; empty when git or the repository is not available
+fn blame_lines(path: &Path, lines: &[usize]) -> HashMap<usize, TodoBlame> {
+    let (Some(dir), Some(name)) = (path.parent(), path.file_name()) else {
+        return HashMap::new();
+    };
+    let dir = if dir.as_os_str().is_empty() {
+        Path::new(".")
+    } else {
+        dir
+    };
+    let mut command = Command::new("git");
+    command
+        .arg("-C")
+        .arg(dir)
+        .args(["blame", "--line-porcelain"]);
+    for line in lines {
+        command.arg(format!("-L{},{}", line, line));
+    }
+    command.arg("--").arg(name);
+    match command.output() {
+        Ok(output) if output.status.success() => {
+            parse_blame(&String::from_utf8_lossy(&output.stdout))
+        }
+        _ => HashMap::new(),
+    }
+}
+
+/// The markers of a file
+fn file_todos(path: &Path, pattern: &TagPattern, options: &TodosOptions) -> Vec<TodoItem> {
+    let extension = path.extension().and_then(|e| e.to_str()).unwrap_or("");
+    let Some(language_impl) = get_language_impl(extension) else {
+        return Vec::new();
+    };
+    let Ok(content) = read_source_file(path) else {
+        return Vec::new();
+    };
+    let Ok(tree) = parse_source(&content, extension) else {
+        return Vec::new();
+    };
+
+    let file = path.to_string_lossy().to_string();
+    let mut items = Vec::new();
+    visit(
+        tree.root_node(),
+        None,
+        content.as_bytes(),
+        &file,
+        language_impl.as_ref(),
+        pattern,
+        options.allow_tests,
+        &mut items,
+    );
+
+    if options.blame && !items.is_empty() {
+        let mut lines: Vec<usize> = items.iter().map(|item| item.line).collect();
+        lines.dedup();
+        let blames = blame_lines(path, &lines);
+        for item in &mut items {
+            item.blame = blames.get(&item.line).cloned();
+        }
+    }
+    items
+}
+
+/// Harvest the markers under a path, sorted by file, line and column
+pub fn find_todos(options: &TodosOptions) -> Result<Vec<TodoItem>> {
+    if !options.path.exists() {
+        anyhow::bail!("Path does not exist: {}", options.path.display());
+    }
+    let pattern = TagPattern::new(options.tags)?;
+    let files = if options.path.is_file() {
+        vec![options.path.to_path_buf()]
+    } else {
+        file_list_cache::get_file_list(options.path, options.allow_tests, options.ignore)?
+            .files
+            .clone()
+    };
+
+    let mut items: Vec<TodoItem> = files
+        .par_iter()
+        .flat_map_iter(|file| file_todos(file, &pattern, options))
+        .collect();
+    items.sort_by(|a, b| {
+        a.file
+            .cmp(&b.file)
+            .then(a.line.cmp(&b.line))
+            .then(a.column.cmp(&b.column))
+    });
+    Ok(items)
+}
+
+/// The number of markers of every tag
+fn tag_counts(items: &[TodoItem]) -> BTreeMap<&str, usize> {
+    let mut counts = BTreeMap::new();
+    for item in items {
+        *counts.entry(item.tag.as_str()).or_insert(0) += 1;
+    }
+    counts
+}
+
+/// Describe where a marker is: the file, line and symbol
+fn item_location(item: &TodoItem) -> String {
+    let mut location = format!("{}:{}", item.file, item.line);
+    if let Some(symbol) = &item.symbol {
+        location.push_str(&format!(" in {}", symbol));
+    }
+    location
+}
+
+/// The tag with its owner, as in `TODO(alice)`
+fn item_tag(item: &TodoItem) -> String {
+    match &item.owner {
+        Some(owner) => format!("{}({})", item.tag, owner),
+        None => item.tag.clone(),
+    }
+}
+
+/// The author and date of a marker, and the issues it references
+fn item_details(item: &TodoItem) -> String {
+    let mut details = Vec::new();
+    if !item.issues.is_empty() {
+        details.push(item.issues.join(", "));
+    }
+    if let Some(blame) = &item.blame {
+        let date = blame.date.split('T').next().unwrap_or(&blame.date);
+        details.push(format!("{}, {}", blame.author, date));
+    }
+    if details.is_empty() {
+        String::new()
+    } else {
+        format!(" ({})", details.join("; "))
+    }
+}
+
+/// Format and print harvested markers
+pub fn format_and_print_todos(items: &[TodoItem], format: &str) -> Result<()> {
+    match format {
+        "json" => {
+            let wrapper = serde_json::json!({
+                "results": items,
+                "summary": {
+                    "count": items.len(),
+                    "tags": tag_counts(items)
+                }
+            });
+            println!("{}", serde_json::to_string_pretty(&wrapper)?);
+        }
+        "jsonl" => {
+            for item in items {
+                println!("{}", json_line("todo", item)?);
+            }
+            let summary = serde_json::json!({ "count": items.len() });
+            println!("{}", json_line("summary", &summary)?);
+        }
+        "markdown" => {
+            for item in items {
+                println!(
+                    "- **{}** `{}`: {}{}",
+                    item_tag(item),
+                    item_location(item),
+                    item.text,
+                    item_details(item)
+                );
+            }
+        }
+        "plain" => {
+            for item in items {
+                println!(
+                    "{} {}: {}{}",
+                    item_location(item),
+                    item_tag(item),
+                    item.text,
+                    item_details(item)
+                );
+            }
+        }
+        _ => {
+            for item in items {
+                let tag = match item.tag.as_str() {
+                    "FIXME" | "XXX" => item_tag(item).red().bold(),
+                    "HACK" => item_tag(item).magenta().bold(),
+                    _ => item_tag(item).yellow().bold(),
+                };
+                println!(
+                    "{} {}: {}{}",
+                    item_location(item).cyan(),
+                    tag,
+                    item.text,
+                    item_details(item).dimmed()
+                );
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Handle the todos command
+pub fn handle_todos(options: &TodosOptions, format: &str) -> Result<()> {
+    let start_time = Instant::now();
+    let items = find_todos(options)?;
+    let duration = start_time.elapsed();
+
+    if !is_structured_format(format) {
+        if items.is_empty() {
+            println!("{}", "No markers found.".yellow().bold());
+            println!("Search completed in {:.2?}", duration);
+            return Ok(());
+        }
+        let counts: Vec<String> = tag_counts(&items)
+            .into_iter()
+            .map(|(tag, count)| format!("{} {}", count, tag))
+            .collect();
+        println!(
+            "Found {} markers ({}) in {:.2?}",
+            items.len(),
+            counts.join(", "),
+            duration
+        );
+        println!();
+    }
+    format_and_print_todos(&items, format)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_comment_markers() {
+        let pattern = TagPattern::new(&["NOTE".to_string()]).unwrap();
+        let comment = "/* TODO(alice): handle retries, see #42 and PAY-7 */";
+        let markers = comment_markers(comment, 9, 4, &pattern);
+        assert_eq!(
+            markers,
+            vec![Marker {
+                line: 10,
+                column: 8,
+                tag: "TODO".to_string(),
+                owner: Some("alice".to_string()),
+                issues: vec!["#42".to_string(), "PAY-7".to_string()],
+                text: "handle retries, see #42 and PAY-7".to_string(),
+            }]
+        );
+        assert!(issue_references("use UTF-8 and SHA-256").is_empty());
+
+        // Issue-shaped parentheses reference an issue instead of naming an owner
+        for (comment, owner, issues) in [
+            ("// TODO(#12): retry", None, vec!["#12"]),
+            ("// FIXME(PAY-7) see #3", None, vec!["PAY-7", "#3"]),
+            (
+                "// TODO(https://github.com/acme/shop/issues/9): retry",
+                None,
+                vec!["https://github.com/acme/shop/issues/9"],
+            ),
+            ("// TODO(alice, #12): retry", Some("alice"), vec!["#12"]),
+            ("// TODO(UTF-8): decode", Some("UTF-8"), vec![]),
+        ] {
+            let markers = comment_markers(comment, 0, 0, &pattern);
+            assert_eq!(markers[0].owner.as_deref(), owner, "{}", comment);
+            assert_eq!(markers[0].issues, issues, "{}", comment);
+        }
+
+        let comment = "// Not a TODOS or XXXL marker, nor a HACK\n// NOTE the cache is global\n// see HACK: below";
+        let markers = comment_markers(comment, 0, 0, &pattern);
+        let tags: Vec<(usize, &str)> = markers
+            .iter()
+            .map(|marker| (marker.line, marker.tag.as_str()))
+            .collect();
+        assert_eq!(tags, [(2, "NOTE"), (3, "HACK")]);
+        assert_eq!(markers[1].text, "below");
+
+        // A marker's text goes on to the end of the comment or the next marker
+        let comment = "/*\n * TODO: retry declined cards\n * with backoff, see #42\n *\n * FIXME: log the\n   attempts */";
+        let markers = comment_markers(comment, 0, 0, &pattern);
+        let texts: Vec<(usize, &str)> = markers
+            .iter()
+            .map(|marker| (marker.line, marker.text.as_str()))
+            .collect();
+        assert_eq!(
+            texts,
+            [
+                (2, "retry declined cards with backoff, see #42"),
+                (5, "log the attempts")
+            ]
+        );
+        assert_eq!(markers[0].issues, ["#42"]);
+    }
+
+    #[test]
+    fn test_parse_blame() {
+        let output = "0123456789012345678901234567890123456789 3 5 1\n\
+                      author Jane Doe\n\
+                      author-mail <jane@example.com>\n\
+                      author-time 1714550400\n\
+                      author-tz +0200\n\
+                      summary Add retries\n\
+                      filename src/lib.rs\n\
+                      \t// TODO: retry\n\
+                      0000000000000000000000000000000000000000 8 9 1\n\
+                      author Not Committed Yet\n\
+                      author-time 1714550400\n\
+                      author-tz +0000\n\
+                      filename src/lib.rs\n\
+                      \t// FIXME: new\n";
+        let blames = parse_blame(output);
+        assert_eq!(blames.len(), 1);
+        assert_eq!(
+            blames[&5],
+            TodoBlame {
+                commit: "0123456789012345678901234567890123456789".to_string(),
+                author: "Jane Doe".to_string(),
+                date: "2024-05-01T10:00:00+02:00".to_string(),
+            }
+        );
+    }
+}
@@ -0,0 +1,402 @@
+//! Watch mode of `search --watch` and `query --watch`.
+//!
+//! A `FileWatcher` waits for files under the searched paths to change and drops what the
+//! file list and tree caches hold about them, so that the caller can rerun its search.
+//! `reparse_changed_files` brings the cached trees and line maps of changed files up to
+//! date beforehand, so that the rerun doesn't have to rebuild them.
+//! `diff_results` then compares the new results with the previous ones, so that only the
+//! results that were added, changed or removed are printed.
+
+use anyhow::{bail, Context, Result};
+use notify::{Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use std::collections::{BTreeSet, HashMap};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{channel, Receiver, RecvTimeoutError};
+use std::time::Duration;
+
+use crate::language::incremental::reparse_incremental;
+use crate::language::tree_cache::{get_cached_content, invalidate_cache_entry, namespace_for_file};
+use crate::models::SearchResult;
+use crate::query::AstMatch;
+use crate::search::file_list_cache::invalidate_file_lists;
+use probe::workspace::{display_path, Workspace};
+
+/// How long to wait for more events after one arrives, so that saving several files at
+/// once, or an editor writing a temporary file and renaming it, causes a single rerun
+const DEBOUNCE: Duration = Duration::from_millis(200);
+
+/// Formats that can print a stream of changes
+const WATCH_FORMATS: &[&str] = &["color", "terminal", "markdown", "plain", "jsonl"];
+
+/// How a result of a rerun differs from the previous run
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ResultChange {
+    Added,
+    Changed,
+    Removed,
+}
+
+/// A result that can be told apart from the other results of a run
+pub trait WatchedResult {
+    /// File of the result, as printed
+    fn file(&self) -> String;
+    /// First line of the result, 1-based
+    fn first_line(&self) -> usize;
+    fn code(&self) -> &str;
+}
+
+impl WatchedResult for SearchResult {
+    fn file(&self) -> String {
+        display_path(self.workspace_root.as_deref(), &self.file)
+    }
+
+    fn first_line(&self) -> usize {
+        self.lines.0
+    }
+
+    fn code(&self) -> &str {
+        &self.code
+    }
+}
+
+impl WatchedResult for AstMatch {
+    fn file(&self) -> String {
+        display_path(self.root.as_deref(), &self.file_path.to_string_lossy())
+    }
+
+    fn first_line(&self) -> usize {
+        self.line_start
+    }
+
+    fn code(&self) -> &str {
+        &self.matched_text
+    }
+}
+
+/// Compare the results of a rerun with those of the previous run
+///
+/// A result with the same file and code as a previous one is unchanged, even when edits
+/// above it moved it. Of the other results, one that starts on the same line of the same
+/// file as a previous result changed it, and the rest were added. The previous results
+/// left over were removed; they come last.
+pub fn diff_results<'a, T: WatchedResult>(
+    previous: &'a [T],
+    current: &'a [T],
+) -> Vec<(ResultChange, &'a T)> {
+    let mut unchanged: HashMap<(String, &str), Vec<usize>> = HashMap::new();
+    for (index, result) in previous.iter().enumerate().rev() {
+        let key = (result.file(), result.code());
+        unchanged.entry(key).or_default().push(index);
+    }
+
+    let mut matched = vec![false; previous.len()];
+    let mut others = Vec::new();
+    for result in current {
+        let key = (result.file(), result.code());
+        match unchanged.get_mut(&key).and_then(Vec::pop) {
+            Some(index) => matched[index] = true,
+            None => others.push(result),
+        }
+    }
+
+    let mut replaced: HashMap<(String, usize), usize> = HashMap::new();
+    for (index, result) in previous.iter().enumerate() {
+        if !matched[index] {
+            let key = (result.file(), result.first_line());
+            replaced.entry(key).or_insert(index);
+        }
+    }
+
+    let mut changes = Vec::new();
+    for result in others {
+        match replaced.remove(&(result.file(), result.first_line())) {
+            Some(index) => {
+                matched[index] = true;
+                changes.push((ResultChange::Changed, result));
+            }
+            None => changes.push((ResultChange::Added, result)),
+        }
+    }
+    for (index, result) in previous.iter().enumerate() {
+        if !matched[index] {
+            changes.push((ResultChange::Removed, result));
+        }
+    }
+    changes
+}
+
+/// Fail unless `format` can print a stream of changes
+pub fn check_watch_format(format: &str) -> Result<()> {
+    if !WATCH_FORMATS.contains(&format) {
+        bail!(
+            "--watch can't print results as {}; use one of: {}",
+            format,
+            WATCH_FORMATS.join(", ")
+        );
+    }
+    Ok(())
+}
+
+/// The paths to watch: the roots of the workspace if there is one, or else `paths`
+pub fn watched_paths(workspace: Option<&Workspace>, paths: &[PathBuf]) -> Vec<PathBuf> {
+    match workspace {
+        Some(workspace) => workspace
+            .roots
+            .iter()
+            .map(|root| root.path.clone())
+            .collect(),
+        None => paths.to_vec(),
+    }
+}
+
+/// Drop what the caches hold about a changed file: the file lists of the directories it
+/// is in, and the syntax tree of a removed file
+///
+/// The tree of a file that still exists is kept: the next search notices the new content
+/// and reparses it from the old tree.
+fn invalidate_path(path: &Path) {
+    invalidate_file_lists(path);
+    if !path.exists() {
+        invalidate_cache_entry(&path.to_string_lossy());
+    }
+}
+
+/// Reparse the changed files that have a cached tree from that tree, so that a rerun
+/// finds their line maps updated instead of building them again
+///
+/// Errors are only reported in debug mode: the rerun parses the file itself.
+pub fn reparse_changed_files(paths: &[PathBuf], allow_tests: bool) {
+    let debug_mode = std::env::var("DEBUG").unwrap_or_default() == "1";
+    for path in paths {
+        let file_path = path.to_string_lossy();
+        let namespace = namespace_for_file(&file_path);
+        let Some(old_content) = get_cached_content(&namespace, &file_path) else {
+            continue;
+        };
+        let Ok(new_content) = std::fs::read_to_string(path) else {
+            continue;
+        };
+        match reparse_incremental(
+            &namespace,
+            &file_path,
+            &old_content,
+            &new_content,
+            allow_tests,
+        ) {
+            Ok(reparse) if debug_mode => println!(
+                "DEBUG: Reparsed {} ({} changed ranges, {} lines reused, {} recomputed{})",
+                file_path,
+                reparse.changed_ranges.len(),
+                reparse.reused_lines,
+                reparse.recomputed_lines,
+                if reparse.reused_tree {
+                    ""
+                } else {
+                    ", without a cached tree"
+                }
+            ),
+            Ok(_) => {}
+            Err(e) => {
+                if debug_mode {
+                    println!("DEBUG: Error reparsing {}: {}", file_path, e);
+                }
+            }
+        }
+    }
+}
+
+/// Watches paths recursively for changed files
+pub struct FileWatcher {
+    /// Dropping the watcher would stop the events
+    _watcher: RecommendedWatcher,
+    events: Receiver<notify::Result<Event>>,
+    /// Every watched path as it was given, with the absolute forms events may use for it
+    roots: Vec<(PathBuf, Vec<PathBuf>)>,
+}
+
+impl FileWatcher {
+    pub fn new(paths: &[PathBuf]) -> Result<Self> {
+        let (sender, events) = channel();
+        let mut watcher =
+            notify::recommended_watcher(sender).context("Failed to start the file watcher")?;
+
+        let mut roots = Vec::new();
+        for path in paths {
+            watcher
+                .watch(path, RecursiveMode::Recursive)
+                .with_context(|| format!("Failed to watch {}", path.display()))?;
+            let mut forms = Vec::new();
+            forms.extend(std::path::absolute(path).ok());
+            forms.extend(path.canonicalize().ok());
+            roots.push((path.clone(), forms));
+        }
+
+        Ok(FileWatcher {
+            _watcher: watcher,
+            events,
+            roots,
+        })
+    }
+
+    /// Block until files change, drop them from the caches and return them, written the
+    /// way the watched path they are in was given (e.g. `./src/main.rs` for `.`)
+    pub fn wait_for_changes(&self) -> Result<Vec<PathBuf>> {
+        loop {
+            let mut changed = BTreeSet::new();
+            let event = self.events.recv().context("The file watcher stopped")?;
+            self.collect(event, &mut changed);
+            loop {
+                match self.events.recv_timeout(DEBOUNCE) {
+                    Ok(event) => self.collect(event, &mut changed),
+                    Err(RecvTimeoutError::Timeout) => break,
+                    Err(RecvTimeoutError::Disconnected) => bail!("The file watcher stopped"),
+                }
+            }
+
+            if changed.is_empty() {
+                continue;
+            }
+            for path in &changed {
+                invalidate_path(path);
+            }
+            return Ok(changed.into_iter().collect());
+        }
+    }
+
+    /// Add the paths an event changed, leaving out reads and version control metadata
+    fn collect(&self, event: notify::Result<Event>, changed: &mut BTreeSet<PathBuf>) {
+        let debug_mode = std::env::var("DEBUG").unwrap_or_default() == "1";
+        let event = match event {
+            Ok(event) => event,
+            Err(e) => {
+                if debug_mode {
+                    println!("DEBUG: File watcher error: {}", e);
+                }
+                return;
+            }
+        };
+        if matches!(event.kind, EventKind::Access(_)) {
+            return;
+        }
+        for path in event.paths {
+            if path.components().any(|c| c.as_os_str() == ".git") {
+                continue;
+            }
+            if debug_mode {
+                println!("DEBUG: {:?} {:?}", event.kind, path);
+            }
+            changed.insert(self.as_given(&path));
+        }
+    }
+
+    /// A path reported by the watcher, written the way the watched path it is in was given
+    fn as_given(&self, path: &Path) -> PathBuf {
+        for (given, forms) in &self.roots {
+            for form in forms {
+                if let Ok(relative) = path.strip_prefix(form) {
+                    return if relative.as_os_str().is_empty() {
+                        given.clone()
+                    } else {
+                        given.join(relative)
+                    };
+                }
+            }
+        }
+        path.to_path_buf()
+    }
+}
+
+/// Print the files that triggered a rerun, for the human-readable formats
+pub fn print_changed_files(paths: &[PathBuf]) {
+    use colored::*;
+
+    let files: Vec<String> = paths.iter().map(|p| p.display().to_string()).collect();
+    println!();
+    println!("{} {}", "Files changed:".bold().green(), files.join(", "));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ast_match(file: &str, line: usize, text: &str) -> AstMatch {
+        AstMatch {
+            file_path: PathBuf::from(file),
+            line_start: line,
+            line_end: line,
+            column_start: 1,
+            column_end: text.len() + 1,
+            matched_text: text.to_string(),
+            root: None,
+        }
+    }
+
+    #[test]
+    fn test_diff_results() {
+        let previous = vec![
+            ast_match("a.rs", 1, "unwrap()"),
+            ast_match("a.rs", 5, "expect(\"x\")"),
+            ast_match("b.rs", 3, "unwrap()"),
+        ];
+        // A line was inserted at the top of a.rs, the expect was edited, b.rs lost its
+        // match and c.rs gained one
+        let current = vec![
+            ast_match("a.rs", 2, "unwrap()"),
+            ast_match("a.rs", 5, "expect(\"y\")"),
+            ast_match("c.rs", 7, "unwrap()"),
+        ];
+
+        let changes: Vec<(ResultChange, String, usize)> = diff_results(&previous, &current)
+            .into_iter()
+            .map(|(change, m)| (change, m.file(), m.first_line()))
+            .collect();
+        assert_eq!(
+            changes,
+            [
+                (ResultChange::Changed, "a.rs".to_string(), 5),
+                (ResultChange::Added, "c.rs".to_string(), 7),
+                (ResultChange::Removed, "b.rs".to_string(), 3),
+            ]
+        );
+
+        assert!(diff_results(&current, &current).is_empty());
+    }
+
+    #[test]
+    fn test_reparse_changed_files() {
+        use crate::language::tree_cache;
+
+        let _guard = tree_cache::acquire_test_mutex();
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().join("lib.rs");
+        let file_path = path.to_string_lossy().to_string();
+
+        let mut parser = tree_sitter::Parser::new();
+        parser
+            .set_language(&tree_sitter_rust::LANGUAGE.into())
+            .unwrap();
+        tree_cache::get_or_parse_tree(&file_path, "fn old() {}\n", &mut parser).unwrap();
+
+        std::fs::write(&path, "fn new() {}\n").unwrap();
+        reparse_changed_files(std::slice::from_ref(&path), false);
+        assert_eq!(
+            get_cached_content(&namespace_for_file(&file_path), &file_path).as_deref(),
+            Some("fn new() {}\n")
+        );
+
+        // Files without a cached tree are left for the rerun to parse
+        let other = dir.path().join("other.rs");
+        std::fs::write(&other, "fn other() {}\n").unwrap();
+        reparse_changed_files(std::slice::from_ref(&other), false);
+        assert_eq!(
+            get_cached_content(
+                &namespace_for_file(&other.to_string_lossy()),
+                &other.to_string_lossy()
+            ),
+            None
+        );
+
+        invalidate_cache_entry(&file_path);
+    }
+}
@@ -0,0 +1,295 @@
+//! Cross-repository workspaces.
+//!
+//! A workspace file names several root directories, each with its own ignore patterns
+//! and languages, so that search, extract and query can run across all of them at once:
+//!
+//! ```toml
+//! # team.toml
+//! ignore = ["*.generated.*"]
+//!
+//! [[roots]]
+//! name = "api"
+//! path = "../api"
+//! ignore = ["vendor/*"]
+//! languages = ["go"]
+//!
+//! [[roots]]
+//! name = "web"
+//! path = "../web"
+//! languages = ["typescript", "javascript"]
+//! ```
+//!
+//! Relative root paths are resolved against the directory containing the workspace file,
+//! so the same file works from any current directory. Root paths may also use the
+//! special formats understood by [`crate::path_resolver::resolve_path`] (e.g. `go:`).
+
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use std::path::{Component, Path, PathBuf};
+
+use crate::path_resolver::resolve_path;
+
+/// A loaded workspace definition
+#[derive(Debug, Clone)]
+pub struct Workspace {
+    /// Optional display name of the workspace
+    pub name: Option<String>,
+    /// Ignore patterns applied to every root
+    pub ignore: Vec<String>,
+    /// The named roots, in the order they were defined
+    pub roots: Vec<WorkspaceRoot>,
+}
+
+/// A named root directory within a workspace
+#[derive(Debug, Clone)]
+pub struct WorkspaceRoot {
+    /// Name used to label results from this root
+    pub name: String,
+    /// Canonical path of the root directory
+    pub path: PathBuf,
+    /// Ignore patterns applied to this root only
+    pub ignore: Vec<String>,
+    /// Languages to limit this root to (empty means all languages)
+    pub languages: Vec<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct WorkspaceFile {
+    name: Option<String>,
+    #[serde(default)]
+    ignore: Vec<String>,
+    #[serde(default)]
+    roots: Vec<WorkspaceRootEntry>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct WorkspaceRootEntry {
+    name: String,
+    path: String,
+    #[serde(default)]
+    ignore: Vec<String>,
+    #[serde(default)]
+    languages: Vec<String>,
+}
+
+impl Workspace {
+    /// Load a workspace definition from a TOML file
+    pub fn load(file: &Path) -> Result<Self> {
+        let content = std::fs::read_to_string(file)
+            .with_context(|| format!("Failed to read workspace file: {}", file.display()))?;
+        let file = file
+            .canonicalize()
+            .with_context(|| format!("Failed to resolve workspace file: {}", file.display()))?;
+        let base_dir = file.parent().unwrap_or(Path::new("/"));
+
+        Self::from_toml_str(&content, base_dir)
+            .with_context(|| format!("Invalid workspace file: {}", file.display()))
+    }
+
+    /// Parse a workspace definition, resolving relative root paths against `base_dir`
+    pub fn from_toml_str(content: &str, base_dir: &Path) -> Result<Self> {
+        let parsed: WorkspaceFile = toml::from_str(content)?;
+
+        if parsed.roots.is_empty() {
+            anyhow::bail!("Workspace must define at least one [[roots]] entry");
+        }
+
+        let mut roots: Vec<WorkspaceRoot> = Vec::with_capacity(parsed.roots.len());
+        for entry in parsed.roots {
+            if entry.name.is_empty() || entry.name.contains(['/', '\\']) {
+                anyhow::bail!("Invalid root name '{}'", entry.name);
+            }
+            if roots.iter().any(|root| root.name == entry.name) {
+                anyhow::bail!("Duplicate root name '{}'", entry.name);
+            }
+
+            let resolved = resolve_path(&entry.path)
+                .map_err(|e| anyhow::anyhow!("Failed to resolve root '{}': {}", entry.name, e))?;
+            let path = if resolved.is_relative() {
+                base_dir.join(resolved)
+            } else {
+                resolved
+            };
+            let path = path.canonicalize().with_context(|| {
+                format!("Root '{}' does not exist: {}", entry.name, path.display())
+            })?;
+
+            roots.push(WorkspaceRoot {
+                name: entry.name,
+                path,
+                ignore: entry.ignore,
+                languages: entry.languages,
+            });
+        }
+
+        Ok(Workspace {
+            name: parsed.name,
+            ignore: parsed.ignore,
+            roots,
+        })
+    }
+
+    /// Get a root by name
+    pub fn root(&self, name: &str) -> Option<&WorkspaceRoot> {
+        self.roots.iter().find(|root| root.name == name)
+    }
+
+    /// Ignore patterns for a root: the workspace-wide ones followed by the root's own
+    pub fn ignores_for(&self, root: &WorkspaceRoot) -> Vec<String> {
+        self.ignore.iter().chain(&root.ignore).cloned().collect()
+    }
+
+    /// Find the root containing `path` and the path relative to that root
+    ///
+    /// When roots are nested, the innermost root wins.
+    pub fn label_path(&self, path: &Path) -> Option<(&WorkspaceRoot, PathBuf)> {
+        let canonical = path.canonicalize().ok();
+        let path = canonical.as_deref().unwrap_or(path);
+
+        self.roots
+            .iter()
+            .filter_map(|root| {
+                path.strip_prefix(&root.path)
+                    .ok()
+                    .map(|relative| (root, relative.to_path_buf()))
+            })
+            .max_by_key(|(root, _)| root.path.components().count())
+    }
+
+    /// Map a `<root name>/<relative path>` reference to a path inside that root
+    ///
+    /// Returns None when the first component is not a root name, or when the
+    /// reference is an existing path relative to the current directory.
+    pub fn resolve_reference(&self, reference: &Path) -> Option<PathBuf> {
+        if reference.is_absolute() || reference.exists() {
+            return None;
+        }
+
+        let mut components = reference.components();
+        let root = match components.next()? {
+            Component::Normal(first) => self.root(first.to_str()?)?,
+            _ => return None,
+        };
+        let rest = components.as_path();
+        if rest.components().any(|c| matches!(c, Component::ParentDir)) {
+            return None;
+        }
+        Some(root.path.join(rest))
+    }
+
+    /// Human-readable summary, e.g. "team (api, web)"
+    pub fn describe(&self) -> String {
+        let names: Vec<&str> = self.roots.iter().map(|root| root.name.as_str()).collect();
+        match &self.name {
+            Some(name) => format!("{} ({})", name, names.join(", ")),
+            None => names.join(", "),
+        }
+    }
+}
+
+/// Format a file path for display, prefixed by its workspace root label if any
+pub fn display_path(root: Option<&str>, file: &str) -> String {
+    match root {
+        Some(root) => format!("[{}] {}", root, file),
+        None => file.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    fn create_roots() -> tempfile::TempDir {
+        let temp_dir = tempfile::tempdir().unwrap();
+        fs::create_dir_all(temp_dir.path().join("repos/api/src")).unwrap();
+        fs::create_dir_all(temp_dir.path().join("repos/web")).unwrap();
+        fs::create_dir_all(temp_dir.path().join("config")).unwrap();
+        temp_dir
+    }
+
+    #[test]
+    fn test_load_resolves_paths_relative_to_file() {
+        let temp_dir = create_roots();
+        let file = temp_dir.path().join("config/team.toml");
+        fs::write(
+            &file,
+            r#"
+name = "team"
+ignore = ["*.min.js"]
+
+[[roots]]
+name = "api"
+path = "../repos/api"
+ignore = ["vendor/*"]
+languages = ["go"]
+
+[[roots]]
+name = "web"
+path = "../repos/web"
+"#,
+        )
+        .unwrap();
+
+        let workspace = Workspace::load(&file).unwrap();
+        assert_eq!(workspace.describe(), "team (api, web)");
+
+        let api = workspace.root("api").unwrap();
+        assert_eq!(
+            api.path,
+            temp_dir.path().join("repos/api").canonicalize().unwrap()
+        );
+        assert_eq!(api.languages, vec!["go"]);
+        assert_eq!(workspace.ignores_for(api), vec!["*.min.js", "vendor/*"]);
+        assert!(workspace.root("web").unwrap().languages.is_empty());
+    }
+
+    #[test]
+    fn test_invalid_workspaces() {
+        let temp_dir = create_roots();
+        let base = temp_dir.path().join("repos");
+
+        assert!(Workspace::from_toml_str("", &base).is_err());
+        assert!(Workspace::from_toml_str(
+            "[[roots]]\nname = \"a\"\npath = \"api\"\n[[roots]]\nname = \"a\"\npath = \"web\"\n",
+            &base
+        )
+        .is_err());
+        assert!(
+            Workspace::from_toml_str("[[roots]]\nname = \"x\"\npath = \"missing\"\n", &base)
+                .is_err()
+        );
+        assert!(Workspace::from_toml_str(
+            "[[roots]]\nname = \"api\"\npath = \"api\"\nlanguage = \"go\"\n",
+            &base
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_label_and_resolve_reference() {
+        let temp_dir = create_roots();
+        let base = temp_dir.path().join("repos");
+        let workspace = Workspace::from_toml_str(
+            "[[roots]]\nname = \"api\"\npath = \"api\"\n[[roots]]\nname = \"web\"\npath = \"web\"\n",
+            &base,
+        )
+        .unwrap();
+
+        let file = base.join("api/src/main.go");
+        fs::write(&file, "package main\n").unwrap();
+        let (root, relative) = workspace.label_path(&file).unwrap();
+        assert_eq!(root.name, "api");
+        assert_eq!(relative, PathBuf::from("src/main.go"));
+        assert!(workspace.label_path(temp_dir.path()).is_none());
+
+        assert_eq!(
+            workspace.resolve_reference(Path::new("api/src/main.go")),
+            Some(workspace.root("api").unwrap().path.join("src/main.go"))
+        );
+        assert_eq!(workspace.resolve_reference(Path::new("other/x.rs")), None);
+        assert_eq!(workspace.resolve_reference(Path::new("api/../x.rs")), None);
+    }
+}
@@ -15,7 +15,7 @@ Here's the detailed breakdown of performance bottlenecks with full relative path
 "#;
 
     // Extract file paths from the text
-    let file_paths = extract::extract_file_paths_from_text(text, true);
+    let file_paths = extract::extract_file_paths_from_text(text, true, &[]);
 
     // Check that we found the expected file paths
     assert_eq!(
@@ -53,7 +53,7 @@ fn test_parse_file_with_apostrophes() {
     let input = "src/file.rs:10";
 
     // Parse the file path
-    let file_paths = extract::parse_file_with_line(input, true);
+    let file_paths = extract::parse_file_with_line(input, true, &[]);
 
     // We should find the file path
     assert_eq!(
@@ -75,7 +75,7 @@ fn test_parse_file_with_apostrophes() {
     // Now let's test that our fix for apostrophes works by creating a test
     // that verifies apostrophes in text don't break the extract_file_paths_from_text function
     let text_with_apostrophe = "Here's a file path: src/file.rs:10";
-    let extracted_paths = extract::extract_file_paths_from_text(text_with_apostrophe, true);
+    let extracted_paths = extract::extract_file_paths_from_text(text_with_apostrophe, true, &[]);
 
     // We should find the file path despite the apostrophe
     assert_eq!(
@@ -106,7 +106,7 @@ Here are some paths:
 "#;
 
     // Extract file paths from the text
-    let file_paths = extract::extract_file_paths_from_text(text, true);
+    let file_paths = extract::extract_file_paths_from_text(text, true, &[]);
 
     // Check that we found the paths
     // Note: We expect at least 2 paths to be found
@@ -0,0 +1,273 @@
+use serde_json::Value;
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+use tempfile::TempDir;
+
+const CHECKOUT: &str = r#"use crate::models::cart::Cart;
+use std::fmt;
+
+const TAX_RATE: u32 = 8;
+
+fn with_tax(amount: u32) -> u32 {
+    amount + amount * TAX_RATE / 100
+}
+
+pub fn checkout_total(cart: &Cart) -> u32 {
+    let subtotal: u32 = cart.items.iter().sum();
+    with_tax(subtotal)
+}
+"#;
+
+const CART: &str = r#"pub struct Cart {
+    pub items: Vec<u32>,
+}
+"#;
+
+const LIB_WITH_TESTS: &str = r#"use std::collections::HashMap;
+
+const LIMIT: usize = 10;
+
+fn helper(map: &HashMap<String, usize>) -> usize {
+    map.len().min(LIMIT)
+}
+
+pub fn count(map: &HashMap<String, usize>) -> usize {
+    helper(map) + LIMIT
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_count() {
+        assert_eq!(count(&HashMap::new()), 10);
+    }
+}
+"#;
+
+fn create_repository() -> TempDir {
+    let temp_dir = TempDir::new().unwrap();
+    fs::create_dir_all(temp_dir.path().join(".git")).unwrap();
+    let models = temp_dir.path().join("src/models");
+    fs::create_dir_all(&models).unwrap();
+    fs::write(temp_dir.path().join("src/checkout.rs"), CHECKOUT).unwrap();
+    fs::write(models.join("cart.rs"), CART).unwrap();
+    temp_dir
+}
+
+fn run_probe(args: &[&str]) -> String {
+    let output = Command::new(env!("CARGO_BIN_EXE_probe"))
+        .args(args)
+        .output()
+        .expect("Failed to execute command");
+    assert!(
+        output.status.success(),
+        "probe {:?} failed: {}",
+        args,
+        String::from_utf8_lossy(&output.stderr)
+    );
+    String::from_utf8_lossy(&output.stdout).to_string()
+}
+
+/// Kind, name and lines of every context block of a JSON result
+fn context_of(result: &Value) -> Vec<(String, String, Value)> {
+    result["context"]
+        .as_array()
+        .expect("Result should have context")
+        .iter()
+        .map(|block| {
+            (
+                block["kind"].as_str().unwrap().to_string(),
+                block["name"].as_str().unwrap_or("").to_string(),
+                block["lines"].clone(),
+            )
+        })
+        .collect()
+}
+
+#[test]
+fn test_extract_expand_context() {
+    let repository = create_repository();
+    let target = format!(
+        "{}#checkout_total",
+        repository.path().join("src/checkout.rs").display()
+    );
+
+    let stdout = run_probe(&["extract", &target, "--expand-context", "--format", "json"]);
+    let json: Value = serde_json::from_str(&stdout).expect("Output should be JSON");
+    let result = &json["results"][0];
+    assert_eq!(
+        context_of(result),
+        [
+            (
+                "imports".to_string(),
+                String::new(),
+                serde_json::json!([1, 2])
+            ),
+            (
+                "function".to_string(),
+                "with_tax".to_string(),
+                serde_json::json!([6, 8])
+            ),
+            (
+                "type".to_string(),
+                "Cart".to_string(),
+                serde_json::json!([1, 3])
+            ),
+        ],
+        "{}",
+        stdout
+    );
+    // The imported type is resolved in another directory of the repository
+    let cart = &result["context"][2];
+    assert!(
+        cart["file"].as_str().unwrap().ends_with("cart.rs"),
+        "{}",
+        cart
+    );
+    assert_eq!(cart["code"], CART.trim_end());
+
+    // Blocks that don't fit in what is left of the budget are skipped: the helper
+    // function doesn't fit next to the imports, the smaller type does
+    let stdout = run_probe(&[
+        "extract",
+        &target,
+        "--expand-context=40",
+        "--format",
+        "json",
+    ]);
+    let json: Value = serde_json::from_str(&stdout).unwrap();
+    let kinds: Vec<String> = context_of(&json["results"][0])
+        .into_iter()
+        .map(|(kind, _, _)| kind)
+        .collect();
+    assert_eq!(kinds, ["imports", "type"], "{}", stdout);
+
+    // Without the option, results have no context
+    let stdout = run_probe(&["extract", &target, "--format", "json"]);
+    let json: Value = serde_json::from_str(&stdout).unwrap();
+    assert!(json["results"][0].get("context").is_none(), "{}", stdout);
+}
+
+#[test]
+fn test_expand_context_ignores_imports_of_nested_modules() {
+    let repository = create_repository();
+    let lib = repository.path().join("src/lib.rs");
+    fs::write(&lib, LIB_WITH_TESTS).unwrap();
+    let target = format!("{}:10", lib.display());
+
+    // The `use super::*;` of the test module is not part of the import section, so the
+    // section doesn't cover the result and the definitions it references
+    let stdout = run_probe(&[
+        "extract",
+        &target,
+        "--expand-context=2000",
+        "--format",
+        "json",
+    ]);
+    let json: Value = serde_json::from_str(&stdout).expect("Output should be JSON");
+    let result = &json["results"][0];
+    assert_eq!(result["lines"], serde_json::json!([9, 11]), "{}", stdout);
+    assert_eq!(
+        context_of(result),
+        [
+            (
+                "imports".to_string(),
+                String::new(),
+                serde_json::json!([1, 1])
+            ),
+            (
+                "function".to_string(),
+                "helper".to_string(),
+                serde_json::json!([5, 7])
+            ),
+            (
+                "constant".to_string(),
+                "LIMIT".to_string(),
+                serde_json::json!([3, 3])
+            ),
+        ],
+        "{}",
+        stdout
+    );
+
+    // A result inside the import section gets no copy of it
+    let interleaved = repository.path().join("src/interleaved.rs");
+    fs::write(
+        &interleaved,
+        "use std::fmt;\n\nfn describe() -> String {\n    String::new()\n}\n\nuse std::io;\n",
+    )
+    .unwrap();
+    let target = format!("{}:4", interleaved.display());
+    let stdout = run_probe(&[
+        "extract",
+        &target,
+        "--expand-context=2000",
+        "--format",
+        "json",
+    ]);
+    let json: Value = serde_json::from_str(&stdout).expect("Output should be JSON");
+    let kinds: Vec<String> = json["results"][0]
+        .get("context")
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+        .map(|block| block["kind"].as_str().unwrap().to_string())
+        .collect();
+    assert!(!kinds.contains(&"imports".to_string()), "{}", stdout);
+}
+
+#[test]
+fn test_search_expand_context() {
+    let repository = create_repository();
+    let path = repository.path().to_str().unwrap();
+
+    let stdout = run_probe(&[
+        "search",
+        "subtotal",
+        path,
+        "--expand-context",
+        "--format",
+        "jsonl",
+    ]);
+    let records: Vec<Value> = stdout
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect();
+    let schema_path =
+        Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/schemas/jsonl_output_schema.json");
+    let schema: Value = serde_json::from_str(&fs::read_to_string(schema_path).unwrap()).unwrap();
+    let schema = jsonschema::JSONSchema::compile(&schema).unwrap();
+    for record in &records {
+        assert!(
+            schema.is_valid(record),
+            "{} does not match the schema",
+            record
+        );
+    }
+    let result = records
+        .iter()
+        .find(|record| record["type"] == "result")
+        .expect("Search should find checkout_total");
+    let names: Vec<String> = context_of(result)
+        .into_iter()
+        .map(|(_, name, _)| name)
+        .collect();
+    assert!(names.contains(&"Cart".to_string()), "{}", stdout);
+
+    let stdout = run_probe(&[
+        "search",
+        "subtotal",
+        path,
+        "--expand-context",
+        "--format",
+        "plain",
+    ]);
+    assert!(
+        stdout.contains("Context: function with_tax (") && !stdout.contains("const TAX_RATE"),
+        "{}",
+        stdout
+    );
+}
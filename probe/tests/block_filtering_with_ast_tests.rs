@@ -433,6 +433,9 @@ fn test_required_terms_query() {
         term_indices: indices.clone(),
         excluded_terms: HashSet::new(),
         exact: false,
+        fuzzy_terms: HashMap::new(),
+        synonym_terms: HashMap::new(),
+        vocabulary: None,
     };
 
     // Use the term indices directly
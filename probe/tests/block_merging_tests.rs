@@ -16,7 +16,6 @@ fn test_merge_ranked_blocks() {
         code:
             "fn test_function() {\n    let x = 1;\n    let y = 2;\n    println!(\"{}\", x + y);\n}"
                 .to_string(),
-        matched_by_filename: None,
         rank: Some(1),
         score: Some(0.9),
         tfidf_score: Some(0.8),
@@ -31,17 +30,13 @@ fn test_merge_ranked_blocks() {
         file_match_rank: Some(1),
         block_unique_terms: Some(2),
         block_total_matches: Some(3),
-        parent_file_id: None,
-        block_id: None,
-        matched_keywords: None,
-        tokenized_content: None,
+        ..Default::default()
     };
     let block2 = SearchResult {
     file: "test_file.rs".to_string(),
     lines: (6, 10),
     node_type: "function".to_string(),
     code: "fn another_function() {\n    let z = 3;\n    let result = z * 2;\n    println!(\"{}\", result);\n}".to_string(),
-    matched_by_filename: None,
     rank: Some(2),
     score: Some(0.8),
     tfidf_score: Some(0.7),
@@ -56,10 +51,7 @@ fn test_merge_ranked_blocks() {
     file_match_rank: Some(1),
     block_unique_terms: Some(2),
     block_total_matches: Some(2),
-    parent_file_id: None,
-    block_id: None,
-    matched_keywords: None,
-    tokenized_content: None,
+    ..Default::default()
 };
 
     // Create block from a different file that should not be merged
@@ -68,7 +60,6 @@ fn test_merge_ranked_blocks() {
         lines: (1, 5),
         node_type: "function".to_string(),
         code: "fn other_function() {\n    let a = 10;\n    let b = 20;\n    println!(\"{}\", a + b);\n}".to_string(),
-        matched_by_filename: None,
         rank: Some(3),
         score: Some(0.7),
         tfidf_score: Some(0.6),
@@ -83,10 +74,7 @@ fn test_merge_ranked_blocks() {
         file_match_rank: Some(2),
         block_unique_terms: Some(1),
         block_total_matches: Some(3),
-        parent_file_id: None,
-        block_id: None,
-        matched_keywords: None,
-        tokenized_content: None,
+        ..Default::default()
     };
 
     // Create a vector with all blocks
@@ -176,22 +164,12 @@ fn test_integration_with_search_flow() {
     let options = SearchOptions {
         path: temp_path,
         queries: &queries,
-        files_only: false,
         custom_ignores: &custom_ignores,
-        exclude_filenames: false,
-        language: None,
         reranker: "combined",
         frequency_search: false,
-        max_results: None,
-        max_bytes: None,
-        max_tokens: None,
         allow_tests: true,
-        no_merge: false,
         merge_threshold: Some(20), // Increase threshold to ensure non-adjacent blocks are merged
-        dry_run: false,
-        session: None,
-        timeout: 30,
-        exact: false,
+        ..Default::default()
     };
 
     // Run a search that should produce multiple overlapping blocks
@@ -289,22 +267,12 @@ fn test_no_merge_flag() {
     let options_with_merge = SearchOptions {
         path: temp_path,
         queries: &queries,
-        files_only: false,
         custom_ignores: &custom_ignores,
-        exclude_filenames: false,
-        language: None,
         reranker: "combined",
         frequency_search: false,
-        max_results: None,
-        max_bytes: None,
-        max_tokens: None,
         allow_tests: true,
-        no_merge: false,
         merge_threshold: Some(20), // Increase threshold to ensure non-adjacent blocks are merged
-        dry_run: false,
-        session: None,
-        timeout: 30,
-        exact: false,
+        ..Default::default()
     };
 
     // Run a search that should produce merged blocks
@@ -314,22 +282,13 @@ fn test_no_merge_flag() {
     let options_without_merge = SearchOptions {
         path: temp_path,
         queries: &queries,
-        files_only: false,
         custom_ignores: &custom_ignores,
-        exclude_filenames: false,
-        language: None,
         reranker: "combined",
         frequency_search: false,
-        max_results: None,
-        max_bytes: None,
-        max_tokens: None,
         allow_tests: true,
         no_merge: true,
         merge_threshold: Some(20), // Increase threshold to ensure non-adjacent blocks are merged
-        dry_run: false,
-        session: None,
-        timeout: 30,
-        exact: false,
+        ..Default::default()
     };
 
     // Run a search that should not merge blocks
@@ -379,3 +338,208 @@ fn test_no_merge_flag() {
         );
     }
 }
+
+const CART: &str = r#"pub struct Cart {
+    items: Vec<u32>,
+}
+
+impl Cart {
+    pub fn add_item(&mut self, item: u32) {
+        self.items.push(item);
+    }
+
+    pub fn remove_item(&mut self, item: u32) {
+        self.items.retain(|i| *i != item);
+    }
+
+    pub fn item_total(&self) -> u32 {
+        self.items.iter().sum()
+    }
+}
+
+pub fn total_with_tax(cart: &Cart) -> u32 {
+    cart.item_total() * 2
+}
+"#;
+
+// Search the cart file and return the line ranges and code of the results, by start line
+fn search_cart(
+    temp_path: &Path,
+    query: &str,
+    max_tokens: Option<usize>,
+    parent_context: bool,
+) -> Vec<((usize, usize), String)> {
+    let queries = vec![query.to_string()];
+    let custom_ignores: Vec<String> = vec![];
+    let options = SearchOptions {
+        path: temp_path,
+        queries: &queries,
+        custom_ignores: &custom_ignores,
+        exclude_filenames: true,
+        frequency_search: false,
+        max_tokens,
+        parent_context,
+        ..Default::default()
+    };
+    let mut results: Vec<((usize, usize), String)> = perform_probe(&options)
+        .unwrap()
+        .results
+        .into_iter()
+        .map(|result| (result.lines, result.code))
+        .collect();
+    results.sort();
+    results
+}
+
+#[test]
+fn test_merging_follows_parent_blocks() {
+    let temp_dir = TempDir::new().unwrap();
+    let temp_path = temp_dir.path();
+    fs::write(temp_path.join("cart.rs"), CART).unwrap();
+
+    // Two methods of the impl are expanded to the whole impl
+    let results = search_cart(temp_path, "push OR retain", None, false);
+    assert_eq!(results.len(), 1, "{:?}", results);
+    assert_eq!(results[0].0, (5, 17));
+    assert!(results[0].1.starts_with("impl Cart {"));
+
+    // Unless the impl does not fit the token budget
+    let results = search_cart(temp_path, "push OR retain", Some(80), false);
+    assert_eq!(
+        results.iter().map(|r| r.0).collect::<Vec<_>>(),
+        [(6, 12)],
+        "{:?}",
+        results
+    );
+
+    // The last method is not merged with the function after the impl
+    let results = search_cart(temp_path, "sum OR tax", None, false);
+    assert_eq!(
+        results.iter().map(|r| r.0).collect::<Vec<_>>(),
+        [(14, 16), (19, 21)],
+        "{:?}",
+        results
+    );
+
+    // Nested blocks can start with the signature line of their parent
+    let results = search_cart(temp_path, "retain", None, true);
+    assert_eq!(results.len(), 1, "{:?}", results);
+    assert_eq!(results[0].0, (5, 12));
+    assert_eq!(
+        results[0].1,
+        "impl Cart {\n... lines 6-9 skipped...\n    pub fn remove_item(&mut self, item: u32) {\n        self.items.retain(|i| *i != item);\n    }"
+    );
+}
+
+// Search the store file with --explain, with or without merging
+fn search_store_explained(temp_path: &Path, no_merge: bool) -> Vec<SearchResult> {
+    let queries = vec!["session insert".to_string()];
+    let custom_ignores: Vec<String> = vec![];
+    let options = SearchOptions {
+        path: temp_path,
+        queries: &queries,
+        custom_ignores: &custom_ignores,
+        exclude_filenames: true,
+        frequency_search: false,
+        no_merge,
+        explain: true,
+        ..Default::default()
+    };
+    perform_probe(&options).unwrap().results
+}
+
+#[test]
+fn test_merged_block_explanation_matches_result() {
+    let temp_dir = TempDir::new().unwrap();
+    let temp_path = temp_dir.path();
+    fs::write(
+        temp_path.join("store.rs"),
+        "fn open_session() -> u32 {\n    let session = 1;\n    session\n}\n\nfn save_session(session: u32) {\n    insert(session);\n    insert(session + 1);\n}\n",
+    )
+    .unwrap();
+
+    let blocks = search_store_explained(temp_path, true);
+    assert_eq!(blocks.len(), 2, "{:?}", blocks);
+    let results = search_store_explained(temp_path, false);
+    assert_eq!(results.len(), 1, "The two functions should be merged");
+    let result = &results[0];
+    assert_eq!(result.lines, (1, 9));
+
+    let explanation = result.explanation.as_ref().unwrap();
+    assert_eq!(explanation.merged_blocks.len(), 1);
+    assert_eq!(explanation.bm25_score, result.bm25_score);
+
+    // The scores are those of the best block, the occurrences those of both blocks
+    let best = blocks
+        .iter()
+        .max_by(|a, b| a.bm25_score.partial_cmp(&b.bm25_score).unwrap())
+        .unwrap();
+    assert_eq!(result.bm25_score, best.bm25_score);
+    for term in &explanation.terms {
+        let constituents: Vec<_> = blocks
+            .iter()
+            .map(|block| {
+                let explanation = block.explanation.as_ref().unwrap();
+                explanation
+                    .terms
+                    .iter()
+                    .find(|t| t.term == term.term)
+                    .unwrap()
+            })
+            .collect();
+        let best_term = best
+            .explanation
+            .as_ref()
+            .unwrap()
+            .terms
+            .iter()
+            .find(|t| t.term == term.term)
+            .unwrap();
+        assert_eq!(term.bm25, best_term.bm25, "{:?}", term);
+        assert_eq!(
+            term.frequency,
+            constituents.iter().map(|t| t.frequency).sum::<usize>(),
+            "{:?}",
+            term
+        );
+        assert!(!term.raw_matches.is_empty(), "{:?}", term);
+    }
+    let insert = explanation.terms.iter().find(|t| t.term == "insert");
+    assert!(insert.is_some_and(|t| t.frequency > 0), "{:?}", explanation);
+}
+
+#[test]
+fn test_merge_fills_gaps_from_decoded_source() {
+    let temp_dir = TempDir::new().unwrap();
+    let file = temp_dir.path().join("menu.rs");
+    // A Latin-1 file, with a comment between the merged functions
+    fs::write(
+        &file,
+        b"fn first() {\n}\n// caf\xe9 menu\n\nfn second() {\n}\n",
+    )
+    .unwrap();
+    let file = file.to_string_lossy().to_string();
+
+    let block = |lines: (usize, usize), code: &str, rank: usize| SearchResult {
+        file: file.clone(),
+        lines,
+        node_type: "function_item".to_string(),
+        code: code.to_string(),
+        rank: Some(rank),
+        ..Default::default()
+    };
+    let merged = merge_ranked_blocks(
+        vec![
+            block((1, 2), "fn first() {\n}", 1),
+            block((5, 6), "fn second() {\n}", 2),
+        ],
+        None,
+    );
+
+    assert_eq!(merged.len(), 1, "{:?}", merged);
+    assert_eq!(merged[0].lines, (1, 6));
+    assert_eq!(
+        merged[0].code,
+        "fn first() {\n}\n// café menu\n\nfn second() {\n}"
+    );
+}
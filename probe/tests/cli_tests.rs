@@ -343,7 +343,7 @@ fn test_cli_custom_ignores() {
 
     // Find where "search.js" appears in the debug output
     if let Some(pos) = stdout.find("search.js") {
-        let start = if pos > 50 { pos - 50 } else { 0 };
+        let start = pos.saturating_sub(50);
         let end = if pos + 50 < stdout.len() {
             pos + 50
         } else {
@@ -421,3 +421,369 @@ fn test_cli_max_results() {
         "Should find only 1 result"
     );
 }
+
+#[test]
+fn test_cli_explain() {
+    let temp_dir = TempDir::new().expect("Failed to create temp dir");
+    create_test_directory_structure(&temp_dir);
+
+    let output = Command::new(env!("CARGO_BIN_EXE_probe"))
+        .args([
+            "search",
+            "search query",
+            temp_dir.path().to_str().unwrap(),
+            "--explain",
+            "--format",
+            "json",
+        ])
+        .output()
+        .expect("Failed to execute command");
+    assert!(output.status.success());
+
+    let json: serde_json::Value =
+        serde_json::from_slice(&output.stdout).expect("Output should be JSON");
+    let results = json["results"].as_array().unwrap();
+    assert!(!results.is_empty());
+    for result in results {
+        let explanation = &result["explanation"];
+        assert_eq!(explanation["query"], "(search OR queri)");
+        // Merging keeps the best score of the merged blocks
+        if explanation["merged_blocks"].as_array().unwrap().is_empty() {
+            assert_eq!(explanation["bm25_score"], result["bm25_score"]);
+        }
+        let terms = explanation["terms"].as_array().unwrap();
+        assert_eq!(terms.len(), 2);
+        assert_eq!(terms[0]["term"], "search");
+        assert!(terms[0]["idf"].is_number());
+        // Every file name contains "search"
+        assert_eq!(terms[0]["filename_frequency"], 1);
+        assert!(explanation["filename_boost"].as_f64().unwrap() > 0.0);
+    }
+
+    // Without --explain there is no explanation
+    let output = Command::new(env!("CARGO_BIN_EXE_probe"))
+        .args(["search", "search query", temp_dir.path().to_str().unwrap()])
+        .args(["--format", "terminal"])
+        .output()
+        .expect("Failed to execute command");
+    assert!(!String::from_utf8_lossy(&output.stdout).contains("Explanation:"));
+
+    let output = Command::new(env!("CARGO_BIN_EXE_probe"))
+        .args(["search", "search query", temp_dir.path().to_str().unwrap()])
+        .args(["--format", "terminal", "--explain"])
+        .output()
+        .expect("Failed to execute command");
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("Explanation:"));
+    assert!(stdout.contains("Query: (search OR queri)"));
+    assert!(stdout.contains("Term \"search\" (optional): matched"));
+}
+
+#[test]
+fn test_cli_fuzzy_search_and_suggestions() {
+    let temp_dir = TempDir::new().expect("Failed to create temp dir");
+    fs::create_dir(temp_dir.path().join("src")).expect("Failed to create src directory");
+    create_test_file(
+        &temp_dir,
+        "src/cache.rs",
+        "pub struct SessionCache {\n    entries: Vec<String>,\n}\n",
+    );
+    let path = temp_dir.path().to_str().unwrap();
+
+    // Without --fuzzy the typo finds nothing, but a correction is suggested
+    let output = Command::new(env!("CARGO_BIN_EXE_probe"))
+        .args(["search", "SesionCache", path, "--format", "terminal"])
+        .output()
+        .expect("Failed to execute command");
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("No results found."));
+    assert!(stdout.contains("Did you mean: session (for \"sesion\")"));
+
+    // Structured formats keep suggestions out of stdout
+    let output = Command::new(env!("CARGO_BIN_EXE_probe"))
+        .args(["search", "SesionCache", path, "--format", "json"])
+        .output()
+        .expect("Failed to execute command");
+    let json: serde_json::Value =
+        serde_json::from_slice(&output.stdout).expect("Output should be JSON");
+    assert_eq!(json["results"].as_array().unwrap().len(), 0);
+    assert!(String::from_utf8_lossy(&output.stderr).contains("Did you mean: session"));
+
+    // With --fuzzy the misspelled term matches "Session"
+    let output = Command::new(env!("CARGO_BIN_EXE_probe"))
+        .args(["search", "SesionCache", path, "--fuzzy", "--format", "json"])
+        .output()
+        .expect("Failed to execute command");
+    assert!(output.status.success());
+    let json: serde_json::Value =
+        serde_json::from_slice(&output.stdout).expect("Output should be JSON");
+    let results = json["results"].as_array().unwrap();
+    assert_eq!(results.len(), 1);
+    assert!(results[0]["code"]
+        .as_str()
+        .unwrap()
+        .contains("pub struct SessionCache"));
+    assert!(results[0]["matched_keywords"]
+        .as_array()
+        .unwrap()
+        .contains(&serde_json::json!("session")));
+
+    // Terms too far from any identifier still find nothing
+    let output = Command::new(env!("CARGO_BIN_EXE_probe"))
+        .args(["search", "SesonKache", path, "--fuzzy", "--format", "json"])
+        .output()
+        .expect("Failed to execute command");
+    let json: serde_json::Value =
+        serde_json::from_slice(&output.stdout).expect("Output should be JSON");
+    assert_eq!(json["results"].as_array().unwrap().len(), 0);
+}
+
+#[test]
+fn test_cli_regex_and_literal_search() {
+    let temp_dir = TempDir::new().expect("Failed to create temp dir");
+    create_test_file(
+        &temp_dir,
+        "errors.rs",
+        "pub fn error_code(code: u32) -> String {\n    format!(\"ERR-{:04}\", code)\n}\n\n\
+         pub fn sum() -> u32 {\n    let total = 1 + 2;\n    total\n}\n",
+    );
+    let path = temp_dir.path().to_str().unwrap();
+
+    let search = |args: &[&str]| -> serde_json::Value {
+        let output = Command::new(env!("CARGO_BIN_EXE_probe"))
+            .args(["search"])
+            .args(args)
+            .args([path, "--format", "json"])
+            .output()
+            .expect("Failed to execute command");
+        assert!(
+            output.status.success(),
+            "probe failed: {}",
+            String::from_utf8_lossy(&output.stderr)
+        );
+        serde_json::from_slice(&output.stdout).expect("Output should be JSON")
+    };
+
+    // A regex finds the enclosing function, with the position of the match
+    let json = search(&[r"ERR-\{:0\d\}", "--regex"]);
+    let results = json["results"].as_array().unwrap();
+    assert_eq!(results.len(), 1);
+    assert_eq!(results[0]["lines"], serde_json::json!([1, 3]));
+    let code = results[0]["code"].as_str().unwrap();
+    let range = &results[0]["match_ranges"][0];
+    assert_eq!(range["line"], 2);
+    assert_eq!(range["column_start"], 14);
+    assert_eq!(range["column_end"], 23);
+    let (start, end) = (
+        range["start_byte"].as_u64().unwrap() as usize,
+        range["end_byte"].as_u64().unwrap() as usize,
+    );
+    assert_eq!(&code[start..end], "ERR-{:04}");
+
+    // Regexes are case-sensitive unless they say otherwise
+    assert!(search(&["err-", "--regex"])["results"]
+        .as_array()
+        .unwrap()
+        .is_empty());
+    assert_eq!(
+        search(&["(?i)err-", "--regex"])["results"]
+            .as_array()
+            .unwrap()
+            .len(),
+        1
+    );
+
+    // Literal text matches regex metacharacters as written
+    let json = search(&["1 + 2", "--literal"]);
+    let results = json["results"].as_array().unwrap();
+    assert_eq!(results.len(), 1);
+    assert!(results[0]["code"]
+        .as_str()
+        .unwrap()
+        .contains("pub fn sum()"));
+    assert!(search(&["1 + 2", "--regex"])["results"]
+        .as_array()
+        .unwrap()
+        .is_empty());
+
+    // Term searches report match ranges too
+    let json = search(&["total"]);
+    let ranges = json["results"][0]["match_ranges"].as_array().unwrap();
+    assert_eq!(ranges.len(), 2);
+
+    // Invalid regexes are reported
+    let output = Command::new(env!("CARGO_BIN_EXE_probe"))
+        .args(["search", "(unclosed", path, "--regex"])
+        .output()
+        .expect("Failed to execute command");
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("Invalid regex"));
+}
+
+#[test]
+fn test_cli_match_ranges_of_merged_blocks() {
+    let temp_dir = TempDir::new().expect("Failed to create temp dir");
+    let mut content = String::from("fn widget_one() {\n    widget();\n}\n");
+    for i in 4..=36 {
+        content.push_str(&format!("// filler {}\n", i));
+    }
+    content.push_str("fn widget_two() {\n    widget();\n}\n");
+    create_test_file(&temp_dir, "widgets.rs", &content);
+
+    let output = Command::new(env!("CARGO_BIN_EXE_probe"))
+        .args(["search", "widget", temp_dir.path().to_str().unwrap()])
+        .args(["--merge-threshold", "40", "--format", "json"])
+        .output()
+        .expect("Failed to execute command");
+    assert!(
+        output.status.success(),
+        "probe failed: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let json: serde_json::Value =
+        serde_json::from_slice(&output.stdout).expect("Output should be JSON");
+    let results = json["results"].as_array().unwrap();
+    assert_eq!(results.len(), 1);
+    assert_eq!(results[0]["lines"], serde_json::json!([1, 39]));
+    let code = results[0]["code"].as_str().unwrap();
+    assert!(code.contains("skipped"));
+
+    // Ranges are on the lines of the file, not on the lines of the merged code
+    let ranges = results[0]["match_ranges"].as_array().unwrap();
+    let lines: Vec<u64> = ranges.iter().map(|r| r["line"].as_u64().unwrap()).collect();
+    assert_eq!(lines, vec![1, 2, 37, 38]);
+    for range in ranges {
+        let (start, end) = (
+            range["start_byte"].as_u64().unwrap() as usize,
+            range["end_byte"].as_u64().unwrap() as usize,
+        );
+        assert_eq!(&code[start..end], "widget");
+    }
+}
+
+#[test]
+fn test_cli_match_ranges_with_parent_context() {
+    let temp_dir = TempDir::new().expect("Failed to create temp dir");
+    create_test_file(
+        &temp_dir,
+        "store.rs",
+        "pub struct Store {\n    items: Vec<u32>,\n}\n\nimpl Store {\n    \
+         pub fn new() -> Self {\n        Store { items: Vec::new() }\n    }\n\n    \
+         pub fn remove_item(&mut self, item: u32) {\n        \
+         self.items.retain(|i| *i != item);\n        self.remove_cached(item);\n    }\n}\n",
+    );
+
+    let output = Command::new(env!("CARGO_BIN_EXE_probe"))
+        .args(["search", "remove", temp_dir.path().to_str().unwrap()])
+        .args(["--parent-context", "--format", "json"])
+        .output()
+        .expect("Failed to execute command");
+    assert!(
+        output.status.success(),
+        "probe failed: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let json: serde_json::Value =
+        serde_json::from_slice(&output.stdout).expect("Output should be JSON");
+    let results = json["results"].as_array().unwrap();
+    assert_eq!(results.len(), 1);
+
+    // The result starts at the signature of its impl, its matches stay on their lines
+    assert_eq!(results[0]["lines"], serde_json::json!([5, 13]));
+    let code = results[0]["code"].as_str().unwrap();
+    assert!(code.starts_with("impl Store {\n... lines 6-9 skipped..."));
+    let ranges = results[0]["match_ranges"].as_array().unwrap();
+    let lines: Vec<u64> = ranges.iter().map(|r| r["line"].as_u64().unwrap()).collect();
+    assert_eq!(lines, vec![10, 12]);
+    for range in ranges {
+        let (start, end) = (
+            range["start_byte"].as_u64().unwrap() as usize,
+            range["end_byte"].as_u64().unwrap() as usize,
+        );
+        // Terms are highlighted by their stem
+        assert_eq!(&code[start..end], "remov");
+    }
+}
+
+#[test]
+fn test_cli_batch_search() {
+    let temp_dir = TempDir::new().expect("Failed to create temp dir");
+    create_test_file(
+        &temp_dir,
+        "lib.rs",
+        "pub fn parse_header(input: &str) -> usize {\n    input.len()\n}\n\n\
+         pub fn write_footer(out: &mut String) {\n    out.push_str(\"footer\");\n}\n",
+    );
+    create_test_file(
+        &temp_dir,
+        "queries.json",
+        r#"["header", "header footer", {"query": "footer", "max_results": 1}]"#,
+    );
+    let path = temp_dir.path().to_str().unwrap();
+    let batch = temp_dir.path().join("queries.json");
+
+    let search = |extra: &[&str]| -> serde_json::Value {
+        let output = Command::new(env!("CARGO_BIN_EXE_probe"))
+            .args(["search", "--batch", batch.to_str().unwrap(), path])
+            .args(["--format", "json"])
+            .args(extra)
+            .output()
+            .expect("Failed to execute command");
+        assert!(
+            output.status.success(),
+            "probe failed: {}",
+            String::from_utf8_lossy(&output.stderr)
+        );
+        serde_json::from_slice(&output.stdout).expect("Output should be JSON")
+    };
+    let lines = |json: &serde_json::Value| -> Vec<Vec<serde_json::Value>> {
+        json["queries"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|query| {
+                let results = query["results"].as_array().unwrap();
+                results.iter().map(|r| r["lines"].clone()).collect()
+            })
+            .collect()
+    };
+
+    // Results are grouped by query, in the order of the batch file
+    let json = search(&[]);
+    let queries = json["queries"].as_array().unwrap();
+    assert_eq!(queries.len(), 3);
+    assert_eq!(queries[1]["query"], "header footer");
+    assert_eq!(
+        lines(&json),
+        vec![
+            vec![serde_json::json!([1, 3])],
+            vec![serde_json::json!([1, 7])],
+            vec![serde_json::json!([5, 7])],
+        ]
+    );
+    assert_eq!(json["summary"]["count"], 3);
+
+    // Deduplication leaves out blocks an earlier query already returned
+    let json = search(&["--dedupe"]);
+    assert_eq!(
+        lines(&json),
+        vec![
+            vec![serde_json::json!([1, 3])],
+            vec![],
+            vec![serde_json::json!([5, 7])],
+        ]
+    );
+    assert_eq!(json["queries"][1]["duplicates_skipped"], 1);
+
+    // A batch file that isn't a list of queries is reported
+    create_test_file(&temp_dir, "invalid.json", r#"{"query": "header"}"#);
+    let output = Command::new(env!("CARGO_BIN_EXE_probe"))
+        .args(["search", "--batch"])
+        .arg(temp_dir.path().join("invalid.json"))
+        .arg(path)
+        .output()
+        .expect("Failed to execute command");
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("Invalid batch file"));
+}
@@ -16,7 +16,6 @@ fn test_merge_different_node_types() {
         code:
             "fn test_function() {\n    let x = 1;\n    let y = 2;\n    println!(\"{}\", x + y);\n}"
                 .to_string(),
-        matched_by_filename: None,
         rank: Some(1),
         score: Some(0.9),
         tfidf_score: Some(0.8),
@@ -31,17 +30,13 @@ fn test_merge_different_node_types() {
         file_match_rank: Some(1),
         block_unique_terms: Some(2),
         block_total_matches: Some(3),
-        parent_file_id: None,
-        block_id: None,
-        matched_keywords: None,
-        tokenized_content: None,
+        ..Default::default()
     };
     let block2 = SearchResult {
     file: "mixed_types.rs".to_string(),
     lines: (6, 10),
     node_type: "comment".to_string(),
     code: "// This is a comment block\n// It explains the function above\n// And provides context\n// For the next function\n// Below".to_string(),
-    matched_by_filename: None,
     rank: Some(2),
     score: Some(0.8),
     tfidf_score: Some(0.7),
@@ -56,10 +51,7 @@ fn test_merge_different_node_types() {
     file_match_rank: Some(1),
     block_unique_terms: Some(2),
     block_total_matches: Some(2),
-    parent_file_id: None,
-    block_id: None,
-    matched_keywords: None,
-    tokenized_content: None,
+    ..Default::default()
 };
 
     let block3 = SearchResult {
@@ -67,7 +59,6 @@ fn test_merge_different_node_types() {
         lines: (11, 15),
         node_type: "function".to_string(),
         code: "fn another_function() {\n    let z = 3;\n    let result = z * 2;\n    println!(\"{}\", result);\n}".to_string(),
-        matched_by_filename: None,
         rank: Some(3),
         score: Some(0.7),
         tfidf_score: Some(0.6),
@@ -82,10 +73,7 @@ fn test_merge_different_node_types() {
         file_match_rank: Some(1),
         block_unique_terms: Some(2),
         block_total_matches: Some(2),
-        parent_file_id: None,
-        block_id: None,
-        matched_keywords: None,
-        tokenized_content: None,
+        ..Default::default()
     };
 
     // Create a vector with all blocks
@@ -134,7 +122,6 @@ fn test_merge_with_gaps() {
         code:
             "fn first_function() {\n    let x = 1;\n    let y = 2;\n    println!(\"{}\", x + y);\n}"
                 .to_string(),
-        matched_by_filename: None,
         rank: Some(1),
         score: Some(0.9),
         tfidf_score: Some(0.8),
@@ -149,10 +136,7 @@ fn test_merge_with_gaps() {
         file_match_rank: Some(1),
         block_unique_terms: Some(2),
         block_total_matches: Some(3),
-        parent_file_id: None,
-        block_id: None,
-        matched_keywords: None,
-        tokenized_content: None,
+        ..Default::default()
     };
 
     // Gap of 3 lines between block1 and block2
@@ -161,7 +145,6 @@ fn test_merge_with_gaps() {
         lines: (9, 13),
         node_type: "function".to_string(),
         code: "fn second_function() {\n    let z = 3;\n    let result = z * 2;\n    println!(\"{}\", result);\n}".to_string(),
-        matched_by_filename: None,
         rank: Some(2),
         score: Some(0.8),
         tfidf_score: Some(0.7),
@@ -176,10 +159,7 @@ fn test_merge_with_gaps() {
         file_match_rank: Some(1),
         block_unique_terms: Some(2),
         block_total_matches: Some(2),
-        parent_file_id: None,
-        block_id: None,
-        matched_keywords: None,
-        tokenized_content: None,
+        ..Default::default()
     };
 
     // Gap of 2 lines between block2 and block3
@@ -190,7 +170,6 @@ fn test_merge_with_gaps() {
         code:
             "fn third_function() {\n    let a = 4;\n    let b = 5;\n    println!(\"{}\", a + b);\n}"
                 .to_string(),
-        matched_by_filename: None,
         rank: Some(3),
         score: Some(0.7),
         tfidf_score: Some(0.6),
@@ -205,10 +184,7 @@ fn test_merge_with_gaps() {
         file_match_rank: Some(1),
         block_unique_terms: Some(2),
         block_total_matches: Some(2),
-        parent_file_id: None,
-        block_id: None,
-        matched_keywords: None,
-        tokenized_content: None,
+        ..Default::default()
     };
 
     // Test with default threshold (5)
@@ -285,7 +261,6 @@ fn test_merge_overlapping_blocks() {
         lines: (1, 7),
         node_type: "function".to_string(),
         code: "fn first_function() {\n    let x = 1;\n    let y = 2;\n    println!(\"{}\", x + y);\n    // Shared lines\n    let shared = true;\n}".to_string(),
-        matched_by_filename: None,
         rank: Some(1),
         score: Some(0.9),
         tfidf_score: Some(0.8),
@@ -300,10 +275,7 @@ fn test_merge_overlapping_blocks() {
         file_match_rank: Some(1),
         block_unique_terms: Some(2),
         block_total_matches: Some(3),
-        parent_file_id: None,
-        block_id: None,
-        matched_keywords: None,
-        tokenized_content: None,
+        ..Default::default()
     };
 
     // Overlaps with block1 (lines 5-7 are shared)
@@ -312,7 +284,6 @@ fn test_merge_overlapping_blocks() {
         lines: (5, 10),
         node_type: "function".to_string(),
         code: "    // Shared lines\n    let shared = true;\n}\n\nfn second_function() {\n    let z = 3;\n}".to_string(),
-        matched_by_filename: None,
         rank: Some(2),
         score: Some(0.8),
         tfidf_score: Some(0.7),
@@ -327,10 +298,7 @@ fn test_merge_overlapping_blocks() {
         file_match_rank: Some(1),
         block_unique_terms: Some(2),
         block_total_matches: Some(2),
-        parent_file_id: None,
-        block_id: None,
-        matched_keywords: None,
-        tokenized_content: None,
+        ..Default::default()
     };
 
     // Create a vector with both blocks
@@ -409,22 +377,13 @@ fn third_function() {
         let options = SearchOptions {
             path: temp_path,
             queries: &queries,
-            files_only: false,
             custom_ignores: &custom_ignores,
             exclude_filenames: true,
-            language: None,
             reranker: "combined",
             frequency_search: false,
-            max_results: None,
-            max_bytes: None,
-            max_tokens: None,
             allow_tests: true,
-            no_merge: false,
             merge_threshold: Some(threshold),
-            dry_run: false,
-            session: None,
-            timeout: 30,
-            exact: false,
+            ..Default::default()
         };
 
         // Run the search
@@ -483,7 +442,6 @@ fn test_merge_parent_child_blocks() {
         lines: (1, 10),
         node_type: "class".to_string(),
         code: "struct TestStruct {\n    x: i32,\n    y: i32,\n}\n\nimpl TestStruct {\n    fn new(x: i32, y: i32) -> Self {\n        Self { x, y }\n    }\n}".to_string(),
-        matched_by_filename: None,
         rank: Some(2),
         score: Some(0.8),
         tfidf_score: Some(0.7),
@@ -500,8 +458,7 @@ fn test_merge_parent_child_blocks() {
         block_total_matches: Some(3),
         parent_file_id: Some("parent_child.rs".to_string()),
         block_id: Some(0),
-        matched_keywords: None,
-        tokenized_content: None,
+        ..Default::default()
     };
 
     // Child block (method inside the struct)
@@ -510,7 +467,6 @@ fn test_merge_parent_child_blocks() {
         lines: (7, 9),
         node_type: "function".to_string(),
         code: "    fn new(x: i32, y: i32) -> Self {\n        Self { x, y }\n    }".to_string(),
-        matched_by_filename: None,
         rank: Some(1),
         score: Some(0.9),
         tfidf_score: Some(0.8),
@@ -527,8 +483,7 @@ fn test_merge_parent_child_blocks() {
         block_total_matches: Some(2),
         parent_file_id: Some("parent_child.rs".to_string()),
         block_id: Some(1),
-        matched_keywords: None,
-        tokenized_content: None,
+        ..Default::default()
     };
 
     // Create a vector with both blocks
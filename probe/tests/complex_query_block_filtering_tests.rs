@@ -294,22 +294,11 @@ func (i *IPWhiteListMiddleware) Process() {
     let options = SearchOptions {
         path: temp_path,
         queries: &queries,
-        files_only: false,
         custom_ignores: &custom_ignores,
-        exclude_filenames: false,
-        language: None,
         reranker: "hybrid",
-        frequency_search: true,
-        max_results: None,
-        max_bytes: None,
-        max_tokens: None,
         allow_tests: true,
         no_merge: true,
-        merge_threshold: None,
-        dry_run: false,
-        session: None,
-        timeout: 30,
-        exact: false,
+        ..Default::default()
     };
 
     // Run the search
@@ -373,22 +362,12 @@ func Process() {
     let options = SearchOptions {
         path: temp_path,
         queries: &queries,
-        files_only: false,
         custom_ignores: &custom_ignores,
         exclude_filenames: false, // Include filenames in search
-        language: None,
         reranker: "hybrid",
-        frequency_search: true,
-        max_results: None,
-        max_bytes: None,
-        max_tokens: None,
         allow_tests: true,
         no_merge: true,
-        merge_threshold: None,
-        dry_run: false,
-        session: None,
-        timeout: 30,
-        exact: false,
+        ..Default::default()
     };
 
     // Run the search
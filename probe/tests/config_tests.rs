@@ -0,0 +1,179 @@
+use serde_json::Value;
+use std::fs;
+use std::process::{Command, Output};
+use tempfile::TempDir;
+
+const CONFIG: &str = r#"
+ignore = ["generated/*"]
+
+[search]
+max-results = 1
+format = "plain"
+
+[profiles.agent]
+format = "json"
+
+[profiles.agent.search]
+ignore = ["legacy/*"]
+"#;
+
+// Create a project with a configuration file and a function in each of four directories
+fn create_project() -> TempDir {
+    let temp_dir = TempDir::new().unwrap();
+    let dir = temp_dir.path();
+    fs::write(dir.join(".probe.toml"), CONFIG).unwrap();
+    for name in ["api", "web", "generated", "legacy"] {
+        fs::create_dir_all(dir.join(name)).unwrap();
+        fs::write(
+            dir.join(name).join("lib.rs"),
+            format!(
+                "fn {}_handler() {{\n    let checkout_total = 1;\n}}\n",
+                name
+            ),
+        )
+        .unwrap();
+    }
+    temp_dir
+}
+
+fn run_probe(args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_probe"))
+        .args(args)
+        .output()
+        .expect("Failed to execute command")
+}
+
+fn stdout_of(args: &[&str]) -> String {
+    let output = run_probe(args);
+    assert!(
+        output.status.success(),
+        "probe {:?} failed: {}",
+        args,
+        String::from_utf8_lossy(&output.stderr)
+    );
+    String::from_utf8_lossy(&output.stdout).to_string()
+}
+
+fn result_count(stdout: &str) -> usize {
+    let json: Value = serde_json::from_str(stdout).expect("Output should be JSON");
+    json["results"].as_array().unwrap().len()
+}
+
+#[test]
+fn test_configuration_defaults_profiles_and_precedence() {
+    let project = create_project();
+    let path = project.path().to_str().unwrap();
+
+    // The [search] section applies, and the command line takes precedence over it
+    let stdout = stdout_of(&["search", "checkout_total", path]);
+    assert!(stdout.contains("Found 1 search results"), "{}", stdout);
+    let stdout = stdout_of(&[
+        "search",
+        "checkout_total",
+        path,
+        "--max-results",
+        "10",
+        "--format",
+        "json",
+    ]);
+    // Files under generated/ are ignored by the top-level ignore patterns
+    assert_eq!(result_count(&stdout), 3, "{}", stdout);
+    assert!(!stdout.contains("generated_handler"), "{}", stdout);
+
+    // A profile overrides the sections, and ignore patterns accumulate
+    let stdout = stdout_of(&[
+        "search",
+        "checkout_total",
+        path,
+        "--profile",
+        "agent",
+        "--max-results",
+        "10",
+    ]);
+    assert_eq!(result_count(&stdout), 2, "{}", stdout);
+    assert!(!stdout.contains("legacy_handler"), "{}", stdout);
+
+    let stdout = stdout_of(&["config", "show", path, "--profile", "agent", "-o", "json"]);
+    let json: Value = serde_json::from_str(&stdout).expect("Output should be JSON");
+    let search = json["commands"]["search"].as_array().unwrap();
+    let option = |name: &str| {
+        search
+            .iter()
+            .find(|option| option["name"] == name)
+            .unwrap_or_else(|| panic!("No {} option in {}", name, stdout))
+    };
+    assert_eq!(option("format")["value"], "json");
+    assert_eq!(
+        option("format")["sources"],
+        serde_json::json!(["[profiles.agent]"])
+    );
+    assert_eq!(
+        option("ignore")["value"],
+        serde_json::json!(["generated/*", "legacy/*"])
+    );
+    assert_eq!(
+        option("ignore")["sources"],
+        serde_json::json!(["(top level)", "[profiles.agent.search]"])
+    );
+    assert_eq!(option("max-results")["value"], 1);
+    assert_eq!(json["commands"]["extract"].as_array().unwrap().len(), 2);
+
+    let stdout = stdout_of(&["config", "show", path, "-o", "plain"]);
+    assert!(stdout.contains("max-results = 1  # [search]"), "{}", stdout);
+
+    // Unknown profiles and options are errors
+    let output = run_probe(&["search", "checkout_total", path, "--profile", "ci"]);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("Unknown profile 'ci'"));
+
+    fs::write(
+        project.path().join(".probe.toml"),
+        "[search]\nmax-result = 1\n",
+    )
+    .unwrap();
+    let output = run_probe(&["search", "checkout_total", path]);
+    assert!(!output.status.success());
+    assert!(
+        String::from_utf8_lossy(&output.stderr).contains("Unknown option 'max-result' in [search]")
+    );
+}
+
+#[test]
+fn test_configuration_flags_can_be_turned_off() {
+    let temp_dir = TempDir::new().unwrap();
+    let dir = temp_dir.path();
+    fs::write(dir.join(".probe.toml"), "allow-tests = true\n").unwrap();
+    fs::create_dir_all(dir.join("src")).unwrap();
+    fs::create_dir_all(dir.join("tests")).unwrap();
+    fs::write(dir.join("src/lib.rs"), "fn checkout_total() {}\n").unwrap();
+    fs::write(
+        dir.join("tests/checkout_test.rs"),
+        "fn checkout_total_works() {}\n",
+    )
+    .unwrap();
+    let path = dir.to_str().unwrap();
+
+    // The configuration turns test files on, and --no-allow-tests turns them off again
+    let stdout = stdout_of(&["search", "checkout_total", path, "--format", "json"]);
+    assert_eq!(result_count(&stdout), 2, "{}", stdout);
+    let stdout = stdout_of(&[
+        "search",
+        "checkout_total",
+        path,
+        "--format",
+        "json",
+        "--no-allow-tests",
+    ]);
+    assert_eq!(result_count(&stdout), 1, "{}", stdout);
+    assert!(!stdout.contains("checkout_test.rs"), "{}", stdout);
+
+    // A flag and its negation can't be given together
+    let output = run_probe(&[
+        "search",
+        "checkout_total",
+        path,
+        "--allow-tests",
+        "--no-allow-tests",
+    ]);
+    assert!(!output.status.success());
+}
@@ -102,22 +102,12 @@ fn test_required_term_query() {
     let options = SearchOptions {
         path: temp_path,
         queries: &queries,
-        files_only: false,
         custom_ignores: &custom_ignores,
-        exclude_filenames: false,
-        language: None,
         reranker: "hybrid",
         frequency_search: false,
-        max_results: None,
-        max_bytes: None,
-        max_tokens: None,
         allow_tests: true,
-        no_merge: false,
         merge_threshold: Some(5),
-        dry_run: false,
-        session: None,
-        timeout: 30,
-        exact: false,
+        ..Default::default()
     };
 
     // Print the temp_path for debugging
@@ -210,22 +200,12 @@ fn test_excluded_term_query() {
     let options = SearchOptions {
         path: temp_path,
         queries: &queries,
-        files_only: false,
         custom_ignores: &custom_ignores,
-        exclude_filenames: false,
-        language: None,
         reranker: "hybrid",
         frequency_search: false,
-        max_results: None,
-        max_bytes: None,
-        max_tokens: None,
         allow_tests: true,
-        no_merge: false,
         merge_threshold: Some(5),
-        dry_run: false,
-        session: None,
-        timeout: 30,
-        exact: false,
+        ..Default::default()
     };
 
     // Print the query for debugging
@@ -304,20 +284,11 @@ fn test_or_query() {
         queries: &queries,
         files_only: true, // Use files_only to ensure we find all matching files
         custom_ignores: &custom_ignores,
-        exclude_filenames: false,
-        language: None,
         reranker: "hybrid",
         frequency_search: true, // Enable frequency search to improve matching
-        max_results: None,
-        max_bytes: None,
-        max_tokens: None,
         allow_tests: true,
-        no_merge: false,
         merge_threshold: Some(5),
-        dry_run: false,
-        session: None,
-        timeout: 30,
-        exact: false,
+        ..Default::default()
     };
 
     // Print the test files for debugging
@@ -402,22 +373,12 @@ fn test_complex_query_or() {
     let options = SearchOptions {
         path: temp_path,
         queries: &queries,
-        files_only: false,
         custom_ignores: &custom_ignores,
-        exclude_filenames: false,
-        language: None,
         reranker: "hybrid",
         frequency_search: true, // Enable frequency search to improve matching
-        max_results: None,
-        max_bytes: None,
-        max_tokens: None,
         allow_tests: true,
-        no_merge: false,
         merge_threshold: Some(5),
-        dry_run: false,
-        session: None,
-        timeout: 30,
-        exact: false,
+        ..Default::default()
     };
 
     // Print the test files for debugging
@@ -500,22 +461,12 @@ fn test_complex_query_exclusion() {
     let options = SearchOptions {
         path: temp_path,
         queries: &queries,
-        files_only: false,
         custom_ignores: &custom_ignores,
-        exclude_filenames: false,
-        language: None,
         reranker: "hybrid",
         frequency_search: false,
-        max_results: None,
-        max_bytes: None,
-        max_tokens: None,
         allow_tests: true,
-        no_merge: false,
         merge_threshold: Some(5),
-        dry_run: false,
-        session: None,
-        timeout: 30,
-        exact: false,
+        ..Default::default()
     };
 
     // Print the query for debugging
@@ -599,22 +550,12 @@ fn test_function() {
     let options = SearchOptions {
         path: temp_path,
         queries: &queries,
-        files_only: false,
         custom_ignores: &custom_ignores,
-        exclude_filenames: false,
-        language: None,
         reranker: "hybrid",
         frequency_search: false,
-        max_results: None,
-        max_bytes: None,
-        max_tokens: None,
         allow_tests: true,
-        no_merge: false,
         merge_threshold: Some(5),
-        dry_run: false,
-        session: None,
-        timeout: 30,
-        exact: false,
+        ..Default::default()
     };
 
     // Run the search
@@ -696,6 +637,9 @@ fn test_filter_code_block_with_ast() {
             set
         },
         exact: false,
+        fuzzy_terms: HashMap::new(),
+        synonym_terms: HashMap::new(),
+        vocabulary: None,
     };
 
     // Create term matches for a block
@@ -772,6 +716,9 @@ fn test_filter_tokenized_block() {
             set
         },
         exact: false,
+        fuzzy_terms: HashMap::new(),
+        synonym_terms: HashMap::new(),
+        vocabulary: None,
     };
 
     // Import the function from probe crate
@@ -844,6 +791,9 @@ fn test_filter_tokenized_block() {
         term_indices: term_indices_or.clone(),
         excluded_terms: HashSet::new(),
         exact: false,
+        fuzzy_terms: HashMap::new(),
+        synonym_terms: HashMap::new(),
+        vocabulary: None,
     };
 
     // Test with only keywordGamma
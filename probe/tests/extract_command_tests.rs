@@ -149,25 +149,7 @@ fn test_format_and_print_extraction_results() {
         lines: (1, 5),
         node_type: "function".to_string(),
         code: "fn test() {\n    println!(\"Hello\");\n}".to_string(),
-        matched_by_filename: None,
-        rank: None,
-        score: None,
-        tfidf_score: None,
-        bm25_score: None,
-        tfidf_rank: None,
-        bm25_rank: None,
-        new_score: None,
-        hybrid2_rank: None,
-        combined_score_rank: None,
-        file_unique_terms: None,
-        file_total_matches: None,
-        file_match_rank: None,
-        block_unique_terms: None,
-        block_total_matches: None,
-        parent_file_id: None,
-        block_id: None,
-        matched_keywords: None,
-        tokenized_content: None,
+        ..Default::default()
     };
 
     // Test different formats
@@ -1013,7 +995,7 @@ index cb2cb64..3717769 100644
 "#;
 
     // Parse the git diff
-    let file_paths = extract_file_paths_from_git_diff(diff_content, true);
+    let file_paths = extract_file_paths_from_git_diff(diff_content, true, &[]);
 
     // Verify that we extracted the correct file path and line number
     assert_eq!(file_paths.len(), 1, "Should extract exactly one file path");
@@ -1062,7 +1044,7 @@ index abcdef1..1234567 100644
 "#;
 
     // Parse the git diff
-    let file_paths = extract_file_paths_from_git_diff(diff_content, true);
+    let file_paths = extract_file_paths_from_git_diff(diff_content, true, &[]);
 
     // Verify that we extracted the correct file paths and line numbers
     assert_eq!(file_paths.len(), 2, "Should extract exactly two file paths");
@@ -51,6 +51,10 @@ fn standalone_function() {
         keep_input: false,
         prompt: None,
         instructions: None,
+        workspace: None,
+        template: None,
+        metrics: false,
+        expand_context: None,
     };
 
     // Call handle_extract
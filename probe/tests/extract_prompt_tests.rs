@@ -100,3 +100,268 @@ fn test() {
         "User instructions should match the input"
     );
 }
+
+/// Write a project prompt template into `dir`/.probe/prompts
+fn write_project_prompt(dir: &std::path::Path, name: &str, content: &str) {
+    let prompts_dir = dir.join(".probe").join("prompts");
+    fs::create_dir_all(&prompts_dir).unwrap();
+    fs::write(prompts_dir.join(name), content).unwrap();
+}
+
+fn run_probe(dir: &std::path::Path, home: &std::path::Path, args: &[&str]) -> std::process::Output {
+    Command::new(env!("CARGO_BIN_EXE_probe"))
+        .args(args)
+        .current_dir(dir)
+        .env("HOME", home)
+        .output()
+        .expect("Failed to execute command")
+}
+
+#[test]
+fn test_extract_with_named_prompt_template() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let home = tempfile::tempdir().unwrap();
+    fs::write(
+        temp_dir.path().join("lib.rs"),
+        "fn first() {\n    println!(\"first\");\n}\n\nfn second() {\n    println!(\"second\");\n}\n",
+    )
+    .unwrap();
+    write_project_prompt(
+        temp_dir.path(),
+        "review.md",
+        "+++\ndescription = \"Review a change\"\nformat = \"json\"\n+++\nReview {{files}} ({{language}}).\nTask: {{instructions}}\n",
+    );
+
+    let output = run_probe(
+        temp_dir.path(),
+        home.path(),
+        &[
+            "extract",
+            "lib.rs:2",
+            "--prompt",
+            "review",
+            "--instructions",
+            "Check error handling",
+        ],
+    );
+    assert!(
+        output.status.success(),
+        "stderr: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+
+    // The template's front matter chose the JSON format
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let json: serde_json::Value =
+        serde_json::from_str(&stdout[stdout.find('{').unwrap()..]).unwrap();
+    let system_prompt = json["system_prompt"].as_str().unwrap();
+    assert!(
+        system_prompt.starts_with("Review lib.rs:1-3 (rust)."),
+        "{}",
+        system_prompt
+    );
+    assert!(system_prompt.contains("Task: Check error handling"));
+    // Instructions rendered into the template are not repeated
+    assert!(json.get("user_instructions").is_none());
+}
+
+#[test]
+fn test_extract_prompt_token_budget() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let home = tempfile::tempdir().unwrap();
+    let body = "    let value = compute_something_long(1, 2, 3, 4, 5, 6, 7, 8, 9);\n".repeat(20);
+    fs::write(
+        temp_dir.path().join("lib.rs"),
+        format!("fn first() {{\n{body}}}\n\nfn second() {{\n{body}}}\n"),
+    )
+    .unwrap();
+    write_project_prompt(
+        temp_dir.path(),
+        "small.md",
+        "+++\ntoken_budget = 800\n+++\nLook at {{files}}.\n",
+    );
+
+    let output = run_probe(
+        temp_dir.path(),
+        home.path(),
+        &[
+            "extract",
+            "lib.rs:2",
+            "lib.rs:25",
+            "--prompt",
+            "small",
+            "-o",
+            "json",
+        ],
+    );
+    assert!(output.status.success());
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let json: serde_json::Value =
+        serde_json::from_str(&stdout[stdout.find('{').unwrap()..]).unwrap();
+    assert_eq!(json["results"].as_array().unwrap().len(), 1);
+    assert_eq!(json["system_prompt"], "Look at lib.rs:1-22.\n");
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("Left out 1 result over the token budget"),
+        "{}",
+        stderr
+    );
+}
+
+#[test]
+fn test_extract_api_renders_prompt_within_budget() {
+    use probe::extract::prompts::{Prompt, PromptSource};
+    use probe::extract::{extract, ExtractInput, ExtractRequest};
+
+    let temp_dir = tempfile::tempdir().unwrap();
+    let body = "    let value = compute_something_long(1, 2, 3, 4, 5, 6, 7, 8, 9);\n".repeat(20);
+    let file = temp_dir.path().join("lib.rs");
+    fs::write(
+        &file,
+        format!("fn first() {{\n{body}}}\n\nfn second() {{\n{body}}}\n"),
+    )
+    .unwrap();
+    let file = file.to_string_lossy().to_string();
+
+    let prompt = Prompt::parse(
+        "small",
+        PromptSource::File,
+        None,
+        "+++\ntoken_budget = 800\n+++\nLook at {{files}} in {{language}}.\n",
+    )
+    .unwrap();
+    let response = extract(ExtractRequest {
+        input: ExtractInput::Targets(vec![format!("{}:2", file), format!("{}:25", file)]),
+        custom_ignores: Vec::new(),
+        context_lines: 0,
+        diff: false,
+        allow_tests: false,
+        workspace: None,
+        metrics: false,
+        expand_context: None,
+        prompt: Some(prompt),
+        instructions: Some("Check error handling".to_string()),
+    })
+    .unwrap();
+
+    // The second function doesn't fit the budget, and the totals only count what's left
+    assert_eq!(response.left_out, 1);
+    assert_eq!(response.results.len(), 1);
+    assert_eq!(response.total_bytes, response.results[0].code.len());
+    assert_eq!(
+        response.system_prompt.as_deref(),
+        Some(format!("Look at {}:1-22 in rust.\n", file).as_str())
+    );
+    // Instructions the prompt doesn't include are shown separately
+    assert_eq!(
+        response.instructions.as_deref(),
+        Some("Check error handling")
+    );
+}
+
+#[test]
+fn test_extract_api_budget_stops_at_first_result_over_it() {
+    use probe::extract::prompts::{Prompt, PromptSource};
+    use probe::extract::{extract, ExtractInput, ExtractRequest};
+
+    let temp_dir = tempfile::tempdir().unwrap();
+    let body = "    let value = compute_something_long(1, 2, 3, 4, 5, 6, 7, 8, 9);\n".repeat(20);
+    let file = temp_dir.path().join("lib.rs");
+    fs::write(
+        &file,
+        format!("fn first() {{\n{body}}}\n\nfn second() {{\n{body}}}\n\nfn third() {{}}\n"),
+    )
+    .unwrap();
+    let file = file.to_string_lossy().to_string();
+
+    let prompt = Prompt::parse(
+        "small",
+        PromptSource::File,
+        None,
+        "+++\ntoken_budget = 800\n+++\nLook at {{files}}.\n",
+    )
+    .unwrap();
+    let response = extract(ExtractRequest {
+        input: ExtractInput::Targets(vec![
+            format!("{}:2", file),
+            format!("{}:25", file),
+            format!("{}:47", file),
+        ]),
+        custom_ignores: Vec::new(),
+        context_lines: 0,
+        diff: false,
+        allow_tests: false,
+        workspace: None,
+        metrics: false,
+        expand_context: None,
+        prompt: Some(prompt),
+        instructions: None,
+    })
+    .unwrap();
+
+    // The third function would fit after the first, but the second one doesn't fit
+    assert_eq!(response.left_out, 2);
+    assert_eq!(response.results.len(), 1);
+    assert_eq!(response.results[0].lines, (1, 22));
+}
+
+#[test]
+fn test_prompts_list_and_show() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let home = tempfile::tempdir().unwrap();
+    write_project_prompt(
+        temp_dir.path(),
+        "review.md",
+        "+++\ndescription = \"Project review\"\nmodel = \"claude-sonnet\"\n+++\nReview {{files}}\n",
+    );
+    let user_dir = home.path().join(".config").join("probe").join("prompts");
+    fs::create_dir_all(&user_dir).unwrap();
+    fs::write(user_dir.join("review.md"), "User review").unwrap();
+    fs::write(user_dir.join("explain.txt"), "Explain {{diff}}").unwrap();
+
+    let output = run_probe(
+        temp_dir.path(),
+        home.path(),
+        &["prompts", "list", "-o", "json"],
+    );
+    assert!(output.status.success());
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    let prompts: Vec<(String, String)> = json["prompts"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|p| {
+            (
+                p["name"].as_str().unwrap().to_string(),
+                p["source"].as_str().unwrap().to_string(),
+            )
+        })
+        .collect();
+    // Project templates shadow user templates of the same name
+    assert!(prompts.contains(&("review".to_string(), "project".to_string())));
+    assert!(prompts.contains(&("explain".to_string(), "user".to_string())));
+    assert!(prompts.contains(&("engineer".to_string(), "builtin".to_string())));
+    assert_eq!(
+        prompts.iter().filter(|(name, _)| name == "review").count(),
+        1
+    );
+
+    let output = run_probe(
+        temp_dir.path(),
+        home.path(),
+        &["prompts", "show", "review", "-o", "json"],
+    );
+    assert!(output.status.success());
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(json["model"], "claude-sonnet");
+    assert_eq!(json["description"], "Project review");
+    assert_eq!(json["variables"], serde_json::json!(["files"]));
+
+    let output = run_probe(
+        temp_dir.path(),
+        home.path(),
+        &["prompts", "show", "missing"],
+    );
+    assert!(!output.status.success());
+}
@@ -0,0 +1,230 @@
+use serde_json::Value;
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+use tempfile::TempDir;
+
+// Run git in a test repository with a fixed identity
+fn git(dir: &Path, args: &[&str]) {
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(dir)
+        .args([
+            "-c",
+            "user.name=Test Author",
+            "-c",
+            "user.email=test@example.com",
+            "-c",
+            "commit.gpgsign=false",
+        ])
+        .args(args)
+        .output()
+        .expect("Failed to run git");
+    assert!(
+        output.status.success(),
+        "git {:?} failed: {}",
+        args,
+        String::from_utf8_lossy(&output.stderr)
+    );
+}
+
+fn commit_file(dir: &Path, file: &str, content: &str, message: &str) {
+    let path = dir.join(file);
+    fs::create_dir_all(path.parent().unwrap()).unwrap();
+    fs::write(path, content).unwrap();
+    git(dir, &["add", "-A"]);
+    git(dir, &["commit", "-q", "-m", message]);
+}
+
+// Create a repository with three commits: an initial version of a payments module, a
+// change inside one function, and the removal of another
+fn create_repository() -> TempDir {
+    let temp_dir = TempDir::new().unwrap();
+    let dir = temp_dir.path();
+    git(dir, &["init", "-q"]);
+
+    let initial = "pub fn charge_card(amount: u64) -> bool {\n    amount > 0\n}\n\n\
+                   pub fn legacy_refund(amount: u64) -> u64 {\n    amount\n}\n";
+    commit_file(dir, "src/payments.rs", initial, "Add payment processing");
+    commit_file(dir, "README.md", "# Payments\n", "Add readme");
+
+    let retried = "pub fn charge_card(amount: u64) -> bool {\n    let attempts = retry_backoff(3);\n    amount > 0 && attempts > 0\n}\n\n\
+                   pub fn legacy_refund(amount: u64) -> u64 {\n    amount\n}\n";
+    commit_file(dir, "src/payments.rs", retried, "Retry failed card charges");
+
+    let removed = "pub fn charge_card(amount: u64) -> bool {\n    let attempts = retry_backoff(3);\n    amount > 0 && attempts > 0\n}\n";
+    commit_file(
+        dir,
+        "src/payments.rs",
+        removed,
+        "Remove the old refund path",
+    );
+
+    temp_dir
+}
+
+fn run_history(args: &[&str]) -> Vec<Value> {
+    let output = Command::new(env!("CARGO_BIN_EXE_probe"))
+        .arg("history")
+        .args(args)
+        .args(["--format", "json"])
+        .output()
+        .expect("Failed to execute command");
+    assert!(
+        output.status.success(),
+        "probe history failed: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let json: Value = serde_json::from_slice(&output.stdout).expect("Output should be JSON");
+    assert_eq!(
+        json["summary"]["count"],
+        json["results"].as_array().unwrap().len()
+    );
+    json["results"].as_array().unwrap().clone()
+}
+
+#[test]
+fn test_history_finds_hunks_with_enclosing_symbol() {
+    let repo = create_repository();
+    let path = repo.path().to_str().unwrap();
+
+    let results = run_history(&["retry_backoff", path]);
+    assert!(!results.is_empty());
+    let hunk = results
+        .iter()
+        .find(|r| r["match"] == "hunk")
+        .expect("A hunk should match");
+    assert_eq!(hunk["file"], "src/payments.rs");
+    assert_eq!(hunk["symbol"], "charge_card");
+    assert_eq!(hunk["author"], "Test Author");
+    assert_eq!(hunk["summary"], "Retry failed card charges");
+    assert_eq!(hunk["commit"].as_str().unwrap().len(), 40);
+    assert!(hunk["code"]
+        .as_str()
+        .unwrap()
+        .contains("+    let attempts = retry_backoff(3);"));
+    assert_eq!(hunk["lines"][0], 2);
+}
+
+#[test]
+fn test_history_removed_code_uses_previous_version() {
+    let repo = create_repository();
+    let path = repo.path().to_str().unwrap();
+
+    // The function only exists before the commit that removed it
+    let results = run_history(&["legacy_refund", path]);
+    let removal = results
+        .iter()
+        .find(|r| r["match"] == "hunk" && r["summary"] == "Remove the old refund path")
+        .expect("The removal should match");
+    assert_eq!(removal["symbol"], "legacy_refund");
+    assert_eq!(removal["lines"][0], 5);
+}
+
+#[test]
+fn test_history_matches_messages_and_query_syntax() {
+    let repo = create_repository();
+    let path = repo.path().to_str().unwrap();
+
+    let results = run_history(&["readme", path]);
+    assert!(results
+        .iter()
+        .any(|r| r["match"] == "message" && r["summary"] == "Add readme"));
+
+    // Excluded terms work as in search; hunks are matched on their changed lines
+    let results = run_history(&["payment -card", path]);
+    assert!(!results.is_empty());
+    for result in &results {
+        let code = result["code"].as_str().unwrap();
+        let changed = if result["match"] == "hunk" {
+            code.lines()
+                .filter(|line| line.starts_with('+') || line.starts_with('-'))
+                .collect::<Vec<_>>()
+                .join("\n")
+        } else {
+            code.to_string()
+        };
+        assert!(!changed.contains("card"), "{} should be excluded", code);
+    }
+}
+
+#[test]
+fn test_history_limits() {
+    let repo = create_repository();
+    let path = repo.path().to_str().unwrap();
+
+    // Only the most recent commit is searched
+    let results = run_history(&["retry_backoff", path, "--max-commits", "1"]);
+    assert!(results
+        .iter()
+        .all(|r| r["summary"] == "Remove the old refund path"));
+
+    // A file limits the history to its commits
+    let readme = repo.path().join("README.md");
+    let results = run_history(&["payment", readme.to_str().unwrap()]);
+    assert!(!results.is_empty());
+    assert!(results.iter().all(|r| r["summary"] == "Add readme"));
+
+    let results = run_history(&["payment", path, "--max-results", "1"]);
+    assert_eq!(results.len(), 1);
+}
+
+#[test]
+fn test_history_jsonl_and_errors() {
+    let repo = create_repository();
+    let path = repo.path().to_str().unwrap();
+
+    let output = Command::new(env!("CARGO_BIN_EXE_probe"))
+        .args(["history", "retry", path, "--format", "jsonl"])
+        .output()
+        .expect("Failed to execute command");
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let records: Vec<Value> = stdout
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect();
+    let schema_path =
+        Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/schemas/jsonl_output_schema.json");
+    let schema: Value = serde_json::from_str(&fs::read_to_string(schema_path).unwrap()).unwrap();
+    let schema = jsonschema::JSONSchema::compile(&schema).unwrap();
+    for record in &records {
+        assert!(
+            schema.is_valid(record),
+            "{} does not match the schema",
+            record
+        );
+    }
+    let (summary, results) = records.split_last().unwrap();
+    assert_eq!(summary["type"], "summary");
+    assert!(!results.is_empty());
+    assert!(results.iter().all(|r| r["type"] == "history"));
+
+    let not_a_repo = TempDir::new().unwrap();
+    let output = Command::new(env!("CARGO_BIN_EXE_probe"))
+        .args(["history", "retry", not_a_repo.path().to_str().unwrap()])
+        .output()
+        .expect("Failed to execute command");
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("Not a git repository"));
+}
+
+#[test]
+fn test_history_without_git_executable() {
+    let repo = create_repository();
+    let empty_path = TempDir::new().unwrap();
+
+    let output = Command::new(env!("CARGO_BIN_EXE_probe"))
+        .args(["history", "retry", repo.path().to_str().unwrap()])
+        .env("PATH", empty_path.path())
+        .output()
+        .expect("Failed to execute command");
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.starts_with("Error: probe history requires a git executable"),
+        "{}",
+        stderr
+    );
+    assert!(!stderr.contains("Not a git repository"), "{}", stderr);
+}
@@ -136,22 +136,12 @@ fn test_search_single_term() {
     let options = SearchOptions {
         path: temp_dir.path(),
         queries: &queries,
-        files_only: false,
         custom_ignores: &custom_ignores,
         exclude_filenames: true,
-        language: None,
         reranker: "hybrid",
         frequency_search: false,
-        max_results: None,
-        max_bytes: None,
-        max_tokens: None,
-        allow_tests: false,
         no_merge: true,
-        merge_threshold: None,
-        dry_run: false,
-        session: None,
-        timeout: 30,
-        exact: false,
+        ..Default::default()
     };
 
     // Search for a single term
@@ -200,22 +190,12 @@ fn test_search_multiple_terms() {
     let options = SearchOptions {
         path: temp_dir.path(),
         queries: &queries,
-        files_only: false,
         custom_ignores: &custom_ignores,
         exclude_filenames: true,
-        language: None,
         reranker: "hybrid",
         frequency_search: false,
-        max_results: None,
-        max_bytes: None,
-        max_tokens: None,
-        allow_tests: false,
         no_merge: true,
-        merge_threshold: None,
-        dry_run: false,
-        session: None,
-        timeout: 30,
-        exact: false,
+        ..Default::default()
     };
 
     // Search for multiple terms
@@ -249,19 +229,10 @@ fn test_search_files_only() {
         files_only: true,
         custom_ignores: &custom_ignores,
         exclude_filenames: true,
-        language: None,
         reranker: "hybrid",
         frequency_search: false,
-        max_results: None,
-        max_bytes: None,
-        max_tokens: None,
-        allow_tests: false,
         no_merge: true,
-        merge_threshold: None,
-        dry_run: false,
-        session: None,
-        timeout: 30,
-        exact: false,
+        ..Default::default()
     };
 
     // Search for files only
@@ -321,22 +292,11 @@ fn test_search_include_filenames() {
     let options = SearchOptions {
         path: temp_dir.path(),
         queries: &queries,
-        files_only: false,
         custom_ignores: &custom_ignores,
-        exclude_filenames: false,
-        language: None,
         reranker: "hybrid",
         frequency_search: false,
-        max_results: None,
-        max_bytes: None,
-        max_tokens: None,
-        allow_tests: false,
         no_merge: true,
-        merge_threshold: None,
-        dry_run: false,
-        session: None,
-        timeout: 30,
-        exact: false,
+        ..Default::default()
     };
 
     // Search with filename matching enabled
@@ -377,22 +337,13 @@ fn test_search_with_limits() {
     let options = SearchOptions {
         path: temp_dir.path(),
         queries: &queries,
-        files_only: false,
         custom_ignores: &custom_ignores,
         exclude_filenames: true,
-        language: None,
         reranker: "hybrid",
         frequency_search: false,
         max_results: Some(2), // limit to 2 results
-        max_bytes: None,
-        max_tokens: None,
-        allow_tests: false,
         no_merge: true,
-        merge_threshold: None,
-        dry_run: false,
-        session: None,
-        timeout: 30,
-        exact: false,
+        ..Default::default()
     };
 
     // Search with limits
@@ -408,9 +359,9 @@ fn test_search_with_limits() {
     assert_eq!(limits.max_results, Some(2));
 
     // Should have skipped files if there were more than 2 matches
-    if search_results.results.len() == 2 && !search_results.skipped_files.is_empty() {
+    if search_results.results.len() == 2 && !search_results.skipped_results.is_empty() {
         // There were more matches that were skipped
-        assert!(!search_results.skipped_files.is_empty());
+        assert!(!search_results.skipped_results.is_empty());
     }
 }
 
@@ -427,22 +378,11 @@ fn test_frequency_search() {
     let options = SearchOptions {
         path: temp_dir.path(),
         queries: &queries,
-        files_only: false,
         custom_ignores: &custom_ignores,
         exclude_filenames: true,
-        language: None,
         reranker: "hybrid",
-        frequency_search: true,
-        max_results: None,
-        max_bytes: None,
-        max_tokens: None,
-        allow_tests: false,
         no_merge: true,
-        merge_threshold: None,
-        dry_run: false,
-        session: None,
-        timeout: 30,
-        exact: false,
+        ..Default::default()
     };
 
     // Search using frequency-based search
@@ -484,23 +424,13 @@ func main() {
     let options = SearchOptions {
         path: temp_dir.path(),
         queries: &queries,
-        files_only: false,
         custom_ignores: &custom_ignores,
         exclude_filenames: false, // filename matching is enabled by default
-        language: None,
         reranker: "hybrid",
         frequency_search: false,
-        max_results: None,
-        max_bytes: None,
-        max_tokens: None,
-        allow_tests: false,
         // using "all terms" mode
         no_merge: true,
-        merge_threshold: None,
-        dry_run: false,
-        session: None,
-        timeout: 30,
-        exact: false,
+        ..Default::default()
     };
 
     // Search for both terms in "all terms" mode
@@ -526,22 +456,12 @@ fn test_search_with_custom_ignores() {
     let options = SearchOptions {
         path: temp_dir.path(),
         queries: &queries,
-        files_only: false,
         custom_ignores: &custom_ignores,
         exclude_filenames: true,
-        language: None,
         reranker: "hybrid",
         frequency_search: false,
-        max_results: None,
-        max_bytes: None,
-        max_tokens: None,
-        allow_tests: false,
         no_merge: true,
-        merge_threshold: None,
-        dry_run: false,
-        session: None,
-        timeout: 30,
-        exact: false,
+        ..Default::default()
     };
 
     // Search with custom ignore patterns
@@ -643,22 +563,13 @@ fn calculate_product(a: i32, b: i32) -> i32 {
     let options = SearchOptions {
         path: temp_dir.path(),
         queries: &queries,
-        files_only: false,
         custom_ignores: &custom_ignores,
         exclude_filenames: true,
-        language: None,
         reranker: "combined",
         frequency_search: false,
-        max_results: None,
-        max_bytes: None,
-        max_tokens: None,
         allow_tests: true,
-        no_merge: false,
         merge_threshold: Some(5),
-        dry_run: false,
-        session: None,
-        timeout: 30,
-        exact: false,
+        ..Default::default()
     };
 
     // Perform search
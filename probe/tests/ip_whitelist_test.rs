@@ -14,22 +14,13 @@ fn test_ip_whitelist_stemming() {
     let options = SearchOptions {
         path: file_path.parent().unwrap().parent().unwrap(), // Use the tests directory
         queries: &queries,
-        files_only: false,
         custom_ignores: &custom_ignores,
         exclude_filenames: true,
-        language: None,
         reranker: "hybrid",
         frequency_search: true, // Use frequency search to get detailed term stats
-        exact: false,
-        max_results: None,
-        max_bytes: None,
-        max_tokens: None,
         allow_tests: true,
         no_merge: true,
-        merge_threshold: None,
-        dry_run: false,
-        session: None,
-        timeout: 30,
+        ..Default::default()
     };
 
     // Enable debug mode to see the actual terms
@@ -103,22 +94,12 @@ fn test_negative_terms_exclude_files() {
     let options = SearchOptions {
         path: file_path.parent().unwrap().parent().unwrap(), // Use the tests directory
         queries: &queries,
-        files_only: false,
         custom_ignores: &custom_ignores,
         exclude_filenames: true,
-        language: None,
         reranker: "hybrid",
-        frequency_search: true,
-        exact: false,
-        max_results: None,
-        max_bytes: None,
-        max_tokens: None,
         allow_tests: true,
         no_merge: true,
-        merge_threshold: None,
-        dry_run: false,
-        session: None,
-        timeout: 30,
+        ..Default::default()
     };
 
     // Enable debug mode to see the actual terms
@@ -0,0 +1,177 @@
+use probe::path_resolver::{JavaPathResolver, PathResolver};
+use std::fs;
+use std::io::Write;
+use std::path::Path;
+
+/// Writes a jar (zip archive) containing the given files.
+fn write_jar(path: &Path, files: &[(&str, &str)]) {
+    fs::create_dir_all(path.parent().unwrap()).unwrap();
+    let mut writer = zip::ZipWriter::new(fs::File::create(path).unwrap());
+    let options = zip::write::SimpleFileOptions::default();
+    for (name, content) in files {
+        writer.start_file(*name, options).unwrap();
+        writer.write_all(content.as_bytes()).unwrap();
+    }
+    writer.finish().unwrap();
+}
+
+#[test]
+fn test_java_resolves_maven_source_jar() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let repo = temp_dir.path().join("m2");
+    let sources = temp_dir.path().join("sources");
+
+    let version_dir = repo.join("org/slf4j/slf4j-api/2.0.9");
+    write_jar(
+        &version_dir.join("slf4j-api-2.0.9-sources.jar"),
+        &[
+            ("META-INF/MANIFEST.MF", "Manifest-Version: 1.0\n"),
+            ("org/slf4j/Logger.java", "public interface Logger {}\n"),
+        ],
+    );
+    // An older version must not be picked
+    fs::create_dir_all(repo.join("org/slf4j/slf4j-api/1.7.36")).unwrap();
+
+    let resolver = JavaPathResolver::with_locations(Some(repo), None, sources.clone());
+    let path = resolver.resolve("org.slf4j:slf4j-api").unwrap();
+
+    assert_eq!(path, sources.join("org.slf4j/slf4j-api/2.0.9"));
+    assert_eq!(
+        fs::read_to_string(path.join("org/slf4j/Logger.java")).unwrap(),
+        "public interface Logger {}\n"
+    );
+
+    // A second resolve reuses the unpacked directory
+    assert_eq!(resolver.resolve("org.slf4j:slf4j-api:2.0.9").unwrap(), path);
+}
+
+#[test]
+fn test_java_resolves_gradle_source_jar() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let cache = temp_dir.path().join("files-2.1");
+    let sources = temp_dir.path().join("sources");
+
+    let version_dir = cache.join("com.google.guava/guava/33.0.0-jre");
+    fs::create_dir_all(version_dir.join("1111")).unwrap();
+    fs::write(version_dir.join("1111/guava-33.0.0-jre.jar"), "binary").unwrap();
+    write_jar(
+        &version_dir.join("2222/guava-33.0.0-jre-sources.jar"),
+        &[(
+            "com/google/common/base/Strings.java",
+            "public final class Strings {}\n",
+        )],
+    );
+
+    let resolver = JavaPathResolver::with_locations(None, Some(cache), sources.clone());
+    let path = resolver.resolve("com.google.guava:guava").unwrap();
+
+    assert_eq!(path, sources.join("com.google.guava/guava/33.0.0-jre"));
+    assert!(path.join("com/google/common/base/Strings.java").is_file());
+}
+
+#[test]
+fn test_java_falls_back_to_version_directory_without_sources() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let repo = temp_dir.path().join("m2");
+    let version_dir = repo.join("junit/junit/4.13.2");
+    fs::create_dir_all(&version_dir).unwrap();
+    fs::write(version_dir.join("junit-4.13.2.pom"), "<project/>").unwrap();
+
+    let resolver =
+        JavaPathResolver::with_locations(Some(repo), None, temp_dir.path().join("sources"));
+    assert_eq!(resolver.resolve("junit:junit").unwrap(), version_dir);
+}
+
+#[test]
+fn test_java_missing_artifact() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let resolver = JavaPathResolver::with_locations(
+        Some(temp_dir.path().join("m2")),
+        Some(temp_dir.path().join("gradle")),
+        temp_dir.path().join("sources"),
+    );
+
+    assert!(resolver.resolve("org.example:missing").is_err());
+    assert!(resolver.resolve("org.example:missing:1.0").is_err());
+}
+
+#[test]
+fn test_java_split_module_and_subpath() {
+    let resolver = JavaPathResolver::new();
+
+    let (module, subpath) = resolver
+        .split_module_and_subpath("org.slf4j:slf4j-api")
+        .unwrap();
+    assert_eq!(module, "org.slf4j:slf4j-api");
+    assert_eq!(subpath, None);
+
+    let (module, subpath) = resolver
+        .split_module_and_subpath("org.slf4j:slf4j-api:2.0.9/org/slf4j/Logger.java")
+        .unwrap();
+    assert_eq!(module, "org.slf4j:slf4j-api:2.0.9");
+    assert_eq!(subpath, Some("org/slf4j/Logger.java".to_string()));
+
+    assert!(resolver.split_module_and_subpath("").is_err());
+    assert!(resolver.split_module_and_subpath("slf4j-api").is_err());
+    assert!(resolver
+        .split_module_and_subpath("org.slf4j:slf4j-api/../x")
+        .is_err());
+}
+
+/// Names in the sources directory of an artifact other than its unpacked versions' lock files
+fn leftovers(artifact_dir: &Path) -> Vec<String> {
+    let mut names: Vec<String> = fs::read_dir(artifact_dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name().to_string_lossy().to_string())
+        .filter(|name| !name.ends_with(".lock"))
+        .collect();
+    names.sort();
+    names
+}
+
+#[test]
+fn test_java_concurrent_unpacks_share_one_tree() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let repo = temp_dir.path().join("m2");
+    let sources = temp_dir.path().join("sources");
+    write_jar(
+        &repo.join("org/slf4j/slf4j-api/2.0.9/slf4j-api-2.0.9-sources.jar"),
+        &[("org/slf4j/Logger.java", "public interface Logger {}\n")],
+    );
+
+    // What an interrupted unpack left behind is replaced
+    let target = sources.join("org.slf4j/slf4j-api/2.0.9");
+    fs::create_dir_all(target.join("org")).unwrap();
+
+    let resolver = JavaPathResolver::with_locations(Some(repo), None, sources.clone());
+    std::thread::scope(|scope| {
+        for _ in 0..8 {
+            scope.spawn(|| {
+                let path = resolver.resolve("org.slf4j:slf4j-api:2.0.9").unwrap();
+                assert!(path.join("org/slf4j/Logger.java").is_file());
+            });
+        }
+    });
+
+    assert_eq!(leftovers(&sources.join("org.slf4j/slf4j-api")), ["2.0.9"]);
+}
+
+#[test]
+fn test_java_failed_unpack_leaves_no_staging_directory() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let repo = temp_dir.path().join("m2");
+    let sources = temp_dir.path().join("sources");
+    // The second entry needs a directory where the first one is a file
+    write_jar(
+        &repo.join("org/example/broken/1.0/broken-1.0-sources.jar"),
+        &[
+            ("org", "not a directory"),
+            ("org/Broken.java", "class Broken {}\n"),
+        ],
+    );
+
+    let resolver = JavaPathResolver::with_locations(Some(repo), None, sources.clone());
+    let _ = resolver.resolve("org.example:broken:1.0");
+
+    assert!(leftovers(&sources.join("org.example/broken")).is_empty());
+}
@@ -0,0 +1,245 @@
+use jsonschema::JSONSchema;
+use serde_json::{json, Value};
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+use tempfile::TempDir;
+
+// Helper function to load and compile one of the schemas in tests/schemas
+fn compile_schema(name: &str) -> JSONSchema {
+    let schema_path = Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/schemas")
+        .join(name);
+    let schema_str = fs::read_to_string(&schema_path).expect("Failed to read schema file");
+    let schema_value: Value = serde_json::from_str(&schema_str).expect("Failed to parse schema");
+    JSONSchema::compile(&schema_value).expect("Failed to compile schema")
+}
+
+fn assert_valid(schema: &JSONSchema, instance: &Value) {
+    if let Err(errors) = schema.validate(instance) {
+        let messages: Vec<String> = errors.map(|e| e.to_string()).collect();
+        panic!("{} does not match schema: {:?}", instance, messages);
+    }
+}
+
+// Helper function to create a small project with functions that search and query can find
+fn create_project(dir: &Path) {
+    fs::create_dir_all(dir.join("src")).unwrap();
+    fs::write(
+        dir.join("src/search.rs"),
+        "pub fn search_function(query: &str) -> bool {\n    query.contains(\"search\")\n}\n\n\
+         pub fn search_tags(tags: &[&str]) -> usize {\n    tags.len()\n}\n",
+    )
+    .unwrap();
+    fs::write(
+        dir.join("src/search.js"),
+        "// Special characters: \"quotes\", <tags> & ampersands\n\
+         function searchFunction(query) {\n  return query.includes('search');\n}\n",
+    )
+    .unwrap();
+}
+
+fn run_probe(args: &[&str]) -> String {
+    let output = Command::new(env!("CARGO_BIN_EXE_probe"))
+        .args(args)
+        .output()
+        .expect("Failed to execute command");
+    assert!(
+        output.status.success(),
+        "probe failed: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    String::from_utf8_lossy(&output.stdout).to_string()
+}
+
+// Parse JSON Lines output, requiring every line to be a record matching the schema
+fn parse_jsonl(stdout: &str) -> Vec<Value> {
+    let schema = compile_schema("jsonl_output_schema.json");
+    let records: Vec<Value> = stdout
+        .lines()
+        .map(|line| serde_json::from_str(line).expect("Every line should be a JSON object"))
+        .collect();
+    for record in &records {
+        assert_valid(&schema, record);
+    }
+
+    // Results come first, then exactly one summary counting them
+    let (summary, results) = records.split_last().expect("Output should not be empty");
+    assert_eq!(summary["type"], "summary");
+    assert!(results.iter().all(|r| r["type"] == "result"));
+    assert_eq!(summary["count"], results.len());
+    records
+}
+
+#[test]
+fn test_search_jsonl_output() {
+    let temp_dir = TempDir::new().unwrap();
+    create_project(temp_dir.path());
+
+    let stdout = run_probe(&[
+        "search",
+        "search",
+        temp_dir.path().to_str().unwrap(),
+        "--format",
+        "jsonl",
+    ]);
+    let records = parse_jsonl(&stdout);
+    assert!(records.len() > 1, "Search should find results");
+    assert!(records[..records.len() - 1]
+        .iter()
+        .all(|r| r["code"].is_string()));
+}
+
+#[test]
+fn test_extract_jsonl_output() {
+    let temp_dir = TempDir::new().unwrap();
+    create_project(temp_dir.path());
+    let file = temp_dir.path().join("src/search.rs");
+    let target = format!("{}:2", file.display());
+
+    let records = parse_jsonl(&run_probe(&["extract", &target, "--format", "jsonl"]));
+    assert_eq!(records.len(), 2);
+    assert_eq!(records[0]["lines"], json!([1, 3]));
+    assert!(records[0]["code"]
+        .as_str()
+        .unwrap()
+        .contains("fn search_function"));
+    assert!(records[1]["total_tokens"].is_number());
+
+    // Dry runs leave out the code
+    let records = parse_jsonl(&run_probe(&[
+        "extract",
+        &target,
+        "--format",
+        "jsonl",
+        "--dry-run",
+    ]));
+    assert_eq!(records.len(), 2);
+    assert!(records[0].get("code").is_none());
+}
+
+#[test]
+fn test_query_jsonl_output() {
+    let temp_dir = TempDir::new().unwrap();
+    create_project(temp_dir.path());
+
+    let records = parse_jsonl(&run_probe(&[
+        "query",
+        "pub fn $NAME($$$PARAMS) $$$BODY",
+        temp_dir.path().to_str().unwrap(),
+        "--language",
+        "rust",
+        "--format",
+        "jsonl",
+    ]));
+    assert_eq!(records.len(), 3);
+    assert_eq!(records[0]["node_type"], "match");
+    assert!(records[0]["column_start"].is_number());
+}
+
+#[test]
+fn test_search_batch_jsonl_output() {
+    let temp_dir = TempDir::new().unwrap();
+    create_project(temp_dir.path());
+    let batch = temp_dir.path().join("queries.json");
+    fs::write(&batch, r#"["search function", "tags"]"#).unwrap();
+
+    let stdout = run_probe(&[
+        "search",
+        "--batch",
+        batch.to_str().unwrap(),
+        temp_dir.path().join("src").to_str().unwrap(),
+        "--format",
+        "jsonl",
+    ]);
+    let schema = compile_schema("jsonl_output_schema.json");
+    let records: Vec<Value> = stdout
+        .lines()
+        .map(|line| serde_json::from_str(line).expect("Every line should be a JSON object"))
+        .collect();
+    for record in &records {
+        assert_valid(&schema, record);
+    }
+
+    // Every query's results are preceded by its query record and followed by its summary
+    let groups: Vec<&[Value]> = records
+        .split_inclusive(|r| r["type"] == "summary")
+        .collect();
+    assert_eq!(groups.len(), 2);
+    for (group, query) in groups.iter().zip(["search function", "tags"]) {
+        assert_eq!(group[0]["type"], "query");
+        assert_eq!(group[0]["query"], query);
+        let results = &group[1..group.len() - 1];
+        assert!(!results.is_empty(), "Query {} should find results", query);
+        assert!(results.iter().all(|r| r["type"] == "result"));
+        assert_eq!(group[group.len() - 1]["count"], results.len());
+    }
+}
+
+#[test]
+fn test_query_sarif_output() {
+    let temp_dir = TempDir::new().unwrap();
+    create_project(temp_dir.path());
+    let pattern = "pub fn $NAME($$$PARAMS) $$$BODY";
+
+    let stdout = run_probe(&[
+        "query",
+        pattern,
+        temp_dir.path().to_str().unwrap(),
+        "--language",
+        "rust",
+        "--format",
+        "sarif",
+    ]);
+    let sarif: Value = serde_json::from_str(&stdout).expect("SARIF output should be pure JSON");
+    assert_valid(&compile_schema("sarif_output_schema.json"), &sarif);
+
+    let run = &sarif["runs"][0];
+    assert_eq!(run["tool"]["driver"]["name"], "probe");
+    assert_eq!(
+        run["tool"]["driver"]["rules"][0]["shortDescription"]["text"],
+        pattern
+    );
+
+    let mut regions: Vec<(u64, u64)> = run["results"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|result| {
+            assert_eq!(result["ruleId"], run["tool"]["driver"]["rules"][0]["id"]);
+            let location = &result["locations"][0]["physicalLocation"];
+            assert!(location["artifactLocation"]["uri"]
+                .as_str()
+                .unwrap()
+                .ends_with("src/search.rs"));
+            let region = &location["region"];
+            (
+                region["startLine"].as_u64().unwrap(),
+                region["endLine"].as_u64().unwrap(),
+            )
+        })
+        .collect();
+    regions.sort();
+    assert_eq!(regions, vec![(1, 3), (5, 7)]);
+}
+
+#[test]
+fn test_jsonl_and_sarif_schemas_reject_invalid_output() {
+    let jsonl = compile_schema("jsonl_output_schema.json");
+    assert!(!jsonl.is_valid(&json!({"file": "a.rs", "lines": [1, 2], "node_type": "function"})));
+    assert!(!jsonl.is_valid(&json!({"type": "result", "file": "a.rs", "lines": [1]})));
+    assert!(!jsonl.is_valid(&json!({"type": "summary", "count": 1, "results": []})));
+    assert!(jsonl.is_valid(&json!({"type": "summary", "count": 0})));
+
+    let sarif = compile_schema("sarif_output_schema.json");
+    assert!(!sarif.is_valid(&json!({"version": "2.0.0", "runs": []})));
+    assert!(!sarif.is_valid(&json!({
+        "version": "2.1.0",
+        "runs": [{
+            "tool": {"driver": {"name": "probe"}},
+            "results": [{"message": {"text": "x"}, "locations": [{
+                "physicalLocation": {"artifactLocation": {"uri": "a.rs"}, "region": {"startLine": 0}}
+            }]}]
+        }]
+    })));
+}
@@ -0,0 +1,150 @@
+use serde_json::Value;
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+use tempfile::TempDir;
+
+const SOURCE: &str = r#"pub fn route(method: &str, path: &str, user: Option<u32>, admin: bool) -> u16 {
+    if method == "GET" {
+        for segment in path.split('/') {
+            if segment.is_empty() {
+                continue;
+            }
+            if segment == "admin" && !admin {
+                return 403;
+            }
+        }
+        200
+    } else if method == "POST" {
+        match user {
+            Some(id) if id > 0 => 201,
+            Some(_) => 400,
+            None => 401,
+        }
+    } else {
+        405
+    }
+}
+
+pub fn is_ready(count: u32) -> bool {
+    count > 0
+}
+"#;
+
+fn create_project() -> TempDir {
+    let temp_dir = TempDir::new().unwrap();
+    let src = temp_dir.path().join("src");
+    fs::create_dir_all(&src).unwrap();
+    fs::write(src.join("router.rs"), SOURCE).unwrap();
+    temp_dir
+}
+
+fn run_probe(args: &[&str]) -> String {
+    let output = Command::new(env!("CARGO_BIN_EXE_probe"))
+        .args(args)
+        .output()
+        .expect("Failed to execute command");
+    assert!(
+        output.status.success(),
+        "probe {:?} failed: {}",
+        args,
+        String::from_utf8_lossy(&output.stderr)
+    );
+    String::from_utf8_lossy(&output.stdout).to_string()
+}
+
+#[test]
+fn test_metrics_report_thresholds() {
+    let project = create_project();
+    let path = project.path().to_str().unwrap();
+
+    // With the default thresholds, no function is reported
+    let stdout = run_probe(&["metrics", path, "--format", "json"]);
+    let json: Value = serde_json::from_str(&stdout).expect("Output should be JSON");
+    assert_eq!(json["summary"]["analyzed"], 2, "{}", stdout);
+    assert_eq!(json["summary"]["count"], 0, "{}", stdout);
+
+    let stdout = run_probe(&[
+        "metrics",
+        path,
+        "--max-complexity",
+        "5",
+        "--max-params",
+        "3",
+        "--format",
+        "json",
+    ]);
+    let json: Value = serde_json::from_str(&stdout).unwrap();
+    let results = json["results"].as_array().unwrap();
+    assert_eq!(results.len(), 1, "{}", stdout);
+    assert_eq!(results[0]["symbol"], "route");
+    assert_eq!(results[0]["lines"], serde_json::json!([1, 21]));
+    // if, for, if, if, &&, else if and the three match arms that are not `_`
+    assert_eq!(results[0]["metrics"]["cyclomatic_complexity"], 10);
+    assert_eq!(results[0]["metrics"]["max_nesting"], 3);
+    assert_eq!(results[0]["metrics"]["parameters"], 4);
+    assert_eq!(
+        results[0]["exceeds"],
+        serde_json::json!(["complexity", "parameters"])
+    );
+
+    let stdout = run_probe(&["metrics", path, "--all", "--format", "jsonl"]);
+    let records: Vec<Value> = stdout
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect();
+    let schema_path =
+        Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/schemas/jsonl_output_schema.json");
+    let schema: Value = serde_json::from_str(&fs::read_to_string(schema_path).unwrap()).unwrap();
+    let schema = jsonschema::JSONSchema::compile(&schema).unwrap();
+    for record in &records {
+        assert!(
+            schema.is_valid(record),
+            "{} does not match the schema",
+            record
+        );
+    }
+    assert_eq!(records.len(), 3);
+    assert_eq!(records[1]["symbol"], "is_ready");
+    assert_eq!(records[1]["metrics"]["cyclomatic_complexity"], 1);
+
+    let stdout = run_probe(&["metrics", path, "--max-nesting", "2", "--format", "plain"]);
+    assert!(
+        stdout.contains("Measured 2 functions") && stdout.contains("router.rs:1-21 route"),
+        "{}",
+        stdout
+    );
+}
+
+#[test]
+fn test_search_and_extract_metrics() {
+    let project = create_project();
+    let path = project.path().to_str().unwrap();
+
+    let stdout = run_probe(&["search", "segment", path, "--metrics", "--format", "json"]);
+    let json: Value = serde_json::from_str(&stdout).expect("Output should be JSON");
+    let results = json["results"].as_array().unwrap();
+    assert!(!results.is_empty(), "{}", stdout);
+    assert!(results
+        .iter()
+        .all(|result| result["metrics"]["cyclomatic_complexity"].is_u64()));
+
+    let target = format!("{}/src/router.rs#is_ready", path);
+    let stdout = run_probe(&["extract", &target, "--metrics", "--format", "json"]);
+    let json: Value = serde_json::from_str(&stdout).expect("Output should be JSON");
+    assert_eq!(
+        json["results"][0]["metrics"],
+        serde_json::json!({
+            "lines": 3,
+            "code_lines": 3,
+            "cyclomatic_complexity": 1,
+            "max_nesting": 0,
+            "parameters": 1
+        })
+    );
+
+    // Without --metrics, results have no metrics
+    let stdout = run_probe(&["extract", &target, "--format", "json"]);
+    let json: Value = serde_json::from_str(&stdout).unwrap();
+    assert!(json["results"][0].get("metrics").is_none(), "{}", stdout);
+}
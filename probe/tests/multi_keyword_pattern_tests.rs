@@ -25,6 +25,9 @@ fn test_multi_keyword_pattern_generation() {
         term_indices,
         excluded_terms: HashSet::new(),
         exact: false,
+        fuzzy_terms: HashMap::new(),
+        synonym_terms: HashMap::new(),
+        vocabulary: None,
     };
 
     // Generate patterns
@@ -86,6 +89,9 @@ fn test_excluded_term_pattern_generation() {
         term_indices,
         excluded_terms,
         exact: false,
+        fuzzy_terms: HashMap::new(),
+        synonym_terms: HashMap::new(),
+        vocabulary: None,
     };
 
     // Generate patterns
@@ -130,6 +136,9 @@ fn test_and_expression_pattern_generation() {
         term_indices,
         excluded_terms: HashSet::new(),
         exact: false,
+        fuzzy_terms: HashMap::new(),
+        synonym_terms: HashMap::new(),
+        vocabulary: None,
     };
 
     // Generate patterns
@@ -189,6 +198,9 @@ fn test_or_expression_pattern_generation() {
         term_indices,
         excluded_terms: HashSet::new(),
         exact: false,
+        fuzzy_terms: HashMap::new(),
+        synonym_terms: HashMap::new(),
+        vocabulary: None,
     };
 
     // Generate patterns
@@ -270,6 +282,9 @@ fn test_complex_expression_pattern_generation() {
         term_indices,
         excluded_terms: HashSet::new(),
         exact: false,
+        fuzzy_terms: HashMap::new(),
+        synonym_terms: HashMap::new(),
+        vocabulary: None,
     };
 
     // Generate patterns
@@ -348,6 +363,9 @@ fn test_pattern_deduplication() {
         term_indices,
         excluded_terms: HashSet::new(),
         exact: false,
+        fuzzy_terms: HashMap::new(),
+        synonym_terms: HashMap::new(),
+        vocabulary: None,
     };
 
     // Generate patterns
@@ -0,0 +1,138 @@
+use probe::path_resolver::{NpmPathResolver, PathResolver};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Creates a package directory with a package.json.
+fn create_package(dir: &Path, name: &str, version: &str) -> PathBuf {
+    fs::create_dir_all(dir).unwrap();
+    fs::write(
+        dir.join("package.json"),
+        format!(r#"{{"name": "{}", "version": "{}"}}"#, name, version),
+    )
+    .unwrap();
+    dir.to_path_buf()
+}
+
+/// Creates a pnpm workspace with a store containing react and @types/node.
+fn create_pnpm_workspace(root: &Path) {
+    fs::write(
+        root.join("pnpm-workspace.yaml"),
+        "packages:\n  - 'packages/*'\n",
+    )
+    .unwrap();
+    create_package(&root.join("packages/ui"), "@acme/ui", "0.1.0");
+
+    let store = root.join("node_modules/.pnpm");
+    create_package(
+        &store.join("react@17.0.2/node_modules/react"),
+        "react",
+        "17.0.2",
+    );
+    create_package(
+        &store.join("react@18.2.0/node_modules/react"),
+        "react",
+        "18.2.0",
+    );
+    create_package(
+        &store.join("@types+node@20.1.0/node_modules/@types/node"),
+        "@types/node",
+        "20.1.0",
+    );
+    create_package(
+        &store.join("react-dom@18.2.0_react@18.2.0/node_modules/react-dom"),
+        "react-dom",
+        "18.2.0",
+    );
+}
+
+#[test]
+fn test_npm_resolves_from_pnpm_store() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let root = temp_dir.path();
+    create_pnpm_workspace(root);
+    let store = root.join("node_modules/.pnpm");
+    let resolver = NpmPathResolver::with_root(root.to_path_buf());
+
+    // The newest stored version wins when no version is given
+    assert_eq!(
+        resolver.resolve("react").unwrap(),
+        store.join("react@18.2.0/node_modules/react")
+    );
+    assert_eq!(
+        resolver.resolve("react@17.0.2").unwrap(),
+        store.join("react@17.0.2/node_modules/react")
+    );
+    assert_eq!(
+        resolver.resolve("@types/node@20.1.0").unwrap(),
+        store.join("@types+node@20.1.0/node_modules/@types/node")
+    );
+    // Peer dependency suffixes are ignored when matching versions
+    assert_eq!(
+        resolver.resolve("react-dom@18.2.0").unwrap(),
+        store.join("react-dom@18.2.0_react@18.2.0/node_modules/react-dom")
+    );
+}
+
+#[test]
+fn test_npm_resolves_workspace_package_from_member() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let root = temp_dir.path();
+    create_pnpm_workspace(root);
+    let member = create_package(&root.join("packages/app"), "@acme/app", "0.1.0");
+
+    let resolver = NpmPathResolver::with_root(member);
+    assert_eq!(
+        resolver.resolve("@acme/ui").unwrap(),
+        root.join("packages/ui")
+    );
+    // The workspace root store is found from a nested member
+    assert!(resolver.resolve("react").is_ok());
+}
+
+#[cfg(unix)]
+#[test]
+fn test_npm_follows_direct_dependency_symlink() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let root = temp_dir.path();
+    create_pnpm_workspace(root);
+    let target = root.join("node_modules/.pnpm/react@17.0.2/node_modules/react");
+    std::os::unix::fs::symlink(&target, root.join("node_modules/react")).unwrap();
+
+    // The linked direct dependency takes precedence over the newest stored version
+    let resolver = NpmPathResolver::with_root(root.to_path_buf());
+    assert_eq!(
+        resolver.resolve("react").unwrap(),
+        target.canonicalize().unwrap()
+    );
+}
+
+#[test]
+fn test_npm_missing_package() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let root = temp_dir.path();
+    create_pnpm_workspace(root);
+    let resolver = NpmPathResolver::with_root(root.to_path_buf());
+
+    assert!(resolver.resolve("left-pad").is_err());
+    assert!(resolver.resolve("react@99.0.0").is_err());
+}
+
+#[test]
+fn test_npm_split_module_and_subpath() {
+    let resolver = NpmPathResolver::new();
+
+    let (module, subpath) = resolver
+        .split_module_and_subpath("react@18.2.0/index.js")
+        .unwrap();
+    assert_eq!(module, "react@18.2.0");
+    assert_eq!(subpath, Some("index.js".to_string()));
+
+    let (module, subpath) = resolver
+        .split_module_and_subpath("@types/node@20.1.0/fs.d.ts")
+        .unwrap();
+    assert_eq!(module, "@types/node@20.1.0");
+    assert_eq!(subpath, Some("fs.d.ts".to_string()));
+
+    assert!(resolver.split_module_and_subpath("").is_err());
+    assert!(resolver.split_module_and_subpath("@types").is_err());
+}
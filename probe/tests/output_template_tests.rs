@@ -0,0 +1,145 @@
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+use tempfile::TempDir;
+
+// Helper function to create a project and a template printing one line per result
+fn create_project(dir: &Path) -> std::path::PathBuf {
+    fs::create_dir_all(dir.join("src")).unwrap();
+    fs::write(
+        dir.join("src/parser.rs"),
+        "pub fn parse_input(input: &str) -> bool {\n    input.contains(\"<tag>\")\n}\n",
+    )
+    .unwrap();
+
+    let template = dir.join("results.tmpl");
+    fs::write(
+        &template,
+        "{{ for r in results }}{r.file}|{r.start_line}-{r.end_line}|{r.language}|{r.node_type}|{r.code | xml}\n\
+         {{ endfor }}{command}|{query}|{totals.count}|{totals.total_bytes}\n",
+    )
+    .unwrap();
+    template
+}
+
+fn run_probe(args: &[&str]) -> (bool, String, String) {
+    let output = Command::new(env!("CARGO_BIN_EXE_probe"))
+        .args(args)
+        .output()
+        .expect("Failed to execute command");
+    (
+        output.status.success(),
+        String::from_utf8_lossy(&output.stdout).to_string(),
+        String::from_utf8_lossy(&output.stderr).to_string(),
+    )
+}
+
+const CODE: &str = "pub fn parse_input(input: &str) -> bool {\n    input.contains(\"<tag>\")\n}";
+const CODE_XML: &str =
+    "pub fn parse_input(input: &amp;str) -&gt; bool {\n    input.contains(&quot;&lt;tag&gt;&quot;)\n}";
+
+#[test]
+fn test_search_and_extract_with_template() {
+    let temp_dir = TempDir::new().unwrap();
+    let template = create_project(temp_dir.path());
+    let src = temp_dir.path().join("src");
+    let file = src.join("parser.rs");
+    let file = file.to_str().unwrap();
+
+    let (success, stdout, _) = run_probe(&[
+        "search",
+        "parse",
+        src.to_str().unwrap(),
+        "--template",
+        template.to_str().unwrap(),
+    ]);
+    assert!(success);
+    // Only the rendered template is printed
+    assert_eq!(
+        stdout,
+        format!(
+            "{}|1-3|rust|function_item|{}\nsearch|parse|1|{}\n",
+            file,
+            CODE_XML,
+            CODE.len()
+        )
+    );
+
+    let (success, stdout, _) = run_probe(&[
+        "extract",
+        &format!("{}:2", file),
+        "--template",
+        template.to_str().unwrap(),
+    ]);
+    assert!(success);
+    assert!(stdout.starts_with(&format!("{}|1-3|rust|", file)));
+    assert!(stdout.contains(&format!("|{}\nextract||1|", CODE_XML)));
+}
+
+#[test]
+fn test_query_with_builtin_and_custom_templates() {
+    let temp_dir = TempDir::new().unwrap();
+    let template = create_project(temp_dir.path());
+    let src = temp_dir.path().join("src");
+    let pattern = "pub fn $NAME($$$PARAMS) $$$BODY";
+
+    let (success, stdout, _) = run_probe(&[
+        "query",
+        pattern,
+        src.to_str().unwrap(),
+        "--language",
+        "rust",
+        "--template",
+        template.to_str().unwrap(),
+    ]);
+    assert!(success);
+    assert!(stdout.contains("|1-3|rust|match|"));
+    assert!(stdout.ends_with(&format!("query|{}|1|{}\n", pattern, CODE.len())));
+
+    let (success, stdout, _) = run_probe(&[
+        "query",
+        pattern,
+        src.to_str().unwrap(),
+        "--language",
+        "rust",
+        "--template",
+        "xml",
+    ]);
+    assert!(success);
+    let doc = roxmltree::Document::parse(&stdout).expect("Built-in xml template should be XML");
+    assert_eq!(
+        doc.descendants()
+            .find(|n| n.has_tag_name("count"))
+            .and_then(|n| n.text()),
+        Some("1")
+    );
+}
+
+#[test]
+fn test_invalid_templates_are_reported() {
+    let temp_dir = TempDir::new().unwrap();
+    create_project(temp_dir.path());
+    let src = temp_dir.path().join("src");
+
+    let (success, _, stderr) = run_probe(&[
+        "search",
+        "parse",
+        src.to_str().unwrap(),
+        "--template",
+        "no-such-template",
+    ]);
+    assert!(!success);
+    assert!(stderr.contains("built-in templates: markdown, xml"));
+
+    let broken = temp_dir.path().join("broken.tmpl");
+    fs::write(&broken, "{{ for r in results }}{r.file}").unwrap();
+    let (success, _, stderr) = run_probe(&[
+        "search",
+        "parse",
+        src.to_str().unwrap(),
+        "--template",
+        broken.to_str().unwrap(),
+    ]);
+    assert!(!success);
+    assert!(stderr.contains("Invalid template"));
+}
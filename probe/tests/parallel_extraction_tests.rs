@@ -35,6 +35,10 @@ fn test_parallel_file_extraction() {
         diff: false,
         allow_tests: true,
         instructions: None,
+        workspace: None,
+        template: None,
+        metrics: false,
+        expand_context: None,
         keep_input: false,
         prompt: None,
     };
@@ -97,6 +101,10 @@ fn function_four() {
         diff: false,
         allow_tests: true,
         instructions: None,
+        workspace: None,
+        template: None,
+        metrics: false,
+        expand_context: None,
         keep_input: false,
         prompt: None,
     };
@@ -164,6 +172,10 @@ fn test_parallel_extraction_performance() {
         diff: false,
         allow_tests: true,
         instructions: None,
+        workspace: None,
+        template: None,
+        metrics: false,
+        expand_context: None,
         keep_input: false,
         prompt: None,
     };
@@ -175,3 +187,91 @@ fn test_parallel_extraction_performance() {
         "Parallel extraction should succeed with many files"
     );
 }
+
+#[test]
+fn test_extract_returns_structured_results() {
+    use probe::extract::{extract, ExtractInput, ExtractRequest};
+
+    let temp_dir = tempdir().unwrap();
+    let file_path = temp_dir.path().join("lib.rs");
+    fs::write(
+        &file_path,
+        "fn outer() {\n    let x = 1;\n    println!(\"{}\", x);\n}\n\nfn other() {}\n",
+    )
+    .unwrap();
+    let file = file_path.to_string_lossy().to_string();
+    let missing = temp_dir.path().join("missing.rs");
+
+    let request = |input| ExtractRequest {
+        input,
+        custom_ignores: Vec::new(),
+        context_lines: 0,
+        diff: false,
+        allow_tests: false,
+        workspace: None,
+        metrics: false,
+        expand_context: None,
+        prompt: None,
+        instructions: None,
+    };
+
+    // A line inside a function and the whole function by symbol give one result, since
+    // duplicates are removed
+    let response = extract(request(ExtractInput::Targets(vec![
+        format!("{}:2", file),
+        format!("{}#outer", file),
+        missing.to_string_lossy().to_string(),
+    ])))
+    .unwrap();
+    assert_eq!(response.targets.len(), 3);
+    assert_eq!(response.targets[1].symbol.as_deref(), Some("outer"));
+    assert_eq!(response.results.len(), 1);
+    assert_eq!(response.results[0].lines, (1, 4));
+    assert_eq!(response.total_bytes, response.results[0].code.len());
+    assert!(response.total_tokens > 0);
+
+    // Files that can't be read are reported instead of failing the request
+    assert_eq!(response.skipped.len(), 1);
+    assert!(response.skipped[0].target.ends_with("missing.rs"));
+    assert!(!response.skipped[0].reason.is_empty());
+
+    // File references are also found in free text
+    let text = format!("error: unused variable\n --> {}:6:4\n", file);
+    let response = extract(request(ExtractInput::Text(text))).unwrap();
+    assert_eq!(response.results.len(), 1);
+    assert!(response.results[0].code.contains("fn other"));
+    assert!(response.skipped.is_empty());
+}
+
+#[test]
+fn test_extract_custom_ignores_apply_to_their_request_only() {
+    use probe::extract::{extract, ExtractInput, ExtractRequest};
+
+    let temp_dir = tempdir().unwrap();
+    let generated = temp_dir.path().join("generated");
+    fs::create_dir(&generated).unwrap();
+    let file_path = generated.join("lib.rs");
+    fs::write(&file_path, "fn generated() {}\n").unwrap();
+    let file = file_path.to_string_lossy().to_string();
+
+    let request = |custom_ignores: Vec<String>| ExtractRequest {
+        input: ExtractInput::Targets(vec![file.clone()]),
+        custom_ignores,
+        context_lines: 0,
+        diff: false,
+        allow_tests: false,
+        workspace: None,
+        metrics: false,
+        expand_context: None,
+        prompt: None,
+        instructions: None,
+    };
+
+    let response = extract(request(vec!["generated".to_string()])).unwrap();
+    assert!(response.results.is_empty());
+    assert_eq!(response.skipped.len(), 1);
+
+    // The ignores of the previous request don't carry over to the next one
+    let response = extract(request(Vec::new())).unwrap();
+    assert_eq!(response.results.len(), 1);
+}
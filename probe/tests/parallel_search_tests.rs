@@ -68,22 +68,15 @@ fn test_parallel_file_search() {
     let options = SearchOptions {
         path: base_path,
         queries: &queries,
-        files_only: false,
         custom_ignores: &custom_ignores,
-        exclude_filenames: false,
-        language: None,
         reranker: "hybrid",
         frequency_search: false,
         max_results: Some(100),
         max_bytes: Some(1_000_000),
         max_tokens: Some(100_000),
         allow_tests: true,
-        no_merge: false,
         merge_threshold: Some(5),
-        dry_run: false,
-        session: None,
-        timeout: 30,
-        exact: false,
+        ..Default::default()
     };
 
     // Measure search time
@@ -140,6 +133,7 @@ fn test_structured_patterns_search() {
         &custom_ignores,
         true,
         None,
+        &Default::default(),
     );
     let duration = start_time.elapsed();
 
@@ -200,22 +194,15 @@ fn function_{}() {{
     let options = SearchOptions {
         path: base_path,
         queries: &queries,
-        files_only: false,
         custom_ignores: &custom_ignores,
-        exclude_filenames: false,
-        language: None,
         reranker: "hybrid",
         frequency_search: false,
         max_results: Some(100),
         max_bytes: Some(1_000_000),
         max_tokens: Some(100_000),
         allow_tests: true,
-        no_merge: false,
         merge_threshold: Some(5),
-        dry_run: false,
-        session: None,
-        timeout: 30,
-        exact: false,
+        ..Default::default()
     };
 
     // Measure search time
@@ -303,22 +290,15 @@ fn function_with_blocks_{}() {{
     let options = SearchOptions {
         path: base_path,
         queries: &queries,
-        files_only: false,
         custom_ignores: &custom_ignores,
-        exclude_filenames: false,
-        language: None,
         reranker: "hybrid",
         frequency_search: false,
         max_results: Some(100),
         max_bytes: Some(1_000_000),
         max_tokens: Some(100_000),
         allow_tests: true,
-        no_merge: false,
         merge_threshold: Some(5),
-        dry_run: false,
-        session: None,
-        timeout: 30,
-        exact: false,
+        ..Default::default()
     };
 
     // Measure search time
@@ -755,3 +755,21 @@ fn test_rust_split_module_and_subpath() {
     // Test error cases
     assert!(resolver.split_module_and_subpath("").is_err());
 }
+
+#[test]
+fn test_rust_split_crate_name_and_subpath() {
+    let resolver = RustPathResolver::new();
+
+    let (module, subpath) = resolver.split_module_and_subpath("serde").unwrap();
+    assert_eq!(module, "serde");
+    assert_eq!(subpath, None);
+
+    let (module, subpath) = resolver
+        .split_module_and_subpath("serde@1.0.200/src/de")
+        .unwrap();
+    assert_eq!(module, "serde@1.0.200");
+    assert_eq!(subpath, Some("src/de".to_string()));
+
+    assert!(resolver.split_module_and_subpath("serde/../x").is_err());
+    assert!(resolver.split_module_and_subpath("@1.0.0").is_err());
+}
@@ -0,0 +1,174 @@
+use probe::search::{perform_probe, perform_workspace_probe, SearchOptions};
+use probe::workspace::Workspace;
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+use tempfile::TempDir;
+
+// Helper function to create a project whose code uses domain words
+fn create_project(dir: &Path) {
+    fs::create_dir_all(dir.join("src")).unwrap();
+    fs::write(
+        dir.join("src/session.rs"),
+        "pub fn login_user(name: &str) -> bool {\n    !name.is_empty()\n}\n",
+    )
+    .unwrap();
+    fs::write(
+        dir.join("src/books.rs"),
+        "pub fn post_invoiceline(amount: i64) -> i64 {\n    amount * 2\n}\n",
+    )
+    .unwrap();
+}
+
+fn search(dir: &Path, query: &str) -> String {
+    let output = Command::new(env!("CARGO_BIN_EXE_probe"))
+        .args(["search", query, dir.to_str().unwrap(), "--format", "json"])
+        .env_remove("PROBE_VOCABULARY")
+        .output()
+        .expect("Failed to execute command");
+    assert!(
+        output.status.success(),
+        "probe failed: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    String::from_utf8_lossy(&output.stdout).to_string()
+}
+
+#[test]
+fn test_vocabulary_file_adds_synonyms_and_terms() {
+    let temp_dir = TempDir::new().unwrap();
+    create_project(temp_dir.path());
+    let src = temp_dir.path().join("src");
+
+    // Without a vocabulary neither word is found
+    assert!(!search(&src, "auth").contains("login_user"));
+    assert!(!search(&src, "invoice").contains("post_invoiceline"));
+
+    // The vocabulary in a parent directory applies, and is read on every search
+    fs::write(
+        temp_dir.path().join(".probe-vocabulary.toml"),
+        "terms = [\"invoice\", \"line\"]\nsynonyms = [[\"auth\", \"authentication\", \"login\"]]\n",
+    )
+    .unwrap();
+    assert!(search(&src, "auth").contains("login_user"));
+    assert!(search(&src, "authentication").contains("login_user"));
+    assert!(search(&src, "invoice").contains("post_invoiceline"));
+
+    // Exact terms aren't expanded
+    assert!(!search(&src, "\"auth\"").contains("login_user"));
+}
+
+#[test]
+fn test_invalid_vocabulary_file_is_reported() {
+    let temp_dir = TempDir::new().unwrap();
+    create_project(temp_dir.path());
+    fs::write(
+        temp_dir.path().join(".probe-vocabulary.toml"),
+        "synonym = [[\"auth\", \"login\"]]\n",
+    )
+    .unwrap();
+
+    let output = Command::new(env!("CARGO_BIN_EXE_probe"))
+        .args(["search", "auth", temp_dir.path().to_str().unwrap()])
+        .env_remove("PROBE_VOCABULARY")
+        .output()
+        .expect("Failed to execute command");
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("Invalid vocabulary file"));
+}
+
+// Helper function to create a project with the same code as every other, and its own
+// synonyms
+fn create_project_with_synonyms(dir: &Path, synonyms: &str) {
+    fs::create_dir_all(dir).unwrap();
+    fs::write(
+        dir.join("lib.rs"),
+        "pub fn login_user(name: &str) -> bool {\n    !name.is_empty()\n}\n\n\
+         pub fn send_invoice(amount: i64) -> i64 {\n    amount * 2\n}\n",
+    )
+    .unwrap();
+    fs::write(
+        dir.join(".probe-vocabulary.toml"),
+        format!("synonyms = [{synonyms}]\n"),
+    )
+    .unwrap();
+}
+
+fn search_options<'a>(path: &'a Path, queries: &'a [String]) -> SearchOptions<'a> {
+    SearchOptions {
+        path,
+        queries,
+        allow_tests: true,
+        ..Default::default()
+    }
+}
+
+fn found(path: &Path, query: &str, symbol: &str) -> bool {
+    let queries = vec![query.to_string()];
+    perform_probe(&search_options(path, &queries))
+        .unwrap()
+        .results
+        .iter()
+        .any(|result| result.code.contains(symbol))
+}
+
+#[test]
+fn test_concurrent_searches_use_their_own_vocabulary() {
+    std::env::remove_var("PROBE_VOCABULARY");
+    let temp_dir = TempDir::new().unwrap();
+    let accounts = temp_dir.path().join("accounts");
+    let billing = temp_dir.path().join("billing");
+    create_project_with_synonyms(&accounts, r#"["auth", "login"]"#);
+    create_project_with_synonyms(&billing, r#"["pay", "invoice"]"#);
+
+    std::thread::scope(|scope| {
+        for _ in 0..4 {
+            scope.spawn(|| {
+                assert!(found(&accounts, "auth", "login_user"));
+                assert!(!found(&accounts, "pay", "send_invoice"));
+            });
+            scope.spawn(|| {
+                assert!(found(&billing, "pay", "send_invoice"));
+                assert!(!found(&billing, "auth", "login_user"));
+            });
+        }
+    });
+}
+
+#[test]
+fn test_workspace_search_uses_the_vocabulary_of_every_root() {
+    std::env::remove_var("PROBE_VOCABULARY");
+    let temp_dir = TempDir::new().unwrap();
+    create_project_with_synonyms(&temp_dir.path().join("accounts"), r#"["auth", "login"]"#);
+    create_project_with_synonyms(&temp_dir.path().join("billing"), r#"["pay", "invoice"]"#);
+    let workspace_file = temp_dir.path().join("workspace.toml");
+    fs::write(
+        &workspace_file,
+        r#"
+name = "shop"
+
+[[roots]]
+name = "accounts"
+path = "accounts"
+
+[[roots]]
+name = "billing"
+path = "billing"
+"#,
+    )
+    .unwrap();
+    let workspace = Workspace::load(&workspace_file).unwrap();
+
+    // The synonyms of the second root apply as well as those of the first
+    for (query, symbol) in [("auth", "login_user"), ("pay", "send_invoice")] {
+        let queries = vec![query.to_string()];
+        let results =
+            perform_workspace_probe(&workspace, &search_options(Path::new("."), &queries))
+                .unwrap()
+                .results;
+        assert!(
+            results.iter().any(|result| result.code.contains(symbol)),
+            "'{query}' should find {symbol}"
+        );
+    }
+}
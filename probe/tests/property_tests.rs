@@ -93,6 +93,8 @@ proptest! {
             documents: &docs_refs,
             query: &query,
             pre_tokenized: None,
+            fuzzy_terms: None,
+            vocabulary: None,
         };
 
         // This should never panic
@@ -0,0 +1,144 @@
+use probe::path_resolver::{PathResolver, PythonPathResolver};
+use std::fs;
+use std::path::Path;
+
+/// Creates a minimal site-packages directory with a few installed distributions.
+fn create_site_packages(root: &Path) -> std::path::PathBuf {
+    let site = root.join("lib").join("python3.11").join("site-packages");
+
+    // A package whose import name matches its distribution name
+    fs::create_dir_all(site.join("requests")).unwrap();
+    fs::write(site.join("requests").join("__init__.py"), "").unwrap();
+    fs::write(
+        site.join("requests").join("adapters.py"),
+        "class HTTPAdapter: pass\n",
+    )
+    .unwrap();
+    let dist_info = site.join("requests-2.31.0.dist-info");
+    fs::create_dir_all(&dist_info).unwrap();
+    fs::write(
+        dist_info.join("METADATA"),
+        "Metadata-Version: 2.1\nName: requests\n",
+    )
+    .unwrap();
+
+    // A distribution whose import name differs (PyYAML -> yaml), via top_level.txt
+    fs::create_dir_all(site.join("yaml")).unwrap();
+    fs::write(site.join("yaml").join("__init__.py"), "").unwrap();
+    let dist_info = site.join("PyYAML-6.0.1.dist-info");
+    fs::create_dir_all(&dist_info).unwrap();
+    fs::write(
+        dist_info.join("METADATA"),
+        "Metadata-Version: 2.1\nName: PyYAML\n",
+    )
+    .unwrap();
+    fs::write(dist_info.join("top_level.txt"), "_yaml\nyaml\n").unwrap();
+
+    // A single-module distribution without top_level.txt, via RECORD
+    fs::write(site.join("six.py"), "").unwrap();
+    let dist_info = site.join("six-1.16.0.dist-info");
+    fs::create_dir_all(&dist_info).unwrap();
+    fs::write(
+        dist_info.join("RECORD"),
+        "six.py,sha256=abc,1000\nsix-1.16.0.dist-info/RECORD,,\n",
+    )
+    .unwrap();
+
+    site
+}
+
+#[test]
+fn test_python_resolves_package_directory() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let site = create_site_packages(temp_dir.path());
+    let resolver = PythonPathResolver::with_site_packages(vec![site.clone()]);
+
+    assert_eq!(resolver.resolve("requests").unwrap(), site.join("requests"));
+}
+
+#[test]
+fn test_python_resolves_distribution_name_via_dist_info() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let site = create_site_packages(temp_dir.path());
+    let resolver = PythonPathResolver::with_site_packages(vec![site.clone()]);
+
+    // Distribution names are normalized, so any spelling works
+    assert_eq!(resolver.resolve("PyYAML").unwrap(), site.join("yaml"));
+    assert_eq!(resolver.resolve("pyyaml").unwrap(), site.join("yaml"));
+    assert_eq!(resolver.resolve("six").unwrap(), site.join("six.py"));
+}
+
+#[test]
+fn test_python_resolves_editable_install_via_pth() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let site = create_site_packages(temp_dir.path());
+
+    // An editable install: the source lives outside site-packages
+    let checkout = temp_dir.path().join("checkout").join("src");
+    fs::create_dir_all(checkout.join("mylib")).unwrap();
+    fs::write(checkout.join("mylib").join("__init__.py"), "").unwrap();
+    fs::write(
+        site.join("__editable__.mylib-0.1.0.pth"),
+        format!("# editable\nimport os\n{}\n", checkout.display()),
+    )
+    .unwrap();
+    let dist_info = site.join("my_lib-0.1.0.dist-info");
+    fs::create_dir_all(&dist_info).unwrap();
+    fs::write(dist_info.join("METADATA"), "Name: my-lib\n").unwrap();
+    fs::write(dist_info.join("top_level.txt"), "mylib\n").unwrap();
+
+    let resolver = PythonPathResolver::with_site_packages(vec![site]);
+    assert_eq!(resolver.resolve("my-lib").unwrap(), checkout.join("mylib"));
+    assert_eq!(resolver.resolve("mylib").unwrap(), checkout.join("mylib"));
+}
+
+#[test]
+fn test_python_resolves_dotted_import_name() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let site = create_site_packages(temp_dir.path());
+    fs::create_dir_all(site.join("google").join("protobuf")).unwrap();
+
+    let resolver = PythonPathResolver::with_site_packages(vec![site.clone()]);
+    assert_eq!(
+        resolver.resolve("google.protobuf").unwrap(),
+        site.join("google").join("protobuf")
+    );
+}
+
+#[test]
+fn test_python_missing_package() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let site = create_site_packages(temp_dir.path());
+    let resolver = PythonPathResolver::with_site_packages(vec![site]);
+
+    assert!(resolver
+        .resolve("this_package_should_not_exist_12345")
+        .is_err());
+    assert!(PythonPathResolver::with_site_packages(vec![])
+        .resolve("requests")
+        .is_err());
+}
+
+#[test]
+fn test_python_split_module_and_subpath() {
+    let resolver = PythonPathResolver::new();
+
+    let (module, subpath) = resolver.split_module_and_subpath("requests").unwrap();
+    assert_eq!(module, "requests");
+    assert_eq!(subpath, None);
+
+    let (module, subpath) = resolver
+        .split_module_and_subpath("requests/adapters.py")
+        .unwrap();
+    assert_eq!(module, "requests");
+    assert_eq!(subpath, Some("adapters.py".to_string()));
+
+    let (module, subpath) = resolver
+        .split_module_and_subpath("google.protobuf/internal/")
+        .unwrap();
+    assert_eq!(module, "google.protobuf");
+    assert_eq!(subpath, Some("internal".to_string()));
+
+    assert!(resolver.split_module_and_subpath("").is_err());
+    assert!(resolver.split_module_and_subpath("requests/../x").is_err());
+}
@@ -224,3 +224,29 @@ fn auto_detected_function() {
 
     Ok(())
 }
+
+#[test]
+fn test_query_request_returns_structured_results() -> Result<()> {
+    use probe::query::{query, QueryRequest};
+
+    let temp_dir = tempdir()?;
+    fs::write(
+        temp_dir.path().join("math.rs"),
+        "fn add(a: i32, b: i32) -> i32 {\n    a + b\n}\n\nfn sub(a: i32, b: i32) -> i32 {\n    a - b\n}\n",
+    )?;
+
+    let response = query(QueryRequest {
+        pattern: "fn $NAME($$$PARAMS) $$$BODY".to_string(),
+        path: temp_dir.path().to_path_buf(),
+        workspace: None,
+        language: Some("rust".to_string()),
+        ignore: Vec::new(),
+        allow_tests: false,
+        max_results: Some(1),
+    })?;
+
+    assert_eq!(response.matches.len(), 1);
+    assert_eq!(response.total_bytes, response.matches[0].matched_text.len());
+    assert!(response.total_tokens > 0);
+    Ok(())
+}
@@ -0,0 +1,290 @@
+use probe::path_resolver::{PathResolver, RustPathResolver};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Writes a crate manifest and returns the crate directory.
+fn create_crate(dir: &Path, name: &str, version: &str, extra: &str) -> PathBuf {
+    fs::create_dir_all(dir.join("src")).unwrap();
+    fs::write(
+        dir.join("Cargo.toml"),
+        format!(
+            "[package]\nname = \"{}\"\nversion = \"{}\"\nedition = \"2021\"\n\n[lib]\npath = \"src/lib.rs\"\n{}",
+            name, version, extra
+        ),
+    )
+    .unwrap();
+    fs::write(dir.join("src").join("lib.rs"), "").unwrap();
+    dir.to_path_buf()
+}
+
+/// Creates a workspace and a fake Cargo home matching its Cargo.lock.
+///
+/// Returns (workspace root, cargo home).
+fn create_fixture(root: &Path) -> (PathBuf, PathBuf) {
+    let workspace = root.join("workspace");
+    let cargo_home = root.join("cargo-home");
+
+    fs::create_dir_all(&workspace).unwrap();
+    fs::write(
+        workspace.join("Cargo.toml"),
+        "[workspace]\nmembers = [\n    \"crates/*\",\n]\n\n[workspace.dependencies]\nserde = \"1\"\n",
+    )
+    .unwrap();
+    create_crate(
+        &workspace.join("crates/app"),
+        "app",
+        "0.1.0",
+        "\n[dependencies]\nserde = { workspace = true }\nshared-utils = { path = \"../../../vendor/shared-utils\" }\n",
+    );
+    create_crate(
+        &workspace.join("crates/core"),
+        "core-lib",
+        "0.1.0",
+        "\n[dependencies]\nserde = \"=1.0.150\"\n",
+    );
+    create_crate(
+        &root.join("vendor/shared-utils"),
+        "shared-utils",
+        "0.3.0",
+        "",
+    );
+
+    fs::write(
+        workspace.join("Cargo.lock"),
+        r#"# This file is automatically @generated by Cargo.
+version = 3
+
+[[package]]
+name = "app"
+version = "0.1.0"
+dependencies = [
+ "serde 1.0.200",
+ "shared-utils",
+]
+
+[[package]]
+name = "core-lib"
+version = "0.1.0"
+dependencies = [
+ "serde 1.0.150",
+]
+
+[[package]]
+name = "serde"
+version = "1.0.150"
+source = "registry+https://github.com/rust-lang/crates.io-index"
+checksum = "aaaa"
+
+[[package]]
+name = "serde"
+version = "1.0.200"
+source = "registry+https://github.com/rust-lang/crates.io-index"
+checksum = "bbbb"
+
+[[package]]
+name = "serde_json"
+version = "1.0.100"
+source = "registry+https://github.com/rust-lang/crates.io-index"
+
+[[package]]
+name = "shared-utils"
+version = "0.3.0"
+
+[[package]]
+name = "tokio-util"
+version = "0.7.10"
+source = "git+https://github.com/tokio-rs/tokio?branch=master#abcdef0123456789abcdef0123456789abcdef01"
+"#,
+    )
+    .unwrap();
+
+    let registry = cargo_home.join("registry/src/index.crates.io-6f17d22bba15001f");
+    for (name, version) in [
+        ("serde", "1.0.150"),
+        ("serde", "1.0.200"),
+        ("serde", "1.0.210"),
+        ("serde_json", "1.0.100"),
+    ] {
+        create_crate(
+            &registry.join(format!("{}-{}", name, version)),
+            name,
+            version,
+            "",
+        );
+    }
+    fs::create_dir_all(registry.join("serde-1.0.200/src/de")).unwrap();
+
+    let checkout = cargo_home.join("git/checkouts/tokio-1234567890abcdef/abcdef0");
+    fs::create_dir_all(&checkout).unwrap();
+    fs::write(
+        checkout.join("Cargo.toml"),
+        "[workspace]\nmembers = [\"tokio\", \"tokio-util\"]\n",
+    )
+    .unwrap();
+    create_crate(&checkout.join("tokio"), "tokio", "1.35.0", "");
+    create_crate(&checkout.join("tokio-util"), "tokio-util", "0.7.10", "");
+
+    (workspace, cargo_home)
+}
+
+#[test]
+fn test_rust_offline_uses_locked_registry_version() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let (workspace, cargo_home) = create_fixture(temp_dir.path());
+    let registry = cargo_home.join("registry/src/index.crates.io-6f17d22bba15001f");
+    let resolver = RustPathResolver::with_locations(workspace, cargo_home.clone());
+
+    // The newest locked version wins over the newer but unlocked 1.0.210
+    assert_eq!(
+        resolver.resolve("serde").unwrap(),
+        registry.join("serde-1.0.200")
+    );
+    assert_eq!(
+        resolver.resolve("serde@1.0.150").unwrap(),
+        registry.join("serde-1.0.150")
+    );
+    // '-' and '_' are interchangeable in crate names
+    assert_eq!(
+        resolver.resolve("serde-json").unwrap(),
+        registry.join("serde_json-1.0.100")
+    );
+}
+
+#[test]
+fn test_rust_offline_prefers_version_of_root_package() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let (workspace, cargo_home) = create_fixture(temp_dir.path());
+    let registry = cargo_home.join("registry/src/index.crates.io-6f17d22bba15001f");
+
+    // Each member resolves the serde version it depends on
+    let core =
+        RustPathResolver::with_locations(workspace.join("crates/core/src"), cargo_home.clone());
+    assert_eq!(
+        core.resolve("serde").unwrap(),
+        registry.join("serde-1.0.150")
+    );
+    let app = RustPathResolver::with_locations(workspace.join("crates/app"), cargo_home.clone());
+    assert_eq!(
+        app.resolve("serde").unwrap(),
+        registry.join("serde-1.0.200")
+    );
+
+    // A required version that isn't downloaded falls back to the newest locked one
+    fs::remove_dir_all(registry.join("serde-1.0.150")).unwrap();
+    assert_eq!(
+        core.resolve("serde").unwrap(),
+        registry.join("serde-1.0.200")
+    );
+}
+
+#[test]
+fn test_rust_offline_pins_unlocked_version_from_registry() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let (workspace, cargo_home) = create_fixture(temp_dir.path());
+    let registry = cargo_home.join("registry/src/index.crates.io-6f17d22bba15001f");
+    let resolver = RustPathResolver::with_locations(workspace, cargo_home);
+
+    assert_eq!(
+        resolver.resolve("serde@1.0.210").unwrap(),
+        registry.join("serde-1.0.210")
+    );
+    assert!(resolver.resolve("serde@2.0.0").is_err());
+}
+
+#[test]
+fn test_rust_offline_resolves_workspace_members_and_path_deps() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let (workspace, cargo_home) = create_fixture(temp_dir.path());
+    // Resolving from inside a member still finds the workspace Cargo.lock
+    let resolver = RustPathResolver::with_locations(workspace.join("crates/app/src"), cargo_home);
+
+    let canonical = |path: PathBuf| path.canonicalize().unwrap();
+    assert_eq!(
+        resolver.resolve("core-lib").unwrap(),
+        canonical(workspace.join("crates/core"))
+    );
+    assert_eq!(
+        resolver.resolve("app").unwrap(),
+        canonical(workspace.join("crates/app"))
+    );
+    assert_eq!(
+        resolver.resolve("shared-utils").unwrap(),
+        canonical(temp_dir.path().join("vendor/shared-utils"))
+    );
+}
+
+#[test]
+fn test_rust_offline_resolves_git_checkout() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let (workspace, cargo_home) = create_fixture(temp_dir.path());
+    let resolver = RustPathResolver::with_locations(workspace, cargo_home.clone());
+
+    let path = resolver.resolve("tokio-util").unwrap();
+    assert_eq!(
+        path,
+        cargo_home
+            .join("git/checkouts/tokio-1234567890abcdef/abcdef0/tokio-util")
+            .canonicalize()
+            .unwrap()
+    );
+}
+
+#[test]
+fn test_rust_offline_missing_crate() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let (workspace, cargo_home) = create_fixture(temp_dir.path());
+    let resolver = RustPathResolver::with_locations(workspace, cargo_home);
+
+    assert!(resolver
+        .resolve("this_crate_should_not_exist_12345")
+        .is_err());
+}
+
+#[test]
+fn test_rust_offline_locked_version_not_downloaded() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let (workspace, cargo_home) = create_fixture(temp_dir.path());
+    let registry = cargo_home.join("registry/src/index.crates.io-6f17d22bba15001f");
+    fs::remove_dir_all(registry.join("serde_json-1.0.100")).unwrap();
+    create_crate(
+        &registry.join("serde_json-1.0.120"),
+        "serde_json",
+        "1.0.120",
+        "",
+    );
+    let resolver = RustPathResolver::with_locations(workspace, cargo_home);
+
+    // Another cached version isn't the locked one, so it isn't used
+    let error = resolver.resolve("serde_json").unwrap_err();
+    assert!(
+        error.contains("locked at 1.0.100 but not downloaded"),
+        "{}",
+        error
+    );
+    // Pinning the cached version still finds it
+    assert_eq!(
+        resolver.resolve("serde_json@1.0.120").unwrap(),
+        registry.join("serde_json-1.0.120")
+    );
+}
+
+#[test]
+fn test_rust_pinned_path_with_subpath() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let (workspace, cargo_home) = create_fixture(temp_dir.path());
+    let resolver = RustPathResolver::with_locations(workspace, cargo_home.clone());
+
+    let (module, subpath) = resolver
+        .split_module_and_subpath("serde@1.0.200/src/de")
+        .unwrap();
+    assert_eq!(module, "serde@1.0.200");
+    assert_eq!(subpath, Some("src/de".to_string()));
+
+    let base = resolver.resolve(&module).unwrap();
+    let full = base.join(subpath.unwrap());
+    assert_eq!(
+        full,
+        cargo_home.join("registry/src/index.crates.io-6f17d22bba15001f/serde-1.0.200/src/de")
+    );
+    assert!(full.is_dir());
+}
@@ -0,0 +1,373 @@
+use serde_json::Value;
+use std::fs;
+use std::path::Path;
+use std::process::{Command, Output};
+use tempfile::TempDir;
+
+// Helper function to create a project with several separately matching functions
+fn create_project(dir: &Path) {
+    fs::create_dir_all(dir.join("src")).unwrap();
+    for i in 0..6 {
+        fs::write(
+            dir.join("src").join(format!("widget_{}.rs", i)),
+            format!(
+                "pub fn build_widget_{i}() -> u32 {{\n    let widget = {i};\n    widget + 1\n}}\n"
+            ),
+        )
+        .unwrap();
+    }
+}
+
+// Run probe with HOME pointing at a temporary directory, so sessions are isolated
+fn run_probe(home: &Path, args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_probe"))
+        .args(args)
+        .env("HOME", home)
+        .env_remove("PROBE_SESSION_ID")
+        .output()
+        .expect("Failed to execute command")
+}
+
+fn parse_json(output: &Output) -> Value {
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let start = stdout.find('{').expect("No JSON in output");
+    serde_json::from_str(&stdout[start..]).expect("Invalid JSON output")
+}
+
+fn result_keys(json: &Value) -> Vec<String> {
+    json["results"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|r| format!("{}:{}", r["file"].as_str().unwrap(), r["lines"][0]))
+        .collect()
+}
+
+fn search_page(home: &Path, project: &Path, page: &str) -> Output {
+    run_probe(
+        home,
+        &[
+            "search",
+            "widget",
+            project.to_str().unwrap(),
+            "--session",
+            "pager",
+            "--page",
+            page,
+            "--max-results",
+            "2",
+            "--no-merge",
+            "--format",
+            "json",
+        ],
+    )
+}
+
+#[test]
+fn test_page_cursor_is_stable() {
+    let home = TempDir::new().unwrap();
+    let project = TempDir::new().unwrap();
+    create_project(project.path());
+
+    let page1 = search_page(home.path(), project.path(), "1");
+    assert!(page1.status.success());
+    let page1_keys = result_keys(&parse_json(&page1));
+    assert_eq!(page1_keys.len(), 2);
+
+    let page2 = search_page(home.path(), project.path(), "2");
+    assert!(page2.status.success());
+    let page2_keys = result_keys(&parse_json(&page2));
+    assert_eq!(page2_keys.len(), 2);
+    assert!(page2_keys.iter().all(|key| !page1_keys.contains(key)));
+
+    // Asking for a page again returns exactly the same blocks
+    let page1_again = search_page(home.path(), project.path(), "1");
+    assert_eq!(result_keys(&parse_json(&page1_again)), page1_keys);
+
+    // Pages can't be skipped
+    let page5 = search_page(home.path(), project.path(), "5");
+    assert!(!page5.status.success());
+    assert!(String::from_utf8_lossy(&page5.stderr).contains("next page for this query is 3"));
+
+    // Paging requires a session
+    let output = run_probe(
+        home.path(),
+        &[
+            "search",
+            "widget",
+            project.path().to_str().unwrap(),
+            "--page",
+            "1",
+        ],
+    );
+    assert!(!output.status.success());
+}
+
+#[test]
+fn test_paged_jsonl_output_only_carries_records() {
+    let home = TempDir::new().unwrap();
+    let project = TempDir::new().unwrap();
+    create_project(project.path());
+
+    let output = run_probe(
+        home.path(),
+        &[
+            "search",
+            "widget",
+            project.path().to_str().unwrap(),
+            "--session",
+            "lines",
+            "--page",
+            "1",
+            "--max-results",
+            "2",
+            "--no-merge",
+            "--format",
+            "jsonl",
+        ],
+    );
+    assert!(output.status.success());
+
+    // Every line of stdout is a JSON record; the session and page status go to stderr
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let types: Vec<String> = stdout
+        .lines()
+        .map(|line| {
+            let record: Value = serde_json::from_str(line)
+                .unwrap_or_else(|e| panic!("Not a JSON line: {:?} ({})", line, e));
+            record["type"].as_str().unwrap().to_string()
+        })
+        .collect();
+    assert_eq!(types, ["result", "result", "summary"]);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("Session ID: lines"), "{}", stderr);
+    assert!(stderr.contains("Page 1 (new)"), "{}", stderr);
+}
+
+#[test]
+fn test_session_list_show_and_clear() {
+    let home = TempDir::new().unwrap();
+    let project = TempDir::new().unwrap();
+    create_project(project.path());
+
+    assert!(search_page(home.path(), project.path(), "1")
+        .status
+        .success());
+    assert!(run_probe(
+        home.path(),
+        &[
+            "search",
+            "build",
+            project.path().to_str().unwrap(),
+            "--session",
+            "other",
+            "--format",
+            "json",
+        ],
+    )
+    .status
+    .success());
+
+    let list = parse_json(&run_probe(
+        home.path(),
+        &["session", "list", "--format", "json"],
+    ));
+    let mut ids: Vec<&str> = list["sessions"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|s| s["session_id"].as_str().unwrap())
+        .collect();
+    ids.sort();
+    assert_eq!(ids, vec!["other", "pager"]);
+
+    let show = parse_json(&run_probe(
+        home.path(),
+        &["session", "show", "pager", "--format", "json"],
+    ));
+    let queries = show["queries"].as_array().unwrap();
+    assert_eq!(queries.len(), 1);
+    assert_eq!(queries[0]["query"], "widget");
+    assert_eq!(queries[0]["pages"].as_array().unwrap().len(), 1);
+    // Blocks are listed by their block key, with the page that served them
+    let blocks = queries[0]["blocks"].as_array().unwrap();
+    assert_eq!(blocks.len(), 2);
+    for block in blocks {
+        let key = block["key"].as_str().unwrap();
+        assert!(key.contains("#function_item:build_widget_"), "{}", key);
+        assert!(block["file"].as_str().unwrap().ends_with(".rs"));
+        assert_eq!(block["status"], "paged");
+        assert_eq!(block["page"], 1);
+    }
+
+    let show = parse_json(&run_probe(
+        home.path(),
+        &["session", "show", "other", "--format", "json"],
+    ));
+    let blocks = show["queries"][0]["blocks"].as_array().unwrap();
+    assert_eq!(blocks.len(), 6);
+    assert!(blocks
+        .iter()
+        .all(|block| block["status"] == "seen" && block["page"].is_null()));
+
+    assert!(run_probe(home.path(), &["session", "clear", "pager"])
+        .status
+        .success());
+    let list = parse_json(&run_probe(
+        home.path(),
+        &["session", "list", "--format", "json"],
+    ));
+    assert_eq!(list["sessions"].as_array().unwrap().len(), 1);
+    assert_eq!(list["sessions"][0]["session_id"], "other");
+}
+
+#[test]
+fn test_expired_sessions_are_ignored_and_collected() {
+    let home = TempDir::new().unwrap();
+    let sessions_dir = home.path().join(".cache/probe/sessions");
+    fs::create_dir_all(&sessions_dir).unwrap();
+
+    // A cache last written long ago, in the format used before timestamps were recorded
+    fs::write(
+        sessions_dir.join("old_1234.json"),
+        r#"{"session_id": "old", "query_hash": "1234", "block_identifiers": ["a.rs:1-2"]}"#,
+    )
+    .unwrap();
+    fs::write(
+        sessions_dir.join("new_5678.json"),
+        format!(
+            r#"{{"session_id": "new", "query_hash": "5678", "block_identifiers": [], "updated_at": {}}}"#,
+            std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .unwrap()
+                .as_secs()
+        ),
+    )
+    .unwrap();
+    let old_time = std::time::SystemTime::now() - std::time::Duration::from_secs(3 * 24 * 60 * 60);
+    fs::File::options()
+        .write(true)
+        .open(sessions_dir.join("old_1234.json"))
+        .unwrap()
+        .set_modified(old_time)
+        .unwrap();
+
+    let list = parse_json(&run_probe(
+        home.path(),
+        &["session", "list", "--format", "json"],
+    ));
+    assert_eq!(list["sessions"].as_array().unwrap().len(), 1);
+    assert_eq!(list["sessions"][0]["session_id"], "new");
+
+    assert!(run_probe(home.path(), &["session", "clear", "--expired"])
+        .status
+        .success());
+    assert!(!sessions_dir.join("old_1234.json").exists());
+    assert!(sessions_dir.join("new_5678.json").exists());
+}
+
+fn search_session(home: &Path, project: &Path, session: &str) -> Value {
+    let output = run_probe(
+        home,
+        &[
+            "search",
+            "widget",
+            project.to_str().unwrap(),
+            "--session",
+            session,
+            "--no-merge",
+            "--format",
+            "json",
+        ],
+    );
+    assert!(output.status.success());
+    parse_json(&output)
+}
+
+#[test]
+fn test_session_dedup_survives_line_shifts_and_reports_changes() {
+    let home = TempDir::new().unwrap();
+    let project = TempDir::new().unwrap();
+    let file = project.path().join("widget.rs");
+    let original = "pub fn build_widget() -> u32 {\n    let widget = 1;\n    widget + 1\n}\n";
+    fs::write(&file, original).unwrap();
+
+    let first = search_session(home.path(), project.path(), "edits");
+    assert_eq!(first["results"].as_array().unwrap().len(), 1);
+    assert!(first["results"][0].get("changed_since_seen").is_none());
+
+    // Shifting the block down doesn't make it reappear
+    fs::write(&file, format!("// header\n// more header\n\n{}", original)).unwrap();
+    let shifted = search_session(home.path(), project.path(), "edits");
+    assert!(shifted["results"].as_array().unwrap().is_empty());
+
+    // Changing the block at the same lines surfaces it again, marked as changed
+    fs::write(
+        &file,
+        format!(
+            "// header\n// more header\n\n{}",
+            original.replace("widget + 1", "widget + 2")
+        ),
+    )
+    .unwrap();
+    let changed = search_session(home.path(), project.path(), "edits");
+    let results = changed["results"].as_array().unwrap();
+    assert_eq!(results.len(), 1);
+    assert_eq!(results[0]["changed_since_seen"], true);
+
+    // Once seen in its new form, it is hidden again
+    let again = search_session(home.path(), project.path(), "edits");
+    assert!(again["results"].as_array().unwrap().is_empty());
+}
+
+#[test]
+fn test_overload_keys_do_not_depend_on_the_results() {
+    let home = TempDir::new().unwrap();
+    let project = TempDir::new().unwrap();
+    let file = project.path().join("Calc.java");
+    let calc = |first_body: &str| {
+        format!(
+            "public class Calc {{
+    int add(int a) {{
+        {first_body}
+    }}
+
+    double add(double a, double b) {{
+        double widget = a + b;
+        return widget;
+    }}
+}}
+"
+        )
+    };
+    let search = || -> Vec<Value> {
+        let output = run_probe(
+            home.path(),
+            &[
+                "search",
+                "widget",
+                project.path().to_str().unwrap(),
+                "--session",
+                "overloads",
+                "--no-merge",
+                "--format",
+                "json",
+            ],
+        );
+        assert!(output.status.success());
+        parse_json(&output)["results"].as_array().unwrap().clone()
+    };
+
+    // Only the second overload matches at first
+    fs::write(&file, calc("return a;")).unwrap();
+    let first = search();
+    assert_eq!(first.len(), 1);
+    assert_eq!(first[0]["lines"][0], 6);
+
+    // Once the first overload matches too, it is new, and the second one stays seen
+    fs::write(&file, calc("int widget = a;\n        return widget;")).unwrap();
+    let second = search();
+    assert_eq!(second.len(), 1);
+    assert_eq!(second[0]["lines"][0], 2);
+    assert!(second[0].get("changed_since_seen").is_none());
+}
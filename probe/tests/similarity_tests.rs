@@ -0,0 +1,145 @@
+use serde_json::Value;
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+use tempfile::TempDir;
+
+const ORIGINAL: &str = r#"pub fn total_price(items: &[Item], discount: f64) -> f64 {
+    let mut total = 0.0;
+    for item in items {
+        if item.quantity > 0 {
+            total += item.price * item.quantity as f64;
+        }
+    }
+    if discount > 0.0 {
+        total -= total * discount / 100.0;
+    }
+    total
+}
+"#;
+
+// The same function with every identifier and constant renamed
+const RENAMED: &str = r#"// Sums an invoice
+pub fn invoice_sum(lines: &[Line], rebate: f64) -> f64 {
+    let mut sum = 0.0;
+    for line in lines {
+        if line.count > 0 {
+            sum += line.amount * line.count as f64;
+        }
+    }
+    if rebate > 0.0 {
+        sum -= sum * rebate / 50.0;
+    }
+    sum
+}
+"#;
+
+const UNRELATED: &str = r#"pub fn parse_header(input: &str) -> Option<(String, String)> {
+    let (name, value) = input.split_once(':')?;
+    let name = name.trim().to_lowercase();
+    if name.is_empty() || name.contains(' ') {
+        return None;
+    }
+    Some((name, value.trim().to_string()))
+}
+"#;
+
+// Create a project with a function, a renamed copy of it and an unrelated function
+fn create_project() -> TempDir {
+    let temp_dir = TempDir::new().unwrap();
+    let src = temp_dir.path().join("src");
+    fs::create_dir_all(&src).unwrap();
+    fs::write(src.join("cart.rs"), ORIGINAL).unwrap();
+    fs::write(
+        src.join("invoice.rs"),
+        format!("{}\n{}", UNRELATED, RENAMED),
+    )
+    .unwrap();
+    temp_dir
+}
+
+fn run_probe(args: &[&str]) -> String {
+    let output = Command::new(env!("CARGO_BIN_EXE_probe"))
+        .args(args)
+        .output()
+        .expect("Failed to execute command");
+    assert!(
+        output.status.success(),
+        "probe {:?} failed: {}",
+        args,
+        String::from_utf8_lossy(&output.stderr)
+    );
+    String::from_utf8_lossy(&output.stdout).to_string()
+}
+
+#[test]
+fn test_similar_finds_renamed_copy() {
+    let project = create_project();
+    let path = project.path().to_str().unwrap();
+    let target = format!("{}/src/cart.rs#total_price", path);
+
+    let stdout = run_probe(&["similar", &target, path, "--format", "json"]);
+    let json: Value = serde_json::from_str(&stdout).expect("Output should be JSON");
+    let results = json["results"].as_array().unwrap();
+    assert_eq!(results.len(), 1, "{}", stdout);
+    assert!(results[0]["file"].as_str().unwrap().ends_with("invoice.rs"));
+    assert_eq!(results[0]["symbol"], "invoice_sum");
+    assert_eq!(results[0]["lines"], serde_json::json!([11, 22]));
+    assert_eq!(results[0]["similarity"], 1.0);
+
+    // The target can also be given by line
+    let target = format!("{}/src/invoice.rs:3", path);
+    let stdout = run_probe(&["similar", &target, path, "--format", "json"]);
+    let json: Value = serde_json::from_str(&stdout).unwrap();
+    assert!(json["results"].as_array().unwrap().is_empty(), "{}", stdout);
+}
+
+#[test]
+fn test_dupes_clusters_near_duplicates() {
+    let project = create_project();
+    let path = project.path().to_str().unwrap();
+    fs::write(
+        project.path().join("src/checkout.rs"),
+        ORIGINAL.replace("total_price", "checkout_total"),
+    )
+    .unwrap();
+
+    let stdout = run_probe(&["dupes", path, "--format", "json"]);
+    let json: Value = serde_json::from_str(&stdout).expect("Output should be JSON");
+    assert_eq!(json["summary"]["count"], 1);
+    let cluster = &json["clusters"][0];
+    assert_eq!(cluster["similarity"], 1.0);
+    let symbols: Vec<&str> = cluster["blocks"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|block| block["symbol"].as_str().unwrap())
+        .collect();
+    assert_eq!(symbols, ["total_price", "checkout_total", "invoice_sum"]);
+
+    let stdout = run_probe(&["dupes", path, "--format", "jsonl"]);
+    let records: Vec<Value> = stdout
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect();
+    let schema_path =
+        Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/schemas/jsonl_output_schema.json");
+    let schema: Value = serde_json::from_str(&fs::read_to_string(schema_path).unwrap()).unwrap();
+    let schema = jsonschema::JSONSchema::compile(&schema).unwrap();
+    for record in &records {
+        assert!(
+            schema.is_valid(record),
+            "{} does not match the schema",
+            record
+        );
+    }
+    assert_eq!(records.len(), 2);
+    assert_eq!(records[0]["type"], "cluster");
+
+    let stdout = run_probe(&["dupes", path, "--format", "plain"]);
+    assert!(
+        stdout.contains("Cluster 1 (similarity 1.00, 3 blocks)"),
+        "{}",
+        stdout
+    );
+}
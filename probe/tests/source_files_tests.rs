@@ -0,0 +1,223 @@
+use serde_json::Value;
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+use tempfile::TempDir;
+
+// Create a project with files of every kind the walker tells apart
+fn create_project() -> TempDir {
+    let temp_dir = TempDir::new().unwrap();
+    let dir = temp_dir.path();
+
+    // Latin-1 and UTF-16 sources are searched
+    fs::write(
+        dir.join("latin1.rs"),
+        b"fn caf\xe9_menu() {\n    let needle_term = 1;\n}\n",
+    )
+    .unwrap();
+    let utf16: Vec<u8> = [0xFF, 0xFE]
+        .into_iter()
+        .chain(
+            "fn wide_text() {\n    let needle_term = 2;\n}\n"
+                .encode_utf16()
+                .flat_map(u16::to_le_bytes),
+        )
+        .collect();
+    fs::write(dir.join("utf16.rs"), utf16).unwrap();
+    let large = format!(
+        "fn large_file() {{\n    let needle_term = 3;\n}}\n{}",
+        "// padding\n".repeat(300)
+    );
+    fs::write(dir.join("large.rs"), large).unwrap();
+
+    // Binary, minified and generated files are not
+    fs::write(dir.join("data.rs"), b"needle_term\0\0\x01\x02").unwrap();
+    fs::write(dir.join("app.min.js"), "function needle_term(){return 1}\n").unwrap();
+    let bundle = format!(
+        "var needle_term = 1;\n{}\n",
+        "var a=needle_term+1;".repeat(400)
+    );
+    fs::write(dir.join("bundle.js"), bundle).unwrap();
+    fs::write(
+        dir.join("schema.rs"),
+        "// @generated by schema-gen\nfn schema() {\n    let needle_term = 4;\n}\n",
+    )
+    .unwrap();
+    fs::create_dir_all(dir.join("generated")).unwrap();
+    fs::write(
+        dir.join(".gitattributes"),
+        "generated/** linguist-generated\n",
+    )
+    .unwrap();
+    fs::write(
+        dir.join("generated/lib.rs"),
+        "fn generated_client() {\n    let needle_term = 5;\n}\n",
+    )
+    .unwrap();
+    temp_dir
+}
+
+fn run_probe(args: &[&str]) -> String {
+    let output = Command::new(env!("CARGO_BIN_EXE_probe"))
+        .args(args)
+        .output()
+        .expect("Failed to execute command");
+    assert!(
+        output.status.success(),
+        "probe {:?} failed: {}",
+        args,
+        String::from_utf8_lossy(&output.stderr)
+    );
+    String::from_utf8_lossy(&output.stdout).to_string()
+}
+
+// The names of the files of a JSON search output, sorted
+fn result_files(stdout: &str, root: &Path) -> Vec<String> {
+    let json: Value = serde_json::from_str(stdout).expect("Output should be JSON");
+    let mut files: Vec<String> = json["results"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|result| {
+            let file = result["file"].as_str().unwrap();
+            Path::new(file)
+                .strip_prefix(root)
+                .unwrap_or(Path::new(file))
+                .to_string_lossy()
+                .to_string()
+        })
+        .collect();
+    files.sort();
+    files.dedup();
+    files
+}
+
+#[test]
+fn test_walker_skips_binary_minified_and_generated_files() {
+    let project = create_project();
+    let root = project.path();
+    let path = root.to_str().unwrap();
+
+    let stdout = run_probe(&["search", "needle_term", path, "--format", "json"]);
+    assert_eq!(
+        result_files(&stdout, root),
+        ["large.rs", "latin1.rs", "utf16.rs"],
+        "{}",
+        stdout
+    );
+    // Non-UTF-8 files are decoded rather than left out
+    assert!(stdout.contains("café_menu"), "{}", stdout);
+    assert!(stdout.contains("wide_text"), "{}", stdout);
+
+    let stdout = run_probe(&[
+        "search",
+        "needle_term",
+        path,
+        "--max-file-size",
+        "2K",
+        "--format",
+        "plain",
+    ]);
+    assert!(
+        stdout.contains("Skipped files: 6 (2 too large, 1 binary, 1 minified, 2 generated)"),
+        "{}",
+        stdout
+    );
+    assert!(!stdout.contains("large_file"), "{}", stdout);
+
+    // Minified and generated files can be searched, binary files never are
+    let stdout = run_probe(&[
+        "search",
+        "needle_term",
+        path,
+        "--include-generated",
+        "--format",
+        "json",
+    ]);
+    assert_eq!(
+        result_files(&stdout, root),
+        [
+            "app.min.js",
+            "bundle.js",
+            "generated/lib.rs",
+            "large.rs",
+            "latin1.rs",
+            "schema.rs",
+            "utf16.rs"
+        ],
+        "{}",
+        stdout
+    );
+}
+
+// Compile one of the output schemas of tests/schemas
+fn schema(name: &str) -> jsonschema::JSONSchema {
+    let schema_path = Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/schemas")
+        .join(name);
+    let schema: Value = serde_json::from_str(&fs::read_to_string(schema_path).unwrap()).unwrap();
+    jsonschema::JSONSchema::compile(&schema).unwrap()
+}
+
+// The name and reason of every skipped file of a JSON value, sorted
+fn skipped_files<'a>(skipped: impl Iterator<Item = &'a Value>, root: &Path) -> Vec<String> {
+    let mut files: Vec<String> = skipped
+        .map(|skipped| {
+            let file = skipped["file"].as_str().unwrap();
+            let file = Path::new(file)
+                .strip_prefix(root)
+                .unwrap_or(Path::new(file));
+            format!(
+                "{} {}",
+                file.to_string_lossy(),
+                skipped["reason"].as_str().unwrap()
+            )
+        })
+        .collect();
+    files.sort();
+    files
+}
+
+#[test]
+fn test_structured_output_reports_skipped_files() {
+    let project = create_project();
+    let root = project.path();
+    let path = root.to_str().unwrap();
+    let expected = [
+        "app.min.js minified",
+        "bundle.js minified",
+        "data.rs binary",
+        "generated/lib.rs generated",
+        "schema.rs generated",
+    ];
+
+    let stdout = run_probe(&["search", "needle_term", path, "--format", "json"]);
+    let json: Value = serde_json::from_str(&stdout).unwrap();
+    assert!(
+        schema("json_output_schema.json").is_valid(&json),
+        "{} does not match the schema",
+        json
+    );
+    let skipped = json["summary"]["skipped_files"].as_array().unwrap();
+    assert_eq!(skipped_files(skipped.iter(), root), expected);
+
+    let stdout = run_probe(&["search", "needle_term", path, "--format", "jsonl"]);
+    let records: Vec<Value> = stdout
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect();
+    let schema = schema("jsonl_output_schema.json");
+    for record in &records {
+        assert!(
+            schema.is_valid(record),
+            "{} does not match the schema",
+            record
+        );
+    }
+    let skipped = records
+        .iter()
+        .filter(|record| record["type"] == "skipped_file");
+    assert_eq!(skipped_files(skipped, root), expected);
+    // The summary still ends the output
+    assert_eq!(records.last().unwrap()["type"], "summary");
+}
@@ -0,0 +1,193 @@
+use serde_json::Value;
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+use tempfile::TempDir;
+
+// Run git in a test repository with a fixed identity and date
+fn git(dir: &Path, args: &[&str]) {
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(dir)
+        .args([
+            "-c",
+            "user.name=Test Author",
+            "-c",
+            "user.email=test@example.com",
+            "-c",
+            "commit.gpgsign=false",
+        ])
+        .args(args)
+        .env("GIT_AUTHOR_DATE", "2024-05-01T10:00:00+02:00")
+        .env("GIT_COMMITTER_DATE", "2024-05-01T10:00:00+02:00")
+        .output()
+        .expect("Failed to run git");
+    assert!(
+        output.status.success(),
+        "git {:?} failed: {}",
+        args,
+        String::from_utf8_lossy(&output.stderr)
+    );
+}
+
+const PAYMENTS: &str = r#"// FIXME: amounts should be decimals
+pub fn charge_card(amount: u64) -> bool {
+    // TODO(alice): retry declined cards, see #42 and PAY-7
+    let label = "TODO: this string is not a marker";
+    amount > 0 && !label.is_empty()
+}
+
+#[test]
+fn test_charge_card() {
+    // TODO: cover declined cards
+    assert!(charge_card(1));
+}
+"#;
+
+const WORKER: &str = r#"def run(queue):
+    # HACK work around the driver bug
+    # NOTE: drains the queue first
+    return queue.pop()
+"#;
+
+// Create a repository with a committed Rust file and a Python file that is not committed
+fn create_repository() -> TempDir {
+    let temp_dir = TempDir::new().unwrap();
+    let dir = temp_dir.path();
+    git(dir, &["init", "-q"]);
+    fs::create_dir_all(dir.join("src")).unwrap();
+    fs::write(dir.join("src/payments.rs"), PAYMENTS).unwrap();
+    git(dir, &["add", "-A"]);
+    git(dir, &["commit", "-q", "-m", "Add payments"]);
+    fs::write(dir.join("src/worker.py"), WORKER).unwrap();
+    temp_dir
+}
+
+fn run_probe(args: &[&str]) -> String {
+    let output = Command::new(env!("CARGO_BIN_EXE_probe"))
+        .args(args)
+        .output()
+        .expect("Failed to execute command");
+    assert!(
+        output.status.success(),
+        "probe {:?} failed: {}",
+        args,
+        String::from_utf8_lossy(&output.stderr)
+    );
+    String::from_utf8_lossy(&output.stdout).to_string()
+}
+
+#[test]
+fn test_todos_with_symbols_issues_and_blame() {
+    let repo = create_repository();
+    let path = repo.path().to_str().unwrap();
+
+    let stdout = run_probe(&["todos", path, "--tag", "NOTE", "--format", "json"]);
+    let json: Value = serde_json::from_str(&stdout).expect("Output should be JSON");
+    let results = json["results"].as_array().unwrap();
+    let markers: Vec<(&str, u64)> = results
+        .iter()
+        .map(|item| {
+            (
+                item["tag"].as_str().unwrap(),
+                item["line"].as_u64().unwrap(),
+            )
+        })
+        .collect();
+    // The test function and the string literal are left out
+    assert_eq!(
+        markers,
+        [("FIXME", 1), ("TODO", 3), ("HACK", 2), ("NOTE", 3)],
+        "{}",
+        stdout
+    );
+    assert_eq!(
+        json["summary"]["tags"],
+        serde_json::json!({"FIXME": 1, "HACK": 1, "NOTE": 1, "TODO": 1})
+    );
+
+    let todo = &results[1];
+    assert!(todo["file"].as_str().unwrap().ends_with("payments.rs"));
+    assert_eq!(todo["column"], 8);
+    assert_eq!(todo["owner"], "alice");
+    assert_eq!(todo["text"], "retry declined cards, see #42 and PAY-7");
+    assert_eq!(todo["issues"], serde_json::json!(["#42", "PAY-7"]));
+    assert_eq!(todo["symbol"], "charge_card");
+    assert_eq!(todo["node_type"], "function_item");
+    assert_eq!(todo["blame"]["author"], "Test Author");
+    assert_eq!(todo["blame"]["date"], "2024-05-01T10:00:00+02:00");
+    assert_eq!(todo["blame"]["commit"].as_str().unwrap().len(), 40);
+
+    // A marker before a function is not in it, and uncommitted lines have no blame
+    assert!(results[0].get("symbol").is_none());
+    assert_eq!(results[2]["symbol"], "run");
+    assert!(results[2].get("blame").is_none());
+
+    // The output is the same from one run to the next
+    let again = run_probe(&["todos", path, "--tag", "NOTE", "--format", "json"]);
+    assert_eq!(stdout, again);
+
+    let stdout = run_probe(&["todos", path, "--allow-tests", "--no-blame", "-o", "jsonl"]);
+    let records: Vec<Value> = stdout
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect();
+    let schema_path =
+        Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/schemas/jsonl_output_schema.json");
+    let schema: Value = serde_json::from_str(&fs::read_to_string(schema_path).unwrap()).unwrap();
+    let schema = jsonschema::JSONSchema::compile(&schema).unwrap();
+    for record in &records {
+        assert!(
+            schema.is_valid(record),
+            "{} does not match the schema",
+            record
+        );
+    }
+    assert_eq!(records.len(), 5);
+    assert_eq!(records[2]["symbol"], "test_charge_card");
+    assert!(records.iter().all(|record| record.get("blame").is_none()));
+
+    let stdout = run_probe(&["todos", path, "--format", "plain"]);
+    assert!(
+        stdout.contains("payments.rs:3 in charge_card TODO(alice): retry declined cards"),
+        "{}",
+        stdout
+    );
+}
+
+#[test]
+fn test_todos_with_issues_in_parentheses() {
+    let temp_dir = TempDir::new().unwrap();
+    fs::write(
+        temp_dir.path().join("refunds.rs"),
+        "// TODO(#12): refund partial captures\n\
+         // FIXME(PAY-9): round refunds down\n\
+         // TODO(bob, #13): notify the customer\n\
+         pub fn refund() {}\n",
+    )
+    .unwrap();
+
+    let stdout = run_probe(&[
+        "todos",
+        temp_dir.path().to_str().unwrap(),
+        "--format",
+        "json",
+    ]);
+    let json: Value = serde_json::from_str(&stdout).expect("Output should be JSON");
+    let markers: Vec<(Value, Value)> = json["results"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|item| (item["owner"].clone(), item["issues"].clone()))
+        .collect();
+    assert_eq!(
+        markers,
+        [
+            (Value::Null, serde_json::json!(["#12"])),
+            (Value::Null, serde_json::json!(["PAY-9"])),
+            (serde_json::json!("bob"), serde_json::json!(["#13"])),
+        ],
+        "{}",
+        stdout
+    );
+}
@@ -0,0 +1,196 @@
+use serde_json::Value;
+use std::fs;
+use std::io::{BufRead, BufReader};
+use std::path::Path;
+use std::process::{Child, Command, Stdio};
+use std::sync::mpsc::{channel, Receiver};
+use std::thread;
+use std::time::Duration;
+use tempfile::TempDir;
+
+/// How long to wait for a rerun to be printed
+const TIMEOUT: Duration = Duration::from_secs(30);
+
+/// A `--watch` run whose JSON Lines records are read as they are printed
+struct WatchRun {
+    child: Child,
+    records: Receiver<Value>,
+}
+
+impl WatchRun {
+    fn start(args: &[&str]) -> Self {
+        let mut child = Command::new(env!("CARGO_BIN_EXE_probe"))
+            .args(args)
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()
+            .expect("Failed to execute command");
+        let stdout = child.stdout.take().unwrap();
+        let (sender, records) = channel();
+        thread::spawn(move || {
+            for line in BufReader::new(stdout).lines() {
+                let Ok(line) = line else { break };
+                let record: Value = serde_json::from_str(&line).expect("Output should be JSONL");
+                if sender.send(record).is_err() {
+                    break;
+                }
+            }
+        });
+        WatchRun { child, records }
+    }
+
+    /// The records up to and including the next summary
+    fn next_update(&self) -> Vec<Value> {
+        let mut update = Vec::new();
+        loop {
+            let record = self
+                .records
+                .recv_timeout(TIMEOUT)
+                .unwrap_or_else(|_| panic!("No summary after {:?}", update));
+            let is_summary = record["type"] == "summary";
+            update.push(record);
+            if is_summary {
+                return update;
+            }
+        }
+    }
+}
+
+impl Drop for WatchRun {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+/// (change, file name, first line) of the result records of an update
+fn changes(update: &[Value]) -> Vec<(String, String, u64)> {
+    update
+        .iter()
+        .filter(|record| record["type"] == "result")
+        .map(|record| {
+            let file = record["file"].as_str().unwrap();
+            (
+                record["change"].as_str().unwrap_or("").to_string(),
+                Path::new(file)
+                    .file_name()
+                    .unwrap()
+                    .to_string_lossy()
+                    .to_string(),
+                record["lines"][0].as_u64().unwrap(),
+            )
+        })
+        .collect()
+}
+
+#[test]
+fn test_search_watch() {
+    let temp_dir = TempDir::new().unwrap();
+    let src = temp_dir.path().join("src");
+    fs::create_dir_all(&src).unwrap();
+    fs::write(
+        src.join("cart.rs"),
+        "fn checkout_total() -> u32 {\n    let total = 1;\n    total\n}\n",
+    )
+    .unwrap();
+
+    let path = src.to_str().unwrap();
+    let run = WatchRun::start(&["search", "total", path, "--watch", "--format", "jsonl"]);
+    assert_eq!(
+        changes(&run.next_update()),
+        [(String::new(), "cart.rs".to_string(), 1)]
+    );
+
+    // A new file adds a result
+    fs::write(
+        src.join("tax.rs"),
+        "fn tax_total() -> u32 {\n    let total = 2;\n    total\n}\n",
+    )
+    .unwrap();
+    let update = run.next_update();
+    assert_eq!(
+        changes(&update),
+        [("added".to_string(), "tax.rs".to_string(), 1)]
+    );
+    assert_eq!(update.last().unwrap()["count"], 2);
+
+    let schema_path =
+        Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/schemas/jsonl_output_schema.json");
+    let schema: Value = serde_json::from_str(&fs::read_to_string(schema_path).unwrap()).unwrap();
+    let schema = jsonschema::JSONSchema::compile(&schema).unwrap();
+    for record in &update {
+        assert!(
+            schema.is_valid(record),
+            "{} does not match the schema",
+            record
+        );
+    }
+
+    // Editing a block changes its result, deleting a file removes its result
+    fs::write(
+        src.join("cart.rs"),
+        "fn checkout_total() -> u32 {\n    let total = 3;\n    total\n}\n",
+    )
+    .unwrap();
+    assert_eq!(
+        changes(&run.next_update()),
+        [("changed".to_string(), "cart.rs".to_string(), 1)]
+    );
+
+    fs::remove_file(src.join("tax.rs")).unwrap();
+    let update = run.next_update();
+    assert_eq!(
+        changes(&update),
+        [("removed".to_string(), "tax.rs".to_string(), 1)]
+    );
+    assert_eq!(update.last().unwrap()["count"], 1);
+}
+
+#[test]
+fn test_query_watch() {
+    let temp_dir = TempDir::new().unwrap();
+    let src = temp_dir.path().join("src");
+    fs::create_dir_all(&src).unwrap();
+    fs::write(src.join("main.rs"), "fn main() {\n    run().unwrap();\n}\n").unwrap();
+
+    let path = src.to_str().unwrap();
+    let run = WatchRun::start(&[
+        "query",
+        "$EXPR.unwrap()",
+        path,
+        "--language",
+        "rust",
+        "--watch",
+        "--format",
+        "jsonl",
+    ]);
+    assert_eq!(
+        changes(&run.next_update()),
+        [(String::new(), "main.rs".to_string(), 2)]
+    );
+
+    fs::write(
+        src.join("main.rs"),
+        "fn main() {\n    run().expect(\"run failed\");\n}\n",
+    )
+    .unwrap();
+    assert_eq!(
+        changes(&run.next_update()),
+        [("removed".to_string(), "main.rs".to_string(), 2)]
+    );
+}
+
+#[test]
+fn test_watch_rejects_json() {
+    let output = Command::new(env!("CARGO_BIN_EXE_probe"))
+        .args(["search", "total", ".", "--watch", "--format", "json"])
+        .output()
+        .expect("Failed to execute command");
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("--watch can't print results as json"),
+        "{}",
+        stderr
+    );
+}
@@ -0,0 +1,207 @@
+use probe::search::{perform_workspace_probe, SearchOptions};
+use probe::workspace::Workspace;
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+use tempfile::TempDir;
+
+// Helper function to create two repositories and a workspace file describing them
+fn create_workspace(temp_dir: &TempDir) -> std::path::PathBuf {
+    let api = temp_dir.path().join("repos/api");
+    let web = temp_dir.path().join("repos/web");
+    fs::create_dir_all(api.join("src")).unwrap();
+    fs::create_dir_all(api.join("vendor")).unwrap();
+    fs::create_dir_all(web.join("src")).unwrap();
+    fs::create_dir_all(temp_dir.path().join("config")).unwrap();
+
+    fs::write(
+        api.join("src/billing.rs"),
+        "pub fn calculate_invoice_total(items: &[u64]) -> u64 {\n    items.iter().sum()\n}\n",
+    )
+    .unwrap();
+    fs::write(
+        api.join("src/invoice.py"),
+        "def calculate_invoice_total(items):\n    return sum(items)\n",
+    )
+    .unwrap();
+    fs::write(
+        api.join("vendor/invoice.rs"),
+        "pub fn calculate_invoice_total() -> u64 {\n    0\n}\n",
+    )
+    .unwrap();
+    fs::write(
+        web.join("src/invoice.js"),
+        "function calculateInvoiceTotal(items) {\n  return items.reduce((a, b) => a + b, 0);\n}\n",
+    )
+    .unwrap();
+
+    let workspace_file = temp_dir.path().join("config/team.toml");
+    fs::write(
+        &workspace_file,
+        r#"
+name = "team"
+
+[[roots]]
+name = "api"
+path = "../repos/api"
+ignore = ["vendor/*"]
+languages = ["rust"]
+
+[[roots]]
+name = "web"
+path = "../repos/web"
+"#,
+    )
+    .unwrap();
+
+    workspace_file
+}
+
+fn search_options<'a>(queries: &'a [String], custom_ignores: &'a [String]) -> SearchOptions<'a> {
+    SearchOptions {
+        path: Path::new("."),
+        queries,
+        custom_ignores,
+        allow_tests: true,
+        ..Default::default()
+    }
+}
+
+#[test]
+fn test_workspace_search_labels_results_by_root() {
+    let temp_dir = TempDir::new().unwrap();
+    let workspace = Workspace::load(&create_workspace(&temp_dir)).unwrap();
+
+    let queries = vec!["invoice total".to_string()];
+    let custom_ignores: Vec<String> = vec![];
+    let results =
+        perform_workspace_probe(&workspace, &search_options(&queries, &custom_ignores)).unwrap();
+
+    let mut found: Vec<(String, String)> = results
+        .results
+        .iter()
+        .map(|r| (r.workspace_root.clone().unwrap(), r.file.clone()))
+        .collect();
+    found.sort();
+    found.dedup();
+
+    // The api root is limited to Rust and ignores vendor/, the web root has no restrictions
+    assert_eq!(
+        found,
+        vec![
+            ("api".to_string(), "src/billing.rs".to_string()),
+            ("web".to_string(), "src/invoice.js".to_string()),
+        ]
+    );
+}
+
+#[test]
+fn test_workspace_search_language_override() {
+    let temp_dir = TempDir::new().unwrap();
+    let workspace = Workspace::load(&create_workspace(&temp_dir)).unwrap();
+
+    let queries = vec!["invoice total".to_string()];
+    let custom_ignores: Vec<String> = vec![];
+    let mut options = search_options(&queries, &custom_ignores);
+    options.language = Some("python");
+    let results = perform_workspace_probe(&workspace, &options).unwrap();
+
+    assert!(!results.results.is_empty());
+    for result in &results.results {
+        assert_eq!(result.workspace_root.as_deref(), Some("api"));
+        assert_eq!(result.file, "src/invoice.py");
+    }
+}
+
+#[test]
+fn test_workspace_cli_from_other_directory() {
+    let temp_dir = TempDir::new().unwrap();
+    let workspace_file = create_workspace(&temp_dir);
+    let other_dir = TempDir::new().unwrap();
+
+    let output = Command::new(env!("CARGO_BIN_EXE_probe"))
+        .args([
+            "--workspace",
+            workspace_file.to_str().unwrap(),
+            "invoice total",
+            "--format",
+            "json",
+        ])
+        .current_dir(other_dir.path())
+        .output()
+        .expect("Failed to execute command");
+
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let json_start = stdout.find('{').expect("No JSON in output");
+    let json: serde_json::Value = serde_json::from_str(&stdout[json_start..]).unwrap();
+
+    let mut found: Vec<(String, String)> = json["results"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|r| {
+            (
+                r["root"].as_str().unwrap().to_string(),
+                r["file"].as_str().unwrap().to_string(),
+            )
+        })
+        .collect();
+    found.sort();
+    found.dedup();
+    assert_eq!(
+        found,
+        vec![
+            ("api".to_string(), "src/billing.rs".to_string()),
+            ("web".to_string(), "src/invoice.js".to_string()),
+        ]
+    );
+}
+
+#[test]
+fn test_workspace_extract_and_query() {
+    let temp_dir = TempDir::new().unwrap();
+    let workspace_file = create_workspace(&temp_dir);
+    let other_dir = TempDir::new().unwrap();
+
+    // "<root>/<path>" references resolve against the workspace roots
+    let output = Command::new(env!("CARGO_BIN_EXE_probe"))
+        .args([
+            "extract",
+            "--workspace",
+            workspace_file.to_str().unwrap(),
+            "web/src/invoice.js:2",
+            "--format",
+            "json",
+        ])
+        .current_dir(other_dir.path())
+        .output()
+        .expect("Failed to execute command");
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let json: serde_json::Value =
+        serde_json::from_str(&stdout[stdout.find('{').unwrap()..]).unwrap();
+    assert_eq!(json["results"][0]["root"], "web");
+    assert_eq!(json["results"][0]["file"], "src/invoice.js");
+
+    let output = Command::new(env!("CARGO_BIN_EXE_probe"))
+        .args([
+            "query",
+            "--workspace",
+            workspace_file.to_str().unwrap(),
+            "pub fn $NAME($$$PARAMS) $$$BODY",
+            "--format",
+            "json",
+        ])
+        .current_dir(other_dir.path())
+        .output()
+        .expect("Failed to execute command");
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let json: serde_json::Value =
+        serde_json::from_str(&stdout[stdout.find('{').unwrap()..]).unwrap();
+    let results = json["results"].as_array().unwrap();
+    assert_eq!(results.len(), 1);
+    assert_eq!(results[0]["root"], "api");
+    assert_eq!(results[0]["file"], "src/billing.rs");
+}